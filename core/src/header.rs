@@ -3,9 +3,9 @@
 //! element header, and element composite types.
 
 use crate::value::{
-    CastValueError, ConvertValueError, DicomDate, DicomDateTime, DicomTime, PrimitiveValue, Value,
+    CastValueError, ConvertValueError, DicomDate, DicomDateTime, DicomTime, FixedOffset,
+    PrimitiveValue, Value, C,
 };
-use chrono::FixedOffset;
 use num_traits::NumCast;
 use snafu::{ensure, Backtrace, Snafu};
 use std::borrow::Cow;
@@ -195,14 +195,44 @@ impl<'v, I, P> Header for DataElementRef<'v, I, P> {
 
 impl<I, P> DataElement<I, P> {
     /// Create an empty data element.
+    ///
+    /// If `vr` is `SQ`, the value is a sequence without any items.
+    /// Otherwise, the value is [`PrimitiveValue::Empty`].
+    /// In both cases, the element is encoded with a length of zero.
     pub fn empty(tag: Tag, vr: VR) -> Self {
+        let value = if vr == VR::SQ {
+            Value::Sequence {
+                items: Default::default(),
+                size: Length(0),
+            }
+        } else {
+            PrimitiveValue::Empty.into()
+        };
         DataElement {
             header: DataElementHeader {
                 tag,
                 vr,
                 len: Length(0),
             },
-            value: PrimitiveValue::Empty.into(),
+            value,
+        }
+    }
+
+    /// Check whether the element is present but empty,
+    /// meaning that its value does not hold any significant content.
+    ///
+    /// This is the case for empty primitive values
+    /// (including text values with only padding),
+    /// sequences without items,
+    /// and pixel sequences without fragments.
+    /// Unlike [`HasLength::is_empty`],
+    /// this does not depend on the length recorded in the header,
+    /// which may be undefined.
+    pub fn is_empty_value(&self) -> bool {
+        match &self.value {
+            Value::Primitive(value) => crate::value::equality::is_empty(self.vr(), value),
+            Value::Sequence { items, .. } => items.is_empty(),
+            Value::PixelSequence { fragments, .. } => fragments.is_empty(),
         }
     }
 
@@ -222,6 +252,15 @@ impl<I, P> DataElement<I, P> {
         &self.value
     }
 
+    /// Retrieve a mutable reference to the items of a sequence value,
+    /// so that items can be modified, added, or removed in place.
+    ///
+    /// Returns `None` if the value is not a sequence.
+    /// The length recorded in the header is left unchanged.
+    pub fn items_mut(&mut self) -> Option<&mut C<I>> {
+        self.value.items_mut()
+    }
+
     /// Move the data value out of the element, discarding the rest. If the
     /// value is a sequence, its lifetime may still be bound to its original
     /// source.
@@ -638,6 +677,16 @@ impl DataElementHeader {
     pub fn is_non_primitive(&self) -> bool {
         self.vr == VR::SQ || self.length().is_undefined()
     }
+
+    /// Check whether this is the header of a binary element
+    /// other than pixel data with an undefined length,
+    /// the value of which is a sequence of fragments.
+    #[inline]
+    pub fn is_fragment_sequence(&self) -> bool {
+        matches!(self.vr, VR::OB | VR::OW)
+            && self.length().is_undefined()
+            && !self.is_encapsulated_pixeldata()
+    }
 }
 
 impl From<SequenceItemHeader> for DataElementHeader {
@@ -1325,6 +1374,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn empty_data_elements() {
+        let element: DataElement<EmptyObject, [u8; 0]> =
+            DataElement::empty(Tag(0x0010, 0x0010), VR::PN);
+        assert_eq!(
+            element.value(),
+            &DicomValue::Primitive(PrimitiveValue::Empty)
+        );
+        assert!(element.is_empty());
+        assert!(element.is_empty_value());
+
+        // empty sequences hold no items
+        let element: DataElement<EmptyObject, [u8; 0]> =
+            DataElement::empty(Tag(0x0008, 0x1110), VR::SQ);
+        assert_eq!(element.items(), Some(&[][..]));
+        assert!(element.is_empty());
+        assert!(element.is_empty_value());
+
+        // padding is not significant
+        let element: DataElement<EmptyObject, [u8; 0]> =
+            DataElement::new(Tag(0x0010, 0x0020), VR::LO, PrimitiveValue::from(" "));
+        assert!(!element.is_empty());
+        assert!(element.is_empty_value());
+
+        let element: DataElement<EmptyObject, [u8; 0]> =
+            DataElement::new(Tag(0x0010, 0x0020), VR::LO, PrimitiveValue::from("ID0001"));
+        assert!(!element.is_empty_value());
+    }
+
     #[test]
     fn create_data_element_from_primitive() {
         let data_element: DataElement<EmptyObject, [u8; 0]> = DataElement::new(
@@ -22,6 +22,22 @@
 //!   and the possible presence of sequences.
 //! - [`error`] contains crate-level error and result types.
 //!
+//! ## Cargo features
+//!
+//! - `chrono` (enabled by default):
+//!   conversions between DICOM date and time values
+//!   and the types of the [`chrono`](https://docs.rs/chrono) crate,
+//!   as well as date and time ranges ([`value::range`]).
+//!   When disabled, date-time values hold a UTC offset
+//!   of a minimal built-in type ([`value::FixedOffset`]),
+//!   and the APIs listed above are not available:
+//!   using them fails to compile
+//!   with an error message asking to enable the feature.
+//! - `arrow`:
+//!   export of numeric primitive values to [Apache Arrow](https://arrow.apache.org) arrays
+//!   ([`value::arrow`]),
+//!   through the `arrow-array` crate.
+//!
 //! [`dictionary`]: ./dictionary/index.html
 //! [`error`]: ./error/index.html
 //! [`header`]: ./header/index.html
@@ -36,6 +52,11 @@ pub use header::{DataElement, DataElementHeader, Length, Tag, VR};
 pub use value::{PrimitiveValue, Value as DicomValue};
 
 // re-export crates that are part of the public API
+#[cfg(feature = "arrow")]
+pub use arrow_array;
+#[cfg(feature = "arrow")]
+pub use arrow_schema;
+#[cfg(feature = "chrono")]
 pub use chrono;
 pub use smallvec;
 
@@ -0,0 +1,197 @@
+//! Handling of DICOM values with the AE (application entity) value representation
+//! as per PS3.5 sect 6.2.
+use snafu::{ensure, Backtrace, Snafu};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The maximum number of characters in an AE title.
+pub const MAX_AE_TITLE_LENGTH: usize = 16;
+
+/// An error which may occur when validating an AE title.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum AeTitleError {
+    #[snafu(display("AE title is empty"))]
+    Empty { backtrace: Backtrace },
+    #[snafu(display(
+        "AE title `{}` is too long ({} characters, maximum is {})",
+        value,
+        len,
+        MAX_AE_TITLE_LENGTH
+    ))]
+    TooLong {
+        value: String,
+        len: usize,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("AE title `{}` contains invalid character {:?}", value.escape_debug(), c))]
+    InvalidCharacter {
+        value: String,
+        c: char,
+        backtrace: Backtrace,
+    },
+}
+
+/// A validated DICOM _Application Entity_ title (AE value representation).
+///
+/// An AE title has between 1 and 16 characters
+/// of the default character repertoire,
+/// excluding the backslash (`\`) and all control characters.
+/// Leading and trailing spaces are not significant,
+/// and are removed on construction.
+///
+/// # Example
+///
+/// ```
+/// # use dicom_core::value::AeTitle;
+/// let ae_title = AeTitle::new(" STORE-SCP ")?;
+/// assert_eq!(ae_title.as_str(), "STORE-SCP");
+/// assert_eq!(ae_title.to_padded(), "STORE-SCP       ");
+///
+/// assert!(AeTitle::new("   ").is_err());
+/// assert!(AeTitle::new("WAY-TOO-LONG-AE-TITLE").is_err());
+/// assert!(AeTitle::new("BACK\\SLASH").is_err());
+/// # Ok::<_, dicom_core::value::AeTitleError>(())
+/// ```
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Ord, PartialOrd)]
+pub struct AeTitle(String);
+
+impl AeTitle {
+    /// Validate and create an AE title from the given text,
+    /// trimming leading and trailing spaces.
+    pub fn new(value: &str) -> Result<Self, AeTitleError> {
+        let value = value.trim_end_matches('\0').trim_matches(' ');
+        ensure!(!value.is_empty(), EmptySnafu);
+
+        if let Some(c) = value
+            .chars()
+            .find(|&c| !(' '..='~').contains(&c) || c == '\\')
+        {
+            return InvalidCharacterSnafu { value, c }.fail();
+        }
+
+        // only ASCII characters at this point
+        ensure!(
+            value.len() <= MAX_AE_TITLE_LENGTH,
+            TooLongSnafu {
+                value,
+                len: value.len()
+            }
+        );
+
+        Ok(AeTitle(value.to_string()))
+    }
+
+    /// Check whether the given text is a valid AE title.
+    pub fn is_valid(value: &str) -> bool {
+        AeTitle::new(value).is_ok()
+    }
+
+    /// Retrieve the AE title as a string slice,
+    /// without padding.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Retrieve the AE title padded with trailing spaces
+    /// to the full 16 characters,
+    /// as it is encoded in association PDUs.
+    pub fn to_padded(&self) -> String {
+        format!("{:<1$}", self.0, MAX_AE_TITLE_LENGTH)
+    }
+
+    /// Convert the AE title into a string, without padding.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Display for AeTitle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for AeTitle {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for AeTitle {
+    type Err = AeTitleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AeTitle::new(s)
+    }
+}
+
+impl TryFrom<&str> for AeTitle {
+    type Error = AeTitleError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        AeTitle::new(value)
+    }
+}
+
+impl TryFrom<String> for AeTitle {
+    type Error = AeTitleError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        AeTitle::new(&value)
+    }
+}
+
+impl From<AeTitle> for String {
+    fn from(value: AeTitle) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_ae_titles() {
+        assert_eq!(AeTitle::new("ANY-SCP").unwrap().as_str(), "ANY-SCP");
+        assert_eq!(AeTitle::new("  PACS 1  ").unwrap().as_str(), "PACS 1");
+        assert_eq!(AeTitle::new("STORAGE\0").unwrap().as_str(), "STORAGE");
+        assert_eq!(
+            AeTitle::new("ABCDEFGHIJKLMNOP").unwrap().as_str(),
+            "ABCDEFGHIJKLMNOP"
+        );
+        // trailing padding does not count towards the length
+        assert!(AeTitle::new("ABCDEFGHIJKLMNOP  ").is_ok());
+        assert_eq!(
+            AeTitle::new("ECHOSCU").unwrap().to_padded(),
+            "ECHOSCU         "
+        );
+    }
+
+    #[test]
+    fn invalid_ae_titles() {
+        assert!(matches!(AeTitle::new(""), Err(AeTitleError::Empty { .. })));
+        assert!(matches!(
+            AeTitle::new("    "),
+            Err(AeTitleError::Empty { .. })
+        ));
+        assert!(matches!(
+            AeTitle::new("ABCDEFGHIJKLMNOPQ"),
+            Err(AeTitleError::TooLong { len: 17, .. })
+        ));
+        assert!(matches!(
+            AeTitle::new("AE\\TITLE"),
+            Err(AeTitleError::InvalidCharacter { c: '\\', .. })
+        ));
+        assert!(matches!(
+            AeTitle::new("AE\tTITLE"),
+            Err(AeTitleError::InvalidCharacter { c: '\t', .. })
+        ));
+        assert!(matches!(
+            AeTitle::new("ÆTITLE"),
+            Err(AeTitleError::InvalidCharacter { c: 'Æ', .. })
+        ));
+    }
+}
@@ -0,0 +1,344 @@
+//! Export of numeric primitive values to [Apache Arrow] arrays,
+//! available with the `arrow` feature.
+//!
+//! A single value is converted with
+//! [`PrimitiveValue::to_arrow_array`],
+//! holding one array element per value.
+//! The values of the same attribute in many data sets
+//! are gathered into a column with [`primitive_column`],
+//! holding one list of values per data set.
+//! Binary numeric values are copied in bulk, never one element at a time.
+//!
+//! Only the binary numeric variants of [`PrimitiveValue`] are supported,
+//! from `U8` to `F64`.
+//! Values of the textual numeric representations
+//! are parsed into a column instead,
+//! with [`decimal_string_column`] for decimal strings (DS)
+//! and [`integer_string_column`] for integer strings (IS).
+//!
+//! [Apache Arrow]: https://arrow.apache.org
+//!
+//! # Example
+//!
+//! ```
+//! use dicom_core::value::arrow::primitive_column;
+//! use dicom_core::arrow_array::Array;
+//! use dicom_core::PrimitiveValue;
+//!
+//! let spacing = PrimitiveValue::from([0.5_f64, 0.5]);
+//! let array = spacing.to_arrow_array()?;
+//! assert_eq!(array.len(), 2);
+//!
+//! // one list per data set, null where the attribute is missing
+//! let other = PrimitiveValue::from([0.25_f64, 0.3]);
+//! let column = primitive_column([Some(&spacing), None, Some(&other)])?;
+//! assert_eq!(column.len(), 3);
+//! assert!(column.is_null(1));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use super::{ConvertValueError, DicomValueType, PrimitiveValue, ValueType};
+use arrow_array::builder::{ListBuilder, PrimitiveBuilder};
+use arrow_array::types::{
+    ArrowPrimitiveType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, UInt16Type,
+    UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{ArrayRef, NullArray};
+use snafu::{Backtrace, ResultExt, Snafu};
+use std::sync::Arc;
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// The value is not of a binary numeric type
+    #[snafu(display("Values of type {:?} cannot be exported to Arrow", value_type))]
+    UnsupportedValueType {
+        value_type: ValueType,
+        backtrace: Backtrace,
+    },
+    /// The values of a column are not all of the same type
+    #[snafu(display("Expected values of type {:?} in column, found {:?}", expected, found))]
+    MismatchedValueType {
+        expected: ValueType,
+        found: ValueType,
+        backtrace: Backtrace,
+    },
+    /// A textual value could not be parsed as a number
+    #[snafu(display("Could not parse value as a number"))]
+    ParseValue {
+        #[snafu(source(from(ConvertValueError, Box::from)))]
+        source: Box<ConvertValueError>,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl PrimitiveValue {
+    /// Convert the values of a binary numeric variant
+    /// into an Arrow array of the respective primitive type,
+    /// with one element per value.
+    ///
+    /// Fails if the value is not of a binary numeric type,
+    /// from `U8` to `F64`.
+    pub fn to_arrow_array(&self) -> Result<ArrayRef> {
+        fn array<T: ArrowPrimitiveType>(values: &[T::Native]) -> ArrayRef {
+            let mut builder = PrimitiveBuilder::<T>::with_capacity(values.len());
+            builder.append_slice(values);
+            Arc::new(builder.finish())
+        }
+
+        Ok(match self {
+            PrimitiveValue::U8(c) => array::<UInt8Type>(c),
+            PrimitiveValue::I16(c) => array::<Int16Type>(c),
+            PrimitiveValue::U16(c) => array::<UInt16Type>(c),
+            PrimitiveValue::I32(c) => array::<Int32Type>(c),
+            PrimitiveValue::U32(c) => array::<UInt32Type>(c),
+            PrimitiveValue::I64(c) => array::<Int64Type>(c),
+            PrimitiveValue::U64(c) => array::<UInt64Type>(c),
+            PrimitiveValue::F32(c) => array::<Float32Type>(c),
+            PrimitiveValue::F64(c) => array::<Float64Type>(c),
+            _ => {
+                return UnsupportedValueTypeSnafu {
+                    value_type: self.value_type(),
+                }
+                .fail()
+            }
+        })
+    }
+}
+
+/// Gather the values of one attribute across many data sets
+/// into an Arrow list array,
+/// with one list of values per data set.
+///
+/// `None` stands for a data set without the attribute,
+/// which becomes a null list.
+/// An empty value becomes an empty list.
+/// The type of the list elements is the type of the first non-empty value,
+/// and all other values must be of the same type.
+/// A column without any values is a [`NullArray`].
+pub fn primitive_column<'a, I>(values: I) -> Result<ArrayRef>
+where
+    I: IntoIterator<Item = Option<&'a PrimitiveValue>>,
+{
+    let values: Vec<_> = values.into_iter().collect();
+    let value_type = values
+        .iter()
+        .flatten()
+        .map(|v| v.value_type())
+        .find(|t| *t != ValueType::Empty);
+
+    match value_type {
+        None => Ok(Arc::new(NullArray::new(values.len()))),
+        Some(t @ ValueType::U8) => list::<UInt8Type>(&values, t, PrimitiveValue::as_u8_slice),
+        Some(t @ ValueType::I16) => list::<Int16Type>(&values, t, PrimitiveValue::as_i16_slice),
+        Some(t @ ValueType::U16) => list::<UInt16Type>(&values, t, PrimitiveValue::as_u16_slice),
+        Some(t @ ValueType::I32) => list::<Int32Type>(&values, t, PrimitiveValue::as_i32_slice),
+        Some(t @ ValueType::U32) => list::<UInt32Type>(&values, t, PrimitiveValue::as_u32_slice),
+        Some(t @ ValueType::I64) => list::<Int64Type>(&values, t, PrimitiveValue::as_i64_slice),
+        Some(t @ ValueType::U64) => list::<UInt64Type>(&values, t, PrimitiveValue::as_u64_slice),
+        Some(t @ ValueType::F32) => list::<Float32Type>(&values, t, PrimitiveValue::as_f32_slice),
+        Some(t @ ValueType::F64) => list::<Float64Type>(&values, t, PrimitiveValue::as_f64_slice),
+        Some(value_type) => UnsupportedValueTypeSnafu { value_type }.fail(),
+    }
+}
+
+/// Gather the values of a decimal string (DS) attribute
+/// across many data sets
+/// into an Arrow list array of 64-bit floats,
+/// with one list of values per data set.
+///
+/// `None` and empty values are handled as in [`primitive_column`].
+/// Textual values are parsed one by one,
+/// and binary numeric values are converted.
+/// Fails if a value cannot be read as a number.
+pub fn decimal_string_column<'a, I>(values: I) -> Result<ArrayRef>
+where
+    I: IntoIterator<Item = Option<&'a PrimitiveValue>>,
+{
+    parsed_list::<Float64Type, _>(values, PrimitiveValue::to_multi_float64)
+}
+
+/// Gather the values of an integer string (IS) attribute
+/// across many data sets
+/// into an Arrow list array of 64-bit integers,
+/// with one list of values per data set.
+///
+/// `None` and empty values are handled as in [`primitive_column`].
+/// Textual values are parsed one by one,
+/// and binary numeric values are converted.
+/// Fails if a value cannot be read as an integer.
+pub fn integer_string_column<'a, I>(values: I) -> Result<ArrayRef>
+where
+    I: IntoIterator<Item = Option<&'a PrimitiveValue>>,
+{
+    parsed_list::<Int64Type, _>(values, PrimitiveValue::to_multi_int::<i64>)
+}
+
+/// Build a list array from values of any variant,
+/// converted through the given function.
+fn parsed_list<'a, T, I>(
+    values: I,
+    convert: fn(&PrimitiveValue) -> Result<Vec<T::Native>, ConvertValueError>,
+) -> Result<ArrayRef>
+where
+    T: ArrowPrimitiveType,
+    I: IntoIterator<Item = Option<&'a PrimitiveValue>>,
+{
+    let values = values.into_iter();
+    let mut builder =
+        ListBuilder::with_capacity(PrimitiveBuilder::<T>::new(), values.size_hint().0);
+    for value in values {
+        match value {
+            None => builder.append_null(),
+            Some(PrimitiveValue::Empty) => builder.append(true),
+            Some(value) => {
+                builder
+                    .values()
+                    .append_slice(&convert(value).context(ParseValueSnafu)?);
+                builder.append(true);
+            }
+        }
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+/// Build a list array from values of a single variant,
+/// borrowed through the given accessor.
+fn list<T>(
+    values: &[Option<&PrimitiveValue>],
+    expected: ValueType,
+    as_slice: fn(&PrimitiveValue) -> Option<&[T::Native]>,
+) -> Result<ArrayRef>
+where
+    T: ArrowPrimitiveType,
+{
+    let len = values
+        .iter()
+        .flatten()
+        .map(|v| v.multiplicity())
+        .sum::<u32>();
+    let mut builder = ListBuilder::with_capacity(
+        PrimitiveBuilder::<T>::with_capacity(len as usize),
+        values.len(),
+    );
+    for value in values {
+        match value {
+            None => builder.append_null(),
+            Some(PrimitiveValue::Empty) => builder.append(true),
+            Some(value) => {
+                let slice = as_slice(value).ok_or_else(|| {
+                    MismatchedValueTypeSnafu {
+                        expected,
+                        found: value.value_type(),
+                    }
+                    .build()
+                })?;
+                builder.values().append_slice(slice);
+                builder.append(true);
+            }
+        }
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::Array;
+    use arrow_schema::DataType;
+
+    #[test]
+    fn primitive_value_to_arrow_array() {
+        let array = PrimitiveValue::from([1_u16, 2, 512])
+            .to_arrow_array()
+            .unwrap();
+        assert_eq!(array.data_type(), &DataType::UInt16);
+        assert_eq!(
+            array.as_primitive::<UInt16Type>().values(),
+            &[1_u16, 2, 512]
+        );
+
+        let array = PrimitiveValue::from(-1.5_f64).to_arrow_array().unwrap();
+        assert_eq!(array.as_primitive::<Float64Type>().values(), &[-1.5]);
+
+        assert!(matches!(
+            PrimitiveValue::from("1\\2").to_arrow_array(),
+            Err(Error::UnsupportedValueType {
+                value_type: ValueType::Str,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn primitive_values_to_arrow_column() {
+        let a = PrimitiveValue::from([1_i32, 2]);
+        let b = PrimitiveValue::from(3_i32);
+        let column =
+            primitive_column([Some(&a), None, Some(&PrimitiveValue::Empty), Some(&b)]).unwrap();
+        assert_eq!(column.len(), 4);
+        let list = column.as_list::<i32>();
+        assert_eq!(list.value_type(), DataType::Int32);
+        assert_eq!(list.value_offsets(), &[0, 2, 2, 2, 3]);
+        assert!(list.is_null(1));
+        assert!(list.is_valid(2));
+        assert_eq!(
+            list.values().as_primitive::<Int32Type>().values(),
+            &[1, 2, 3]
+        );
+
+        // no values at all
+        let column = primitive_column([None, Some(&PrimitiveValue::Empty)]).unwrap();
+        assert_eq!(column.data_type(), &DataType::Null);
+        assert_eq!(column.len(), 2);
+
+        // values of different types
+        let c = PrimitiveValue::from(4_u16);
+        assert!(matches!(
+            primitive_column([Some(&a), Some(&c)]),
+            Err(Error::MismatchedValueType {
+                expected: ValueType::I32,
+                found: ValueType::U16,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn textual_values_to_arrow_column() {
+        let a = PrimitiveValue::Strs(["0.5", "-1.25e1"].iter().map(|s| s.to_string()).collect());
+        let b = PrimitiveValue::from("3 ");
+        let column =
+            decimal_string_column([Some(&a), None, Some(&PrimitiveValue::Empty), Some(&b)])
+                .unwrap();
+        let list = column.as_list::<i32>();
+        assert_eq!(list.value_type(), DataType::Float64);
+        assert_eq!(list.value_offsets(), &[0, 2, 2, 2, 3]);
+        assert!(list.is_null(1));
+        assert_eq!(
+            list.values().as_primitive::<Float64Type>().values(),
+            &[0.5, -12.5, 3.]
+        );
+
+        let c = PrimitiveValue::Strs(["512", "-7"].iter().map(|s| s.to_string()).collect());
+        let column = integer_string_column([Some(&c), Some(&b)]).unwrap();
+        let list = column.as_list::<i32>();
+        assert_eq!(list.value_type(), DataType::Int64);
+        assert_eq!(
+            list.values().as_primitive::<Int64Type>().values(),
+            &[512, -7, 3]
+        );
+
+        // decimal strings are not integers
+        assert!(matches!(
+            integer_string_column([Some(&a)]),
+            Err(Error::ParseValue { .. })
+        ));
+        assert!(matches!(
+            decimal_string_column([Some(&PrimitiveValue::from("1.5mm"))]),
+            Err(Error::ParseValue { .. })
+        ));
+    }
+}
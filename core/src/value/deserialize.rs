@@ -3,8 +3,10 @@ use crate::value::partial::{
     check_component, DateComponent, DicomDate, DicomDateTime, DicomTime,
     Error as PartialValuesError,
 };
-use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
-use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+use crate::value::FixedOffset;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
 use std::convert::TryFrom;
 use std::ops::{Add, Mul, Sub};
 
@@ -52,6 +54,8 @@ pub enum Error {
     },
     #[snafu(display("Seconds '{secs}' out of bounds when constructing FixedOffset"))]
     SecsOutOfBounds { secs: i32, backtrace: Backtrace },
+    #[snafu(display("Unexpected {} trailing bytes after value", len))]
+    TrailingBytes { len: usize, backtrace: Backtrace },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -60,6 +64,7 @@ type Result<T, E = Error> = std::result::Result<T, E>;
   * As per standard, a full 8 byte representation (YYYYMMDD) is required,
   otherwise, the operation fails.
 */
+#[cfg(feature = "chrono")]
 pub fn parse_date(buf: &[u8]) -> Result<NaiveDate> {
     match buf.len() {
         4 => IncompleteValueSnafu {
@@ -196,6 +201,7 @@ pub fn parse_time_partial(buf: &[u8]) -> Result<(DicomTime, &[u8])> {
 * For Time with missing components, or if exact second fraction accuracy needs to be preserved,
   use `parse_time_partial`.
 */
+#[cfg(feature = "chrono")]
 pub fn parse_time(buf: &[u8]) -> Result<(NaiveTime, &[u8])> {
     // at least HHMMSS.F required
     match buf.len() {
@@ -337,6 +343,7 @@ where
 * For DateTime with missing components, or if exact second fraction accuracy needs to be preserved,
   use `parse_datetime_partial`.
 */
+#[cfg(feature = "chrono")]
 pub fn parse_datetime(buf: &[u8], dt_utc_offset: FixedOffset) -> Result<DateTime<FixedOffset>> {
     let date = parse_date(buf)?;
     let buf = &buf[8..];
@@ -389,27 +396,7 @@ pub fn parse_datetime_partial(buf: &[u8], dt_utc_offset: FixedOffset) -> Result<
 
     let offset = match buf.len() {
         0 => dt_utc_offset,
-        len if len > 4 => {
-            let tz_sign = buf[0];
-            let buf = &buf[1..];
-            let tz_h: u32 = read_number(&buf[0..2])?;
-            let tz_m: u32 = read_number(&buf[2..4])?;
-            let s = (tz_h * 60 + tz_m) * 60;
-            match tz_sign {
-                b'+' => {
-                    check_component(DateComponent::UtcEast, &s).context(InvalidComponentSnafu)?;
-                    FixedOffset::east_opt(s as i32)
-                        .context(SecsOutOfBoundsSnafu { secs: s as i32 })?
-                }
-                b'-' => {
-                    check_component(DateComponent::UtcWest, &s).context(InvalidComponentSnafu)?;
-                    FixedOffset::west_opt(s as i32)
-                        .context(SecsOutOfBoundsSnafu { secs: s as i32 })?
-                }
-                c => return InvalidTimeZoneSignTokenSnafu { value: c }.fail(),
-            }
-        }
-        _ => return UnexpectedEndOfElementSnafu.fail(),
+        _ => parse_utc_offset(buf)?,
     };
 
     match time {
@@ -420,11 +407,46 @@ pub fn parse_datetime_partial(buf: &[u8], dt_utc_offset: FixedOffset) -> Result<
     }
 }
 
+/** Decode a UTC offset in the form `&ZZXX`
+ * (`&` being either `+` or `-`).
+ * This is the format of the optional suffix of a DT value,
+ * as well as of the _Timezone Offset From UTC_ (0008,0201) attribute.
+ * Trailing padding (spaces or null characters) is ignored,
+ * but any other trailing byte is rejected.
+ */
+pub fn parse_utc_offset(buf: &[u8]) -> Result<FixedOffset> {
+    let len = buf
+        .iter()
+        .rposition(|c| *c != b' ' && *c != b'\0')
+        .map_or(0, |i| i + 1);
+    let buf = &buf[..len];
+    if buf.len() < 5 {
+        return UnexpectedEndOfElementSnafu.fail();
+    }
+    ensure!(buf.len() == 5, TrailingBytesSnafu { len: buf.len() - 5 });
+    let tz_sign = buf[0];
+    let buf = &buf[1..];
+    let tz_h: u32 = read_number(&buf[0..2])?;
+    let tz_m: u32 = read_number(&buf[2..4])?;
+    let s = (tz_h * 60 + tz_m) * 60;
+    match tz_sign {
+        b'+' => {
+            check_component(DateComponent::UtcEast, &s).context(InvalidComponentSnafu)?;
+            FixedOffset::east_opt(s as i32).context(SecsOutOfBoundsSnafu { secs: s as i32 })
+        }
+        b'-' => {
+            check_component(DateComponent::UtcWest, &s).context(InvalidComponentSnafu)?;
+            FixedOffset::west_opt(s as i32).context(SecsOutOfBoundsSnafu { secs: s as i32 })
+        }
+        c => InvalidTimeZoneSignTokenSnafu { value: c }.fail(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{FixedOffset, NaiveDate, NaiveTime, TimeZone};
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_parse_date() {
         assert_eq!(
@@ -612,6 +634,7 @@ mod tests {
         ));
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_parse_time() {
         assert_eq!(
@@ -777,6 +800,7 @@ mod tests {
             })
         ));
     }
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_parse_datetime() {
         let default_offset = FixedOffset::east_opt(0).unwrap();
@@ -1069,4 +1093,47 @@ mod tests {
         assert!(parse_datetime_partial(b"20171130101010.204+01", default_offset).is_err());
         assert!(parse_datetime_partial(b"20171130101010.204+011", default_offset).is_err());
     }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(
+            parse_utc_offset(b"+0000").unwrap(),
+            FixedOffset::east_opt(0).unwrap()
+        );
+        assert_eq!(
+            parse_utc_offset(b"+0530").unwrap(),
+            FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap()
+        );
+        assert_eq!(
+            parse_utc_offset(b"-0800").unwrap(),
+            FixedOffset::west_opt(8 * 3600).unwrap()
+        );
+        assert!(matches!(
+            parse_utc_offset(b"+1401"),
+            Err(Error::InvalidComponent { .. })
+        ));
+        assert!(matches!(
+            parse_utc_offset(b"*0100"),
+            Err(Error::InvalidTimeZoneSignToken { .. })
+        ));
+        assert!(matches!(
+            parse_utc_offset(b"+01"),
+            Err(Error::UnexpectedEndOfElement { .. })
+        ));
+        assert!(parse_utc_offset(b"").is_err());
+        // padding is ignored
+        assert_eq!(
+            parse_utc_offset(b"-0800 ").unwrap(),
+            FixedOffset::west_opt(8 * 3600).unwrap()
+        );
+        // anything else after the offset is rejected
+        assert!(matches!(
+            parse_utc_offset(b"+05001"),
+            Err(Error::TrailingBytes { len: 1, .. })
+        ));
+        assert!(matches!(
+            parse_utc_offset(b"+0500 1"),
+            Err(Error::TrailingBytes { len: 2, .. })
+        ));
+    }
 }
@@ -0,0 +1,320 @@
+//! Attribute value equality semantics.
+//!
+//! The functions in this module compare DICOM primitive values
+//! under the rules of their value representation,
+//! rather than by their exact in-memory representation.
+//! In particular:
+//!
+//! - trailing padding (spaces and null characters) is never significant
+//!   in textual values;
+//! - leading spaces are not significant for the value representations
+//!   in which the standard declares them as such
+//!   (AE, AS, CS, DA, DS, DT, IS, LO, SH and TM);
+//! - values of CS and PN are compared in a case-insensitive manner,
+//!   and empty trailing name components in PN are ignored;
+//! - DS and IS values are compared by their numeric value,
+//!   regardless of formatting (`"1.50"` is equal to `"1.5"` and `" 1.5E0"`);
+//! - values in other numeric representations are compared by number,
+//!   even if they are held in different primitive value variants.
+//!
+//! Both dataset comparison and attribute query matching
+//! are expected to build on top of these functions.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{dicom_value, PrimitiveValue, VR};
+//! use dicom_core::value::equality::value_eq;
+//!
+//! assert!(value_eq(
+//!     VR::DS,
+//!     &dicom_value!(Strs, ["1.50 ", "-2"]),
+//!     &dicom_value!(Strs, ["1.5", "-2.0"]),
+//! ));
+//! assert!(value_eq(
+//!     VR::PN,
+//!     &PrimitiveValue::from("Smith^John^^"),
+//!     &PrimitiveValue::from("SMITH^JOHN"),
+//! ));
+//! assert!(!value_eq(
+//!     VR::LO,
+//!     &PrimitiveValue::from("Smith"),
+//!     &PrimitiveValue::from("SMITH"),
+//! ));
+//! ```
+
+use crate::header::VR;
+use crate::value::PrimitiveValue;
+
+/// Check whether leading spaces are insignificant
+/// in values of the given value representation.
+pub fn leading_spaces_insignificant(vr: VR) -> bool {
+    matches!(
+        vr,
+        VR::AE | VR::AS | VR::CS | VR::DA | VR::DS | VR::DT | VR::IS | VR::LO | VR::SH | VR::TM
+    )
+}
+
+/// Check whether values of the given value representation
+/// should be compared without regard to letter case.
+pub fn is_case_insensitive(vr: VR) -> bool {
+    matches!(vr, VR::CS | VR::PN)
+}
+
+/// Check whether the given value representation holds textual data.
+pub fn is_textual(vr: VR) -> bool {
+    matches!(
+        vr,
+        VR::AE
+            | VR::AS
+            | VR::CS
+            | VR::DA
+            | VR::DS
+            | VR::DT
+            | VR::IS
+            | VR::LO
+            | VR::LT
+            | VR::PN
+            | VR::SH
+            | VR::ST
+            | VR::TM
+            | VR::UC
+            | VR::UI
+            | VR::UR
+            | VR::UT
+    )
+}
+
+/// Remove all insignificant characters
+/// from a single textual value of the given value representation.
+///
+/// The string is expected to contain only one value
+/// (no backslash delimiters).
+/// Case folding is not applied here,
+/// see [`text_eq`] for a full comparison.
+pub fn normalize_text(vr: VR, value: &str) -> &str {
+    let value = value.trim_end_matches([' ', '\0']);
+    let value = if leading_spaces_insignificant(vr) {
+        value.trim_start_matches(' ')
+    } else {
+        value
+    };
+    if vr == VR::PN {
+        // empty trailing components and component groups are not significant
+        value.trim_end_matches(['^', '=', ' '])
+    } else {
+        value
+    }
+}
+
+/// Compare two single textual values of the given value representation.
+///
+/// DS and IS values are compared by numeric value if both can be parsed,
+/// falling back to a textual comparison otherwise.
+pub fn text_eq(vr: VR, a: &str, b: &str) -> bool {
+    let a = normalize_text(vr, a);
+    let b = normalize_text(vr, b);
+
+    match vr {
+        VR::DS => {
+            if let (Ok(a), Ok(b)) = (a.parse::<f64>(), b.parse::<f64>()) {
+                return a == b;
+            }
+        }
+        VR::IS => {
+            if let (Ok(a), Ok(b)) = (a.parse::<i64>(), b.parse::<i64>()) {
+                return a == b;
+            }
+        }
+        _ => {}
+    }
+
+    if is_case_insensitive(vr) {
+        a.chars()
+            .flat_map(char::to_lowercase)
+            .eq(b.chars().flat_map(char::to_lowercase))
+    } else {
+        a == b
+    }
+}
+
+/// Compare two primitive values
+/// under the equality semantics of the given value representation.
+///
+/// Values of any variant are accepted,
+/// so a DS value held as `F64` can be compared
+/// against a DS value held as text.
+/// An empty value is equal to a value
+/// consisting of a single empty string.
+pub fn value_eq(vr: VR, a: &PrimitiveValue, b: &PrimitiveValue) -> bool {
+    if is_empty(vr, a) || is_empty(vr, b) {
+        return is_empty(vr, a) && is_empty(vr, b);
+    }
+
+    if is_textual(vr) {
+        let a = a.to_multi_str();
+        let b = b.to_multi_str();
+        return a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| text_eq(vr, a, b));
+    }
+
+    match vr {
+        VR::AT => match (a, b) {
+            (PrimitiveValue::Tags(a), PrimitiveValue::Tags(b)) => a == b,
+            _ => false,
+        },
+        VR::FL | VR::FD | VR::OF | VR::OD => match (a.to_multi_float64(), b.to_multi_float64()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        },
+        VR::SS | VR::US | VR::SL | VR::UL | VR::SV | VR::UV => {
+            match (a.to_multi_int::<i128>(), b.to_multi_int::<i128>()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            }
+        }
+        VR::SQ => false,
+        // OB, OW, OL, OV and UN: compare the raw bytes
+        _ => a.to_bytes() == b.to_bytes(),
+    }
+}
+
+/// Check whether the value is empty,
+/// in the sense that it does not hold any significant content.
+///
+/// Besides [`PrimitiveValue::Empty`],
+/// this includes values without any elements
+/// and textual values consisting of a single string
+/// with only insignificant characters (such as padding).
+pub fn is_empty(vr: VR, value: &PrimitiveValue) -> bool {
+    match value {
+        PrimitiveValue::Empty => true,
+        PrimitiveValue::Str(s) => normalize_text(vr, s).is_empty(),
+        PrimitiveValue::Strs(s) => {
+            s.is_empty() || (s.len() == 1 && normalize_text(vr, &s[0]).is_empty())
+        }
+        _ => value.multiplicity() == 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dicom_value;
+    use crate::header::Tag;
+    use crate::value::DicomDate;
+
+    #[test]
+    fn text_padding_is_ignored() {
+        assert!(text_eq(VR::UI, "1.2.840.10008.1.2\0", "1.2.840.10008.1.2"));
+        assert!(text_eq(VR::LO, "ABC  ", "ABC"));
+        assert!(text_eq(VR::SH, "  ABC", "ABC"));
+        // leading spaces are significant in text VRs
+        assert!(!text_eq(VR::LT, "  ABC", "ABC"));
+        assert!(text_eq(VR::LT, "ABC \0", "ABC"));
+    }
+
+    #[test]
+    fn case_sensitivity() {
+        assert!(text_eq(VR::CS, "original", "ORIGINAL"));
+        assert!(text_eq(VR::PN, "Simões^João", "SIMÕES^JOÃO"));
+        assert!(!text_eq(VR::LO, "abc", "ABC"));
+        assert!(!text_eq(VR::UI, "1.2.3", "1.2.4"));
+    }
+
+    #[test]
+    fn person_name_components() {
+        assert!(text_eq(VR::PN, "Smith^John^^^", "Smith^John"));
+        assert!(text_eq(VR::PN, "Smith^John==", "Smith^John"));
+        assert!(!text_eq(VR::PN, "Smith^^John", "Smith^John"));
+    }
+
+    #[test]
+    fn numeric_strings() {
+        assert!(text_eq(VR::DS, "1.50", "1.5"));
+        assert!(text_eq(VR::DS, " 1.5E0 ", "1.5"));
+        assert!(text_eq(VR::DS, "-0", "0"));
+        assert!(!text_eq(VR::DS, "1.5", "1.6"));
+        assert!(text_eq(VR::IS, "+0012", "12"));
+        assert!(!text_eq(VR::IS, "12", "13"));
+        // unparsable values fall back to text comparison
+        assert!(text_eq(VR::IS, "abc ", "abc"));
+    }
+
+    #[test]
+    fn primitive_values() {
+        // different variants, same DS value
+        assert!(value_eq(
+            VR::DS,
+            &dicom_value!(F64, [1.5, 2.]),
+            &dicom_value!(Strs, ["1.50", "2"]),
+        ));
+        // multiplicity must match
+        assert!(!value_eq(
+            VR::DS,
+            &dicom_value!(Strs, ["1.5", "2"]),
+            &dicom_value!(Strs, ["1.5"]),
+        ));
+        // IS as I32 versus text
+        assert!(value_eq(
+            VR::IS,
+            &dicom_value!(I32, [5, 6]),
+            &dicom_value!(Strs, [" 5", "06 "]),
+        ));
+        // binary integers in different variants
+        assert!(value_eq(
+            VR::US,
+            &dicom_value!(U16, [512]),
+            &dicom_value!(U32, [512]),
+        ));
+        assert!(!value_eq(
+            VR::US,
+            &dicom_value!(U16, [512]),
+            &dicom_value!(U16, [513]),
+        ));
+        // dates held as text or as dates
+        assert!(value_eq(
+            VR::DA,
+            &PrimitiveValue::from(DicomDate::from_ymd(2014, 10, 12).unwrap()),
+            &PrimitiveValue::from("20141012 "),
+        ));
+        assert!(value_eq(
+            VR::AT,
+            &PrimitiveValue::from(Tag(0x0010, 0x0010)),
+            &PrimitiveValue::from(Tag(0x0010, 0x0010)),
+        ));
+        assert!(value_eq(
+            VR::OB,
+            &dicom_value!(U8, [1, 2, 3]),
+            &PrimitiveValue::from(vec![1_u8, 2, 3]),
+        ));
+    }
+
+    #[test]
+    fn empty_values() {
+        assert!(value_eq(
+            VR::LO,
+            &PrimitiveValue::Empty,
+            &PrimitiveValue::Empty
+        ));
+        assert!(value_eq(
+            VR::LO,
+            &PrimitiveValue::Empty,
+            &PrimitiveValue::from(" "),
+        ));
+        assert!(value_eq(
+            VR::PN,
+            &dicom_value!(Strs, ["^^"]),
+            &PrimitiveValue::Empty,
+        ));
+        assert!(!value_eq(
+            VR::LO,
+            &PrimitiveValue::Empty,
+            &PrimitiveValue::from("A"),
+        ));
+        assert!(!value_eq(
+            VR::US,
+            &PrimitiveValue::Empty,
+            &dicom_value!(U16, [0]),
+        ));
+    }
+}
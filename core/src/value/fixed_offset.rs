@@ -0,0 +1,95 @@
+//! A minimal time zone offset type,
+//! used in place of [`chrono::FixedOffset`]
+//! when the `chrono` feature is disabled.
+//!
+//! [`chrono::FixedOffset`]: https://docs.rs/chrono/0.4/chrono/offset/struct.FixedOffset.html
+
+use std::fmt;
+
+/// A fixed offset from UTC,
+/// in the range of -86_399 to 86_399 seconds.
+///
+/// This type mirrors the parts of the `chrono::FixedOffset` API
+/// needed for holding the UTC offset of a DICOM date-time.
+/// Enable the `chrono` feature to use the `chrono` type instead.
+#[derive(Copy, Clone, Eq, Hash, PartialEq)]
+pub struct FixedOffset {
+    local_minus_utc: i32,
+}
+
+impl FixedOffset {
+    /// Create an offset for the Eastern Hemisphere,
+    /// from the given number of seconds ahead of UTC.
+    ///
+    /// Returns `None` if the offset is out of bounds.
+    pub const fn east_opt(secs: i32) -> Option<FixedOffset> {
+        if -86_400 < secs && secs < 86_400 {
+            Some(FixedOffset {
+                local_minus_utc: secs,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Create an offset for the Western Hemisphere,
+    /// from the given number of seconds behind UTC.
+    ///
+    /// Returns `None` if the offset is out of bounds.
+    pub const fn west_opt(secs: i32) -> Option<FixedOffset> {
+        if -86_400 < secs && secs < 86_400 {
+            Some(FixedOffset {
+                local_minus_utc: -secs,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The number of seconds to add to UTC to obtain the local time.
+    pub const fn local_minus_utc(&self) -> i32 {
+        self.local_minus_utc
+    }
+
+    /// The number of seconds to add to the local time to obtain UTC.
+    pub const fn utc_minus_local(&self) -> i32 {
+        -self.local_minus_utc
+    }
+}
+
+impl fmt::Display for FixedOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.local_minus_utc < 0 { '-' } else { '+' };
+        let offset = self.local_minus_utc.unsigned_abs();
+        let (hours, minutes, seconds) = (offset / 3600, offset / 60 % 60, offset % 60);
+        if seconds == 0 {
+            write!(f, "{}{:02}:{:02}", sign, hours, minutes)
+        } else {
+            write!(f, "{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds)
+        }
+    }
+}
+
+impl fmt::Debug for FixedOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedOffset;
+
+    #[test]
+    fn fixed_offset_bounds_and_display() {
+        assert_eq!(FixedOffset::east_opt(0).unwrap().to_string(), "+00:00");
+        assert_eq!(FixedOffset::east_opt(3600).unwrap().to_string(), "+01:00");
+        assert_eq!(FixedOffset::west_opt(5400).unwrap().to_string(), "-01:30");
+        assert_eq!(
+            FixedOffset::west_opt(5400).unwrap().local_minus_utc(),
+            -5400
+        );
+        assert_eq!(FixedOffset::east_opt(86_400), None);
+        assert_eq!(FixedOffset::west_opt(-86_400), None);
+    }
+}
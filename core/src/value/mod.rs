@@ -5,25 +5,42 @@ use num_traits::NumCast;
 use smallvec::SmallVec;
 use std::{borrow::Cow, str::FromStr};
 
+pub mod ae_title;
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod deserialize;
+pub mod equality;
+#[cfg(not(feature = "chrono"))]
+mod fixed_offset;
 pub mod partial;
 pub mod person_name;
 mod primitive;
+#[cfg(feature = "chrono")]
 pub mod range;
+#[cfg(not(feature = "chrono"))]
+mod requires_chrono;
 pub mod serialize;
 
+pub use self::ae_title::{AeTitle, AeTitleError};
 pub use self::deserialize::Error as DeserializeError;
 pub use self::partial::{DicomDate, DicomDateTime, DicomTime};
 pub use self::person_name::PersonName;
+#[cfg(feature = "chrono")]
 pub use self::range::{AsRange, DateRange, DateTimeRange, TimeRange};
+#[cfg(not(feature = "chrono"))]
+pub use self::requires_chrono::{AsRange, RequiresChrono};
 
 pub use self::primitive::{
     CastValueError, ConvertValueError, InvalidValueReadError, ModifyValueError, PrimitiveValue,
     ValueType,
 };
 
-/// re-exported from chrono
-use chrono::FixedOffset;
+// the UTC offset type of date-time values,
+// re-exported from chrono if available
+#[cfg(not(feature = "chrono"))]
+pub use self::fixed_offset::FixedOffset;
+#[cfg(feature = "chrono")]
+pub use chrono::FixedOffset;
 
 /// An aggregation of one or more elements in a value.
 pub type C<T> = SmallVec<[T; 2]>;
@@ -144,6 +161,14 @@ impl<I, P> Value<I, P> {
         }
     }
 
+    /// Gets a mutable reference to the items.
+    pub fn items_mut(&mut self) -> Option<&mut C<I>> {
+        match *self {
+            Value::Sequence { ref mut items, .. } => Some(items),
+            _ => None,
+        }
+    }
+
     /// Retrieves the primitive value.
     pub fn into_primitive(self) -> Option<PrimitiveValue> {
         match self {
@@ -507,6 +532,7 @@ where
     /// If the value is a primitive, it will be converted into
     /// a `DateRange` as described in [`PrimitiveValue::to_date_range`].
     ///
+    #[cfg(feature = "chrono")]
     pub fn to_date_range(&self) -> Result<DateRange, ConvertValueError> {
         match self {
             Value::Primitive(v) => v.to_date_range(),
@@ -523,6 +549,7 @@ where
     /// If the value is a primitive, it will be converted into
     /// a `TimeRange` as described in [`PrimitiveValue::to_time_range`].
     ///
+    #[cfg(feature = "chrono")]
     pub fn to_time_range(&self) -> Result<TimeRange, ConvertValueError> {
         match self {
             Value::Primitive(v) => v.to_time_range(),
@@ -539,6 +566,7 @@ where
     /// If the value is a primitive, it will be converted into
     /// a `DateTimeRange` as described in [`PrimitiveValue::to_datetime_range`].
     ///
+    #[cfg(feature = "chrono")]
     pub fn to_datetime_range(
         &self,
         offset: FixedOffset,
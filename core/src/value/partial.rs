@@ -1,8 +1,14 @@
 //! Handling of partial precision of Date, Time and DateTime values.
 
+#[cfg(feature = "chrono")]
 use crate::value::range::AsRange;
-use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveTime, Timelike};
-use snafu::{Backtrace, ResultExt, Snafu};
+use crate::value::FixedOffset;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Timelike};
+#[cfg(feature = "chrono")]
+use snafu::ResultExt;
+use snafu::{Backtrace, Snafu};
+#[cfg(feature = "chrono")]
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::ops::RangeInclusive;
@@ -80,7 +86,8 @@ pub enum DateComponent {
 /// [date](NaiveDate) values.
 ///
 /// # Example
-/// ```
+#[cfg_attr(feature = "chrono", doc = "```")]
+#[cfg_attr(not(feature = "chrono"), doc = "```ignore")]
 /// # use std::error::Error;
 /// # use std::convert::TryFrom;
 /// use chrono::NaiveDate;
@@ -115,7 +122,8 @@ pub struct DicomDate(DicomDateImpl);
 /// [time](NaiveTime) values.
 ///
 /// # Example
-/// ```
+#[cfg_attr(feature = "chrono", doc = "```")]
+#[cfg_attr(not(feature = "chrono"), doc = "```ignore")]
 /// # use std::error::Error;
 /// # use std::convert::TryFrom;
 /// use chrono::NaiveTime;
@@ -182,7 +190,8 @@ enum DicomTimeImpl {
 /// It implements [AsRange] trait and also holds a [FixedOffset] value, from which corresponding
 /// [datetime][DateTime] values can be retrieved.
 /// # Example
-/// ```
+#[cfg_attr(feature = "chrono", doc = "```")]
+#[cfg_attr(not(feature = "chrono"), doc = "```ignore")]
 /// # use std::error::Error;
 /// # use std::convert::TryFrom;
 /// use chrono::{DateTime, FixedOffset, TimeZone, NaiveDateTime, NaiveDate, NaiveTime};
@@ -319,6 +328,7 @@ impl DicomDate {
     }
 }
 
+#[cfg(feature = "chrono")]
 impl TryFrom<&NaiveDate> for DicomDate {
     type Error = Error;
     fn try_from(date: &NaiveDate) -> Result<Self> {
@@ -505,6 +515,7 @@ impl DicomTime {
     }
 }
 
+#[cfg(feature = "chrono")]
 impl TryFrom<&NaiveTime> for DicomTime {
     type Error = Error;
     fn try_from(time: &NaiveTime) -> Result<Self> {
@@ -586,7 +597,7 @@ impl DicomDateTime {
         time: DicomTime,
         offset: FixedOffset,
     ) -> Result<DicomDateTime> {
-        if date.is_precise() {
+        if is_precise_date(&date) {
             Ok(DicomDateTime {
                 date,
                 time: Some(time),
@@ -616,6 +627,17 @@ impl DicomDateTime {
     }
 }
 
+#[cfg(feature = "chrono")]
+fn is_precise_date(date: &DicomDate) -> bool {
+    date.is_precise()
+}
+
+#[cfg(not(feature = "chrono"))]
+fn is_precise_date(date: &DicomDate) -> bool {
+    date.precision() == DateComponent::Day
+}
+
+#[cfg(feature = "chrono")]
 impl TryFrom<&DateTime<FixedOffset>> for DicomDateTime {
     type Error = Error;
     fn try_from(dt: &DateTime<FixedOffset>) -> Result<Self> {
@@ -778,6 +800,7 @@ impl DicomDateTime {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "chrono")]
     use chrono::{NaiveDateTime, TimeZone};
 
     #[test]
@@ -794,7 +817,11 @@ mod tests {
             DicomDate::from_y(1944).unwrap(),
             DicomDate(DicomDateImpl::Year(1944))
         );
+    }
 
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dicom_date_chrono() {
         assert_eq!(DicomDate::from_ymd(1944, 2, 29).unwrap().is_precise(), true);
         assert_eq!(DicomDate::from_ym(1944, 2).unwrap().is_precise(), false);
         assert_eq!(DicomDate::from_y(1944).unwrap().is_precise(), false);
@@ -866,6 +893,55 @@ mod tests {
             DicomTime(DicomTimeImpl::Hour(1))
         );
 
+        assert_eq!(
+            DicomTime::from_hms_milli(9, 1, 1, 1).unwrap(),
+            DicomTime(DicomTimeImpl::Fraction(9, 1, 1, 1, 3))
+        );
+
+        assert_eq!(
+            DicomTime::from_hmsf(9, 1, 1, 1, 4).unwrap().to_string(),
+            "09:01:01.0001"
+        );
+        assert_eq!(
+            DicomTime::from_hmsf(9, 1, 1, 0, 1).unwrap().to_string(),
+            "09:01:01.0"
+        );
+        assert_eq!(
+            DicomTime::from_hmsf(7, 55, 1, 1, 5).unwrap().to_encoded(),
+            "075501.00001"
+        );
+        // any precision for zero is just one zero
+        assert_eq!(
+            DicomTime::from_hmsf(9, 1, 1, 0, 6).unwrap().to_encoded(),
+            "090101.0"
+        );
+
+        assert!(matches!(
+            DicomTime::from_hmsf(9, 1, 1, 1, 7),
+            Err(Error::FractionPrecisionRange { value: 7, .. })
+        ));
+
+        assert!(matches!(
+            DicomTime::from_hms_milli(9, 1, 1, 1000),
+            Err(Error::InvalidComponent {
+                component: DateComponent::Millisecond,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            DicomTime::from_hmsf(9, 1, 1, 123456, 3),
+            Err(Error::FractionPrecisionMismatch {
+                fraction: 123456,
+                precision: 3,
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dicom_time_chrono() {
         assert_eq!(
             DicomTime::from_hms_milli(9, 1, 1, 123)
                 .unwrap()
@@ -903,11 +979,6 @@ mod tests {
             true
         );
 
-        assert_eq!(
-            DicomTime::from_hms_milli(9, 1, 1, 1).unwrap(),
-            DicomTime(DicomTimeImpl::Fraction(9, 1, 1, 1, 3))
-        );
-
         assert_eq!(
             DicomTime::try_from(&NaiveTime::from_hms_milli_opt(16, 31, 28, 123).unwrap()).unwrap(),
             DicomTime(DicomTimeImpl::Fraction(16, 31, 28, 123_000, 6))
@@ -928,46 +999,6 @@ mod tests {
             DicomTime(DicomTimeImpl::Fraction(16, 31, 28, 0, 6))
         );
 
-        assert_eq!(
-            DicomTime::from_hmsf(9, 1, 1, 1, 4).unwrap().to_string(),
-            "09:01:01.0001"
-        );
-        assert_eq!(
-            DicomTime::from_hmsf(9, 1, 1, 0, 1).unwrap().to_string(),
-            "09:01:01.0"
-        );
-        assert_eq!(
-            DicomTime::from_hmsf(7, 55, 1, 1, 5).unwrap().to_encoded(),
-            "075501.00001"
-        );
-        // any precision for zero is just one zero
-        assert_eq!(
-            DicomTime::from_hmsf(9, 1, 1, 0, 6).unwrap().to_encoded(),
-            "090101.0"
-        );
-
-        assert!(matches!(
-            DicomTime::from_hmsf(9, 1, 1, 1, 7),
-            Err(Error::FractionPrecisionRange { value: 7, .. })
-        ));
-
-        assert!(matches!(
-            DicomTime::from_hms_milli(9, 1, 1, 1000),
-            Err(Error::InvalidComponent {
-                component: DateComponent::Millisecond,
-                ..
-            })
-        ));
-
-        assert!(matches!(
-            DicomTime::from_hmsf(9, 1, 1, 123456, 3),
-            Err(Error::FractionPrecisionMismatch {
-                fraction: 123456,
-                precision: 3,
-                ..
-            })
-        ));
-
         assert!(matches!(
             DicomTime::try_from(&NaiveTime::from_hms_micro_opt(16, 31, 28, 1_000_000).unwrap()),
             Err(Error::InvalidComponent {
@@ -994,6 +1025,34 @@ mod tests {
             }
         );
 
+        assert!(matches!(
+            DicomDateTime::from_date_and_time(
+                DicomDate::from_ym(2020, 2).unwrap(),
+                DicomTime::from_hms_milli(23, 59, 59, 999).unwrap(),
+                default_offset
+            ),
+            Err(Error::DateTimeFromPartials {
+                value: DateComponent::Month,
+                ..
+            })
+        ));
+        assert!(matches!(
+            DicomDateTime::from_date_and_time(
+                DicomDate::from_y(1).unwrap(),
+                DicomTime::from_hms_micro(23, 59, 59, 10).unwrap(),
+                default_offset
+            ),
+            Err(Error::DateTimeFromPartials {
+                value: DateComponent::Year,
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dicom_datetime_chrono() {
+        let default_offset = FixedOffset::east_opt(0).unwrap();
         assert_eq!(
             DicomDateTime::from_date(DicomDate::from_ym(2020, 2).unwrap(), default_offset)
                 .earliest()
@@ -1097,29 +1156,6 @@ mod tests {
             Err(crate::value::range::Error::InvalidDate { .. })
         ));
 
-        assert!(matches!(
-            DicomDateTime::from_date_and_time(
-                DicomDate::from_ym(2020, 2).unwrap(),
-                DicomTime::from_hms_milli(23, 59, 59, 999).unwrap(),
-                default_offset
-            ),
-            Err(Error::DateTimeFromPartials {
-                value: DateComponent::Month,
-                ..
-            })
-        ));
-        assert!(matches!(
-            DicomDateTime::from_date_and_time(
-                DicomDate::from_y(1).unwrap(),
-                DicomTime::from_hms_micro(23, 59, 59, 10).unwrap(),
-                default_offset
-            ),
-            Err(Error::DateTimeFromPartials {
-                value: DateComponent::Year,
-                ..
-            })
-        ));
-
         assert!(matches!(
             DicomDateTime::from_date_and_time(
                 DicomDate::from_ymd(2000, 1, 1).unwrap(),
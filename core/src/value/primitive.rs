@@ -6,8 +6,9 @@ use super::DicomValueType;
 use crate::header::{HasLength, Length, Tag};
 use crate::value::partial::{DateComponent, DicomDate, DicomDateTime, DicomTime, Precision};
 use crate::value::person_name::PersonName;
+#[cfg(feature = "chrono")]
 use crate::value::range::{DateRange, DateTimeRange, TimeRange};
-use chrono::FixedOffset;
+use crate::value::FixedOffset;
 use itertools::Itertools;
 use num_traits::NumCast;
 use safe_transmute::to_bytes::transmute_to_bytes;
@@ -75,16 +76,19 @@ pub enum InvalidValueReadError {
         #[snafu(backtrace)]
         source: crate::value::partial::Error,
     },
+    #[cfg(feature = "chrono")]
     #[snafu(display("Failed to read text as a date range"))]
     ParseDateRange {
         #[snafu(backtrace)]
         source: crate::value::range::Error,
     },
+    #[cfg(feature = "chrono")]
     #[snafu(display("Failed to read text as a time range"))]
     ParseTimeRange {
         #[snafu(backtrace)]
         source: crate::value::range::Error,
     },
+    #[cfg(feature = "chrono")]
     #[snafu(display("Failed to read text as a date-time range"))]
     ParseDateTimeRange {
         #[snafu(backtrace)]
@@ -183,6 +187,7 @@ impl std::error::Error for ConvertValueError {
 pub type Result<T, E = InvalidValueReadError> = std::result::Result<T, E>;
 
 // Re-exported from chrono
+#[cfg(feature = "chrono")]
 pub use chrono::{DateTime, NaiveDate, NaiveTime};
 
 /// An aggregation of one or more elements in a value.
@@ -387,6 +392,27 @@ impl_from_array_for_primitive_1_to_8!(DicomDate, Date);
 impl_from_array_for_primitive_1_to_8!(DicomTime, Time);
 impl_from_array_for_primitive_1_to_8!(DicomDateTime, DateTime);
 
+/// Implement a method on `PrimitiveValue`
+/// for borrowing the values of one binary numeric variant
+/// as a contiguous slice.
+macro_rules! impl_primitive_as_slice {
+    ($($method: ident, $typ: ty, $variant: ident;)*) => {
+        $(
+            #[doc = concat!(
+                "Borrow all values as a slice of `", stringify!($typ), "`,\n",
+                "without copying or converting them.\n\n",
+                "Returns `None` if the value is not of the variant `", stringify!($variant), "`.",
+            )]
+            pub fn $method(&self) -> Option<&[$typ]> {
+                match self {
+                    PrimitiveValue::$variant(c) => Some(c),
+                    _ => None,
+                }
+            }
+        )*
+    };
+}
+
 impl PrimitiveValue {
     /// Create a single unsigned 16-bit value.
     pub fn new_u16(value: u16) -> Self {
@@ -403,6 +429,18 @@ impl PrimitiveValue {
         PrimitiveValue::I32(C::from_elem(value, 1))
     }
 
+    impl_primitive_as_slice! {
+        as_u8_slice, u8, U8;
+        as_i16_slice, i16, I16;
+        as_u16_slice, u16, U16;
+        as_i32_slice, i32, I32;
+        as_u32_slice, u32, U32;
+        as_i64_slice, i64, I64;
+        as_u64_slice, u64, U64;
+        as_f32_slice, f32, F32;
+        as_f64_slice, f64, F64;
+    }
+
     /// Obtain the number of individual elements. This number may not
     /// match the DICOM value multiplicity in some value representations.
     pub fn multiplicity(&self) -> u32 {
@@ -963,17 +1001,16 @@ impl PrimitiveValue {
         T: FromStr<Err = std::num::ParseIntError>,
     {
         match self {
-            PrimitiveValue::Str(s) => {
-                s.trim_start()
-                    .trim_end_matches(|c: char| c == '\0' || c.is_whitespace())
-                    .parse()
-                    .context(ParseIntegerSnafu)
-                    .map_err(|err| ConvertValueError {
-                        requested: "integer",
-                        original: self.value_type(),
-                        cause: Some(err),
-                    })
-            }
+            PrimitiveValue::Str(s) => s
+                .trim_start()
+                .trim_end_matches(|c: char| c == '\0' || c.is_whitespace())
+                .parse()
+                .context(ParseIntegerSnafu)
+                .map_err(|err| ConvertValueError {
+                    requested: "integer",
+                    original: self.value_type(),
+                    cause: Some(err),
+                }),
             PrimitiveValue::Strs(s) if !s.is_empty() => s[0]
                 .trim_start()
                 .trim_end_matches(|c: char| c == '\0' || c.is_whitespace())
@@ -2014,6 +2051,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_naive_date(&self) -> Result<NaiveDate, ConvertValueError> {
         match self {
             PrimitiveValue::Date(v) if !v.is_empty() => v[0]
@@ -2100,6 +2138,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_multi_naive_date(&self) -> Result<Vec<NaiveDate>, ConvertValueError> {
         match self {
             PrimitiveValue::Date(v) if !v.is_empty() => v
@@ -2167,7 +2206,8 @@ impl PrimitiveValue {
     ///
     /// # Example
     ///
-    /// ```
+    #[cfg_attr(feature = "chrono", doc = "```")]
+    #[cfg_attr(not(feature = "chrono"), doc = "```ignore")]
     /// # use dicom_core::value::{C, PrimitiveValue};
     /// # use smallvec::smallvec;
     /// # use chrono::NaiveDate;
@@ -2352,6 +2392,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_naive_time(&self) -> Result<NaiveTime, ConvertValueError> {
         match self {
             PrimitiveValue::Time(v) if !v.is_empty() => v[0]
@@ -2443,6 +2484,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_multi_naive_time(&self) -> Result<Vec<NaiveTime>, ConvertValueError> {
         match self {
             PrimitiveValue::Time(v) if !v.is_empty() => v
@@ -2513,7 +2555,8 @@ impl PrimitiveValue {
     ///
     /// # Example
     ///
-    /// ```
+    #[cfg_attr(feature = "chrono", doc = "```")]
+    #[cfg_attr(not(feature = "chrono"), doc = "```ignore")]
     /// # use dicom_core::value::{C, PrimitiveValue};
     /// # use chrono::NaiveTime;
     /// use dicom_core::value::{AsRange, DicomTime};
@@ -2752,6 +2795,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_chrono_datetime(
         &self,
         default_offset: FixedOffset,
@@ -2860,6 +2904,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_multi_chrono_datetime(
         &self,
         default_offset: FixedOffset,
@@ -2933,7 +2978,8 @@ impl PrimitiveValue {
     /// - [`.range()`](crate::value::range::AsRange::range)
     /// # Example
     ///
-    /// ```
+    #[cfg_attr(feature = "chrono", doc = "```")]
+    #[cfg_attr(not(feature = "chrono"), doc = "```ignore")]
     /// # use dicom_core::value::{C, PrimitiveValue};
     /// # use smallvec::smallvec;
     /// # use chrono::{DateTime, FixedOffset, TimeZone};
@@ -3117,6 +3163,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_date_range(&self) -> Result<DateRange, ConvertValueError> {
         match self {
             PrimitiveValue::Str(s) => super::range::parse_date_range(s.trim_end().as_bytes())
@@ -3192,6 +3239,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_time_range(&self) -> Result<TimeRange, ConvertValueError> {
         match self {
             PrimitiveValue::Str(s) => super::range::parse_time_range(s.trim_end().as_bytes())
@@ -3273,6 +3321,7 @@ impl PrimitiveValue {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "chrono")]
     pub fn to_datetime_range(
         &self,
         offset: FixedOffset,
@@ -3967,9 +4016,11 @@ mod tests {
     use super::{CastValueError, ConvertValueError, InvalidValueReadError};
     use crate::dicom_value;
     use crate::value::partial::{DicomDate, DicomDateTime, DicomTime};
+    #[cfg(feature = "chrono")]
     use crate::value::range::{DateRange, DateTimeRange, TimeRange};
-    use crate::value::{PrimitiveValue, ValueType};
-    use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+    use crate::value::{FixedOffset, PrimitiveValue, ValueType};
+    #[cfg(feature = "chrono")]
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
     use smallvec::smallvec;
 
     #[test]
@@ -4227,6 +4278,7 @@ mod tests {
         ));
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn primitive_value_to_naive_date() {
         // to NaiveDate
@@ -4304,6 +4356,7 @@ mod tests {
         assert!(dicom_value!(Strs, ["-44"]).to_multi_date().is_err());
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn primitive_value_to_naive_time() {
         // trivial conversion
@@ -4417,6 +4470,7 @@ mod tests {
         ));
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn primitive_value_to_chrono_datetime() {
         let this_datetime = FixedOffset::east_opt(1)
@@ -4596,6 +4650,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn primitive_value_to_date_range() {
         // converts first value of sequence
@@ -4607,6 +4662,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn primitive_value_to_time_range() {
         assert_eq!(
@@ -4619,6 +4675,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn primitive_value_to_datetime_range() {
         let offset = FixedOffset::west_opt(3600).unwrap();
@@ -4801,4 +4858,18 @@ mod tests {
 
         assert_ne!(dicom_value!(Strs, ["Doe^John", "Silva^João"]), "Doe^John");
     }
+
+    #[test]
+    fn primitive_value_as_slice() {
+        let value = PrimitiveValue::from([1_u16, 2, 3]);
+        assert_eq!(value.as_u16_slice(), Some(&[1_u16, 2, 3][..]));
+        assert_eq!(value.as_u32_slice(), None);
+
+        let value = PrimitiveValue::from([0.5_f64, -1.25]);
+        assert_eq!(value.as_f64_slice(), Some(&[0.5, -1.25][..]));
+        assert_eq!(value.as_f32_slice(), None);
+
+        assert_eq!(PrimitiveValue::from("1\\2").as_i32_slice(), None);
+        assert_eq!(PrimitiveValue::Empty.as_u8_slice(), None);
+    }
 }
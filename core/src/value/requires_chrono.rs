@@ -0,0 +1,121 @@
+//! Placeholders of the APIs which require the `chrono` feature.
+//!
+//! Without the `chrono` feature,
+//! the methods listed here exist only so that using them
+//! fails to compile with an error message
+//! pointing to the feature which needs to be enabled.
+//! None of them can be called.
+//!
+//! ```compile_fail
+//! # use dicom_core::value::PrimitiveValue;
+//! // error: this API requires the `chrono` feature of `dicom-core`
+//! let date = PrimitiveValue::from("20230415").to_naive_date();
+//! ```
+//!
+//! ```compile_fail
+//! # use dicom_core::value::{AsRange, DicomDate};
+//! // error: this API requires the `chrono` feature of `dicom-core`
+//! let date = DicomDate::from_ym(2023, 4)?.earliest();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use super::{DicomDate, DicomDateTime, DicomTime, FixedOffset, PrimitiveValue, Value};
+
+/// A marker trait for the APIs which require the `chrono` feature.
+///
+/// This trait has no implementations,
+/// so a method bound by it can never be called.
+#[diagnostic::on_unimplemented(
+    message = "this API requires the `chrono` feature of `dicom-core`",
+    label = "requires the `chrono` feature",
+    note = "enable the `chrono` feature of `dicom-core`, which is enabled by default"
+)]
+pub trait RequiresChrono {}
+
+/// Placeholder of the trait for retrieving
+/// the range of a partial date or time value,
+/// which requires the `chrono` feature.
+pub trait AsRange {
+    /// Requires the `chrono` feature.
+    fn exact(&self) -> !
+    where
+        Self: RequiresChrono,
+    {
+        unreachable!()
+    }
+
+    /// Requires the `chrono` feature.
+    fn earliest(&self) -> !
+    where
+        Self: RequiresChrono,
+    {
+        unreachable!()
+    }
+
+    /// Requires the `chrono` feature.
+    fn latest(&self) -> !
+    where
+        Self: RequiresChrono,
+    {
+        unreachable!()
+    }
+
+    /// Requires the `chrono` feature.
+    fn range(&self) -> !
+    where
+        Self: RequiresChrono,
+    {
+        unreachable!()
+    }
+
+    /// Requires the `chrono` feature.
+    fn is_precise(&self) -> !
+    where
+        Self: RequiresChrono,
+    {
+        unreachable!()
+    }
+}
+
+impl AsRange for DicomDate {}
+impl AsRange for DicomTime {}
+impl AsRange for DicomDateTime {}
+
+/// Declare placeholder methods which can never be called.
+///
+/// The higher-ranked bound is never satisfied,
+/// but is only checked where the method is used.
+macro_rules! requires_chrono {
+    ($($name: ident ( $($arg: ident : $t: ty),* );)*) => {
+        $(
+            /// Requires the `chrono` feature.
+            pub fn $name(&self, $(_: $t),*) -> !
+            where
+                for<'a> &'a Self: RequiresChrono,
+            {
+                unreachable!()
+            }
+        )*
+    };
+}
+
+impl PrimitiveValue {
+    requires_chrono! {
+        to_naive_date();
+        to_multi_naive_date();
+        to_naive_time();
+        to_multi_naive_time();
+        to_chrono_datetime(default_offset: FixedOffset);
+        to_multi_chrono_datetime(default_offset: FixedOffset);
+        to_date_range();
+        to_time_range();
+        to_datetime_range(offset: FixedOffset);
+    }
+}
+
+impl<I, P> Value<I, P> {
+    requires_chrono! {
+        to_date_range();
+        to_time_range();
+        to_datetime_range(offset: FixedOffset);
+    }
+}
@@ -0,0 +1,280 @@
+//! Derive macros for DICOM-rs.
+//!
+//! This crate provides `#[derive(DicomModel)]`,
+//! implementing `dicom_object::model::DicomModel` for structs
+//! whose fields map to the attributes of a DICOM data set.
+//! It is usually used through `dicom-object` with the `derive` feature.
+//!
+//! # Attributes
+//!
+//! Each field is mapped to the attribute
+//! identified by one of the following field attributes:
+//!
+//! - `#[dicom(keyword = "PatientName")]`,
+//!   an attribute keyword in the standard data dictionary;
+//! - `#[dicom(tag = "(0010,0010)")]`,
+//!   an attribute tag, also written as `0010,0010` or `00100010`.
+//!
+//! Without either, the keyword is derived from the field name
+//! in UpperCamelCase (`patient_name` maps to `PatientName`).
+//! Keywords are resolved at compile time.
+//!
+//! `#[dicom(vr = "LO")]` sets the VR used when writing the attribute,
+//! which otherwise is the one in the object's data dictionary.
+//! The VR must be given for attributes outside of the dictionary,
+//! such as private attributes.
+//!
+//! `#[dicom(skip)]` leaves a field out of the mapping:
+//! it is set to its default value when reading,
+//! and not written.
+//!
+//! On the struct, `#[dicom(crate = "dicom::object")]`
+//! sets the path to the `dicom-object` crate,
+//! for when it is only available through the `dicom` parent crate.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::model::DicomModel;
+//!
+//! #[derive(Debug, DicomModel)]
+//! struct Patient {
+//!     #[dicom(keyword = "PatientName")]
+//!     name: String,
+//!     #[dicom(keyword = "PatientID")]
+//!     id: String,
+//!     patient_birth_date: Option<String>,
+//!     #[dicom(tag = "(0009,1001)", vr = "LO")]
+//!     site: Option<String>,
+//!     #[dicom(skip)]
+//!     visits: u32,
+//! }
+//!
+//! let obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+//!     DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("12345")),
+//! ]);
+//! let mut patient = Patient::from_dicom(&obj)?;
+//! assert_eq!(patient.name, "Doe^John");
+//! assert_eq!(patient.patient_birth_date, None);
+//!
+//! patient.site = Some("North".to_string());
+//! let obj = patient.to_dicom()?;
+//! assert_eq!(obj.element(dicom_core::Tag(0x0009, 0x1001))?.vr(), VR::LO);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::{Tag, VR};
+use dicom_dictionary_std::StandardDataDictionary;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Path};
+
+/// Derive `DicomModel` for a struct with named fields.
+///
+/// See the [crate-level documentation](crate) for the supported attributes.
+#[proc_macro_derive(DicomModel, attributes(dicom))]
+pub fn derive_dicom_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The mapping of a single field.
+struct FieldMapping {
+    ident: syn::Ident,
+    /// the attribute and the VR to write, or `None` if skipped
+    attribute: Option<(Tag, Option<VR>)>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut krate: Path = syn::parse_quote!(::dicom_object);
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("dicom")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                let path: LitStr = meta.value()?.parse()?;
+                krate = path.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported dicom attribute"))
+            }
+        })?;
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "DicomModel can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "DicomModel can only be derived for structs",
+            ))
+        }
+    };
+    let mappings = fields
+        .iter()
+        .map(field_mapping)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let private = quote!(#krate::model::__private);
+    let tag_tokens = |tag: Tag| {
+        let (group, element) = (tag.group(), tag.element());
+        quote!(#private::Tag(#group, #element))
+    };
+
+    let reads = mappings.iter().map(|mapping| {
+        let ident = &mapping.ident;
+        match mapping.attribute {
+            Some((tag, _)) => {
+                let tag = tag_tokens(tag);
+                quote! {
+                    #ident: #krate::model::FieldValue::read_field(
+                        #tag,
+                        obj.element_opt(#tag).ok().flatten(),
+                    )?
+                }
+            }
+            None => quote!(#ident: ::core::default::Default::default()),
+        }
+    });
+    let writes = mappings.iter().filter_map(|mapping| {
+        let ident = &mapping.ident;
+        let (tag, vr) = mapping.attribute?;
+        let tag = tag_tokens(tag);
+        let vr = match vr {
+            Some(vr) => {
+                let vr = syn::Ident::new(vr.to_string(), proc_macro2::Span::call_site());
+                quote!(::core::option::Option::Some(#private::VR::#vr))
+            }
+            None => quote!(::core::option::Option::None),
+        };
+        Some(quote! {
+            #krate::model::FieldValue::write_field(&self.#ident, obj, #tag, #vr)?;
+        })
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::model::DicomModel for #name #ty_generics #where_clause {
+            fn from_dicom<__D>(
+                obj: &#krate::InMemDicomObject<__D>,
+            ) -> #krate::model::Result<Self>
+            where
+                __D: #private::DataDictionary + ::core::clone::Clone,
+            {
+                ::core::result::Result::Ok(#name {
+                    #(#reads,)*
+                })
+            }
+
+            fn write_dicom<__D>(
+                &self,
+                obj: &mut #krate::InMemDicomObject<__D>,
+            ) -> #krate::model::Result<()>
+            where
+                __D: #private::DataDictionary + ::core::clone::Clone,
+            {
+                #(#writes)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+fn field_mapping(field: &syn::Field) -> syn::Result<FieldMapping> {
+    let ident = field.ident.clone().expect("named field");
+    let mut tag = None;
+    let mut vr = None;
+    let mut skip = false;
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("dicom")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                return Ok(());
+            }
+            let value: LitStr = meta.value()?.parse()?;
+            if meta.path.is_ident("tag") {
+                let parsed = value
+                    .value()
+                    .parse::<Tag>()
+                    .map_err(|_| syn::Error::new(value.span(), "invalid attribute tag"))?;
+                tag = Some(parsed);
+            } else if meta.path.is_ident("keyword") {
+                tag = Some(lookup_keyword(&value.value(), value.span())?);
+            } else if meta.path.is_ident("vr") {
+                let parsed = value
+                    .value()
+                    .parse::<VR>()
+                    .map_err(|_| syn::Error::new(value.span(), "invalid value representation"))?;
+                vr = Some(parsed);
+            } else {
+                return Err(meta.error("unsupported dicom attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if skip {
+        return Ok(FieldMapping {
+            ident,
+            attribute: None,
+        });
+    }
+    let tag = match tag {
+        Some(tag) => tag,
+        None => {
+            let keyword = upper_camel_case(&ident.to_string());
+            lookup_keyword(&keyword, ident.span()).map_err(|_| {
+                syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "no attribute with keyword `{}`, \
+                         use #[dicom(keyword = \"...\")] or #[dicom(tag = \"...\")]",
+                        keyword
+                    ),
+                )
+            })?
+        }
+    };
+    Ok(FieldMapping {
+        ident,
+        attribute: Some((tag, vr)),
+    })
+}
+
+fn lookup_keyword(keyword: &str, span: proc_macro2::Span) -> syn::Result<Tag> {
+    StandardDataDictionary
+        .by_name(keyword)
+        .map(|entry| entry.tag())
+        .ok_or_else(|| syn::Error::new(span, format!("unknown attribute keyword `{}`", keyword)))
+}
+
+/// Convert a field name in snake_case to UpperCamelCase.
+fn upper_camel_case(name: &str) -> String {
+    let name = name.strip_prefix("r#").unwrap_or(name);
+    name.split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+//! Tests for `#[derive(DicomModel)]`.
+use dicom_core::value::{DicomDate, PrimitiveValue, Value};
+use dicom_core::{DataElement, Length, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_object::model::{DicomModel, Error};
+use dicom_object::InMemDicomObject;
+
+#[derive(Debug, PartialEq, DicomModel)]
+struct Code {
+    code_value: String,
+    coding_scheme_designator: String,
+    code_meaning: Option<String>,
+}
+
+#[derive(Debug, PartialEq, DicomModel)]
+struct Image {
+    #[dicom(keyword = "SOPInstanceUID")]
+    sop_instance_uid: String,
+    rows: u16,
+    columns: u16,
+    #[dicom(keyword = "ImageType")]
+    image_type: Vec<String>,
+    pixel_spacing: Option<Vec<f64>>,
+    #[dicom(tag = "(0008,0023)")]
+    date: Option<DicomDate>,
+    #[dicom(keyword = "AnatomicRegionSequence")]
+    regions: Vec<Code>,
+    #[dicom(tag = "0009,1001", vr = "LO")]
+    private_note: Option<String>,
+    #[dicom(skip)]
+    cached: Option<u64>,
+}
+
+fn sample() -> InMemDicomObject {
+    InMemDicomObject::from_element_iter([
+        DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4\0"),
+        ),
+        DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+        DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(256_u16)),
+        DataElement::new(
+            tags::IMAGE_TYPE,
+            VR::CS,
+            PrimitiveValue::Strs(["ORIGINAL".to_string(), "PRIMARY".to_string()].into()),
+        ),
+        DataElement::new(
+            tags::PIXEL_SPACING,
+            VR::DS,
+            PrimitiveValue::Strs(["0.5".to_string(), "0.5".to_string()].into()),
+        ),
+        DataElement::new(tags::CONTENT_DATE, VR::DA, PrimitiveValue::from("20230131")),
+        DataElement::new(
+            tags::ANATOMIC_REGION_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: vec![InMemDicomObject::from_element_iter([
+                    DataElement::new(tags::CODE_VALUE, VR::SH, PrimitiveValue::from("T-D3000")),
+                    DataElement::new(
+                        tags::CODING_SCHEME_DESIGNATOR,
+                        VR::SH,
+                        PrimitiveValue::from("SRT"),
+                    ),
+                ])]
+                .into(),
+                size: Length::UNDEFINED,
+            },
+        ),
+    ])
+}
+
+#[test]
+fn read_and_write_model() {
+    let image = Image::from_dicom(&sample()).unwrap();
+    assert_eq!(
+        image,
+        Image {
+            sop_instance_uid: "1.2.3.4".to_string(),
+            rows: 512,
+            columns: 256,
+            image_type: vec!["ORIGINAL".to_string(), "PRIMARY".to_string()],
+            pixel_spacing: Some(vec![0.5, 0.5]),
+            date: Some(DicomDate::from_ymd(2023, 1, 31).unwrap()),
+            regions: vec![Code {
+                code_value: "T-D3000".to_string(),
+                coding_scheme_designator: "SRT".to_string(),
+                code_meaning: None,
+            }],
+            private_note: None,
+            cached: None,
+        }
+    );
+
+    let image = Image {
+        private_note: Some("reviewed".to_string()),
+        cached: Some(1),
+        ..image
+    };
+    let obj = image.to_dicom().unwrap();
+    assert_eq!(obj.element(tags::ROWS).unwrap().vr(), VR::US);
+    assert_eq!(obj.element(tags::PIXEL_SPACING).unwrap().vr(), VR::DS);
+    assert_eq!(obj.element(Tag(0x0009, 0x1001)).unwrap().vr(), VR::LO);
+    assert!(obj.element_opt(tags::CODE_MEANING).unwrap().is_none());
+    // skipped fields are not written, and read as their default
+    assert_eq!(
+        Image::from_dicom(&obj).unwrap(),
+        Image {
+            cached: None,
+            ..image
+        }
+    );
+}
+
+#[test]
+fn missing_required_attribute() {
+    let mut obj = sample();
+    obj.remove_element(tags::COLUMNS);
+    assert!(matches!(
+        Image::from_dicom(&obj),
+        Err(Error::MissingAttribute { tag, .. }) if tag == tags::COLUMNS
+    ));
+}
@@ -0,0 +1,183 @@
+//! Mapping between frames and fragments of encapsulated pixel data.
+//!
+//! Encapsulated pixel data is held in a sequence of fragments,
+//! and a frame may span one or more consecutive fragments.
+//! The fragments of each frame are identified
+//! through the _Extended Offset Table_ or the basic offset table,
+//! which contain the byte offset of the first fragment of each frame.
+//! These offsets are relative to the first byte of the first fragment's item,
+//! so each fragment accounts for its length plus 8 bytes of item header.
+//!
+//! Without an offset table,
+//! the fragments of a frame can only be identified
+//! if there is a single frame (which spans all fragments)
+//! or exactly one fragment per frame.
+use super::PixelDataObject;
+use std::ops::Range;
+
+/// The length of a fragment's item header in bytes.
+const ITEM_HEADER_LENGTH: u64 = 8;
+
+/// Iterate over the fragments of the given encapsulated pixel data,
+/// in order.
+///
+/// The iterator is empty if the pixel data is native.
+pub fn fragments(src: &dyn PixelDataObject) -> impl Iterator<Item = Vec<u8>> + '_ {
+    let nr_fragments = src.number_of_fragments().unwrap_or(0) as usize;
+    (0..nr_fragments).map_while(move |index| src.fragment(index))
+}
+
+/// Determine the range of fragments which belong to the given frame,
+/// based on the lengths of all fragments
+/// and a table of byte offsets to the first fragment of each frame.
+///
+/// Returns `None` if the frame is out of range
+/// or an offset does not point to the start of a fragment.
+pub fn frame_fragment_range(
+    fragment_lengths: &[u64],
+    offsets: &[u64],
+    frame: u32,
+) -> Option<Range<usize>> {
+    let frame = frame as usize;
+    let fragment_at = |offset: u64| {
+        let mut position = 0;
+        for (index, length) in fragment_lengths.iter().enumerate() {
+            if position == offset {
+                return Some(index);
+            }
+            if position > offset {
+                return None;
+            }
+            position += length + ITEM_HEADER_LENGTH;
+        }
+        None
+    };
+
+    let start = fragment_at(*offsets.get(frame)?)?;
+    let end = match offsets.get(frame + 1) {
+        Some(&offset) => fragment_at(offset)?,
+        None => fragment_lengths.len(),
+    };
+    Some(start..end).filter(|range| !range.is_empty())
+}
+
+/// Determine the range of fragments of every frame,
+/// given the lengths of all fragments,
+/// the frame offset table (if any),
+/// and the number of frames.
+///
+/// Returns `None` if the fragments of each frame cannot be identified:
+/// when the offset table does not have one offset per frame
+/// or is inconsistent with the fragments,
+/// or when there is no offset table
+/// and more than one frame
+/// which do not map to exactly one fragment each.
+pub fn frame_fragment_ranges(
+    fragment_lengths: &[u64],
+    offsets: Option<&[u64]>,
+    number_of_frames: u32,
+) -> Option<Vec<Range<usize>>> {
+    let nr_fragments = fragment_lengths.len();
+    if number_of_frames == 1 {
+        return Some(std::iter::once(0..nr_fragments).collect());
+    }
+    match offsets {
+        Some(offsets) if offsets.len() == number_of_frames as usize => (0..number_of_frames)
+            .map(|frame| frame_fragment_range(fragment_lengths, offsets, frame))
+            .collect(),
+        Some(_) => None,
+        None if nr_fragments == number_of_frames as usize => {
+            Some((0..nr_fragments).map(|i| i..i + 1).collect())
+        }
+        None => None,
+    }
+}
+
+/// Determine the range of fragments of every frame
+/// in the given encapsulated pixel data,
+/// using its frame offsets if available.
+///
+/// Returns `None` if the pixel data is native
+/// or the fragments of each frame cannot be identified
+/// (see [`frame_fragment_ranges`]).
+pub fn object_frame_fragment_ranges(src: &dyn PixelDataObject) -> Option<Vec<Range<usize>>> {
+    let nr_fragments = src.number_of_fragments()?;
+    let nr_frames = u32::from(src.number_of_frames().unwrap_or(1));
+    if nr_frames == 1 {
+        return Some(std::iter::once(0..nr_fragments as usize).collect());
+    }
+    match src.frame_offsets() {
+        Some(offsets) => {
+            let lengths: Vec<u64> = fragments(src).map(|f| f.len() as u64).collect();
+            frame_fragment_ranges(&lengths, Some(&offsets), nr_frames)
+        }
+        None if nr_fragments == nr_frames => {
+            Some((0..nr_fragments as usize).map(|i| i..i + 1).collect())
+        }
+        None => None,
+    }
+}
+
+/// Retrieve the encoded data of a single frame
+/// in the given encapsulated pixel data,
+/// joined from the fragments which belong to it.
+///
+/// Returns `None` if the frame is out of range
+/// or its fragments cannot be identified.
+pub fn frame_data(src: &dyn PixelDataObject, frame: u32) -> Option<Vec<u8>> {
+    let range = object_frame_fragment_ranges(src)?
+        .into_iter()
+        .nth(frame as usize)?;
+    if range.len() == 1 {
+        return src.fragment(range.start);
+    }
+    let mut data = Vec::new();
+    for index in range {
+        data.extend_from_slice(&src.fragment(index)?);
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_frames_through_offsets() {
+        // frame 0: fragments 0 and 1, frame 1: fragment 2, frame 2: fragments 3 to 5
+        let lengths = [4, 2, 2, 10, 6, 2];
+        let offsets = [0, 8 + 4 + 8 + 2, 8 + 4 + 8 + 2 + 8 + 2];
+        assert_eq!(frame_fragment_range(&lengths, &offsets, 0), Some(0..2));
+        assert_eq!(frame_fragment_range(&lengths, &offsets, 1), Some(2..3));
+        assert_eq!(frame_fragment_range(&lengths, &offsets, 2), Some(3..6));
+        assert_eq!(frame_fragment_range(&lengths, &offsets, 3), None);
+
+        assert_eq!(
+            frame_fragment_ranges(&lengths, Some(&offsets), 3),
+            Some(vec![0..2, 2..3, 3..6])
+        );
+        // offset table does not match the number of frames
+        assert_eq!(frame_fragment_ranges(&lengths, Some(&offsets), 2), None);
+    }
+
+    #[test]
+    fn map_frames_without_offsets() {
+        let lengths = [4, 2, 2];
+        let ranges = frame_fragment_ranges(&lengths, None, 1).unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 0..3);
+        assert_eq!(
+            frame_fragment_ranges(&lengths, None, 3),
+            Some(vec![0..1, 1..2, 2..3])
+        );
+        assert_eq!(frame_fragment_ranges(&lengths, None, 2), None);
+    }
+
+    #[test]
+    fn reject_offsets_between_fragments() {
+        let lengths = [2, 2];
+        assert_eq!(frame_fragment_range(&lengths, &[0, 4], 1), None);
+        assert_eq!(frame_fragment_range(&lengths, &[0, 0], 0), None);
+        assert_eq!(frame_fragment_ranges(&lengths, Some(&[0, 4]), 2), None);
+    }
+}
@@ -1,10 +1,22 @@
 //! Support for JPG image decoding.
+//!
+//! This adapter decodes JPEG Baseline (Process 1)
+//! and the other JPEG processes supported by [`jpeg_decoder`],
+//! with one image per frame.
+//! Sequential DCT-based images with 12-bit samples,
+//! as in JPEG Extended (Process 2 & 4),
+//! are decoded into 16-bit samples by a dedicated decoder.
+//! Lossless images (Process 14),
+//! including the first-order prediction (selection value 1) variant,
+//! are decoded into samples of the declared _Bits Allocated_.
 
-use super::MissingAttributeSnafu;
-use crate::adapters::{DecodeResult, PixelDataObject, PixelRWAdapter};
-use jpeg_decoder::Decoder;
+use super::{jpeg_extended, MissingAttributeSnafu, WriteOutputSnafu};
+use crate::adapters::{
+    decode_frame_in_full, fragments::frame_data, DecodeResult, PixelDataObject, PixelRWAdapter,
+};
+use jpeg_decoder::{CodingProcess, Decoder};
 use snafu::{whatever, OptionExt, ResultExt};
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct JPEGAdapter;
@@ -12,6 +24,13 @@ pub struct JPEGAdapter;
 impl PixelRWAdapter for JPEGAdapter {
     /// Decode DICOM image data with jpeg encoding.
     fn decode(&self, src: &dyn PixelDataObject, dst: &mut Vec<u8>) -> DecodeResult<()> {
+        dst.clear();
+        self.decode_to_writer(src, dst)
+    }
+
+    /// Decode DICOM image data with jpeg encoding,
+    /// one frame at a time.
+    fn decode_to_writer(&self, src: &dyn PixelDataObject, to: &mut dyn Write) -> DecodeResult<()> {
         let cols = src
             .cols()
             .context(MissingAttributeSnafu { name: "Columns" })?;
@@ -32,11 +51,14 @@ impl PixelRWAdapter for JPEGAdapter {
 
         // `stride` it the total number of bytes for each sample plane
         let stride: usize = bytes_per_sample as usize * cols as usize * rows as usize;
-        dst.resize((samples_per_pixel as usize * stride) * nr_frames, 0);
+        let frame_size = samples_per_pixel as usize * stride;
+        if frame_size == 0 {
+            return Ok(());
+        }
 
         // Embedded jpegs can span multiple fragments
         // Hence we collect all fragments into single vector
-        // and then just iterate a cursor for each frame
+        // and then decode each frame in turn
         let fragments: Vec<u8> = src
             .raw_pixel_data()
             .whatever_context("Expected to have raw pixel data available")?
@@ -45,32 +67,172 @@ impl PixelRWAdapter for JPEGAdapter {
             .flatten()
             .collect();
 
-        let fragments_len = fragments.len() as u64;
-        let mut cursor = Cursor::new(fragments);
-        let mut dst_offset = 0;
-
-        loop {
-            let mut decoder = Decoder::new(&mut cursor);
-            let decoded = decoder
-                .decode()
-                .map_err(|e| Box::new(e) as Box<_>)
-                .whatever_context("JPEG decoder failure")?;
-
-            let decoded_len = decoded.len();
-            dst[dst_offset..(dst_offset + decoded_len)].copy_from_slice(&decoded);
-            dst_offset += decoded_len;
-
-            // dicom fields always have to have an even length and fill this space with padding
-            // if uneven we have to move one position further to consume this padding
-            if cursor.position() % 2 > 0 {
-                cursor.set_position(cursor.position() + 1);
-            }
-
-            if cursor.position() >= fragments_len {
-                break;
-            }
+        let mut frame = vec![0; frame_size];
+        let mut position = 0;
+        for i in 0..nr_frames {
+            position += decode_image(
+                &fragments[position..],
+                i,
+                (cols, rows, samples_per_pixel),
+                bytes_per_sample,
+                &mut frame,
+            )?;
+            to.write_all(&frame).context(WriteOutputSnafu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decode a single frame of DICOM image data with jpeg encoding.
+    ///
+    /// Only the fragments of the requested frame are decoded
+    /// if they can be identified,
+    /// which is the case for objects with one fragment per frame
+    /// or with a basic offset table.
+    fn decode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        let data = match frame_data(src, frame) {
+            Some(data) => data,
+            None => return decode_frame_in_full(self, src, frame, dst),
+        };
+
+        let cols = src
+            .cols()
+            .context(MissingAttributeSnafu { name: "Columns" })?;
+        let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+        let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+            name: "SamplesPerPixel",
+        })?;
+        let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+            name: "BitsAllocated",
+        })?;
+
+        if bits_allocated != 8 && bits_allocated != 16 {
+            whatever!("BitsAllocated other than 8 or 16 is not supported");
         }
+        let bytes_per_sample = bits_allocated / 8;
+        let frame_size =
+            bytes_per_sample as usize * cols as usize * rows as usize * samples_per_pixel as usize;
 
+        let start = dst.len();
+        dst.resize(start + frame_size, 0);
+        if frame_size == 0 {
+            return Ok(());
+        }
+        decode_image(
+            &data,
+            frame as usize,
+            (cols, rows, samples_per_pixel),
+            bytes_per_sample,
+            &mut dst[start..],
+        )?;
         Ok(())
     }
 }
+
+/// Decode the first JPEG image in `data` into `frame`,
+/// which must have the exact size of the decoded image.
+///
+/// `dimensions` are the expected columns, rows, and samples per pixel,
+/// and `i` is the frame number, for error reporting.
+/// Returns the position in `data` right after the end of the image.
+fn decode_image(
+    data: &[u8],
+    i: usize,
+    dimensions: (u16, u16, u16),
+    bytes_per_sample: u16,
+    frame: &mut [u8],
+) -> DecodeResult<usize> {
+    let (cols, rows, samples_per_pixel) = dimensions;
+
+    // fragments are padded to an even length,
+    // and some encoders leave more bytes after the end of image,
+    // so look for the start of the next image
+    let start = data.windows(2).position(|w| w == [0xFF, 0xD8]);
+    let start = match start {
+        Some(start) => start,
+        None => whatever!("No JPEG image found for frame #{}", i),
+    };
+
+    let (decoded, end) = if jpeg_extended::is_extended_precision(&data[start..]) {
+        // 12-bit samples, expanded to 16 bits
+        let (image, len) = jpeg_extended::decode(&data[start..])
+            .map_err(|e| Box::new(e) as Box<_>)
+            .whatever_context("JPEG decoder failure")?;
+        if (image.width, image.height, image.components) != (cols, rows, samples_per_pixel as usize)
+        {
+            whatever!(
+                "JPEG frame #{} is {}x{} with {} components, expected {}x{} with {}",
+                i,
+                image.width,
+                image.height,
+                image.components,
+                cols,
+                rows,
+                samples_per_pixel
+            );
+        }
+        let decoded = image
+            .samples
+            .into_iter()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        (decoded, start + len)
+    } else {
+        let mut cursor = Cursor::new(&data[start..]);
+        let mut decoder = Decoder::new(&mut cursor);
+        let decoded = decoder
+            .decode()
+            .map_err(|e| Box::new(e) as Box<_>)
+            .whatever_context("JPEG decoder failure")?;
+        let decoded = match decoder.info() {
+            Some(info) if info.coding_process == CodingProcess::Lossless => lossless_samples(
+                decoded,
+                frame.len() / bytes_per_sample as usize,
+                bytes_per_sample,
+            ),
+            _ => decoded,
+        };
+        (decoded, start + cursor.position() as usize)
+    };
+
+    if decoded.len() != frame.len() {
+        whatever!(
+            "JPEG frame #{} has {} bytes, expected {}",
+            i,
+            decoded.len(),
+            frame.len()
+        );
+    }
+    frame.copy_from_slice(&decoded);
+    Ok(end)
+}
+
+/// Convert the output of the decoder for a lossless JPEG image
+/// into little endian samples of the given size in bytes.
+///
+/// The decoder yields one byte per sample for a precision of 8 bits,
+/// and 16-bit samples in native byte order for any other precision.
+fn lossless_samples(decoded: Vec<u8>, nr_samples: usize, bytes_per_sample: u16) -> Vec<u8> {
+    if decoded.len() == nr_samples && bytes_per_sample == 2 {
+        // 8-bit precision in 16 bits allocated
+        return decoded.into_iter().flat_map(|s| [s, 0]).collect();
+    }
+    if decoded.len() != nr_samples * 2 {
+        // already one byte per sample, or not the expected size
+        return decoded;
+    }
+    let samples = decoded
+        .chunks_exact(2)
+        .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]));
+    if bytes_per_sample == 1 {
+        // precision lower than 8 bits
+        samples.map(|sample| sample as u8).collect()
+    } else {
+        samples.flat_map(u16::to_le_bytes).collect()
+    }
+}
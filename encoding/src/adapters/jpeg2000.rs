@@ -0,0 +1,2052 @@
+//! Support for JPEG 2000 image encoding.
+//!
+//! This adapter encodes images into
+//! JPEG 2000 Image Compression (Lossless Only),
+//! with one codestream per frame.
+//! The encoder is a pure Rust implementation of a subset of ITU-T T.800
+//! (ISO/IEC 15444-1):
+//! the reversible 5-3 wavelet transformation,
+//! a single quality layer,
+//! 64x64 code-blocks
+//! and one precinct per resolution level.
+//! The image can be split into tiles of a configurable size.
+//!
+//! Components are encoded independently,
+//! without a multiple component transformation,
+//! so that the photometric interpretation of the image is preserved.
+//! Decoding is not supported.
+
+use super::encode_error::MissingAttributeSnafu;
+use crate::adapters::{
+    DecodeResult, EncodeError, EncodeOptions, EncodeResult, PixelDataObject, PixelRWAdapter,
+};
+use snafu::{whatever, OptionExt};
+
+/// The largest tile extent which fits a single precinct
+/// of the default precinct size.
+const MAX_PRECINCT_SIZE: u32 = 1 << 15;
+
+/// Exponent of the code-block width and height.
+const CODE_BLOCK_EXPONENT: u32 = 6;
+
+/// Number of contexts of the MQ coder.
+const NUM_CONTEXTS: usize = 19;
+
+/// First sign coding context.
+const SIGN_CONTEXT: usize = 9;
+/// First magnitude refinement context.
+const REFINEMENT_CONTEXT: usize = 14;
+/// Run length context.
+const RUN_LENGTH_CONTEXT: usize = 17;
+/// Uniform context.
+const UNIFORM_CONTEXT: usize = 18;
+
+/// Adapter for JPEG 2000 lossless encoding.
+///
+/// The default adapter encodes each frame as a single tile
+/// (or tiles of 32768 samples in each dimension, for larger images)
+/// with up to 5 wavelet decomposition levels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Jpeg2000Adapter {
+    tile_size: Option<(u32, u32)>,
+    decomposition_levels: u8,
+}
+
+impl Jpeg2000Adapter {
+    /// Create a new adapter with the default coding parameters.
+    pub const fn new() -> Self {
+        Jpeg2000Adapter {
+            tile_size: None,
+            decomposition_levels: 5,
+        }
+    }
+
+    /// Split the image into tiles of the given width and height.
+    ///
+    /// Tiles are anchored at the top left corner of the image.
+    /// A tile must not cross a multiple of 32768 samples
+    /// in either dimension.
+    pub const fn with_tile_size(self, width: u32, height: u32) -> Self {
+        Jpeg2000Adapter {
+            tile_size: Some((width, height)),
+            ..self
+        }
+    }
+
+    /// Set the maximum number of wavelet decomposition levels, up to 32.
+    ///
+    /// Fewer levels are used for tiles which are too small.
+    pub const fn with_decomposition_levels(self, levels: u8) -> Self {
+        Jpeg2000Adapter {
+            decomposition_levels: levels,
+            ..self
+        }
+    }
+}
+
+impl Default for Jpeg2000Adapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encode TS: 1.2.840.10008.1.2.4.90 (JPEG 2000 Lossless Only)
+impl PixelRWAdapter for Jpeg2000Adapter {
+    /// Decoding JPEG 2000 is not supported,
+    /// so this always fails.
+    fn decode(&self, _src: &dyn PixelDataObject, _dst: &mut Vec<u8>) -> DecodeResult<()> {
+        whatever!("JPEG 2000 decoding is not supported")
+    }
+
+    /// Encode the DICOM image into JPEG 2000,
+    /// with the fragments of all frames concatenated.
+    ///
+    /// Since each frame is encoded into its own fragment,
+    /// [`encode_frame`](PixelRWAdapter::encode_frame)
+    /// should be preferred for multi-frame images.
+    fn encode(
+        &self,
+        src: &dyn PixelDataObject,
+        options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        let nr_frames = src.number_of_frames().unwrap_or(1);
+        for frame in 0..nr_frames {
+            self.encode_frame(src, frame.into(), options.clone(), dst)?;
+        }
+        Ok(())
+    }
+
+    /// Encode a single frame of the DICOM image
+    /// into one JPEG 2000 codestream fragment.
+    ///
+    /// The sample precision and signedness of the codestream
+    /// follow _Bits Stored_ and _Pixel Representation_.
+    fn encode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        _options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        let cols = src
+            .cols()
+            .context(MissingAttributeSnafu { name: "Columns" })?;
+        let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+        let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+            name: "SamplesPerPixel",
+        })?;
+        let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+            name: "BitsAllocated",
+        })?;
+        let bits_stored = src.bits_stored().unwrap_or(bits_allocated);
+        let signed = src.pixel_representation().unwrap_or(0) == 1;
+        let planar_configuration = src.planar_configuration().unwrap_or(0);
+
+        if bits_allocated != 8 && bits_allocated != 16 {
+            return Err(EncodeError::CustomEncodeError {
+                message: "BitsAllocated other than 8 or 16 is not supported",
+            });
+        }
+        if bits_stored == 0 || bits_stored > bits_allocated {
+            return Err(EncodeError::CustomEncodeError {
+                message: "BitsStored is not compatible with BitsAllocated",
+            });
+        }
+        if cols == 0 || rows == 0 || samples_per_pixel == 0 {
+            return Err(EncodeError::CustomEncodeError {
+                message: "Image dimensions not supported by JPEG 2000",
+            });
+        }
+
+        // native pixel data is held in a single value, not in fragments
+        if src.fragment(0).is_some() {
+            return Err(EncodeError::NotNative);
+        }
+        let data = src
+            .raw_pixel_data()
+            .and_then(|raw| raw.fragments.into_iter().next())
+            .ok_or(EncodeError::MissingAttribute { name: "PixelData" })?;
+
+        let bytes_per_sample = bits_allocated as usize / 8;
+        let components = samples_per_pixel as usize;
+        let nr_pixels = rows as usize * cols as usize;
+        let frame_size = nr_pixels * components * bytes_per_sample;
+        let frame_start = frame as usize * frame_size;
+        let frame_data = data.get(frame_start..frame_start + frame_size).ok_or(
+            EncodeError::CustomEncodeError {
+                message: "Frame out of bounds",
+            },
+        )?;
+
+        let raw_samples: Vec<u16> = if bytes_per_sample == 1 {
+            frame_data.iter().map(|&b| u16::from(b)).collect()
+        } else {
+            frame_data
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect()
+        };
+        // keep only the stored bits of each sample
+        let shift = 32 - u32::from(bits_stored);
+        let to_sample = |raw: u16| {
+            let value = i32::from(raw) << shift;
+            if signed {
+                value >> shift
+            } else {
+                ((value as u32) >> shift) as i32
+            }
+        };
+        // the codec takes one plane per component
+        let planes: Vec<Vec<i32>> = (0..components)
+            .map(|c| {
+                if planar_configuration == 0 || components == 1 {
+                    raw_samples
+                        .iter()
+                        .skip(c)
+                        .step_by(components)
+                        .map(|&raw| to_sample(raw))
+                        .collect()
+                } else {
+                    raw_samples[c * nr_pixels..(c + 1) * nr_pixels]
+                        .iter()
+                        .map(|&raw| to_sample(raw))
+                        .collect()
+                }
+            })
+            .collect();
+
+        let image = Image {
+            width: u32::from(cols),
+            height: u32::from(rows),
+            precision: bits_stored as u8,
+            signed,
+            planes,
+        };
+        let width = image.width.min(MAX_PRECINCT_SIZE);
+        let height = image.height.min(MAX_PRECINCT_SIZE);
+        let (tile_width, tile_height) = self.tile_size.unwrap_or((width, height));
+        let fragment = encode(
+            &image,
+            tile_width,
+            tile_height,
+            u32::from(self.decomposition_levels),
+        )
+        .map_err(|message| EncodeError::CustomEncodeError { message })?;
+        let odd = fragment.len() & 1 != 0;
+        dst.extend(fragment);
+        // keep fragments at an even length
+        if odd {
+            dst.push(0);
+        }
+        Ok(())
+    }
+}
+
+/// An image to encode, with one plane of samples per component.
+#[derive(Debug)]
+struct Image {
+    width: u32,
+    height: u32,
+    precision: u8,
+    signed: bool,
+    planes: Vec<Vec<i32>>,
+}
+
+/// Subband orientation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Orientation {
+    LL,
+    HL,
+    LH,
+    HH,
+}
+
+impl Orientation {
+    /// The base 2 logarithm of the nominal gain of the subband
+    fn gain(self) -> u32 {
+        match self {
+            Orientation::LL => 0,
+            Orientation::HL | Orientation::LH => 1,
+            Orientation::HH => 2,
+        }
+    }
+}
+
+/// The coefficients of one subband of a tile-component.
+#[derive(Debug)]
+struct Subband {
+    orientation: Orientation,
+    x0: u32,
+    y0: u32,
+    width: u32,
+    height: u32,
+    coefficients: Vec<i32>,
+}
+
+/// A code-block after entropy coding.
+#[derive(Debug)]
+struct CodeBlock {
+    data: Vec<u8>,
+    passes: u32,
+    bitplanes: u32,
+}
+
+/// The code-blocks of one subband,
+/// in raster order.
+#[derive(Debug)]
+struct CodedSubband {
+    orientation: Orientation,
+    blocks_wide: usize,
+    blocks_high: usize,
+    blocks: Vec<CodeBlock>,
+}
+
+/// A resolution level of a tile-component,
+/// `None` if it is empty and therefore has no packet.
+type CodedResolution = Option<Vec<CodedSubband>>;
+
+/// Encode an image into a JPEG 2000 codestream.
+fn encode(
+    image: &Image,
+    tile_width: u32,
+    tile_height: u32,
+    max_levels: u32,
+) -> Result<Vec<u8>, &'static str> {
+    if tile_width == 0 || tile_height == 0 {
+        return Err("Tile size must not be zero");
+    }
+    let tiles_wide = image.width.div_ceil(tile_width);
+    let tiles_high = image.height.div_ceil(tile_height);
+    if tiles_wide * tiles_high > u32::from(u16::MAX) {
+        return Err("Too many tiles");
+    }
+    if image.planes.len() > 16384 {
+        return Err("Too many components");
+    }
+
+    // all resolution levels should be at least one sample wide
+    let smallest = tile_width
+        .min(tile_height)
+        .min(image.width)
+        .min(image.height);
+    let levels = max_levels.min(31 - smallest.leading_zeros()).min(32);
+
+    // transform and entropy code every tile-component
+    let mut tiles = Vec::with_capacity((tiles_wide * tiles_high) as usize);
+    for ty in 0..tiles_high {
+        for tx in 0..tiles_wide {
+            let x0 = tx * tile_width;
+            let y0 = ty * tile_height;
+            let x1 = (x0 + tile_width).min(image.width);
+            let y1 = (y0 + tile_height).min(image.height);
+            if x0 / MAX_PRECINCT_SIZE != (x1 - 1) / MAX_PRECINCT_SIZE
+                || y0 / MAX_PRECINCT_SIZE != (y1 - 1) / MAX_PRECINCT_SIZE
+            {
+                return Err("Tiles must not cross a multiple of 32768 samples");
+            }
+
+            let components: Vec<Vec<CodedResolution>> = image
+                .planes
+                .iter()
+                .map(|plane| {
+                    let mut samples = Vec::with_capacity(((x1 - x0) * (y1 - y0)) as usize);
+                    for y in y0..y1 {
+                        let row = (y * image.width) as usize;
+                        samples.extend_from_slice(&plane[row + x0 as usize..row + x1 as usize]);
+                    }
+                    if !image.signed {
+                        // DC level shift
+                        let offset = 1 << (image.precision - 1);
+                        for sample in &mut samples {
+                            *sample -= offset;
+                        }
+                    }
+                    forward_dwt(samples, (x0, y0, x1, y1), levels)
+                        .into_iter()
+                        .map(|resolution| {
+                            resolution.map(|subbands| subbands.iter().map(code_subband).collect())
+                        })
+                        .collect()
+                })
+                .collect();
+            tiles.push(components);
+        }
+    }
+
+    // enough guard bits for the number of bit-planes of every code-block
+    let precision = u32::from(image.precision);
+    let guard_bits = tiles
+        .iter()
+        .flatten()
+        .flatten()
+        .flatten()
+        .flatten()
+        .flat_map(|subband| {
+            let exponent = precision + subband.orientation.gain();
+            subband
+                .blocks
+                .iter()
+                .map(move |block| (block.bitplanes + 1).saturating_sub(exponent))
+        })
+        .fold(2, u32::max);
+    if guard_bits > 7 {
+        return Err("Sample values out of range for the sample precision");
+    }
+
+    let mut out = Vec::new();
+    write_main_header(&mut out, image, tile_width, tile_height, levels, guard_bits);
+
+    for (index, tile) in tiles.iter().enumerate() {
+        // packets in layer-resolution-component-position order
+        let mut body = Vec::new();
+        for resolution in 0..=levels as usize {
+            for component in tile {
+                if let Some(subbands) = &component[resolution] {
+                    write_packet(&mut body, subbands, precision, guard_bits);
+                }
+            }
+        }
+
+        // SOT
+        out.extend_from_slice(&[0xFF, 0x90, 0, 10]);
+        out.extend_from_slice(&(index as u16).to_be_bytes());
+        out.extend_from_slice(&(body.len() as u32 + 14).to_be_bytes());
+        out.extend_from_slice(&[0, 1]);
+        // SOD
+        out.extend_from_slice(&[0xFF, 0x93]);
+        out.extend(body);
+    }
+
+    // EOC
+    out.extend_from_slice(&[0xFF, 0xD9]);
+    Ok(out)
+}
+
+/// Write the SOC marker and the main header marker segments.
+fn write_main_header(
+    out: &mut Vec<u8>,
+    image: &Image,
+    tile_width: u32,
+    tile_height: u32,
+    levels: u32,
+    guard_bits: u32,
+) {
+    let components = image.planes.len() as u16;
+
+    // SOC
+    out.extend_from_slice(&[0xFF, 0x4F]);
+
+    // SIZ
+    out.extend_from_slice(&[0xFF, 0x51]);
+    out.extend_from_slice(&(38 + 3 * components).to_be_bytes());
+    // capabilities
+    out.extend_from_slice(&[0, 0]);
+    for value in [
+        image.width,
+        image.height,
+        0,
+        0,
+        tile_width,
+        tile_height,
+        0,
+        0,
+    ]
+    .iter()
+    {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out.extend_from_slice(&components.to_be_bytes());
+    let depth = (image.precision - 1) | if image.signed { 0x80 } else { 0 };
+    for _ in 0..components {
+        out.extend_from_slice(&[depth, 1, 1]);
+    }
+
+    // COD
+    out.extend_from_slice(&[0xFF, 0x52, 0, 12]);
+    // default precincts, no SOP or EPH markers
+    out.push(0);
+    // LRCP progression, 1 layer, no multiple component transformation
+    out.extend_from_slice(&[0, 0, 1, 0]);
+    // decomposition levels, code-block size, code-block style
+    let block_size = (CODE_BLOCK_EXPONENT - 2) as u8;
+    out.extend_from_slice(&[levels as u8, block_size, block_size, 0]);
+    // reversible 5-3 wavelet transformation
+    out.push(1);
+
+    // QCD
+    out.extend_from_slice(&[0xFF, 0x5C]);
+    out.extend_from_slice(&(4 + 3 * levels as u16).to_be_bytes());
+    // no quantization
+    out.push((guard_bits << 5) as u8);
+    let precision = u32::from(image.precision);
+    out.push((precision << 3) as u8);
+    for _ in 0..levels {
+        for orientation in [Orientation::HL, Orientation::LH, Orientation::HH].iter() {
+            out.push(((precision + orientation.gain()) << 3) as u8);
+        }
+    }
+}
+
+/// Apply the forward reversible wavelet transformation
+/// to the samples of a tile-component,
+/// given the tile-component's region `(x0, y0, x1, y1)`.
+///
+/// Returns the subbands of each resolution level,
+/// from the lowest resolution.
+fn forward_dwt(
+    samples: Vec<i32>,
+    region: (u32, u32, u32, u32),
+    levels: u32,
+) -> Vec<Option<Vec<Subband>>> {
+    let (mut u0, mut v0, mut u1, mut v1) = region;
+    let mut data = samples;
+    let mut line = Vec::new();
+    let mut buffer = Vec::new();
+    let mut resolutions = Vec::with_capacity(levels as usize + 1);
+
+    for _ in 0..levels {
+        let width = (u1 - u0) as usize;
+        let height = (v1 - v0) as usize;
+        if width > 0 && height > 0 {
+            // vertical, then horizontal filtering
+            for x in 0..width {
+                line.clear();
+                line.extend((0..height).map(|y| data[y * width + x]));
+                forward_1d(&mut line, v0, &mut buffer);
+                for (y, &value) in line.iter().enumerate() {
+                    data[y * width + x] = value;
+                }
+            }
+            for row in data.chunks_exact_mut(width) {
+                forward_1d(row, u0, &mut buffer);
+            }
+        }
+
+        let low_width = (u1.div_ceil(2) - u0.div_ceil(2)) as usize;
+        let low_height = (v1.div_ceil(2) - v0.div_ceil(2)) as usize;
+        let extract = |orientation, x_range: (usize, usize), y_range: (usize, usize)| {
+            let (xa, xb) = x_range;
+            let (ya, yb) = y_range;
+            let mut coefficients = Vec::with_capacity((xb - xa) * (yb - ya));
+            for y in ya..yb {
+                coefficients.extend_from_slice(&data[y * width + xa..y * width + xb]);
+            }
+            let x0 = if xa == 0 { u0.div_ceil(2) } else { u0 / 2 };
+            let y0 = if ya == 0 { v0.div_ceil(2) } else { v0 / 2 };
+            Subband {
+                orientation,
+                x0,
+                y0,
+                width: (xb - xa) as u32,
+                height: (yb - ya) as u32,
+                coefficients,
+            }
+        };
+        let subbands = vec![
+            extract(Orientation::HL, (low_width, width), (0, low_height)),
+            extract(Orientation::LH, (0, low_width), (low_height, height)),
+            extract(Orientation::HH, (low_width, width), (low_height, height)),
+        ];
+        let low = extract(Orientation::LL, (0, low_width), (0, low_height));
+
+        resolutions.push(if width > 0 && height > 0 {
+            Some(subbands)
+        } else {
+            None
+        });
+        data = low.coefficients;
+        u0 = u0.div_ceil(2);
+        v0 = v0.div_ceil(2);
+        u1 = u1.div_ceil(2);
+        v1 = v1.div_ceil(2);
+    }
+
+    resolutions.push(if u1 > u0 && v1 > v0 {
+        Some(vec![Subband {
+            orientation: Orientation::LL,
+            x0: u0,
+            y0: v0,
+            width: u1 - u0,
+            height: v1 - v0,
+            coefficients: data,
+        }])
+    } else {
+        None
+    });
+    resolutions.reverse();
+    resolutions
+}
+
+/// Apply the one-dimensional reversible 5-3 filter
+/// to a line of samples starting at the absolute coordinate `start`,
+/// leaving the low-pass coefficients followed by the high-pass coefficients.
+fn forward_1d(line: &mut [i32], start: u32, buffer: &mut Vec<i32>) {
+    let len = line.len() as i64;
+    if len == 1 {
+        if start & 1 != 0 {
+            line[0] *= 2;
+        }
+        return;
+    }
+
+    let i0 = i64::from(start);
+    let period = 2 * (len - 1);
+    // periodic symmetric extension
+    let x = |i: i64| {
+        let k = (i - i0).rem_euclid(period);
+        line[if k < len { k } else { period - k } as usize]
+    };
+    let high = |i: i64| x(i) - ((x(i - 1) + x(i + 1)) >> 1);
+
+    buffer.clear();
+    buffer.extend(
+        (i0..i0 + len)
+            .filter(|i| i & 1 == 0)
+            .map(|i| x(i) + ((high(i - 1) + high(i + 1) + 2) >> 2)),
+    );
+    buffer.extend((i0..i0 + len).filter(|i| i & 1 != 0).map(high));
+    line.copy_from_slice(buffer);
+}
+
+/// Partition a subband into code-blocks and entropy code each of them.
+fn code_subband(subband: &Subband) -> CodedSubband {
+    let size = 1 << CODE_BLOCK_EXPONENT;
+    let grid = |start: u32, len: u32| {
+        if len == 0 {
+            return Vec::new();
+        }
+        let end = start + len;
+        let first = start / size * size;
+        (first..end)
+            .step_by(size as usize)
+            .map(|b| (b.max(start), (b + size).min(end)))
+            .collect::<Vec<_>>()
+    };
+    let columns = grid(subband.x0, subband.width);
+    let rows = grid(subband.y0, subband.height);
+
+    let mut blocks = Vec::with_capacity(columns.len() * rows.len());
+    for &(by0, by1) in &rows {
+        for &(bx0, bx1) in &columns {
+            let width = (bx1 - bx0) as usize;
+            let height = (by1 - by0) as usize;
+            let mut coefficients = Vec::with_capacity(width * height);
+            for y in by0..by1 {
+                let row = ((y - subband.y0) * subband.width) as usize;
+                let start = row + (bx0 - subband.x0) as usize;
+                coefficients.extend_from_slice(&subband.coefficients[start..start + width]);
+            }
+            blocks.push(encode_code_block(
+                &coefficients,
+                width,
+                height,
+                subband.orientation,
+            ));
+        }
+    }
+
+    CodedSubband {
+        orientation: subband.orientation,
+        blocks_wide: columns.len(),
+        blocks_high: rows.len(),
+        blocks,
+    }
+}
+
+/// Probability estimation table of the MQ coder (T.800 Table C.2):
+/// Qe, next index after MPS, next index after LPS, MPS switch.
+const MQ_TABLE: [(u32, u8, u8, bool); 47] = [
+    (0x5601, 1, 1, true),
+    (0x3401, 2, 6, false),
+    (0x1801, 3, 9, false),
+    (0x0AC1, 4, 12, false),
+    (0x0521, 5, 29, false),
+    (0x0221, 38, 33, false),
+    (0x5601, 7, 6, true),
+    (0x5401, 8, 14, false),
+    (0x4801, 9, 14, false),
+    (0x3801, 10, 14, false),
+    (0x3001, 11, 17, false),
+    (0x2401, 12, 18, false),
+    (0x1C01, 13, 20, false),
+    (0x1601, 29, 21, false),
+    (0x5601, 15, 14, true),
+    (0x5401, 16, 14, false),
+    (0x5101, 17, 15, false),
+    (0x4801, 18, 16, false),
+    (0x3801, 19, 17, false),
+    (0x3401, 20, 18, false),
+    (0x3001, 21, 19, false),
+    (0x2801, 22, 19, false),
+    (0x2401, 23, 20, false),
+    (0x2201, 24, 21, false),
+    (0x1C01, 25, 22, false),
+    (0x1801, 26, 23, false),
+    (0x1601, 27, 24, false),
+    (0x1401, 28, 25, false),
+    (0x1201, 29, 26, false),
+    (0x1101, 30, 27, false),
+    (0x0AC1, 31, 28, false),
+    (0x09C1, 32, 29, false),
+    (0x08A1, 33, 30, false),
+    (0x0521, 34, 31, false),
+    (0x0441, 35, 32, false),
+    (0x02A1, 36, 33, false),
+    (0x0221, 37, 34, false),
+    (0x0141, 38, 35, false),
+    (0x0111, 39, 36, false),
+    (0x0085, 40, 37, false),
+    (0x0049, 41, 38, false),
+    (0x0025, 42, 39, false),
+    (0x0015, 43, 40, false),
+    (0x0009, 44, 41, false),
+    (0x0005, 45, 42, false),
+    (0x0001, 45, 43, false),
+    (0x5601, 46, 46, false),
+];
+
+/// The initial state of each context (T.800 Table D.7):
+/// probability estimation index and most probable symbol.
+fn initial_contexts() -> [(u8, u8); NUM_CONTEXTS] {
+    let mut contexts = [(0, 0); NUM_CONTEXTS];
+    contexts[0] = (4, 0);
+    contexts[RUN_LENGTH_CONTEXT] = (3, 0);
+    contexts[UNIFORM_CONTEXT] = (46, 0);
+    contexts
+}
+
+/// The MQ arithmetic encoder (T.800 Annex C.2).
+#[derive(Debug)]
+struct MqEncoder {
+    a: u32,
+    c: u32,
+    ct: u32,
+    /// the output bytes,
+    /// the last one being the byte buffer `B`
+    /// and the first one a placeholder preceding the codeword
+    out: Vec<u8>,
+    contexts: [(u8, u8); NUM_CONTEXTS],
+}
+
+impl MqEncoder {
+    fn new() -> Self {
+        MqEncoder {
+            a: 0x8000,
+            c: 0,
+            ct: 12,
+            out: vec![0],
+            contexts: initial_contexts(),
+        }
+    }
+
+    fn encode(&mut self, cx: usize, bit: u32) {
+        let (index, mps) = self.contexts[cx];
+        let (qe, next_mps, next_lps, switch) = MQ_TABLE[index as usize];
+        self.a -= qe;
+        if bit == u32::from(mps) {
+            if self.a & 0x8000 == 0 {
+                if self.a < qe {
+                    self.a = qe;
+                } else {
+                    self.c += qe;
+                }
+                self.contexts[cx].0 = next_mps;
+                self.renormalize();
+            } else {
+                self.c += qe;
+            }
+        } else {
+            if self.a < qe {
+                self.c += qe;
+            } else {
+                self.a = qe;
+            }
+            if switch {
+                self.contexts[cx].1 = 1 - mps;
+            }
+            self.contexts[cx].0 = next_lps;
+            self.renormalize();
+        }
+    }
+
+    fn renormalize(&mut self) {
+        loop {
+            self.a <<= 1;
+            self.c <<= 1;
+            self.ct -= 1;
+            if self.ct == 0 {
+                self.byte_out();
+            }
+            if self.a & 0x8000 != 0 {
+                break;
+            }
+        }
+    }
+
+    fn byte_out(&mut self) {
+        let last = self.out.len() - 1;
+        if self.out[last] == 0xFF {
+            self.out.push((self.c >> 20) as u8);
+            self.c &= 0xF_FFFF;
+            self.ct = 7;
+        } else if self.c < 0x800_0000 {
+            self.out.push((self.c >> 19) as u8);
+            self.c &= 0x7_FFFF;
+            self.ct = 8;
+        } else {
+            // propagate the carry
+            self.out[last] += 1;
+            if self.out[last] == 0xFF {
+                self.c &= 0x7FF_FFFF;
+                self.out.push((self.c >> 20) as u8);
+                self.c &= 0xF_FFFF;
+                self.ct = 7;
+            } else {
+                self.out.push((self.c >> 19) as u8);
+                self.c &= 0x7_FFFF;
+                self.ct = 8;
+            }
+        }
+    }
+
+    /// Terminate the codeword and retrieve its bytes.
+    fn flush(mut self) -> Vec<u8> {
+        let limit = self.c + self.a;
+        self.c |= 0xFFFF;
+        if self.c >= limit {
+            self.c -= 0x8000;
+        }
+        self.c <<= self.ct;
+        self.byte_out();
+        self.c <<= self.ct;
+        self.byte_out();
+        if self.out.last() == Some(&0xFF) {
+            self.out.pop();
+        }
+        self.out.remove(0);
+        self.out
+    }
+}
+
+/// Zero coding context of a coefficient
+/// given the number of significant horizontal, vertical and diagonal neighbours
+/// (T.800 Table D.1).
+fn zero_coding_context(orientation: Orientation, h: u32, v: u32, d: u32) -> usize {
+    let (h, v) = match orientation {
+        Orientation::HL => (v, h),
+        _ => (h, v),
+    };
+    if orientation == Orientation::HH {
+        return match (d, h + v) {
+            (d, _) if d >= 3 => 8,
+            (2, hv) if hv >= 1 => 7,
+            (2, _) => 6,
+            (1, hv) if hv >= 2 => 5,
+            (1, 1) => 4,
+            (1, _) => 3,
+            (_, hv) if hv >= 2 => 2,
+            (_, 1) => 1,
+            _ => 0,
+        };
+    }
+    match (h, v, d) {
+        (2, _, _) => 8,
+        (1, v, _) if v >= 1 => 7,
+        (1, 0, d) if d >= 1 => 6,
+        (1, _, _) => 5,
+        (0, 2, _) => 4,
+        (0, 1, _) => 3,
+        (0, 0, d) if d >= 2 => 2,
+        (0, 0, 1) => 1,
+        _ => 0,
+    }
+}
+
+/// Sign coding context and XOR bit of a coefficient
+/// given the horizontal and vertical sign contributions
+/// (T.800 Table D.3).
+fn sign_context(h: i32, v: i32) -> (usize, u32) {
+    let (offset, xor) = match (h, v) {
+        (1, 1) => (4, 0),
+        (1, 0) => (3, 0),
+        (1, _) => (2, 0),
+        (0, 1) => (1, 0),
+        (0, 0) => (0, 0),
+        (0, _) => (1, 1),
+        (_, 1) => (2, 1),
+        (_, 0) => (3, 1),
+        _ => (4, 1),
+    };
+    (SIGN_CONTEXT + offset, xor)
+}
+
+/// Significance state of the coefficients of a code-block,
+/// with a border of insignificant coefficients around it.
+#[derive(Debug)]
+struct BlockState {
+    stride: usize,
+    significant: Vec<bool>,
+    negative: Vec<bool>,
+    visited: Vec<bool>,
+    refined: Vec<bool>,
+}
+
+impl BlockState {
+    fn new(width: usize, height: usize) -> Self {
+        let stride = width + 2;
+        let len = stride * (height + 2);
+        BlockState {
+            stride,
+            significant: vec![false; len],
+            negative: vec![false; len],
+            visited: vec![false; len],
+            refined: vec![false; len],
+        }
+    }
+
+    /// The state index of the coefficient at the given position.
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y + 1) * self.stride + x + 1
+    }
+
+    /// Count the significant horizontal, vertical and diagonal neighbours.
+    fn neighbours(&self, i: usize) -> (u32, u32, u32) {
+        let s = |i: usize| u32::from(self.significant[i]);
+        let stride = self.stride;
+        (
+            s(i - 1) + s(i + 1),
+            s(i - stride) + s(i + stride),
+            s(i - stride - 1) + s(i - stride + 1) + s(i + stride - 1) + s(i + stride + 1),
+        )
+    }
+
+    fn zero_coding_context(&self, i: usize, orientation: Orientation) -> usize {
+        let (h, v, d) = self.neighbours(i);
+        zero_coding_context(orientation, h, v, d)
+    }
+
+    fn sign_context(&self, i: usize) -> (usize, u32) {
+        let contribution = |i: usize| match (self.significant[i], self.negative[i]) {
+            (false, _) => 0,
+            (true, false) => 1,
+            (true, true) => -1,
+        };
+        let stride = self.stride;
+        let h = (contribution(i - 1) + contribution(i + 1)).clamp(-1, 1);
+        let v = (contribution(i - stride) + contribution(i + stride)).clamp(-1, 1);
+        sign_context(h, v)
+    }
+
+    fn refinement_context(&self, i: usize) -> usize {
+        if self.refined[i] {
+            REFINEMENT_CONTEXT + 2
+        } else {
+            let (h, v, d) = self.neighbours(i);
+            REFINEMENT_CONTEXT + usize::from(h + v + d > 0)
+        }
+    }
+}
+
+/// Entropy code the coefficients of a code-block
+/// with all coding passes in a single terminated codeword.
+fn encode_code_block(
+    coefficients: &[i32],
+    width: usize,
+    height: usize,
+    orientation: Orientation,
+) -> CodeBlock {
+    let max = coefficients
+        .iter()
+        .map(|c| c.unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    let bitplanes = 32 - max.leading_zeros();
+    if bitplanes == 0 {
+        return CodeBlock {
+            data: Vec::new(),
+            passes: 0,
+            bitplanes: 0,
+        };
+    }
+
+    let mut state = BlockState::new(width, height);
+    let mut magnitudes = vec![0; state.significant.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let i = state.index(x, y);
+            let c = coefficients[y * width + x];
+            magnitudes[i] = c.unsigned_abs();
+            state.negative[i] = c < 0;
+        }
+    }
+
+    let mut mq = MqEncoder::new();
+    for plane in (0..bitplanes).rev() {
+        let bit = |i: usize| (magnitudes[i] >> plane) & 1;
+        if plane != bitplanes - 1 {
+            // significance propagation pass
+            for_each_in_stripes(width, height, |x, y| {
+                let i = state.index(x, y);
+                if state.significant[i] {
+                    return;
+                }
+                let cx = state.zero_coding_context(i, orientation);
+                if cx == 0 {
+                    return;
+                }
+                mq.encode(cx, bit(i));
+                state.visited[i] = true;
+                if bit(i) == 1 {
+                    encode_sign(&mut mq, &mut state, i);
+                }
+            });
+
+            // magnitude refinement pass
+            for_each_in_stripes(width, height, |x, y| {
+                let i = state.index(x, y);
+                if state.significant[i] && !state.visited[i] {
+                    mq.encode(state.refinement_context(i), bit(i));
+                    state.refined[i] = true;
+                }
+            });
+        }
+
+        // cleanup pass
+        for y0 in (0..height).step_by(4) {
+            for x in 0..width {
+                let mut y = y0;
+                let stripe_end = (y0 + 4).min(height);
+                if stripe_end - y0 == 4
+                    && (y0..stripe_end).all(|y| {
+                        let i = state.index(x, y);
+                        !state.significant[i]
+                            && !state.visited[i]
+                            && state.zero_coding_context(i, orientation) == 0
+                    })
+                {
+                    // run length mode
+                    let first = (y0..stripe_end).position(|y| bit(state.index(x, y)) == 1);
+                    match first {
+                        None => {
+                            mq.encode(RUN_LENGTH_CONTEXT, 0);
+                            continue;
+                        }
+                        Some(k) => {
+                            mq.encode(RUN_LENGTH_CONTEXT, 1);
+                            mq.encode(UNIFORM_CONTEXT, (k as u32 >> 1) & 1);
+                            mq.encode(UNIFORM_CONTEXT, k as u32 & 1);
+                            y = y0 + k;
+                            let i = state.index(x, y);
+                            encode_sign(&mut mq, &mut state, i);
+                            y += 1;
+                        }
+                    }
+                }
+                for y in y..stripe_end {
+                    let i = state.index(x, y);
+                    if state.significant[i] || state.visited[i] {
+                        continue;
+                    }
+                    mq.encode(state.zero_coding_context(i, orientation), bit(i));
+                    if bit(i) == 1 {
+                        encode_sign(&mut mq, &mut state, i);
+                    }
+                }
+            }
+        }
+        for visited in &mut state.visited {
+            *visited = false;
+        }
+    }
+
+    CodeBlock {
+        data: mq.flush(),
+        passes: 3 * bitplanes - 2,
+        bitplanes,
+    }
+}
+
+/// Encode the sign of a coefficient which just became significant.
+fn encode_sign(mq: &mut MqEncoder, state: &mut BlockState, i: usize) {
+    let (cx, xor) = state.sign_context(i);
+    mq.encode(cx, u32::from(state.negative[i]) ^ xor);
+    state.significant[i] = true;
+}
+
+/// Visit the positions of a code-block in the scan order of the coding passes:
+/// stripes of 4 rows from the top,
+/// each scanned column by column.
+fn for_each_in_stripes(width: usize, height: usize, mut f: impl FnMut(usize, usize)) {
+    for y0 in (0..height).step_by(4) {
+        for x in 0..width {
+            for y in y0..(y0 + 4).min(height) {
+                f(x, y);
+            }
+        }
+    }
+}
+
+/// A node of a tag tree being encoded.
+#[derive(Debug, Copy, Clone)]
+struct TagTreeNode {
+    value: u32,
+    /// the lower bound of the value known to the decoder
+    low: u32,
+    /// whether the value is known to the decoder
+    known: bool,
+}
+
+/// A tag tree (T.800 B.10.2) being encoded.
+#[derive(Debug)]
+struct TagTree {
+    /// the width and nodes of each level, from the leaves to the root
+    levels: Vec<(usize, Vec<TagTreeNode>)>,
+}
+
+impl TagTree {
+    fn new(width: usize, height: usize, values: impl IntoIterator<Item = u32>) -> Self {
+        let node = |value| TagTreeNode {
+            value,
+            low: 0,
+            known: false,
+        };
+        let leaves: Vec<_> = values.into_iter().map(node).collect();
+        let mut levels = vec![(width, leaves)];
+        let (mut w, mut h) = (width, height);
+        while w > 1 || h > 1 {
+            let (parent_w, parent_h) = (w.div_ceil(2), h.div_ceil(2));
+            let children = &levels[levels.len() - 1].1;
+            let mut parents = vec![node(u32::MAX); parent_w * parent_h];
+            for y in 0..h {
+                for x in 0..w {
+                    let parent = &mut parents[(y / 2) * parent_w + x / 2];
+                    parent.value = parent.value.min(children[y * w + x].value);
+                }
+            }
+            levels.push((parent_w, parents));
+            w = parent_w;
+            h = parent_h;
+        }
+        TagTree { levels }
+    }
+
+    /// Encode the information needed to know
+    /// whether the value of the given leaf is lower than `threshold`,
+    /// or the value itself if it is.
+    fn encode(&mut self, writer: &mut HeaderWriter, x: usize, y: usize, threshold: u32) {
+        let mut low = 0;
+        for level in (0..self.levels.len()).rev() {
+            let (width, nodes) = &mut self.levels[level];
+            let node = &mut nodes[(y >> level) * *width + (x >> level)];
+            if low > node.low {
+                node.low = low;
+            } else {
+                low = node.low;
+            }
+            while low < threshold {
+                if low >= node.value {
+                    if !node.known {
+                        writer.write_bit(1);
+                        node.known = true;
+                    }
+                    break;
+                }
+                writer.write_bit(0);
+                low += 1;
+            }
+            node.low = low;
+        }
+    }
+}
+
+/// A packet header bit writer with bit stuffing (T.800 B.10.1).
+#[derive(Debug)]
+struct HeaderWriter {
+    out: Vec<u8>,
+    byte: u32,
+    bits: u32,
+    capacity: u32,
+}
+
+impl HeaderWriter {
+    fn new() -> Self {
+        HeaderWriter {
+            out: Vec::new(),
+            byte: 0,
+            bits: 0,
+            capacity: 8,
+        }
+    }
+
+    fn write_bit(&mut self, bit: u32) {
+        self.byte = (self.byte << 1) | bit;
+        self.bits += 1;
+        if self.bits == self.capacity {
+            self.out.push(self.byte as u8);
+            // after 0xFF, the next byte has only 7 bits
+            self.capacity = if self.byte == 0xFF { 7 } else { 8 };
+            self.byte = 0;
+            self.bits = 0;
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u32) {
+        for i in (0..count).rev() {
+            self.write_bit((value >> i) & 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        while self.bits > 0 {
+            self.write_bit(0);
+        }
+        if self.out.last() == Some(&0xFF) {
+            self.out.push(0);
+        }
+        self.out
+    }
+}
+
+/// Write the packet of one resolution level of a tile-component,
+/// with all code-blocks in the only quality layer.
+fn write_packet(out: &mut Vec<u8>, subbands: &[CodedSubband], precision: u32, guard_bits: u32) {
+    let mut header = HeaderWriter::new();
+    let empty = subbands
+        .iter()
+        .all(|subband| subband.blocks.iter().all(|block| block.passes == 0));
+    if empty {
+        header.write_bit(0);
+        out.extend(header.finish());
+        return;
+    }
+    header.write_bit(1);
+
+    for subband in subbands {
+        let max_bitplanes = guard_bits + precision + subband.orientation.gain() - 1;
+        let mut inclusion = TagTree::new(
+            subband.blocks_wide,
+            subband.blocks_high,
+            subband.blocks.iter().map(|b| u32::from(b.passes == 0)),
+        );
+        let mut zero_bitplanes = TagTree::new(
+            subband.blocks_wide,
+            subband.blocks_high,
+            subband.blocks.iter().map(|b| max_bitplanes - b.bitplanes),
+        );
+
+        for (i, block) in subband.blocks.iter().enumerate() {
+            let x = i % subband.blocks_wide;
+            let y = i / subband.blocks_wide;
+            inclusion.encode(&mut header, x, y, 1);
+            if block.passes == 0 {
+                continue;
+            }
+            zero_bitplanes.encode(&mut header, x, y, u32::MAX);
+
+            // number of coding passes (T.800 Table B.4)
+            match block.passes {
+                1 => header.write_bits(0, 1),
+                2 => header.write_bits(0b10, 2),
+                n @ 3..=5 => header.write_bits((0b11 << 2) | (n - 3), 4),
+                n @ 6..=36 => header.write_bits((0b1111 << 5) | (n - 6), 9),
+                n => header.write_bits((0b1_1111_1111 << 7) | (n - 37), 16),
+            }
+
+            // length of the codeword segment (T.800 B.10.7.1)
+            let lblock = 3;
+            let pass_bits = 31 - block.passes.leading_zeros();
+            let len = block.data.len() as u32;
+            let len_bits = 32 - len.leading_zeros();
+            let increment = len_bits.saturating_sub(lblock + pass_bits);
+            for _ in 0..increment {
+                header.write_bit(1);
+            }
+            header.write_bit(0);
+            header.write_bits(len, lblock + increment + pass_bits);
+        }
+    }
+
+    out.extend(header.finish());
+    for subband in subbands {
+        for block in &subband.blocks {
+            out.extend_from_slice(&block.data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::testing::TestPixelData;
+
+    /// The MQ arithmetic decoder (T.800 Annex C.3).
+    struct MqDecoder<'a> {
+        data: &'a [u8],
+        position: usize,
+        a: u32,
+        c: u32,
+        ct: u32,
+        contexts: [(u8, u8); NUM_CONTEXTS],
+    }
+
+    impl<'a> MqDecoder<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            let mut mq = MqDecoder {
+                data,
+                position: 0,
+                a: 0x8000,
+                c: u32::from(data.first().copied().unwrap_or(0xFF)) << 16,
+                ct: 0,
+                contexts: initial_contexts(),
+            };
+            mq.byte_in();
+            mq.c <<= 7;
+            mq.ct -= 7;
+            mq
+        }
+
+        fn byte(&self, position: usize) -> u8 {
+            self.data.get(position).copied().unwrap_or(0xFF)
+        }
+
+        fn byte_in(&mut self) {
+            if self.byte(self.position) == 0xFF {
+                if self.byte(self.position + 1) > 0x8F {
+                    self.c += 0xFF00;
+                    self.ct = 8;
+                } else {
+                    self.position += 1;
+                    self.c += u32::from(self.byte(self.position)) << 9;
+                    self.ct = 7;
+                }
+            } else {
+                self.position += 1;
+                self.c += u32::from(self.byte(self.position)) << 8;
+                self.ct = 8;
+            }
+        }
+
+        fn decode(&mut self, cx: usize) -> u32 {
+            let (index, mps) = self.contexts[cx];
+            let (qe, next_mps, next_lps, switch) = MQ_TABLE[index as usize];
+            let lps = |contexts: &mut [(u8, u8); NUM_CONTEXTS]| {
+                if switch {
+                    contexts[cx].1 = 1 - mps;
+                }
+                contexts[cx].0 = next_lps;
+                u32::from(1 - mps)
+            };
+            self.a -= qe;
+            let bit = if (self.c >> 16) < qe {
+                let bit = if self.a < qe {
+                    self.contexts[cx].0 = next_mps;
+                    u32::from(mps)
+                } else {
+                    lps(&mut self.contexts)
+                };
+                self.a = qe;
+                bit
+            } else {
+                self.c -= qe << 16;
+                if self.a & 0x8000 != 0 {
+                    return u32::from(mps);
+                }
+                if self.a < qe {
+                    lps(&mut self.contexts)
+                } else {
+                    self.contexts[cx].0 = next_mps;
+                    u32::from(mps)
+                }
+            };
+            loop {
+                if self.ct == 0 {
+                    self.byte_in();
+                }
+                self.a <<= 1;
+                self.c <<= 1;
+                self.ct -= 1;
+                if self.a & 0x8000 != 0 {
+                    break;
+                }
+            }
+            bit
+        }
+    }
+
+    /// Decode a code-block coded with the given number of passes.
+    fn decode_code_block(
+        data: &[u8],
+        width: usize,
+        height: usize,
+        orientation: Orientation,
+        bitplanes: u32,
+        passes: u32,
+    ) -> Vec<i32> {
+        let mut state = BlockState::new(width, height);
+        let mut magnitudes = vec![0_u32; state.significant.len()];
+        let mut mq = MqDecoder::new(data);
+        let mut passes_left = passes;
+
+        let decode_sign = |mq: &mut MqDecoder, state: &mut BlockState, i: usize| {
+            let (cx, xor) = state.sign_context(i);
+            state.negative[i] = (mq.decode(cx) ^ xor) == 1;
+            state.significant[i] = true;
+        };
+
+        for plane in (0..bitplanes).rev() {
+            if plane != bitplanes - 1 {
+                for_each_in_stripes(width, height, |x, y| {
+                    let i = state.index(x, y);
+                    if state.significant[i] {
+                        return;
+                    }
+                    let cx = state.zero_coding_context(i, orientation);
+                    if cx == 0 {
+                        return;
+                    }
+                    state.visited[i] = true;
+                    if mq.decode(cx) == 1 {
+                        magnitudes[i] |= 1 << plane;
+                        decode_sign(&mut mq, &mut state, i);
+                    }
+                });
+                for_each_in_stripes(width, height, |x, y| {
+                    let i = state.index(x, y);
+                    if state.significant[i] && !state.visited[i] {
+                        magnitudes[i] |= mq.decode(state.refinement_context(i)) << plane;
+                        state.refined[i] = true;
+                    }
+                });
+                passes_left -= 2;
+            }
+
+            for y0 in (0..height).step_by(4) {
+                for x in 0..width {
+                    let mut y = y0;
+                    let stripe_end = (y0 + 4).min(height);
+                    if stripe_end - y0 == 4
+                        && (y0..stripe_end).all(|y| {
+                            let i = state.index(x, y);
+                            !state.significant[i]
+                                && !state.visited[i]
+                                && state.zero_coding_context(i, orientation) == 0
+                        })
+                    {
+                        if mq.decode(RUN_LENGTH_CONTEXT) == 0 {
+                            continue;
+                        }
+                        let k = (mq.decode(UNIFORM_CONTEXT) << 1) | mq.decode(UNIFORM_CONTEXT);
+                        y = y0 + k as usize;
+                        let i = state.index(x, y);
+                        magnitudes[i] |= 1 << plane;
+                        decode_sign(&mut mq, &mut state, i);
+                        y += 1;
+                    }
+                    for y in y..stripe_end {
+                        let i = state.index(x, y);
+                        if state.significant[i] || state.visited[i] {
+                            continue;
+                        }
+                        if mq.decode(state.zero_coding_context(i, orientation)) == 1 {
+                            magnitudes[i] |= 1 << plane;
+                            decode_sign(&mut mq, &mut state, i);
+                        }
+                    }
+                }
+            }
+            for visited in &mut state.visited {
+                *visited = false;
+            }
+            passes_left -= 1;
+        }
+        assert_eq!(passes_left, 0);
+
+        let mut coefficients = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let i = state.index(x, y);
+                let m = magnitudes[i] as i32;
+                coefficients.push(if state.negative[i] { -m } else { m });
+            }
+        }
+        coefficients
+    }
+
+    /// A packet header bit reader, undoing bit stuffing.
+    struct HeaderReader<'a> {
+        data: &'a [u8],
+        position: usize,
+        byte: u8,
+        bits: u32,
+    }
+
+    impl<'a> HeaderReader<'a> {
+        fn read_bit(&mut self) -> u32 {
+            if self.bits == 0 {
+                let stuffed = self.position > 0 && self.data[self.position - 1] == 0xFF;
+                self.byte = self.data[self.position];
+                self.position += 1;
+                self.bits = if stuffed { 7 } else { 8 };
+            }
+            self.bits -= 1;
+            u32::from(self.byte >> self.bits) & 1
+        }
+
+        fn read_bits(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |value, _| (value << 1) | self.read_bit())
+        }
+
+        /// Skip to the end of the header.
+        fn finish(self) -> usize {
+            if self.data[self.position - 1] == 0xFF {
+                self.position + 1
+            } else {
+                self.position
+            }
+        }
+    }
+
+    /// A tag tree being decoded,
+    /// as (value or `u32::MAX` if unknown, lower bound) of each node.
+    struct TagTreeDecoder {
+        levels: Vec<(usize, Vec<(u32, u32)>)>,
+    }
+
+    impl TagTreeDecoder {
+        fn new(width: usize, height: usize) -> Self {
+            let mut levels = vec![(width, vec![(u32::MAX, 0); width * height])];
+            let (mut w, mut h) = (width, height);
+            while w > 1 || h > 1 {
+                w = w.div_ceil(2);
+                h = h.div_ceil(2);
+                levels.push((w, vec![(u32::MAX, 0); w * h]));
+            }
+            TagTreeDecoder { levels }
+        }
+
+        /// Whether the value of the leaf is lower than the threshold.
+        fn decode(
+            &mut self,
+            reader: &mut HeaderReader,
+            x: usize,
+            y: usize,
+            threshold: u32,
+        ) -> bool {
+            let mut low = 0;
+            let mut value = u32::MAX;
+            for level in (0..self.levels.len()).rev() {
+                let (width, nodes) = &mut self.levels[level];
+                let node = &mut nodes[(y >> level) * *width + (x >> level)];
+                if low > node.1 {
+                    node.1 = low;
+                } else {
+                    low = node.1;
+                }
+                while low < threshold && low < node.0 {
+                    if reader.read_bit() == 1 {
+                        node.0 = low;
+                    } else {
+                        low += 1;
+                    }
+                }
+                node.1 = low;
+                value = node.0;
+            }
+            value < threshold
+        }
+
+        fn value(&self, x: usize, y: usize) -> u32 {
+            self.levels[0].1[y * self.levels[0].0 + x].0
+        }
+    }
+
+    /// Apply the one-dimensional inverse reversible 5-3 filter
+    /// to a line of low-pass coefficients followed by high-pass coefficients.
+    fn inverse_1d(line: &mut [i32], start: u32) {
+        let len = line.len() as i64;
+        if len == 1 {
+            if start & 1 != 0 {
+                line[0] /= 2;
+            }
+            return;
+        }
+        let i0 = i64::from(start);
+        let lows = (i0..i0 + len).filter(|i| i & 1 == 0).count();
+        let mut interleaved = vec![0; len as usize];
+        let (mut l, mut h) = (0, lows);
+        for (k, i) in (i0..i0 + len).enumerate() {
+            if i & 1 == 0 {
+                interleaved[k] = line[l];
+                l += 1;
+            } else {
+                interleaved[k] = line[h];
+                h += 1;
+            }
+        }
+        let period = 2 * (len - 1);
+        let at = |i: i64| {
+            let k = (i - i0).rem_euclid(period);
+            (if k < len { k } else { period - k }) as usize
+        };
+        let y = interleaved.clone();
+        for i in (i0..i0 + len).filter(|i| i & 1 == 0) {
+            interleaved[at(i)] = y[at(i)] - ((y[at(i - 1)] + y[at(i + 1)] + 2) >> 2);
+        }
+        for i in (i0..i0 + len).filter(|i| i & 1 != 0) {
+            interleaved[at(i)] =
+                y[at(i)] + ((interleaved[at(i - 1)] + interleaved[at(i + 1)]) >> 1);
+        }
+        line.copy_from_slice(&interleaved);
+    }
+
+    fn read_u16(data: &[u8], at: usize) -> u16 {
+        u16::from_be_bytes([data[at], data[at + 1]])
+    }
+
+    fn read_u32(data: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+    }
+
+    /// Decode a codestream produced by this encoder
+    /// into one plane of samples per component.
+    fn decode(data: &[u8]) -> (u32, u32, Vec<Vec<i32>>) {
+        assert_eq!(&data[..4], &[0xFF, 0x4F, 0xFF, 0x51]);
+        let width = read_u32(data, 8);
+        let height = read_u32(data, 12);
+        let tile_width = read_u32(data, 24);
+        let tile_height = read_u32(data, 28);
+        let components = read_u16(data, 40) as usize;
+        let depth = data[42];
+        let signed = depth & 0x80 != 0;
+        let precision = u32::from(depth & 0x7F) + 1;
+        let mut position = 42 + 3 * components;
+
+        assert_eq!(&data[position..position + 2], &[0xFF, 0x52]);
+        let levels = u32::from(data[position + 9]);
+        position += 2 + read_u16(data, position + 2) as usize;
+        assert_eq!(&data[position..position + 2], &[0xFF, 0x5C]);
+        let guard_bits = u32::from(data[position + 4] >> 5);
+        let exponents: Vec<u32> = (0..=3 * levels as usize)
+            .map(|i| u32::from(data[position + 5 + i] >> 3))
+            .collect();
+        position += 2 + read_u16(data, position + 2) as usize;
+
+        let tiles_wide = width.div_ceil(tile_width);
+        let mut planes = vec![vec![0; (width * height) as usize]; components];
+        while data[position..position + 2] == [0xFF, 0x90] {
+            let index = u32::from(read_u16(data, position + 4));
+            let end = position + read_u32(data, position + 6) as usize;
+            assert_eq!(&data[position + 12..position + 14], &[0xFF, 0x93]);
+            position += 14;
+
+            let x0 = index % tiles_wide * tile_width;
+            let y0 = index / tiles_wide * tile_height;
+            let x1 = (x0 + tile_width).min(width);
+            let y1 = (y0 + tile_height).min(height);
+            let region = (x0, y0, x1, y1);
+
+            // subband geometry, to be filled with decoded coefficients
+            let zeros = vec![0; ((x1 - x0) * (y1 - y0)) as usize];
+            let mut tile: Vec<Vec<Option<Vec<Subband>>>> = (0..components)
+                .map(|_| forward_dwt(zeros.clone(), region, levels))
+                .collect();
+
+            for resolution in 0..=levels as usize {
+                for component in tile.iter_mut() {
+                    let subbands = match &mut component[resolution] {
+                        Some(subbands) => subbands,
+                        None => continue,
+                    };
+                    let mut reader = HeaderReader {
+                        data: &data[position..end],
+                        position: 0,
+                        byte: 0,
+                        bits: 0,
+                    };
+                    let mut blocks = Vec::new();
+                    if reader.read_bit() == 1 {
+                        for (b, subband) in subbands.iter().enumerate() {
+                            let exponent = exponents[if resolution == 0 {
+                                0
+                            } else {
+                                3 * (resolution - 1) + 1 + b
+                            }];
+                            let max_bitplanes = guard_bits + exponent - 1;
+                            let size = 1 << CODE_BLOCK_EXPONENT;
+                            let grid = |start: u32, len: u32| {
+                                let end = start + len;
+                                (start / size * size..end)
+                                    .step_by(size as usize)
+                                    .map(|b| (b.max(start), (b + size).min(end)))
+                                    .collect::<Vec<_>>()
+                            };
+                            let columns = if subband.width > 0 {
+                                grid(subband.x0, subband.width)
+                            } else {
+                                vec![]
+                            };
+                            let rows = if subband.height > 0 {
+                                grid(subband.y0, subband.height)
+                            } else {
+                                vec![]
+                            };
+                            let mut inclusion = TagTreeDecoder::new(columns.len(), rows.len());
+                            let mut zero_bitplanes = TagTreeDecoder::new(columns.len(), rows.len());
+                            for (y, &(by0, by1)) in rows.iter().enumerate() {
+                                for (x, &(bx0, bx1)) in columns.iter().enumerate() {
+                                    if !inclusion.decode(&mut reader, x, y, 1) {
+                                        continue;
+                                    }
+                                    let mut threshold = 1;
+                                    while !zero_bitplanes.decode(&mut reader, x, y, threshold) {
+                                        threshold += 1;
+                                    }
+                                    let bitplanes = max_bitplanes - zero_bitplanes.value(x, y);
+                                    let passes = if reader.read_bit() == 0 {
+                                        1
+                                    } else if reader.read_bit() == 0 {
+                                        2
+                                    } else {
+                                        match reader.read_bits(2) {
+                                            3 => match reader.read_bits(5) {
+                                                31 => reader.read_bits(7) + 37,
+                                                n => n + 6,
+                                            },
+                                            n => n + 3,
+                                        }
+                                    };
+                                    let mut lblock = 3;
+                                    while reader.read_bit() == 1 {
+                                        lblock += 1;
+                                    }
+                                    let len = reader.read_bits(lblock + 31 - passes.leading_zeros())
+                                        as usize;
+                                    blocks.push((b, bx0, by0, bx1, by1, bitplanes, passes, len));
+                                }
+                            }
+                        }
+                    }
+                    position += reader.finish();
+
+                    for (b, bx0, by0, bx1, by1, bitplanes, passes, len) in blocks {
+                        let subband = &mut subbands[b];
+                        let w = (bx1 - bx0) as usize;
+                        let h = (by1 - by0) as usize;
+                        let coefficients = decode_code_block(
+                            &data[position..position + len],
+                            w,
+                            h,
+                            subband.orientation,
+                            bitplanes,
+                            passes,
+                        );
+                        position += len;
+                        for y in 0..h {
+                            let row = (by0 - subband.y0) as usize + y;
+                            let start = row * subband.width as usize + (bx0 - subband.x0) as usize;
+                            subband.coefficients[start..start + w]
+                                .copy_from_slice(&coefficients[y * w..(y + 1) * w]);
+                        }
+                    }
+                }
+            }
+            assert_eq!(position, end);
+
+            // inverse wavelet transformation of each tile-component
+            for (c, component) in tile.into_iter().enumerate() {
+                let mut regions = vec![region];
+                for _ in 0..levels {
+                    let (u0, v0, u1, v1) = regions[regions.len() - 1];
+                    regions.push((
+                        u0.div_ceil(2),
+                        v0.div_ceil(2),
+                        u1.div_ceil(2),
+                        v1.div_ceil(2),
+                    ));
+                }
+                let mut resolutions = component.into_iter();
+                let mut low = resolutions
+                    .next()
+                    .unwrap()
+                    .map(|mut s| s.remove(0).coefficients)
+                    .unwrap_or_default();
+                for (level, subbands) in resolutions.enumerate() {
+                    let (u0, v0, u1, v1) = regions[levels as usize - 1 - level];
+                    let w = (u1 - u0) as usize;
+                    let h = (v1 - v0) as usize;
+                    let subbands = match subbands {
+                        Some(subbands) => subbands,
+                        None => {
+                            low = vec![];
+                            continue;
+                        }
+                    };
+                    let lw = (u1.div_ceil(2) - u0.div_ceil(2)) as usize;
+                    let lh = (v1.div_ceil(2) - v0.div_ceil(2)) as usize;
+                    let mut full = vec![0; w * h];
+                    let mut place = |coefficients: &[i32], xa: usize, ya: usize, bw: usize| {
+                        for (k, &value) in coefficients.iter().enumerate() {
+                            full[(ya + k / bw) * w + xa + k % bw] = value;
+                        }
+                    };
+                    place(&low, 0, 0, lw);
+                    place(&subbands[0].coefficients, lw, 0, w - lw);
+                    place(&subbands[1].coefficients, 0, lh, lw);
+                    place(&subbands[2].coefficients, lw, lh, w - lw);
+                    for row in full.chunks_exact_mut(w) {
+                        inverse_1d(row, u0);
+                    }
+                    for x in 0..w {
+                        let mut column: Vec<i32> = (0..h).map(|y| full[y * w + x]).collect();
+                        inverse_1d(&mut column, v0);
+                        for (y, value) in column.into_iter().enumerate() {
+                            full[y * w + x] = value;
+                        }
+                    }
+                    low = full;
+                }
+
+                let offset = if signed { 0 } else { 1 << (precision - 1) };
+                let tw = (x1 - x0) as usize;
+                for (k, value) in low.into_iter().enumerate() {
+                    let x = x0 as usize + k % tw;
+                    let y = y0 as usize + k / tw;
+                    planes[c][y * width as usize + x] = value + offset;
+                }
+            }
+        }
+        assert_eq!(&data[position..position + 2], &[0xFF, 0xD9]);
+        (width, height, planes)
+    }
+
+    /// Encode the image through the adapter and decode it back,
+    /// with both the test decoder and OpenJPEG.
+    fn roundtrip(adapter: Jpeg2000Adapter, native: &TestPixelData) -> Vec<Vec<i32>> {
+        let mut fragment = Vec::new();
+        adapter
+            .encode_frame(native, 0, EncodeOptions::new(), &mut fragment)
+            .unwrap();
+        assert_eq!(fragment.len() % 2, 0);
+        let (width, height, planes) = decode(&fragment);
+        assert_eq!((width, height), (native.cols.into(), native.rows.into()));
+        #[cfg(feature = "interop-tests")]
+        assert_eq!(reference_decode(&fragment, native), planes);
+        planes
+    }
+
+    /// Decode the codestream with OpenJPEG,
+    /// independently from the decoder above,
+    /// and check the image parameters against the native pixel data.
+    #[cfg(feature = "interop-tests")]
+    fn reference_decode(fragment: &[u8], native: &TestPixelData) -> Vec<Vec<i32>> {
+        let image = jpeg2k::Image::from_bytes(fragment).unwrap();
+        assert_eq!(
+            (image.width(), image.height()),
+            (native.cols.into(), native.rows.into())
+        );
+        assert_eq!(image.num_components(), u32::from(native.samples_per_pixel));
+        image
+            .components()
+            .iter()
+            .map(|component| {
+                assert_eq!(component.precision(), u32::from(native.bits_stored));
+                assert_eq!(component.is_signed(), native.pixel_representation == 1);
+                component.data().to_vec()
+            })
+            .collect()
+    }
+
+    fn test_image(rows: u16, cols: u16, bits_stored: u16, signed: bool) -> TestPixelData {
+        let mut seed = 0x2545_F491_u32;
+        let pixels: Vec<u8> = (0..u32::from(rows) * u32::from(cols))
+            .flat_map(|i| {
+                // smooth gradient with noise
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let x = i % u32::from(cols);
+                let y = i / u32::from(cols);
+                let value = (x * 37 + y * 11 + (seed & 0x3F)) % (1 << bits_stored);
+                let value = if signed {
+                    value as i32 - (1 << (bits_stored - 1))
+                } else {
+                    value as i32
+                };
+                (value as u16).to_le_bytes()
+            })
+            .collect();
+        TestPixelData {
+            rows,
+            cols,
+            samples_per_pixel: 1,
+            bits_allocated: 16,
+            bits_stored,
+            pixel_representation: u16::from(signed),
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(pixels),
+            fragments: vec![],
+        }
+    }
+
+    fn samples(native: &TestPixelData) -> Vec<i32> {
+        let shift = 32 - u32::from(native.bits_stored);
+        native
+            .native
+            .as_ref()
+            .unwrap()
+            .chunks_exact(2)
+            .map(|b| {
+                let value = i32::from(u16::from_le_bytes([b[0], b[1]])) << shift;
+                if native.pixel_representation == 1 {
+                    value >> shift
+                } else {
+                    ((value as u32) >> shift) as i32
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn forward_1d_is_reversible() {
+        let mut buffer = Vec::new();
+        for start in 0..4 {
+            for len in 1..12 {
+                let original: Vec<i32> = (0..len).map(|i| (i * i * 7 % 23) - 11).collect();
+                let mut line = original.clone();
+                forward_1d(&mut line, start, &mut buffer);
+                inverse_1d(&mut line, start);
+                assert_eq!(line, original, "start {} length {}", start, len);
+            }
+        }
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        // 12-bit unsigned, several code-blocks and decomposition levels
+        let native = test_image(150, 200, 12, false);
+        let planes = roundtrip(Jpeg2000Adapter::new(), &native);
+        assert_eq!(planes, vec![samples(&native)]);
+
+        // 16-bit signed
+        let native = test_image(70, 90, 16, true);
+        let planes = roundtrip(Jpeg2000Adapter::new(), &native);
+        assert_eq!(planes, vec![samples(&native)]);
+
+        // tiny images
+        for &(rows, cols) in [(1, 1), (1, 7), (9, 1), (2, 3)].iter() {
+            let native = test_image(rows, cols, 8, false);
+            let planes = roundtrip(Jpeg2000Adapter::new(), &native);
+            assert_eq!(planes, vec![samples(&native)]);
+        }
+    }
+
+    #[test]
+    fn encode_roundtrip_tiled() {
+        let native = test_image(100, 130, 10, false);
+        for &(tile_width, tile_height) in [(64, 64), (37, 23), (130, 1), (1, 100)].iter() {
+            let adapter = Jpeg2000Adapter::new()
+                .with_tile_size(tile_width, tile_height)
+                .with_decomposition_levels(3);
+            let planes = roundtrip(adapter, &native);
+            assert_eq!(
+                planes,
+                vec![samples(&native)],
+                "tiles of {}x{}",
+                tile_width,
+                tile_height
+            );
+        }
+    }
+
+    #[test]
+    fn encode_roundtrip_rgb() {
+        let pixels: Vec<u8> = (0..40 * 30_u32)
+            .flat_map(|i| [(i * 3) as u8, 0x80, (i / 40 * 8) as u8])
+            .collect();
+        let native = TestPixelData {
+            rows: 30,
+            cols: 40,
+            samples_per_pixel: 3,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 2,
+            native: Some([pixels.clone(), vec![0; pixels.len()]].concat()),
+            fragments: vec![],
+        };
+        let expected: Vec<Vec<i32>> = (0..3)
+            .map(|c| {
+                pixels
+                    .iter()
+                    .skip(c)
+                    .step_by(3)
+                    .map(|&s| i32::from(s))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(roundtrip(Jpeg2000Adapter::new(), &native), expected);
+
+        // same image, with one plane per sample
+        let planar: Vec<u8> = expected
+            .iter()
+            .flatten()
+            .map(|&s| s as u8)
+            .chain(std::iter::repeat_n(0, pixels.len()))
+            .collect();
+        let native = TestPixelData {
+            planar_configuration: 1,
+            native: Some(planar),
+            ..native
+        };
+        assert_eq!(roundtrip(Jpeg2000Adapter::new(), &native), expected);
+
+        // all frames at once
+        let mut all = Vec::new();
+        Jpeg2000Adapter::new()
+            .encode(&native, EncodeOptions::new(), &mut all)
+            .unwrap();
+        let codestreams = all.windows(4).filter(|w| w == &[0xFF, 0x4F, 0xFF, 0x51]);
+        assert_eq!(codestreams.count(), 2);
+    }
+
+    #[test]
+    fn encode_rejects_tiles_across_precincts() {
+        let image = Image {
+            width: 40000,
+            height: 1,
+            precision: 8,
+            signed: false,
+            planes: vec![vec![0; 40000]],
+        };
+        assert!(encode(&image, 30000, 1, 5).is_err());
+        assert!(encode(&image, 32768, 1, 5).is_ok());
+    }
+
+    /// The test sequence of the MQ coder (T.88 Annex H.2),
+    /// coded with a single context.
+    #[test]
+    fn mq_encoder_test_sequence() {
+        const INPUT: [u8; 32] = [
+            0x00, 0x02, 0x00, 0x51, 0x00, 0x00, 0x00, 0xC0, 0x03, 0x52, 0x87, 0x2A, 0xAA, 0xAA,
+            0xAA, 0xAA, 0x82, 0xC0, 0x20, 0x00, 0xFC, 0xD7, 0x9E, 0xF6, 0xBF, 0x7F, 0xED, 0x90,
+            0x4F, 0x46, 0xA3, 0xBF,
+        ];
+        const OUTPUT: [u8; 28] = [
+            0x84, 0xC7, 0x3B, 0xFC, 0xE1, 0xA1, 0x43, 0x04, 0x02, 0x20, 0x00, 0x00, 0x41, 0x0D,
+            0xBB, 0x86, 0xF4, 0x31, 0x7F, 0xFF, 0x88, 0xFF, 0x37, 0x47, 0x1A, 0xDB, 0x6A, 0xDF,
+        ];
+
+        let mut mq = MqEncoder::new();
+        mq.contexts[1] = (0, 0);
+        for byte in INPUT.iter() {
+            for i in (0..8).rev() {
+                mq.encode(1, u32::from(byte >> i) & 1);
+            }
+        }
+        let out = mq.flush();
+        assert_eq!(&out[..OUTPUT.len()], &OUTPUT[..]);
+    }
+}
@@ -0,0 +1,891 @@
+//! Decoding of JPEG images with 12-bit sample precision.
+//!
+//! The `jpeg-decoder` crate only supports DCT-based JPEG images
+//! with 8-bit samples,
+//! but the JPEG Extended (Process 2 & 4) transfer syntax
+//! is often used with 12-bit samples (process 4),
+//! especially in CR and DX archives.
+//! This module implements a decoder for sequential DCT-based images
+//! with Huffman coding (ISO/IEC 10918-1 Annex F),
+//! without any color space transformation.
+use snafu::{ensure, Snafu};
+use std::f32::consts::PI;
+
+#[derive(Debug, Snafu)]
+pub(crate) enum Error {
+    #[snafu(display("Invalid JPEG data: {}", message))]
+    Format { message: &'static str },
+    #[snafu(display("Unsupported JPEG feature: {}", feature))]
+    Unsupported { feature: &'static str },
+}
+
+pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Index in natural (row-major) order
+/// of each coefficient in zig-zag order.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// A decoded image.
+#[derive(Debug)]
+pub(crate) struct Image {
+    pub width: u16,
+    pub height: u16,
+    pub components: usize,
+    /// The decoded samples, with the components of each pixel contiguous
+    pub samples: Vec<u16>,
+}
+
+/// Check whether the given JPEG image should be decoded by this module,
+/// which is the case for sequential DCT-based images with 12-bit samples.
+pub(crate) fn is_extended_precision(data: &[u8]) -> bool {
+    frame_precision(data) == Some(12)
+}
+
+/// Find the sample precision of a sequential DCT-based JPEG image.
+fn frame_precision(data: &[u8]) -> Option<u8> {
+    if data.get(0..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        match *data.get(pos + 1)? {
+            0xFF => pos += 1,
+            0xC0 | 0xC1 => return data.get(pos + 4).copied(),
+            // other frame types, start of scan or end of image
+            0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA | 0xD9 => return None,
+            _ => {
+                let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]);
+                pos += 2 + len as usize;
+            }
+        }
+    }
+}
+
+/// Decode a sequential DCT-based JPEG image.
+///
+/// Returns the image and the number of bytes read,
+/// up to and including the end of image marker.
+pub(crate) fn decode(data: &[u8]) -> Result<(Image, usize)> {
+    ensure!(
+        data.starts_with(&[0xFF, 0xD8]),
+        FormatSnafu {
+            message: "missing start of image"
+        }
+    );
+
+    let mut decoder = Decoder::default();
+    let mut pos = 2;
+    loop {
+        ensure!(
+            pos + 1 < data.len(),
+            FormatSnafu {
+                message: "unexpected end of data"
+            }
+        );
+        if data[pos] != 0xFF {
+            // tolerate garbage between segments
+            pos += 1;
+            continue;
+        }
+        let marker = data[pos + 1];
+        pos += 2;
+        match marker {
+            // fill bytes
+            0xFF => pos -= 1,
+            // end of image
+            0xD9 => break,
+            // markers without a segment
+            0x01 | 0xD0..=0xD7 => {}
+            _ => {
+                ensure!(
+                    pos + 2 <= data.len(),
+                    FormatSnafu {
+                        message: "unexpected end of data"
+                    }
+                );
+                let len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+                ensure!(
+                    len >= 2 && pos + len <= data.len(),
+                    FormatSnafu {
+                        message: "invalid segment length"
+                    }
+                );
+                let segment = &data[pos + 2..pos + len];
+                pos += len;
+                match marker {
+                    0xDB => decoder.read_quantization_tables(segment)?,
+                    0xC4 => decoder.read_huffman_tables(segment)?,
+                    0xC0 | 0xC1 => decoder.read_frame_header(segment)?,
+                    0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                        return UnsupportedSnafu {
+                            feature: "non-sequential or arithmetic coding process",
+                        }
+                        .fail()
+                    }
+                    0xDD => {
+                        ensure!(
+                            segment.len() >= 2,
+                            FormatSnafu {
+                                message: "invalid restart interval"
+                            }
+                        );
+                        decoder.restart_interval = u16::from_be_bytes([segment[0], segment[1]]);
+                    }
+                    0xDA => pos = decoder.decode_scan(segment, data, pos)?,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok((decoder.into_image()?, pos))
+}
+
+#[derive(Debug)]
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quantization_table: usize,
+    dc_table: usize,
+    ac_table: usize,
+    dc_pred: i32,
+    /// Decoded samples, covering whole MCUs
+    plane: Vec<u16>,
+    /// The width of `plane`, in samples
+    stride: usize,
+}
+
+#[derive(Debug)]
+struct Frame {
+    precision: u8,
+    width: usize,
+    height: usize,
+    h_max: usize,
+    v_max: usize,
+    mcus_x: usize,
+    mcus_y: usize,
+    components: Vec<Component>,
+}
+
+#[derive(Debug, Default)]
+struct Decoder {
+    quantization_tables: [Option<[u16; 64]>; 4],
+    dc_tables: [Option<HuffmanTable>; 4],
+    ac_tables: [Option<HuffmanTable>; 4],
+    restart_interval: u16,
+    frame: Option<Frame>,
+}
+
+impl Decoder {
+    fn read_quantization_tables(&mut self, mut segment: &[u8]) -> Result<()> {
+        while let Some((&pq_tq, rest)) = segment.split_first() {
+            let precision = (pq_tq >> 4) as usize;
+            let id = (pq_tq & 0x0F) as usize;
+            let len = 64 * (precision + 1);
+            ensure!(
+                precision <= 1 && id < 4 && rest.len() >= len,
+                FormatSnafu {
+                    message: "invalid quantization table"
+                }
+            );
+            let mut table = [0; 64];
+            for (k, q) in table.iter_mut().enumerate() {
+                *q = if precision == 0 {
+                    rest[k] as u16
+                } else {
+                    u16::from_be_bytes([rest[2 * k], rest[2 * k + 1]])
+                };
+            }
+            self.quantization_tables[id] = Some(table);
+            segment = &rest[len..];
+        }
+        Ok(())
+    }
+
+    fn read_huffman_tables(&mut self, mut segment: &[u8]) -> Result<()> {
+        while segment.len() >= 17 {
+            let class = segment[0] >> 4;
+            let id = (segment[0] & 0x0F) as usize;
+            let mut counts = [0_u8; 16];
+            counts.copy_from_slice(&segment[1..17]);
+            let nr_values: usize = counts.iter().map(|&c| c as usize).sum();
+            ensure!(
+                class <= 1 && id < 4 && segment.len() >= 17 + nr_values,
+                FormatSnafu {
+                    message: "invalid Huffman table"
+                }
+            );
+            let table = HuffmanTable::new(&counts, &segment[17..17 + nr_values])?;
+            if class == 0 {
+                self.dc_tables[id] = Some(table);
+            } else {
+                self.ac_tables[id] = Some(table);
+            }
+            segment = &segment[17 + nr_values..];
+        }
+        Ok(())
+    }
+
+    fn read_frame_header(&mut self, segment: &[u8]) -> Result<()> {
+        ensure!(
+            self.frame.is_none(),
+            UnsupportedSnafu {
+                feature: "multiple frames"
+            }
+        );
+        ensure!(
+            segment.len() >= 6,
+            FormatSnafu {
+                message: "invalid frame header"
+            }
+        );
+        let precision = segment[0];
+        let height = u16::from_be_bytes([segment[1], segment[2]]) as usize;
+        let width = u16::from_be_bytes([segment[3], segment[4]]) as usize;
+        let nr_components = segment[5] as usize;
+        ensure!(
+            precision == 8 || precision == 12,
+            UnsupportedSnafu {
+                feature: "sample precision other than 8 or 12"
+            }
+        );
+        ensure!(
+            height > 0,
+            UnsupportedSnafu {
+                feature: "number of lines defined after the first scan"
+            }
+        );
+        ensure!(
+            width > 0 && nr_components > 0 && segment.len() >= 6 + 3 * nr_components,
+            FormatSnafu {
+                message: "invalid frame header"
+            }
+        );
+
+        let mut components = Vec::with_capacity(nr_components);
+        for c in segment[6..6 + 3 * nr_components].chunks_exact(3) {
+            let h = (c[1] >> 4) as usize;
+            let v = (c[1] & 0x0F) as usize;
+            ensure!(
+                (1..=4).contains(&h) && (1..=4).contains(&v) && c[2] < 4,
+                FormatSnafu {
+                    message: "invalid frame component"
+                }
+            );
+            components.push(Component {
+                id: c[0],
+                h,
+                v,
+                quantization_table: c[2] as usize,
+                dc_table: 0,
+                ac_table: 0,
+                dc_pred: 0,
+                plane: Vec::new(),
+                stride: 0,
+            });
+        }
+
+        let h_max = components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = components.iter().map(|c| c.v).max().unwrap_or(1);
+        let mcus_x = width.div_ceil(8 * h_max);
+        let mcus_y = height.div_ceil(8 * v_max);
+        for c in &mut components {
+            c.stride = mcus_x * c.h * 8;
+            c.plane = vec![0; c.stride * mcus_y * c.v * 8];
+        }
+
+        self.frame = Some(Frame {
+            precision,
+            width,
+            height,
+            h_max,
+            v_max,
+            mcus_x,
+            mcus_y,
+            components,
+        });
+        Ok(())
+    }
+
+    /// Decode the scan starting at `pos`,
+    /// returning the position of the first marker after the scan.
+    fn decode_scan(&mut self, header: &[u8], data: &[u8], pos: usize) -> Result<usize> {
+        let frame = self.frame.as_mut().ok_or(Error::Format {
+            message: "scan before frame header",
+        })?;
+        ensure!(
+            !header.is_empty() && header.len() >= 1 + 2 * header[0] as usize + 3,
+            FormatSnafu {
+                message: "invalid scan header"
+            }
+        );
+        let nr_components = header[0] as usize;
+        let mut scan_components = Vec::with_capacity(nr_components);
+        for c in header[1..1 + 2 * nr_components].chunks_exact(2) {
+            let index =
+                frame
+                    .components
+                    .iter()
+                    .position(|fc| fc.id == c[0])
+                    .ok_or(Error::Format {
+                        message: "unknown scan component",
+                    })?;
+            let component = &mut frame.components[index];
+            component.dc_table = (c[1] >> 4) as usize;
+            component.ac_table = (c[1] & 0x0F) as usize;
+            ensure!(
+                component.dc_table < 4 && component.ac_table < 4,
+                FormatSnafu {
+                    message: "invalid scan component"
+                }
+            );
+            scan_components.push(index);
+        }
+        let spectral = &header[1 + 2 * nr_components..];
+        ensure!(
+            spectral[0] == 0 && spectral[1] == 63 && spectral[2] == 0,
+            UnsupportedSnafu {
+                feature: "progressive scan"
+            }
+        );
+
+        let mut reader = BitReader::new(data, pos);
+        for &index in &scan_components {
+            frame.components[index].dc_pred = 0;
+        }
+
+        // each MCU is a list of (component index, block x, block y)
+        let mut mcus: Vec<Vec<(usize, usize, usize)>> = Vec::new();
+        if let [index] = scan_components[..] {
+            // non-interleaved: one block per MCU,
+            // covering only the component's own samples
+            let c = &frame.components[index];
+            let blocks_x = (frame.width * c.h).div_ceil(frame.h_max).div_ceil(8);
+            let blocks_y = (frame.height * c.v).div_ceil(frame.v_max).div_ceil(8);
+            for by in 0..blocks_y {
+                for bx in 0..blocks_x {
+                    mcus.push(vec![(index, bx, by)]);
+                }
+            }
+        } else {
+            for my in 0..frame.mcus_y {
+                for mx in 0..frame.mcus_x {
+                    let mut mcu = Vec::new();
+                    for &index in &scan_components {
+                        let c = &frame.components[index];
+                        for by in 0..c.v {
+                            for bx in 0..c.h {
+                                mcu.push((index, mx * c.h + bx, my * c.v + by));
+                            }
+                        }
+                    }
+                    mcus.push(mcu);
+                }
+            }
+        }
+
+        let restart_interval = self.restart_interval as usize;
+        for (i, mcu) in mcus.iter().enumerate() {
+            if restart_interval > 0 && i > 0 && i % restart_interval == 0 {
+                reader.restart()?;
+                for &index in &scan_components {
+                    frame.components[index].dc_pred = 0;
+                }
+            }
+            for &(index, bx, by) in mcu {
+                let component = &mut frame.components[index];
+                let quantization = self.quantization_tables[component.quantization_table]
+                    .as_ref()
+                    .ok_or(Error::Format {
+                        message: "missing quantization table",
+                    })?;
+                let dc = self.dc_tables[component.dc_table]
+                    .as_ref()
+                    .ok_or(Error::Format {
+                        message: "missing Huffman table",
+                    })?;
+                let ac = self.ac_tables[component.ac_table]
+                    .as_ref()
+                    .ok_or(Error::Format {
+                        message: "missing Huffman table",
+                    })?;
+
+                let coefficients =
+                    decode_block(&mut reader, &mut component.dc_pred, dc, ac, quantization)?;
+                let start = by * 8 * component.stride + bx * 8;
+                idct(
+                    &coefficients,
+                    frame.precision,
+                    &mut component.plane[start..],
+                    component.stride,
+                );
+            }
+        }
+
+        Ok(reader.end())
+    }
+
+    fn into_image(self) -> Result<Image> {
+        let frame = self.frame.ok_or(Error::Format {
+            message: "missing frame header",
+        })?;
+        let mut samples = Vec::with_capacity(frame.width * frame.height * frame.components.len());
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                for c in &frame.components {
+                    let cy = y * c.v / frame.v_max;
+                    let cx = x * c.h / frame.h_max;
+                    samples.push(c.plane[cy * c.stride + cx]);
+                }
+            }
+        }
+        Ok(Image {
+            width: frame.width as u16,
+            height: frame.height as u16,
+            components: frame.components.len(),
+            samples,
+        })
+    }
+}
+
+/// A Huffman decoding table (ISO/IEC 10918-1 F.2.2.3).
+#[derive(Debug)]
+struct HuffmanTable {
+    max_code: [i32; 17],
+    min_code: [i32; 17],
+    value_offset: [usize; 17],
+    values: Vec<u8>,
+}
+
+impl HuffmanTable {
+    fn new(counts: &[u8; 16], values: &[u8]) -> Result<Self> {
+        let mut max_code = [-1; 17];
+        let mut min_code = [0; 17];
+        let mut value_offset = [0; 17];
+        let mut code = 0_i32;
+        let mut k = 0;
+        for (i, &count) in counts.iter().enumerate() {
+            let len = i + 1;
+            if count > 0 {
+                value_offset[len] = k;
+                min_code[len] = code;
+                code += count as i32;
+                k += count as usize;
+                max_code[len] = code - 1;
+            }
+            ensure!(
+                code <= 1 << len,
+                FormatSnafu {
+                    message: "invalid Huffman table"
+                }
+            );
+            code <<= 1;
+        }
+        Ok(HuffmanTable {
+            max_code,
+            min_code,
+            value_offset,
+            values: values.to_vec(),
+        })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u8> {
+        let mut code = reader.bit() as i32;
+        for len in 1..=16 {
+            if code <= self.max_code[len] {
+                let k = self.value_offset[len] + (code - self.min_code[len]) as usize;
+                return Ok(self.values[k]);
+            }
+            code = (code << 1) | reader.bit() as i32;
+        }
+        FormatSnafu {
+            message: "invalid Huffman code",
+        }
+        .fail()
+    }
+}
+
+/// Reader of entropy-coded data,
+/// removing stuffed bytes and stopping at markers.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    acc: u32,
+    nr_bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        BitReader {
+            data,
+            pos,
+            acc: 0,
+            nr_bits: 0,
+        }
+    }
+
+    fn fill(&mut self) {
+        while self.nr_bits <= 24 {
+            let byte = match self.data.get(self.pos) {
+                Some(0xFF) => match self.data.get(self.pos + 1) {
+                    Some(0x00) => {
+                        self.pos += 2;
+                        0xFF
+                    }
+                    // a marker: feed zeros
+                    _ => 0,
+                },
+                Some(&byte) => {
+                    self.pos += 1;
+                    byte
+                }
+                None => 0,
+            };
+            self.acc |= (byte as u32) << (24 - self.nr_bits);
+            self.nr_bits += 8;
+        }
+    }
+
+    fn bit(&mut self) -> u32 {
+        self.bits(1)
+    }
+
+    fn bits(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        if self.nr_bits < n {
+            self.fill();
+        }
+        let value = self.acc >> (32 - n);
+        self.acc <<= n;
+        self.nr_bits -= n;
+        value
+    }
+
+    /// Discard the remaining bits and consume the next restart marker.
+    fn restart(&mut self) -> Result<()> {
+        self.acc = 0;
+        self.nr_bits = 0;
+        match self.data.get(self.pos..self.pos + 2) {
+            Some([0xFF, 0xD0..=0xD7]) => {
+                self.pos += 2;
+                Ok(())
+            }
+            _ => FormatSnafu {
+                message: "missing restart marker",
+            }
+            .fail(),
+        }
+    }
+
+    /// Discard the remaining bits,
+    /// returning the position of the next marker.
+    fn end(self) -> usize {
+        let mut pos = self.pos;
+        while let Some(window) = self.data.get(pos..pos + 2) {
+            if window[0] == 0xFF && window[1] != 0x00 {
+                break;
+            }
+            pos += 1;
+        }
+        pos
+    }
+}
+
+/// Decode the dequantized coefficients of a block, in natural order.
+fn decode_block(
+    reader: &mut BitReader,
+    dc_pred: &mut i32,
+    dc: &HuffmanTable,
+    ac: &HuffmanTable,
+    quantization: &[u16; 64],
+) -> Result<[i32; 64]> {
+    let mut coefficients = [0; 64];
+
+    let size = dc.decode(reader)? as u32;
+    ensure!(
+        size <= 16,
+        FormatSnafu {
+            message: "invalid DC coefficient"
+        }
+    );
+    *dc_pred += extend(reader.bits(size), size);
+    coefficients[0] = *dc_pred * quantization[0] as i32;
+
+    let mut k = 1;
+    while k < 64 {
+        let rs = ac.decode(reader)?;
+        let run = (rs >> 4) as usize;
+        let size = (rs & 0x0F) as u32;
+        if size == 0 {
+            if run == 15 {
+                k += 16;
+                continue;
+            }
+            break;
+        }
+        k += run;
+        ensure!(
+            k < 64,
+            FormatSnafu {
+                message: "invalid AC coefficient"
+            }
+        );
+        coefficients[ZIGZAG[k]] = extend(reader.bits(size), size) * quantization[k] as i32;
+        k += 1;
+    }
+
+    Ok(coefficients)
+}
+
+/// Convert the additional bits of a coefficient to its signed value
+/// (ISO/IEC 10918-1 F.2.2.1).
+fn extend(value: u32, size: u32) -> i32 {
+    if size == 0 {
+        0
+    } else if value < 1 << (size - 1) {
+        value as i32 - (1 << size) + 1
+    } else {
+        value as i32
+    }
+}
+
+/// Apply the inverse DCT to a block of coefficients,
+/// writing the level shifted samples to `out`.
+fn idct(coefficients: &[i32; 64], precision: u8, out: &mut [u16], stride: usize) {
+    // basis[x][u] = C(u) / 2 * cos((2x + 1) u pi / 16)
+    let mut basis = [[0_f32; 8]; 8];
+    for (x, row) in basis.iter_mut().enumerate() {
+        for (u, b) in row.iter_mut().enumerate() {
+            let c = if u == 0 { 0.5_f32.sqrt() } else { 1. };
+            *b = c / 2. * ((2 * x + 1) as f32 * u as f32 * PI / 16.).cos();
+        }
+    }
+
+    let mut rows = [[0_f32; 8]; 8];
+    for (v, row) in rows.iter_mut().enumerate() {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = (0..8)
+                .map(|u| basis[x][u] * coefficients[v * 8 + u] as f32)
+                .sum();
+        }
+    }
+
+    let shift = (1 << (precision - 1)) as f32;
+    let max = ((1 << precision) - 1) as f32;
+    for y in 0..8 {
+        for x in 0..8 {
+            let value: f32 = (0..8).map(|v| basis[y][v] * rows[v][x]).sum();
+            out[y * stride + x] = (value + shift).round().clamp(0., max) as u16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal encoder of a 12-bit monochrome sequential JPEG image,
+    /// with a unit quantization table and a restart interval.
+    fn encode_12bit(width: usize, height: usize, samples: &[u16]) -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8];
+
+        // 16-bit quantization table with all ones
+        out.extend([0xFF, 0xDB, 0, 131, 0x10]);
+        for _ in 0..64 {
+            out.extend([0, 1]);
+        }
+
+        // extended sequential frame header
+        out.extend([0xFF, 0xC1, 0, 11, 12]);
+        out.extend((height as u16).to_be_bytes());
+        out.extend((width as u16).to_be_bytes());
+        out.extend([1, 1, 0x11, 0]);
+
+        // Huffman tables with 8-bit codes for every symbol
+        let dc_values: Vec<u8> = (0..16).collect();
+        let ac_values: Vec<u8> = vec![0x00, 0xF0]
+            .into_iter()
+            .chain((0..16).flat_map(|r| (1..=14).map(move |s| r << 4 | s)))
+            .collect();
+        for (class, values) in [(0x00, &dc_values), (0x10, &ac_values)] {
+            out.extend([0xFF, 0xC4]);
+            out.extend((19 + values.len() as u16).to_be_bytes());
+            out.push(class);
+            let mut counts = [0; 16];
+            counts[7] = values.len() as u8;
+            out.extend(counts);
+            out.extend(values.iter());
+        }
+
+        // restart every 2 blocks
+        out.extend([0xFF, 0xDD, 0, 4, 0, 2]);
+
+        out.extend([0xFF, 0xDA, 0, 8, 1, 1, 0x00, 0, 63, 0]);
+
+        let mut bits = BitWriter::default();
+        let mut dc_pred = 0;
+        let blocks_x = width.div_ceil(8);
+        let blocks_y = height.div_ceil(8);
+        for (i, (by, bx)) in (0..blocks_y)
+            .flat_map(|by| (0..blocks_x).map(move |bx| (by, bx)))
+            .enumerate()
+        {
+            if i > 0 && i % 2 == 0 {
+                bits.flush(&mut out);
+                out.extend([0xFF, 0xD0 + ((i / 2 - 1) % 8) as u8]);
+                dc_pred = 0;
+            }
+
+            // forward DCT, with edge replication
+            let sample = |x: usize, y: usize| {
+                let x = (bx * 8 + x).min(width - 1);
+                let y = (by * 8 + y).min(height - 1);
+                samples[y * width + x] as f32 - 2048.
+            };
+            let mut coefficients = [0_i32; 64];
+            for v in 0..8 {
+                for u in 0..8 {
+                    let cu = if u == 0 { 0.5_f32.sqrt() } else { 1. };
+                    let cv = if v == 0 { 0.5_f32.sqrt() } else { 1. };
+                    let mut sum = 0.;
+                    for y in 0..8 {
+                        for x in 0..8 {
+                            sum += sample(x, y)
+                                * ((2 * x + 1) as f32 * u as f32 * PI / 16.).cos()
+                                * ((2 * y + 1) as f32 * v as f32 * PI / 16.).cos();
+                        }
+                    }
+                    coefficients[v * 8 + u] = (cu * cv / 4. * sum).round() as i32;
+                }
+            }
+
+            let diff = coefficients[0] - dc_pred;
+            dc_pred = coefficients[0];
+            let size = magnitude(diff);
+            bits.write(size, 8);
+            bits.write(additional_bits(diff, size), size);
+
+            let mut run = 0;
+            for &index in &ZIGZAG[1..] {
+                let value = coefficients[index];
+                if value == 0 {
+                    run += 1;
+                    continue;
+                }
+                while run >= 16 {
+                    bits.write(1, 8);
+                    run -= 16;
+                }
+                let size = magnitude(value);
+                let symbol = run << 4 | size;
+                let code = ac_values.iter().position(|&s| s as u32 == symbol).unwrap();
+                bits.write(code as u32, 8);
+                bits.write(additional_bits(value, size), size);
+                run = 0;
+            }
+            if run > 0 {
+                bits.write(0, 8);
+            }
+        }
+        bits.flush(&mut out);
+        out.extend([0xFF, 0xD9]);
+        out
+    }
+
+    fn magnitude(value: i32) -> u32 {
+        32 - value.unsigned_abs().leading_zeros()
+    }
+
+    fn additional_bits(value: i32, size: u32) -> u32 {
+        if value < 0 {
+            (value - 1) as u32 & ((1 << size) - 1)
+        } else {
+            value as u32
+        }
+    }
+
+    #[derive(Default)]
+    struct BitWriter {
+        acc: u32,
+        nr_bits: u32,
+        bytes: Vec<u8>,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u32, n: u32) {
+            for i in (0..n).rev() {
+                self.acc = self.acc << 1 | (value >> i) & 1;
+                self.nr_bits += 1;
+                if self.nr_bits == 8 {
+                    self.bytes.push(self.acc as u8);
+                    if self.acc == 0xFF {
+                        self.bytes.push(0);
+                    }
+                    self.acc = 0;
+                    self.nr_bits = 0;
+                }
+            }
+        }
+
+        fn flush(&mut self, out: &mut Vec<u8>) {
+            if self.nr_bits > 0 {
+                self.write(0xFF, 8 - self.nr_bits);
+            }
+            out.append(&mut self.bytes);
+        }
+    }
+
+    #[test]
+    fn decode_12bit_grayscale() {
+        let (width, height) = (21, 13);
+        let samples: Vec<u16> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                (x * 100 + y * 80 + (x * y) % 7 * 40) as u16
+            })
+            .collect();
+        let mut data = encode_12bit(width, height, &samples);
+        assert!(is_extended_precision(&data));
+
+        // padding after the end of image is not consumed
+        let len = data.len();
+        data.push(0);
+
+        let (image, consumed) = decode(&data).unwrap();
+        assert_eq!(consumed, len);
+        assert_eq!((image.width, image.height), (21, 13));
+        assert_eq!(image.components, 1);
+        assert_eq!(image.samples.len(), samples.len());
+        let max_error = image
+            .samples
+            .iter()
+            .zip(&samples)
+            .map(|(&a, &b)| a.abs_diff(b))
+            .max()
+            .unwrap();
+        assert!(max_error <= 2, "max error was {}", max_error);
+    }
+
+    #[test]
+    fn extended_precision_only_for_12bit_sequential() {
+        // 8-bit baseline frame header
+        let data = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0, 4, 0, 0, 0xFF, 0xC0, 0, 11, 8, 0, 1, 0, 1, 1, 1, 0x11, 0,
+        ];
+        assert!(!is_extended_precision(&data));
+        // 12-bit progressive frame header
+        let data = [0xFF, 0xD8, 0xFF, 0xC2, 0, 11, 12, 0, 1, 0, 1, 1, 1, 0x11, 0];
+        assert!(!is_extended_precision(&data));
+        assert!(!is_extended_precision(&[0, 1, 2]));
+    }
+}
@@ -0,0 +1,1996 @@
+//! Support for JPEG-LS image decoding and encoding.
+//!
+//! This adapter decodes JPEG-LS Lossless Image Compression
+//! and JPEG-LS Lossy (Near-Lossless) Image Compression,
+//! with one image per frame.
+//! The codec is a pure Rust implementation of ITU-T T.87
+//! (ISO/IEC 14495-1),
+//! supporting 2 to 16 bits per sample,
+//! any number of components
+//! and all three interleave modes.
+//! Mapping tables and restart intervals are not supported.
+//!
+//! Encoding is always lossless,
+//! which is also valid for the near-lossless transfer syntax.
+
+use super::{MissingAttributeSnafu, WriteOutputSnafu};
+use crate::adapters::{
+    decode_frame_in_full, fragments::frame_data, DecodeResult, EncodeError, EncodeOptions,
+    EncodeResult, PixelDataObject, PixelRWAdapter,
+};
+use snafu::{ensure, whatever, OptionExt, ResultExt, Snafu};
+use std::io::Write;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JpegLsAdapter;
+
+/// Decode TS: 1.2.840.10008.1.2.4.80 (JPEG-LS Lossless)
+/// and 1.2.840.10008.1.2.4.81 (JPEG-LS Near-Lossless)
+impl PixelRWAdapter for JpegLsAdapter {
+    /// Decode the DICOM image from JPEG-LS completely.
+    fn decode(&self, src: &dyn PixelDataObject, dst: &mut Vec<u8>) -> DecodeResult<()> {
+        dst.clear();
+        self.decode_to_writer(src, dst)
+    }
+
+    /// Decode the DICOM image from JPEG-LS,
+    /// one frame at a time.
+    fn decode_to_writer(&self, src: &dyn PixelDataObject, to: &mut dyn Write) -> DecodeResult<()> {
+        let cols = src
+            .cols()
+            .context(MissingAttributeSnafu { name: "Columns" })?;
+        let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+        let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+            name: "SamplesPerPixel",
+        })?;
+        let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+            name: "BitsAllocated",
+        })?;
+
+        if bits_allocated != 8 && bits_allocated != 16 {
+            whatever!("BitsAllocated other than 8 or 16 is not supported");
+        }
+
+        let nr_frames = src.number_of_frames().unwrap_or(1) as usize;
+        let bytes_per_sample = bits_allocated as usize / 8;
+        let frame_size =
+            cols as usize * rows as usize * samples_per_pixel as usize * bytes_per_sample;
+        if frame_size == 0 {
+            return Ok(());
+        }
+
+        // images may span multiple fragments,
+        // so all fragments are collected into a single buffer
+        let data: Vec<u8> = src
+            .raw_pixel_data()
+            .whatever_context("Expected to have raw pixel data available")?
+            .fragments
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let mut frame = vec![0; frame_size];
+        let mut position = 0;
+        for i in 0..nr_frames {
+            position += decode_image(
+                &data[position..],
+                i,
+                (cols, rows, samples_per_pixel),
+                bytes_per_sample,
+                &mut frame,
+            )?;
+            to.write_all(&frame).context(WriteOutputSnafu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decode a single frame of the DICOM image from JPEG-LS.
+    ///
+    /// Only the fragments of the requested frame are decoded
+    /// if they can be identified,
+    /// which is the case for objects with one fragment per frame
+    /// or with a basic offset table.
+    fn decode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        let data = match frame_data(src, frame) {
+            Some(data) => data,
+            None => return decode_frame_in_full(self, src, frame, dst),
+        };
+
+        let cols = src
+            .cols()
+            .context(MissingAttributeSnafu { name: "Columns" })?;
+        let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+        let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+            name: "SamplesPerPixel",
+        })?;
+        let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+            name: "BitsAllocated",
+        })?;
+
+        if bits_allocated != 8 && bits_allocated != 16 {
+            whatever!("BitsAllocated other than 8 or 16 is not supported");
+        }
+        let bytes_per_sample = bits_allocated as usize / 8;
+        let frame_size =
+            cols as usize * rows as usize * samples_per_pixel as usize * bytes_per_sample;
+
+        let start = dst.len();
+        dst.resize(start + frame_size, 0);
+        if frame_size == 0 {
+            return Ok(());
+        }
+        decode_image(
+            &data,
+            frame as usize,
+            (cols, rows, samples_per_pixel),
+            bytes_per_sample,
+            &mut dst[start..],
+        )?;
+        Ok(())
+    }
+
+    /// Encode the DICOM image into JPEG-LS,
+    /// with the fragments of all frames concatenated.
+    ///
+    /// Since each frame is encoded into its own fragment,
+    /// prefer [`encode_frame`](PixelRWAdapter::encode_frame)
+    /// to retain the boundaries between fragments.
+    fn encode(
+        &self,
+        src: &dyn PixelDataObject,
+        options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        let nr_frames = src.number_of_frames().unwrap_or(1);
+        for frame in 0..nr_frames {
+            self.encode_frame(src, frame.into(), options.clone(), dst)?;
+        }
+        Ok(())
+    }
+
+    /// Encode a single frame of the DICOM image
+    /// into one JPEG-LS lossless fragment.
+    ///
+    /// Multi-component images are encoded in line interleaved mode.
+    /// The sample precision is the smallest one
+    /// which fits all samples of the frame.
+    fn encode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        _options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        use super::encode_error::MissingAttributeSnafu;
+
+        let cols = src
+            .cols()
+            .context(MissingAttributeSnafu { name: "Columns" })?;
+        let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+        let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+            name: "SamplesPerPixel",
+        })?;
+        let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+            name: "BitsAllocated",
+        })?;
+        let planar_configuration = src.planar_configuration().unwrap_or(0);
+
+        if bits_allocated != 8 && bits_allocated != 16 {
+            return Err(EncodeError::CustomEncodeError {
+                message: "BitsAllocated other than 8 or 16 is not supported",
+            });
+        }
+        if cols == 0 || rows == 0 || samples_per_pixel == 0 || samples_per_pixel > 255 {
+            return Err(EncodeError::CustomEncodeError {
+                message: "Image dimensions not supported by JPEG-LS",
+            });
+        }
+
+        // native pixel data is held in a single value, not in fragments
+        if src.fragment(0).is_some() {
+            return Err(EncodeError::NotNative);
+        }
+        let data = src
+            .raw_pixel_data()
+            .and_then(|raw| raw.fragments.into_iter().next())
+            .ok_or(EncodeError::MissingAttribute { name: "PixelData" })?;
+
+        let bytes_per_sample = bits_allocated as usize / 8;
+        let components = samples_per_pixel as usize;
+        let nr_pixels = rows as usize * cols as usize;
+        let frame_size = nr_pixels * components * bytes_per_sample;
+        let frame_start = frame as usize * frame_size;
+        let frame_data = data.get(frame_start..frame_start + frame_size).ok_or(
+            EncodeError::CustomEncodeError {
+                message: "Frame out of bounds",
+            },
+        )?;
+
+        let plane_samples: Vec<u16> = if bytes_per_sample == 1 {
+            frame_data.iter().map(|&b| u16::from(b)).collect()
+        } else {
+            frame_data
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect()
+        };
+        // the codec takes the samples of each pixel contiguous
+        let samples = if planar_configuration == 0 || components == 1 {
+            plane_samples
+        } else {
+            (0..nr_pixels * components)
+                .map(|i| plane_samples[(i % components) * nr_pixels + i / components])
+                .collect()
+        };
+
+        let max_sample = samples.iter().copied().max().unwrap_or(0);
+        let precision = bit_length(i32::from(max_sample)).max(2) as u8;
+        let image = Image {
+            width: cols,
+            height: rows,
+            components,
+            samples,
+        };
+        let interleave = if components > 1 { 1 } else { 0 };
+        let fragment = encode(&image, precision, 0, interleave);
+        let odd = fragment.len() & 1 != 0;
+        dst.extend(fragment);
+        // keep fragments at an even length
+        if odd {
+            dst.push(0);
+        }
+        Ok(())
+    }
+}
+
+/// Decode the first JPEG-LS image in `data` into `frame`,
+/// which must have the exact size of the decoded image.
+///
+/// `dimensions` are the expected columns, rows, and samples per pixel,
+/// and `i` is the frame number, for error reporting.
+/// Returns the position in `data` right after the end of the image.
+fn decode_image(
+    data: &[u8],
+    i: usize,
+    dimensions: (u16, u16, u16),
+    bytes_per_sample: usize,
+    frame: &mut [u8],
+) -> DecodeResult<usize> {
+    let (cols, rows, samples_per_pixel) = dimensions;
+
+    // skip padding until the start of the next image
+    let start = data.windows(2).position(|w| w == [0xFF, 0xD8]);
+    let start = match start {
+        Some(start) => start,
+        None => whatever!("No JPEG-LS image found for frame #{}", i),
+    };
+
+    let (image, len) = decode(&data[start..])
+        .map_err(|e| Box::new(e) as Box<_>)
+        .whatever_context("JPEG-LS decoder failure")?;
+
+    if (image.width, image.height, image.components) != (cols, rows, samples_per_pixel as usize) {
+        whatever!(
+            "JPEG-LS frame #{} is {}x{} with {} components, expected {}x{} with {}",
+            i,
+            image.width,
+            image.height,
+            image.components,
+            cols,
+            rows,
+            samples_per_pixel
+        );
+    }
+
+    if bytes_per_sample == 1 {
+        for (out, sample) in frame.iter_mut().zip(image.samples) {
+            *out = sample as u8;
+        }
+    } else {
+        for (out, sample) in frame.chunks_exact_mut(2).zip(image.samples) {
+            out.copy_from_slice(&sample.to_le_bytes());
+        }
+    }
+    Ok(start + len)
+}
+
+#[derive(Debug, Snafu)]
+enum Error {
+    #[snafu(display("Invalid JPEG-LS data: {}", message))]
+    Format { message: &'static str },
+    #[snafu(display("Unsupported JPEG-LS feature: {}", feature))]
+    Unsupported { feature: &'static str },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Run length order for each run index (T.87 A.7.1.1)
+const J: [u32; 32] = [
+    0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 9, 10, 11, 12, 13,
+    14, 15,
+];
+
+/// Default gradient thresholds for 8-bit samples (T.87 C.2.4.1.1.1)
+const BASIC_T1: i32 = 3;
+const BASIC_T2: i32 = 7;
+const BASIC_T3: i32 = 21;
+
+/// A decoded image.
+#[derive(Debug)]
+struct Image {
+    width: u16,
+    height: u16,
+    components: usize,
+    /// The decoded samples, with the components of each pixel contiguous
+    samples: Vec<u16>,
+}
+
+/// The coding parameters of a scan.
+#[derive(Debug, Copy, Clone)]
+struct Parameters {
+    max_val: i32,
+    near: i32,
+    t1: i32,
+    t2: i32,
+    t3: i32,
+    reset: i32,
+    range: i32,
+    qbpp: u32,
+    limit: u32,
+}
+
+impl Parameters {
+    /// Derive the coding parameters for the given sample precision,
+    /// near-lossless bound and preset parameters,
+    /// where zero values in the preset parameters select the defaults
+    /// (T.87 C.2.4.1.1).
+    fn new(precision: u8, near: i32, preset: [i32; 5]) -> Result<Self> {
+        let [max_val, t1, t2, t3, reset] = preset;
+        let max_val = if max_val == 0 {
+            (1 << precision) - 1
+        } else {
+            max_val
+        };
+        ensure!(
+            near >= 0 && near <= (max_val / 2).min(255),
+            FormatSnafu {
+                message: "invalid near-lossless bound"
+            }
+        );
+
+        let clamp = |i: i32, j: i32| if i > max_val || i < j { j } else { i };
+        let (default_t1, default_t2, default_t3) = if max_val >= 128 {
+            let factor = (max_val.min(4095) + 128) / 256;
+            let t1 = clamp(factor * (BASIC_T1 - 2) + 2 + 3 * near, near + 1);
+            let t2 = clamp(factor * (BASIC_T2 - 3) + 3 + 5 * near, t1);
+            let t3 = clamp(factor * (BASIC_T3 - 4) + 4 + 7 * near, t2);
+            (t1, t2, t3)
+        } else {
+            let factor = 256 / (max_val + 1);
+            let t1 = clamp((BASIC_T1 / factor).max(2) + 3 * near, near + 1);
+            let t2 = clamp((BASIC_T2 / factor).max(3) + 5 * near, t1);
+            let t3 = clamp((BASIC_T3 / factor).max(4) + 7 * near, t2);
+            (t1, t2, t3)
+        };
+        let t1 = if t1 == 0 { default_t1 } else { t1 };
+        let t2 = if t2 == 0 { default_t2 } else { t2 };
+        let t3 = if t3 == 0 { default_t3 } else { t3 };
+        let reset = if reset == 0 { 64 } else { reset };
+
+        let range = (max_val + 2 * near) / (2 * near + 1) + 1;
+        let qbpp = bit_length(range - 1);
+        let bpp = bit_length(max_val).max(2);
+        let limit = 2 * (bpp + bpp.max(8));
+
+        Ok(Parameters {
+            max_val,
+            near,
+            t1,
+            t2,
+            t3,
+            reset,
+            range,
+            qbpp,
+            limit,
+        })
+    }
+
+    fn quantize_gradient(&self, d: i32) -> i32 {
+        if d <= -self.t3 {
+            -4
+        } else if d <= -self.t2 {
+            -3
+        } else if d <= -self.t1 {
+            -2
+        } else if d < -self.near {
+            -1
+        } else if d <= self.near {
+            0
+        } else if d < self.t1 {
+            1
+        } else if d < self.t2 {
+            2
+        } else if d < self.t3 {
+            3
+        } else {
+            4
+        }
+    }
+
+    fn clamp(&self, value: i32) -> i32 {
+        value.max(0).min(self.max_val)
+    }
+
+    /// Quantize a prediction error
+    /// according to the near-lossless bound (T.87 A.4.4).
+    fn quantize_error(&self, error: i32) -> i32 {
+        let step = 2 * self.near + 1;
+        if error > 0 {
+            (self.near + error) / step
+        } else {
+            -(self.near - error) / step
+        }
+    }
+
+    /// Reduce a quantized prediction error
+    /// to the range of the error mapping (T.87 A.4.5).
+    fn reduce_error(&self, error: i32) -> i32 {
+        let error = if error < 0 { error + self.range } else { error };
+        if error >= (self.range + 1) / 2 {
+            error - self.range
+        } else {
+            error
+        }
+    }
+
+    /// Reconstruct a sample from its prediction and prediction error,
+    /// with modular reduction (T.87 A.4.2).
+    fn reconstruct(&self, predicted: i32, error: i32) -> i32 {
+        let step = 2 * self.near + 1;
+        let mut value = predicted + error * step;
+        if value < -self.near {
+            value += self.range * step;
+        } else if value > self.max_val + self.near {
+            value -= self.range * step;
+        }
+        self.clamp(value)
+    }
+}
+
+/// The number of bits needed to represent the given value.
+fn bit_length(value: i32) -> u32 {
+    32 - (value.max(0) as u32).leading_zeros()
+}
+
+/// The variables of a regular mode context.
+#[derive(Debug, Copy, Clone)]
+struct Context {
+    a: i32,
+    b: i32,
+    c: i32,
+    n: i32,
+}
+
+/// The variables of a run interruption context.
+#[derive(Debug, Copy, Clone)]
+struct RunContext {
+    a: i32,
+    n: i32,
+    nn: i32,
+}
+
+impl Context {
+    /// The Golomb coding variable of the context (T.87 A.5.1).
+    fn golomb_k(&self) -> u32 {
+        let mut k = 0;
+        while (self.n << k) < self.a {
+            k += 1;
+        }
+        k
+    }
+
+    /// Update the context variables
+    /// with the prediction error of a sample (T.87 A.6).
+    fn update(&mut self, error: i32, params: &Parameters) {
+        self.b += error * (2 * params.near + 1);
+        self.a += error.abs();
+        if self.n == params.reset {
+            self.a >>= 1;
+            self.b >>= 1;
+            self.n >>= 1;
+        }
+        self.n += 1;
+        if self.b <= -self.n {
+            self.b += self.n;
+            if self.c > -128 {
+                self.c -= 1;
+            }
+            if self.b <= -self.n {
+                self.b = -self.n + 1;
+            }
+        } else if self.b > 0 {
+            self.b -= self.n;
+            if self.c < 127 {
+                self.c += 1;
+            }
+            if self.b > 0 {
+                self.b = 0;
+            }
+        }
+    }
+}
+
+impl RunContext {
+    /// The Golomb coding variable of the context
+    /// for the given run interruption type (T.87 A.7.2.1).
+    fn golomb_k(&self, ri_type: usize) -> u32 {
+        let temp = if ri_type == 0 {
+            self.a
+        } else {
+            self.a + (self.n >> 1)
+        };
+        let mut k = 0;
+        while (self.n << k) < temp {
+            k += 1;
+        }
+        k
+    }
+
+    /// Update the context variables
+    /// with the prediction error of a run interruption sample
+    /// and its mapped value (T.87 A.7.2.2).
+    fn update(&mut self, error: i32, mapped: i32, ri_type: usize, params: &Parameters) {
+        if error < 0 {
+            self.nn += 1;
+        }
+        self.a += (mapped + 1 - ri_type as i32) >> 1;
+        if self.n == params.reset {
+            self.a >>= 1;
+            self.n >>= 1;
+            self.nn >>= 1;
+        }
+        self.n += 1;
+    }
+}
+
+/// The median edge detector (T.87 A.4.1).
+fn predict(ra: i32, rb: i32, rc: i32) -> i32 {
+    if rc >= ra.max(rb) {
+        ra.min(rb)
+    } else if rc <= ra.min(rb) {
+        ra.max(rb)
+    } else {
+        ra + rb - rc
+    }
+}
+
+/// The state of the coding process of a scan.
+struct State {
+    params: Parameters,
+    contexts: Vec<Context>,
+    run_contexts: [RunContext; 2],
+}
+
+impl State {
+    fn new(params: Parameters) -> Self {
+        let a = ((params.range + 32) / 64).max(2);
+        State {
+            params,
+            contexts: vec![
+                Context {
+                    a,
+                    b: 0,
+                    c: 0,
+                    n: 1
+                };
+                365
+            ],
+            run_contexts: [RunContext { a, n: 1, nn: 0 }; 2],
+        }
+    }
+
+    /// Compute the context index and sign of the local gradients,
+    /// or `None` if run mode should be used.
+    fn context_of(&self, ra: i32, rb: i32, rc: i32, rd: i32) -> Option<(usize, i32)> {
+        let q1 = self.params.quantize_gradient(rd - rb);
+        let q2 = self.params.quantize_gradient(rb - rc);
+        let q3 = self.params.quantize_gradient(rc - ra);
+        let q = (q1 * 9 + q2) * 9 + q3;
+        if q == 0 {
+            None
+        } else if q < 0 {
+            Some((-q as usize, -1))
+        } else {
+            Some((q as usize, 1))
+        }
+    }
+
+    /// Decode a sample in regular mode (T.87 A.4 to A.6).
+    fn decode_regular(
+        &mut self,
+        reader: &mut BitReader,
+        ra: i32,
+        rb: i32,
+        rc: i32,
+        (q, sign): (usize, i32),
+    ) -> Result<i32> {
+        let params = self.params;
+        let ctx = &mut self.contexts[q];
+        let predicted = params.clamp(predict(ra, rb, rc) + sign * ctx.c);
+        let k = ctx.golomb_k();
+
+        let mapped = reader.read_golomb(k, params.limit, params.qbpp)?;
+        let mut error = if mapped & 1 == 0 {
+            mapped / 2
+        } else {
+            -(mapped + 1) / 2
+        };
+        if k == 0 && params.near == 0 && 2 * ctx.b <= -ctx.n {
+            error = -error - 1;
+        }
+
+        ctx.update(error, &params);
+        Ok(params.reconstruct(predicted, sign * error))
+    }
+
+    /// Decode the prediction error of a run interruption sample
+    /// (T.87 A.7.2).
+    fn decode_interruption_error(
+        &mut self,
+        reader: &mut BitReader,
+        ri_type: usize,
+        run_index: usize,
+    ) -> Result<i32> {
+        let params = self.params;
+        let ctx = &mut self.run_contexts[ri_type];
+        let k = ctx.golomb_k(ri_type);
+
+        let limit = params.limit - J[run_index] - 1;
+        let mapped = reader.read_golomb(k, limit, params.qbpp)?;
+        let t = mapped + ri_type as i32;
+        let map = t & 1 == 1;
+        let abs = (t + map as i32) / 2;
+        let error = if (k != 0 || 2 * ctx.nn >= ctx.n) == map {
+            -abs
+        } else {
+            abs
+        };
+        ctx.update(error, mapped, ri_type, &params);
+        Ok(error)
+    }
+
+    /// Decode the length of a run of `remaining` samples at most,
+    /// updating the run index (T.87 A.7.1.2).
+    ///
+    /// Returns the run length
+    /// and whether the run was interrupted before the end of the line.
+    fn decode_run_length(
+        &mut self,
+        reader: &mut BitReader,
+        run_index: &mut usize,
+        remaining: usize,
+    ) -> Result<(usize, bool)> {
+        let mut length = 0;
+        while reader.read_bit()? == 1 {
+            let count = (1_usize << J[*run_index]).min(remaining - length);
+            length += count;
+            if count == 1 << J[*run_index] && *run_index < 31 {
+                *run_index += 1;
+            }
+            if length == remaining {
+                return Ok((length, false));
+            }
+        }
+        if J[*run_index] > 0 {
+            length += reader.read_bits(J[*run_index])? as usize;
+        }
+        ensure!(
+            length < remaining,
+            FormatSnafu {
+                message: "run length exceeds line"
+            }
+        );
+        Ok((length, true))
+    }
+
+    /// Encode a sample in regular mode,
+    /// returning the reconstructed sample.
+    fn encode_regular(
+        &mut self,
+        writer: &mut BitWriter,
+        value: i32,
+        ra: i32,
+        rb: i32,
+        rc: i32,
+        (q, sign): (usize, i32),
+    ) -> i32 {
+        let params = self.params;
+        let ctx = &mut self.contexts[q];
+        let predicted = params.clamp(predict(ra, rb, rc) + sign * ctx.c);
+        let error = params.quantize_error(sign * (value - predicted));
+        let reconstructed = params.reconstruct(predicted, sign * error);
+        let error = params.reduce_error(error);
+
+        let k = ctx.golomb_k();
+        let mapped = if params.near == 0 && k == 0 && 2 * ctx.b <= -ctx.n {
+            if error >= 0 {
+                2 * error + 1
+            } else {
+                -2 * (error + 1)
+            }
+        } else if error >= 0 {
+            2 * error
+        } else {
+            -2 * error - 1
+        };
+        writer.write_golomb(k, mapped, params.limit, params.qbpp);
+        ctx.update(error, &params);
+        reconstructed
+    }
+
+    /// Encode a run interruption sample,
+    /// returning the reconstructed sample.
+    fn encode_interruption(
+        &mut self,
+        writer: &mut BitWriter,
+        value: i32,
+        ra: i32,
+        rb: i32,
+        ri_type: usize,
+        run_index: usize,
+    ) -> i32 {
+        let params = self.params;
+        let (predicted, sign) = if ri_type == 1 {
+            (ra, 1)
+        } else {
+            (rb, sign(rb - ra))
+        };
+        let error = params.quantize_error(sign * (value - predicted));
+        let reconstructed = params.reconstruct(predicted, sign * error);
+        let error = params.reduce_error(error);
+
+        let ctx = &mut self.run_contexts[ri_type];
+        let k = ctx.golomb_k(ri_type);
+        let map = (k == 0 && error > 0 && 2 * ctx.nn < ctx.n)
+            || (error < 0 && (2 * ctx.nn >= ctx.n || k != 0));
+        let mapped = 2 * error.abs() - ri_type as i32 - map as i32;
+        let limit = params.limit - J[run_index] - 1;
+        writer.write_golomb(k, mapped, limit, params.qbpp);
+        ctx.update(error, mapped, ri_type, &params);
+        reconstructed
+    }
+
+    /// Encode the length of a run,
+    /// `end` being whether the run reaches the end of the line.
+    fn encode_run_length(
+        &mut self,
+        writer: &mut BitWriter,
+        mut length: usize,
+        end: bool,
+        run_index: &mut usize,
+    ) {
+        while length >= 1 << J[*run_index] {
+            writer.write_bits(1, 1);
+            length -= 1 << J[*run_index];
+            if *run_index < 31 {
+                *run_index += 1;
+            }
+        }
+        if end {
+            if length > 0 {
+                writer.write_bits(1, 1);
+            }
+        } else {
+            writer.write_bits(0, 1);
+            writer.write_bits(length as u32, J[*run_index]);
+        }
+    }
+}
+
+/// Reader of JPEG-LS entropy-coded data,
+/// where each 0xFF byte is followed by a stuffed zero bit.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    byte: u8,
+    bits_left: u32,
+    previous_ff: bool,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            position: 0,
+            byte: 0,
+            bits_left: 0,
+            previous_ff: false,
+        }
+    }
+
+    fn read_bit(&mut self) -> Result<u32> {
+        if self.bits_left == 0 {
+            let byte = *self.data.get(self.position).context(FormatSnafu {
+                message: "unexpected end of scan data",
+            })?;
+            if self.previous_ff {
+                ensure!(
+                    byte & 0x80 == 0,
+                    FormatSnafu {
+                        message: "unexpected marker in scan data"
+                    }
+                );
+                self.bits_left = 7;
+            } else {
+                self.bits_left = 8;
+            }
+            self.previous_ff = byte == 0xFF;
+            self.byte = byte;
+            self.position += 1;
+        }
+        self.bits_left -= 1;
+        Ok(u32::from((self.byte >> self.bits_left) & 1))
+    }
+
+    fn read_bits(&mut self, count: u32) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Ok(value)
+    }
+
+    /// Read a value in limited length Golomb code (T.87 A.5.3).
+    fn read_golomb(&mut self, k: u32, limit: u32, qbpp: u32) -> Result<i32> {
+        let mut high_bits = 0;
+        while self.read_bit()? == 0 {
+            high_bits += 1;
+            ensure!(
+                high_bits < limit,
+                FormatSnafu {
+                    message: "invalid Golomb code"
+                }
+            );
+        }
+        if high_bits >= limit - qbpp - 1 {
+            return Ok(self.read_bits(qbpp)? as i32 + 1);
+        }
+        Ok(((high_bits << k) | self.read_bits(k)?) as i32)
+    }
+
+    /// The position right after the scan data,
+    /// where the next marker is expected.
+    fn end_position(&self) -> usize {
+        let mut position = self.position;
+        // skip any remaining bytes until a marker
+        while position + 1 < self.data.len()
+            && !(self.data[position] == 0xFF && self.data[position + 1] & 0x80 != 0)
+        {
+            position += 1;
+        }
+        position
+    }
+}
+
+/// Writer of JPEG-LS entropy-coded data,
+/// where each 0xFF byte is followed by a stuffed zero bit.
+#[derive(Debug, Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    byte: u8,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, count: u32) {
+        for i in (0..count).rev() {
+            let bit = value.checked_shr(i).unwrap_or(0) & 1;
+            self.byte = (self.byte << 1) | bit as u8;
+            self.bits += 1;
+            let capacity = if self.out.last() == Some(&0xFF) { 7 } else { 8 };
+            if self.bits == capacity {
+                self.out.push(self.byte);
+                self.byte = 0;
+                self.bits = 0;
+            }
+        }
+    }
+
+    /// Write a value in limited length Golomb code (T.87 A.5.3).
+    fn write_golomb(&mut self, k: u32, value: i32, limit: u32, qbpp: u32) {
+        let high_bits = (value >> k) as u32;
+        if high_bits < limit - qbpp - 1 {
+            self.write_bits(0, high_bits);
+            self.write_bits(1, 1);
+            self.write_bits(value as u32, k);
+        } else {
+            self.write_bits(0, limit - qbpp - 1);
+            self.write_bits(1, 1);
+            self.write_bits(value as u32 - 1, qbpp);
+        }
+    }
+
+    /// Pad the last byte with zero bits and retrieve the written data.
+    fn finish(mut self) -> Vec<u8> {
+        while self.bits != 0 {
+            self.write_bits(0, 1);
+        }
+        if self.out.last() == Some(&0xFF) {
+            // complete the bit stuffing of a trailing 0xFF byte
+            self.out.push(0);
+        }
+        self.out
+    }
+}
+
+/// The header of a JPEG-LS frame.
+#[derive(Debug, Default)]
+struct Frame {
+    precision: u8,
+    width: u16,
+    height: u16,
+    component_ids: Vec<u8>,
+}
+
+/// Decode a JPEG-LS image.
+///
+/// Returns the image and the number of bytes read,
+/// up to and including the end of image marker.
+fn decode(data: &[u8]) -> Result<(Image, usize)> {
+    ensure!(
+        data.starts_with(&[0xFF, 0xD8]),
+        FormatSnafu {
+            message: "missing start of image"
+        }
+    );
+
+    let mut frame: Option<Frame> = None;
+    let mut samples = Vec::new();
+    let mut preset = [0; 5];
+    let mut pos = 2;
+    loop {
+        ensure!(
+            pos + 1 < data.len(),
+            FormatSnafu {
+                message: "unexpected end of data"
+            }
+        );
+        if data[pos] != 0xFF {
+            // tolerate garbage between segments
+            pos += 1;
+            continue;
+        }
+        let marker = data[pos + 1];
+        pos += 2;
+        match marker {
+            // fill bytes
+            0xFF => pos -= 1,
+            // end of image
+            0xD9 => break,
+            _ => {
+                ensure!(
+                    pos + 2 <= data.len(),
+                    FormatSnafu {
+                        message: "unexpected end of data"
+                    }
+                );
+                let len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+                ensure!(
+                    len >= 2 && pos + len <= data.len(),
+                    FormatSnafu {
+                        message: "invalid segment length"
+                    }
+                );
+                let segment = &data[pos + 2..pos + len];
+                pos += len;
+                match marker {
+                    // start of frame, JPEG-LS
+                    0xF7 => {
+                        let header = read_frame_header(segment)?;
+                        samples = vec![
+                            0;
+                            header.width as usize
+                                * header.height as usize
+                                * header.component_ids.len()
+                        ];
+                        frame = Some(header);
+                    }
+                    // other frame types
+                    0xC0..=0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                        return UnsupportedSnafu {
+                            feature: "frame type other than JPEG-LS",
+                        }
+                        .fail()
+                    }
+                    // JPEG-LS preset parameters
+                    0xF8 => read_preset_parameters(segment, &mut preset)?,
+                    // define restart interval
+                    0xDD => {
+                        let interval = segment.iter().any(|&b| b != 0);
+                        ensure!(
+                            !interval,
+                            UnsupportedSnafu {
+                                feature: "restart intervals"
+                            }
+                        );
+                    }
+                    // start of scan
+                    0xDA => {
+                        let frame = frame.as_ref().context(FormatSnafu {
+                            message: "start of scan before frame header",
+                        })?;
+                        let len = decode_scan(frame, segment, &data[pos..], preset, &mut samples)?;
+                        pos += len;
+                    }
+                    // application data, comments and other segments
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let frame = frame.context(FormatSnafu {
+        message: "missing frame header",
+    })?;
+    Ok((
+        Image {
+            width: frame.width,
+            height: frame.height,
+            components: frame.component_ids.len(),
+            samples,
+        },
+        pos,
+    ))
+}
+
+fn read_frame_header(segment: &[u8]) -> Result<Frame> {
+    ensure!(
+        segment.len() >= 6,
+        FormatSnafu {
+            message: "invalid frame header"
+        }
+    );
+    let precision = segment[0];
+    let height = u16::from_be_bytes([segment[1], segment[2]]);
+    let width = u16::from_be_bytes([segment[3], segment[4]]);
+    let nr_components = segment[5] as usize;
+    ensure!(
+        (2..=16).contains(&precision),
+        FormatSnafu {
+            message: "invalid sample precision"
+        }
+    );
+    ensure!(
+        height > 0 && width > 0,
+        UnsupportedSnafu {
+            feature: "image dimensions defined in preset parameters"
+        }
+    );
+    ensure!(
+        nr_components > 0 && segment.len() >= 6 + 3 * nr_components,
+        FormatSnafu {
+            message: "invalid frame header"
+        }
+    );
+    let component_ids = (0..nr_components).map(|i| segment[6 + 3 * i]).collect();
+    Ok(Frame {
+        precision,
+        width,
+        height,
+        component_ids,
+    })
+}
+
+fn read_preset_parameters(segment: &[u8], preset: &mut [i32; 5]) -> Result<()> {
+    match segment.first() {
+        Some(1) => {
+            ensure!(
+                segment.len() >= 11,
+                FormatSnafu {
+                    message: "invalid preset parameters"
+                }
+            );
+            for (i, value) in preset.iter_mut().enumerate() {
+                *value = i32::from(u16::from_be_bytes([segment[1 + 2 * i], segment[2 + 2 * i]]));
+            }
+            Ok(())
+        }
+        Some(2) | Some(3) => UnsupportedSnafu {
+            feature: "mapping tables",
+        }
+        .fail(),
+        _ => UnsupportedSnafu {
+            feature: "preset parameters other than coding parameters",
+        }
+        .fail(),
+    }
+}
+
+/// Decode one scan into the interleaved samples of the image.
+///
+/// Returns the number of bytes of entropy-coded data.
+fn decode_scan(
+    frame: &Frame,
+    header: &[u8],
+    data: &[u8],
+    preset: [i32; 5],
+    samples: &mut [u16],
+) -> Result<usize> {
+    ensure!(
+        !header.is_empty(),
+        FormatSnafu {
+            message: "invalid scan header"
+        }
+    );
+    let nr_scan_components = header[0] as usize;
+    ensure!(
+        nr_scan_components > 0 && header.len() >= 1 + 2 * nr_scan_components + 3,
+        FormatSnafu {
+            message: "invalid scan header"
+        }
+    );
+    let mut components = Vec::with_capacity(nr_scan_components);
+    for i in 0..nr_scan_components {
+        let id = header[1 + 2 * i];
+        let mapping_table = header[2 + 2 * i];
+        ensure!(
+            mapping_table == 0,
+            UnsupportedSnafu {
+                feature: "mapping tables"
+            }
+        );
+        let index = frame
+            .component_ids
+            .iter()
+            .position(|&c| c == id)
+            .context(FormatSnafu {
+                message: "unknown component in scan",
+            })?;
+        components.push(index);
+    }
+    let rest = &header[1 + 2 * nr_scan_components..];
+    let near = i32::from(rest[0]);
+    let interleave = rest[1];
+    ensure!(
+        rest[2] & 0x0F == 0,
+        UnsupportedSnafu {
+            feature: "point transform"
+        }
+    );
+    ensure!(
+        interleave <= 2,
+        FormatSnafu {
+            message: "invalid interleave mode"
+        }
+    );
+    ensure!(
+        interleave != 0 || nr_scan_components == 1,
+        FormatSnafu {
+            message: "multiple components in non-interleaved scan"
+        }
+    );
+
+    let params = Parameters::new(frame.precision, near, preset)?;
+    let mut state = State::new(params);
+    let mut reader = BitReader::new(data);
+
+    let width = frame.width as usize;
+    let nr_components = frame.component_ids.len();
+    // previous and current line of each component,
+    // with one extra sample on each side
+    let mut previous = vec![vec![0_i32; width + 2]; nr_scan_components];
+    let mut current = vec![vec![0_i32; width + 2]; nr_scan_components];
+    let mut run_index = vec![0_usize; nr_scan_components];
+
+    for y in 0..frame.height as usize {
+        for c in 0..nr_scan_components {
+            // Ra of the first sample is the sample above it,
+            // Rd of the last sample is the sample above it
+            current[c][0] = previous[c][1];
+            previous[c][width + 1] = previous[c][width];
+        }
+
+        if interleave == 2 && nr_scan_components > 1 {
+            decode_line_interleaved(
+                &mut state,
+                &mut reader,
+                &previous,
+                &mut current,
+                &mut run_index[0],
+            )?;
+        } else {
+            for c in 0..nr_scan_components {
+                decode_line(
+                    &mut state,
+                    &mut reader,
+                    &previous[c],
+                    &mut current[c],
+                    &mut run_index[c],
+                )?;
+            }
+        }
+
+        for (c, &component) in components.iter().enumerate() {
+            for x in 0..width {
+                samples[(y * width + x) * nr_components + component] = current[c][x + 1] as u16;
+            }
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Ok(reader.end_position())
+}
+
+/// Decode a line of a single component.
+fn decode_line(
+    state: &mut State,
+    reader: &mut BitReader,
+    previous: &[i32],
+    current: &mut [i32],
+    run_index: &mut usize,
+) -> Result<()> {
+    let width = current.len() - 2;
+    let mut x = 1;
+    while x <= width {
+        let ra = current[x - 1];
+        let rb = previous[x];
+        let rc = previous[x - 1];
+        let rd = previous[x + 1];
+
+        match state.context_of(ra, rb, rc, rd) {
+            Some(context) => {
+                current[x] = state.decode_regular(reader, ra, rb, rc, context)?;
+                x += 1;
+            }
+            None => {
+                let (length, interrupted) =
+                    state.decode_run_length(reader, run_index, width + 1 - x)?;
+                for sample in &mut current[x..x + length] {
+                    *sample = ra;
+                }
+                x += length;
+                if interrupted {
+                    let rb = previous[x];
+                    let ri_type = ((ra - rb).abs() <= state.params.near) as usize;
+                    let error = state.decode_interruption_error(reader, ri_type, *run_index)?;
+                    current[x] = if ri_type == 1 {
+                        state.params.reconstruct(ra, error)
+                    } else {
+                        state.params.reconstruct(rb, error * sign(rb - ra))
+                    };
+                    *run_index = run_index.saturating_sub(1);
+                    x += 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode a line of all components in sample interleaved mode.
+fn decode_line_interleaved(
+    state: &mut State,
+    reader: &mut BitReader,
+    previous: &[Vec<i32>],
+    current: &mut [Vec<i32>],
+    run_index: &mut usize,
+) -> Result<()> {
+    let nr_components = current.len();
+    let width = current[0].len() - 2;
+    let mut contexts = Vec::with_capacity(nr_components);
+    let mut x = 1;
+    while x <= width {
+        contexts.clear();
+        for c in 0..nr_components {
+            let (ra, rb, rc, rd) = (
+                current[c][x - 1],
+                previous[c][x],
+                previous[c][x - 1],
+                previous[c][x + 1],
+            );
+            contexts.push(state.context_of(ra, rb, rc, rd));
+        }
+
+        if contexts.iter().any(Option::is_some) {
+            // regular mode for each component,
+            // with a zero gradient context when needed
+            for c in 0..nr_components {
+                let (ra, rb, rc) = (current[c][x - 1], previous[c][x], previous[c][x - 1]);
+                let context = contexts[c].unwrap_or((0, 1));
+                current[c][x] = state.decode_regular(reader, ra, rb, rc, context)?;
+            }
+            x += 1;
+            continue;
+        }
+
+        // run mode over whole pixels
+        let (length, interrupted) = state.decode_run_length(reader, run_index, width + 1 - x)?;
+        for line in current.iter_mut() {
+            let ra = line[x - 1];
+            for sample in &mut line[x..x + length] {
+                *sample = ra;
+            }
+        }
+        x += length;
+        if interrupted {
+            for c in 0..nr_components {
+                let ra = current[c][x - 1];
+                let rb = previous[c][x];
+                let error = state.decode_interruption_error(reader, 0, *run_index)?;
+                current[c][x] = state.params.reconstruct(rb, error * sign(rb - ra));
+            }
+            *run_index = run_index.saturating_sub(1);
+            x += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Encode an image into JPEG-LS
+/// with the given sample precision, near-lossless bound
+/// and interleave mode,
+/// using the default coding parameters.
+fn encode(image: &Image, precision: u8, near: u8, interleave: u8) -> Vec<u8> {
+    let width = image.width as usize;
+    let height = image.height as usize;
+    let components = image.components;
+
+    let mut out = vec![0xFF, 0xD8];
+    // start of frame, JPEG-LS
+    out.extend_from_slice(&[0xFF, 0xF7]);
+    out.extend_from_slice(&(8 + 3 * components as u16).to_be_bytes());
+    out.push(precision);
+    out.extend_from_slice(&image.height.to_be_bytes());
+    out.extend_from_slice(&image.width.to_be_bytes());
+    out.push(components as u8);
+    for c in 0..components {
+        out.extend_from_slice(&[c as u8 + 1, 0x11, 0]);
+    }
+
+    let scans: Vec<Vec<usize>> = if interleave == 0 {
+        (0..components).map(|c| vec![c]).collect()
+    } else {
+        vec![(0..components).collect()]
+    };
+    for scan in scans {
+        // start of scan
+        out.extend_from_slice(&[0xFF, 0xDA]);
+        out.extend_from_slice(&(6 + 2 * scan.len() as u16).to_be_bytes());
+        out.push(scan.len() as u8);
+        for &c in &scan {
+            out.extend_from_slice(&[c as u8 + 1, 0]);
+        }
+        out.extend_from_slice(&[near, interleave, 0]);
+
+        let params = Parameters::new(precision, i32::from(near), [0; 5])
+            .expect("near-lossless bound should be valid for the precision");
+        let mut state = State::new(params);
+        let mut writer = BitWriter::default();
+
+        let planes: Vec<Vec<i32>> = scan
+            .iter()
+            .map(|&c| {
+                image.samples[c..]
+                    .iter()
+                    .step_by(components)
+                    .map(|&s| i32::from(s))
+                    .collect()
+            })
+            .collect();
+        let mut previous = vec![vec![0_i32; width + 2]; scan.len()];
+        let mut current = vec![vec![0_i32; width + 2]; scan.len()];
+        let mut run_index = vec![0_usize; scan.len()];
+        for y in 0..height {
+            for c in 0..scan.len() {
+                current[c][0] = previous[c][1];
+                previous[c][width + 1] = previous[c][width];
+            }
+            let lines: Vec<&[i32]> = planes
+                .iter()
+                .map(|plane| &plane[y * width..(y + 1) * width])
+                .collect();
+            if interleave == 2 && scan.len() > 1 {
+                encode_line_interleaved(
+                    &mut state,
+                    &mut writer,
+                    &lines,
+                    &previous,
+                    &mut current,
+                    &mut run_index[0],
+                );
+            } else {
+                for c in 0..scan.len() {
+                    encode_line(
+                        &mut state,
+                        &mut writer,
+                        lines[c],
+                        &previous[c],
+                        &mut current[c],
+                        &mut run_index[c],
+                    );
+                }
+            }
+            std::mem::swap(&mut previous, &mut current);
+        }
+        out.extend(writer.finish());
+    }
+
+    // end of image
+    out.extend_from_slice(&[0xFF, 0xD9]);
+    out
+}
+
+/// Encode a line of a single component.
+fn encode_line(
+    state: &mut State,
+    writer: &mut BitWriter,
+    line: &[i32],
+    previous: &[i32],
+    current: &mut [i32],
+    run_index: &mut usize,
+) {
+    let width = line.len();
+    let near = state.params.near;
+    let mut x = 1;
+    while x <= width {
+        let ra = current[x - 1];
+        let rb = previous[x];
+        let rc = previous[x - 1];
+        let rd = previous[x + 1];
+
+        match state.context_of(ra, rb, rc, rd) {
+            Some(context) => {
+                current[x] = state.encode_regular(writer, line[x - 1], ra, rb, rc, context);
+                x += 1;
+            }
+            None => {
+                let start = x;
+                while x <= width && (line[x - 1] - ra).abs() <= near {
+                    current[x] = ra;
+                    x += 1;
+                }
+                state.encode_run_length(writer, x - start, x > width, run_index);
+                if x <= width {
+                    let rb = previous[x];
+                    let ri_type = ((ra - rb).abs() <= near) as usize;
+                    current[x] =
+                        state.encode_interruption(writer, line[x - 1], ra, rb, ri_type, *run_index);
+                    *run_index = run_index.saturating_sub(1);
+                    x += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Encode a line of all components in sample interleaved mode.
+fn encode_line_interleaved(
+    state: &mut State,
+    writer: &mut BitWriter,
+    lines: &[&[i32]],
+    previous: &[Vec<i32>],
+    current: &mut [Vec<i32>],
+    run_index: &mut usize,
+) {
+    let nr_components = lines.len();
+    let width = lines[0].len();
+    let near = state.params.near;
+    let mut contexts = Vec::with_capacity(nr_components);
+    let mut x = 1;
+    while x <= width {
+        contexts.clear();
+        for c in 0..nr_components {
+            let (ra, rb, rc, rd) = (
+                current[c][x - 1],
+                previous[c][x],
+                previous[c][x - 1],
+                previous[c][x + 1],
+            );
+            contexts.push(state.context_of(ra, rb, rc, rd));
+        }
+
+        if contexts.iter().any(Option::is_some) {
+            for c in 0..nr_components {
+                let (ra, rb, rc) = (current[c][x - 1], previous[c][x], previous[c][x - 1]);
+                let context = contexts[c].unwrap_or((0, 1));
+                current[c][x] = state.encode_regular(writer, lines[c][x - 1], ra, rb, rc, context);
+            }
+            x += 1;
+            continue;
+        }
+
+        // run mode over whole pixels
+        let start = x;
+        while x <= width
+            && (0..nr_components).all(|c| (lines[c][x - 1] - current[c][start - 1]).abs() <= near)
+        {
+            for line in current.iter_mut() {
+                line[x] = line[start - 1];
+            }
+            x += 1;
+        }
+        state.encode_run_length(writer, x - start, x > width, run_index);
+        if x <= width {
+            for c in 0..nr_components {
+                let (ra, rb) = (current[c][x - 1], previous[c][x]);
+                current[c][x] =
+                    state.encode_interruption(writer, lines[c][x - 1], ra, rb, 0, *run_index);
+            }
+            *run_index = run_index.saturating_sub(1);
+            x += 1;
+        }
+    }
+}
+
+fn sign(value: i32) -> i32 {
+    if value < 0 {
+        -1
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::testing::TestPixelData;
+
+    /// Encode interleaved samples with the given coding options.
+    fn encode_samples(
+        width: usize,
+        height: usize,
+        components: usize,
+        precision: u8,
+        near: u8,
+        interleave: u8,
+        samples: &[u16],
+    ) -> Vec<u8> {
+        let image = Image {
+            width: width as u16,
+            height: height as u16,
+            components,
+            samples: samples.to_vec(),
+        };
+        encode(&image, precision, near, interleave)
+    }
+
+    /// A test image with smooth regions, edges and noise.
+    fn test_image(width: usize, height: usize, components: usize, max_val: u32) -> Vec<u16> {
+        let mut seed = 0x2545_F491_u32;
+        (0..width * height * components)
+            .map(|i| {
+                let (x, y, c) = (
+                    (i / components) % width,
+                    i / components / width,
+                    i % components,
+                );
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let value = if y < height / 3 {
+                    // flat region
+                    (c as u32 * 40) % (max_val + 1)
+                } else if y < 2 * height / 3 {
+                    // gradient
+                    ((x * max_val as usize) / width) as u32
+                } else {
+                    seed % (max_val + 1)
+                };
+                value as u16
+            })
+            .collect()
+    }
+
+    fn check_roundtrip(components: usize, precision: u8, near: u8, interleave: u8) {
+        let (width, height) = (23, 17);
+        let max_val = (1_u32 << precision) - 1;
+        let samples = test_image(width, height, components, max_val);
+        let mut data = encode_samples(
+            width, height, components, precision, near, interleave, &samples,
+        );
+        let len = data.len();
+        data.push(0);
+
+        let (image, consumed) = decode(&data).unwrap();
+        assert_eq!(consumed, len);
+        assert_eq!((image.width, image.height), (23, 17));
+        assert_eq!(image.components, components);
+        assert_eq!(image.samples.len(), samples.len());
+        let max_error = image
+            .samples
+            .iter()
+            .zip(&samples)
+            .map(|(&a, &b)| a.abs_diff(b))
+            .max()
+            .unwrap();
+        assert!(
+            max_error <= u16::from(near),
+            "max error was {} with precision {}, NEAR {}, ILV {}",
+            max_error,
+            precision,
+            near,
+            interleave
+        );
+    }
+
+    /// Example image and bit stream from ITU-T T.87 Annex H.3
+    const T87_EXAMPLE: [u8; 57] = [
+        0xFF, 0xD8, 0xFF, 0xF7, 0x00, 0x0B, 0x08, 0x00, 0x04, 0x00, 0x04, 0x01, 0x01, 0x11, 0x00,
+        0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x6C, 0x80,
+        0x20, 0x8E, 0x01, 0xC0, 0x00, 0x00, 0x57, 0x40, 0x00, 0x00, 0x6E, 0xE6, 0x00, 0x00, 0x01,
+        0xBC, 0x18, 0x00, 0x00, 0x05, 0xD8, 0x00, 0x00, 0x91, 0x60, 0xFF, 0xD9,
+    ];
+    const T87_EXAMPLE_SAMPLES: [u16; 16] = [
+        0, 0, 90, 74, 68, 50, 43, 205, 64, 145, 145, 145, 100, 145, 145, 145,
+    ];
+
+    #[test]
+    fn decode_t87_example() {
+        let (image, consumed) = decode(&T87_EXAMPLE).unwrap();
+        assert_eq!(consumed, T87_EXAMPLE.len());
+        assert_eq!((image.width, image.height, image.components), (4, 4, 1));
+        assert_eq!(image.samples, T87_EXAMPLE_SAMPLES);
+    }
+
+    #[test]
+    fn encode_t87_example() {
+        let image = Image {
+            width: 4,
+            height: 4,
+            components: 1,
+            samples: T87_EXAMPLE_SAMPLES.to_vec(),
+        };
+        // the example frame header declares component 1
+        // with the same parameters as the encoder
+        assert_eq!(encode(&image, 8, 0, 0), T87_EXAMPLE);
+    }
+
+    #[test]
+    fn roundtrip_lossless() {
+        for &precision in &[8, 12, 16] {
+            check_roundtrip(1, precision, 0, 0);
+        }
+        check_roundtrip(1, 2, 0, 0);
+        for &interleave in &[0, 1, 2] {
+            check_roundtrip(3, 8, 0, interleave);
+        }
+    }
+
+    #[test]
+    fn roundtrip_near_lossless() {
+        for &near in &[1, 3, 10] {
+            check_roundtrip(1, 8, near, 0);
+            check_roundtrip(1, 12, near, 0);
+            for &interleave in &[1, 2] {
+                check_roundtrip(3, 8, near, interleave);
+            }
+        }
+    }
+
+    #[test]
+    fn reject_mapping_tables() {
+        let mut data = encode_samples(2, 2, 1, 8, 0, 0, &[1, 2, 3, 4]);
+        // set the mapping table selector of the scan component
+        let sos = data.windows(2).position(|w| w == [0xFF, 0xDA]).unwrap();
+        data[sos + 6] = 1;
+        assert!(matches!(decode(&data), Err(Error::Unsupported { .. })));
+    }
+
+    /// Checks against CharLS, as an independent implementation.
+    #[cfg(feature = "interop-tests")]
+    mod charls_interop {
+        use super::*;
+
+        /// Coding options (components, precision, NEAR, ILV) to check.
+        const CHARLS_CASES: [(usize, u8, u8, u8); 12] = [
+            (1, 2, 0, 0),
+            (1, 8, 0, 0),
+            (1, 12, 0, 0),
+            (1, 16, 0, 0),
+            (3, 8, 0, 0),
+            (3, 8, 0, 1),
+            (3, 16, 0, 2),
+            (1, 8, 2, 0),
+            (1, 12, 7, 0),
+            (3, 8, 1, 0),
+            (3, 8, 3, 1),
+            (3, 12, 5, 2),
+        ];
+
+        /// Convert interleaved samples to the native layout of CharLS,
+        /// which has one plane per component in non-interleaved mode.
+        fn charls_layout(samples: &[u16], components: usize, interleave: u8) -> Vec<u16> {
+            if interleave == 0 {
+                (0..components)
+                    .flat_map(|c| samples.iter().skip(c).step_by(components).copied())
+                    .collect()
+            } else {
+                samples.to_vec()
+            }
+        }
+
+        /// Convert samples in the native layout of CharLS to interleaved samples.
+        fn interleaved(planes: &[u16], components: usize, interleave: u8) -> Vec<u16> {
+            if interleave == 0 {
+                let len = planes.len() / components;
+                (0..planes.len())
+                    .map(|i| planes[(i % components) * len + i / components])
+                    .collect()
+            } else {
+                planes.to_vec()
+            }
+        }
+
+        fn charls_bytes(samples: &[u16], precision: u8) -> Vec<u8> {
+            if precision <= 8 {
+                samples.iter().map(|&s| s as u8).collect()
+            } else {
+                samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+            }
+        }
+
+        fn charls_samples(bytes: &[u8], precision: u8) -> Vec<u16> {
+            if precision <= 8 {
+                bytes.iter().map(|&b| u16::from(b)).collect()
+            } else {
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                    .collect()
+            }
+        }
+
+        fn charls_interleave_mode(interleave: u8) -> charls::InterleaveMode {
+            match interleave {
+                0 => charls::InterleaveMode::None,
+                1 => charls::InterleaveMode::Line,
+                _ => charls::InterleaveMode::Sample,
+            }
+        }
+
+        /// Decode code streams written by CharLS,
+        /// which must yield the same samples as CharLS decodes.
+        #[test]
+        fn decode_charls_codestreams() {
+            let (width, height) = (37, 19);
+            for &(components, precision, near, interleave) in CHARLS_CASES.iter() {
+                let max_val = (1_u32 << precision) - 1;
+                let samples = test_image(width, height, components, max_val);
+                let mut charls = charls::CharLS::default();
+                charls
+                    .set_interleave_mode(charls_interleave_mode(interleave))
+                    .unwrap();
+                let frame_info = charls::FrameInfo {
+                    width: width as u32,
+                    height: height as u32,
+                    bits_per_sample: precision.into(),
+                    component_count: components as i32,
+                };
+                let data = charls
+                    .encode(
+                        frame_info,
+                        near.into(),
+                        &charls_bytes(&charls_layout(&samples, components, interleave), precision),
+                    )
+                    .unwrap();
+                let expected = interleaved(
+                    &charls_samples(&charls.decode(&data).unwrap(), precision),
+                    components,
+                    interleave,
+                );
+
+                let (image, consumed) = decode(&data).unwrap();
+                assert_eq!(consumed, data.len());
+                assert_eq!((image.width, image.height), (37, 19));
+                assert_eq!(image.components, components);
+                assert_eq!(
+                    image.samples, expected,
+                    "precision {}, NEAR {}, ILV {}",
+                    precision, near, interleave
+                );
+                if near == 0 {
+                    assert_eq!(image.samples, samples);
+                }
+            }
+        }
+
+        /// Decode code streams written by this encoder with CharLS,
+        /// which must yield the same samples as this decoder.
+        #[test]
+        fn encode_decodes_with_charls() {
+            let (width, height) = (37, 19);
+            for &(components, precision, near, interleave) in CHARLS_CASES.iter() {
+                let max_val = (1_u32 << precision) - 1;
+                let samples = test_image(width, height, components, max_val);
+                let data = encode_samples(
+                    width, height, components, precision, near, interleave, &samples,
+                );
+
+                let frame_info = charls::CharLS::default().get_frame_info(&data).unwrap();
+                assert_eq!(
+                    frame_info,
+                    charls::FrameInfo {
+                        width: width as u32,
+                        height: height as u32,
+                        bits_per_sample: precision.into(),
+                        component_count: components as i32,
+                    }
+                );
+                let decoded = interleaved(
+                    &charls_samples(&charls::CharLS::default().decode(&data).unwrap(), precision),
+                    components,
+                    interleave,
+                );
+                let (image, _) = decode(&data).unwrap();
+                assert_eq!(
+                    decoded, image.samples,
+                    "precision {}, NEAR {}, ILV {}",
+                    precision, near, interleave
+                );
+                if near == 0 {
+                    assert_eq!(decoded, samples);
+                }
+            }
+        }
+    }
+
+    /// Encode the given native object frame by frame
+    /// and decode it back with the same adapter,
+    /// checking each frame against CharLS.
+    fn roundtrip(native: &TestPixelData) -> Vec<u8> {
+        let fragments: Vec<_> = (0..native.number_of_frames as u32)
+            .map(|frame| {
+                let mut fragment = Vec::new();
+                JpegLsAdapter
+                    .encode_frame(native, frame, EncodeOptions::new(), &mut fragment)
+                    .unwrap();
+                assert_eq!(fragment.len() % 2, 0);
+                fragment
+            })
+            .collect();
+
+        let encapsulated = TestPixelData {
+            planar_configuration: 0,
+            native: None,
+            fragments,
+            ..*native
+        };
+        let mut decoded = Vec::new();
+        JpegLsAdapter.decode(&encapsulated, &mut decoded).unwrap();
+
+        // decoding frame by frame yields the same pixel data
+        let mut frames = Vec::new();
+        for frame in 0..native.number_of_frames as u32 {
+            JpegLsAdapter
+                .decode_frame(&encapsulated, frame, &mut frames)
+                .unwrap();
+        }
+        assert_eq!(frames, decoded);
+
+        // the fragments also decode with CharLS
+        #[cfg(feature = "interop-tests")]
+        {
+            let frame_size = decoded.len() / native.number_of_frames as usize;
+            for (fragment, frame) in encapsulated
+                .fragments
+                .iter()
+                .zip(decoded.chunks(frame_size))
+            {
+                assert_eq!(charls::CharLS::default().decode(fragment).unwrap(), frame);
+            }
+        }
+
+        decoded
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        // 16-bit monochrome with 12 bits stored, 2 frames
+        let pixels: Vec<u8> = test_image(40, 30, 1, 4095)
+            .into_iter()
+            .chain(test_image(40, 30, 1, 1023))
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let native = TestPixelData {
+            rows: 30,
+            cols: 40,
+            samples_per_pixel: 1,
+            bits_allocated: 16,
+            bits_stored: 16,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 2,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        assert_eq!(roundtrip(&native), pixels);
+
+        // encoding all frames at once concatenates the fragments
+        let mut all = Vec::new();
+        JpegLsAdapter
+            .encode(&native, EncodeOptions::new(), &mut all)
+            .unwrap();
+        assert_eq!(all.windows(2).filter(|w| w == &[0xFF, 0xD8]).count(), 2);
+
+        // 8-bit RGB, interleaved
+        let pixels: Vec<u8> = test_image(9, 7, 3, 255)
+            .into_iter()
+            .map(|s| s as u8)
+            .collect();
+        let native = TestPixelData {
+            rows: 7,
+            cols: 9,
+            samples_per_pixel: 3,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        assert_eq!(roundtrip(&native), pixels);
+
+        // same image, with one plane per sample
+        let planar: Vec<u8> = (0..3)
+            .flat_map(|s| pixels.iter().skip(s).step_by(3).copied())
+            .collect();
+        let native = TestPixelData {
+            planar_configuration: 1,
+            native: Some(planar),
+            ..native
+        };
+        assert_eq!(roundtrip(&native), pixels);
+    }
+
+    #[test]
+    fn decode_frame_without_frame_boundaries() {
+        let pixels: Vec<u8> = test_image(12, 10, 1, 255)
+            .into_iter()
+            .chain(test_image(12, 10, 1, 127))
+            .map(|s| s as u8)
+            .collect();
+        let native = TestPixelData {
+            rows: 10,
+            cols: 12,
+            samples_per_pixel: 1,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 2,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        let mut all = Vec::new();
+        JpegLsAdapter
+            .encode(&native, EncodeOptions::new(), &mut all)
+            .unwrap();
+
+        // 3 fragments for 2 frames, without an offset table
+        let third = all.len() / 3;
+        let encapsulated = TestPixelData {
+            native: None,
+            fragments: vec![
+                all[..third].to_vec(),
+                all[third..2 * third].to_vec(),
+                all[2 * third..].to_vec(),
+            ],
+            ..native
+        };
+        let mut frame = Vec::new();
+        JpegLsAdapter
+            .decode_frame(&encapsulated, 1, &mut frame)
+            .unwrap();
+        assert_eq!(frame, &pixels[120..]);
+        assert!(JpegLsAdapter
+            .decode_frame(&encapsulated, 2, &mut frame)
+            .is_err());
+    }
+}
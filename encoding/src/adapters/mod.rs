@@ -1,9 +1,19 @@
 //! Module for built-in pixel data adapters.
 
 use dicom_core::value::C;
-use snafu::Snafu;
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::io::Write;
 
+pub mod fragments;
+#[cfg(feature = "jpeg")]
 pub mod jpeg;
+#[cfg(feature = "jpeg2000")]
+pub mod jpeg2000;
+#[cfg(feature = "jpeg")]
+mod jpeg_extended;
+#[cfg(feature = "jpeg-ls")]
+pub mod jpeg_ls;
+#[cfg(feature = "rle")]
 pub mod rle_lossless;
 
 /// Error conditions when decoding pixel data.
@@ -28,11 +38,15 @@ pub enum DecodeError {
     /// A required attribute is missing from the DICOM
     #[snafu(display("Missing required attribute: {}", name))]
     MissingAttribute { name: &'static str },
+
+    /// Could not write the decoded pixel data
+    WriteOutput { source: std::io::Error },
 }
 
 /// Error conditions when encoding pixel data.
 #[derive(Debug, Snafu)]
 #[non_exhaustive]
+#[snafu(module)]
 pub enum EncodeError {
     /// A custom error when encoding fails
     #[snafu(display("Error encoding pixel data {}", message))]
@@ -43,6 +57,10 @@ pub enum EncodeError {
 
     /// Encoding is not implemented
     NotImplemented,
+
+    /// A required attribute is missing from the DICOM object
+    #[snafu(display("Missing required attribute: {}", name))]
+    MissingAttribute { name: &'static str },
 }
 
 pub type DecodeResult<T, E = DecodeError> = Result<T, E>;
@@ -84,6 +102,24 @@ pub trait PixelDataObject {
     /// Return the BitsAllocated attribute or None if it is not set
     fn bits_allocated(&self) -> Option<u16>;
 
+    /// Return the BitsStored attribute or None if it is not set
+    fn bits_stored(&self) -> Option<u16>;
+
+    /// Return the PixelRepresentation attribute or None if it is not set
+    fn pixel_representation(&self) -> Option<u16>;
+
+    /// Return the PlanarConfiguration attribute or None if it is not set
+    fn planar_configuration(&self) -> Option<u16>;
+
+    /// Return the PhotometricInterpretation attribute,
+    /// without trailing padding,
+    /// or None if it is not set
+    ///
+    /// The default implementation returns None.
+    fn photometric_interpretation(&self) -> Option<&str> {
+        None
+    }
+
     /// Return the NumberOfFrames attribute or None if it is not set
     fn number_of_frames(&self) -> Option<u16>;
 
@@ -98,9 +134,44 @@ pub trait PixelDataObject {
     /// or byte fragments if encapsulated.
     /// Returns None if no pixel data is found
     fn raw_pixel_data(&self) -> Option<RawPixelData>;
+
+    /// Return the byte offset of the first fragment of each frame
+    /// in encapsulated pixel data,
+    /// relative to the first byte of the first fragment's item.
+    ///
+    /// The offsets should be retrieved from
+    /// the _Extended Offset Table_ if present,
+    /// which is required for pixel data larger than 4 GiB,
+    /// or from the basic offset table otherwise.
+    /// Returns None if there is no offset table.
+    ///
+    /// The default implementation retrieves the basic offset table
+    /// through [`raw_pixel_data`](PixelDataObject::raw_pixel_data).
+    fn frame_offsets(&self) -> Option<Vec<u64>> {
+        let raw = self.raw_pixel_data()?;
+        if raw.offset_table.is_empty() {
+            return None;
+        }
+        Some(raw.offset_table.iter().map(|&o| u64::from(o)).collect())
+    }
 }
 
 /// Custom options when encoding pixel data into an encapsulated form.
+///
+/// All options are hints,
+/// which adapters may ignore if they are not applicable
+/// to the transfer syntax or not supported by the encoder.
+///
+/// # Example
+///
+/// ```
+/// # use dicom_encoding::adapters::{EncodeOptions, ProgressionOrder};
+/// let options = EncodeOptions::new()
+///     .compression_ratio(10.)
+///     .progression_order(ProgressionOrder::Rpcl);
+/// assert_eq!(options.compression_ratio, Some(10.));
+/// assert!(!options.lossless);
+/// ```
 #[derive(Debug, Default, Clone)]
 #[non_exhaustive]
 pub struct EncodeOptions {
@@ -124,12 +195,84 @@ pub struct EncodeOptions {
     /// If this option is not specified,
     /// the actual effort is decided by the underlying adapter.
     pub effort: Option<u8>,
+
+    /// The target compression ratio of the output,
+    /// as the size of the native pixel data
+    /// divided by the size of the encoded pixel data
+    /// (e.g. `10.` for 10:1).
+    /// Encoders which support rate control,
+    /// such as JPEG 2000,
+    /// should prefer this option over `quality`.
+    /// It is ignored for lossless encodings.
+    pub compression_ratio: Option<f32>,
+
+    /// The progression order of the output codestream,
+    /// for codecs which support one (such as JPEG 2000).
+    /// If this option is not specified,
+    /// the progression order is decided by the underlying adapter.
+    pub progression_order: Option<ProgressionOrder>,
+
+    /// Whether the output must be a lossless encoding.
+    /// When set, `quality` and `compression_ratio` are ignored,
+    /// and adapters which can only encode lossily
+    /// should fail instead of degrading the image.
+    pub lossless: bool,
 }
 
 impl EncodeOptions {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Set the quality of the output image.
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// Set the amount of effort to encode the pixel data.
+    pub fn effort(mut self, effort: u8) -> Self {
+        self.effort = Some(effort);
+        self
+    }
+
+    /// Set the target compression ratio of the output.
+    pub fn compression_ratio(mut self, compression_ratio: f32) -> Self {
+        self.compression_ratio = Some(compression_ratio);
+        self
+    }
+
+    /// Set the progression order of the output codestream.
+    pub fn progression_order(mut self, progression_order: ProgressionOrder) -> Self {
+        self.progression_order = Some(progression_order);
+        self
+    }
+
+    /// Set whether the output must be a lossless encoding.
+    pub fn lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+}
+
+/// The order in which a progressive codestream is organized,
+/// as defined by JPEG 2000.
+///
+/// Each variant is named after the nesting of
+/// layer (L), resolution level (R), component (C) and position (P),
+/// from the outermost to the innermost.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ProgressionOrder {
+    /// Layer-resolution-component-position
+    Lrcp,
+    /// Resolution-layer-component-position
+    Rlcp,
+    /// Resolution-position-component-layer
+    Rpcl,
+    /// Position-component-resolution-layer
+    Pcrl,
+    /// Component-position-resolution-layer
+    Cprl,
 }
 
 /// Trait object responsible for decoding and encoding
@@ -159,6 +302,139 @@ pub trait PixelRWAdapter {
     /// (planar configuration of 0).
     fn decode(&self, src: &dyn PixelDataObject, dst: &mut Vec<u8>) -> DecodeResult<()>;
 
+    /// Decode a single frame of the given DICOM object
+    /// containing encapsulated pixel data
+    /// into native pixel data,
+    /// appending the frame's bytes to the given vector `dst`.
+    ///
+    /// `frame` is the index of the frame, starting at 0.
+    /// The same preconditions and output format
+    /// as in [`decode`](PixelRWAdapter::decode) apply.
+    ///
+    /// This is the preferred way to retrieve individual frames
+    /// of large multi-frame objects.
+    /// The default implementation decodes all frames
+    /// and keeps only the requested one,
+    /// so adapters which can locate a frame's fragments
+    /// should override it.
+    fn decode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        decode_frame_in_full(self, src, frame, dst)
+    }
+
+    /// Decode a rectangular region of a single frame
+    /// of the given DICOM object
+    /// containing encapsulated pixel data
+    /// into native pixel data,
+    /// appending the region's bytes to the given vector `dst`
+    /// row by row.
+    ///
+    /// `frame` is the index of the frame, starting at 0.
+    /// The same preconditions and output format
+    /// as in [`decode`](PixelRWAdapter::decode) apply.
+    /// An error is returned if the region
+    /// does not fit within the bounds of the image.
+    ///
+    /// This allows viewers of very large frames,
+    /// such as those of whole slide images,
+    /// to retrieve only the area currently in view.
+    /// The default implementation decodes the whole frame
+    /// and copies the region out of it,
+    /// so adapters of tiled codecs (such as JPEG 2000)
+    /// which can decode only the tiles intersecting the region
+    /// should override it.
+    fn decode_region(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        region: Region,
+        dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        let cols = src
+            .cols()
+            .context(MissingAttributeSnafu { name: "Columns" })?;
+        let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+        let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+            name: "SamplesPerPixel",
+        })?;
+        let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+            name: "BitsAllocated",
+        })?;
+        if !region.fits_within(u32::from(cols), u32::from(rows)) {
+            snafu::whatever!("Region {:?} out of image bounds", region);
+        }
+
+        let mut data = Vec::new();
+        self.decode_frame(src, frame, &mut data)?;
+        let pixel_size = samples_per_pixel as usize * (bits_allocated as usize / 8);
+        copy_region(&data, cols as usize * pixel_size, pixel_size, region, dst);
+        Ok(())
+    }
+
+    /// Decode a single frame of the given DICOM object
+    /// containing encapsulated pixel data
+    /// at a reduced resolution,
+    /// appending the frame's bytes to the given vector `dst`.
+    ///
+    /// Each resolution `level` halves the number of columns and rows,
+    /// rounded up (see [`reduced_dimensions`]),
+    /// so that level 0 is the full resolution.
+    /// The same preconditions and output format
+    /// as in [`decode`](PixelRWAdapter::decode) apply.
+    ///
+    /// This is meant for producing thumbnails and previews quickly.
+    /// The default implementation decodes the full frame
+    /// and keeps the top-left pixel of each block,
+    /// so adapters of codecs with resolution levels (such as JPEG 2000)
+    /// which can stop decoding early should override it.
+    fn decode_at_resolution(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        level: u8,
+        dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        let cols = src
+            .cols()
+            .context(MissingAttributeSnafu { name: "Columns" })?;
+        let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+            name: "SamplesPerPixel",
+        })?;
+        let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+            name: "BitsAllocated",
+        })?;
+
+        let mut data = Vec::new();
+        self.decode_frame(src, frame, &mut data)?;
+        let pixel_size = samples_per_pixel as usize * (bits_allocated as usize / 8);
+        decimate(&data, cols as usize, pixel_size, level, dst);
+        Ok(())
+    }
+
+    /// Decode the given DICOM object
+    /// containing encapsulated pixel data
+    /// into native pixel data,
+    /// writing the bytes of each frame in order to the given writer.
+    ///
+    /// The same preconditions and output format
+    /// as in [`decode`](PixelRWAdapter::decode) apply.
+    ///
+    /// This allows streaming large objects to another destination
+    /// while holding only one decoded frame in memory at a time.
+    /// The default implementation decodes all frames at once
+    /// before writing them,
+    /// so adapters which can decode one frame at a time
+    /// should override it.
+    fn decode_to_writer(&self, src: &dyn PixelDataObject, to: &mut dyn Write) -> DecodeResult<()> {
+        let mut data = Vec::new();
+        self.decode(src, &mut data)?;
+        to.write_all(&data).context(WriteOutputSnafu)
+    }
+
     /// Encode a DICOM object's image into the format supported by this adapter,
     /// writing a byte stream of pixel data fragment values
     /// into the given destination.
@@ -181,6 +457,138 @@ pub trait PixelRWAdapter {
     ) -> EncodeResult<()> {
         Err(EncodeError::NotImplemented)
     }
+
+    /// Encode a single frame of a DICOM object's image
+    /// into the format supported by this adapter,
+    /// writing the byte stream of one pixel data fragment
+    /// into the given destination.
+    ///
+    /// This is the preferred way to encode multi-frame images
+    /// into transfer syntaxes which require one fragment per frame.
+    /// The same preconditions as in [`encode`](PixelRWAdapter::encode) apply.
+    #[allow(unused_variables)]
+    fn encode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        Err(EncodeError::NotImplemented)
+    }
+}
+
+/// A rectangular region of an image, in pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Region {
+    /// The column of the top-left corner of the region
+    pub x: u32,
+    /// The row of the top-left corner of the region
+    pub y: u32,
+    /// The number of columns in the region
+    pub width: u32,
+    /// The number of rows in the region
+    pub height: u32,
+}
+
+impl Region {
+    /// Create a new region from its top-left corner and dimensions.
+    pub const fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Check whether the region is within the bounds
+    /// of an image with the given number of columns and rows.
+    pub fn fits_within(&self, cols: u32, rows: u32) -> bool {
+        u64::from(self.x) + u64::from(self.width) <= u64::from(cols)
+            && u64::from(self.y) + u64::from(self.height) <= u64::from(rows)
+    }
+}
+
+/// Copy a region out of a decoded frame into `dst`,
+/// given the length of each row and of each pixel in bytes.
+pub(crate) fn copy_region(
+    frame: &[u8],
+    row_length: usize,
+    pixel_size: usize,
+    region: Region,
+    dst: &mut Vec<u8>,
+) {
+    let start = region.x as usize * pixel_size;
+    let end = start + region.width as usize * pixel_size;
+    for row in frame
+        .chunks_exact(row_length)
+        .skip(region.y as usize)
+        .take(region.height as usize)
+    {
+        dst.extend_from_slice(&row[start..end]);
+    }
+}
+
+/// Determine the number of columns and rows of an image
+/// at the given resolution level,
+/// where each level halves both dimensions, rounding up.
+pub fn reduced_dimensions(cols: u32, rows: u32, level: u8) -> (u32, u32) {
+    if level >= 32 {
+        return (cols.min(1), rows.min(1));
+    }
+    let reduce = |n: u32| ((u64::from(n) + (1 << level) - 1) >> level) as u32;
+    (reduce(cols), reduce(rows))
+}
+
+/// Copy the top-left pixel of each block of 2^`level` by 2^`level` pixels
+/// of a decoded frame into `dst`,
+/// given the number of columns and the length of each pixel in bytes.
+pub(crate) fn decimate(frame: &[u8], cols: usize, pixel_size: usize, level: u8, dst: &mut Vec<u8>) {
+    let step = 1_usize.checked_shl(u32::from(level)).unwrap_or(usize::MAX);
+    let row_length = cols * pixel_size;
+    if row_length == 0 {
+        return;
+    }
+    for row in frame.chunks_exact(row_length).step_by(step) {
+        for pixel in row.chunks_exact(pixel_size).step_by(step) {
+            dst.extend_from_slice(pixel);
+        }
+    }
+}
+
+/// Decode a single frame by decoding all frames
+/// and keeping only the requested one.
+pub(crate) fn decode_frame_in_full<A>(
+    adapter: &A,
+    src: &dyn PixelDataObject,
+    frame: u32,
+    dst: &mut Vec<u8>,
+) -> DecodeResult<()>
+where
+    A: PixelRWAdapter + ?Sized,
+{
+    let cols = src
+        .cols()
+        .context(MissingAttributeSnafu { name: "Columns" })?;
+    let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+    let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+        name: "SamplesPerPixel",
+    })?;
+    let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+        name: "BitsAllocated",
+    })?;
+    let frame_size =
+        cols as usize * rows as usize * samples_per_pixel as usize * (bits_allocated as usize / 8);
+
+    let mut data = Vec::new();
+    adapter.decode(src, &mut data)?;
+    let start = frame as usize * frame_size;
+    let frame_data = data
+        .get(start..start + frame_size)
+        .with_whatever_context(|| format!("Frame #{} out of bounds", frame))?;
+    dst.extend_from_slice(frame_data);
+    Ok(())
 }
 
 /// Alias type for a dynamically dispatched data adapter.
@@ -195,6 +603,43 @@ impl PixelRWAdapter for NeverPixelAdapter {
         unreachable!();
     }
 
+    fn decode_frame(
+        &self,
+        _src: &dyn PixelDataObject,
+        _frame: u32,
+        _dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        unreachable!();
+    }
+
+    fn decode_to_writer(
+        &self,
+        _src: &dyn PixelDataObject,
+        _to: &mut dyn Write,
+    ) -> DecodeResult<()> {
+        unreachable!();
+    }
+
+    fn decode_region(
+        &self,
+        _src: &dyn PixelDataObject,
+        _frame: u32,
+        _region: Region,
+        _dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        unreachable!();
+    }
+
+    fn decode_at_resolution(
+        &self,
+        _src: &dyn PixelDataObject,
+        _frame: u32,
+        _level: u8,
+        _dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        unreachable!();
+    }
+
     fn encode(
         &self,
         _src: &dyn PixelDataObject,
@@ -203,4 +648,99 @@ impl PixelRWAdapter for NeverPixelAdapter {
     ) -> EncodeResult<()> {
         unreachable!();
     }
+    fn encode_frame(
+        &self,
+        _src: &dyn PixelDataObject,
+        _frame: u32,
+        _options: EncodeOptions,
+        _dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        unreachable!();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{PixelDataObject, RawPixelData};
+    use dicom_core::smallvec::smallvec;
+
+    /// Minimal pixel data object holding either
+    /// native pixel data or one fragment per frame.
+    pub(crate) struct TestPixelData {
+        pub rows: u16,
+        pub cols: u16,
+        pub samples_per_pixel: u16,
+        pub bits_allocated: u16,
+        pub bits_stored: u16,
+        pub pixel_representation: u16,
+        pub planar_configuration: u16,
+        pub number_of_frames: u16,
+        pub native: Option<Vec<u8>>,
+        pub fragments: Vec<Vec<u8>>,
+    }
+
+    impl PixelDataObject for TestPixelData {
+        fn rows(&self) -> Option<u16> {
+            Some(self.rows)
+        }
+
+        fn cols(&self) -> Option<u16> {
+            Some(self.cols)
+        }
+
+        fn samples_per_pixel(&self) -> Option<u16> {
+            Some(self.samples_per_pixel)
+        }
+
+        fn bits_allocated(&self) -> Option<u16> {
+            Some(self.bits_allocated)
+        }
+
+        fn bits_stored(&self) -> Option<u16> {
+            Some(self.bits_stored)
+        }
+
+        fn pixel_representation(&self) -> Option<u16> {
+            Some(self.pixel_representation)
+        }
+
+        fn planar_configuration(&self) -> Option<u16> {
+            Some(self.planar_configuration)
+        }
+
+        fn photometric_interpretation(&self) -> Option<&str> {
+            Some(if self.samples_per_pixel == 3 {
+                "RGB"
+            } else {
+                "MONOCHROME2"
+            })
+        }
+
+        fn number_of_frames(&self) -> Option<u16> {
+            Some(self.number_of_frames)
+        }
+
+        fn number_of_fragments(&self) -> Option<u32> {
+            if self.native.is_some() {
+                None
+            } else {
+                Some(self.fragments.len() as u32)
+            }
+        }
+
+        fn fragment(&self, fragment: usize) -> Option<Vec<u8>> {
+            self.fragments.get(fragment).cloned()
+        }
+
+        fn raw_pixel_data(&self) -> Option<RawPixelData> {
+            let fragments = match &self.native {
+                Some(native) => smallvec![native.clone()],
+                None => self.fragments.iter().cloned().collect(),
+            };
+            Some(RawPixelData {
+                fragments,
+                offset_table: Default::default(),
+            })
+        }
+    }
 }
@@ -9,20 +9,90 @@
 use byteordered::byteorder::{ByteOrder, LittleEndian};
 use snafu::{whatever, OptionExt, ResultExt};
 
-use crate::adapters::{DecodeResult, PixelDataObject, PixelRWAdapter};
-use std::io::{self, Read, Seek};
+use crate::adapters::{
+    DecodeResult, EncodeError, EncodeOptions, EncodeResult, PixelDataObject, PixelRWAdapter,
+};
+use std::io::{self, Read, Seek, Write};
 
-use super::MissingAttributeSnafu;
+use super::fragments::{frame_data, object_frame_fragment_ranges};
+use super::{MissingAttributeSnafu, WriteOutputSnafu};
+use std::ops::Range;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct RLELosslessAdapter;
 
+/// An RLE Lossless adapter which tolerates malformed data.
+///
+/// Segments which end early,
+/// segments missing from the RLE header,
+/// and segment offsets beyond the end of the fragment
+/// are filled with zeros,
+/// and reported as a warning instead of failing the whole read.
+/// Encoding is the same as in [`RLELosslessAdapter`].
+///
+/// This adapter can replace the default one at run time
+/// through the transfer syntax registry.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LenientRLELosslessAdapter;
+
 /// Decode TS: 1.2.840.10008.1.2.5 (RLE Lossless)
 impl PixelRWAdapter for RLELosslessAdapter {
     /// Decode the DICOM image from RLE Lossless completely.
     ///
     /// See <http://dicom.nema.org/medical/Dicom/2018d/output/chtml/part05/chapter_G.html>
     fn decode(&self, src: &dyn PixelDataObject, dst: &mut Vec<u8>) -> DecodeResult<()> {
+        dst.clear();
+        self.decode_to_writer(src, dst)
+    }
+
+    /// Decode the DICOM image from RLE Lossless,
+    /// one frame at a time.
+    fn decode_to_writer(&self, src: &dyn PixelDataObject, to: &mut dyn Write) -> DecodeResult<()> {
+        decode_rle_to_writer(src, to, false)
+    }
+
+    /// Decode a single frame of the DICOM image from RLE Lossless.
+    fn decode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        decode_rle_single_frame(src, frame, dst, false)
+    }
+
+    /// Encode the DICOM image into RLE Lossless,
+    /// with the fragments of all frames concatenated.
+    ///
+    /// Since each frame is encoded into its own fragment,
+    /// prefer [`encode_frame`](PixelRWAdapter::encode_frame)
+    /// to retain the boundaries between fragments.
+    fn encode(
+        &self,
+        src: &dyn PixelDataObject,
+        options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        let nr_frames = src.number_of_frames().unwrap_or(1);
+        for frame in 0..nr_frames {
+            self.encode_frame(src, frame.into(), options.clone(), dst)?;
+        }
+        Ok(())
+    }
+
+    /// Encode a single frame of the DICOM image
+    /// into one RLE Lossless fragment.
+    ///
+    /// See <http://dicom.nema.org/medical/Dicom/2018d/output/chtml/part05/chapter_G.html>
+    fn encode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        _options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        use super::encode_error::MissingAttributeSnafu;
+
         let cols = src
             .cols()
             .context(MissingAttributeSnafu { name: "Columns" })?;
@@ -33,109 +103,461 @@ impl PixelRWAdapter for RLELosslessAdapter {
         let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
             name: "BitsAllocated",
         })?;
+        let planar_configuration = src.planar_configuration().unwrap_or(0);
 
-        if bits_allocated != 8 && bits_allocated != 16 {
-            whatever!("BitsAllocated other than 8 or 16 is not supported");
-        }
-        // For RLE the number of fragments = number of frames
-        // therefore, we can fetch the fragments one-by-one
-        let nr_frames =
-            src.number_of_fragments()
-                .whatever_context("Invalid pixel data, no fragments found")? as usize;
-        let bytes_per_sample = bits_allocated / 8;
-        // `stride` it the total number of bytes for each sample plane
-        let stride: usize = bytes_per_sample as usize * cols as usize * rows as usize;
-        let frame_size = samples_per_pixel as usize * stride;
-        dst.resize(frame_size * nr_frames, 0);
-
-        // RLE encoded data is ordered like this (for 16-bit, 3 sample):
-        //  Segment: 0     | 1     | 2     | 3     | 4     | 5
-        //           R MSB | R LSB | G MSB | G LSB | B MSB | B LSB
-        //  A segment contains only the MSB or LSB parts of all the sample pixels
-
-        // To minimise the amount of array manipulation later, and to make things
-        // faster we interleave each segment in a manner consistent with a planar
-        // configuration of 1 (and use little endian byte ordering):
-        //    All red samples             | All green samples           | All blue
-        //    Pxl 1   Pxl 2   ... Pxl N   | Pxl 1   Pxl 2   ... Pxl N   | ...
-        //    LSB MSB LSB MSB ... LSB MSB | LSB MSB LSB MSB ... LSB MSB | ...
-
-        for i in 0..nr_frames {
-            let fragment = &src
-                .fragment(i)
-                .whatever_context("No pixel data found for frame")?;
-            let mut offsets = read_rle_header(fragment);
-            offsets.push(fragment.len() as u32);
-
-            for sample_number in 0..samples_per_pixel {
-                for byte_offset in (0..bytes_per_sample).rev() {
-                    // ii is 1, 0, 3, 2, 5, 4 for the example above
-                    // This is where the segment order correction occurs
-                    let ii = sample_number * bytes_per_sample + byte_offset;
-                    let segment = &fragment
-                        [offsets[ii as usize] as usize..offsets[(ii + 1) as usize] as usize];
-                    let buff = io::Cursor::new(segment);
-                    let mut decoded_segment: Vec<u8> = vec![0; rows as usize * cols as usize];
-                    let decode_length = decode_rle_segment(buff, &mut decoded_segment)
-                        .map_err(|e| Box::new(e) as Box<_>)
-                        .whatever_context("Failed to read RLE segments")?;
-
-                    assert_eq!(decode_length, decoded_segment.len());
-
-                    // Interleave pixels as described in the example above
-                    let byte_offset = bytes_per_sample - byte_offset - 1;
-                    let sample_offset = (sample_number * bytes_per_sample) as usize;
-
-                    let start = frame_size * i
-                        + sample_offset
-                        + byte_offset as usize;
-                    let end = frame_size * (i + 1);
-                    for (decoded_index, dst_index) in
-                        (start..end).step_by(bytes_per_sample as usize * samples_per_pixel as usize).enumerate()
-                    {
-                        dst[dst_index] = decoded_segment[decoded_index];
-                    }
-                }
+        // same restriction as in decoding,
+        // so that every encoded fragment can be read back
+        if !matches!(bits_allocated, 8 | 16 | 32) {
+            return Err(EncodeError::CustomEncodeError {
+                message: "BitsAllocated other than 8, 16 or 32 is not supported",
+            });
+        }
+        let bytes_per_sample = (bits_allocated / 8) as usize;
+        let samples_per_pixel = samples_per_pixel as usize;
+        let nr_segments = bytes_per_sample * samples_per_pixel;
+        if nr_segments > 15 {
+            return Err(EncodeError::CustomEncodeError {
+                message: "Too many segments required for RLE Lossless",
+            });
+        }
+
+        // native pixel data is held in a single value, not in fragments
+        if src.fragment(0).is_some() {
+            return Err(EncodeError::NotNative);
+        }
+        let data = src
+            .raw_pixel_data()
+            .and_then(|raw| raw.fragments.into_iter().next())
+            .ok_or(EncodeError::MissingAttribute { name: "PixelData" })?;
+
+        let nr_pixels = rows as usize * cols as usize;
+        let frame_size = nr_pixels * nr_segments;
+        let frame_start = frame as usize * frame_size;
+        let frame_data = data.get(frame_start..frame_start + frame_size).ok_or(
+            EncodeError::CustomEncodeError {
+                message: "Frame out of bounds",
+            },
+        )?;
+
+        // Segments are ordered by sample,
+        // from the most significant byte to the least significant byte.
+        // Native pixel data is in little endian,
+        // either with interleaved samples (planar configuration 0)
+        // or with one plane per sample (planar configuration 1).
+        // Segments are independent of each other,
+        // so they are encoded in parallel if possible.
+        let encode_segment = |segment_number: usize| {
+            let sample_number = segment_number / bytes_per_sample;
+            let byte_offset = bytes_per_sample - 1 - segment_number % bytes_per_sample;
+            let plane: Vec<u8> = if planar_configuration == 0 {
+                let start = sample_number * bytes_per_sample + byte_offset;
+                frame_data[start..]
+                    .iter()
+                    .step_by(nr_segments)
+                    .take(nr_pixels)
+                    .copied()
+                    .collect()
+            } else {
+                let start = sample_number * nr_pixels * bytes_per_sample + byte_offset;
+                frame_data[start..]
+                    .iter()
+                    .step_by(bytes_per_sample)
+                    .take(nr_pixels)
+                    .copied()
+                    .collect()
+            };
+
+            // each row is encoded separately
+            let mut segment = Vec::new();
+            for row in plane.chunks(cols as usize) {
+                encode_rle_segment_row(row, &mut segment);
+            }
+            if segment.len() % 2 != 0 {
+                segment.push(0);
             }
+            segment
+        };
+        #[cfg(feature = "rayon")]
+        let segments: Vec<Vec<u8>> = {
+            use rayon::prelude::*;
+            (0..nr_segments)
+                .into_par_iter()
+                .map(encode_segment)
+                .collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let segments: Vec<Vec<u8>> = (0..nr_segments).map(encode_segment).collect();
+
+        // RLE header: number of segments, followed by 15 segment offsets
+        let mut header = [0_u32; 16];
+        header[0] = nr_segments as u32;
+        let mut offset = 64;
+        for (i, segment) in segments.iter().enumerate() {
+            header[i + 1] = offset;
+            offset += segment.len() as u32;
+        }
+
+        let header_start = dst.len();
+        dst.resize(header_start + 64, 0);
+        LittleEndian::write_u32_into(&header, &mut dst[header_start..]);
+        for segment in segments {
+            dst.extend(segment);
         }
         Ok(())
     }
+}
 
-    // TODO(#125) implement `encode`
+/// Decode TS: 1.2.840.10008.1.2.5 (RLE Lossless), tolerating malformed data
+impl PixelRWAdapter for LenientRLELosslessAdapter {
+    /// Decode the DICOM image from RLE Lossless completely,
+    /// filling missing data with zeros.
+    fn decode(&self, src: &dyn PixelDataObject, dst: &mut Vec<u8>) -> DecodeResult<()> {
+        dst.clear();
+        self.decode_to_writer(src, dst)
+    }
+
+    /// Decode the DICOM image from RLE Lossless,
+    /// one frame at a time,
+    /// filling missing data with zeros.
+    fn decode_to_writer(&self, src: &dyn PixelDataObject, to: &mut dyn Write) -> DecodeResult<()> {
+        decode_rle_to_writer(src, to, true)
+    }
+
+    /// Decode a single frame of the DICOM image from RLE Lossless,
+    /// filling missing data with zeros.
+    fn decode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        dst: &mut Vec<u8>,
+    ) -> DecodeResult<()> {
+        decode_rle_single_frame(src, frame, dst, true)
+    }
+
+    /// Encode the DICOM image into RLE Lossless,
+    /// as in [`RLELosslessAdapter`].
+    fn encode(
+        &self,
+        src: &dyn PixelDataObject,
+        options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        RLELosslessAdapter.encode(src, options, dst)
+    }
+
+    /// Encode a single frame of the DICOM image into RLE Lossless,
+    /// as in [`RLELosslessAdapter`].
+    fn encode_frame(
+        &self,
+        src: &dyn PixelDataObject,
+        frame: u32,
+        options: EncodeOptions,
+        dst: &mut Vec<u8>,
+    ) -> EncodeResult<()> {
+        RLELosslessAdapter.encode_frame(src, frame, options, dst)
+    }
+}
+
+/// Decode all frames of RLE Lossless pixel data into the given writer.
+fn decode_rle_to_writer(
+    src: &dyn PixelDataObject,
+    to: &mut dyn Write,
+    lenient: bool,
+) -> DecodeResult<()> {
+    let cols = src
+        .cols()
+        .context(MissingAttributeSnafu { name: "Columns" })?;
+    let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+    let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+        name: "SamplesPerPixel",
+    })?;
+    let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+        name: "BitsAllocated",
+    })?;
+
+    if !matches!(bits_allocated, 8 | 16 | 32) {
+        whatever!("BitsAllocated other than 8, 16 or 32 is not supported");
+    }
+    // each frame may span one or more fragments
+    let frame_fragments = object_frame_fragment_ranges(src)
+        .whatever_context("Could not identify the fragments of each frame")?;
+    let bytes_per_sample = bits_allocated / 8;
+    // `stride` it the total number of bytes for each sample plane
+    let stride: usize = bytes_per_sample as usize * cols as usize * rows as usize;
+    let frame_size = samples_per_pixel as usize * stride;
+    if frame_size == 0 {
+        return Ok(());
+    }
+
+    let mut frame = vec![0; frame_size];
+    for fragments in frame_fragments {
+        let fragment = &frame_fragments_data(src, fragments)?;
+        decode_rle_frame(
+            fragment,
+            (cols, rows, samples_per_pixel),
+            bytes_per_sample,
+            &mut frame,
+            lenient,
+        )?;
+        to.write_all(&frame).context(WriteOutputSnafu)?;
+    }
+    Ok(())
 }
 
-// Read the RLE header and return the offsets
-fn read_rle_header(fragment: &[u8]) -> Vec<u32> {
-    let nr_segments = LittleEndian::read_u32(&fragment[0..4]);
-    let mut offsets = vec![0; nr_segments as usize];
-    LittleEndian::read_u32_into(&fragment[4..4 * (nr_segments + 1) as usize], &mut offsets);
-    offsets
+/// Decode a single frame of RLE Lossless pixel data,
+/// appending it to `dst`.
+fn decode_rle_single_frame(
+    src: &dyn PixelDataObject,
+    frame: u32,
+    dst: &mut Vec<u8>,
+    lenient: bool,
+) -> DecodeResult<()> {
+    let cols = src
+        .cols()
+        .context(MissingAttributeSnafu { name: "Columns" })?;
+    let rows = src.rows().context(MissingAttributeSnafu { name: "Rows" })?;
+    let samples_per_pixel = src.samples_per_pixel().context(MissingAttributeSnafu {
+        name: "SamplesPerPixel",
+    })?;
+    let bits_allocated = src.bits_allocated().context(MissingAttributeSnafu {
+        name: "BitsAllocated",
+    })?;
+
+    if !matches!(bits_allocated, 8 | 16 | 32) {
+        whatever!("BitsAllocated other than 8, 16 or 32 is not supported");
+    }
+    let bytes_per_sample = bits_allocated / 8;
+    let frame_size =
+        samples_per_pixel as usize * bytes_per_sample as usize * cols as usize * rows as usize;
+
+    let fragment = &frame_data(src, frame).whatever_context("No pixel data found for frame")?;
+    let start = dst.len();
+    dst.resize(start + frame_size, 0);
+    decode_rle_frame(
+        fragment,
+        (cols, rows, samples_per_pixel),
+        bytes_per_sample,
+        &mut dst[start..],
+        lenient,
+    )
+}
+
+/// Retrieve the RLE Lossless data of a frame,
+/// joined from the given range of fragments.
+fn frame_fragments_data(
+    src: &dyn PixelDataObject,
+    fragments: Range<usize>,
+) -> DecodeResult<Vec<u8>> {
+    let mut data = Vec::new();
+    for index in fragments {
+        let fragment = src
+            .fragment(index)
+            .whatever_context("No pixel data found for frame")?;
+        data.extend_from_slice(&fragment);
+    }
+    Ok(data)
+}
+
+/// Decode the RLE Lossless data of a single frame into `frame`,
+/// given the frame's columns, rows, and samples per pixel.
+fn decode_rle_frame(
+    fragment: &[u8],
+    dimensions: (u16, u16, u16),
+    bytes_per_sample: u16,
+    frame: &mut [u8],
+    lenient: bool,
+) -> DecodeResult<()> {
+    let (cols, rows, samples_per_pixel) = dimensions;
+
+    // RLE encoded data is ordered like this (for 16-bit, 3 sample):
+    //  Segment: 0     | 1     | 2     | 3     | 4     | 5
+    //           R MSB | R LSB | G MSB | G LSB | B MSB | B LSB
+    //  A segment contains only the MSB or LSB parts of all the sample pixels
+    //  (32-bit samples have 4 segments each, from the most significant byte)
+
+    // To minimise the amount of array manipulation later, and to make things
+    // faster we interleave each segment in a manner consistent with a planar
+    // configuration of 1 (and use little endian byte ordering):
+    //    All red samples             | All green samples           | All blue
+    //    Pxl 1   Pxl 2   ... Pxl N   | Pxl 1   Pxl 2   ... Pxl N   | ...
+    //    LSB MSB LSB MSB ... LSB MSB | LSB MSB LSB MSB ... LSB MSB | ...
+
+    let mut offsets = match read_rle_header(fragment) {
+        Some(offsets) => offsets,
+        None if lenient => {
+            tracing::warn!("Invalid RLE header, filling frame with zeros");
+            Vec::new()
+        }
+        None => whatever!("Invalid RLE header"),
+    };
+    let nr_segments = offsets.len();
+    offsets.push(fragment.len() as u32);
+
+    let nr_pixels = rows as usize * cols as usize;
+    let step = bytes_per_sample as usize * samples_per_pixel as usize;
+    if nr_segments < step {
+        if !lenient {
+            whatever!(
+                "RLE header declares {} segments, but {} are required",
+                nr_segments,
+                step
+            );
+        }
+        tracing::warn!(
+            "RLE header declares {} segments, but {} are required; filling missing segments with zeros",
+            nr_segments,
+            step
+        );
+    }
+    // a single segment is decoded in place,
+    // otherwise each segment goes through the same buffer
+    let mut decoded_segment: Vec<u8> = if step == 1 {
+        Vec::new()
+    } else {
+        vec![0; nr_pixels]
+    };
+
+    for sample_number in 0..samples_per_pixel {
+        for byte_offset in (0..bytes_per_sample).rev() {
+            // ii is 1, 0, 3, 2, 5, 4 for the example above
+            // This is where the segment order correction occurs
+            let ii = (sample_number * bytes_per_sample + byte_offset) as usize;
+            let segment = match offsets
+                .get(ii..ii + 2)
+                .and_then(|o| fragment.get(o[0] as usize..o[1] as usize))
+            {
+                Some(segment) => segment,
+                None if lenient => {
+                    // take what is available of the segment, if anything
+                    offsets
+                        .get(ii)
+                        .and_then(|&start| fragment.get(start as usize..))
+                        .filter(|_| ii < nr_segments)
+                        .unwrap_or(&[])
+                }
+                None => whatever!("Invalid RLE segment offsets"),
+            };
+
+            let target = if step == 1 {
+                &mut frame[..nr_pixels]
+            } else {
+                &mut decoded_segment[..]
+            };
+            let decoded = decode_rle_segment(segment, target);
+            if decoded < target.len() {
+                if !lenient {
+                    whatever!("Failed to read RLE segments");
+                }
+                if ii < nr_segments {
+                    tracing::warn!(
+                        "RLE segment {} ends early, filling the remaining {} bytes with zeros",
+                        ii,
+                        target.len() - decoded
+                    );
+                }
+                target[decoded..].fill(0);
+            }
+            if step == 1 {
+                continue;
+            }
+
+            // Interleave pixels as described in the example above
+            let byte_offset = bytes_per_sample - byte_offset - 1;
+            let start = (sample_number * bytes_per_sample + byte_offset) as usize;
+            for (dst, src) in frame[start..]
+                .iter_mut()
+                .step_by(step)
+                .zip(&decoded_segment)
+            {
+                *dst = *src;
+            }
+        }
+    }
+    Ok(())
 }
 
-fn decode_rle_segment<R: Read + Seek>(mut reader: R, target_buffer: &mut Vec<u8>) -> io::Result<usize> {
+/// Read the RLE header and return the segment offsets,
+/// or `None` if the header is incomplete.
+fn read_rle_header(fragment: &[u8]) -> Option<Vec<u32>> {
+    let nr_segments = LittleEndian::read_u32(fragment.get(0..4)?) as usize;
+    if nr_segments > 15 {
+        return None;
+    }
+    let mut offsets = vec![0; nr_segments];
+    LittleEndian::read_u32_into(fragment.get(4..4 * (nr_segments + 1))?, &mut offsets);
+    Some(offsets)
+}
+
+/// Decode an RLE segment with the PackBits algorithm
+/// until the target buffer is full,
+/// returning the number of bytes decoded.
+///
+/// Fewer bytes than the length of the buffer are decoded
+/// if the segment ends too early.
+/// Runs which would overflow the buffer are truncated.
+fn decode_rle_segment(mut segment: &[u8], target_buffer: &mut [u8]) -> usize {
     let mut pos: usize = 0;
-    let mut header: [u8; 1] = [0];
-    let mut data: [u8; 1] = [0];
     while pos < target_buffer.len() {
-        reader.read_exact(&mut header)?;
-        let h = header[0] as i8;
-        if (-127..=-1).contains(&h) {
-            let new_pos = pos + (1 - h as isize) as usize;
-            reader.read_exact(&mut data)?;
-            for i in pos .. new_pos {
-                target_buffer[i] = data[0];
-            }
-            pos = new_pos;
-        } else if h >= 0 {
-            let num_vals = h as usize + 1;
-            reader.read_exact(&mut target_buffer[pos..pos + num_vals])?;
-            pos += num_vals;
+        let (&header, rest) = match segment.split_first() {
+            Some(split) => split,
+            None => break,
+        };
+        let h = header as i8;
+        if h >= 0 {
+            let num_vals = (h as usize + 1).min(rest.len());
+            let end = (pos + num_vals).min(target_buffer.len());
+            target_buffer[pos..end].copy_from_slice(&rest[..end - pos]);
+            pos = end;
+            segment = &rest[num_vals..];
+        } else if h != -128 {
+            let (&value, rest) = match rest.split_first() {
+                Some(split) => split,
+                None => break,
+            };
+            let end = (pos + (1 - h as isize) as usize).min(target_buffer.len());
+            target_buffer[pos..end].fill(value);
+            pos = end;
+            segment = rest;
         } else {
             // h = -128 is a no-op.
+            segment = rest;
+        }
+    }
+    pos
+}
+
+/// Encode a row of bytes into the given RLE segment
+/// with the PackBits algorithm.
+///
+/// Runs of at least 3 equal bytes become replicate runs,
+/// all other bytes are gathered into literal runs.
+fn encode_rle_segment_row(row: &[u8], out: &mut Vec<u8>) {
+    fn write_literal(data: &[u8], out: &mut Vec<u8>) {
+        for chunk in data.chunks(128) {
+            out.push((chunk.len() - 1) as u8);
+            out.extend_from_slice(chunk);
         }
     }
-    Ok(pos)
 
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < row.len() {
+        let run = row[i..]
+            .iter()
+            .take(128)
+            .take_while(|&&b| b == row[i])
+            .count();
+        if run >= 3 {
+            write_literal(&row[literal_start..i], out);
+            out.push((1 - run as i16) as u8);
+            out.push(row[i]);
+            i += run;
+            literal_start = i;
+        } else {
+            i += 1;
+        }
+    }
+    write_literal(&row[literal_start..], out);
 }
 
 /// PackBits Reader from the image-tiff crate
@@ -198,6 +620,503 @@ impl Read for PackBitsReader {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::adapters::testing::TestPixelData;
+
+    /// Encode the given native object frame by frame
+    /// and decode it back with the same adapter.
+    fn roundtrip(native: &TestPixelData) -> Vec<u8> {
+        let fragments: Vec<_> = (0..native.number_of_frames as u32)
+            .map(|frame| {
+                let mut fragment = Vec::new();
+                RLELosslessAdapter
+                    .encode_frame(native, frame, EncodeOptions::new(), &mut fragment)
+                    .unwrap();
+                assert_eq!(fragment.len() % 2, 0);
+                fragment
+            })
+            .collect();
+
+        let encapsulated = TestPixelData {
+            planar_configuration: 0,
+            native: None,
+            fragments,
+            ..*native
+        };
+        let mut decoded = Vec::new();
+        RLELosslessAdapter
+            .decode(&encapsulated, &mut decoded)
+            .unwrap();
+
+        // decoding frame by frame yields the same pixel data
+        let mut frames = Vec::new();
+        for frame in 0..native.number_of_frames as u32 {
+            RLELosslessAdapter
+                .decode_frame(&encapsulated, frame, &mut frames)
+                .unwrap();
+        }
+        assert_eq!(frames, decoded);
+
+        decoded
+    }
+
+    #[test]
+    fn test_rle_encode_roundtrip() {
+        // 16-bit monochrome, 2 frames, with long runs and noise
+        let pixels: Vec<u8> = (0..2 * 8 * 300_u32)
+            .flat_map(|i| {
+                let v = if i % 300 < 150 {
+                    0x0400
+                } else {
+                    (i * 7919) as u16
+                };
+                v.to_le_bytes()
+            })
+            .collect();
+        let native = TestPixelData {
+            rows: 8,
+            cols: 300,
+            samples_per_pixel: 1,
+            bits_allocated: 16,
+            bits_stored: 16,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 2,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        assert_eq!(roundtrip(&native), pixels);
+
+        // encoding all frames at once concatenates the fragments
+        let mut all = Vec::new();
+        RLELosslessAdapter
+            .encode(&native, EncodeOptions::new(), &mut all)
+            .unwrap();
+        assert_eq!(LittleEndian::read_u32(&all[0..4]), 2);
+
+        // 8-bit RGB, interleaved
+        let pixels: Vec<u8> = (0..5 * 6_u32)
+            .flat_map(|i| [(i * 3) as u8, 0x80, (i / 6) as u8])
+            .collect();
+        let native = TestPixelData {
+            rows: 5,
+            cols: 6,
+            samples_per_pixel: 3,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        assert_eq!(roundtrip(&native), pixels);
+
+        // same image, with one plane per sample
+        let planar: Vec<u8> = (0..3)
+            .flat_map(|s| pixels.iter().skip(s).step_by(3).copied())
+            .collect();
+        let native = TestPixelData {
+            planar_configuration: 1,
+            native: Some(planar),
+            ..native
+        };
+        assert_eq!(roundtrip(&native), pixels);
+    }
+
+    #[test]
+    fn test_rle_32bit_roundtrip() {
+        // 32-bit monochrome, 2 frames
+        let pixels: Vec<u8> = (0..2 * 3 * 10_u32)
+            .flat_map(|i| {
+                let v = if i % 10 < 4 {
+                    0x0102_0304
+                } else {
+                    i.wrapping_mul(0x9E37_79B9)
+                };
+                v.to_le_bytes()
+            })
+            .collect();
+        let native = TestPixelData {
+            rows: 3,
+            cols: 10,
+            samples_per_pixel: 1,
+            bits_allocated: 32,
+            bits_stored: 32,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 2,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        assert_eq!(roundtrip(&native), pixels);
+
+        // 32-bit, 3 samples per pixel: 12 segments
+        let pixels: Vec<u8> = (0..2 * 5_u32)
+            .flat_map(|i| [i, i << 8, u32::MAX - i])
+            .flat_map(u32::to_le_bytes)
+            .collect();
+        let native = TestPixelData {
+            rows: 2,
+            cols: 5,
+            samples_per_pixel: 3,
+            bits_allocated: 32,
+            bits_stored: 32,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        assert_eq!(roundtrip(&native), pixels);
+    }
+
+    /// A writer which fails once it has received the given number of bytes.
+    struct LimitedWriter {
+        data: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for LimitedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.data.len() + buf.len() > self.limit {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "limit reached"));
+            }
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rle_decode_to_writer() {
+        // 8-bit monochrome, 3 frames
+        let pixels: Vec<u8> = (0..3 * 4 * 5_u32).map(|i| (i * 11) as u8).collect();
+        let native = TestPixelData {
+            rows: 4,
+            cols: 5,
+            samples_per_pixel: 1,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 3,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        let fragments = (0..3)
+            .map(|frame| {
+                let mut fragment = Vec::new();
+                RLELosslessAdapter
+                    .encode_frame(&native, frame, EncodeOptions::new(), &mut fragment)
+                    .unwrap();
+                fragment
+            })
+            .collect();
+        let encapsulated = TestPixelData {
+            native: None,
+            fragments,
+            ..native
+        };
+
+        let mut out = Vec::new();
+        RLELosslessAdapter
+            .decode_to_writer(&encapsulated, &mut out)
+            .unwrap();
+        assert_eq!(out, pixels);
+
+        // frames are written as soon as they are decoded
+        let mut out = LimitedWriter {
+            data: Vec::new(),
+            limit: 2 * 20,
+        };
+        let err = RLELosslessAdapter
+            .decode_to_writer(&encapsulated, &mut out)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::adapters::DecodeError::WriteOutput { .. }
+        ));
+        assert_eq!(out.data, &pixels[..40]);
+    }
+
+    #[test]
+    fn test_rle_decode_frame_in_many_fragments() {
+        // 8-bit monochrome, 1 frame split across 3 fragments
+        let pixels: Vec<u8> = (0..6 * 7_u32).map(|i| (i * 5) as u8).collect();
+        let native = TestPixelData {
+            rows: 6,
+            cols: 7,
+            samples_per_pixel: 1,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        let mut encoded = Vec::new();
+        RLELosslessAdapter
+            .encode_frame(&native, 0, EncodeOptions::new(), &mut encoded)
+            .unwrap();
+        let fragments = vec![
+            encoded[..40].to_vec(),
+            encoded[40..70].to_vec(),
+            encoded[70..].to_vec(),
+        ];
+        let encapsulated = TestPixelData {
+            native: None,
+            fragments,
+            ..native
+        };
+
+        let mut decoded = Vec::new();
+        RLELosslessAdapter
+            .decode(&encapsulated, &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, pixels);
+
+        let mut frame = Vec::new();
+        RLELosslessAdapter
+            .decode_frame(&encapsulated, 0, &mut frame)
+            .unwrap();
+        assert_eq!(frame, pixels);
+    }
+
+    #[test]
+    fn test_rle_lenient_decode() {
+        // 16-bit monochrome, 1 frame of a single value
+        let pixels: Vec<u8> = [0x34_u8, 0x12].repeat(4 * 4);
+        let native = TestPixelData {
+            rows: 4,
+            cols: 4,
+            samples_per_pixel: 1,
+            bits_allocated: 16,
+            bits_stored: 16,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        let mut encoded = Vec::new();
+        RLELosslessAdapter
+            .encode_frame(&native, 0, EncodeOptions::new(), &mut encoded)
+            .unwrap();
+
+        // second segment (LSB) loses its last row
+        let truncated = TestPixelData {
+            native: None,
+            fragments: vec![encoded[..encoded.len() - 2].to_vec()],
+            ..native
+        };
+        let mut out = Vec::new();
+        assert!(RLELosslessAdapter.decode(&truncated, &mut out).is_err());
+        LenientRLELosslessAdapter
+            .decode(&truncated, &mut out)
+            .unwrap();
+        assert_eq!(out[..24], pixels[..24]);
+        assert_eq!(out[24..], [0x00_u8, 0x12].repeat(4));
+
+        // header declares only the first (MSB) segment
+        let mut fragment = encoded.clone();
+        fragment[0] = 1;
+        let missing = TestPixelData {
+            native: None,
+            fragments: vec![fragment],
+            ..native
+        };
+        assert!(RLELosslessAdapter.decode(&missing, &mut out).is_err());
+        LenientRLELosslessAdapter
+            .decode(&missing, &mut out)
+            .unwrap();
+        assert_eq!(out, [0x00_u8, 0x12].repeat(4 * 4));
+
+        // well formed data is decoded as usual
+        let valid = TestPixelData {
+            native: None,
+            fragments: vec![encoded],
+            ..native
+        };
+        LenientRLELosslessAdapter.decode(&valid, &mut out).unwrap();
+        assert_eq!(out, pixels);
+    }
+
+    #[test]
+    fn test_rle_decode_region() {
+        use crate::adapters::Region;
+
+        // 8-bit RGB, 2 frames of 4x5 pixels
+        let pixels: Vec<u8> = (0..2 * 4 * 5_u32)
+            .flat_map(|i| [i as u8, 0x80, (i * 3) as u8])
+            .collect();
+        let native = TestPixelData {
+            rows: 4,
+            cols: 5,
+            samples_per_pixel: 3,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 2,
+            native: Some(pixels.clone()),
+            fragments: vec![],
+        };
+        let fragments = (0..2)
+            .map(|frame| {
+                let mut fragment = Vec::new();
+                RLELosslessAdapter
+                    .encode_frame(&native, frame, EncodeOptions::new(), &mut fragment)
+                    .unwrap();
+                fragment
+            })
+            .collect();
+        let encapsulated = TestPixelData {
+            native: None,
+            fragments,
+            ..native
+        };
+
+        // columns 1 to 3 of rows 2 and 3 of the second frame
+        let mut region = Vec::new();
+        RLELosslessAdapter
+            .decode_region(&encapsulated, 1, Region::new(1, 2, 3, 2), &mut region)
+            .unwrap();
+        let expected: Vec<u8> = [20 + 2 * 5 + 1, 20 + 3 * 5 + 1]
+            .iter()
+            .flat_map(|&start| pixels[start * 3..(start + 3) * 3].to_vec())
+            .collect();
+        assert_eq!(region, expected);
+
+        // region out of bounds
+        assert!(RLELosslessAdapter
+            .decode_region(&encapsulated, 0, Region::new(3, 0, 3, 1), &mut region)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rle_decode_at_resolution() {
+        use crate::adapters::reduced_dimensions;
+
+        // 16-bit monochrome, 1 frame of 5x7 pixels
+        let pixels: Vec<u16> = (0..5 * 7).map(|i| i * 1000).collect();
+        let native = TestPixelData {
+            rows: 5,
+            cols: 7,
+            samples_per_pixel: 1,
+            bits_allocated: 16,
+            bits_stored: 16,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(pixels.iter().flat_map(|p| p.to_le_bytes()).collect()),
+            fragments: vec![],
+        };
+        let mut fragment = Vec::new();
+        RLELosslessAdapter
+            .encode_frame(&native, 0, EncodeOptions::new(), &mut fragment)
+            .unwrap();
+        let encapsulated = TestPixelData {
+            native: None,
+            fragments: vec![fragment],
+            ..native
+        };
+
+        assert_eq!(reduced_dimensions(7, 5, 1), (4, 3));
+        let mut out = Vec::new();
+        RLELosslessAdapter
+            .decode_at_resolution(&encapsulated, 0, 1, &mut out)
+            .unwrap();
+        let expected: Vec<u8> = [0_u16, 2, 4, 6, 14, 16, 18, 20, 28, 30, 32, 34]
+            .iter()
+            .flat_map(|&i| pixels[i as usize].to_le_bytes())
+            .collect();
+        assert_eq!(out, expected);
+
+        // level 0 is the full resolution
+        let mut out = Vec::new();
+        RLELosslessAdapter
+            .decode_at_resolution(&encapsulated, 0, 0, &mut out)
+            .unwrap();
+        assert_eq!(Some(out), native.native);
+    }
+
+    #[test]
+    fn test_rle_encode_rejects_encapsulated() {
+        let encapsulated = TestPixelData {
+            rows: 1,
+            cols: 1,
+            samples_per_pixel: 1,
+            bits_allocated: 8,
+            bits_stored: 8,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: None,
+            fragments: vec![vec![0; 66]],
+        };
+        let mut out = Vec::new();
+        assert!(matches!(
+            RLELosslessAdapter.encode_frame(&encapsulated, 0, EncodeOptions::new(), &mut out),
+            Err(EncodeError::NotNative)
+        ));
+    }
+
+    #[test]
+    fn test_rle_encode_rejects_unsupported_bits_allocated() {
+        // 24-bit samples could be split into 3 segments,
+        // but they cannot be decoded
+        let native = TestPixelData {
+            rows: 2,
+            cols: 2,
+            samples_per_pixel: 1,
+            bits_allocated: 24,
+            bits_stored: 24,
+            pixel_representation: 0,
+            planar_configuration: 0,
+            number_of_frames: 1,
+            native: Some(vec![0x11; 12]),
+            fragments: vec![],
+        };
+        let mut out = Vec::new();
+        assert!(matches!(
+            RLELosslessAdapter.encode_frame(&native, 0, EncodeOptions::new(), &mut out),
+            Err(EncodeError::CustomEncodeError { .. })
+        ));
+        assert!(out.is_empty());
+
+        // nor can an RLE fragment of such samples
+        let encapsulated = TestPixelData {
+            native: None,
+            fragments: vec![[&3_u32.to_le_bytes()[..], &[0; 60]].concat()],
+            ..native
+        };
+        assert!(RLELosslessAdapter.decode(&encapsulated, &mut out).is_err());
+    }
+
+    #[test]
+    fn test_rle_decode_segment() {
+        // replicate run, no-op, literal run
+        let segment = [0xFE, 0xAA, 0x80, 0x02, 0x01, 0x02, 0x03];
+        let mut out = [0; 6];
+        assert_eq!(decode_rle_segment(&segment, &mut out), 6);
+        assert_eq!(out, [0xAA, 0xAA, 0xAA, 0x01, 0x02, 0x03]);
+
+        // runs beyond the end of the buffer are truncated
+        let mut out = [0; 4];
+        assert_eq!(decode_rle_segment(&segment, &mut out), 4);
+        assert_eq!(out, [0xAA, 0xAA, 0xAA, 0x01]);
+
+        // segment ends too early
+        let mut out = [0; 8];
+        assert_eq!(decode_rle_segment(&segment, &mut out), 6);
+        let mut out = [0; 8];
+        assert_eq!(decode_rle_segment(&segment[..5], &mut out), 4);
+        assert_eq!(out[..4], [0xAA, 0xAA, 0xAA, 0x01]);
+    }
 
     #[test]
     fn test_packbits() {
@@ -0,0 +1,348 @@
+//! Little endian data element decoder
+//! which detects elements in the other VR encoding.
+//!
+//! Some files declare a transfer syntax in the file meta group
+//! which does not match how the data set is encoded,
+//! or mix implicit VR and explicit VR elements in the same data set.
+//! The decoder in this module reads each element header
+//! in the declared encoding (implicit or explicit VR),
+//! unless its bytes only make sense in the other encoding,
+//! in which case the element is read in the other encoding
+//! and the deviation is recorded.
+
+use crate::decode::basic::LittleEndianBasicDecoder;
+use crate::decode::*;
+use byteordered::byteorder::{ByteOrder, LittleEndian};
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::header::{DataElementHeader, Length, SequenceItemHeader};
+use dicom_core::{Tag, VR};
+use dicom_dictionary_std::StandardDataDictionary;
+use snafu::ResultExt;
+use std::fmt;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+/// An element which was not encoded in the declared VR encoding.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct VrEncodingDeviation {
+    /// the tag of the element
+    pub tag: Tag,
+    /// whether the element was found to be in explicit VR
+    /// (in implicit VR otherwise)
+    pub explicit_vr: bool,
+}
+
+/// A shared record of the elements which were read
+/// in a VR encoding other than the declared one.
+///
+/// Clones of this log share the same records.
+#[derive(Debug, Default, Clone)]
+pub struct VrDeviationLog(Arc<Mutex<Vec<VrEncodingDeviation>>>);
+
+impl VrDeviationLog {
+    /// Retrieve a copy of all deviations recorded so far.
+    pub fn to_vec(&self) -> Vec<VrEncodingDeviation> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Remove and return all deviations recorded so far.
+    pub fn take(&self) -> Vec<VrEncodingDeviation> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn push(&self, deviation: VrEncodingDeviation) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(deviation);
+    }
+}
+
+/// A data element decoder for little endian transfer syntaxes
+/// which detects elements encoded in the other VR encoding.
+///
+/// In explicit VR,
+/// an element with invalid VR bytes is read in implicit VR.
+/// In implicit VR,
+/// an element with valid VR bytes after the tag
+/// is read in explicit VR
+/// if the VR matches the one in the data dictionary,
+/// or if the attribute is unknown.
+pub struct AutoVRLittleEndianDecoder<D = StandardDataDictionary> {
+    dict: D,
+    explicit_vr: bool,
+    basic: LittleEndianBasicDecoder,
+    deviations: VrDeviationLog,
+}
+
+impl<D> fmt::Debug for AutoVRLittleEndianDecoder<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AutoVRLittleEndianDecoder")
+            .field("dict", &"«omitted»")
+            .field("explicit_vr", &self.explicit_vr)
+            .field("deviations", &self.deviations)
+            .finish()
+    }
+}
+
+impl AutoVRLittleEndianDecoder<StandardDataDictionary> {
+    /// Create a decoder for the given declared VR encoding,
+    /// using the standard data dictionary.
+    pub fn new(explicit_vr: bool) -> Self {
+        Self::with_dict(StandardDataDictionary, explicit_vr)
+    }
+}
+
+impl<D> AutoVRLittleEndianDecoder<D>
+where
+    D: DataDictionary,
+{
+    /// Create a decoder for the given declared VR encoding,
+    /// using a custom data dictionary.
+    pub fn with_dict(dictionary: D, explicit_vr: bool) -> Self {
+        AutoVRLittleEndianDecoder {
+            dict: dictionary,
+            explicit_vr,
+            basic: LittleEndianBasicDecoder,
+            deviations: VrDeviationLog::default(),
+        }
+    }
+
+    /// Retrieve a handle to the log of elements
+    /// read in a VR encoding other than the declared one.
+    pub fn deviations(&self) -> VrDeviationLog {
+        self.deviations.clone()
+    }
+
+    /// Resolve the VR of an element in implicit VR.
+    fn implicit_vr(&self, tag: Tag) -> VR {
+        // the VR of OW must be used for Pixel Data (7FE0,0010)
+        // and Overlay Data (60xx, 3000)
+        if tag == Tag(0x7FE0, 0x0010) || (tag.0 >> 8 == 0x60 && tag.1 == 0x3000) {
+            VR::OW
+        } else {
+            self.dict
+                .by_tag(tag)
+                .map(|entry| entry.vr())
+                .unwrap_or(VR::UN)
+        }
+    }
+
+    /// Check whether the two bytes after the tag
+    /// of an element declared in implicit VR
+    /// should rather be taken as an explicit VR.
+    fn looks_explicit(&self, tag: Tag, vr: VR) -> bool {
+        match self.dict.by_tag(tag) {
+            Some(entry) => entry.vr() == vr,
+            None => true,
+        }
+    }
+
+    fn record(&self, tag: Tag, explicit_vr: bool) {
+        tracing::warn!(
+            "Element {} is encoded in {} VR, unlike the rest of the data set",
+            tag,
+            if explicit_vr { "explicit" } else { "implicit" }
+        );
+        self.deviations
+            .push(VrEncodingDeviation { tag, explicit_vr });
+    }
+}
+
+impl<D> Decode for AutoVRLittleEndianDecoder<D>
+where
+    D: DataDictionary,
+{
+    fn decode_header<S>(&self, source: &mut S) -> Result<(DataElementHeader, usize)>
+    where
+        S: ?Sized + Read,
+    {
+        // tag and the next 4 bytes are in place in both encodings
+        let mut buf = [0u8; 8];
+        source
+            .read_exact(&mut buf[0..4])
+            .context(ReadHeaderTagSnafu)?;
+        let tag = Tag(
+            LittleEndian::read_u16(&buf[0..2]),
+            LittleEndian::read_u16(&buf[2..4]),
+        );
+        source.read_exact(&mut buf[4..8]).context(ReadLengthSnafu)?;
+
+        if tag.0 == 0xFFFE {
+            // item delimiters do not have VR or reserved field
+            let len = LittleEndian::read_u32(&buf[4..8]);
+            return Ok((DataElementHeader::new(tag, VR::UN, Length(len)), 8));
+        }
+
+        let explicit_vr = match VR::from_binary([buf[4], buf[5]]) {
+            Some(vr) if self.explicit_vr || self.looks_explicit(tag, vr) => Some(vr),
+            // accept lowercase VRs as the explicit VR decoder does
+            None if self.explicit_vr => {
+                VR::from_binary([buf[4].to_ascii_uppercase(), buf[5].to_ascii_uppercase()])
+            }
+            _ => None,
+        };
+
+        match explicit_vr {
+            Some(vr) => {
+                if !self.explicit_vr {
+                    self.record(tag, true);
+                }
+                match vr {
+                    VR::OB
+                    | VR::OD
+                    | VR::OF
+                    | VR::OL
+                    | VR::OW
+                    | VR::SQ
+                    | VR::UC
+                    | VR::UR
+                    | VR::UT
+                    | VR::UN => {
+                        // 2 reserved bytes were read, then 4 bytes for data length
+                        let mut len = [0u8; 4];
+                        source.read_exact(&mut len).context(ReadLengthSnafu)?;
+                        let len = LittleEndian::read_u32(&len);
+                        Ok((DataElementHeader::new(tag, vr, Length(len)), 12))
+                    }
+                    _ => {
+                        let len = u32::from(LittleEndian::read_u16(&buf[6..8]));
+                        Ok((DataElementHeader::new(tag, vr, Length(len)), 8))
+                    }
+                }
+            }
+            None => {
+                if self.explicit_vr {
+                    self.record(tag, false);
+                }
+                let len = LittleEndian::read_u32(&buf[4..8]);
+                Ok((
+                    DataElementHeader::new(tag, self.implicit_vr(tag), Length(len)),
+                    8,
+                ))
+            }
+        }
+    }
+
+    fn decode_item_header<S>(&self, source: &mut S) -> Result<SequenceItemHeader>
+    where
+        S: ?Sized + Read,
+    {
+        let mut buf = [0u8; 8];
+        source.read_exact(&mut buf).context(ReadItemHeaderSnafu)?;
+        // retrieve tag
+        let group = LittleEndian::read_u16(&buf[0..2]);
+        let element = LittleEndian::read_u16(&buf[2..4]);
+        let len = LittleEndian::read_u32(&buf[4..8]);
+
+        SequenceItemHeader::new((group, element), Length(len)).context(BadSequenceHeaderSnafu)
+    }
+
+    fn decode_tag<S>(&self, source: &mut S) -> Result<Tag>
+    where
+        S: ?Sized + Read,
+    {
+        self.basic.decode_tag(source).context(ReadTagSnafu)
+    }
+}
+
+impl<S: ?Sized, D> DecodeFrom<S> for AutoVRLittleEndianDecoder<D>
+where
+    S: Read,
+    D: DataDictionary,
+{
+    #[inline]
+    fn decode_header(&self, source: &mut S) -> Result<(DataElementHeader, usize)> {
+        Decode::decode_header(self, source)
+    }
+
+    #[inline]
+    fn decode_item_header(&self, source: &mut S) -> Result<SequenceItemHeader> {
+        Decode::decode_item_header(self, source)
+    }
+
+    #[inline]
+    fn decode_tag(&self, source: &mut S) -> Result<Tag> {
+        Decode::decode_tag(self, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoVRLittleEndianDecoder, VrEncodingDeviation};
+    use crate::decode::Decode;
+    use dicom_core::header::{HasLength, Header, Length};
+    use dicom_core::{Tag, VR};
+
+    #[rustfmt::skip]
+    const MIXED: &[u8] = &[
+        // (0008,0060) Modality, explicit VR CS, length 2
+        0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x02, 0x00,
+        b'M', b'R',
+        // (0010,0010) PatientName, implicit VR, length 4
+        0x10, 0x00, 0x10, 0x00, 0x04, 0x00, 0x00, 0x00,
+        b'D', b'o', b'e', b' ',
+        // (0009,1001) private, explicit VR OB, length 2
+        0x09, 0x00, 0x01, 0x10, b'O', b'B', 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x01, 0x02,
+    ];
+
+    fn read_headers(decoder: &AutoVRLittleEndianDecoder) -> Vec<(Tag, VR, Length, usize)> {
+        let mut cursor = MIXED;
+        let mut headers = Vec::new();
+        while !cursor.is_empty() {
+            let (header, bytes_read) = decoder.decode_header(&mut cursor).unwrap();
+            let len = header.length().0 as usize;
+            cursor = &cursor[len..];
+            headers.push((header.tag(), header.vr(), header.length(), bytes_read));
+        }
+        headers
+    }
+
+    #[test]
+    fn read_implicit_element_in_explicit_vr() {
+        let decoder = AutoVRLittleEndianDecoder::new(true);
+        assert_eq!(
+            read_headers(&decoder),
+            vec![
+                (Tag(0x0008, 0x0060), VR::CS, Length(2), 8),
+                (Tag(0x0010, 0x0010), VR::PN, Length(4), 8),
+                (Tag(0x0009, 0x1001), VR::OB, Length(2), 12),
+            ]
+        );
+        assert_eq!(
+            decoder.deviations().take(),
+            vec![VrEncodingDeviation {
+                tag: Tag(0x0010, 0x0010),
+                explicit_vr: false,
+            }]
+        );
+        assert!(decoder.deviations().to_vec().is_empty());
+    }
+
+    #[test]
+    fn read_explicit_elements_in_implicit_vr() {
+        let decoder = AutoVRLittleEndianDecoder::new(false);
+        assert_eq!(
+            read_headers(&decoder),
+            vec![
+                (Tag(0x0008, 0x0060), VR::CS, Length(2), 8),
+                (Tag(0x0010, 0x0010), VR::PN, Length(4), 8),
+                (Tag(0x0009, 0x1001), VR::OB, Length(2), 12),
+            ]
+        );
+        assert_eq!(
+            decoder.deviations().to_vec(),
+            vec![
+                VrEncodingDeviation {
+                    tag: Tag(0x0008, 0x0060),
+                    explicit_vr: true,
+                },
+                VrEncodingDeviation {
+                    tag: Tag(0x0009, 0x1001),
+                    explicit_vr: true,
+                },
+            ]
+        );
+    }
+}
@@ -514,4 +514,51 @@ mod tests {
         be.decode_ul_into(data, &mut out_be).unwrap();
         assert_eq!(out_be, [0xC33C_33CC, 0x55AA_55AA]);
     }
+
+    #[test]
+    fn test_read_signed_and_floats_into() {
+        let values = [1.5_f32, -2.25, 1e-3];
+        let data_le: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let data_be: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+
+        for (decoder, data) in [
+            (BasicDecoder::new(Endianness::Little), &data_le),
+            (BasicDecoder::new(Endianness::Big), &data_be),
+        ] {
+            let mut out = [0.; 3];
+            decoder.decode_fl_into(&data[..], &mut out).unwrap();
+            assert_eq!(out, values);
+
+            let mut out = [0; 6];
+            decoder.decode_ss_into(&data[..], &mut out).unwrap();
+            let expected: Vec<i16> = data
+                .chunks(2)
+                .map(|b| match decoder.endianness() {
+                    Endianness::Little => i16::from_le_bytes([b[0], b[1]]),
+                    Endianness::Big => i16::from_be_bytes([b[0], b[1]]),
+                })
+                .collect();
+            assert_eq!(&out[..], &expected[..]);
+
+            // not enough data for the whole slice
+            let mut out = [0.; 2];
+            assert!(decoder.decode_fd_into(&data[..], &mut out).is_err());
+        }
+
+        let values = [-1_i64, 0x0102_0304_0506_0708];
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        let mut out = [0; 2];
+        BigEndianBasicDecoder
+            .decode_sv_into(&data[..], &mut out)
+            .unwrap();
+        assert_eq!(out, values);
+
+        let values = [-0.5_f64, 1e100];
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut out = [0.; 2];
+        LittleEndianBasicDecoder
+            .decode_fd_into(&data[..], &mut out)
+            .unwrap();
+        assert_eq!(out, values);
+    }
 }
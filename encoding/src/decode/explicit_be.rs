@@ -39,7 +39,7 @@ impl Decode for ExplicitVRBigEndianDecoder {
 
         // retrieve explicit VR
         source.read_exact(&mut buf[0..2]).context(ReadVrSnafu)?;
-        let vr = VR::from_binary([buf[0], buf[1]]).unwrap_or(VR::UN);
+        let vr = resolve_explicit_vr(Tag(group, element), [buf[0], buf[1]]);
 
         let bytes_read;
 
@@ -38,7 +38,7 @@ impl Decode for ExplicitVRLittleEndianDecoder {
 
         // retrieve explicit VR
         source.read_exact(&mut buf[0..2]).context(ReadVrSnafu)?;
-        let vr = VR::from_binary([buf[0], buf[1]]).unwrap_or(VR::UN);
+        let vr = resolve_explicit_vr(Tag(group, element), [buf[0], buf[1]]);
         let bytes_read;
 
         // retrieve data length
@@ -252,4 +252,52 @@ mod tests {
             assert_eq!(elem.length(), Length(0));
         }
     }
+
+    #[test]
+    fn decode_invalid_vr_bytes() {
+        #[rustfmt::skip]
+        const RAW_INVALID_VR: &[u8] = &[
+            // (0010,0010) PatientName, VR "??", length 4
+            0x10, 0x00, 0x10, 0x00, b'?', b'?', 0x04, 0x00,
+            b'D', b'o', b'e', b' ',
+            // (7FE0,0010) PixelData, VR "ob", length 2
+            0xE0, 0x7F, 0x10, 0x00, b'o', b'b', 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+            // (0009,1010) private element, VR "  ", length 2
+            0x09, 0x00, 0x10, 0x10, b' ', b' ', 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+
+        let dec = ExplicitVRLittleEndianDecoder::default();
+        let mut cursor = Cursor::new(RAW_INVALID_VR);
+
+        // VR is taken from the dictionary
+        let (elem, bytes_read) = dec
+            .decode_header(&mut cursor)
+            .expect("should recover from an invalid VR");
+        assert_eq!(elem.tag(), Tag(0x0010, 0x0010));
+        assert_eq!(elem.vr(), VR::PN);
+        assert_eq!(elem.length(), Length(4));
+        assert_eq!(bytes_read, 8);
+        cursor.seek(SeekFrom::Current(4)).unwrap();
+
+        // lowercase VR is accepted
+        let (elem, bytes_read) = dec
+            .decode_header(&mut cursor)
+            .expect("should recover from a lowercase VR");
+        assert_eq!(elem.tag(), Tag(0x7FE0, 0x0010));
+        assert_eq!(elem.vr(), VR::OB);
+        assert_eq!(elem.length(), Length(2));
+        assert_eq!(bytes_read, 12);
+        cursor.seek(SeekFrom::Current(2)).unwrap();
+
+        // unknown attributes fall back to UN
+        let (elem, bytes_read) = dec
+            .decode_header(&mut cursor)
+            .expect("should recover from an invalid VR");
+        assert_eq!(elem.tag(), Tag(0x0009, 0x1010));
+        assert_eq!(elem.vr(), VR::UN);
+        assert_eq!(elem.length(), Length(2));
+        assert_eq!(bytes_read, 12);
+    }
 }
@@ -3,11 +3,14 @@
 use self::explicit_le::ExplicitVRLittleEndianDecoder;
 use self::implicit_le::{ImplicitVRLittleEndianDecoder, StandardImplicitVRLittleEndianDecoder};
 use byteordered::Endianness;
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
 use dicom_core::header::{DataElementHeader, SequenceItemHeader};
-use dicom_core::Tag;
+use dicom_core::{Tag, VR};
+use dicom_dictionary_std::StandardDataDictionary;
 use snafu::{Backtrace, Snafu};
 use std::io::{self, Read};
 
+pub mod auto_le;
 pub mod basic;
 pub mod explicit_be;
 pub mod explicit_le;
@@ -77,6 +80,36 @@ pub fn file_header_decoder() -> ExplicitVRLittleEndianDecoder {
     ExplicitVRLittleEndianDecoder::default()
 }
 
+/// Resolve the value representation of an element
+/// from the two VR bytes read in an explicit VR transfer syntax.
+///
+/// Files in the wild may contain invalid VR bytes,
+/// such as lowercase letters, spaces, or `??`.
+/// Rather than failing,
+/// a lowercase VR is taken as its uppercase counterpart,
+/// and any other invalid VR is replaced by
+/// the VR of the attribute in the standard data dictionary,
+/// or UN if the attribute is unknown.
+/// A warning is emitted whenever the VR has to be recovered.
+pub(crate) fn resolve_explicit_vr(tag: Tag, bytes: [u8; 2]) -> VR {
+    if let Some(vr) = VR::from_binary(bytes) {
+        return vr;
+    }
+    let vr = VR::from_binary(bytes.map(|b| b.to_ascii_uppercase())).unwrap_or_else(|| {
+        StandardDataDictionary
+            .by_tag(tag)
+            .map(|entry| entry.vr())
+            .unwrap_or(VR::UN)
+    });
+    tracing::warn!(
+        "Invalid VR bytes {:02X?} in element {}, assuming {}",
+        bytes,
+        tag,
+        vr
+    );
+    vr
+}
+
 /** Type trait for reading and decoding basic data values from a data source.
  *
  * This trait aims to provide methods for reading binary numbers based on the
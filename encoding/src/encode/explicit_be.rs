@@ -83,7 +83,7 @@ impl Encode for ExplicitVRBigEndianEncoder {
     where
         W: Write,
     {
-        let mut buf = [0u8, 4];
+        let mut buf = [0u8; 4];
         BigEndian::write_u16(&mut buf[..], tag.group());
         BigEndian::write_u16(&mut buf[2..], tag.element());
         to.write_all(&buf).context(WriteTagSnafu)
@@ -189,7 +189,7 @@ mod tests {
     use super::ExplicitVRBigEndianEncoder;
     use crate::encode::Encode;
     use dicom_core::header::{DataElementHeader, Length};
-    use dicom_core::{Tag, VR};
+    use dicom_core::{PrimitiveValue, Tag, VR};
     use std::io::{Cursor, Write};
 
     type Result = std::result::Result<(), Box<dyn std::error::Error>>;
@@ -297,4 +297,32 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn encode_tag_and_values() -> Result {
+        let enc = ExplicitVRBigEndianEncoder::default();
+        let mut out = Vec::new();
+
+        enc.encode_tag(&mut out, Tag(0x0028, 0x0010))?;
+        assert_eq!(&out[..], &[0x00, 0x28, 0x00, 0x10]);
+
+        out.clear();
+        let bytes_written =
+            enc.encode_primitive(&mut out, &PrimitiveValue::U16([0x0102, 0x0304][..].into()))?;
+        assert_eq!(bytes_written, 4);
+        assert_eq!(&out[..], &[0x01, 0x02, 0x03, 0x04]);
+
+        out.clear();
+        enc.encode_primitive(&mut out, &PrimitiveValue::from(1.5_f64))?;
+        assert_eq!(&out[..], &1.5_f64.to_be_bytes());
+
+        out.clear();
+        enc.encode_primitive(
+            &mut out,
+            &PrimitiveValue::Tags([Tag(0x7FE0, 0x0010)][..].into()),
+        )?;
+        assert_eq!(&out[..], &[0x7F, 0xE0, 0x00, 0x10]);
+
+        Ok(())
+    }
 }
@@ -14,6 +14,19 @@
 //!
 //! For the time being, all APIs are based on synchronous I/O.
 //!
+//! ## Cargo features
+//!
+//! Each built-in pixel data codec in [`adapters`]
+//! is behind a Cargo feature:
+//! `jpeg`, `jpeg-ls`, `jpeg2000` and `rle`.
+//! They are all enabled by default through the `codecs` feature.
+//! Disable default features to build without any codec,
+//! leaving only the data set encoding and decoding primitives.
+//! The standard data dictionary is always a dependency,
+//! as the implicit VR decoders use it to resolve value representations.
+//! The `rayon` feature encodes the segments of RLE Lossless frames
+//! in parallel.
+//!
 //! [transfer syntax specifier]: ./transfer_syntax/index.html
 
 pub mod adapters;
@@ -18,7 +18,10 @@
 //!
 //! [`SpecificCharacterSet`]: ./enum.SpecificCharacterSet.html
 
-use encoding::all::{GB18030, ISO_8859_1, ISO_8859_2, ISO_8859_3, ISO_8859_4, ISO_8859_5, UTF_8};
+use encoding::all::{
+    EUC_JP, GB18030, GBK, ISO_8859_1, ISO_8859_2, ISO_8859_3, ISO_8859_4, ISO_8859_5, UTF_8,
+    WINDOWS_949,
+};
 use encoding::{DecoderTrap, EncoderTrap, Encoding, RawDecoder, StringWriter};
 use snafu::{Backtrace, Snafu};
 use std::borrow::Cow;
@@ -80,6 +83,19 @@ pub trait TextCodec {
     /// feature multiple text values by using the backslash character ('\')
     /// as the value delimiter.
     fn encode(&self, text: &str) -> EncodeResult<Vec<u8>>;
+
+    /// Decode the given byte buffer as a sequence of strings,
+    /// delimited by backslash characters ('\').
+    ///
+    /// The default implementation splits the buffer at each backslash byte
+    /// and decodes each part separately.
+    /// Codecs in which a backslash byte may be part of a multi-byte character
+    /// should override this method.
+    fn decode_values(&self, text: &[u8]) -> DecodeResult<Vec<String>> {
+        text.split(|b| *b == b'\\')
+            .map(|part| self.decode(part))
+            .collect()
+    }
 }
 
 impl<T: ?Sized> TextCodec for Box<T>
@@ -97,6 +113,10 @@ where
     fn encode(&self, text: &str) -> EncodeResult<Vec<u8>> {
         self.as_ref().encode(text)
     }
+
+    fn decode_values(&self, text: &[u8]) -> DecodeResult<Vec<String>> {
+        self.as_ref().decode_values(text)
+    }
 }
 
 impl<'a, T: ?Sized> TextCodec for &'a T
@@ -114,6 +134,10 @@ where
     fn encode(&self, text: &str) -> EncodeResult<Vec<u8>> {
         (**self).encode(text)
     }
+
+    fn decode_values(&self, text: &[u8]) -> DecodeResult<Vec<String>> {
+        (**self).decode_values(text)
+    }
 }
 
 /// Type alias for a type erased text codec.
@@ -147,6 +171,13 @@ pub enum SpecificCharacterSet {
     IsoIr192,
     /// **GB18030**: The Simplified Chinese character set.
     Gb18030,
+    /// **GBK**: The Simplified Chinese character set,
+    /// a subset of GB18030.
+    Gbk,
+    /// A combination of character sets with ISO 2022 code extensions,
+    /// switched through escape sequences,
+    /// as used in Japanese, Korean and Chinese data sets.
+    Iso2022(Iso2022CharacterSet),
     // Support for more text encodings is tracked in issue #40.
 }
 
@@ -180,10 +211,65 @@ impl SpecificCharacterSet {
             "ISO_IR_144" | "ISO_IR 144" | "ISO 2022 IR 144" => Some(IsoIr144),
             "ISO_IR_192" | "ISO_IR 192" => Some(IsoIr192),
             "GB18030" => Some(Gb18030),
+            "GBK" => Some(Gbk),
+            "ISO_IR_13" | "ISO_IR 13" | "ISO 2022 IR 13" => {
+                Some(Iso2022(Iso2022CharacterSet::new(CodeElement::IsoIr13, &[])))
+            }
+            // multi-byte code elements as the only value,
+            // taken as designated to G1 from the start
+            "ISO 2022 IR 58" => Some(Iso2022(Iso2022CharacterSet::new(CodeElement::IsoIr58, &[]))),
+            "ISO 2022 IR 149" => Some(Iso2022(Iso2022CharacterSet::new(
+                CodeElement::IsoIr149,
+                &[],
+            ))),
             _ => None,
         }
     }
 
+    /// Obtain the specific character set identified by
+    /// all values of a Specific Character Set (0008, 0005) element.
+    ///
+    /// A single value is resolved as in [`from_code`](Self::from_code).
+    /// Multiple values declare ISO 2022 code extensions,
+    /// in which case an empty first value stands for the default repertoire.
+    /// Returns `None` if any of the code elements is not supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_encoding::text::{CodeElement, Iso2022CharacterSet, SpecificCharacterSet};
+    /// let character_set = SpecificCharacterSet::from_codes(["", "ISO 2022 IR 149"]);
+    /// assert_eq!(
+    ///     character_set,
+    ///     Some(SpecificCharacterSet::Iso2022(Iso2022CharacterSet::new(
+    ///         CodeElement::IsoIr6,
+    ///         &[CodeElement::IsoIr149],
+    ///     ))),
+    /// );
+    /// ```
+    pub fn from_codes<'a, I>(codes: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut codes = codes.into_iter();
+        let first = codes.next().unwrap_or_default();
+        let extensions = codes
+            .map(CodeElement::from_term)
+            .collect::<Option<Vec<_>>>()?;
+        if extensions.is_empty() {
+            return Self::from_code(first);
+        }
+        let initial = if first.trim().is_empty() {
+            CodeElement::IsoIr6
+        } else {
+            CodeElement::from_term(first)?
+        };
+        Some(SpecificCharacterSet::Iso2022(Iso2022CharacterSet::new(
+            initial,
+            &extensions,
+        )))
+    }
+
     /// Retrieve the respective text codec.
     #[deprecated(since = "0.5.0", note = "Use this value as the codec itself")]
     pub fn codec(self) -> Option<Box<dyn TextCodec>> {
@@ -196,6 +282,8 @@ impl SpecificCharacterSet {
             SpecificCharacterSet::IsoIr144 => Some(Box::new(IsoIr144CharacterSetCodec)),
             SpecificCharacterSet::IsoIr192 => Some(Box::new(Utf8CharacterSetCodec)),
             SpecificCharacterSet::Gb18030 => Some(Box::new(Gb18030CharacterSetCodec)),
+            SpecificCharacterSet::Gbk => Some(Box::new(GbkCharacterSetCodec)),
+            SpecificCharacterSet::Iso2022(charset) => Some(Box::new(charset)),
         }
     }
 }
@@ -211,6 +299,8 @@ impl TextCodec for SpecificCharacterSet {
             SpecificCharacterSet::IsoIr144 => "ISO_IR 144",
             SpecificCharacterSet::IsoIr192 => "ISO_IR 192",
             SpecificCharacterSet::Gb18030 => "GB18030",
+            SpecificCharacterSet::Gbk => "GBK",
+            SpecificCharacterSet::Iso2022(charset) => charset.name(),
         }
     }
 
@@ -224,6 +314,8 @@ impl TextCodec for SpecificCharacterSet {
             SpecificCharacterSet::IsoIr144 => IsoIr144CharacterSetCodec.decode(text),
             SpecificCharacterSet::IsoIr192 => Utf8CharacterSetCodec.decode(text),
             SpecificCharacterSet::Gb18030 => Gb18030CharacterSetCodec.decode(text),
+            SpecificCharacterSet::Gbk => GbkCharacterSetCodec.decode(text),
+            SpecificCharacterSet::Iso2022(charset) => charset.decode(text),
         }
     }
 
@@ -237,6 +329,18 @@ impl TextCodec for SpecificCharacterSet {
             SpecificCharacterSet::IsoIr144 => IsoIr144CharacterSetCodec.encode(text),
             SpecificCharacterSet::IsoIr192 => Utf8CharacterSetCodec.encode(text),
             SpecificCharacterSet::Gb18030 => Gb18030CharacterSetCodec.encode(text),
+            SpecificCharacterSet::Gbk => GbkCharacterSetCodec.encode(text),
+            SpecificCharacterSet::Iso2022(charset) => charset.encode(text),
+        }
+    }
+
+    fn decode_values(&self, text: &[u8]) -> DecodeResult<Vec<String>> {
+        match self {
+            SpecificCharacterSet::Iso2022(charset) => charset.decode_values(text),
+            _ => text
+                .split(|b| *b == b'\\')
+                .map(|part| self.decode(part))
+                .collect(),
         }
     }
 }
@@ -315,6 +419,382 @@ decl_character_set!(IsoIr110CharacterSetCodec, "ISO_IR 110", ISO_8859_4);
 decl_character_set!(IsoIr144CharacterSetCodec, "ISO_IR 144", ISO_8859_5);
 decl_character_set!(Utf8CharacterSetCodec, "ISO_IR 192", UTF_8);
 decl_character_set!(Gb18030CharacterSetCodec, "GB18030", GB18030);
+decl_character_set!(GbkCharacterSetCodec, "GBK", GBK);
+
+/// The escape character (ESC),
+/// which starts every ISO 2022 escape sequence.
+const ESC: u8 = 0x1B;
+
+/// A code element which can be invoked
+/// through ISO 2022 escape sequences
+/// in a specific character set with code extensions
+/// (PS3.3 C.12.1.1.2 and PS3.5 Section 6.1.2.5).
+///
+/// Each code element is designated either to G0,
+/// for bytes with the most significant bit unset,
+/// or to G1, for bytes with the most significant bit set.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum CodeElement {
+    /// **ISO-IR 6** (G0): the default repertoire (ASCII).
+    IsoIr6,
+    /// **ISO-IR 13** (G1): JIS X 0201 Katakana.
+    IsoIr13,
+    /// **ISO-IR 14** (G0): JIS X 0201 Romaji,
+    /// invoked together with ISO-IR 13.
+    IsoIr14,
+    /// **ISO-IR 58** (G1): GB 2312 Simplified Chinese.
+    IsoIr58,
+    /// **ISO-IR 87** (G0): JIS X 0208 Kanji.
+    IsoIr87,
+    /// **ISO-IR 100** (G1): Latin alphabet no. 1.
+    IsoIr100,
+    /// **ISO-IR 101** (G1): Latin alphabet no. 2.
+    IsoIr101,
+    /// **ISO-IR 109** (G1): Latin alphabet no. 3.
+    IsoIr109,
+    /// **ISO-IR 110** (G1): Latin alphabet no. 4.
+    IsoIr110,
+    /// **ISO-IR 144** (G1): Cyrillic.
+    IsoIr144,
+    /// **ISO-IR 149** (G1): KS X 1001 Hangul and Hanja.
+    IsoIr149,
+    /// **ISO-IR 159** (G0): JIS X 0212 supplementary Kanji.
+    IsoIr159,
+}
+
+impl CodeElement {
+    /// All code elements, in order.
+    const ALL: [CodeElement; 12] = [
+        CodeElement::IsoIr6,
+        CodeElement::IsoIr13,
+        CodeElement::IsoIr14,
+        CodeElement::IsoIr58,
+        CodeElement::IsoIr87,
+        CodeElement::IsoIr100,
+        CodeElement::IsoIr101,
+        CodeElement::IsoIr109,
+        CodeElement::IsoIr110,
+        CodeElement::IsoIr144,
+        CodeElement::IsoIr149,
+        CodeElement::IsoIr159,
+    ];
+
+    /// Obtain the code element identified by the given defined term
+    /// of the Specific Character Set (0008, 0005) element,
+    /// such as `"ISO 2022 IR 87"`.
+    pub fn from_term(term: &str) -> Option<Self> {
+        let term = term.trim();
+        CodeElement::ALL
+            .iter()
+            .copied()
+            .filter(|e| *e != CodeElement::IsoIr14)
+            .find(|e| e.term() == term)
+    }
+
+    /// The defined term of this code element
+    /// in the Specific Character Set (0008, 0005) element.
+    pub fn term(self) -> &'static str {
+        match self {
+            CodeElement::IsoIr6 => "ISO 2022 IR 6",
+            CodeElement::IsoIr13 => "ISO 2022 IR 13",
+            CodeElement::IsoIr14 => "ISO 2022 IR 14",
+            CodeElement::IsoIr58 => "ISO 2022 IR 58",
+            CodeElement::IsoIr87 => "ISO 2022 IR 87",
+            CodeElement::IsoIr100 => "ISO 2022 IR 100",
+            CodeElement::IsoIr101 => "ISO 2022 IR 101",
+            CodeElement::IsoIr109 => "ISO 2022 IR 109",
+            CodeElement::IsoIr110 => "ISO 2022 IR 110",
+            CodeElement::IsoIr144 => "ISO 2022 IR 144",
+            CodeElement::IsoIr149 => "ISO 2022 IR 149",
+            CodeElement::IsoIr159 => "ISO 2022 IR 159",
+        }
+    }
+
+    /// The escape sequence which designates this code element.
+    pub fn escape_sequence(self) -> &'static [u8] {
+        match self {
+            CodeElement::IsoIr6 => b"\x1B(B",
+            CodeElement::IsoIr13 => b"\x1B)I",
+            CodeElement::IsoIr14 => b"\x1B(J",
+            CodeElement::IsoIr58 => b"\x1B$)A",
+            CodeElement::IsoIr87 => b"\x1B$B",
+            CodeElement::IsoIr100 => b"\x1B-A",
+            CodeElement::IsoIr101 => b"\x1B-B",
+            CodeElement::IsoIr109 => b"\x1B-C",
+            CodeElement::IsoIr110 => b"\x1B-D",
+            CodeElement::IsoIr144 => b"\x1B-L",
+            CodeElement::IsoIr149 => b"\x1B$)C",
+            CodeElement::IsoIr159 => b"\x1B$(D",
+        }
+    }
+
+    /// Whether this code element is designated to G0.
+    pub fn is_g0(self) -> bool {
+        matches!(
+            self,
+            CodeElement::IsoIr6
+                | CodeElement::IsoIr14
+                | CodeElement::IsoIr87
+                | CodeElement::IsoIr159
+        )
+    }
+
+    /// The number of bytes of each character in this code element.
+    fn char_len(self) -> usize {
+        match self {
+            CodeElement::IsoIr58
+            | CodeElement::IsoIr87
+            | CodeElement::IsoIr149
+            | CodeElement::IsoIr159 => 2,
+            _ => 1,
+        }
+    }
+
+    /// Find the code element designated by the escape sequence
+    /// at the start of the given bytes.
+    fn from_escape_sequence(bytes: &[u8]) -> Option<Self> {
+        CodeElement::ALL
+            .iter()
+            .copied()
+            .find(|e| bytes.starts_with(e.escape_sequence()))
+    }
+
+    /// Decode a single character of this code element,
+    /// replacing it with U+FFFD if it is not valid.
+    fn decode_char(self, bytes: &[u8], out: &mut String) {
+        let decoded = match (self, bytes) {
+            (CodeElement::IsoIr6, _) | (CodeElement::IsoIr14, _) => {
+                ISO_8859_1.decode(bytes, DecoderTrap::Replace)
+            }
+            (CodeElement::IsoIr13, [b @ 0xA1..=0xDF]) => {
+                out.extend(char::from_u32(0xFF61 + u32::from(b - 0xA1)));
+                return;
+            }
+            (CodeElement::IsoIr13, _) => Ok(String::from('\u{FFFD}')),
+            (CodeElement::IsoIr58, _) => GBK.decode(bytes, DecoderTrap::Replace),
+            (CodeElement::IsoIr87, [b0, b1]) => {
+                EUC_JP.decode(&[b0 | 0x80, b1 | 0x80], DecoderTrap::Replace)
+            }
+            (CodeElement::IsoIr159, [b0, b1]) => {
+                EUC_JP.decode(&[0x8F, b0 | 0x80, b1 | 0x80], DecoderTrap::Replace)
+            }
+            (CodeElement::IsoIr87, _) | (CodeElement::IsoIr159, _) => Ok(String::from('\u{FFFD}')),
+            (CodeElement::IsoIr100, _) => ISO_8859_1.decode(bytes, DecoderTrap::Replace),
+            (CodeElement::IsoIr101, _) => ISO_8859_2.decode(bytes, DecoderTrap::Replace),
+            (CodeElement::IsoIr109, _) => ISO_8859_3.decode(bytes, DecoderTrap::Replace),
+            (CodeElement::IsoIr110, _) => ISO_8859_4.decode(bytes, DecoderTrap::Replace),
+            (CodeElement::IsoIr144, _) => ISO_8859_5.decode(bytes, DecoderTrap::Replace),
+            (CodeElement::IsoIr149, _) => WINDOWS_949.decode(bytes, DecoderTrap::Replace),
+        };
+        match decoded {
+            Ok(text) => out.push_str(&text),
+            Err(_) => out.push('\u{FFFD}'),
+        }
+    }
+
+    /// Encode a single character in this code element,
+    /// returning `None` if it is not part of its repertoire.
+    ///
+    /// Encoding into JIS X 0212 (ISO-IR 159) is not supported.
+    fn encode_char(self, c: char) -> Option<Vec<u8>> {
+        let mut buf = [0; 4];
+        let text = &*c.encode_utf8(&mut buf);
+        let encoded = match self {
+            CodeElement::IsoIr6 | CodeElement::IsoIr14 => {
+                return Some(vec![c as u8]).filter(|_| c.is_ascii());
+            }
+            CodeElement::IsoIr13 => {
+                let offset = u32::from(c).checked_sub(0xFF61).filter(|o| *o <= 0x3E)?;
+                return Some(vec![0xA1 + offset as u8]);
+            }
+            CodeElement::IsoIr159 => return None,
+            CodeElement::IsoIr58 => GBK.encode(text, EncoderTrap::Strict),
+            CodeElement::IsoIr87 => EUC_JP.encode(text, EncoderTrap::Strict),
+            CodeElement::IsoIr100 => ISO_8859_1.encode(text, EncoderTrap::Strict),
+            CodeElement::IsoIr101 => ISO_8859_2.encode(text, EncoderTrap::Strict),
+            CodeElement::IsoIr109 => ISO_8859_3.encode(text, EncoderTrap::Strict),
+            CodeElement::IsoIr110 => ISO_8859_4.encode(text, EncoderTrap::Strict),
+            CodeElement::IsoIr144 => ISO_8859_5.encode(text, EncoderTrap::Strict),
+            CodeElement::IsoIr149 => WINDOWS_949.encode(text, EncoderTrap::Strict),
+        }
+        .ok()?;
+        // only accept characters in the upper half,
+        // within the code element's repertoire
+        if encoded.len() != self.char_len() || encoded.iter().any(|b| *b < 0xA0) {
+            return None;
+        }
+        match self {
+            CodeElement::IsoIr87 => Some(encoded.iter().map(|b| b & 0x7F).collect()),
+            _ => Some(encoded),
+        }
+    }
+}
+
+/// Check whether the given byte is a delimiter
+/// after which the initial code elements are restored:
+/// the value delimiter or one of the control characters
+/// CR, LF, FF and TAB.
+fn is_iso_2022_delimiter(b: u8) -> bool {
+    matches!(b, b'\\' | b'\r' | b'\n' | 0x0C | b'\t')
+}
+
+/// A specific character set with ISO 2022 code extensions,
+/// made of an initial code element
+/// and the code elements which may be invoked
+/// through escape sequences.
+///
+/// At the start of each value,
+/// and after each value delimiter or control character,
+/// the code elements are reset to the initial ones:
+/// the default repertoire in G0,
+/// and the initial code element in G1 (if applicable).
+/// All known escape sequences are recognized when decoding,
+/// whereas encoding only switches to the declared code elements.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct Iso2022CharacterSet {
+    initial: CodeElement,
+    extensions: [Option<CodeElement>; 4],
+}
+
+impl Iso2022CharacterSet {
+    /// Create a character set with the given initial code element
+    /// and code extensions.
+    ///
+    /// At most four code extensions are retained.
+    pub fn new(initial: CodeElement, extensions: &[CodeElement]) -> Self {
+        let mut slots = [None; 4];
+        for (slot, e) in slots.iter_mut().zip(extensions) {
+            *slot = Some(*e);
+        }
+        Iso2022CharacterSet {
+            initial,
+            extensions: slots,
+        }
+    }
+
+    /// The initial code element.
+    pub fn initial(&self) -> CodeElement {
+        self.initial
+    }
+
+    /// Iterate over the declared code extensions.
+    pub fn extensions(&self) -> impl Iterator<Item = CodeElement> + '_ {
+        self.extensions.iter().flatten().copied()
+    }
+
+    /// The code elements in G0 and G1
+    /// at the start of each value.
+    fn initial_state(&self) -> (CodeElement, Option<CodeElement>) {
+        match self.initial {
+            CodeElement::IsoIr13 | CodeElement::IsoIr14 => {
+                (CodeElement::IsoIr14, Some(CodeElement::IsoIr13))
+            }
+            e if e.is_g0() => (CodeElement::IsoIr6, None),
+            e => (CodeElement::IsoIr6, Some(e)),
+        }
+    }
+}
+
+impl TextCodec for Iso2022CharacterSet {
+    fn name(&self) -> &'static str {
+        self.initial.term()
+    }
+
+    fn decode(&self, text: &[u8]) -> DecodeResult<String> {
+        let (mut g0, mut g1) = self.initial_state();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < text.len() {
+            let b = text[i];
+            if b == ESC {
+                if let Some(e) = CodeElement::from_escape_sequence(&text[i..]) {
+                    if e.is_g0() {
+                        g0 = e;
+                    } else {
+                        g1 = Some(e);
+                    }
+                    i += e.escape_sequence().len();
+                    continue;
+                }
+            }
+            let (element, len) = match (b, g1) {
+                (0x21..=0x7E, _) => (g0, g0.char_len()),
+                (0x00..=0x7F, _) => (CodeElement::IsoIr6, 1),
+                (_, Some(g1)) => (g1, g1.char_len()),
+                (_, None) => (CodeElement::IsoIr100, 1),
+            };
+            let end = (i + len).min(text.len());
+            element.decode_char(&text[i..end], &mut out);
+            if len == 1 && is_iso_2022_delimiter(b) {
+                // delimiters reset the code elements in use
+                let (initial_g0, initial_g1) = self.initial_state();
+                g0 = initial_g0;
+                g1 = initial_g1;
+            }
+            i = end;
+        }
+        Ok(out)
+    }
+
+    fn encode(&self, text: &str) -> EncodeResult<Vec<u8>> {
+        let (initial_g0, initial_g1) = self.initial_state();
+        let (mut g0, mut g1) = (initial_g0, initial_g1);
+        let mut out = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            if c.is_ascii() {
+                if g0 != initial_g0 {
+                    out.extend_from_slice(initial_g0.escape_sequence());
+                    g0 = initial_g0;
+                }
+                out.push(c as u8);
+                // also designate G1 again after person name delimiters,
+                // as required for PN values
+                if is_iso_2022_delimiter(c as u8) || c == '^' || c == '=' {
+                    g1 = initial_g1;
+                }
+                continue;
+            }
+            if let Some(bytes) = g1.and_then(|e| e.encode_char(c)) {
+                out.extend(bytes);
+                continue;
+            }
+            if let Some(bytes) = Some(g0)
+                .filter(|e| e.char_len() > 1)
+                .and_then(|e| e.encode_char(c))
+            {
+                out.extend(bytes);
+                continue;
+            }
+            let (element, bytes) = std::iter::once(self.initial)
+                .chain(self.extensions())
+                .find_map(|e| e.encode_char(c).map(|bytes| (e, bytes)))
+                .ok_or_else(|| {
+                    EncodeCustomSnafu {
+                        message: format!("character {:?} not supported by {}", c, self.name()),
+                    }
+                    .build()
+                })?;
+            out.extend_from_slice(element.escape_sequence());
+            if element.is_g0() {
+                g0 = element;
+            } else {
+                g1 = Some(element);
+            }
+            out.extend(bytes);
+        }
+        if g0 != initial_g0 {
+            out.extend_from_slice(initial_g0.escape_sequence());
+        }
+        Ok(out)
+    }
+
+    fn decode_values(&self, text: &[u8]) -> DecodeResult<Vec<String>> {
+        // backslash bytes may be part of multi-byte characters,
+        // so values can only be split after decoding
+        Ok(self.decode(text)?.split('\\').map(String::from).collect())
+    }
+}
 
 /// The result of a text validation procedure (please see [`validate_iso_8859`]).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -435,4 +915,104 @@ mod tests {
             b"\xb8\xd2\xd0\xdd\xda\xde\xd2^\xb0\xdd\xd4\xe0\xd5\xd9",
         );
     }
+
+    #[test]
+    fn gb18030_chinese() {
+        // PS3.5 Annex K.1
+        let codec = SpecificCharacterSet::from_code("GB18030").unwrap();
+        test_codec(
+            codec,
+            "Wang^XiaoDong=王^小东=",
+            b"Wang^XiaoDong=\xcd\xf5^\xd0\xa1\xb6\xab=",
+        );
+        // characters outside of GBK
+        test_codec(codec, "\u{20087}", b"\x95\x32\x90\x31");
+    }
+
+    #[test]
+    fn gbk_chinese() {
+        let codec = SpecificCharacterSet::from_code("GBK").unwrap();
+        assert_eq!(codec, SpecificCharacterSet::Gbk);
+        assert_eq!(codec.name(), "GBK");
+        test_codec(
+            codec,
+            "Wang^XiaoDong=王^小东=",
+            b"Wang^XiaoDong=\xcd\xf5^\xd0\xa1\xb6\xab=",
+        );
+        assert!(codec.encode("\u{20087}").is_err());
+    }
+
+    #[test]
+    fn iso_2022_ir_87_japanese() {
+        // PS3.5 Annex H.3.1
+        let codec = SpecificCharacterSet::from_codes(["", "ISO 2022 IR 87"]).unwrap();
+        test_codec(
+            codec,
+            "Yamada^Tarou=山田^太郎=やまだ^たろう",
+            b"Yamada^Tarou=\x1b$B;3ED\x1b(B^\x1b$BB@O:\x1b(B=\x1b$B$d$^$@\x1b(B^\x1b$B$?$m$&\x1b(B",
+        );
+    }
+
+    #[test]
+    fn iso_2022_ir_13_japanese() {
+        // PS3.5 Annex H.3.2
+        let codec = SpecificCharacterSet::from_codes(["ISO 2022 IR 13", "ISO 2022 IR 87"]).unwrap();
+        assert_eq!(codec.name(), "ISO 2022 IR 13");
+        test_codec(
+            codec,
+            "ﾔﾏﾀﾞ^ﾀﾛｳ=山田^太郎=やまだ^たろう",
+            b"\xd4\xcf\xc0\xde^\xc0\xdb\xb3=\x1b$B;3ED\x1b(J^\x1b$BB@O:\x1b(J=\x1b$B$d$^$@\x1b(J^\x1b$B$?$m$&\x1b(J",
+        );
+    }
+
+    #[test]
+    fn iso_2022_ir_149_korean() {
+        // PS3.5 Annex I.2
+        let codec = SpecificCharacterSet::from_codes(["", "ISO 2022 IR 149"]).unwrap();
+        test_codec(
+            codec,
+            "Hong^Gildong=洪^吉洞=홍^길동",
+            b"Hong^Gildong=\x1b$)C\xfb\xf3^\x1b$)C\xd1\xce\xd4\xd7=\x1b$)C\xc8\xab^\x1b$)C\xb1\xe6\xb5\xbf",
+        );
+    }
+
+    #[test]
+    fn iso_2022_ir_149_single_value() {
+        // Korean text without code extensions
+        let codec = SpecificCharacterSet::from_code("ISO 2022 IR 149").unwrap();
+        assert_eq!(codec.name(), "ISO 2022 IR 149");
+        test_codec(codec, "Hong^홍", b"Hong^\xc8\xab");
+        // escape sequences are still recognized
+        assert_eq!(codec.decode(b"\x1b$)C\xc8\xab").unwrap(), "홍");
+    }
+
+    #[test]
+    fn iso_2022_values_and_delimiters() {
+        let codec = SpecificCharacterSet::from_codes(["", "ISO 2022 IR 87"]).unwrap();
+        // a backslash byte inside a multi-byte character does not split values
+        let values = codec
+            .decode_values(b"\x1b$B%=\x1b(B\\\x1b$B%=\x1b(B")
+            .unwrap();
+        assert_eq!(values, vec!["ソ", "ソ"]);
+
+        // the initial code elements are restored after a delimiter
+        let codec = SpecificCharacterSet::from_codes(["", "ISO 2022 IR 149"]).unwrap();
+        assert_eq!(codec.decode(b"\x1b$)C\xc8\xab\r\n\xe9").unwrap(), "홍\r\né");
+
+        // characters outside of the declared code elements cannot be encoded
+        assert!(codec.encode("山田").is_ok());
+        assert!(codec.encode("やまだ").is_ok());
+        assert!(codec.encode("ก").is_err());
+
+        // unsupported code elements
+        assert_eq!(
+            SpecificCharacterSet::from_codes(["", "ISO 2022 IR 999"]),
+            None
+        );
+        // single values are resolved as before
+        assert_eq!(
+            SpecificCharacterSet::from_codes(["ISO_IR 192"]),
+            Some(SpecificCharacterSet::IsoIr192)
+        );
+    }
 }
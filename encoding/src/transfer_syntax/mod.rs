@@ -183,9 +183,27 @@ pub trait DataRWAdapter<R, W> {
         R: Read;
 
     /// Adapt a byte writer.
+    ///
+    /// The adapted writer should complete its output
+    /// (e.g. by finishing a compressed stream)
+    /// when it is dropped.
     fn adapt_writer(&self, writer: W) -> Self::Writer
     where
         W: Write;
+
+    /// Adapt a byte writer,
+    /// using the given compression level
+    /// from 0 (no compression) to 9 (best compression)
+    /// if the adapter compresses its output.
+    ///
+    /// By default, the level is ignored.
+    fn adapt_writer_with_level(&self, writer: W, level: u32) -> Self::Writer
+    where
+        W: Write,
+    {
+        let _ = level;
+        self.adapt_writer(writer)
+    }
 }
 
 /// Alias type for a dynamically dispatched data adapter.
@@ -223,6 +241,14 @@ where
     {
         (**self).adapt_writer(writer)
     }
+
+    /// Adapt a byte writer with the given compression level.
+    fn adapt_writer_with_level(&self, writer: W, level: u32) -> Self::Writer
+    where
+        W: Write,
+    {
+        (**self).adapt_writer_with_level(writer, level)
+    }
 }
 
 /// An immaterial type representing a data set adapter which is never required,
@@ -313,6 +339,11 @@ impl<D, P> TransferSyntax<D, P> {
         self.byte_order
     }
 
+    /// Check whether this transfer syntax uses explicit value representations.
+    pub const fn explicit_vr(&self) -> bool {
+        self.explicit_vr
+    }
+
     /// Obtain this transfer syntax' codec specification.
     pub fn codec(&self) -> &Codec<D, P> {
         &self.codec
@@ -4,7 +4,9 @@
 //! and replaces the various DICOM attributes of the [_Image Pixel_ module][1]
 //! (such as Rows, Columns, PixelData, ...)
 //! with those of another file.
-//! The _Presentation LUT Shape_ attribute is set to `IDENTITY`.
+//! The _Presentation LUT Shape_ attribute is set to `IDENTITY`,
+//! and an item identifying this tool as modifying equipment
+//! is appended to the _Contributing Equipment Sequence_.
 //! Other attributes are copied as is.
 //!
 //! The new DICOM object is saved to a new file,
@@ -16,6 +18,7 @@ use std::path::PathBuf;
 
 use dicom_core::{value::PrimitiveValue, DataElement, VR};
 use dicom_dictionary_std::tags;
+use dicom_object::equipment::{ContributingEquipment, PurposeOfReference};
 use dicom_object::{open_file, FileMetaTableBuilder};
 use structopt::StructOpt;
 
@@ -173,6 +176,13 @@ fn main() {
         PrimitiveValue::from(pixeldata),
     ));
 
+    obj.add_contributing_equipment(
+        &ContributingEquipment::new("DICOM-rs")
+            .manufacturer_model_name(env!("CARGO_PKG_NAME"))
+            .software_version(env!("CARGO_PKG_VERSION")),
+        PurposeOfReference::Modifying,
+    );
+
     let class_uid = obj.meta().media_storage_sop_class_uid.clone();
 
     let obj = obj
@@ -0,0 +1,180 @@
+//! Compare the memory retained and the lookup time
+//! of in-memory objects and compact objects
+//! when holding the headers of many instances.
+//!
+//! Run with `cargo bench -p dicom-object --bench storage`.
+use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_object::compact::{CompactDicomObject, Interner};
+use dicom_object::InMemDicomObject;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// A global allocator keeping track of the bytes currently allocated.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const INSTANCES: usize = 20_000;
+const INSTANCES_PER_SERIES: usize = 200;
+const LOOKUP_ROUNDS: usize = 20;
+
+const LOOKUP_TAGS: [Tag; 6] = [
+    tags::SOP_INSTANCE_UID,
+    tags::MODALITY,
+    tags::PATIENT_NAME,
+    tags::SERIES_INSTANCE_UID,
+    tags::INSTANCE_NUMBER,
+    tags::ROWS,
+];
+
+/// Build the header of a synthetic CT instance.
+fn header(index: usize) -> InMemDicomObject {
+    let series = index / INSTANCES_PER_SERIES;
+    let text = |tag, vr, value: String| DataElement::new(tag, vr, PrimitiveValue::from(value));
+    InMemDicomObject::from_element_iter([
+        text(
+            tags::SOP_CLASS_UID,
+            VR::UI,
+            "1.2.840.10008.5.1.4.1.1.2".into(),
+        ),
+        text(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            format!("2.25.1{:08}", index),
+        ),
+        text(tags::STUDY_DATE, VR::DA, "20210315".into()),
+        text(tags::MODALITY, VR::CS, "CT".into()),
+        text(tags::MANUFACTURER, VR::LO, "ACME Imaging".into()),
+        text(tags::INSTITUTION_NAME, VR::LO, "General Hospital".into()),
+        text(
+            tags::STUDY_DESCRIPTION,
+            VR::LO,
+            "CHEST ABDOMEN PELVIS".into(),
+        ),
+        text(
+            tags::SERIES_DESCRIPTION,
+            VR::LO,
+            format!("AXIAL {}", series),
+        ),
+        text(
+            tags::PATIENT_NAME,
+            VR::PN,
+            format!("Doe^John^{}", series / 4),
+        ),
+        text(tags::PATIENT_ID, VR::LO, format!("P{:06}", series / 4)),
+        text(tags::PATIENT_BIRTH_DATE, VR::DA, "19700101".into()),
+        text(tags::PATIENT_SEX, VR::CS, "M".into()),
+        text(tags::BODY_PART_EXAMINED, VR::CS, "CHEST".into()),
+        text(tags::SLICE_THICKNESS, VR::DS, "1.25".into()),
+        text(
+            tags::STUDY_INSTANCE_UID,
+            VR::UI,
+            format!("2.25.2{:06}", series / 4),
+        ),
+        text(
+            tags::SERIES_INSTANCE_UID,
+            VR::UI,
+            format!("2.25.3{:06}", series),
+        ),
+        text(
+            tags::INSTANCE_NUMBER,
+            VR::IS,
+            (index % INSTANCES_PER_SERIES).to_string(),
+        ),
+        DataElement::new(
+            tags::IMAGE_POSITION_PATIENT,
+            VR::DS,
+            PrimitiveValue::Strs(
+                vec![
+                    "-250".to_string(),
+                    "-250".to_string(),
+                    (index % 400).to_string(),
+                ]
+                .into(),
+            ),
+        ),
+        DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+        DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(512_u16)),
+    ])
+}
+
+/// Measure the bytes retained by the value built by the given function.
+fn retained<T>(build: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let value = build();
+    let after = ALLOCATED.load(Ordering::Relaxed);
+    (value, after.saturating_sub(before))
+}
+
+/// Time the given lookup over all objects and tags, in nanoseconds per lookup.
+fn lookup_time<T>(objects: &[T], lookup: impl Fn(&T, Tag) -> bool) -> f64 {
+    let start = Instant::now();
+    let mut found = 0;
+    for _ in 0..LOOKUP_ROUNDS {
+        for obj in objects {
+            for tag in LOOKUP_TAGS {
+                if lookup(black_box(obj), black_box(tag)) {
+                    found += 1;
+                }
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+    assert_eq!(found, LOOKUP_ROUNDS * objects.len() * LOOKUP_TAGS.len());
+    elapsed.as_nanos() as f64 / found as f64
+}
+
+fn main() {
+    let (in_mem, in_mem_bytes) = retained(|| (0..INSTANCES).map(header).collect::<Vec<_>>());
+
+    let ((compact, interner), compact_bytes) = retained(|| {
+        let mut interner = Interner::new();
+        let compact: Vec<_> = in_mem
+            .iter()
+            .map(|obj| CompactDicomObject::from_object(obj, &mut interner))
+            .collect();
+        (compact, interner)
+    });
+
+    let in_mem_lookup = lookup_time(&in_mem, |obj, tag| obj.element(tag).is_ok());
+    let compact_lookup = lookup_time(&compact, |obj, tag| obj.element(tag).is_ok());
+
+    println!("{} instance headers", INSTANCES);
+    println!(
+        "{:<10} {:>12} {:>12} {:>12}",
+        "storage", "bytes", "per object", "ns/lookup"
+    );
+    println!(
+        "{:<10} {:>12} {:>12} {:>12.1}",
+        "in-memory",
+        in_mem_bytes,
+        in_mem_bytes / INSTANCES,
+        in_mem_lookup
+    );
+    println!(
+        "{:<10} {:>12} {:>12} {:>12.1}",
+        "compact",
+        compact_bytes,
+        compact_bytes / INSTANCES,
+        compact_lookup
+    );
+    println!("{} distinct text values interned", interner.len());
+}
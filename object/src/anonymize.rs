@@ -0,0 +1,1237 @@
+//! De-identification of DICOM objects.
+//!
+//! This module implements a partial
+//! Basic Application Level Confidentiality Profile
+//! (PS3.15 Annex E).
+//! Only a subset of the attributes of Table E.1-1 is listed,
+//! covering those most likely to identify the patient,
+//! as well as the repeating groups of curves (50xx,xxxx),
+//! overlay data (60xx,3000) and overlay comments (60xx,4000).
+//! Each attribute of the profile is subject to an [`Action`]:
+//! it may be removed, emptied, replaced with a dummy value,
+//! cleaned, or have its UIDs replaced.
+//! Actions are applied at every level of the object,
+//! including in the items of sequences.
+//! Private attributes are removed by default.
+//!
+//! The following profile options (PS3.15 E.3) can be enabled
+//! in [`AnonymizeOptions`]:
+//!
+//! - _Retain Longitudinal Temporal Information With Full Dates_,
+//!   keeping dates and times;
+//! - _Retain Device Identity_;
+//! - _Retain Patient Characteristics_;
+//! - _Retain UIDs_.
+//!
+//! The action of any attribute can also be overridden.
+//! Attributes which are not in the profile are kept by default,
+//! or are subject to the action set with
+//! [`unlisted_action`](AnonymizeOptions::unlisted_action).
+//! Once de-identified, the object is marked with
+//! _Patient Identity Removed_ and the _De-identification Method_,
+//! and the de-identifying equipment is appended
+//! to its _Contributing Equipment Sequence_.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::anonymize::{anonymize, Action, AnonymizeOptions};
+//!
+//! let mut obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+//!     DataElement::new(tags::PATIENT_ADDRESS, VR::LO, PrimitiveValue::from("Main Street")),
+//!     DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from("20230101")),
+//!     DataElement::new(tags::PATIENT_AGE, VR::AS, PrimitiveValue::from("042Y")),
+//! ]);
+//!
+//! let options = AnonymizeOptions::new()
+//!     .retain_patient_characteristics(true)
+//!     .with_action(tags::PATIENT_NAME, Action::Replace(PrimitiveValue::from("Subject 1")));
+//! anonymize(&mut obj, &options)?;
+//!
+//! assert_eq!(obj.element(tags::PATIENT_NAME)?.to_str()?, "Subject 1");
+//! assert!(obj.element_opt(tags::PATIENT_ADDRESS)?.is_none());
+//! assert_eq!(obj.element(tags::STUDY_DATE)?.to_str()?, "");
+//! assert_eq!(obj.element(tags::PATIENT_AGE)?.to_str()?, "042Y");
+//! assert_eq!(obj.element(tags::PATIENT_IDENTITY_REMOVED)?.to_str()?, "YES");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::equipment::{ContributingEquipment, PurposeOfReference};
+use crate::mem::InMemDicomObject;
+use crate::uid::{self, RerootStrategy, UidMapper};
+use crate::FileDicomObject;
+use dicom_core::dictionary::DataDictionary;
+use dicom_core::header::Header;
+use dicom_core::value::{PrimitiveValue, Value};
+use dicom_core::{DataElement, Length, Tag, VR};
+use dicom_dictionary_std::tags;
+use snafu::{ResultExt, Snafu};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Could not replace UID `{}`", uid))]
+    ReplaceUid {
+        uid: String,
+        #[snafu(backtrace)]
+        source: uid::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The de-identification action for an attribute (PS3.15 Table E.1-1).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Keep the attribute as is (K).
+    Keep,
+    /// Remove the attribute (X).
+    Remove,
+    /// Replace the value with an empty value (Z).
+    Empty,
+    /// Replace the value with a non-identifying dummy value
+    /// of the same VR (D).
+    Dummy,
+    /// Replace the value with one of similar meaning
+    /// which does not contain identifying information (C),
+    /// using the cleaner function of the options.
+    Clean,
+    /// Replace the UIDs in the value,
+    /// consistently across objects (U).
+    ReplaceUid,
+    /// Replace the value with the given one.
+    Replace(PrimitiveValue),
+}
+
+/// The profile option which retains an attribute.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Retain {
+    Never,
+    Dates,
+    DeviceIdentity,
+    PatientCharacteristics,
+    Uids,
+}
+
+/// The attributes of the Basic Profile with their actions,
+/// and the option which retains them, if any.
+///
+/// Where the profile gives alternative actions depending on the attribute type,
+/// the one suitable for the least strict type is used.
+const BASIC_PROFILE: &[(Tag, Action, Retain)] = &[
+    // patient identity
+    (tags::PATIENT_NAME, Action::Empty, Retain::Never),
+    (tags::PATIENT_ID, Action::Empty, Retain::Never),
+    (tags::ISSUER_OF_PATIENT_ID, Action::Remove, Retain::Never),
+    (tags::PATIENT_BIRTH_DATE, Action::Empty, Retain::Never),
+    (tags::PATIENT_BIRTH_TIME, Action::Remove, Retain::Never),
+    (
+        tags::OTHER_PATIENT_I_DS_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    // Other Patient IDs (retired)
+    (Tag(0x0010, 0x1000), Action::Remove, Retain::Never),
+    (tags::OTHER_PATIENT_NAMES, Action::Remove, Retain::Never),
+    // Medical Record Locator (retired)
+    (Tag(0x0010, 0x1090), Action::Remove, Retain::Never),
+    (
+        tags::PATIENT_BIRTH_DATE_IN_ALTERNATIVE_CALENDAR,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::PATIENT_INSURANCE_PLAN_CODE_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::PATIENT_PRIMARY_LANGUAGE_CODE_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::PATIENT_STATE, Action::Remove, Retain::Never),
+    (tags::PATIENT_BIRTH_NAME, Action::Remove, Retain::Never),
+    (
+        tags::PATIENT_MOTHER_BIRTH_NAME,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::PATIENT_ADDRESS, Action::Remove, Retain::Never),
+    (
+        tags::PATIENT_TELEPHONE_NUMBERS,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::COUNTRY_OF_RESIDENCE, Action::Remove, Retain::Never),
+    (tags::REGION_OF_RESIDENCE, Action::Remove, Retain::Never),
+    (tags::MILITARY_RANK, Action::Remove, Retain::Never),
+    (tags::BRANCH_OF_SERVICE, Action::Remove, Retain::Never),
+    (tags::OCCUPATION, Action::Remove, Retain::Never),
+    (
+        tags::PATIENT_RELIGIOUS_PREFERENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::RESPONSIBLE_PERSON, Action::Remove, Retain::Never),
+    (
+        tags::RESPONSIBLE_ORGANIZATION,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::ADDITIONAL_PATIENT_HISTORY,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::PATIENT_COMMENTS, Action::Remove, Retain::Never),
+    (tags::MEDICAL_ALERTS, Action::Remove, Retain::Never),
+    (tags::ALLERGIES, Action::Remove, Retain::Never),
+    (tags::LAST_MENSTRUAL_DATE, Action::Remove, Retain::Never),
+    (
+        tags::CURRENT_PATIENT_LOCATION,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::REFERENCED_PATIENT_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    // patient characteristics
+    (
+        tags::PATIENT_SEX,
+        Action::Empty,
+        Retain::PatientCharacteristics,
+    ),
+    (
+        tags::PATIENT_AGE,
+        Action::Remove,
+        Retain::PatientCharacteristics,
+    ),
+    (
+        tags::PATIENT_SIZE,
+        Action::Remove,
+        Retain::PatientCharacteristics,
+    ),
+    (
+        tags::PATIENT_WEIGHT,
+        Action::Remove,
+        Retain::PatientCharacteristics,
+    ),
+    (
+        tags::ETHNIC_GROUP,
+        Action::Remove,
+        Retain::PatientCharacteristics,
+    ),
+    (
+        tags::SMOKING_STATUS,
+        Action::Remove,
+        Retain::PatientCharacteristics,
+    ),
+    (
+        tags::PREGNANCY_STATUS,
+        Action::Remove,
+        Retain::PatientCharacteristics,
+    ),
+    (
+        tags::PATIENT_SEX_NEUTERED,
+        Action::Empty,
+        Retain::PatientCharacteristics,
+    ),
+    // study, visit and procedure
+    (tags::ACCESSION_NUMBER, Action::Empty, Retain::Never),
+    (
+        tags::ISSUER_OF_ACCESSION_NUMBER_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::STUDY_ID, Action::Empty, Retain::Never),
+    (tags::STUDY_DESCRIPTION, Action::Remove, Retain::Never),
+    (tags::SERIES_DESCRIPTION, Action::Remove, Retain::Never),
+    (tags::PROTOCOL_NAME, Action::Remove, Retain::Never),
+    (tags::IMAGE_COMMENTS, Action::Remove, Retain::Never),
+    (tags::FRAME_COMMENTS, Action::Remove, Retain::Never),
+    (tags::DERIVATION_DESCRIPTION, Action::Remove, Retain::Never),
+    (tags::CONTRAST_BOLUS_AGENT, Action::Empty, Retain::Never),
+    (tags::ADMISSION_ID, Action::Remove, Retain::Never),
+    (
+        tags::ADMITTING_DIAGNOSES_DESCRIPTION,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::REQUEST_ATTRIBUTES_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::REQUESTED_PROCEDURE_DESCRIPTION,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::PERFORMED_PROCEDURE_STEP_ID,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::PERFORMED_PROCEDURE_STEP_DESCRIPTION,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::ACQUISITION_DEVICE_PROCESSING_DESCRIPTION,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::ADMITTING_DIAGNOSES_CODE_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    // Study Comments (retired)
+    (Tag(0x0032, 0x4000), Action::Remove, Retain::Never),
+    (tags::VISIT_COMMENTS, Action::Remove, Retain::Never),
+    // Text Comments (retired)
+    (Tag(0x4000, 0x4000), Action::Remove, Retain::Never),
+    // Interpretation Author (retired)
+    (Tag(0x4008, 0x010C), Action::Remove, Retain::Never),
+    (tags::CONTENT_SEQUENCE, Action::Remove, Retain::Never),
+    // persons and institutions
+    (tags::REFERRING_PHYSICIAN_NAME, Action::Empty, Retain::Never),
+    (
+        tags::REFERRING_PHYSICIAN_ADDRESS,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::REFERRING_PHYSICIAN_TELEPHONE_NUMBERS,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::PHYSICIANS_OF_RECORD, Action::Remove, Retain::Never),
+    (
+        tags::PERFORMING_PHYSICIAN_NAME,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::NAME_OF_PHYSICIANS_READING_STUDY,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::REFERRING_PHYSICIAN_IDENTIFICATION_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::PHYSICIANS_OF_RECORD_IDENTIFICATION_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::PERFORMING_PHYSICIAN_IDENTIFICATION_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::PHYSICIANS_READING_STUDY_IDENTIFICATION_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::OPERATOR_IDENTIFICATION_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::REQUESTING_PHYSICIAN, Action::Remove, Retain::Never),
+    (tags::OPERATORS_NAME, Action::Remove, Retain::Never),
+    (tags::ORDER_ENTERED_BY, Action::Remove, Retain::Never),
+    (tags::PERSON_NAME, Action::Dummy, Retain::Never),
+    (tags::INSTITUTION_NAME, Action::Remove, Retain::Never),
+    (tags::INSTITUTION_ADDRESS, Action::Remove, Retain::Never),
+    (
+        tags::INSTITUTION_CODE_SEQUENCE,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (tags::PERSON_ADDRESS, Action::Remove, Retain::Never),
+    (
+        tags::PERSON_TELEPHONE_NUMBERS,
+        Action::Remove,
+        Retain::Never,
+    ),
+    (
+        tags::INSTITUTIONAL_DEPARTMENT_NAME,
+        Action::Remove,
+        Retain::Never,
+    ),
+    // dates and times
+    (tags::STUDY_DATE, Action::Empty, Retain::Dates),
+    (tags::STUDY_TIME, Action::Empty, Retain::Dates),
+    (tags::SERIES_DATE, Action::Remove, Retain::Dates),
+    (tags::SERIES_TIME, Action::Remove, Retain::Dates),
+    (tags::ACQUISITION_DATE, Action::Empty, Retain::Dates),
+    (tags::ACQUISITION_TIME, Action::Empty, Retain::Dates),
+    (tags::ACQUISITION_DATE_TIME, Action::Empty, Retain::Dates),
+    (tags::CONTENT_DATE, Action::Empty, Retain::Dates),
+    (tags::CONTENT_TIME, Action::Empty, Retain::Dates),
+    (tags::INSTANCE_CREATION_DATE, Action::Remove, Retain::Dates),
+    (tags::INSTANCE_CREATION_TIME, Action::Remove, Retain::Dates),
+    // device identity
+    (tags::STATION_NAME, Action::Remove, Retain::DeviceIdentity),
+    (
+        tags::DEVICE_SERIAL_NUMBER,
+        Action::Remove,
+        Retain::DeviceIdentity,
+    ),
+    (tags::DEVICE_UID, Action::ReplaceUid, Retain::DeviceIdentity),
+    (tags::DETECTOR_ID, Action::Remove, Retain::DeviceIdentity),
+    (tags::GANTRY_ID, Action::Remove, Retain::DeviceIdentity),
+    (tags::PLATE_ID, Action::Remove, Retain::DeviceIdentity),
+    (tags::CASSETTE_ID, Action::Remove, Retain::DeviceIdentity),
+    // instance UIDs
+    (tags::STUDY_INSTANCE_UID, Action::ReplaceUid, Retain::Uids),
+    (tags::SERIES_INSTANCE_UID, Action::ReplaceUid, Retain::Uids),
+    (tags::SOP_INSTANCE_UID, Action::ReplaceUid, Retain::Uids),
+    (
+        tags::MEDIA_STORAGE_SOP_INSTANCE_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (
+        tags::REFERENCED_SOP_INSTANCE_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (
+        tags::FRAME_OF_REFERENCE_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (
+        tags::REFERENCED_FRAME_OF_REFERENCE_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (
+        tags::SYNCHRONIZATION_FRAME_OF_REFERENCE_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (tags::INSTANCE_CREATOR_UID, Action::ReplaceUid, Retain::Uids),
+    (
+        tags::IRRADIATION_EVENT_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (tags::CONCATENATION_UID, Action::ReplaceUid, Retain::Uids),
+    (
+        tags::DIMENSION_ORGANIZATION_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (
+        tags::STORAGE_MEDIA_FILE_SET_UID,
+        Action::ReplaceUid,
+        Retain::Uids,
+    ),
+    (tags::UID, Action::ReplaceUid, Retain::Uids),
+    (tags::TARGET_UID, Action::ReplaceUid, Retain::Uids),
+    (tags::TRANSACTION_UID, Action::ReplaceUid, Retain::Uids),
+    (tags::FIDUCIAL_UID, Action::ReplaceUid, Retain::Uids),
+];
+
+/// The action of the Basic Profile for the attributes of repeating groups:
+/// Curve Data (50xx,xxxx),
+/// Overlay Data (60xx,3000) and Overlay Comments (60xx,4000).
+fn repeating_group_action(tag: Tag) -> Option<Action> {
+    if tag.group() & 1 == 1 {
+        return None;
+    }
+    match (tag.group() & 0xFF00, tag.element()) {
+        (0x5000, _) | (0x6000, 0x3000) | (0x6000, 0x4000) => Some(Action::Remove),
+        _ => None,
+    }
+}
+
+/// Options for de-identification.
+///
+/// By default, the Basic Profile is applied without any option,
+/// private attributes are removed,
+/// UIDs are replaced with UIDs under the `2.25` root
+/// derived from a hash of the original UID,
+/// cleaned values are emptied,
+/// attributes which are not in the profile are kept,
+/// and this library is recorded as the de-identifying equipment.
+#[derive(Debug, Clone)]
+pub struct AnonymizeOptions {
+    retain_dates: bool,
+    retain_device_identity: bool,
+    retain_patient_characteristics: bool,
+    retain_uids: bool,
+    remove_private_tags: bool,
+    uid_strategy: RerootStrategy,
+    cleaner: fn(VR, &str) -> String,
+    overrides: BTreeMap<Tag, Action>,
+    unlisted: Action,
+    equipment: Option<ContributingEquipment>,
+}
+
+impl Default for AnonymizeOptions {
+    fn default() -> Self {
+        AnonymizeOptions {
+            retain_dates: false,
+            retain_device_identity: false,
+            retain_patient_characteristics: false,
+            retain_uids: false,
+            remove_private_tags: true,
+            uid_strategy: RerootStrategy::Hash {
+                new_root: "2.25".to_string(),
+            },
+            cleaner: |_, _| String::new(),
+            overrides: BTreeMap::new(),
+            unlisted: Action::Keep,
+            equipment: Some(ContributingEquipment::dicom_rs()),
+        }
+    }
+}
+
+impl AnonymizeOptions {
+    /// Create a new set of de-identification options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether dates and times are retained
+    /// (Retain Longitudinal Temporal Information With Full Dates Option).
+    pub fn retain_dates(mut self, enabled: bool) -> Self {
+        self.retain_dates = enabled;
+        self
+    }
+
+    /// Set whether the identity of the equipment is retained,
+    /// such as the station name and device serial number
+    /// (Retain Device Identity Option).
+    pub fn retain_device_identity(mut self, enabled: bool) -> Self {
+        self.retain_device_identity = enabled;
+        self
+    }
+
+    /// Set whether the physical characteristics of the patient are retained,
+    /// such as sex, age, size and weight
+    /// (Retain Patient Characteristics Option).
+    pub fn retain_patient_characteristics(mut self, enabled: bool) -> Self {
+        self.retain_patient_characteristics = enabled;
+        self
+    }
+
+    /// Set whether instance UIDs are retained (Retain UIDs Option).
+    pub fn retain_uids(mut self, enabled: bool) -> Self {
+        self.retain_uids = enabled;
+        self
+    }
+
+    /// Set whether private attributes are removed.
+    pub fn remove_private_tags(mut self, enabled: bool) -> Self {
+        self.remove_private_tags = enabled;
+        self
+    }
+
+    /// Set the strategy for producing the replacement UIDs.
+    ///
+    /// The strategy should map the same UID to the same replacement
+    /// in every object,
+    /// so that references between objects are preserved.
+    /// Otherwise, the objects of a study should be de-identified
+    /// with the same [`UidMapper`],
+    /// using [`anonymize_with_mapper`].
+    pub fn uid_strategy(mut self, strategy: RerootStrategy) -> Self {
+        self.uid_strategy = strategy;
+        self
+    }
+
+    /// Set the function producing the cleaned version
+    /// of each textual value with the [`Clean`](Action::Clean) action.
+    pub fn cleaner(mut self, cleaner: fn(VR, &str) -> String) -> Self {
+        self.cleaner = cleaner;
+        self
+    }
+
+    /// Override the action for the given attribute,
+    /// regardless of the profile and its options.
+    pub fn with_action(mut self, tag: Tag, action: Action) -> Self {
+        self.overrides.insert(tag, action);
+        self
+    }
+
+    /// Set the action for the attributes which are not in the profile
+    /// and have no overridden action,
+    /// [`Keep`](Action::Keep) by default.
+    ///
+    /// Setting it to [`Remove`](Action::Remove)
+    /// only keeps the attributes known not to identify the patient;
+    /// any other attribute to keep,
+    /// such as the _SOP Class UID_,
+    /// must then be given the `Keep` action with [`with_action`](Self::with_action).
+    pub fn unlisted_action(mut self, action: Action) -> Self {
+        self.unlisted = action;
+        self
+    }
+
+    /// Set the equipment to record as de-identifying equipment
+    /// in the _Contributing Equipment Sequence_,
+    /// or `None` to not record any.
+    pub fn contributing_equipment(mut self, equipment: Option<ContributingEquipment>) -> Self {
+        self.equipment = equipment;
+        self
+    }
+
+    /// Determine the action for the given attribute.
+    pub fn action(&self, tag: Tag) -> Action {
+        if let Some(action) = self.overrides.get(&tag) {
+            return action.clone();
+        }
+        if self.remove_private_tags && tag.group() & 1 == 1 {
+            return Action::Remove;
+        }
+        match BASIC_PROFILE.iter().find(|(t, _, _)| *t == tag) {
+            Some((_, action, retain)) => {
+                let retained = match retain {
+                    Retain::Never => false,
+                    Retain::Dates => self.retain_dates,
+                    Retain::DeviceIdentity => self.retain_device_identity,
+                    Retain::PatientCharacteristics => self.retain_patient_characteristics,
+                    Retain::Uids => self.retain_uids,
+                };
+                if retained {
+                    Action::Keep
+                } else {
+                    action.clone()
+                }
+            }
+            None => repeating_group_action(tag).unwrap_or_else(|| self.unlisted.clone()),
+        }
+    }
+}
+
+/// De-identify the given object according to the given options.
+///
+/// The object is left unchanged if a UID cannot be replaced.
+pub fn anonymize<D>(obj: &mut InMemDicomObject<D>, options: &AnonymizeOptions) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let mut mapper = UidMapper::new(options.uid_strategy.clone());
+    anonymize_with_mapper(obj, options, &mut mapper)
+}
+
+/// De-identify the given file object according to the given options,
+/// as in [`anonymize`],
+/// also replacing the _Media Storage SOP Instance UID_
+/// of the file meta group.
+pub fn anonymize_file<D>(
+    obj: &mut FileDicomObject<InMemDicomObject<D>>,
+    options: &AnonymizeOptions,
+) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let mut mapper = UidMapper::new(options.uid_strategy.clone());
+    anonymize_file_with_mapper(obj, options, &mut mapper)
+}
+
+/// De-identify the given object according to the given options,
+/// replacing UIDs through the given mapper
+/// instead of the UID strategy of the options.
+///
+/// Passing the same mapper for all objects of a study
+/// keeps their UIDs and the references between them consistent,
+/// whichever strategy the mapper uses.
+/// The object and the mapper are left unchanged
+/// if a UID cannot be replaced.
+pub fn anonymize_with_mapper<D>(
+    obj: &mut InMemDicomObject<D>,
+    options: &AnonymizeOptions,
+    mapper: &mut UidMapper,
+) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let mut result = obj.clone();
+    let mut new_mapper = mapper.clone();
+    apply(&mut result, options, &mut new_mapper)?;
+    mark(&mut result, options);
+    *obj = result;
+    *mapper = new_mapper;
+    Ok(())
+}
+
+/// De-identify the given file object according to the given options,
+/// as in [`anonymize_with_mapper`],
+/// also replacing the _Media Storage SOP Instance UID_
+/// of the file meta group.
+pub fn anonymize_file_with_mapper<D>(
+    obj: &mut FileDicomObject<InMemDicomObject<D>>,
+    options: &AnonymizeOptions,
+    mapper: &mut UidMapper,
+) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let uid = obj.meta().media_storage_sop_instance_uid().to_string();
+    let mut new_mapper = mapper.clone();
+    let new_uid = match options.action(tags::MEDIA_STORAGE_SOP_INSTANCE_UID) {
+        Action::ReplaceUid => replace_uid(&uid, &mut new_mapper)?,
+        _ => uid,
+    };
+    anonymize_with_mapper(obj, options, &mut new_mapper)?;
+    *mapper = new_mapper;
+    let meta = obj.meta_mut();
+    meta.media_storage_sop_instance_uid = new_uid;
+    meta.update_information_group_length();
+    Ok(())
+}
+
+fn apply<D>(
+    obj: &mut InMemDicomObject<D>,
+    options: &AnonymizeOptions,
+    mapper: &mut UidMapper,
+) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let tags: Vec<Tag> = obj.iter().map(|e| e.tag()).collect();
+    for tag in tags {
+        let action = options.action(tag);
+        if action == Action::Remove {
+            obj.remove_element(tag);
+            continue;
+        }
+        let elem = match obj.take_element(tag) {
+            Ok(elem) => elem,
+            Err(_) => continue,
+        };
+        let vr = elem.vr();
+        let value = match (action, elem.into_value()) {
+            (Action::Empty, _) => empty_value(vr),
+            (action, Value::Sequence { mut items, size }) => {
+                if action == Action::Dummy || action == Action::Clean {
+                    empty_value(vr)
+                } else {
+                    for item in items.iter_mut() {
+                        apply(item, options, mapper)?;
+                    }
+                    Value::Sequence { items, size }
+                }
+            }
+            (Action::Keep, value) => value,
+            (Action::Replace(value), _) => Value::Primitive(value),
+            (Action::Dummy, _) => dummy_value(vr),
+            (Action::Clean, Value::Primitive(value)) => {
+                let values: Vec<String> = value
+                    .to_multi_str()
+                    .iter()
+                    .map(|v| (options.cleaner)(vr, v))
+                    .collect();
+                Value::Primitive(PrimitiveValue::Strs(values.into()))
+            }
+            (Action::ReplaceUid, Value::Primitive(value)) => {
+                let uids = value
+                    .to_multi_str()
+                    .iter()
+                    .map(|uid| replace_uid(uid, mapper))
+                    .collect::<Result<Vec<_>>>()?;
+                Value::Primitive(PrimitiveValue::Strs(uids.into()))
+            }
+            (_, value) => value,
+        };
+        obj.put(DataElement::new(tag, vr, value));
+    }
+    Ok(())
+}
+
+fn replace_uid(uid: &str, mapper: &mut UidMapper) -> Result<String> {
+    mapper.map_uid(uid).context(ReplaceUidSnafu { uid })
+}
+
+fn empty_value<D>(vr: VR) -> Value<InMemDicomObject<D>, Vec<u8>> {
+    if vr == VR::SQ {
+        Value::Sequence {
+            items: Default::default(),
+            size: Length::UNDEFINED,
+        }
+    } else {
+        Value::Primitive(PrimitiveValue::Empty)
+    }
+}
+
+/// A non-identifying value of the given VR.
+fn dummy_value<D>(vr: VR) -> Value<InMemDicomObject<D>, Vec<u8>> {
+    let dummy = match vr {
+        VR::DA => "19000101",
+        VR::TM => "000000.00",
+        VR::DT => "19000101000000.00",
+        VR::AS => "000Y",
+        VR::IS | VR::DS => "0",
+        VR::AE | VR::CS | VR::LO | VR::LT | VR::PN | VR::SH | VR::ST | VR::UC | VR::UT => {
+            "ANONYMIZED"
+        }
+        _ => return empty_value(vr),
+    };
+    Value::Primitive(PrimitiveValue::from(dummy))
+}
+
+/// Record the de-identification in the object (PS3.15 E.1.1).
+fn mark<D>(obj: &mut InMemDicomObject<D>, options: &AnonymizeOptions)
+where
+    D: DataDictionary + Clone,
+{
+    let mut codes = vec![("113100", "Basic Application Confidentiality Profile")];
+    if options.retain_dates {
+        codes.push((
+            "113106",
+            "Retain Longitudinal Temporal Information Full Dates Option",
+        ));
+    }
+    if options.retain_patient_characteristics {
+        codes.push(("113108", "Retain Patient Characteristics Option"));
+    }
+    if options.retain_device_identity {
+        codes.push(("113109", "Retain Device Identity Option"));
+    }
+    if options.retain_uids {
+        codes.push(("113110", "Retain UIDs Option"));
+    }
+
+    let method: Vec<String> = codes
+        .iter()
+        .map(|(_, meaning)| meaning.to_string())
+        .collect();
+    let items: Vec<InMemDicomObject<D>> = codes
+        .iter()
+        .map(|(value, meaning)| {
+            let mut item = InMemDicomObject::new_empty_with_dict(obj.dict.clone());
+            item.put(DataElement::new(
+                tags::CODE_VALUE,
+                VR::SH,
+                PrimitiveValue::from(*value),
+            ));
+            item.put(DataElement::new(
+                tags::CODING_SCHEME_DESIGNATOR,
+                VR::SH,
+                PrimitiveValue::from("DCM"),
+            ));
+            item.put(DataElement::new(
+                tags::CODE_MEANING,
+                VR::LO,
+                PrimitiveValue::from(*meaning),
+            ));
+            item
+        })
+        .collect();
+
+    obj.put(DataElement::new(
+        tags::PATIENT_IDENTITY_REMOVED,
+        VR::CS,
+        PrimitiveValue::from("YES"),
+    ));
+    obj.put(DataElement::new(
+        tags::DEIDENTIFICATION_METHOD,
+        VR::LO,
+        PrimitiveValue::Strs(method.into()),
+    ));
+    obj.put(DataElement::new(
+        tags::DEIDENTIFICATION_METHOD_CODE_SEQUENCE,
+        VR::SQ,
+        Value::Sequence {
+            items: items.into(),
+            size: Length::UNDEFINED,
+        },
+    ));
+    obj.put(DataElement::new(
+        tags::LONGITUDINAL_TEMPORAL_INFORMATION_MODIFIED,
+        VR::CS,
+        PrimitiveValue::from(if options.retain_dates {
+            "UNMODIFIED"
+        } else {
+            "REMOVED"
+        }),
+    ));
+    if let Some(equipment) = &options.equipment {
+        obj.add_contributing_equipment(equipment, PurposeOfReference::DeIdentifying);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::FileMetaTableBuilder;
+
+    fn instance() -> InMemDicomObject {
+        let item = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::REFERENCED_SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.2"),
+            ),
+            DataElement::new(
+                tags::REFERENCED_SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("1.9.1.1.1"),
+            ),
+            DataElement::new(
+                tags::OPERATORS_NAME,
+                VR::PN,
+                PrimitiveValue::from("Smith^Jane"),
+            ),
+        ]);
+        InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.2"),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("1.9.1.1.2"),
+            ),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("1.9.1"),
+            ),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("12345")),
+            DataElement::new(tags::PATIENT_SEX, VR::CS, PrimitiveValue::from("M")),
+            DataElement::new(tags::PATIENT_WEIGHT, VR::DS, PrimitiveValue::from("80")),
+            DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from("20230101")),
+            DataElement::new(tags::SERIES_TIME, VR::TM, PrimitiveValue::from("101010")),
+            DataElement::new(
+                tags::INSTITUTION_NAME,
+                VR::LO,
+                PrimitiveValue::from("General Hospital"),
+            ),
+            DataElement::new(tags::STATION_NAME, VR::SH, PrimitiveValue::from("CT01")),
+            DataElement::new(
+                tags::PERSON_NAME,
+                VR::PN,
+                PrimitiveValue::from("Observer^Olivia"),
+            ),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+            DataElement::new(
+                Tag(0x0009, 0x0010),
+                VR::LO,
+                PrimitiveValue::from("ACME 1.0"),
+            ),
+            DataElement::new(
+                Tag(0x0009, 0x1001),
+                VR::LO,
+                PrimitiveValue::from("Doe^John"),
+            ),
+            DataElement::new(
+                tags::REFERENCED_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+        ])
+    }
+
+    fn text(obj: &InMemDicomObject, tag: Tag) -> Option<String> {
+        obj.element_opt(tag)
+            .unwrap()
+            .map(|e| e.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn apply_basic_profile() {
+        let mut obj = instance();
+        anonymize(&mut obj, &AnonymizeOptions::new()).unwrap();
+
+        // emptied
+        assert_eq!(text(&obj, tags::PATIENT_NAME).as_deref(), Some(""));
+        assert_eq!(text(&obj, tags::PATIENT_ID).as_deref(), Some(""));
+        assert_eq!(text(&obj, tags::PATIENT_SEX).as_deref(), Some(""));
+        assert_eq!(text(&obj, tags::STUDY_DATE).as_deref(), Some(""));
+        // removed
+        assert_eq!(text(&obj, tags::PATIENT_WEIGHT), None);
+        assert_eq!(text(&obj, tags::SERIES_TIME), None);
+        assert_eq!(text(&obj, tags::INSTITUTION_NAME), None);
+        assert_eq!(text(&obj, tags::STATION_NAME), None);
+        assert_eq!(text(&obj, Tag(0x0009, 0x0010)), None);
+        assert_eq!(text(&obj, Tag(0x0009, 0x1001)), None);
+        // dummy
+        assert_eq!(text(&obj, tags::PERSON_NAME).as_deref(), Some("ANONYMIZED"));
+        // kept
+        assert_eq!(text(&obj, tags::MODALITY).as_deref(), Some("CT"));
+        assert_eq!(
+            text(&obj, tags::SOP_CLASS_UID).as_deref(),
+            Some("1.2.840.10008.5.1.4.1.1.2")
+        );
+
+        // replaced UIDs, consistently with references
+        let sop_instance_uid = text(&obj, tags::SOP_INSTANCE_UID).unwrap();
+        assert!(sop_instance_uid.starts_with("2.25."));
+        let study_instance_uid = text(&obj, tags::STUDY_INSTANCE_UID).unwrap();
+        assert!(study_instance_uid.starts_with("2.25."));
+        assert_ne!(sop_instance_uid, study_instance_uid);
+        let item = &obj
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            text(item, tags::REFERENCED_SOP_INSTANCE_UID),
+            UidMapper::new(AnonymizeOptions::new().uid_strategy)
+                .map_uid("1.9.1.1.1")
+                .ok()
+        );
+        assert_eq!(
+            text(item, tags::REFERENCED_SOP_CLASS_UID).as_deref(),
+            Some("1.2.840.10008.5.1.4.1.1.2")
+        );
+        assert_eq!(text(item, tags::OPERATORS_NAME), None);
+
+        // marked as de-identified
+        assert_eq!(
+            text(&obj, tags::PATIENT_IDENTITY_REMOVED).as_deref(),
+            Some("YES")
+        );
+        assert_eq!(
+            text(&obj, tags::LONGITUDINAL_TEMPORAL_INFORMATION_MODIFIED).as_deref(),
+            Some("REMOVED")
+        );
+        let codes = obj
+            .element(tags::DEIDENTIFICATION_METHOD_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(codes.len(), 1);
+        assert_eq!(text(&codes[0], tags::CODE_VALUE).as_deref(), Some("113100"));
+        let equipment = obj.contributing_equipment();
+        assert_eq!(equipment.len(), 1);
+        assert_eq!(
+            text(&equipment[0], tags::MANUFACTURER).as_deref(),
+            Some("DICOM-rs")
+        );
+        let purpose = &equipment[0]
+            .element(tags::PURPOSE_OF_REFERENCE_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(text(purpose, tags::CODE_VALUE).as_deref(), Some("109104"));
+
+        // the same study is de-identified to the same UIDs
+        let mut obj2 = instance();
+        anonymize(&mut obj2, &AnonymizeOptions::new()).unwrap();
+        assert_eq!(
+            text(&obj2, tags::STUDY_INSTANCE_UID),
+            Some(study_instance_uid)
+        );
+    }
+
+    /// Other Patient IDs, a retired attribute
+    const OTHER_PATIENT_IDS: Tag = Tag(0x0010, 0x1000);
+
+    #[test]
+    fn remove_other_ids_overlays_and_curves() {
+        let other_id = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PATIENT_ID,
+            VR::LO,
+            PrimitiveValue::from("67890"),
+        )]);
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(OTHER_PATIENT_IDS, VR::LO, PrimitiveValue::from("67890")),
+            DataElement::new(
+                tags::OTHER_PATIENT_I_DS_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![other_id].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(Tag(0x5000, 0x0005), VR::US, PrimitiveValue::from(2_u16)),
+            DataElement::new(
+                Tag(0x5000, 0x3000),
+                VR::OW,
+                PrimitiveValue::from(vec![1_u8, 2]),
+            ),
+            DataElement::new(Tag(0x6000, 0x0010), VR::US, PrimitiveValue::from(2_u16)),
+            DataElement::new(
+                Tag(0x6000, 0x3000),
+                VR::OW,
+                PrimitiveValue::from(vec![3_u8, 4]),
+            ),
+            DataElement::new(
+                Tag(0x6002, 0x3000),
+                VR::OW,
+                PrimitiveValue::from(vec![5_u8, 6]),
+            ),
+            DataElement::new(
+                Tag(0x6002, 0x4000),
+                VR::LT,
+                PrimitiveValue::from("drawn by Dr. Smith"),
+            ),
+        ]);
+        anonymize(&mut obj, &AnonymizeOptions::new()).unwrap();
+
+        assert!(obj.element_opt(OTHER_PATIENT_IDS).unwrap().is_none());
+        assert!(obj
+            .element_opt(tags::OTHER_PATIENT_I_DS_SEQUENCE)
+            .unwrap()
+            .is_none());
+        for tag in [
+            Tag(0x5000, 0x0005),
+            Tag(0x5000, 0x3000),
+            Tag(0x6000, 0x3000),
+            Tag(0x6002, 0x3000),
+            Tag(0x6002, 0x4000),
+        ] {
+            assert!(obj.element_opt(tag).unwrap().is_none(), "{} was kept", tag);
+        }
+        // overlay attributes other than the data and comments are kept
+        assert!(obj.element_opt(Tag(0x6000, 0x0010)).unwrap().is_some());
+    }
+
+    #[test]
+    fn apply_unlisted_action() {
+        let mut obj = instance();
+        let options = AnonymizeOptions::new()
+            .unlisted_action(Action::Remove)
+            .with_action(tags::SOP_CLASS_UID, Action::Keep);
+        anonymize(&mut obj, &options).unwrap();
+
+        // not in the profile
+        assert_eq!(text(&obj, tags::MODALITY), None);
+        assert_eq!(
+            text(&obj, tags::SOP_CLASS_UID).as_deref(),
+            Some("1.2.840.10008.5.1.4.1.1.2")
+        );
+        // in the profile
+        assert_eq!(text(&obj, tags::PATIENT_NAME).as_deref(), Some(""));
+        assert_ne!(
+            text(&obj, tags::SOP_INSTANCE_UID).as_deref(),
+            Some("1.9.1.1.2")
+        );
+        assert!(text(&obj, tags::SOP_INSTANCE_UID).is_some());
+    }
+
+    #[test]
+    fn apply_profile_options_and_overrides() {
+        let mut obj = instance();
+        let options = AnonymizeOptions::new()
+            .retain_dates(true)
+            .retain_device_identity(true)
+            .retain_patient_characteristics(true)
+            .retain_uids(true)
+            .remove_private_tags(false)
+            .cleaner(|_, v| v.replace("General ", ""))
+            .with_action(tags::INSTITUTION_NAME, Action::Clean)
+            .with_action(tags::MODALITY, Action::Remove)
+            .with_action(
+                tags::PATIENT_ID,
+                Action::Replace(PrimitiveValue::from("SUBJECT-01")),
+            )
+            .contributing_equipment(None);
+        anonymize(&mut obj, &options).unwrap();
+
+        assert_eq!(text(&obj, tags::PATIENT_NAME).as_deref(), Some(""));
+        assert_eq!(text(&obj, tags::PATIENT_ID).as_deref(), Some("SUBJECT-01"));
+        assert_eq!(text(&obj, tags::PATIENT_SEX).as_deref(), Some("M"));
+        assert_eq!(text(&obj, tags::PATIENT_WEIGHT).as_deref(), Some("80"));
+        assert_eq!(text(&obj, tags::STUDY_DATE).as_deref(), Some("20230101"));
+        assert_eq!(text(&obj, tags::SERIES_TIME).as_deref(), Some("101010"));
+        assert_eq!(text(&obj, tags::STATION_NAME).as_deref(), Some("CT01"));
+        assert_eq!(
+            text(&obj, tags::INSTITUTION_NAME).as_deref(),
+            Some("Hospital")
+        );
+        assert_eq!(text(&obj, tags::MODALITY), None);
+        assert_eq!(
+            text(&obj, tags::STUDY_INSTANCE_UID).as_deref(),
+            Some("1.9.1")
+        );
+        assert_eq!(text(&obj, Tag(0x0009, 0x1001)).as_deref(), Some("Doe^John"));
+        assert_eq!(
+            text(&obj, tags::LONGITUDINAL_TEMPORAL_INFORMATION_MODIFIED).as_deref(),
+            Some("UNMODIFIED")
+        );
+        let codes = obj
+            .element(tags::DEIDENTIFICATION_METHOD_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(codes.len(), 5);
+        assert!(obj.contributing_equipment().is_empty());
+    }
+
+    #[test]
+    fn anonymize_file_meta() {
+        let mut obj = instance()
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.2")
+                    .transfer_syntax("1.2.840.10008.1.2.1"),
+            )
+            .unwrap();
+        anonymize_file(&mut obj, &AnonymizeOptions::new()).unwrap();
+        assert_eq!(
+            obj.meta().media_storage_sop_instance_uid(),
+            obj.element(tags::SOP_INSTANCE_UID)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+        assert!(obj
+            .meta()
+            .media_storage_sop_instance_uid()
+            .starts_with("2.25."));
+    }
+
+    #[test]
+    fn anonymize_study_with_mapper() {
+        let mut mapper = UidMapper::unique("2.25").unwrap();
+        let options = AnonymizeOptions::new();
+        let mut first = instance();
+        let mut second = instance();
+        second.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.9.1.1.1"),
+        ));
+        anonymize_with_mapper(&mut first, &options, &mut mapper).unwrap();
+        anonymize_with_mapper(&mut second, &options, &mut mapper).unwrap();
+
+        let study_uid = text(&first, tags::STUDY_INSTANCE_UID).unwrap();
+        assert_eq!(
+            text(&second, tags::STUDY_INSTANCE_UID),
+            Some(study_uid.clone())
+        );
+        assert_eq!(mapper.get("1.9.1"), Some(study_uid.as_str()));
+        // not the same UID as with the default hash strategy
+        let mut obj = instance();
+        anonymize(&mut obj, &options).unwrap();
+        assert_ne!(text(&obj, tags::STUDY_INSTANCE_UID), Some(study_uid));
+
+        // the second instance is the one referenced by the first
+        let item = &first
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            text(item, tags::REFERENCED_SOP_INSTANCE_UID),
+            text(&second, tags::SOP_INSTANCE_UID)
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Bulk export of attribute values across many objects
+//! to [Apache Arrow], available with the `arrow` feature.
+//!
+//! [`column`] gathers the numeric values of one attribute
+//! from each object into an Arrow list array,
+//! parsing decimal strings (DS) and integer strings (IS) into numbers,
+//! and [`record_batch`] does the same for several attributes,
+//! with one column per attribute and one row per object.
+//! See [`dicom_core::value::arrow`] for how values are converted.
+//!
+//! [Apache Arrow]: https://arrow.apache.org
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! use dicom_object::arrow::record_batch;
+//! use dicom_object::InMemDicomObject;
+//!
+//! let objects: Vec<InMemDicomObject> = (0..3_u16)
+//!     .map(|i| {
+//!         InMemDicomObject::from_element_iter([
+//!             DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(256 * (i + 1))),
+//!             DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(256_u16)),
+//!         ])
+//!     })
+//!     .collect();
+//!
+//! let batch = record_batch(&objects, &[tags::ROWS, tags::COLUMNS])?;
+//! assert_eq!(batch.num_rows(), 3);
+//! assert_eq!(batch.schema().field(0).name(), "Rows");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::InMemDicomObject;
+use dicom_core::arrow_array::{ArrayRef, RecordBatch};
+use dicom_core::arrow_schema::{ArrowError, Field, Schema};
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::value::arrow::{
+    decimal_string_column, integer_string_column, primitive_column, Error as ConvertError,
+};
+use dicom_core::{Tag, VR};
+use dicom_dictionary_std::StandardDataDictionary;
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+use std::sync::Arc;
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// An element does not have a primitive value
+    #[snafu(display("Element {} does not have a primitive value", tag))]
+    NotPrimitive { tag: Tag, backtrace: Backtrace },
+    /// The values of an element could not be exported
+    #[snafu(display("Could not export the values of element {}", tag))]
+    ConvertColumn {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: ConvertError,
+    },
+    /// The record batch could not be built from the columns
+    #[snafu(display("Could not build record batch"))]
+    BuildRecordBatch {
+        source: ArrowError,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Gather the values of the given attribute in each of the given objects
+/// into an Arrow list array, with one list per object.
+///
+/// Objects without the attribute have a null list.
+/// Values of a decimal string (DS) element are parsed into 64-bit floats,
+/// and values of an integer string (IS) element into 64-bit integers,
+/// as decided by the VR of the first element found.
+/// Fails if the values are not all of the same binary numeric type,
+/// if a DS or IS value is not a number,
+/// or if the element is a sequence.
+pub fn column<'a, I, D>(objects: I, tag: Tag) -> Result<ArrayRef>
+where
+    I: IntoIterator<Item = &'a InMemDicomObject<D>>,
+    D: 'a + DataDictionary + Clone,
+{
+    let mut vr = None;
+    let values = objects
+        .into_iter()
+        .map(|obj| match obj.element(tag) {
+            Ok(elem) => {
+                vr.get_or_insert(elem.vr());
+                elem.value()
+                    .primitive()
+                    .map(Some)
+                    .context(NotPrimitiveSnafu { tag })
+            }
+            Err(_) => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match vr {
+        Some(VR::DS) => decimal_string_column(values),
+        Some(VR::IS) => integer_string_column(values),
+        _ => primitive_column(values),
+    }
+    .context(ConvertColumnSnafu { tag })
+}
+
+/// Gather the values of the given attributes in each of the given objects
+/// into an Arrow record batch,
+/// with one row per object and one column per attribute,
+/// as built by [`column`].
+///
+/// Columns are named after the attribute's keyword in the standard dictionary,
+/// or after its tag if the attribute is not in the dictionary.
+pub fn record_batch<'a, I, D>(objects: I, tags: &[Tag]) -> Result<RecordBatch>
+where
+    I: IntoIterator<Item = &'a InMemDicomObject<D>>,
+    D: 'a + DataDictionary + Clone,
+{
+    let objects: Vec<_> = objects.into_iter().collect();
+    let columns = tags
+        .iter()
+        .map(|&tag| column(objects.iter().copied(), tag))
+        .collect::<Result<Vec<_>>>()?;
+    let fields: Vec<_> = tags
+        .iter()
+        .zip(&columns)
+        .map(|(&tag, column)| {
+            let name = StandardDataDictionary
+                .by_tag(tag)
+                .map(|entry| entry.alias().to_string())
+                .unwrap_or_else(|| tag.to_string());
+            Field::new(name, column.data_type().clone(), true)
+        })
+        .collect();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).context(BuildRecordBatchSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::arrow_array::cast::AsArray;
+    use dicom_core::arrow_array::types::{Float64Type, Int64Type, UInt16Type};
+    use dicom_core::arrow_array::Array;
+    use dicom_core::arrow_schema::DataType;
+    use dicom_core::value::{Value, C};
+    use dicom_core::{DataElement, Length, PrimitiveValue, VR};
+    use dicom_dictionary_std::tags;
+
+    fn objects() -> Vec<InMemDicomObject> {
+        vec![
+            InMemDicomObject::from_element_iter([
+                DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+                DataElement::new(
+                    tags::PIXEL_SPACING,
+                    VR::DS,
+                    PrimitiveValue::from([0.5_f64, 0.25]),
+                ),
+            ]),
+            InMemDicomObject::from_element_iter([DataElement::new(
+                tags::ROWS,
+                VR::US,
+                PrimitiveValue::from(256_u16),
+            )]),
+            InMemDicomObject::from_element_iter([
+                DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(128_u16)),
+                DataElement::new(
+                    tags::PIXEL_SPACING,
+                    VR::DS,
+                    PrimitiveValue::from([1.0_f64, 1.0]),
+                ),
+                DataElement::new(Tag(0x0009, 0x1001), VR::UL, PrimitiveValue::Empty),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn export_column_across_objects() {
+        let objects = objects();
+        let column = column(&objects, tags::PIXEL_SPACING).unwrap();
+        let list = column.as_list::<i32>();
+        assert_eq!(list.len(), 3);
+        assert!(list.is_null(1));
+        assert_eq!(list.value_offsets(), &[0, 2, 2, 4]);
+        assert_eq!(
+            list.values().as_primitive::<Float64Type>().values(),
+            &[0.5, 0.25, 1.0, 1.0]
+        );
+
+        let err = super::column(
+            &[InMemDicomObject::from_element_iter([DataElement::new(
+                tags::REFERENCED_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: C::new(),
+                    size: Length::UNDEFINED,
+                },
+            )])],
+            tags::REFERENCED_IMAGE_SEQUENCE,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::NotPrimitive { .. }));
+    }
+
+    #[test]
+    fn export_textual_numbers() {
+        let objects = vec![
+            InMemDicomObject::from_element_iter([
+                DataElement::new(tags::SLICE_THICKNESS, VR::DS, PrimitiveValue::from("2.5")),
+                DataElement::new(tags::INSTANCE_NUMBER, VR::IS, PrimitiveValue::from("12")),
+            ]),
+            InMemDicomObject::from_element_iter([DataElement::new(
+                tags::INSTANCE_NUMBER,
+                VR::IS,
+                PrimitiveValue::Strs(["13".to_string(), "-1".to_string()].into()),
+            )]),
+        ];
+        let batch =
+            record_batch(&objects, &[tags::SLICE_THICKNESS, tags::INSTANCE_NUMBER]).unwrap();
+
+        let thickness = batch.column(0).as_list::<i32>();
+        assert!(thickness.is_null(1));
+        assert_eq!(
+            thickness.values().as_primitive::<Float64Type>().values(),
+            &[2.5]
+        );
+        let numbers = batch.column(1).as_list::<i32>();
+        assert_eq!(numbers.value_offsets(), &[0, 1, 3]);
+        assert_eq!(
+            numbers.values().as_primitive::<Int64Type>().values(),
+            &[12, 13, -1]
+        );
+
+        let err = super::column(
+            &[InMemDicomObject::from_element_iter([DataElement::new(
+                tags::SLICE_THICKNESS,
+                VR::DS,
+                PrimitiveValue::from("thick"),
+            )])],
+            tags::SLICE_THICKNESS,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ConvertColumn {
+                source: ConvertError::ParseValue { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn export_record_batch() {
+        let objects = objects();
+        let batch = record_batch(
+            &objects,
+            &[tags::ROWS, tags::PIXEL_SPACING, Tag(0x0009, 0x1001)],
+        )
+        .unwrap();
+        assert_eq!(batch.num_rows(), 3);
+
+        let schema = batch.schema();
+        assert_eq!(schema.field(0).name(), "Rows");
+        assert_eq!(schema.field(1).name(), "PixelSpacing");
+        assert_eq!(schema.field(2).name(), "(0009,1001)");
+        assert_eq!(schema.field(2).data_type(), &DataType::Null);
+
+        let rows = batch.column(0).as_list::<i32>();
+        assert_eq!(
+            rows.values().as_primitive::<UInt16Type>().values(),
+            &[512, 256, 128]
+        );
+    }
+}
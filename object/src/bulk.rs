@@ -0,0 +1,456 @@
+//! Externalization of bulk data while reading.
+//!
+//! With [`open_file_with_bulk_data`](crate::OpenFileOptions::open_file_with_bulk_data),
+//! element values larger than a given threshold
+//! are not read into memory.
+//! Their bytes are written to a [`BulkDataSink`] instead,
+//! such as a directory of side files ([`DirectorySink`]),
+//! and the element is kept in the object with an empty value,
+//! alongside a [`BulkDataRef`] describing where the value went.
+//! The rest of the data set can then be inspected and edited as usual.
+//!
+//! Only primitive values are externalized.
+//! The fragments of encapsulated pixel data,
+//! as well as the values of elements nested in sequences
+//! smaller than the threshold,
+//! are still read into memory.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use dicom_dictionary_std::tags;
+//! use dicom_object::bulk::DirectorySink;
+//! use dicom_object::OpenFileOptions;
+//!
+//! let mut sink = DirectorySink::new("bulk");
+//! let obj = OpenFileOptions::new()
+//!     .open_file_with_bulk_data("path/to/file.dcm", 64 * 1024, &mut sink)?;
+//! if let Some(pixel_data) = obj.bulk_data(tags::PIXEL_DATA) {
+//!     println!("pixel data stored in {}", pixel_data.uri());
+//! }
+//! # Result::<(), Box<dyn std::error::Error>>::Ok(())
+//! ```
+use crate::file::ReadSettings;
+use dicom_core::header::{DataElementHeader, Header};
+use dicom_core::value::PrimitiveValue;
+use dicom_core::{Tag, VR};
+use dicom_encoding::TransferSyntax;
+use dicom_parser::dataset::lazy_read::{Error as LazyReadError, LazyDataSetReader};
+use dicom_parser::dataset::{DataToken, Error as TokenError, LazyDataToken};
+use dicom_parser::stateful::decode::{DynStatefulDecoder, Error as DecoderError};
+use dicom_parser::StatefulDecode;
+use snafu::{Backtrace, ResultExt, Snafu};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// An error which may occur while externalizing bulk data.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// Could not create the data set decoder
+    CreateDecoder {
+        #[snafu(backtrace)]
+        source: DecoderError,
+    },
+    /// Could not read data set token
+    ReadToken {
+        #[snafu(backtrace)]
+        source: LazyReadError,
+    },
+    /// Could not read data set value
+    ReadValue {
+        #[snafu(backtrace)]
+        source: TokenError,
+    },
+    /// Could not create the destination of a bulk data value
+    #[snafu(display("Could not create the destination of bulk data for element {}", tag))]
+    CreateDestination {
+        tag: Tag,
+        source: io::Error,
+        backtrace: Backtrace,
+    },
+    /// Could not write a bulk data value
+    #[snafu(display("Could not write bulk data of element {}", tag))]
+    WriteValue {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: TokenError,
+    },
+    /// Could not finish writing a bulk data value
+    #[snafu(display("Could not finish writing bulk data of element {}", tag))]
+    FlushValue {
+        tag: Tag,
+        source: io::Error,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A reference to an element value
+/// which was written to a bulk data sink instead of being kept in memory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BulkDataRef {
+    vr: VR,
+    length: u32,
+    uri: String,
+}
+
+impl BulkDataRef {
+    /// Create a new bulk data reference.
+    pub fn new(vr: VR, length: u32, uri: impl Into<String>) -> Self {
+        BulkDataRef {
+            vr,
+            length,
+            uri: uri.into(),
+        }
+    }
+
+    /// The value representation of the element, as read.
+    pub fn vr(&self) -> VR {
+        self.vr
+    }
+
+    /// The length of the value in bytes.
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// The location of the value,
+    /// as given by the sink to which it was written.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+}
+
+/// A destination for externalized element values.
+///
+/// The bytes of each value are written exactly as encoded in the source,
+/// in its transfer syntax.
+pub trait BulkDataSink {
+    /// The type of writer receiving the bytes of a value.
+    type Writer: Write;
+
+    /// Prepare the destination of the value of the given element,
+    /// returning the URI by which it will be referred to
+    /// and a writer for its bytes.
+    fn create(&mut self, header: &DataElementHeader) -> io::Result<(String, Self::Writer)>;
+}
+
+impl<S: ?Sized> BulkDataSink for &mut S
+where
+    S: BulkDataSink,
+{
+    type Writer = S::Writer;
+
+    fn create(&mut self, header: &DataElementHeader) -> io::Result<(String, Self::Writer)> {
+        (**self).create(header)
+    }
+}
+
+/// A bulk data sink writing each value to a new file in a directory.
+///
+/// Files are named after the order in which values were written
+/// and the element's tag, as in `0001_7FE00010.bin`.
+/// The URI of each value is the path to its file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectorySink {
+    dir: PathBuf,
+    count: u32,
+}
+
+impl DirectorySink {
+    /// Create a sink writing to the given directory,
+    /// which is created if it does not exist yet.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DirectorySink {
+            dir: dir.into(),
+            count: 0,
+        }
+    }
+}
+
+impl BulkDataSink for DirectorySink {
+    type Writer = BufWriter<File>;
+
+    fn create(&mut self, header: &DataElementHeader) -> io::Result<(String, Self::Writer)> {
+        std::fs::create_dir_all(&self.dir)?;
+        self.count += 1;
+        let tag = header.tag();
+        let path = self.dir.join(format!(
+            "{:04}_{:04X}{:04X}.bin",
+            self.count,
+            tag.group(),
+            tag.element()
+        ));
+        let file = File::create(&path)?;
+        Ok((path.display().to_string(), BufWriter::new(file)))
+    }
+}
+
+/// A shared handle to the reference of the last value externalized
+/// by [`BulkDataTokens`].
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PendingBulkData(Rc<RefCell<Option<BulkDataRef>>>);
+
+impl PendingBulkData {
+    /// Take the reference of the value last externalized, if any.
+    pub fn take(&self) -> Option<BulkDataRef> {
+        self.0.borrow_mut().take()
+    }
+}
+
+/// An iterator of data set tokens
+/// which writes values larger than a threshold to a bulk data sink.
+///
+/// Externalized values are yielded as empty primitive values,
+/// while their reference is left in the associated [`PendingBulkData`].
+pub(crate) struct BulkDataTokens<S, K> {
+    reader: LazyDataSetReader<S>,
+    sink: K,
+    threshold: u32,
+    pending: PendingBulkData,
+}
+
+impl<R, K> BulkDataTokens<DynStatefulDecoder<R>, K>
+where
+    R: Read,
+    K: BulkDataSink,
+{
+    /// Create a new iterator reading from the given source
+    /// in the given transfer syntax,
+    /// with the character set and odd length strategy of the given settings.
+    pub fn new_with_ts(
+        source: R,
+        ts: &TransferSyntax,
+        settings: &ReadSettings,
+        sink: K,
+        threshold: u32,
+    ) -> Result<Self> {
+        let mut parser = DynStatefulDecoder::new_with(source, ts, settings.charset, 0)
+            .context(CreateDecoderSnafu)?;
+        parser.set_odd_length_strategy(settings.odd_length());
+        Ok(BulkDataTokens {
+            reader: LazyDataSetReader::new(parser),
+            sink,
+            threshold,
+            pending: PendingBulkData::default(),
+        })
+    }
+}
+
+impl<S, K> BulkDataTokens<S, K> {
+    /// Obtain a handle to the references of externalized values.
+    pub fn pending(&self) -> PendingBulkData {
+        self.pending.clone()
+    }
+}
+
+impl<S, K> Iterator for BulkDataTokens<S, K>
+where
+    S: StatefulDecode,
+    K: BulkDataSink,
+{
+    type Item = Result<DataToken>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let threshold = self.threshold;
+        let token = match self.reader.next()?.context(ReadTokenSnafu) {
+            Ok(token) => token,
+            Err(e) => return Some(Err(e)),
+        };
+        let header = match &token {
+            LazyDataToken::LazyValue { header, .. } if matches!(header.len.get(), Some(len) if len > threshold) => {
+                *header
+            }
+            _ => return Some(token.into_owned().context(ReadValueSnafu)),
+        };
+
+        let tag = header.tag();
+        let result = self
+            .sink
+            .create(&header)
+            .context(CreateDestinationSnafu { tag })
+            .and_then(|(uri, mut writer)| {
+                token
+                    .read_value_into(&mut writer)
+                    .context(WriteValueSnafu { tag })?;
+                writer.flush().context(FlushValueSnafu { tag })?;
+                Ok(uri)
+            });
+        Some(result.map(|uri| {
+            *self.pending.0.borrow_mut() = Some(BulkDataRef::new(header.vr(), header.len.0, uri));
+            DataToken::PrimitiveValue(PrimitiveValue::Empty)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileMetaTableBuilder, InMemDicomObject, OpenFileOptions};
+    use dicom_core::value::PrimitiveValue;
+    use dicom_core::value::Value;
+    use dicom_core::DataElement;
+    use dicom_core::Length;
+    use dicom_dictionary_std::tags;
+
+    const SC_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.7";
+    const EXPLICIT_VR_LE: &str = "1.2.840.10008.1.2.1";
+
+    /// A sink keeping values in memory.
+    #[derive(Default)]
+    struct MemorySink(Vec<Rc<RefCell<Vec<u8>>>>);
+
+    struct SharedWriter(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl BulkDataSink for MemorySink {
+        type Writer = SharedWriter;
+
+        fn create(&mut self, _header: &DataElementHeader) -> io::Result<(String, SharedWriter)> {
+            let data = Rc::new(RefCell::new(Vec::new()));
+            self.0.push(data.clone());
+            Ok((format!("mem:{}", self.0.len() - 1), SharedWriter(data)))
+        }
+    }
+
+    fn file_bytes() -> Vec<u8> {
+        let item = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::CODE_VALUE, VR::SH, PrimitiveValue::from("113100")),
+            DataElement::new(
+                Tag(0x0009, 0x1010),
+                VR::OB,
+                PrimitiveValue::from(vec![0x55_u8; 64]),
+            ),
+        ]);
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from(SC_IMAGE_STORAGE),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.1"),
+            ),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new(
+                tags::DEIDENTIFICATION_METHOD_CODE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OW,
+                PrimitiveValue::U16(vec![0x1234; 256].into()),
+            ),
+        ]);
+        let file = obj
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .transfer_syntax(EXPLICIT_VR_LE)
+                    .media_storage_sop_class_uid(SC_IMAGE_STORAGE)
+                    .media_storage_sop_instance_uid("2.25.1"),
+            )
+            .unwrap();
+        let mut bytes = Vec::new();
+        file.write_all(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn externalize_values_above_threshold() {
+        let mut sink = MemorySink::default();
+        let obj = OpenFileOptions::new()
+            .from_reader_with_bulk_data(&file_bytes()[..], 32, &mut sink)
+            .unwrap();
+
+        // small values are read as usual
+        assert_eq!(
+            obj.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Doe^John"
+        );
+        assert!(obj.bulk_data(tags::PATIENT_NAME).is_none());
+
+        // large values are left empty, with a reference
+        let pixel_data = obj.element(tags::PIXEL_DATA).unwrap();
+        assert!(pixel_data.is_empty_value());
+        let bulk = obj.bulk_data(tags::PIXEL_DATA).unwrap();
+        assert_eq!(bulk.vr(), VR::OW);
+        assert_eq!(bulk.length(), 512);
+        assert_eq!(bulk.uri(), "mem:1");
+        assert_eq!(
+            &sink.0[1].borrow()[..4],
+            &[0x34, 0x12, 0x34, 0x12],
+            "bytes should be written as encoded"
+        );
+
+        // including in nested items
+        let item = &obj
+            .element(tags::DEIDENTIFICATION_METHOD_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            item.element(tags::CODE_VALUE).unwrap().to_str().unwrap(),
+            "113100"
+        );
+        let private = item.bulk_data(Tag(0x0009, 0x1010)).unwrap();
+        assert_eq!(private.length(), 64);
+        assert_eq!(private.uri(), "mem:0");
+        assert_eq!(*sink.0[0].borrow(), vec![0x55; 64]);
+        assert_eq!(obj.iter_bulk_data().count(), 1);
+    }
+
+    #[test]
+    fn restore_and_replace_bulk_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.dcm");
+        std::fs::write(&path, file_bytes()).unwrap();
+
+        let mut sink = DirectorySink::new(dir.path().join("bulk"));
+        let mut obj = OpenFileOptions::new()
+            .open_file_with_bulk_data(&path, 128, &mut sink)
+            .unwrap();
+        let bulk = obj.bulk_data(tags::PIXEL_DATA).unwrap().clone();
+        assert!(bulk.uri().ends_with("0001_7FE00010.bin"));
+
+        // load the value back from the side file
+        let bytes = std::fs::read(bulk.uri()).unwrap();
+        assert_eq!(bytes.len(), 512);
+        assert_eq!(obj.restore_bulk_data(tags::PIXEL_DATA, bytes), Some(bulk));
+        assert!(obj.bulk_data(tags::PIXEL_DATA).is_none());
+        let pixel_data = obj.element(tags::PIXEL_DATA).unwrap();
+        assert_eq!(pixel_data.vr(), VR::OW);
+        assert_eq!(pixel_data.to_bytes().unwrap().len(), 512);
+
+        // replacing an externalized element forgets its reference
+        let mut obj = OpenFileOptions::new()
+            .open_file_with_bulk_data(&path, 128, &mut sink)
+            .unwrap();
+        obj.put(DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OW,
+            PrimitiveValue::U16(vec![0; 4].into()),
+        ));
+        assert!(obj.bulk_data(tags::PIXEL_DATA).is_none());
+        assert_eq!(obj.restore_bulk_data(tags::PIXEL_DATA, vec![]), None);
+    }
+}
@@ -0,0 +1,485 @@
+//! Compact, read-optimized storage of DICOM objects.
+//!
+//! Workloads which hold the headers of many instances in memory,
+//! such as indexing a large archive,
+//! pay for the flexibility of [`InMemDicomObject`]
+//! in the overhead of its map of elements
+//! and in the separate allocation of every text value.
+//! A [`CompactDicomObject`] keeps the elements
+//! in a single boxed slice sorted by tag,
+//! looked up by binary search,
+//! and shares equal text values across objects through an [`Interner`].
+//! Compact objects cannot be modified,
+//! but can be converted back into an in-memory object.
+//!
+//! Files can be read directly into this representation
+//! with [`OpenFileOptions::open_file_compact`](crate::OpenFileOptions::open_file_compact).
+//! The file is still read into an in-memory object first,
+//! so the peak memory usage of reading a single file is not reduced.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::compact::{CompactDicomObject, Interner};
+//!
+//! let mut interner = Interner::new();
+//! let headers: Vec<_> = (0..3)
+//!     .map(|i| {
+//!         let obj = InMemDicomObject::from_element_iter([
+//!             DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+//!             DataElement::new(tags::INSTANCE_NUMBER, VR::IS, PrimitiveValue::from(i.to_string())),
+//!         ]);
+//!         CompactDicomObject::from_object(&obj, &mut interner)
+//!     })
+//!     .collect();
+//!
+//! assert_eq!(headers[2].element(tags::MODALITY)?.to_str().as_deref(), Some("CT"));
+//! // "CT" is only stored once
+//! assert_eq!(interner.len(), 4);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::{InMemDicomObject, InMemElement, InMemFragment};
+use crate::{NoSuchAttributeNameSnafu, NoSuchDataElementTagSnafu, Result};
+use dicom_core::header::{HasLength, Header};
+use dicom_core::value::{PrimitiveValue, Value};
+use dicom_core::{DataDictionary, DataElement, Length, Tag, VR};
+use dicom_dictionary_std::StandardDataDictionary;
+use snafu::OptionExt;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// A pool of shared text values.
+///
+/// Interning the values of many objects in the same pool
+/// keeps a single copy of each distinct value,
+/// such as modalities, SOP class UIDs,
+/// and the attributes shared by all instances of a series.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    /// Create an empty pool.
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    /// Obtain the shared copy of the given text value,
+    /// adding it to the pool if it is not there yet.
+    pub fn intern(&mut self, value: &str) -> Arc<str> {
+        if let Some(shared) = self.strings.get(value) {
+            return Arc::clone(shared);
+        }
+        let shared: Arc<str> = Arc::from(value);
+        self.strings.insert(Arc::clone(&shared));
+        shared
+    }
+
+    /// The number of distinct values in the pool.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// The value of a compact data element.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompactValue {
+    /// An empty value.
+    Empty,
+    /// A single text value.
+    Str(Arc<str>),
+    /// Multiple text values.
+    Strs(Box<[Arc<str>]>),
+    /// Any other primitive value.
+    Other(Box<PrimitiveValue>),
+    /// A sequence of items.
+    Sequence(Box<[CompactDicomObject]>),
+    /// An encapsulated pixel data sequence.
+    PixelSequence(Box<CompactPixelSequence>),
+}
+
+impl CompactValue {
+    /// Retrieve the value as a single string,
+    /// with multiple values separated by backslashes.
+    ///
+    /// Returns `None` if the value is a sequence.
+    pub fn to_str(&self) -> Option<Cow<'_, str>> {
+        match self {
+            CompactValue::Empty => Some(Cow::Borrowed("")),
+            CompactValue::Str(value) => Some(Cow::Borrowed(value)),
+            CompactValue::Strs(values) => Some(Cow::Owned(values.join("\\"))),
+            CompactValue::Other(value) => Some(value.to_str()),
+            CompactValue::Sequence(_) | CompactValue::PixelSequence(_) => None,
+        }
+    }
+
+    /// Retrieve the items of the value, if it is a sequence.
+    pub fn items(&self) -> Option<&[CompactDicomObject]> {
+        match self {
+            CompactValue::Sequence(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Create a compact value from the value of an in-memory element,
+    /// interning its text.
+    fn from_value<D>(
+        value: &Value<InMemDicomObject<D>, InMemFragment>,
+        interner: &mut Interner,
+    ) -> Self
+    where
+        D: DataDictionary + Clone,
+    {
+        match value {
+            Value::Primitive(PrimitiveValue::Empty) => CompactValue::Empty,
+            Value::Primitive(PrimitiveValue::Str(value)) => {
+                CompactValue::Str(interner.intern(value))
+            }
+            Value::Primitive(PrimitiveValue::Strs(values)) => {
+                CompactValue::Strs(values.iter().map(|value| interner.intern(value)).collect())
+            }
+            Value::Primitive(value) => CompactValue::Other(Box::new(value.clone())),
+            Value::Sequence { items, .. } => CompactValue::Sequence(
+                items
+                    .iter()
+                    .map(|item| CompactDicomObject::from_object(item, interner))
+                    .collect(),
+            ),
+            Value::PixelSequence {
+                offset_table,
+                fragments,
+            } => CompactValue::PixelSequence(Box::new(CompactPixelSequence {
+                offset_table: offset_table.iter().copied().collect(),
+                fragments: fragments.iter().cloned().collect(),
+            })),
+        }
+    }
+
+    /// Convert the value back into the value of an in-memory element.
+    fn to_value<D>(&self, dict: &D) -> Value<InMemDicomObject<D>, InMemFragment>
+    where
+        D: DataDictionary + Clone,
+    {
+        match self {
+            CompactValue::Empty => PrimitiveValue::Empty.into(),
+            CompactValue::Str(value) => PrimitiveValue::from(&**value).into(),
+            CompactValue::Strs(values) => {
+                PrimitiveValue::Strs(values.iter().map(|value| value.to_string()).collect()).into()
+            }
+            CompactValue::Other(value) => (**value).clone().into(),
+            CompactValue::Sequence(items) => Value::Sequence {
+                items: items
+                    .iter()
+                    .map(|item| item.to_object_with_dict(dict.clone()))
+                    .collect(),
+                size: Length::UNDEFINED,
+            },
+            CompactValue::PixelSequence(sequence) => Value::PixelSequence {
+                offset_table: sequence.offset_table.iter().copied().collect(),
+                fragments: sequence.fragments.iter().cloned().collect(),
+            },
+        }
+    }
+}
+
+/// The contents of an encapsulated pixel data sequence
+/// in a compact object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactPixelSequence {
+    offset_table: Box<[u32]>,
+    fragments: Box<[InMemFragment]>,
+}
+
+impl CompactPixelSequence {
+    /// Retrieve the basic offset table.
+    pub fn offset_table(&self) -> &[u32] {
+        &self.offset_table
+    }
+
+    /// Retrieve the compressed fragments.
+    pub fn fragments(&self) -> &[InMemFragment] {
+        &self.fragments
+    }
+}
+
+/// A data element of a compact object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactElement {
+    tag: Tag,
+    vr: VR,
+    len: Length,
+    value: CompactValue,
+}
+
+impl CompactElement {
+    /// Retrieve the value representation of the element.
+    pub fn vr(&self) -> VR {
+        self.vr
+    }
+
+    /// Retrieve the value of the element.
+    pub fn value(&self) -> &CompactValue {
+        &self.value
+    }
+
+    /// Retrieve the value as a single string,
+    /// with multiple values separated by backslashes.
+    ///
+    /// Returns `None` if the value is a sequence.
+    pub fn to_str(&self) -> Option<Cow<'_, str>> {
+        self.value.to_str()
+    }
+
+    /// Retrieve the items of the value, if it is a sequence.
+    pub fn items(&self) -> Option<&[CompactDicomObject]> {
+        self.value.items()
+    }
+}
+
+impl HasLength for CompactElement {
+    fn length(&self) -> Length {
+        self.len
+    }
+}
+
+impl Header for CompactElement {
+    fn tag(&self) -> Tag {
+        self.tag
+    }
+}
+
+/// A read-only DICOM object
+/// keeping its elements in a slice sorted by tag.
+///
+/// See the [module documentation](crate::compact) for more details.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CompactDicomObject {
+    elements: Box<[CompactElement]>,
+}
+
+impl CompactDicomObject {
+    /// Create a compact copy of the given object,
+    /// interning its text values in the given pool.
+    pub fn from_object<D>(obj: &InMemDicomObject<D>, interner: &mut Interner) -> Self
+    where
+        D: DataDictionary + Clone,
+    {
+        // elements of an in-memory object are already sorted by tag
+        let elements = obj
+            .iter()
+            .map(|elem| CompactElement {
+                tag: elem.tag(),
+                vr: elem.vr(),
+                len: elem.length(),
+                value: CompactValue::from_value(elem.value(), interner),
+            })
+            .collect();
+        CompactDicomObject { elements }
+    }
+
+    /// Convert this object back into an in-memory object,
+    /// using the standard data dictionary.
+    pub fn to_object(&self) -> InMemDicomObject {
+        self.to_object_with_dict(StandardDataDictionary)
+    }
+
+    /// Convert this object back into an in-memory object,
+    /// using the given data dictionary.
+    pub fn to_object_with_dict<D>(&self, dict: D) -> InMemDicomObject<D>
+    where
+        D: DataDictionary + Clone,
+    {
+        let elements: Vec<InMemElement<D>> = self
+            .elements
+            .iter()
+            .map(|elem| {
+                let value = elem.value.to_value(&dict);
+                match elem.value {
+                    // sequence lengths are recalculated when written
+                    CompactValue::Sequence(_) => DataElement::new(elem.tag, elem.vr, value),
+                    _ => DataElement::new_with_len(elem.tag, elem.vr, elem.len, value),
+                }
+            })
+            .collect();
+        InMemDicomObject::from_iter_with_dict(elements, dict)
+    }
+
+    /// Retrieve a particular DICOM element by its tag.
+    ///
+    /// An error is returned if the element does not exist.
+    pub fn element(&self, tag: Tag) -> Result<&CompactElement> {
+        self.elements
+            .binary_search_by_key(&tag, |elem| elem.tag)
+            .ok()
+            .map(|index| &self.elements[index])
+            .context(NoSuchDataElementTagSnafu { tag })
+    }
+
+    /// Retrieve a particular DICOM element by its name
+    /// in the standard data dictionary.
+    ///
+    /// An error is returned if the element does not exist.
+    pub fn element_by_name(&self, name: &str) -> Result<&CompactElement> {
+        let tag = StandardDataDictionary
+            .by_name(name)
+            .context(NoSuchAttributeNameSnafu { name })?
+            .tag
+            .inner();
+        self.element(tag)
+    }
+
+    /// Iterate over the elements of this object in ascending tag order.
+    pub fn iter(&self) -> impl Iterator<Item = &CompactElement> + '_ {
+        self.elements.iter()
+    }
+
+    /// Iterate over the tags of this object's elements.
+    pub fn tags(&self) -> impl Iterator<Item = Tag> + '_ {
+        self.elements.iter().map(|elem| elem.tag)
+    }
+
+    /// The number of elements in the root of this object.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Whether this object has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a CompactDicomObject {
+    type Item = &'a CompactElement;
+    type IntoIter = std::slice::Iter<'a, CompactElement>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::value::C;
+    use dicom_dictionary_std::tags;
+
+    fn sample(instance_number: &str) -> InMemDicomObject {
+        let item = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::CODE_VALUE,
+            VR::SH,
+            PrimitiveValue::from("113101"),
+        )]);
+        InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::IMAGE_TYPE,
+                VR::CS,
+                PrimitiveValue::Strs(["ORIGINAL".to_string(), "PRIMARY".to_string()].into()),
+            ),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::Empty),
+            DataElement::new(
+                tags::INSTANCE_NUMBER,
+                VR::IS,
+                PrimitiveValue::from(instance_number),
+            ),
+            DataElement::new(
+                tags::PROCEDURE_CODE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                Value::PixelSequence {
+                    offset_table: C::new(),
+                    fragments: vec![vec![1, 2, 3, 4]].into(),
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn compact_object_lookup_and_interning() {
+        let mut interner = Interner::new();
+        let a = CompactDicomObject::from_object(&sample("1"), &mut interner);
+        let b = CompactDicomObject::from_object(&sample("2"), &mut interner);
+
+        assert_eq!(a.len(), 7);
+        assert_eq!(
+            a.tags().collect::<Vec<_>>(),
+            sample("1").tags().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            b.element(tags::INSTANCE_NUMBER).unwrap().to_str().unwrap(),
+            "2"
+        );
+        assert_eq!(
+            a.element_by_name("ImageType").unwrap().to_str().unwrap(),
+            "ORIGINAL\\PRIMARY"
+        );
+        assert_eq!(a.element(tags::ROWS).unwrap().to_str().unwrap(), "512");
+        assert_eq!(a.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(), "");
+        let items = a
+            .element(tags::PROCEDURE_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(
+            items[0]
+                .element(tags::CODE_VALUE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "113101"
+        );
+        assert!(a.element(tags::PATIENT_ID).is_err());
+        assert!(a.element(tags::STUDY_DATE).is_err());
+
+        // shared text is stored once
+        let modality = |obj: &CompactDicomObject| match obj.element(tags::MODALITY).unwrap().value()
+        {
+            CompactValue::Str(value) => Arc::clone(value),
+            value => panic!("unexpected value {:?}", value),
+        };
+        assert!(Arc::ptr_eq(&modality(&a), &modality(&b)));
+        // ORIGINAL, PRIMARY, CT, 1, 113101, 2
+        assert_eq!(interner.len(), 6);
+    }
+
+    #[test]
+    fn compact_object_into_in_mem_object() {
+        let obj = sample("1");
+        let mut interner = Interner::new();
+        let compact = CompactDicomObject::from_object(&obj, &mut interner);
+        let back = compact.to_object();
+
+        assert_eq!(
+            back.tags().collect::<Vec<_>>(),
+            obj.tags().collect::<Vec<_>>()
+        );
+        for (elem, original) in back.iter().zip(obj.iter()) {
+            assert_eq!(elem.vr(), original.vr());
+            match original.value() {
+                // undefined lengths never compare equal
+                Value::Sequence { items, .. } => assert_eq!(elem.items().unwrap(), &items[..]),
+                Value::PixelSequence { .. } => assert_eq!(elem.value(), original.value()),
+                _ => assert_eq!(elem, original),
+            }
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Derivation of new instances from existing ones.
+//!
+//! Workflows which modify the pixel data of an instance,
+//! such as the removal of burned-in annotations,
+//! should store the result as a new instance
+//! which refers to the original one (PS3.3 C.7.6.1.1.3).
+//! [`into_derived`](InMemDicomObject::into_derived)
+//! turns an object into such a derived instance:
+//!
+//! - a new _SOP Instance UID_ is assigned;
+//! - _Image Type_, if present, is marked as `DERIVED`;
+//! - _Derivation Description_ and _Derivation Code Sequence_
+//!   describe the derivation;
+//! - an item referring to the original instance
+//!   is appended to the _Source Image Sequence_;
+//! - the processing equipment, if given,
+//!   is appended to the _Contributing Equipment Sequence_.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::derived::Derivation;
+//!
+//! let original = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::SOP_CLASS_UID, VR::UI, PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7")),
+//!     DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from("2.25.1")),
+//! ]);
+//!
+//! let derivation = Derivation::new("Burned-in annotations removed");
+//! let derived = original.clone().into_derived(&derivation)?;
+//!
+//! assert_ne!(derived.element(tags::SOP_INSTANCE_UID)?.to_str()?, "2.25.1");
+//! let source = &derived.element(tags::SOURCE_IMAGE_SEQUENCE)?.items().unwrap()[0];
+//! assert_eq!(source.element(tags::REFERENCED_SOP_INSTANCE_UID)?.to_str()?, "2.25.1");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::equipment::{ContributingEquipment, PurposeOfReference};
+use crate::mem::InMemDicomObject;
+use crate::uid::generate_uid;
+use crate::FileDicomObject;
+use dicom_core::value::{PrimitiveValue, Value, C};
+use dicom_core::{DataDictionary, DataElement, Length, Tag, VR};
+use dicom_dictionary_std::tags;
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Missing attribute {} to refer to the source instance", tag))]
+    MissingSourceAttribute { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Could not generate a new UID"))]
+    GenerateUid {
+        #[snafu(backtrace)]
+        source: crate::uid::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The description of how an instance was derived from another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derivation {
+    description: String,
+    codes: Vec<(String, String, String)>,
+    uid_root: String,
+    equipment: Option<ContributingEquipment>,
+}
+
+impl Derivation {
+    /// Create a derivation with the given description,
+    /// recorded in _Derivation Description_.
+    pub fn new(description: impl Into<String>) -> Self {
+        Derivation {
+            description: description.into(),
+            codes: Vec::new(),
+            uid_root: "2.25".to_string(),
+            equipment: None,
+        }
+    }
+
+    /// Add a coded description of the derivation
+    /// to the _Derivation Code Sequence_,
+    /// usually from CID 7203 _Image Derivation_.
+    pub fn code(
+        mut self,
+        code_value: impl Into<String>,
+        coding_scheme_designator: impl Into<String>,
+        code_meaning: impl Into<String>,
+    ) -> Self {
+        self.codes.push((
+            code_value.into(),
+            coding_scheme_designator.into(),
+            code_meaning.into(),
+        ));
+        self
+    }
+
+    /// Set the root under which the new SOP Instance UID is generated,
+    /// which is `2.25` by default.
+    pub fn uid_root(mut self, uid_root: impl Into<String>) -> Self {
+        self.uid_root = uid_root.into();
+        self
+    }
+
+    /// Set the equipment to record as processing equipment
+    /// in the _Contributing Equipment Sequence_ of the derived instance.
+    /// No equipment is recorded by default.
+    pub fn equipment(mut self, equipment: ContributingEquipment) -> Self {
+        self.equipment = Some(equipment);
+        self
+    }
+}
+
+impl<D> InMemDicomObject<D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Turn this object into a new instance derived from it,
+    /// as described in the [module documentation](crate::derived).
+    ///
+    /// Fails if the object does not have
+    /// a _SOP Class UID_ and a _SOP Instance UID_.
+    pub fn into_derived(mut self, derivation: &Derivation) -> Result<Self> {
+        let source_uid = |tag| {
+            self.element_opt(tag)
+                .ok()
+                .flatten()
+                .and_then(|e| e.to_str().ok())
+                .map(|uid| uid.trim_end_matches(['\0', ' ']).to_string())
+                .filter(|uid| !uid.is_empty())
+                .context(MissingSourceAttributeSnafu { tag })
+        };
+        let sop_class_uid = source_uid(tags::SOP_CLASS_UID)?;
+        let sop_instance_uid = source_uid(tags::SOP_INSTANCE_UID)?;
+        let new_uid = generate_uid(&derivation.uid_root).context(GenerateUidSnafu)?;
+
+        let source = InMemDicomObject::from_iter_with_dict(
+            [
+                DataElement::new(
+                    tags::REFERENCED_SOP_CLASS_UID,
+                    VR::UI,
+                    PrimitiveValue::from(sop_class_uid),
+                ),
+                DataElement::new(
+                    tags::REFERENCED_SOP_INSTANCE_UID,
+                    VR::UI,
+                    PrimitiveValue::from(sop_instance_uid),
+                ),
+            ],
+            self.dict.clone(),
+        );
+        let mut sources = match self
+            .take_element(tags::SOURCE_IMAGE_SEQUENCE)
+            .map(|e| e.into_value())
+        {
+            Ok(Value::Sequence { items, .. }) => items,
+            _ => C::new(),
+        };
+        sources.push(source);
+
+        let codes: C<_> = derivation
+            .codes
+            .iter()
+            .map(|(value, scheme, meaning)| {
+                InMemDicomObject::from_iter_with_dict(
+                    [
+                        DataElement::new(
+                            tags::CODE_VALUE,
+                            VR::SH,
+                            PrimitiveValue::from(value.as_str()),
+                        ),
+                        DataElement::new(
+                            tags::CODING_SCHEME_DESIGNATOR,
+                            VR::SH,
+                            PrimitiveValue::from(scheme.as_str()),
+                        ),
+                        DataElement::new(
+                            tags::CODE_MEANING,
+                            VR::LO,
+                            PrimitiveValue::from(meaning.as_str()),
+                        ),
+                    ],
+                    self.dict.clone(),
+                )
+            })
+            .collect();
+
+        // Image Type (0008,0008) value 1 is either ORIGINAL or DERIVED
+        if let Some(elem) = self.element_opt(tags::IMAGE_TYPE).ok().flatten() {
+            if let Ok(mut values) = elem.to_multi_str().map(|v| v.to_vec()) {
+                if let Some(first) = values.first_mut() {
+                    *first = "DERIVED".to_string();
+                    self.put(DataElement::new(
+                        tags::IMAGE_TYPE,
+                        VR::CS,
+                        PrimitiveValue::Strs(values.into()),
+                    ));
+                }
+            }
+        }
+
+        self.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(new_uid),
+        ));
+        self.put(DataElement::new(
+            tags::DERIVATION_DESCRIPTION,
+            VR::ST,
+            PrimitiveValue::from(derivation.description.as_str()),
+        ));
+        // codes of an earlier derivation no longer apply
+        if codes.is_empty() {
+            self.remove_element(tags::DERIVATION_CODE_SEQUENCE);
+        } else {
+            self.put(DataElement::new(
+                tags::DERIVATION_CODE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: codes,
+                    size: Length::UNDEFINED,
+                },
+            ));
+        }
+        self.put(DataElement::new(
+            tags::SOURCE_IMAGE_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: sources,
+                size: Length::UNDEFINED,
+            },
+        ));
+        if let Some(equipment) = &derivation.equipment {
+            self.add_contributing_equipment(equipment, PurposeOfReference::Processing);
+        }
+        Ok(self)
+    }
+}
+
+impl<D> FileDicomObject<InMemDicomObject<D>>
+where
+    D: DataDictionary + Clone,
+{
+    /// Turn this file object into a new instance derived from it,
+    /// as in [`InMemDicomObject::into_derived`],
+    /// also updating the _Media Storage SOP Instance UID_
+    /// of the file meta group.
+    ///
+    /// The preamble is carried over,
+    /// unless it holds a TIFF header,
+    /// which would not match the derived contents.
+    pub fn into_derived(self, derivation: &Derivation) -> Result<Self> {
+        let mut meta = self.meta().clone();
+        let preamble = (!self.has_tiff_preamble()).then(|| *self.preamble());
+        let obj = self.into_inner().into_derived(derivation)?;
+        let new_uid = obj
+            .element(tags::SOP_INSTANCE_UID)
+            .ok()
+            .and_then(|e| e.to_str().ok())
+            .map(|uid| uid.into_owned())
+            .unwrap_or_default();
+        meta.media_storage_sop_instance_uid = new_uid;
+        meta.update_information_group_length();
+        let mut file = obj.with_exact_meta(meta);
+        if let Some(preamble) = preamble {
+            file.set_preamble(preamble);
+        }
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::FileMetaTableBuilder;
+
+    fn original() -> InMemDicomObject {
+        InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::IMAGE_TYPE,
+                VR::CS,
+                PrimitiveValue::Strs(["ORIGINAL".to_string(), "PRIMARY".to_string()].into()),
+            ),
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7"),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.1"),
+            ),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.2"),
+            ),
+        ])
+    }
+
+    fn text(obj: &InMemDicomObject, tag: Tag) -> String {
+        obj.element(tag).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn derive_instance_with_references() {
+        let derivation = Derivation::new("Burned-in annotations removed")
+            .code("113101", "DCM", "Clean Pixel Data Option")
+            .uid_root("1.2.3")
+            .equipment(ContributingEquipment::new("ACME").software_version("1.0"));
+        let derived = original().into_derived(&derivation).unwrap();
+
+        assert!(text(&derived, tags::SOP_INSTANCE_UID).starts_with("1.2.3."));
+        assert_eq!(text(&derived, tags::STUDY_INSTANCE_UID), "2.25.2");
+        assert_eq!(
+            derived
+                .element(tags::IMAGE_TYPE)
+                .unwrap()
+                .to_multi_str()
+                .unwrap()[..],
+            ["DERIVED".to_string(), "PRIMARY".to_string()]
+        );
+        assert_eq!(
+            text(&derived, tags::DERIVATION_DESCRIPTION),
+            "Burned-in annotations removed"
+        );
+        let codes = derived
+            .element(tags::DERIVATION_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(text(&codes[0], tags::CODE_VALUE), "113101");
+        assert_eq!(text(&codes[0], tags::CODING_SCHEME_DESIGNATOR), "DCM");
+        let equipment = derived.contributing_equipment();
+        assert_eq!(equipment.len(), 1);
+        assert_eq!(text(&equipment[0], tags::MANUFACTURER), "ACME");
+        let purpose = &equipment[0]
+            .element(tags::PURPOSE_OF_REFERENCE_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(text(purpose, tags::CODE_VALUE), "109102");
+
+        // deriving again keeps the earlier sources
+        let derived = derived.into_derived(&Derivation::new("Cropped")).unwrap();
+        assert!(derived.element(tags::DERIVATION_CODE_SEQUENCE).is_err());
+        let sources = derived
+            .element(tags::SOURCE_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(
+            text(&sources[0], tags::REFERENCED_SOP_INSTANCE_UID),
+            "2.25.1"
+        );
+        assert_eq!(
+            text(&sources[0], tags::REFERENCED_SOP_CLASS_UID),
+            "1.2.840.10008.5.1.4.1.1.7"
+        );
+        assert!(text(&sources[1], tags::REFERENCED_SOP_INSTANCE_UID).starts_with("1.2.3."));
+        // no equipment was given for the second derivation
+        assert_eq!(derived.contributing_equipment().len(), 1);
+    }
+
+    #[test]
+    fn derive_file_updates_meta() {
+        let file = original()
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .transfer_syntax("1.2.840.10008.1.2.1")
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7"),
+            )
+            .unwrap();
+        let derived = file.into_derived(&Derivation::new("Modified")).unwrap();
+        assert_eq!(
+            derived.meta().media_storage_sop_instance_uid(),
+            text(&derived, tags::SOP_INSTANCE_UID)
+        );
+
+        let err = InMemDicomObject::new_empty()
+            .into_derived(&Derivation::new("Modified"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::MissingSourceAttribute {
+                tag: tags::SOP_CLASS_UID,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,650 @@
+//! Encryption at rest of selected elements in cached objects.
+//!
+//! Applications which keep parsed DICOM objects in a local cache
+//! should not turn that cache into an unprotected store
+//! of protected health information (PHI).
+//! [`CacheEncryption`] encrypts the values of a configurable set of elements
+//! when writing an object to the cache,
+//! and transparently decrypts them when reading it back.
+//!
+//! The cryptographic primitive is supplied by the caller
+//! through the [`ElementCipher`] trait,
+//! along with the key.
+//! With the `aes-gcm` feature,
+//! [`AesGcmCipher`] implements it with the AES-GCM cipher
+//! of the RustCrypto project
+//! (the [`aes-gcm`](https://crates.io/crates/aes-gcm) crate).
+//!
+//! # Cache representation
+//!
+//! The cache holds a regular DICOM file.
+//! Each encrypted element keeps its tag,
+//! with a value representation of UN
+//! and the encrypted form of the original element as its value,
+//! so that the original value representation is restored on load.
+//! The value starts with a byte telling
+//! whether a trailing padding byte was added to the ciphertext.
+//! The tag of the element is authenticated along with the value,
+//! so that encrypted values cannot be moved to other elements.
+//! Objects in _Implicit VR Little Endian_
+//! are cached in _Explicit VR Little Endian_,
+//! since the value representation UN would not survive otherwise.
+//!
+//! Only elements at the root of the data set are encrypted.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use dicom_object::encryption::ElementCipher;
+//! # struct MyCipher;
+//! # impl ElementCipher for MyCipher {
+//! #     type Error = std::io::Error;
+//! #     fn encrypt(&self, _: &[u8], p: &[u8]) -> Result<Vec<u8>, Self::Error> { Ok(p.to_vec()) }
+//! #     fn decrypt(&self, _: &[u8], c: &[u8]) -> Result<Vec<u8>, Self::Error> { Ok(c.to_vec()) }
+//! # }
+//! use dicom_object::encryption::CacheEncryption;
+//! use dicom_object::open_file;
+//! use std::fs::File;
+//!
+//! let encryption = CacheEncryption::new(MyCipher);
+//! let obj = open_file("0001.dcm")?;
+//! encryption.write_cached(&obj, File::create("cache/0001.dcm")?)?;
+//!
+//! let cached = encryption.read_cached(File::open("cache/0001.dcm")?)?;
+//! assert_eq!(cached.element_by_name("PatientName")?, obj.element_by_name("PatientName")?);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::InMemDicomObject;
+use crate::{DefaultDicomObject, FileDicomObject};
+use dicom_core::value::PrimitiveValue;
+use dicom_core::{DataElement, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_transfer_syntax_registry::entries::{
+    EXPLICIT_VR_LITTLE_ENDIAN, IMPLICIT_VR_LITTLE_ENDIAN,
+};
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+use std::io::{Read, Write};
+
+/// The elements encrypted by default:
+/// the identifying attributes of the patient
+/// and other persons or organizations related to the study.
+pub const DEFAULT_ENCRYPTED_TAGS: &[Tag] = &[
+    tags::PATIENT_NAME,
+    tags::PATIENT_ID,
+    tags::ISSUER_OF_PATIENT_ID,
+    tags::PATIENT_BIRTH_DATE,
+    tags::PATIENT_BIRTH_TIME,
+    tags::OTHER_PATIENT_I_DS_SEQUENCE,
+    tags::OTHER_PATIENT_NAMES,
+    tags::PATIENT_BIRTH_NAME,
+    tags::PATIENT_MOTHER_BIRTH_NAME,
+    tags::PATIENT_ADDRESS,
+    tags::PATIENT_TELEPHONE_NUMBERS,
+    tags::ACCESSION_NUMBER,
+    tags::REFERRING_PHYSICIAN_NAME,
+    tags::PERFORMING_PHYSICIAN_NAME,
+    tags::OPERATORS_NAME,
+    tags::INSTITUTION_NAME,
+    tags::INSTITUTION_ADDRESS,
+];
+
+/// An authenticated cipher for the values of cached elements.
+///
+/// Implementations are expected to use
+/// an authenticated encryption algorithm such as AES-GCM,
+/// to generate a fresh nonce in every call to [`encrypt`](ElementCipher::encrypt)
+/// and to include it in the output.
+/// Decryption must fail if the data or the associated data were tampered with.
+pub trait ElementCipher {
+    /// The type of error returned by the cipher.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Encrypt the given plaintext,
+    /// authenticating it together with the associated data.
+    fn encrypt(&self, associated_data: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Self::Error>;
+
+    /// Decrypt data produced by [`encrypt`](ElementCipher::encrypt)
+    /// with the same associated data.
+    fn decrypt(&self, associated_data: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error>;
+}
+
+impl<T: ?Sized + ElementCipher> ElementCipher for &T {
+    type Error = T::Error;
+
+    fn encrypt(&self, associated_data: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        (**self).encrypt(associated_data, plaintext)
+    }
+
+    fn decrypt(&self, associated_data: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        (**self).decrypt(associated_data, data)
+    }
+}
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum EncryptionError {
+    /// Could not encode the element to encrypt
+    #[snafu(display("Could not encode element {}", tag))]
+    EncodeElement {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+    /// Could not encrypt the element
+    #[snafu(display("Could not encrypt element {}", tag))]
+    Encrypt {
+        tag: Tag,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        backtrace: Backtrace,
+    },
+    /// Could not decrypt the element
+    #[snafu(display("Could not decrypt element {}", tag))]
+    Decrypt {
+        tag: Tag,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        backtrace: Backtrace,
+    },
+    /// The encrypted value is malformed
+    #[snafu(display("Malformed encrypted value in element {}", tag))]
+    MalformedValue { tag: Tag, backtrace: Backtrace },
+    /// The decrypted element could not be decoded
+    #[snafu(display("Could not decode decrypted element {}", tag))]
+    DecodeElement {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+    /// The decrypted data does not hold the expected element
+    #[snafu(display("Decrypted data does not hold element {}", tag))]
+    MissingElement { tag: Tag, backtrace: Backtrace },
+    /// Could not write the cached object
+    WriteCache {
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+    /// Could not read the cached object
+    ReadCache {
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+}
+
+pub type Result<T, E = EncryptionError> = std::result::Result<T, E>;
+
+/// An [`ElementCipher`] using AES-256 in Galois/Counter Mode,
+/// available with the `aes-gcm` feature.
+///
+/// A random 96-bit nonce is generated for every value
+/// and stored in front of the ciphertext,
+/// which ends with the 128-bit authentication tag.
+/// Since nonces are random,
+/// a single key should not encrypt more than 2<sup>32</sup> values.
+///
+/// # Example
+///
+/// ```
+/// use dicom_object::encryption::{AesGcmCipher, CacheEncryption};
+///
+/// // the key should be kept in a secure key store
+/// let key = AesGcmCipher::generate_key();
+/// let encryption = CacheEncryption::new(AesGcmCipher::new(&key));
+/// ```
+#[cfg(feature = "aes-gcm")]
+#[derive(Clone)]
+pub struct AesGcmCipher(aes_gcm::Aes256Gcm);
+
+#[cfg(feature = "aes-gcm")]
+impl AesGcmCipher {
+    /// The length of a key in bytes.
+    pub const KEY_LEN: usize = 32;
+
+    /// The length of the nonce in front of each ciphertext in bytes.
+    pub const NONCE_LEN: usize = 12;
+
+    /// Create a cipher with the given 256-bit key.
+    pub fn new(key: &[u8; Self::KEY_LEN]) -> Self {
+        use aes_gcm::KeyInit;
+        AesGcmCipher(aes_gcm::Aes256Gcm::new(key.into()))
+    }
+
+    /// Generate a new random key
+    /// with the random number generator of the operating system.
+    pub fn generate_key() -> [u8; Self::KEY_LEN] {
+        use aes_gcm::aead::OsRng;
+        use aes_gcm::KeyInit;
+        aes_gcm::Aes256Gcm::generate_key(&mut OsRng).into()
+    }
+}
+
+#[cfg(feature = "aes-gcm")]
+impl std::fmt::Debug for AesGcmCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never print the key schedule
+        f.write_str("AesGcmCipher { .. }")
+    }
+}
+
+#[cfg(feature = "aes-gcm")]
+impl ElementCipher for AesGcmCipher {
+    type Error = aes_gcm::Error;
+
+    fn encrypt(&self, associated_data: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+
+        let nonce = aes_gcm::Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.0.encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: associated_data,
+            },
+        )?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn decrypt(&self, associated_data: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        use aes_gcm::aead::{Aead, Payload};
+
+        if data.len() < Self::NONCE_LEN {
+            return Err(aes_gcm::Error);
+        }
+        let (nonce, ciphertext) = data.split_at(Self::NONCE_LEN);
+        self.0.decrypt(
+            aes_gcm::Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: associated_data,
+            },
+        )
+    }
+}
+
+/// Encryption of selected elements of objects written to a local cache.
+#[derive(Debug, Clone)]
+pub struct CacheEncryption<C> {
+    cipher: C,
+    tags: Vec<Tag>,
+}
+
+impl<C> CacheEncryption<C>
+where
+    C: ElementCipher,
+{
+    /// Create a cache encryption helper with the given cipher,
+    /// encrypting the elements in [`DEFAULT_ENCRYPTED_TAGS`].
+    pub fn new(cipher: C) -> Self {
+        CacheEncryption {
+            cipher,
+            tags: DEFAULT_ENCRYPTED_TAGS.to_vec(),
+        }
+    }
+
+    /// Replace the set of elements to encrypt.
+    pub fn with_tags(mut self, tags: impl IntoIterator<Item = Tag>) -> Self {
+        self.tags = tags.into_iter().collect();
+        self
+    }
+
+    /// Add an element to the set of elements to encrypt.
+    pub fn add_tag(mut self, tag: Tag) -> Self {
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        self
+    }
+
+    /// The elements encrypted by this helper.
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Write the given object into the cache,
+    /// with the configured elements encrypted.
+    pub fn write_cached<W: Write>(&self, obj: &DefaultDicomObject, to: W) -> Result<()> {
+        self.encrypt_object(obj)?
+            .write_all(to)
+            .context(WriteCacheSnafu)
+    }
+
+    /// Read an object from the cache,
+    /// decrypting the encrypted elements.
+    pub fn read_cached<R: Read>(&self, from: R) -> Result<DefaultDicomObject> {
+        let mut obj = FileDicomObject::from_reader(from).context(ReadCacheSnafu)?;
+        self.decrypt_object(&mut obj)?;
+        Ok(obj)
+    }
+
+    /// Create a copy of the object
+    /// with the configured elements encrypted.
+    ///
+    /// Elements which are absent from the object are ignored.
+    pub fn encrypt_object(&self, obj: &DefaultDicomObject) -> Result<DefaultDicomObject> {
+        let mut obj = obj.clone();
+        if obj.meta().transfer_syntax() == IMPLICIT_VR_LITTLE_ENDIAN.uid() {
+            obj.meta_mut()
+                .set_transfer_syntax(&EXPLICIT_VR_LITTLE_ENDIAN);
+        }
+
+        for &tag in &self.tags {
+            let elem = match obj.take_element(tag) {
+                Ok(elem) => elem,
+                Err(_) => continue,
+            };
+
+            let mut plaintext = Vec::new();
+            InMemDicomObject::from_element_iter([elem])
+                .write_dataset_with_ts(&mut plaintext, &EXPLICIT_VR_LITTLE_ENDIAN.erased())
+                .context(EncodeElementSnafu { tag })?;
+            let ciphertext = self
+                .cipher
+                .encrypt(&associated_data(tag), &plaintext)
+                .map_err(|e| Box::new(e) as Box<_>)
+                .context(EncryptSnafu { tag })?;
+
+            // values must have an even length,
+            // so record whether a padding byte was added
+            let padding = (ciphertext.len() + 1) % 2;
+            let mut data = Vec::with_capacity(ciphertext.len() + 1 + padding);
+            data.push(padding as u8);
+            data.extend_from_slice(&ciphertext);
+            data.resize(data.len() + padding, 0);
+
+            obj.put(DataElement::new(tag, VR::UN, PrimitiveValue::from(data)));
+        }
+        Ok(obj)
+    }
+
+    /// Decrypt the encrypted elements of an object in place.
+    ///
+    /// Only elements of the configured set
+    /// with a value representation of UN are decrypted,
+    /// any others are left as they are.
+    pub fn decrypt_object(&self, obj: &mut DefaultDicomObject) -> Result<()> {
+        for &tag in &self.tags {
+            let data = match obj.element_opt(tag) {
+                Ok(Some(elem)) if elem.vr() == VR::UN => elem.to_bytes().ok(),
+                _ => None,
+            };
+            let data = match data {
+                Some(data) => data,
+                None => continue,
+            };
+
+            let ciphertext = match data.split_first() {
+                Some((&padding, rest)) if (padding as usize) <= rest.len().min(1) => {
+                    &rest[..rest.len() - padding as usize]
+                }
+                _ => return MalformedValueSnafu { tag }.fail(),
+            };
+            let plaintext = self
+                .cipher
+                .decrypt(&associated_data(tag), ciphertext)
+                .map_err(|e| Box::new(e) as Box<_>)
+                .context(DecryptSnafu { tag })?;
+            let mut item = InMemDicomObject::read_dataset_with_ts(
+                &plaintext[..],
+                &EXPLICIT_VR_LITTLE_ENDIAN.erased(),
+            )
+            .context(DecodeElementSnafu { tag })?;
+            let elem = item
+                .take_element(tag)
+                .ok()
+                .context(MissingElementSnafu { tag })?;
+            obj.put(elem);
+        }
+        Ok(())
+    }
+}
+
+/// The data authenticated along with an element value: its tag.
+fn associated_data(tag: Tag) -> [u8; 4] {
+    let [g0, g1] = tag.group().to_le_bytes();
+    let [e0, e1] = tag.element().to_le_bytes();
+    [g0, g1, e0, e1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::FileMetaTableBuilder;
+    use dicom_core::value::{Value, C};
+    use dicom_core::Length;
+    use std::fmt;
+
+    /// A keyed XOR "cipher" with a checksum, for testing only.
+    struct TestCipher(u8);
+
+    #[derive(Debug)]
+    struct TestCipherError;
+
+    impl fmt::Display for TestCipherError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("authentication failed")
+        }
+    }
+
+    impl std::error::Error for TestCipherError {}
+
+    impl TestCipher {
+        fn checksum(&self, aad: &[u8], data: &[u8]) -> u8 {
+            aad.iter()
+                .chain(data)
+                .fold(self.0, |sum, &b| sum.rotate_left(3) ^ b)
+        }
+    }
+
+    impl ElementCipher for TestCipher {
+        type Error = TestCipherError;
+
+        fn encrypt(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, TestCipherError> {
+            let mut data: Vec<u8> = plaintext.iter().map(|b| b ^ self.0).collect();
+            data.push(self.checksum(aad, &data));
+            Ok(data)
+        }
+
+        fn decrypt(&self, aad: &[u8], data: &[u8]) -> Result<Vec<u8>, TestCipherError> {
+            let (checksum, data) = data.split_last().ok_or(TestCipherError)?;
+            if *checksum != self.checksum(aad, data) {
+                return Err(TestCipherError);
+            }
+            Ok(data.iter().map(|b| b ^ self.0).collect())
+        }
+    }
+
+    fn test_object(ts: &str) -> DefaultDicomObject {
+        let other_ids = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PATIENT_ID,
+            VR::LO,
+            PrimitiveValue::from("OTHER-1"),
+        )]);
+        InMemDicomObject::from_element_iter([
+            DataElement::new(tags::SOP_CLASS_UID, VR::UI, PrimitiveValue::from("1.2.3")),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.1"),
+            ),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^Jane")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("12345")),
+            DataElement::new(
+                tags::OTHER_PATIENT_I_DS_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: C::from_vec(vec![other_ids]),
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax(ts)
+                .media_storage_sop_class_uid("1.2.3"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn cache_roundtrip() {
+        for ts in [
+            IMPLICIT_VR_LITTLE_ENDIAN.uid(),
+            EXPLICIT_VR_LITTLE_ENDIAN.uid(),
+        ]
+        .iter()
+        {
+            let obj = test_object(ts);
+            let encryption = CacheEncryption::new(TestCipher(0x5A));
+
+            let mut cache = Vec::new();
+            encryption.write_cached(&obj, &mut cache).unwrap();
+            // the values are not stored in the clear
+            assert!(!cache.windows(8).any(|w| w == b"Doe^Jane"));
+            assert!(!cache.windows(7).any(|w| w == b"OTHER-1"));
+            assert!(cache.windows(2).any(|w| w == b"CT"));
+
+            // compare with the same object cached without encryption
+            let mut plain = Vec::new();
+            obj.write_all(&mut plain).unwrap();
+            let obj = FileDicomObject::from_reader(&plain[..]).unwrap();
+
+            let cached = encryption.read_cached(&cache[..]).unwrap();
+            for tag in [tags::PATIENT_NAME, tags::PATIENT_ID, tags::MODALITY].iter() {
+                assert_eq!(cached.element(*tag).unwrap(), obj.element(*tag).unwrap());
+            }
+            let items = cached
+                .element(tags::OTHER_PATIENT_I_DS_SEQUENCE)
+                .unwrap()
+                .items()
+                .unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(
+                items[0]
+                    .element(tags::PATIENT_ID)
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+                "OTHER-1"
+            );
+        }
+    }
+
+    #[test]
+    fn encrypted_elements_are_bound_to_their_tag() {
+        let encryption = CacheEncryption::new(TestCipher(0x5A));
+        let mut encrypted = encryption
+            .encrypt_object(&test_object(EXPLICIT_VR_LITTLE_ENDIAN.uid()))
+            .unwrap();
+        assert_eq!(encrypted.element(tags::PATIENT_NAME).unwrap().vr(), VR::UN);
+
+        // move the encrypted patient name into the patient ID
+        let name = encrypted.take_element(tags::PATIENT_NAME).unwrap();
+        encrypted.put(DataElement::new(
+            tags::PATIENT_ID,
+            VR::UN,
+            name.into_value(),
+        ));
+        assert!(matches!(
+            encryption.decrypt_object(&mut encrypted),
+            Err(EncryptionError::Decrypt { .. })
+        ));
+
+        // the wrong key does not decrypt either
+        let mut encrypted = encryption
+            .encrypt_object(&test_object(EXPLICIT_VR_LITTLE_ENDIAN.uid()))
+            .unwrap();
+        assert!(CacheEncryption::new(TestCipher(0x33))
+            .decrypt_object(&mut encrypted)
+            .is_err());
+    }
+
+    #[test]
+    fn only_configured_elements_are_encrypted() {
+        let encryption = CacheEncryption::new(TestCipher(1)).with_tags([tags::PATIENT_NAME]);
+        let encrypted = encryption
+            .encrypt_object(&test_object(EXPLICIT_VR_LITTLE_ENDIAN.uid()))
+            .unwrap();
+        assert_eq!(encrypted.element(tags::PATIENT_NAME).unwrap().vr(), VR::UN);
+        assert_eq!(encrypted.element(tags::PATIENT_ID).unwrap().vr(), VR::LO);
+        assert_eq!(
+            encryption.add_tag(tags::PATIENT_ID).tags(),
+            &[tags::PATIENT_NAME, tags::PATIENT_ID]
+        );
+    }
+
+    #[cfg(feature = "aes-gcm")]
+    #[test]
+    fn aes_gcm_roundtrip() {
+        let cipher = AesGcmCipher::new(&[7; AesGcmCipher::KEY_LEN]);
+        let aad = associated_data(tags::PATIENT_NAME);
+
+        let a = cipher.encrypt(&aad, b"Doe^Jane").unwrap();
+        let b = cipher.encrypt(&aad, b"Doe^Jane").unwrap();
+        // nonce, ciphertext and tag
+        assert_eq!(a.len(), AesGcmCipher::NONCE_LEN + 8 + 16);
+        // a fresh nonce is used every time
+        assert_ne!(a[..AesGcmCipher::NONCE_LEN], b[..AesGcmCipher::NONCE_LEN]);
+        assert_eq!(cipher.decrypt(&aad, &a).unwrap(), b"Doe^Jane");
+        assert_eq!(cipher.decrypt(&aad, &b).unwrap(), b"Doe^Jane");
+
+        // through the cache
+        let obj = test_object(EXPLICIT_VR_LITTLE_ENDIAN.uid());
+        let encryption = CacheEncryption::new(AesGcmCipher::new(&AesGcmCipher::generate_key()));
+        let mut cache = Vec::new();
+        encryption.write_cached(&obj, &mut cache).unwrap();
+        assert!(!cache.windows(8).any(|w| w == b"Doe^Jane"));
+        let cached = encryption.read_cached(&cache[..]).unwrap();
+        assert_eq!(
+            cached.element(tags::PATIENT_NAME).unwrap(),
+            obj.element(tags::PATIENT_NAME).unwrap()
+        );
+    }
+
+    #[cfg(feature = "aes-gcm")]
+    #[test]
+    fn aes_gcm_rejects_tampered_data() {
+        let cipher = AesGcmCipher::new(&[7; AesGcmCipher::KEY_LEN]);
+        let aad = associated_data(tags::PATIENT_NAME);
+        let data = cipher.encrypt(&aad, b"Doe^Jane").unwrap();
+
+        // any modified byte, in the nonce, ciphertext or tag
+        for i in 0..data.len() {
+            let mut tampered = data.clone();
+            tampered[i] ^= 0x01;
+            assert!(cipher.decrypt(&aad, &tampered).is_err());
+        }
+        // truncated data
+        assert!(cipher.decrypt(&aad, &data[..data.len() - 1]).is_err());
+        assert!(cipher.decrypt(&aad, &data[..4]).is_err());
+        // other associated data
+        assert!(cipher
+            .decrypt(&associated_data(tags::PATIENT_ID), &data)
+            .is_err());
+        // another key
+        assert!(AesGcmCipher::new(&[8; AesGcmCipher::KEY_LEN])
+            .decrypt(&aad, &data)
+            .is_err());
+
+        // a tampered value in the cache
+        let encryption = CacheEncryption::new(cipher);
+        let mut encrypted = encryption
+            .encrypt_object(&test_object(EXPLICIT_VR_LITTLE_ENDIAN.uid()))
+            .unwrap();
+        let mut value = encrypted
+            .element(tags::PATIENT_NAME)
+            .unwrap()
+            .to_bytes()
+            .unwrap()
+            .into_owned();
+        let middle = value.len() / 2;
+        value[middle] ^= 0x80;
+        encrypted.put(DataElement::new(
+            tags::PATIENT_NAME,
+            VR::UN,
+            PrimitiveValue::from(value),
+        ));
+        assert!(matches!(
+            encryption.decrypt_object(&mut encrypted),
+            Err(EncryptionError::Decrypt { .. })
+        ));
+    }
+}
@@ -0,0 +1,404 @@
+//! Traceability of modifications to DICOM instances.
+//!
+//! Equipment which modifies an existing instance,
+//! or derives a new one from it,
+//! should identify itself in the _Contributing Equipment Sequence_
+//! (0018,A001) of the resulting instance
+//! (PS3.3 C.12.1.1.7).
+//! This module provides the means to describe such equipment
+//! and to append the respective items to a DICOM object.
+//!
+//! The pipelines of this crate append these items on their own:
+//! [de-identification](crate::anonymize) records the de-identifying equipment
+//! unless disabled in its options,
+//! and [derivation](crate::derived) records the processing equipment
+//! given to the [`Derivation`](crate::derived::Derivation).
+//! Transcoding in `dicom-pixeldata` takes the modifying equipment
+//! as one of its options.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::equipment::{ContributingEquipment, PurposeOfReference};
+//!
+//! let equipment = ContributingEquipment::new("ACME Imaging")
+//!     .manufacturer_model_name("Anonymizer")
+//!     .software_version("2.1.0");
+//!
+//! let mut obj = InMemDicomObject::new_empty();
+//! obj.add_contributing_equipment(&equipment, PurposeOfReference::DeIdentifying);
+//! obj.add_contributing_equipment(&equipment, PurposeOfReference::Modifying);
+//! assert_eq!(obj.contributing_equipment().len(), 2);
+//! ```
+use crate::mem::InMemDicomObject;
+use dicom_core::chrono::{DateTime, FixedOffset, Local};
+use dicom_core::value::{PrimitiveValue, Value, C};
+use dicom_core::{DataDictionary, DataElement, Length, VR};
+use dicom_dictionary_std::tags;
+
+/// The purpose of reference of a contributing equipment item,
+/// as in CID 7005 _Contributing Equipment Purposes of Reference_.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PurposeOfReference {
+    /// `(109101, DCM, "Acquisition Equipment")`
+    Acquisition,
+    /// `(109102, DCM, "Processing Equipment")`
+    Processing,
+    /// `(109103, DCM, "Modifying Equipment")`
+    Modifying,
+    /// `(109104, DCM, "De-identifying Equipment")`
+    DeIdentifying,
+    /// `(109105, DCM, "Frame Extracting Equipment")`
+    FrameExtracting,
+    /// `(109106, DCM, "Enhanced Multi-frame Conversion Equipment")`
+    EnhancedMultiFrameConversion,
+    /// Any other coded purpose of reference.
+    Other {
+        /// Code Value (0008,0100)
+        code_value: String,
+        /// Coding Scheme Designator (0008,0102)
+        coding_scheme_designator: String,
+        /// Code Meaning (0008,0104)
+        code_meaning: String,
+    },
+}
+
+impl PurposeOfReference {
+    /// Retrieve the code value, coding scheme designator and code meaning
+    /// of this purpose of reference.
+    pub fn code(&self) -> (&str, &str, &str) {
+        match self {
+            PurposeOfReference::Acquisition => ("109101", "DCM", "Acquisition Equipment"),
+            PurposeOfReference::Processing => ("109102", "DCM", "Processing Equipment"),
+            PurposeOfReference::Modifying => ("109103", "DCM", "Modifying Equipment"),
+            PurposeOfReference::DeIdentifying => ("109104", "DCM", "De-identifying Equipment"),
+            PurposeOfReference::FrameExtracting => ("109105", "DCM", "Frame Extracting Equipment"),
+            PurposeOfReference::EnhancedMultiFrameConversion => {
+                ("109106", "DCM", "Enhanced Multi-frame Conversion Equipment")
+            }
+            PurposeOfReference::Other {
+                code_value,
+                coding_scheme_designator,
+                code_meaning,
+            } => (code_value, coding_scheme_designator, code_meaning),
+        }
+    }
+}
+
+/// The identity of a piece of equipment contributing to a DICOM instance,
+/// usually the application embedding this crate.
+///
+/// Only the manufacturer is required,
+/// all other attributes are included when set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContributingEquipment {
+    manufacturer: String,
+    manufacturer_model_name: Option<String>,
+    software_versions: Vec<String>,
+    device_serial_number: Option<String>,
+    institution_name: Option<String>,
+    station_name: Option<String>,
+    contribution_description: Option<String>,
+}
+
+impl ContributingEquipment {
+    /// Create a new contributing equipment description
+    /// with the given manufacturer.
+    pub fn new(manufacturer: impl Into<String>) -> Self {
+        ContributingEquipment {
+            manufacturer: manufacturer.into(),
+            manufacturer_model_name: None,
+            software_versions: Vec::new(),
+            device_serial_number: None,
+            institution_name: None,
+            station_name: None,
+            contribution_description: None,
+        }
+    }
+
+    /// Describe this library as the contributing equipment,
+    /// with its name and version.
+    pub fn dicom_rs() -> Self {
+        ContributingEquipment::new("DICOM-rs")
+            .manufacturer_model_name(env!("CARGO_PKG_NAME"))
+            .software_version(env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Set the Manufacturer's Model Name (0008,1090).
+    pub fn manufacturer_model_name(mut self, value: impl Into<String>) -> Self {
+        self.manufacturer_model_name = Some(value.into());
+        self
+    }
+
+    /// Add a value to the Software Versions (0018,1020).
+    pub fn software_version(mut self, value: impl Into<String>) -> Self {
+        self.software_versions.push(value.into());
+        self
+    }
+
+    /// Set the Device Serial Number (0018,1000).
+    pub fn device_serial_number(mut self, value: impl Into<String>) -> Self {
+        self.device_serial_number = Some(value.into());
+        self
+    }
+
+    /// Set the Institution Name (0008,0080).
+    pub fn institution_name(mut self, value: impl Into<String>) -> Self {
+        self.institution_name = Some(value.into());
+        self
+    }
+
+    /// Set the Station Name (0008,1010).
+    pub fn station_name(mut self, value: impl Into<String>) -> Self {
+        self.station_name = Some(value.into());
+        self
+    }
+
+    /// Set the Contribution Description (0018,A003).
+    pub fn contribution_description(mut self, value: impl Into<String>) -> Self {
+        self.contribution_description = Some(value.into());
+        self
+    }
+
+    /// Build a Contributing Equipment Sequence item
+    /// for a contribution with the given purpose at the given date-time.
+    pub fn to_item<D>(
+        &self,
+        purpose: &PurposeOfReference,
+        datetime: DateTime<FixedOffset>,
+        dict: D,
+    ) -> InMemDicomObject<D>
+    where
+        D: DataDictionary + Clone,
+    {
+        let (code_value, coding_scheme_designator, code_meaning) = purpose.code();
+        let code_item = InMemDicomObject::from_iter_with_dict(
+            [
+                DataElement::new(tags::CODE_VALUE, VR::SH, PrimitiveValue::from(code_value)),
+                DataElement::new(
+                    tags::CODING_SCHEME_DESIGNATOR,
+                    VR::SH,
+                    PrimitiveValue::from(coding_scheme_designator),
+                ),
+                DataElement::new(
+                    tags::CODE_MEANING,
+                    VR::LO,
+                    PrimitiveValue::from(code_meaning),
+                ),
+            ],
+            dict.clone(),
+        );
+
+        let mut item = InMemDicomObject::from_iter_with_dict(
+            [
+                DataElement::new(
+                    tags::MANUFACTURER,
+                    VR::LO,
+                    PrimitiveValue::from(self.manufacturer.as_str()),
+                ),
+                DataElement::new(
+                    tags::PURPOSE_OF_REFERENCE_CODE_SEQUENCE,
+                    VR::SQ,
+                    Value::Sequence {
+                        items: C::from_vec(vec![code_item]),
+                        size: Length::UNDEFINED,
+                    },
+                ),
+                DataElement::new(
+                    tags::CONTRIBUTION_DATE_TIME,
+                    VR::DT,
+                    PrimitiveValue::from(datetime.format("%Y%m%d%H%M%S%.6f%z").to_string()),
+                ),
+            ],
+            dict,
+        );
+
+        let optional = [
+            (
+                tags::MANUFACTURER_MODEL_NAME,
+                VR::LO,
+                &self.manufacturer_model_name,
+            ),
+            (
+                tags::DEVICE_SERIAL_NUMBER,
+                VR::LO,
+                &self.device_serial_number,
+            ),
+            (tags::INSTITUTION_NAME, VR::LO, &self.institution_name),
+            (tags::STATION_NAME, VR::SH, &self.station_name),
+            (
+                tags::CONTRIBUTION_DESCRIPTION,
+                VR::ST,
+                &self.contribution_description,
+            ),
+        ];
+        for (tag, vr, value) in optional {
+            if let Some(value) = value {
+                item.put(DataElement::new(
+                    tag,
+                    vr,
+                    PrimitiveValue::from(value.as_str()),
+                ));
+            }
+        }
+        if !self.software_versions.is_empty() {
+            item.put(DataElement::new(
+                tags::SOFTWARE_VERSIONS,
+                VR::LO,
+                PrimitiveValue::Strs(self.software_versions.iter().cloned().collect()),
+            ));
+        }
+
+        item
+    }
+}
+
+impl Default for ContributingEquipment {
+    /// Describe this library as the contributing equipment.
+    fn default() -> Self {
+        ContributingEquipment::dicom_rs()
+    }
+}
+
+impl<D> InMemDicomObject<D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Append an item describing the given equipment
+    /// to the object's Contributing Equipment Sequence,
+    /// with the current date-time as the contribution date-time.
+    ///
+    /// The sequence is created if it does not exist yet.
+    /// Existing items are preserved,
+    /// so that the full history of contributions is retained.
+    pub fn add_contributing_equipment(
+        &mut self,
+        equipment: &ContributingEquipment,
+        purpose: PurposeOfReference,
+    ) {
+        self.add_contributing_equipment_at(equipment, purpose, Local::now().into())
+    }
+
+    /// Append an item describing the given equipment
+    /// to the object's Contributing Equipment Sequence,
+    /// with the given contribution date-time.
+    pub fn add_contributing_equipment_at(
+        &mut self,
+        equipment: &ContributingEquipment,
+        purpose: PurposeOfReference,
+        datetime: DateTime<FixedOffset>,
+    ) {
+        let item = equipment.to_item(&purpose, datetime, self.dict.clone());
+
+        let (mut items, size) = match self
+            .take_element(tags::CONTRIBUTING_EQUIPMENT_SEQUENCE)
+            .map(|e| e.into_value())
+        {
+            Ok(Value::Sequence { items, size }) => (items, size),
+            _ => (C::new(), Length::UNDEFINED),
+        };
+        items.push(item);
+
+        self.put(DataElement::new(
+            tags::CONTRIBUTING_EQUIPMENT_SEQUENCE,
+            VR::SQ,
+            Value::Sequence { items, size },
+        ));
+    }
+
+    /// Retrieve the items of the object's Contributing Equipment Sequence,
+    /// or an empty slice if there is none.
+    pub fn contributing_equipment(&self) -> &[InMemDicomObject<D>] {
+        self.element_opt(tags::CONTRIBUTING_EQUIPMENT_SEQUENCE)
+            .ok()
+            .flatten()
+            .and_then(|e| e.items())
+            .unwrap_or(&[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::chrono::TimeZone;
+
+    #[test]
+    fn add_contributing_equipment_items() {
+        let equipment = ContributingEquipment::new("ACME")
+            .manufacturer_model_name("Converter")
+            .software_version("1.0")
+            .software_version("lib 0.5")
+            .institution_name("General Hospital");
+        let datetime = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2022, 11, 3, 14, 30, 5)
+            .unwrap();
+
+        let mut obj = InMemDicomObject::new_empty();
+        assert!(obj.contributing_equipment().is_empty());
+        obj.add_contributing_equipment_at(&equipment, PurposeOfReference::Modifying, datetime);
+        obj.add_contributing_equipment_at(
+            &ContributingEquipment::default(),
+            PurposeOfReference::DeIdentifying,
+            datetime,
+        );
+
+        let items = obj.contributing_equipment();
+        assert_eq!(items.len(), 2);
+
+        let item = &items[0];
+        assert_eq!(
+            item.element(tags::MANUFACTURER).unwrap().to_str().unwrap(),
+            "ACME"
+        );
+        assert_eq!(
+            item.element(tags::SOFTWARE_VERSIONS)
+                .unwrap()
+                .to_multi_str()
+                .unwrap()
+                .as_ref(),
+            &["1.0".to_string(), "lib 0.5".to_string()]
+        );
+        assert_eq!(
+            item.element(tags::INSTITUTION_NAME)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "General Hospital"
+        );
+        assert!(item.element_opt(tags::STATION_NAME).unwrap().is_none());
+        assert_eq!(
+            item.element(tags::CONTRIBUTION_DATE_TIME)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "20221103143005.000000+0100"
+        );
+        let code = &item
+            .element(tags::PURPOSE_OF_REFERENCE_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            code.element(tags::CODE_VALUE).unwrap().to_str().unwrap(),
+            "109103"
+        );
+
+        let item = &items[1];
+        assert_eq!(
+            item.element(tags::MANUFACTURER).unwrap().to_str().unwrap(),
+            "DICOM-rs"
+        );
+        let code = &item
+            .element(tags::PURPOSE_OF_REFERENCE_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            code.element(tags::CODE_MEANING).unwrap().to_str().unwrap(),
+            "De-identifying Equipment"
+        );
+    }
+}
@@ -1,16 +1,30 @@
-use dicom_core::{DataDictionary, Tag};
+use dicom_core::{DataDictionary, Tag, VR};
 use dicom_dictionary_std::StandardDataDictionary;
+use dicom_encoding::text::SpecificCharacterSet;
 use dicom_encoding::transfer_syntax::TransferSyntaxIndex;
-use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+use dicom_parser::dataset::read::{DataSetReaderOptions, ReadWarning};
+use dicom_parser::stateful::decode::OddLengthStrategy;
+use dicom_transfer_syntax_registry::{entries, TransferSyntaxRegistry};
 
-use crate::{DefaultDicomObject, Result};
+use crate::bulk::BulkDataSink;
+use crate::compact::{CompactDicomObject, Interner};
+use crate::progress::{Progress, ProgressReader};
+use crate::tokens::{ExplicitLengthSqItemStrategy, GroupLengthStrategy};
+use crate::{
+    CancelledSnafu, DefaultDicomObject, DeviationSnafu, FileDicomObject, OpenFileSnafu, Result,
+};
+use snafu::ResultExt;
+use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
 /// Create a DICOM object by reading from a byte source.
 ///
-/// This function assumes the standard file encoding structure without the
-/// preamble: file meta group, followed by the rest of the data set.
+/// This function assumes the standard file encoding structure:
+/// magic code, file meta group, and the rest of the data set.
+/// The 128-byte preamble before the magic code is optional:
+/// if present, it is detected and kept,
+/// including the TIFF header of a dual personality file.
 pub fn from_reader<F>(file: F) -> Result<DefaultDicomObject>
 where
     F: Read,
@@ -54,6 +68,12 @@ pub struct OpenFileOptions<D = StandardDataDictionary, T = TransferSyntaxRegistr
     ts_index: T,
     read_until: Option<Tag>,
     read_preamble: ReadPreamble,
+    preserve_encoding: bool,
+    transcode_to_utf8: bool,
+    skip_pixel_data: bool,
+    charset: Option<SpecificCharacterSet>,
+    mode: ReadMode,
+    duplicate_tags: DuplicateTagPolicy,
 }
 
 impl OpenFileOptions {
@@ -82,12 +102,95 @@ impl<D, T> OpenFileOptions<D, T> {
         self
     }
 
+    /// Set whether to skip the pixel data of the object.
+    ///
+    /// If enabled, the reading process ends
+    /// before the first pixel data element
+    /// (_Float Pixel Data_, _Double Float Pixel Data_ or _Pixel Data_)
+    /// in the object's root data set,
+    /// so that the pixel data is never read into memory.
+    /// This can be combined with [`read_until`](Self::read_until),
+    /// in which case reading ends at the earliest of the two.
+    ///
+    /// This is disabled by default.
+    pub fn skip_pixel_data(mut self, skip: bool) -> Self {
+        self.skip_pixel_data = skip;
+        self
+    }
+
+    /// Set the character set assumed for text values,
+    /// until the data set declares its own
+    /// through _Specific Character Set_.
+    ///
+    /// This allows reading files which omit the attribute
+    /// despite containing text outside of the default repertoire.
+    /// If not set, the default character repertoire is assumed.
+    pub fn charset(mut self, charset: SpecificCharacterSet) -> Self {
+        self.charset = Some(charset);
+        self
+    }
+
+    /// Set how deviations from the standard in the data set are handled.
+    ///
+    /// This is [`ReadMode::Default`] by default.
+    pub fn mode(mut self, mode: ReadMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set how elements repeating the tag of a previous element
+    /// in the same data set are handled.
+    ///
+    /// This is [`DuplicateTagPolicy::KeepLast`] by default.
+    /// Duplicate elements are reported as
+    /// [`DuplicateTag`](ReadWarning::DuplicateTag) warnings
+    /// regardless of the policy.
+    pub fn duplicate_tags(mut self, policy: DuplicateTagPolicy) -> Self {
+        self.duplicate_tags = policy;
+        self
+    }
+
     /// Set whether to read the 128-byte DICOM file preamble.
     pub fn read_preamble(mut self, option: ReadPreamble) -> Self {
         self.read_preamble = option;
         self
     }
 
+    /// Set whether to keep the original encoding of the data set's values,
+    /// so that writing the object back reproduces them byte for byte.
+    ///
+    /// This covers text values,
+    /// which may otherwise be re-encoded differently
+    /// (with a different character set or padding),
+    /// and binary values of an odd length.
+    /// The original encoding is only used for elements
+    /// which still have the same value and VR when written.
+    /// Changing the object's _Specific Character Set_
+    /// discards the original encoding of all values.
+    /// The file meta group is always written anew.
+    ///
+    /// This is disabled by default,
+    /// as it takes additional memory for all retained values.
+    pub fn preserve_encoding(mut self, preserve: bool) -> Self {
+        self.preserve_encoding = preserve;
+        self
+    }
+
+    /// Set whether to convert all text to UTF-8 after reading,
+    /// so that applications deal with a uniform text encoding.
+    ///
+    /// Text values are decoded with the declared _Specific Character Set_,
+    /// which is then replaced with `ISO_IR 192`
+    /// (see [`transcode_to_utf8`](crate::InMemDicomObject::transcode_to_utf8)).
+    /// This takes precedence over
+    /// [`preserve_encoding`](Self::preserve_encoding).
+    ///
+    /// This is disabled by default.
+    pub fn transcode_to_utf8(mut self, transcode: bool) -> Self {
+        self.transcode_to_utf8 = transcode;
+        self
+    }
+
     /// Set the transfer syntax index to use when reading the file.
     pub fn tranfer_syntax_index<Tr>(self, ts_index: Tr) -> OpenFileOptions<D, Tr>
     where
@@ -97,6 +200,12 @@ impl<D, T> OpenFileOptions<D, T> {
             data_dictionary: self.data_dictionary,
             read_until: self.read_until,
             read_preamble: self.read_preamble,
+            preserve_encoding: self.preserve_encoding,
+            transcode_to_utf8: self.transcode_to_utf8,
+            skip_pixel_data: self.skip_pixel_data,
+            charset: self.charset,
+            mode: self.mode,
+            duplicate_tags: self.duplicate_tags,
             ts_index,
         }
     }
@@ -111,6 +220,12 @@ impl<D, T> OpenFileOptions<D, T> {
             data_dictionary: dict,
             read_until: self.read_until,
             read_preamble: self.read_preamble,
+            preserve_encoding: self.preserve_encoding,
+            transcode_to_utf8: self.transcode_to_utf8,
+            skip_pixel_data: self.skip_pixel_data,
+            charset: self.charset,
+            mode: self.mode,
+            duplicate_tags: self.duplicate_tags,
             ts_index: self.ts_index,
         }
     }
@@ -123,20 +238,105 @@ impl<D, T> OpenFileOptions<D, T> {
         D: Clone,
         T: TransferSyntaxIndex,
     {
-        DefaultDicomObject::open_file_with_all_options(
+        let transcode = self.transcode_to_utf8;
+        let settings = self.settings();
+        let obj = DefaultDicomObject::open_file_with_all_options(
             path,
             self.data_dictionary,
             self.ts_index,
-            self.read_until,
-            self.read_preamble,
-        )
+            &settings,
+            &mut Vec::new(),
+        )?;
+        Ok(transcoded(obj, transcode))
+    }
+
+    /// Open the file at the given path,
+    /// also retrieving the non-fatal deviations from the standard
+    /// found while reading the data set.
+    ///
+    /// This allows slightly broken files to be loaded
+    /// while still informing the user about what is wrong with them.
+    pub fn open_file_with_warnings<P>(
+        self,
+        path: P,
+    ) -> Result<(DefaultDicomObject<D>, Vec<ReadWarning>)>
+    where
+        P: AsRef<Path>,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let transcode = self.transcode_to_utf8;
+        let settings = self.settings();
+        let mut warnings = Vec::new();
+        let obj = DefaultDicomObject::open_file_with_all_options(
+            path,
+            self.data_dictionary,
+            self.ts_index,
+            &settings,
+            &mut warnings,
+        )?;
+        Ok((transcoded(obj, transcode), warnings))
+    }
+
+    /// Open the file at the given path,
+    /// reporting the number of bytes read to the given progress observer.
+    ///
+    /// The total number of bytes is estimated from the file's size.
+    /// If the observer cancels the operation,
+    /// reading stops with a [`Cancelled`](crate::Error::Cancelled) error.
+    pub fn open_file_with_progress<P, Pr>(
+        self,
+        path: P,
+        progress: Pr,
+    ) -> Result<DefaultDicomObject<D>>
+    where
+        P: AsRef<Path>,
+        Pr: Progress,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|_| OpenFileSnafu { filename: path })?;
+        let size = file.metadata().ok().map(|m| m.len());
+        self.from_reader_with_progress(file, size, progress)
+    }
+
+    /// Open the file at the given path,
+    /// writing element values longer than `threshold` bytes
+    /// to the given bulk data sink instead of keeping them in memory.
+    ///
+    /// Externalized elements are kept in the object with an empty value,
+    /// and the reference to their value is available through
+    /// [`bulk_data`](crate::InMemDicomObject::bulk_data).
+    /// See the [`bulk`](crate::bulk) module for more details.
+    pub fn open_file_with_bulk_data<P, K>(
+        self,
+        path: P,
+        threshold: u32,
+        sink: K,
+    ) -> Result<DefaultDicomObject<D>>
+    where
+        P: AsRef<Path>,
+        K: BulkDataSink,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|_| OpenFileSnafu { filename: path })?;
+        self.from_reader_with_bulk_data(file, threshold, sink)
     }
 
     /// Obtain a DICOM object by reading from a byte source.
     ///
-    /// This method assumes
-    /// the standard file encoding structure without the preamble:
-    /// file meta group, followed by the rest of the data set.
+    /// This method assumes the standard file encoding structure:
+    /// magic code, file meta group, and the rest of the data set.
+    /// Unless configured otherwise with [`read_preamble`](Self::read_preamble),
+    /// the 128-byte preamble before the magic code is optional:
+    /// if present, it is detected and kept,
+    /// including the TIFF header of a dual personality file.
     pub fn from_reader<R>(self, from: R) -> Result<DefaultDicomObject<D>>
     where
         R: Read,
@@ -144,13 +344,362 @@ impl<D, T> OpenFileOptions<D, T> {
         D: Clone,
         T: TransferSyntaxIndex,
     {
-        DefaultDicomObject::from_reader_with_all_options(
+        let transcode = self.transcode_to_utf8;
+        let settings = self.settings();
+        let obj = DefaultDicomObject::from_reader_with_all_options(
+            from,
+            self.data_dictionary,
+            self.ts_index,
+            &settings,
+            &mut Vec::new(),
+        )?;
+        Ok(transcoded(obj, transcode))
+    }
+
+    /// Obtain a DICOM object by reading from a byte source,
+    /// also retrieving the non-fatal deviations from the standard
+    /// found while reading the data set.
+    ///
+    /// See [`from_reader`](Self::from_reader)
+    /// for the expected structure of the source.
+    pub fn from_reader_with_warnings<R>(
+        self,
+        from: R,
+    ) -> Result<(DefaultDicomObject<D>, Vec<ReadWarning>)>
+    where
+        R: Read,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let transcode = self.transcode_to_utf8;
+        let settings = self.settings();
+        let mut warnings = Vec::new();
+        let obj = DefaultDicomObject::from_reader_with_all_options(
+            from,
+            self.data_dictionary,
+            self.ts_index,
+            &settings,
+            &mut warnings,
+        )?;
+        Ok((transcoded(obj, transcode), warnings))
+    }
+
+    /// Open the file at the given path
+    /// into a compact, read-only object,
+    /// interning its text values in the given pool.
+    ///
+    /// The file is read into an in-memory object before it is converted,
+    /// so this reduces the memory retained by the object,
+    /// not the peak memory usage while reading.
+    /// See the [`compact`](crate::compact) module for more details.
+    pub fn open_file_compact<P>(
+        self,
+        path: P,
+        interner: &mut Interner,
+    ) -> Result<FileDicomObject<CompactDicomObject>>
+    where
+        P: AsRef<Path>,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let obj = self.open_file(path)?;
+        Ok(compacted(obj, interner))
+    }
+
+    /// Obtain a compact, read-only DICOM object by reading from a byte source,
+    /// interning its text values in the given pool.
+    ///
+    /// See [`from_reader`](Self::from_reader)
+    /// for the expected structure of the source,
+    /// and [`open_file_compact`](Self::open_file_compact)
+    /// for the memory usage of this method.
+    pub fn from_reader_compact<R>(
+        self,
+        from: R,
+        interner: &mut Interner,
+    ) -> Result<FileDicomObject<CompactDicomObject>>
+    where
+        R: Read,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let obj = self.from_reader(from)?;
+        Ok(compacted(obj, interner))
+    }
+
+    /// Obtain a DICOM object by reading from a byte source,
+    /// writing element values longer than `threshold` bytes
+    /// to the given bulk data sink instead of keeping them in memory.
+    ///
+    /// See [`from_reader`](Self::from_reader)
+    /// for the expected structure of the source,
+    /// and [`open_file_with_bulk_data`](Self::open_file_with_bulk_data)
+    /// for how values are externalized.
+    pub fn from_reader_with_bulk_data<R, K>(
+        self,
+        from: R,
+        threshold: u32,
+        sink: K,
+    ) -> Result<DefaultDicomObject<D>>
+    where
+        R: Read,
+        K: BulkDataSink,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let transcode = self.transcode_to_utf8;
+        let settings = self.settings();
+        let obj = DefaultDicomObject::from_reader_with_bulk_data(
             from,
             self.data_dictionary,
             self.ts_index,
-            self.read_until,
-            self.read_preamble,
-        )
+            &settings,
+            (threshold, sink),
+        )?;
+        Ok(transcoded(obj, transcode))
+    }
+
+    /// Open the file at the given path,
+    /// which may either be a DICOM file
+    /// or a raw data set without a preamble or file meta group.
+    ///
+    /// See [`from_reader_or_dataset`](Self::from_reader_or_dataset)
+    /// for how raw data sets are recognized.
+    pub fn open_file_or_dataset<P>(self, path: P) -> Result<DefaultDicomObject<D>>
+    where
+        P: AsRef<Path>,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|_| OpenFileSnafu { filename: path })?;
+        self.from_reader_or_dataset(file)
+    }
+
+    /// Obtain a DICOM object by reading from a byte source,
+    /// which may either follow the DICOM file structure
+    /// or contain a raw data set without a preamble or file meta group.
+    ///
+    /// Sources with the DICOM magic code
+    /// (with or without the preamble)
+    /// are read as with [`from_reader`](Self::from_reader).
+    /// Otherwise, the source is read as a raw data set,
+    /// in a transfer syntax detected from its first element
+    /// (see [`detect_dataset_transfer_syntax`]).
+    /// A file meta group is then created for the object,
+    /// with the detected transfer syntax
+    /// and the SOP class and instance UIDs found in the data set.
+    /// Raw data sets which start with a file meta group are also accepted.
+    pub fn from_reader_or_dataset<R>(self, from: R) -> Result<DefaultDicomObject<D>>
+    where
+        R: Read,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let transcode = self.transcode_to_utf8;
+        let settings = self.settings();
+        let obj = DefaultDicomObject::from_reader_or_dataset_with_all_options(
+            from,
+            self.data_dictionary,
+            self.ts_index,
+            &settings,
+            &mut Vec::new(),
+        )?;
+        Ok(transcoded(obj, transcode))
+    }
+
+    /// Obtain a DICOM object by reading from a byte source,
+    /// which is expected to provide about `bytes_total` bytes,
+    /// reporting the number of bytes read to the given progress observer.
+    ///
+    /// If the observer cancels the operation,
+    /// reading stops with a [`Cancelled`](crate::Error::Cancelled) error.
+    pub fn from_reader_with_progress<R, Pr>(
+        self,
+        from: R,
+        bytes_total: Option<u64>,
+        progress: Pr,
+    ) -> Result<DefaultDicomObject<D>>
+    where
+        R: Read,
+        Pr: Progress,
+        D: DataDictionary,
+        D: Clone,
+        T: TransferSyntaxIndex,
+    {
+        let transcode = self.transcode_to_utf8;
+        let settings = self.settings();
+        let mut reader = ProgressReader::new(from, bytes_total, progress);
+        let result = DefaultDicomObject::from_reader_with_all_options(
+            &mut reader,
+            self.data_dictionary,
+            self.ts_index,
+            &settings,
+            &mut Vec::new(),
+        );
+        if reader.is_cancelled() {
+            return CancelledSnafu.fail();
+        }
+        let obj = result?;
+        reader.finish();
+        Ok(transcoded(obj, transcode))
+    }
+
+    /// Gather the settings of the reading operation.
+    fn settings(&self) -> ReadSettings {
+        let pixel_data = Some(Tag(0x7FE0, 0x0008)).filter(|_| self.skip_pixel_data);
+        ReadSettings {
+            read_until: match (self.read_until, pixel_data) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            read_preamble: self.read_preamble,
+            preserve_encoding: self.preserve_encoding,
+            charset: self.charset.unwrap_or(SpecificCharacterSet::Default),
+            mode: self.mode,
+            duplicate_tags: self.duplicate_tags,
+        }
+    }
+}
+
+/// Apply the text transcoding option to an object which was read.
+fn transcoded<D>(mut obj: DefaultDicomObject<D>, transcode: bool) -> DefaultDicomObject<D>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    if transcode {
+        obj.transcode_to_utf8();
+    }
+    obj
+}
+
+fn compacted<D>(
+    obj: DefaultDicomObject<D>,
+    interner: &mut Interner,
+) -> FileDicomObject<CompactDicomObject>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    FileDicomObject {
+        obj: CompactDicomObject::from_object(&obj.obj, interner),
+        meta: obj.meta,
+        preamble: obj.preamble,
+        modified: obj.modified,
+    }
+}
+
+/// How deviations from the standard are handled
+/// when reading a data set.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum ReadMode {
+    /// Fail on any deviation from the standard
+    /// which the reader can detect,
+    /// including values with an odd length.
+    Strict,
+    /// Accept common deviations from the standard,
+    /// reporting them as warnings.
+    #[default]
+    Default,
+    /// Accept common deviations from the standard, reporting them as warnings,
+    /// and also read elements encoded in implicit VR
+    /// in a data set declared as explicit VR, and vice versa.
+    Lenient,
+}
+
+/// How elements with the same tag as a previous element
+/// in the same data set (or sequence item) are handled
+/// when reading a data set.
+///
+/// Elements which are out of ascending tag order
+/// but do not repeat a previous tag
+/// are placed in order in the object,
+/// unless the policy is [`Fail`](DuplicateTagPolicy::Fail).
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum DuplicateTagPolicy {
+    /// Keep the first element with the tag,
+    /// ignoring the later ones.
+    KeepFirst,
+    /// Keep the last element with the tag,
+    /// replacing the earlier ones.
+    #[default]
+    KeepLast,
+    /// Fail on the first duplicate element
+    /// or element out of ascending tag order.
+    Fail,
+    /// Keep the last element with the tag in the object,
+    /// and the elements which it replaced aside,
+    /// as [duplicate elements](crate::InMemDicomObject::duplicate_elements).
+    CollectAll,
+}
+
+/// The settings of an operation reading a DICOM file.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct ReadSettings {
+    /// the tag of the element before which reading ends, if any
+    pub read_until: Option<Tag>,
+    /// whether to read the preamble
+    pub read_preamble: ReadPreamble,
+    /// whether to keep the original encoding of values
+    pub preserve_encoding: bool,
+    /// the character set assumed for text values
+    pub charset: SpecificCharacterSet,
+    /// how deviations from the standard are handled
+    pub mode: ReadMode,
+    /// how elements with a repeated tag are handled
+    pub duplicate_tags: DuplicateTagPolicy,
+}
+
+impl Default for ReadSettings {
+    fn default() -> Self {
+        ReadSettings {
+            read_until: None,
+            read_preamble: ReadPreamble::Auto,
+            preserve_encoding: false,
+            charset: SpecificCharacterSet::Default,
+            mode: ReadMode::Default,
+            duplicate_tags: DuplicateTagPolicy::KeepLast,
+        }
+    }
+}
+
+impl ReadSettings {
+    /// The odd value length strategy according to the read mode.
+    pub fn odd_length(&self) -> OddLengthStrategy {
+        match self.mode {
+            ReadMode::Strict => OddLengthStrategy::Fail,
+            ReadMode::Default | ReadMode::Lenient => OddLengthStrategy::Accept,
+        }
+    }
+
+    /// The options of the data set reader according to these settings.
+    pub fn reader_options(&self) -> DataSetReaderOptions {
+        DataSetReaderOptions::default()
+            .odd_length(self.odd_length())
+            .detect_vr_mismatch(self.mode == ReadMode::Lenient)
+    }
+
+    /// Collect the warnings found by the data set reader,
+    /// failing on the first one in strict mode.
+    pub fn check_warnings(
+        &self,
+        found: Vec<ReadWarning>,
+        warnings: &mut Vec<ReadWarning>,
+    ) -> Result<()> {
+        if self.mode == ReadMode::Strict {
+            if let Some(&warning) = found.first() {
+                return DeviationSnafu { warning }.fail();
+            }
+        }
+        warnings.extend(found);
+        Ok(())
     }
 }
 
@@ -158,8 +707,12 @@ impl<D, T> OpenFileOptions<D, T> {
 /// whether to read the 128-byte DICOM file preamble.
 #[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
 pub enum ReadPreamble {
-    /// Read the preamble only when opening a file by path,
-    /// and do not read the preamble when reading from a byte source.
+    /// Read the preamble only if the source
+    /// does not start with the DICOM magic code.
+    ///
+    /// This accepts sources with or without the preamble,
+    /// regardless of what the preamble contains,
+    /// such as the TIFF header of a dual personality file.
     Auto,
     /// Never read the preamble,
     /// thus assuming that the original source does not have it.
@@ -174,3 +727,154 @@ impl Default for ReadPreamble {
         ReadPreamble::Auto
     }
 }
+
+/// Read the file preamble from the given source
+/// according to the given option.
+///
+/// Returns the preamble, or all zeros if it was not read,
+/// and whether the magic code was already consumed from the source
+/// in the process of detecting the preamble.
+pub(crate) fn detect_preamble<R>(
+    source: &mut R,
+    option: ReadPreamble,
+) -> std::io::Result<([u8; 128], bool)>
+where
+    R: Read,
+{
+    let mut preamble = [0; 128];
+    match option {
+        ReadPreamble::Never => Ok((preamble, false)),
+        ReadPreamble::Always => {
+            source.read_exact(&mut preamble)?;
+            Ok((preamble, false))
+        }
+        ReadPreamble::Auto => {
+            source.read_exact(&mut preamble[..4])?;
+            if &preamble[..4] == b"DICM" {
+                return Ok(([0; 128], true));
+            }
+            source.read_exact(&mut preamble[4..])?;
+            Ok((preamble, false))
+        }
+    }
+}
+
+/// Detect the transfer syntax of a raw data set
+/// from the first bytes of its encoded form,
+/// returning its UID.
+///
+/// The first element header is inspected
+/// to tell whether the VR is explicit,
+/// and which byte order yields the more plausible tag
+/// (the lower group number).
+/// Only the uncompressed transfer syntaxes can be detected:
+/// _Implicit VR Little Endian_,
+/// _Explicit VR Little Endian_,
+/// and _Explicit VR Big Endian_.
+///
+/// Returns `None` if there are not enough bytes for an element header.
+pub fn detect_dataset_transfer_syntax(head: &[u8]) -> Option<&'static str> {
+    let header = head.get(..8)?;
+    let group_le = u16::from_le_bytes([header[0], header[1]]);
+    let group_be = u16::from_be_bytes([header[0], header[1]]);
+    let explicit_vr = VR::from_binary([header[4], header[5]]).is_some();
+    let uid = match (explicit_vr, group_be < group_le) {
+        (false, _) => entries::IMPLICIT_VR_LITTLE_ENDIAN.uid(),
+        (true, false) => entries::EXPLICIT_VR_LITTLE_ENDIAN.uid(),
+        (true, true) => entries::EXPLICIT_VR_BIG_ENDIAN.uid(),
+    };
+    Some(uid)
+}
+
+/// Check whether the given bytes start with a TIFF or BigTIFF header,
+/// in either byte order.
+pub(crate) fn is_tiff_header(bytes: &[u8]) -> bool {
+    matches!(
+        bytes.get(..4),
+        Some(b"II*\0") | Some(b"MM\0*") | Some(b"II+\0") | Some(b"MM\0+")
+    )
+}
+
+/// A set of options for writing a DICOM file or data set.
+///
+/// Writing only requires the destination to implement [`Write`](std::io::Write),
+/// so that objects can be written to sockets, pipes, or compressors.
+/// The data set is written in a single pass:
+/// the lengths of sequences and items are either calculated
+/// before they are written or left undefined,
+/// according to the [explicit length strategy](Self::explicit_length_sq_item_strategy),
+/// and deflated data sets are compressed as they are written.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dicom_object::{open_file, WriteOptions};
+/// # use dicom_object::tokens::ExplicitLengthSqItemStrategy;
+/// let obj = open_file("path/to/file.dcm")?;
+/// let options = WriteOptions::new()
+///     .explicit_length_sq_item_strategy(ExplicitLengthSqItemStrategy::SetUndefined);
+/// obj.write_to_file_with_options("path/to/out.dcm", &options)?;
+/// # Result::<(), Box<dyn std::error::Error>>::Ok(())
+/// ```
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct WriteOptions {
+    explicit_length_sq_item_strategy: ExplicitLengthSqItemStrategy,
+    compression_level: Option<u32>,
+    group_length: GroupLengthStrategy,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        WriteOptions::default()
+    }
+
+    /// Set how to write sequences and items
+    /// which were recorded with an explicit length.
+    ///
+    /// By default, their lengths are recalculated.
+    pub fn explicit_length_sq_item_strategy(
+        mut self,
+        strategy: ExplicitLengthSqItemStrategy,
+    ) -> Self {
+        self.explicit_length_sq_item_strategy = strategy;
+        self
+    }
+
+    /// Retrieve the strategy for writing sequences and items
+    /// which were recorded with an explicit length.
+    pub fn get_explicit_length_sq_item_strategy(&self) -> ExplicitLengthSqItemStrategy {
+        self.explicit_length_sq_item_strategy
+    }
+
+    /// Set the compression level of the data set,
+    /// from 0 (no compression) to 9 (best compression),
+    /// for transfer syntaxes which compress the data set as a whole,
+    /// such as _Deflated Explicit VR Little Endian_.
+    ///
+    /// The file meta group is never compressed.
+    /// By default, the codec's default level is used.
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.compression_level = Some(level);
+        self
+    }
+
+    /// Retrieve the compression level of the data set, if set.
+    pub fn get_compression_level(&self) -> Option<u32> {
+        self.compression_level
+    }
+
+    /// Set how to write group length elements (gggg,0000)
+    /// in the data set.
+    ///
+    /// By default, they are written as they are in the object.
+    pub fn group_length(mut self, strategy: GroupLengthStrategy) -> Self {
+        self.group_length = strategy;
+        self
+    }
+
+    /// Retrieve the strategy for writing group length elements.
+    pub fn get_group_length_strategy(&self) -> GroupLengthStrategy {
+        self.group_length
+    }
+}
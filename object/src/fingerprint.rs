@@ -0,0 +1,458 @@
+//! Attribute-level fingerprinting of DICOM objects.
+//!
+//! A [`Fingerprint`] is a SHA-256 digest
+//! over the attributes of a data set,
+//! computed by a [`Fingerprinter`]
+//! which selects the attributes to take into account.
+//! Unlike a digest of the file's bytes,
+//! the fingerprint does not depend on the transfer syntax,
+//! the file meta group, group lengths,
+//! or insignificant differences in the values,
+//! such as padding, letter case in code strings and person names,
+//! or the textual form of decimal numbers.
+//! Two objects whose attributes are equal
+//! under the equality semantics of their value representations
+//! (see [`dicom_core::value::equality`])
+//! have the same fingerprint.
+//!
+//! This can be used to detect duplicate instances
+//! and to audit changes to instances which are sent again,
+//! possibly with new UIDs and dates.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::fingerprint::Fingerprinter;
+//!
+//! let original = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.2.3.4")),
+//!     DataElement::new(tags::CONTENT_DATE, VR::DA, PrimitiveValue::from("20240102")),
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+//!     DataElement::new(tags::SLICE_THICKNESS, VR::DS, PrimitiveValue::from("1.50")),
+//! ]);
+//! let resent = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.2.3.5")),
+//!     DataElement::new(tags::CONTENT_DATE, VR::DA, PrimitiveValue::from("20240215")),
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("DOE^JOHN^^^")),
+//!     DataElement::new(tags::SLICE_THICKNESS, VR::DS, PrimitiveValue::from(1.5)),
+//! ]);
+//!
+//! let fingerprinter = Fingerprinter::new().exclude_uids().exclude_dates();
+//! assert_eq!(fingerprinter.fingerprint(&original), fingerprinter.fingerprint(&resent));
+//! assert_ne!(
+//!     Fingerprinter::new().fingerprint(&original),
+//!     Fingerprinter::new().fingerprint(&resent),
+//! );
+//! ```
+use crate::mem::{InMemDicomObject, InMemFragment};
+use dicom_core::dictionary::DataDictionary;
+use dicom_core::header::Header;
+use dicom_core::value::equality::{is_case_insensitive, is_empty, is_textual, normalize_text};
+use dicom_core::value::{PrimitiveValue, Value};
+use dicom_core::{Tag, VR};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The digest of the selected attributes of a DICOM object.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    /// Retrieve the bytes of the digest.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    /// Format the digest in lowercase hexadecimal.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// A builder-like type for computing the fingerprint of DICOM objects
+/// over a selection of attributes.
+///
+/// By default, all attributes are taken into account,
+/// except for group lengths.
+#[derive(Debug, Default, Clone)]
+pub struct Fingerprinter {
+    attributes: Option<BTreeSet<Tag>>,
+    excluded: BTreeSet<Tag>,
+    excluded_vrs: BTreeSet<VR>,
+    exclude_private: bool,
+}
+
+impl Fingerprinter {
+    /// Create a fingerprinter over all attributes.
+    pub fn new() -> Self {
+        Fingerprinter::default()
+    }
+
+    /// Only take the given attributes of the root data set into account.
+    ///
+    /// Items of selected sequences are fingerprinted in full,
+    /// save for the excluded attributes.
+    pub fn attributes<I>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = Tag>,
+    {
+        self.attributes = Some(tags.into_iter().collect());
+        self
+    }
+
+    /// Ignore the attribute with the given tag,
+    /// in the root data set and in sequence items.
+    pub fn exclude(mut self, tag: Tag) -> Self {
+        self.excluded.insert(tag);
+        self
+    }
+
+    /// Ignore all attributes of the given value representation,
+    /// in the root data set and in sequence items.
+    pub fn exclude_vr(mut self, vr: VR) -> Self {
+        self.excluded_vrs.insert(vr);
+        self
+    }
+
+    /// Ignore all unique identifiers (UI).
+    pub fn exclude_uids(self) -> Self {
+        self.exclude_vr(VR::UI)
+    }
+
+    /// Ignore all dates and times (DA, DT, and TM).
+    pub fn exclude_dates(self) -> Self {
+        self.exclude_vr(VR::DA)
+            .exclude_vr(VR::DT)
+            .exclude_vr(VR::TM)
+    }
+
+    /// Set whether to ignore private attributes,
+    /// including private creator elements.
+    pub fn exclude_private(mut self, exclude_private: bool) -> Self {
+        self.exclude_private = exclude_private;
+        self
+    }
+
+    /// Compute the fingerprint of the given object.
+    pub fn fingerprint<D>(&self, obj: &InMemDicomObject<D>) -> Fingerprint
+    where
+        D: DataDictionary,
+        D: Clone,
+    {
+        let mut hasher = Sha256::new();
+        self.update_object(&mut hasher, obj, true);
+        Fingerprint(hasher.finalize().into())
+    }
+
+    fn is_included(&self, tag: Tag, vr: VR, root: bool) -> bool {
+        if root {
+            if let Some(attributes) = &self.attributes {
+                if !attributes.contains(&tag) {
+                    return false;
+                }
+            }
+        }
+        let is_group_length = tag.element() == 0x0000;
+        let is_private = tag.group() % 2 == 1;
+        !(is_group_length
+            || (self.exclude_private && is_private)
+            || self.excluded.contains(&tag)
+            || self.excluded_vrs.contains(&vr))
+    }
+
+    fn update_object<D>(&self, hasher: &mut Sha256, obj: &InMemDicomObject<D>, root: bool)
+    where
+        D: DataDictionary,
+        D: Clone,
+    {
+        for elem in obj.iter() {
+            let tag = elem.tag();
+            let vr = elem.vr();
+            if !self.is_included(tag, vr, root) {
+                continue;
+            }
+            hasher.update(tag.group().to_le_bytes());
+            hasher.update(tag.element().to_le_bytes());
+            self.update_value(hasher, vr, elem.value());
+        }
+        // delimit the data set, so that elements of consecutive items
+        // cannot be confused with each other
+        hasher.update([0xFF]);
+    }
+
+    fn update_value<D>(
+        &self,
+        hasher: &mut Sha256,
+        vr: VR,
+        value: &Value<InMemDicomObject<D>, InMemFragment>,
+    ) where
+        D: DataDictionary,
+        D: Clone,
+    {
+        match value {
+            Value::Primitive(value) if is_empty(vr, value) => {
+                hasher.update([0x00]);
+            }
+            Value::Primitive(value) if is_textual(vr) => {
+                hasher.update([0x01]);
+                let values = value.to_multi_str();
+                update_len(hasher, values.len());
+                for value in values.iter() {
+                    update_text(hasher, vr, value);
+                }
+            }
+            Value::Primitive(value) => {
+                hasher.update([0x02]);
+                update_binary(hasher, vr, value);
+            }
+            Value::Sequence { items, .. } => {
+                hasher.update([0x03]);
+                update_len(hasher, items.len());
+                for item in items {
+                    self.update_object(hasher, item, false);
+                }
+            }
+            Value::PixelSequence {
+                offset_table: _,
+                fragments,
+            } => {
+                // the offset table only locates frames in the fragments
+                hasher.update([0x04]);
+                update_len(hasher, fragments.len());
+                for fragment in fragments {
+                    update_len(hasher, fragment.len());
+                    hasher.update(fragment);
+                }
+            }
+        }
+    }
+}
+
+fn update_len(hasher: &mut Sha256, len: usize) {
+    hasher.update((len as u64).to_le_bytes());
+}
+
+/// Feed a single textual value in canonical form.
+fn update_text(hasher: &mut Sha256, vr: VR, value: &str) {
+    let value = normalize_text(vr, value);
+    match vr {
+        VR::DS => {
+            if let Ok(number) = value.parse::<f64>() {
+                hasher.update([0x01]);
+                // +0.0 and -0.0 are equal
+                hasher.update((number + 0.0).to_le_bytes());
+                return;
+            }
+        }
+        VR::IS => {
+            if let Ok(number) = value.parse::<i64>() {
+                hasher.update([0x02]);
+                hasher.update(number.to_le_bytes());
+                return;
+            }
+        }
+        _ => {}
+    }
+    let value = if is_case_insensitive(vr) {
+        Cow::Owned(value.to_lowercase())
+    } else {
+        Cow::Borrowed(value)
+    };
+    hasher.update([0x00]);
+    update_len(hasher, value.len());
+    hasher.update(value.as_bytes());
+}
+
+/// Feed a non-textual value in canonical form,
+/// with numbers in little endian.
+fn update_binary(hasher: &mut Sha256, vr: VR, value: &PrimitiveValue) {
+    match vr {
+        VR::FL | VR::FD | VR::OF | VR::OD => {
+            if let Ok(values) = value.to_multi_float64() {
+                update_len(hasher, values.len());
+                for number in values {
+                    hasher.update((number + 0.0).to_le_bytes());
+                }
+                return;
+            }
+        }
+        VR::SS | VR::US | VR::SL | VR::UL | VR::SV | VR::UV => {
+            if let Ok(values) = value.to_multi_int::<i128>() {
+                update_len(hasher, values.len());
+                for number in values {
+                    hasher.update(number.to_le_bytes());
+                }
+                return;
+            }
+        }
+        _ => {}
+    }
+
+    match value {
+        PrimitiveValue::Tags(tags) => {
+            update_len(hasher, tags.len());
+            for tag in tags {
+                hasher.update(tag.group().to_le_bytes());
+                hasher.update(tag.element().to_le_bytes());
+            }
+        }
+        PrimitiveValue::U8(values) => {
+            update_len(hasher, values.len());
+            hasher.update(values);
+        }
+        PrimitiveValue::I16(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        PrimitiveValue::U16(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        PrimitiveValue::I32(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        PrimitiveValue::U32(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        PrimitiveValue::I64(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        PrimitiveValue::U64(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        PrimitiveValue::F32(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        PrimitiveValue::F64(values) => update_numbers(hasher, values, |x| x.to_le_bytes()),
+        value => {
+            let bytes = value.to_bytes();
+            update_len(hasher, bytes.len());
+            hasher.update(&bytes);
+        }
+    }
+}
+
+fn update_numbers<T, B>(hasher: &mut Sha256, values: &[T], to_le_bytes: impl Fn(&T) -> B)
+where
+    B: AsRef<[u8]>,
+{
+    update_len(hasher, values.len());
+    for value in values {
+        hasher.update(to_le_bytes(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, Length};
+    use dicom_dictionary_std::tags;
+
+    fn series_reference(uid: &str) -> InMemDicomObject {
+        InMemDicomObject::from_element_iter([DataElement::new(
+            tags::SERIES_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(uid),
+        )])
+    }
+
+    fn sample() -> InMemDicomObject {
+        InMemDicomObject::from_element_iter([
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new(Tag(0x0009, 0x0010), VR::LO, PrimitiveValue::from("ACME")),
+            DataElement::new(Tag(0x0009, 0x1001), VR::LO, PrimitiveValue::from("x")),
+            DataElement::new(tags::INSTANCE_NUMBER, VR::IS, PrimitiveValue::from("7")),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+            DataElement::new(
+                tags::REFERENCED_SERIES_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![series_reference("1.2.3"), series_reference("1.2.4")].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn fingerprint_ignores_insignificant_differences() {
+        let fingerprinter = Fingerprinter::new();
+        let a = fingerprinter.fingerprint(&sample());
+
+        // same object
+        assert_eq!(a, fingerprinter.fingerprint(&sample()));
+        assert_eq!(a.to_string().len(), 64);
+
+        // padding, case, number representation, and group lengths
+        let mut obj = sample();
+        obj.put(DataElement::new(
+            tags::MODALITY,
+            VR::CS,
+            PrimitiveValue::from("ct "),
+        ));
+        obj.put(DataElement::new(
+            tags::INSTANCE_NUMBER,
+            VR::IS,
+            PrimitiveValue::from(7),
+        ));
+        obj.put(DataElement::new(
+            tags::ROWS,
+            VR::US,
+            PrimitiveValue::from(512_i32),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x0010, 0x0000),
+            VR::UL,
+            PrimitiveValue::from(42_u32),
+        ));
+        assert_eq!(a, fingerprinter.fingerprint(&obj));
+    }
+
+    #[test]
+    fn fingerprint_detects_changes_in_selected_attributes() {
+        let fingerprinter = Fingerprinter::new();
+        let a = fingerprinter.fingerprint(&sample());
+
+        // a change in a nested item
+        let mut obj = sample();
+        obj.items_mut(tags::REFERENCED_SERIES_SEQUENCE).unwrap()[1] = series_reference("1.2.5");
+        assert_ne!(a, fingerprinter.fingerprint(&obj));
+        // items moving from one to another
+        let mut obj = sample();
+        obj.remove_item(tags::REFERENCED_SERIES_SEQUENCE, 1)
+            .unwrap();
+        assert_ne!(a, fingerprinter.fingerprint(&obj));
+
+        // except when excluded
+        let fingerprinter = Fingerprinter::new().exclude_uids();
+        let mut obj = sample();
+        obj.items_mut(tags::REFERENCED_SERIES_SEQUENCE).unwrap()[1] = series_reference("1.2.5");
+        assert_eq!(
+            fingerprinter.fingerprint(&sample()),
+            fingerprinter.fingerprint(&obj)
+        );
+
+        // private attributes
+        let mut obj = sample();
+        obj.put(DataElement::new(
+            Tag(0x0009, 0x1001),
+            VR::LO,
+            PrimitiveValue::from("y"),
+        ));
+        assert_ne!(a, Fingerprinter::new().fingerprint(&obj));
+        let fingerprinter = Fingerprinter::new().exclude_private(true);
+        assert_eq!(
+            fingerprinter.fingerprint(&sample()),
+            fingerprinter.fingerprint(&obj)
+        );
+
+        // selected attributes only
+        let fingerprinter = Fingerprinter::new().attributes([tags::MODALITY, tags::ROWS]);
+        let mut obj = sample();
+        obj.remove_element(tags::PATIENT_NAME);
+        assert_eq!(
+            fingerprinter.fingerprint(&sample()),
+            fingerprinter.fingerprint(&obj)
+        );
+        obj.remove_element(tags::ROWS);
+        assert_ne!(
+            fingerprinter.fingerprint(&sample()),
+            fingerprinter.fingerprint(&obj)
+        );
+    }
+}
@@ -0,0 +1,502 @@
+//! Grouping of instances into the patient, study and series hierarchy.
+//!
+//! Archives and viewers usually present a set of instances
+//! as a tree of patients, their studies,
+//! the series of each study and the instances of each series.
+//! A [`HierarchyCollector`] receives instances in any order,
+//! each one paired with its [key attributes](InstanceKey),
+//! and builds such a [`Hierarchy`],
+//! with the instances of each series sorted
+//! by _Instance Number_ or by their position along the slice normal.
+//!
+//! Anything can be kept at the leaves of the tree:
+//! whole objects, file paths, or nothing at all.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::hierarchy::HierarchyCollector;
+//!
+//! # let instance = |series: &str, number: &str| InMemDicomObject::from_element_iter([
+//! #     DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("P1")),
+//! #     DataElement::new(tags::STUDY_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.2.3")),
+//! #     DataElement::new(tags::SERIES_INSTANCE_UID, VR::UI, PrimitiveValue::from(series)),
+//! #     DataElement::new(tags::INSTANCE_NUMBER, VR::IS, PrimitiveValue::from(number)),
+//! # ]);
+//! let mut collector = HierarchyCollector::new();
+//! collector.add_object(instance("1.2.3.2", "1"));
+//! collector.add_object(instance("1.2.3.1", "2"));
+//! collector.add_object(instance("1.2.3.1", "1"));
+//!
+//! let hierarchy = collector.collect();
+//! assert_eq!(hierarchy.patients.len(), 1);
+//! let study = &hierarchy.patients[0].studies[0];
+//! assert_eq!(study.series.len(), 2);
+//! let numbers: Vec<_> = study.series[1]
+//!     .instances
+//!     .iter()
+//!     .map(|instance| instance.key.instance_number)
+//!     .collect();
+//! assert_eq!(numbers, [Some(1), Some(2)]);
+//! ```
+use crate::mem::InMemDicomObject;
+use crate::FileDicomObject;
+use dicom_core::{DataDictionary, Tag};
+use dicom_dictionary_std::tags;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+/// The attributes of an instance
+/// which place it in the hierarchy.
+///
+/// Attributes which are missing or cannot be read are `None`.
+/// Instances without a patient, study or series identifier
+/// are grouped together under a node with that identifier absent.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct InstanceKey {
+    pub patient_id: Option<String>,
+    pub patient_name: Option<String>,
+    pub study_instance_uid: Option<String>,
+    pub study_date: Option<String>,
+    pub study_description: Option<String>,
+    pub series_instance_uid: Option<String>,
+    pub series_number: Option<i32>,
+    pub modality: Option<String>,
+    pub sop_instance_uid: Option<String>,
+    pub instance_number: Option<i32>,
+    /// _Image Position (Patient)_
+    pub image_position: Option<[f64; 3]>,
+    /// _Image Orientation (Patient)_
+    pub image_orientation: Option<[f64; 6]>,
+}
+
+impl InstanceKey {
+    /// Create a key with all attributes absent.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the key attributes of an object.
+    pub fn from_object<D>(obj: &InMemDicomObject<D>) -> Self
+    where
+        D: DataDictionary + Clone,
+    {
+        let element = |tag: Tag| obj.element_opt(tag).ok().flatten();
+        let text = |tag: Tag| {
+            element(tag)
+                .and_then(|e| e.to_str().ok())
+                .map(|s| s.trim_matches([' ', '\0']).to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let int = |tag: Tag| element(tag).and_then(|e| e.to_int::<i32>().ok());
+        let floats = |tag: Tag| element(tag).and_then(|e| e.to_multi_float64().ok());
+
+        InstanceKey {
+            patient_id: text(tags::PATIENT_ID),
+            patient_name: text(tags::PATIENT_NAME),
+            study_instance_uid: text(tags::STUDY_INSTANCE_UID),
+            study_date: text(tags::STUDY_DATE),
+            study_description: text(tags::STUDY_DESCRIPTION),
+            series_instance_uid: text(tags::SERIES_INSTANCE_UID),
+            series_number: int(tags::SERIES_NUMBER),
+            modality: text(tags::MODALITY),
+            sop_instance_uid: text(tags::SOP_INSTANCE_UID),
+            instance_number: int(tags::INSTANCE_NUMBER),
+            image_position: floats(tags::IMAGE_POSITION_PATIENT)
+                .and_then(|v| <[f64; 3]>::try_from(v).ok()),
+            image_orientation: floats(tags::IMAGE_ORIENTATION_PATIENT)
+                .and_then(|v| <[f64; 6]>::try_from(v).ok()),
+        }
+    }
+
+    /// The position of the instance along the normal of its image plane,
+    /// or along the Z axis if the orientation is unknown.
+    pub fn slice_location(&self) -> Option<f64> {
+        let [x, y, z] = self.image_position?;
+        match self.image_orientation {
+            Some([r0, r1, r2, c0, c1, c2]) => {
+                let normal = [r1 * c2 - r2 * c1, r2 * c0 - r0 * c2, r0 * c1 - r1 * c0];
+                Some(x * normal[0] + y * normal[1] + z * normal[2])
+            }
+            None => Some(z),
+        }
+    }
+}
+
+/// How the instances of each series are sorted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InstanceOrder {
+    /// By _Instance Number_,
+    /// then by position along the slice normal.
+    #[default]
+    InstanceNumber,
+    /// By position along the slice normal,
+    /// then by _Instance Number_.
+    Position,
+    /// In the order in which the instances were added.
+    Insertion,
+}
+
+/// A leaf of the hierarchy.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Instance<T> {
+    /// The key attributes of the instance
+    pub key: InstanceKey,
+    /// The value given for the instance
+    pub value: T,
+}
+
+/// A series and its instances.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Series<T> {
+    pub series_instance_uid: Option<String>,
+    pub series_number: Option<i32>,
+    pub modality: Option<String>,
+    pub instances: Vec<Instance<T>>,
+}
+
+/// A study and its series.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Study<T> {
+    pub study_instance_uid: Option<String>,
+    pub study_date: Option<String>,
+    pub study_description: Option<String>,
+    pub series: Vec<Series<T>>,
+}
+
+/// A patient and their studies.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Patient<T> {
+    pub patient_id: Option<String>,
+    pub patient_name: Option<String>,
+    pub studies: Vec<Study<T>>,
+}
+
+/// A tree of patients, studies, series and instances.
+///
+/// Patients are kept in the order in which they were first seen,
+/// studies are sorted by _Study Date_,
+/// and series by _Series Number_.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Hierarchy<T> {
+    pub patients: Vec<Patient<T>>,
+}
+
+impl<T> Hierarchy<T> {
+    /// Iterate over all instances in the hierarchy, depth first.
+    pub fn instances(&self) -> impl Iterator<Item = &Instance<T>> {
+        self.patients
+            .iter()
+            .flat_map(|patient| &patient.studies)
+            .flat_map(|study| &study.series)
+            .flat_map(|series| &series.instances)
+    }
+
+    /// Find the series with the given _Series Instance UID_.
+    pub fn series(&self, series_instance_uid: &str) -> Option<&Series<T>> {
+        self.patients
+            .iter()
+            .flat_map(|patient| &patient.studies)
+            .flat_map(|study| &study.series)
+            .find(|series| series.series_instance_uid.as_deref() == Some(series_instance_uid))
+    }
+
+    /// The total number of instances in the hierarchy.
+    pub fn len(&self) -> usize {
+        self.instances().count()
+    }
+
+    /// Whether the hierarchy has no instances.
+    pub fn is_empty(&self) -> bool {
+        self.patients.is_empty()
+    }
+}
+
+/// A collector of instances into a [`Hierarchy`].
+#[derive(Debug)]
+pub struct HierarchyCollector<T> {
+    order: InstanceOrder,
+    instances: Vec<Instance<T>>,
+}
+
+impl<T> Default for HierarchyCollector<T> {
+    fn default() -> Self {
+        HierarchyCollector {
+            order: InstanceOrder::default(),
+            instances: Vec::new(),
+        }
+    }
+}
+
+impl<T> HierarchyCollector<T> {
+    /// Create an empty collector,
+    /// sorting instances by _Instance Number_.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how the instances of each series are sorted.
+    pub fn order(mut self, order: InstanceOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Add an instance by its key attributes.
+    pub fn add(&mut self, key: InstanceKey, value: T) {
+        self.instances.push(Instance { key, value });
+    }
+
+    /// The number of instances added so far.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Whether no instances were added.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Build the hierarchy of all instances added.
+    pub fn collect(self) -> Hierarchy<T> {
+        let mut patients: Vec<Patient<T>> = Vec::new();
+        let mut patient_index: HashMap<Option<String>, usize> = HashMap::new();
+        let mut study_index: HashMap<(usize, Option<String>), usize> = HashMap::new();
+        let mut series_index: HashMap<(usize, usize, Option<String>), usize> = HashMap::new();
+
+        for instance in self.instances {
+            let key = &instance.key;
+
+            let p = *patient_index
+                .entry(key.patient_id.clone())
+                .or_insert_with(|| {
+                    patients.push(Patient {
+                        patient_id: key.patient_id.clone(),
+                        patient_name: key.patient_name.clone(),
+                        studies: Vec::new(),
+                    });
+                    patients.len() - 1
+                });
+            let patient = &mut patients[p];
+
+            let s = *study_index
+                .entry((p, key.study_instance_uid.clone()))
+                .or_insert_with(|| {
+                    patient.studies.push(Study {
+                        study_instance_uid: key.study_instance_uid.clone(),
+                        study_date: key.study_date.clone(),
+                        study_description: key.study_description.clone(),
+                        series: Vec::new(),
+                    });
+                    patient.studies.len() - 1
+                });
+            let study = &mut patient.studies[s];
+
+            let r = *series_index
+                .entry((p, s, key.series_instance_uid.clone()))
+                .or_insert_with(|| {
+                    study.series.push(Series {
+                        series_instance_uid: key.series_instance_uid.clone(),
+                        series_number: key.series_number,
+                        modality: key.modality.clone(),
+                        instances: Vec::new(),
+                    });
+                    study.series.len() - 1
+                });
+            study.series[r].instances.push(instance);
+        }
+
+        // sort only after grouping, as the indices above refer to insertion order
+        for patient in &mut patients {
+            patient
+                .studies
+                .sort_by(|a, b| cmp_present_first(&a.study_date, &b.study_date));
+            for study in &mut patient.studies {
+                study
+                    .series
+                    .sort_by(|a, b| cmp_present_first(&a.series_number, &b.series_number));
+                for series in &mut study.series {
+                    sort_instances(&mut series.instances, self.order);
+                }
+            }
+        }
+
+        Hierarchy { patients }
+    }
+}
+
+impl<D> HierarchyCollector<InMemDicomObject<D>>
+where
+    D: DataDictionary + Clone,
+{
+    /// Add an object, keeping the whole object in the hierarchy.
+    pub fn add_object(&mut self, obj: InMemDicomObject<D>) {
+        let key = InstanceKey::from_object(&obj);
+        self.add(key, obj);
+    }
+}
+
+impl<D> HierarchyCollector<FileDicomObject<InMemDicomObject<D>>>
+where
+    D: DataDictionary + Clone,
+{
+    /// Add a file object, keeping the whole object in the hierarchy.
+    pub fn add_file_object(&mut self, obj: FileDicomObject<InMemDicomObject<D>>) {
+        let key = InstanceKey::from_object(&obj);
+        self.add(key, obj);
+    }
+}
+
+impl<T> Extend<(InstanceKey, T)> for HierarchyCollector<T> {
+    fn extend<I: IntoIterator<Item = (InstanceKey, T)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.add(key, value);
+        }
+    }
+}
+
+impl<T> FromIterator<(InstanceKey, T)> for Hierarchy<T> {
+    fn from_iter<I: IntoIterator<Item = (InstanceKey, T)>>(iter: I) -> Self {
+        let mut collector = HierarchyCollector::new();
+        collector.extend(iter);
+        collector.collect()
+    }
+}
+
+/// Sort the instances of a series, keeping ties in insertion order.
+fn sort_instances<T>(instances: &mut [Instance<T>], order: InstanceOrder) {
+    let by_number = |a: &Instance<T>, b: &Instance<T>| {
+        cmp_present_first(&a.key.instance_number, &b.key.instance_number)
+    };
+    let by_position = |a: &Instance<T>, b: &Instance<T>| {
+        cmp_present_first(&a.key.slice_location(), &b.key.slice_location())
+    };
+    match order {
+        InstanceOrder::InstanceNumber => {
+            instances.sort_by(|a, b| by_number(a, b).then_with(|| by_position(a, b)))
+        }
+        InstanceOrder::Position => {
+            instances.sort_by(|a, b| by_position(a, b).then_with(|| by_number(a, b)))
+        }
+        InstanceOrder::Insertion => {}
+    }
+}
+
+/// Compare two optional values, placing absent (or unordered) values last.
+fn cmp_present_first<V: PartialOrd>(a: &Option<V>, b: &Option<V>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, PrimitiveValue, VR};
+
+    fn key(patient: &str, study: &str, series: &str, number: Option<i32>) -> InstanceKey {
+        InstanceKey {
+            patient_id: Some(patient.to_string()),
+            study_instance_uid: Some(study.to_string()),
+            series_instance_uid: Some(series.to_string()),
+            instance_number: number,
+            ..InstanceKey::new()
+        }
+    }
+
+    #[test]
+    fn collect_groups_and_sorts() {
+        let mut collector = HierarchyCollector::new();
+        collector.add(key("P2", "2.1", "2.1.1", Some(3)), "e");
+        collector.add(key("P1", "1.1", "1.1.1", Some(2)), "b");
+        collector.add(key("P1", "1.1", "1.1.1", None), "c");
+        collector.add(key("P1", "1.1", "1.1.1", Some(1)), "a");
+        collector.add(key("P1", "1.2", "1.2.1", Some(1)), "d");
+        assert_eq!(collector.len(), 5);
+
+        let hierarchy = collector.collect();
+        assert_eq!(hierarchy.len(), 5);
+        let patients: Vec<_> = hierarchy
+            .patients
+            .iter()
+            .map(|p| p.patient_id.as_deref().unwrap())
+            .collect();
+        assert_eq!(patients, ["P2", "P1"]);
+        assert_eq!(hierarchy.patients[1].studies.len(), 2);
+
+        let series = hierarchy.series("1.1.1").unwrap();
+        let values: Vec<_> = series.instances.iter().map(|i| i.value).collect();
+        assert_eq!(values, ["a", "b", "c"]);
+
+        let all: Vec<_> = hierarchy.instances().map(|i| i.value).collect();
+        assert_eq!(all, ["e", "a", "b", "c", "d"]);
+        assert!(hierarchy.series("9.9").is_none());
+    }
+
+    #[test]
+    fn sort_by_position_along_normal() {
+        let obj = |z: &str, number: &str| {
+            InMemDicomObject::from_element_iter([
+                DataElement::new(
+                    tags::SERIES_INSTANCE_UID,
+                    VR::UI,
+                    PrimitiveValue::from("1.2.3"),
+                ),
+                DataElement::new(tags::INSTANCE_NUMBER, VR::IS, PrimitiveValue::from(number)),
+                DataElement::new(
+                    tags::IMAGE_POSITION_PATIENT,
+                    VR::DS,
+                    PrimitiveValue::Strs(
+                        vec!["-100".to_string(), "-100".to_string(), z.to_string()].into(),
+                    ),
+                ),
+                DataElement::new(
+                    tags::IMAGE_ORIENTATION_PATIENT,
+                    VR::DS,
+                    PrimitiveValue::Strs(
+                        ["1", "0", "0", "0", "1", "0"]
+                            .iter()
+                            .map(|s| s.to_string())
+                            .collect(),
+                    ),
+                ),
+            ])
+        };
+
+        let mut collector = HierarchyCollector::new().order(InstanceOrder::Position);
+        collector.add_object(obj("10.5", "1"));
+        collector.add_object(obj("-2.5", "3"));
+        collector.add_object(obj("4", "2"));
+        let hierarchy = collector.collect();
+
+        let patient = &hierarchy.patients[0];
+        assert_eq!(patient.patient_id, None);
+        let series = &patient.studies[0].series[0];
+        assert_eq!(series.series_instance_uid.as_deref(), Some("1.2.3"));
+        let locations: Vec<_> = series
+            .instances
+            .iter()
+            .map(|i| i.key.slice_location().unwrap())
+            .collect();
+        assert_eq!(locations, [-2.5, 4., 10.5]);
+        assert_eq!(series.instances[0].key.instance_number, Some(3));
+        assert_eq!(
+            series.instances[0].key.image_orientation,
+            Some([1., 0., 0., 0., 1., 0.])
+        );
+    }
+}
@@ -0,0 +1,890 @@
+//! Remote DICOM objects read with HTTP range requests.
+//!
+//! A [`RemoteDicomObject`] only fetches the parts of a DICOM file
+//! which are needed:
+//! the attributes before the pixel data are fetched when the object is opened,
+//! and the bytes of each frame are fetched on demand.
+//! This suits objects stored behind a web server or in object storage,
+//! where downloading whole multi-frame files
+//! to read their attributes or a few frames would be wasteful.
+//!
+//! The bytes are fetched from a [`RangeSource`].
+//! [`HttpSource`] implements it over HTTP(S),
+//! and requires the server to support range requests.
+//!
+//! Data sets in a transfer syntax with a data set codec
+//! (such as _Deflated Explicit VR Little Endian_)
+//! cannot be read this way.
+//! Attributes after the pixel data are not read.
+//!
+//! This module is only available with the `http` feature enabled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dicom_dictionary_std::tags;
+//! use dicom_object::http::RemoteDicomObject;
+//!
+//! let obj = RemoteDicomObject::open_url("https://example.com/studies/1/series/2/image.dcm")?;
+//! println!("{}", obj.header().element(tags::PATIENT_ID)?.to_str()?);
+//! // only the bytes of the last frame are fetched
+//! let last = obj.number_of_frames() - 1;
+//! let frame = obj.frame(last)?;
+//! println!("{} bytes in frame #{}", frame.len(), last);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::file::{detect_preamble, ReadPreamble};
+use crate::mem::InMemDicomObject;
+use crate::meta::FileMetaTable;
+use dicom_core::header::{HasLength, Header};
+use dicom_core::Tag;
+use dicom_dictionary_std::tags;
+use dicom_encoding::text::SpecificCharacterSet;
+use dicom_encoding::transfer_syntax::{Codec, TransferSyntaxIndex};
+use dicom_parser::dataset::read::DataSetReader;
+use dicom_parser::dataset::DataToken;
+use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
+use std::cell::Cell;
+use std::io::Read;
+use std::ops::Range;
+
+/// The number of bytes fetched at first to read the attributes,
+/// doubled until all attributes before the pixel data are fetched.
+const INITIAL_FETCH: u64 = 64 * 1024;
+
+/// The tag of an item in an encapsulated value.
+const ITEM: Tag = Tag(0xFFFE, 0xE000);
+
+/// The tag of the end of an encapsulated value.
+const SEQUENCE_DELIMITATION_ITEM: Tag = Tag(0xFFFE, 0xE0DD);
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Could not retrieve the size of the object"))]
+    FetchSize {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not fetch bytes {}..{} of the object", start, end))]
+    FetchRange {
+        start: u64,
+        end: u64,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Object ended before byte {}", end))]
+    PrematureEnd { end: u64, backtrace: Backtrace },
+    #[snafu(display("Could not read preamble"))]
+    ReadPreamble {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not parse meta group data set"))]
+    ParseMetaDataSet {
+        #[snafu(backtrace)]
+        source: crate::meta::Error,
+    },
+    #[snafu(display("Unsupported transfer syntax `{}`", uid))]
+    UnsupportedTransferSyntax { uid: String, backtrace: Backtrace },
+    #[snafu(display("Could not read data set token"))]
+    ReadToken {
+        #[snafu(backtrace)]
+        source: dicom_parser::dataset::read::Error,
+    },
+    #[snafu(display("Could not read data set"))]
+    ReadDataSet {
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+    #[snafu(display("Missing or invalid attribute {} to locate frames", name))]
+    MissingAttribute {
+        name: &'static str,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Frames of {} bits are not byte-aligned", bits))]
+    UnalignedFrames { bits: u64, backtrace: Backtrace },
+    #[snafu(display("Pixel data is shorter than its {} frames", frames))]
+    ShortPixelData { frames: u32, backtrace: Backtrace },
+    #[snafu(display(
+        "Cannot tell the boundaries of {} frames in {} fragments without an offset table",
+        frames,
+        fragments
+    ))]
+    UnknownFrameBoundaries {
+        frames: u32,
+        fragments: usize,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Unexpected element {} at byte {} of the pixel data", tag, position))]
+    UnexpectedItem {
+        tag: Tag,
+        position: u64,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("No frame #{} in an object of {} frames", index, frames))]
+    NoSuchFrame {
+        index: u32,
+        frames: u32,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A source of bytes which can be fetched by range,
+/// such as a file on a web server.
+pub trait RangeSource {
+    /// Retrieve the total number of bytes of the source.
+    fn size(&self) -> std::io::Result<u64>;
+
+    /// Fetch the bytes in the given range.
+    ///
+    /// Fewer bytes may be returned if the range goes past the end of the source.
+    fn read_range(&self, range: Range<u64>) -> std::io::Result<Vec<u8>>;
+}
+
+impl RangeSource for [u8] {
+    fn size(&self) -> std::io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+
+    fn read_range(&self, range: Range<u64>) -> std::io::Result<Vec<u8>> {
+        let end = (range.end as usize).min(self.len());
+        let start = (range.start as usize).min(end);
+        Ok(self[start..end].to_vec())
+    }
+}
+
+impl RangeSource for Vec<u8> {
+    fn size(&self) -> std::io::Result<u64> {
+        self[..].size()
+    }
+
+    fn read_range(&self, range: Range<u64>) -> std::io::Result<Vec<u8>> {
+        self[..].read_range(range)
+    }
+}
+
+impl<T> RangeSource for &T
+where
+    T: RangeSource + ?Sized,
+{
+    fn size(&self) -> std::io::Result<u64> {
+        (**self).size()
+    }
+
+    fn read_range(&self, range: Range<u64>) -> std::io::Result<Vec<u8>> {
+        (**self).read_range(range)
+    }
+}
+
+/// A file fetched from a URL with HTTP range requests.
+///
+/// Servers which do not honour range requests are reported as an error,
+/// rather than downloading the whole file on every request.
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    agent: ureq::Agent,
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpSource {
+    /// Create a source for the file at the given URL.
+    pub fn new(url: impl Into<String>) -> Self {
+        HttpSource::with_agent(ureq::Agent::new(), url)
+    }
+
+    /// Create a source for the file at the given URL,
+    /// using the given agent for the requests.
+    pub fn with_agent(agent: ureq::Agent, url: impl Into<String>) -> Self {
+        HttpSource {
+            agent,
+            url: url.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Add a header to every request,
+    /// such as `Authorization` or `Accept`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The URL of the file.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn get(&self, range: &Range<u64>) -> std::io::Result<ureq::Response> {
+        let mut request = self
+            .agent
+            .get(&self.url)
+            .set("Range", &format!("bytes={}-{}", range.start, range.end - 1));
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        let response = request.call().map_err(std::io::Error::other)?;
+        if response.status() != 206 {
+            return Err(std::io::Error::other(format!(
+                "server did not honour range request (status {})",
+                response.status()
+            )));
+        }
+        Ok(response)
+    }
+}
+
+impl RangeSource for HttpSource {
+    fn size(&self) -> std::io::Result<u64> {
+        let response = self.get(&(0..1))?;
+        // Content-Range: bytes 0-0/<size>
+        response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit('/').next())
+            .and_then(|size| size.trim().parse().ok())
+            .ok_or_else(|| std::io::Error::other("missing size in Content-Range header"))
+    }
+
+    fn read_range(&self, range: Range<u64>) -> std::io::Result<Vec<u8>> {
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+        let len = range.end - range.start;
+        let mut bytes = Vec::with_capacity(len as usize);
+        self.get(&range)?
+            .into_reader()
+            .take(len)
+            .read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// A DICOM file of which only the attributes are kept in memory,
+/// while the frames of its pixel data are fetched on demand.
+#[derive(Debug)]
+pub struct RemoteDicomObject<S = HttpSource> {
+    source: S,
+    meta: FileMetaTable,
+    header: InMemDicomObject,
+    /// the byte ranges of each frame,
+    /// holding the items of the fragments if encapsulated
+    frames: Vec<Range<u64>>,
+    encapsulated: bool,
+}
+
+impl RemoteDicomObject<HttpSource> {
+    /// Open the DICOM file at the given URL,
+    /// fetching its attributes.
+    pub fn open_url(url: &str) -> Result<Self> {
+        RemoteDicomObject::open(HttpSource::new(url))
+    }
+}
+
+impl<S> RemoteDicomObject<S>
+where
+    S: RangeSource,
+{
+    /// Open the DICOM file in the given source,
+    /// fetching its attributes and locating the frames of its pixel data.
+    ///
+    /// The file may start with or without the 128-byte preamble.
+    /// If the pixel data is encapsulated
+    /// without a basic or extended offset table,
+    /// the header of each fragment is fetched separately.
+    pub fn open(source: S) -> Result<Self> {
+        let size = source.size().context(FetchSizeSnafu)?;
+        let mut data = fetch(&source, 0..size.min(INITIAL_FETCH), size)?;
+        let prefix = loop {
+            let complete = data.len() as u64 == size;
+            if let Some(prefix) = Prefix::read(&data, complete)? {
+                break prefix;
+            }
+            let end = size.min(data.len() as u64 * 2);
+            let more = fetch(&source, data.len() as u64..end, size)?;
+            data.extend(more);
+        };
+
+        let Prefix {
+            meta,
+            header,
+            pixel_data,
+        } = prefix;
+
+        let (frames, encapsulated) = match pixel_data {
+            None => (Vec::new(), false),
+            Some(PixelDataPosition::Native { offset, len }) => {
+                (native_frames(&header, offset, len)?, false)
+            }
+            Some(PixelDataPosition::Encapsulated { offset }) => {
+                (encapsulated_frames(&source, &header, offset, size)?, true)
+            }
+        };
+
+        Ok(RemoteDicomObject {
+            source,
+            meta,
+            header,
+            frames,
+            encapsulated,
+        })
+    }
+
+    /// The file meta group of the object.
+    pub fn meta(&self) -> &FileMetaTable {
+        &self.meta
+    }
+
+    /// The attributes of the object before the pixel data.
+    pub fn header(&self) -> &InMemDicomObject {
+        &self.header
+    }
+
+    /// The source of the object's bytes.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// The number of frames in the pixel data,
+    /// or zero if the object does not have pixel data.
+    pub fn number_of_frames(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    /// Fetch the bytes of the frame at the given index.
+    ///
+    /// The bytes of the frame's fragments are concatenated
+    /// if the pixel data is encapsulated.
+    pub fn frame(&self, index: u32) -> Result<Vec<u8>> {
+        let range = self
+            .frames
+            .get(index as usize)
+            .cloned()
+            .context(NoSuchFrameSnafu {
+                index,
+                frames: self.number_of_frames(),
+            })?;
+        let size = self.source.size().context(FetchSizeSnafu)?;
+        let start = range.start;
+        let bytes = fetch(&self.source, range, size)?;
+        if !self.encapsulated {
+            return Ok(bytes);
+        }
+
+        let mut frame = Vec::new();
+        let mut position = 0;
+        while position + 8 <= bytes.len() {
+            let (tag, len) = item_header(&bytes[position..position + 8]);
+            if tag == SEQUENCE_DELIMITATION_ITEM {
+                break;
+            }
+            ensure!(
+                tag == ITEM,
+                UnexpectedItemSnafu {
+                    tag,
+                    position: start + position as u64
+                }
+            );
+            let data_start = position + 8;
+            let data_end = data_start + len as usize;
+            ensure!(
+                data_end <= bytes.len(),
+                PrematureEndSnafu {
+                    end: start + data_end as u64
+                }
+            );
+            frame.extend_from_slice(&bytes[data_start..data_end]);
+            position = data_end;
+        }
+        Ok(frame)
+    }
+}
+
+/// Fetch exactly the bytes in the given range of the source.
+fn fetch<S>(source: &S, range: Range<u64>, size: u64) -> Result<Vec<u8>>
+where
+    S: RangeSource + ?Sized,
+{
+    let Range { start, end } = range;
+    ensure!(end <= size, PrematureEndSnafu { end });
+    let bytes = source
+        .read_range(start..end)
+        .context(FetchRangeSnafu { start, end })?;
+    ensure!(bytes.len() as u64 == end - start, PrematureEndSnafu { end });
+    Ok(bytes)
+}
+
+/// Decode the tag and length of an item header in little endian.
+fn item_header(bytes: &[u8]) -> (Tag, u32) {
+    let group = u16::from_le_bytes([bytes[0], bytes[1]]);
+    let element = u16::from_le_bytes([bytes[2], bytes[3]]);
+    let len = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    (Tag(group, element), len)
+}
+
+/// The position of the pixel data value in the file.
+enum PixelDataPosition {
+    Native { offset: u64, len: u32 },
+    Encapsulated { offset: u64 },
+}
+
+/// The attributes at the start of a file,
+/// read from the bytes fetched so far.
+struct Prefix {
+    meta: FileMetaTable,
+    header: InMemDicomObject,
+    pixel_data: Option<PixelDataPosition>,
+}
+
+impl Prefix {
+    /// Read the attributes before the pixel data,
+    /// or return `None` if more bytes are needed.
+    fn read(data: &[u8], complete: bool) -> Result<Option<Prefix>> {
+        let mut source = data;
+        let magic_read = match detect_preamble(&mut source, ReadPreamble::Auto) {
+            Ok((_, magic_read)) => magic_read,
+            Err(_) if !complete => return Ok(None),
+            Err(e) => return Err(e).context(ReadPreambleSnafu),
+        };
+        let meta = if magic_read {
+            FileMetaTable::from_reader((&b"DICM"[..]).chain(&mut source))
+        } else {
+            FileMetaTable::from_reader(&mut source)
+        };
+        let meta = match meta {
+            Ok(meta) => meta,
+            Err(_) if !complete => return Ok(None),
+            Err(e) => return Err(e).context(ParseMetaDataSetSnafu),
+        };
+        let start = data.len() - source.len();
+
+        let ts = TransferSyntaxRegistry
+            .get(meta.transfer_syntax())
+            .filter(|ts| !matches!(ts.codec(), Codec::Dataset(_)))
+            .context(UnsupportedTransferSyntaxSnafu {
+                uid: meta.transfer_syntax(),
+            })?;
+
+        // find where the pixel data starts in the root data set
+        let position = Cell::new(0);
+        let reader = SliceReader {
+            data: &data[start..],
+            position: &position,
+        };
+        let mut reader =
+            match DataSetReader::new_with_ts_cs(reader, ts, SpecificCharacterSet::Default) {
+                Ok(reader) => reader,
+                Err(_) if !complete => return Ok(None),
+                Err(e) => return Err(e).context(ReadTokenSnafu),
+            };
+        let mut depth = 0;
+        let mut end = data.len() - start;
+        let mut pixel_data = None;
+        loop {
+            let header_start = position.get();
+            let token = match reader.next() {
+                Some(Ok(token)) => token,
+                Some(Err(_)) if !complete => return Ok(None),
+                Some(Err(e)) => return Err(e).context(ReadTokenSnafu),
+                None if !complete => return Ok(None),
+                None => break,
+            };
+            let value_start = (start + position.get()) as u64;
+            match token {
+                DataToken::ElementHeader(header)
+                    if depth == 0 && header.tag() == tags::PIXEL_DATA =>
+                {
+                    end = header_start;
+                    pixel_data = Some(PixelDataPosition::Native {
+                        offset: value_start,
+                        len: header.length().0,
+                    });
+                    break;
+                }
+                DataToken::PixelSequenceStart if depth == 0 => {
+                    end = header_start;
+                    pixel_data = Some(PixelDataPosition::Encapsulated {
+                        offset: value_start,
+                    });
+                    break;
+                }
+                DataToken::SequenceStart { .. }
+                | DataToken::PixelSequenceStart
+                | DataToken::FragmentSequenceStart { .. } => depth += 1,
+                DataToken::SequenceEnd => depth -= 1,
+                _ => {}
+            }
+        }
+
+        let header = InMemDicomObject::read_dataset_with_ts(&data[start..start + end], ts)
+            .context(ReadDataSetSnafu)?;
+        Ok(Some(Prefix {
+            meta,
+            header,
+            pixel_data,
+        }))
+    }
+}
+
+/// A reader over a slice which keeps track of the bytes read.
+struct SliceReader<'a> {
+    data: &'a [u8],
+    position: &'a Cell<usize>,
+}
+
+impl Read for SliceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.data.read(buf)?;
+        self.position.set(self.position.get() + n);
+        Ok(n)
+    }
+}
+
+/// Retrieve an attribute of the image pixel module as an integer.
+fn pixel_attribute(header: &InMemDicomObject, tag: Tag, name: &'static str) -> Result<u64> {
+    header
+        .element(tag)
+        .ok()
+        .and_then(|elem| elem.to_int::<u64>().ok())
+        .context(MissingAttributeSnafu { name })
+}
+
+/// The number of frames declared in the header.
+fn number_of_frames(header: &InMemDicomObject) -> Result<u32> {
+    match header.element_opt(tags::NUMBER_OF_FRAMES) {
+        Ok(Some(elem)) if !elem.is_empty_value() => {
+            elem.to_int::<u32>().ok().context(MissingAttributeSnafu {
+                name: "NumberOfFrames",
+            })
+        }
+        _ => Ok(1),
+    }
+}
+
+/// Locate the frames of native pixel data.
+fn native_frames(header: &InMemDicomObject, offset: u64, len: u32) -> Result<Vec<Range<u64>>> {
+    let rows = pixel_attribute(header, tags::ROWS, "Rows")?;
+    let columns = pixel_attribute(header, tags::COLUMNS, "Columns")?;
+    let samples = pixel_attribute(header, tags::SAMPLES_PER_PIXEL, "SamplesPerPixel")?;
+    let bits = pixel_attribute(header, tags::BITS_ALLOCATED, "BitsAllocated")?;
+    let frames = number_of_frames(header)?;
+
+    let frame_bits = rows * columns * samples * bits;
+    ensure!(
+        frame_bits % 8 == 0,
+        UnalignedFramesSnafu { bits: frame_bits }
+    );
+    let frame_size = frame_bits / 8;
+    ensure!(
+        frame_size * u64::from(frames) <= u64::from(len),
+        ShortPixelDataSnafu { frames }
+    );
+    Ok((0..u64::from(frames))
+        .map(|i| {
+            let start = offset + i * frame_size;
+            start..start + frame_size
+        })
+        .collect())
+}
+
+/// Locate the items of each frame of encapsulated pixel data,
+/// which is always encoded in little endian.
+fn encapsulated_frames<S>(
+    source: &S,
+    header: &InMemDicomObject,
+    offset: u64,
+    size: u64,
+) -> Result<Vec<Range<u64>>>
+where
+    S: RangeSource,
+{
+    let frames = number_of_frames(header)?;
+
+    // the basic offset table is the first item
+    let (tag, len) = item_header(&fetch(source, offset..offset + 8, size)?);
+    ensure!(
+        tag == ITEM,
+        UnexpectedItemSnafu {
+            tag,
+            position: offset
+        }
+    );
+    let first_fragment = offset + 8 + u64::from(len);
+
+    // use the extended offset table if available
+    let extended = header
+        .element_opt(tags::EXTENDED_OFFSET_TABLE)
+        .ok()
+        .flatten()
+        .and_then(|elem| elem.to_multi_int::<u64>().ok());
+    let extended_lengths = header
+        .element_opt(tags::EXTENDED_OFFSET_TABLE_LENGTHS)
+        .ok()
+        .flatten()
+        .and_then(|elem| elem.to_multi_int::<u64>().ok());
+    if let (Some(offsets), Some(lengths)) = (extended, extended_lengths) {
+        if offsets.len() == frames as usize && lengths.len() == frames as usize {
+            return Ok(offsets
+                .iter()
+                .zip(&lengths)
+                .map(|(&offset, &len)| {
+                    let start = first_fragment + offset;
+                    start..start + 8 + len
+                })
+                .collect());
+        }
+    }
+
+    // or else the basic offset table
+    if len > 0 {
+        let table = fetch(source, offset + 8..first_fragment, size)?;
+        let offsets: Vec<u64> = table
+            .chunks_exact(4)
+            .map(|chunk| u64::from(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])))
+            .collect();
+        if offsets.len() == frames as usize {
+            let ends = offsets
+                .iter()
+                .skip(1)
+                .map(|&offset| first_fragment + offset)
+                .chain(std::iter::once(size));
+            return Ok(offsets
+                .iter()
+                .zip(ends)
+                .map(|(&offset, end)| first_fragment + offset..end)
+                .collect());
+        }
+    }
+
+    // or else walk through the fragments
+    let mut fragments = Vec::new();
+    let mut position = first_fragment;
+    loop {
+        let (tag, len) = item_header(&fetch(source, position..position + 8, size)?);
+        if tag == SEQUENCE_DELIMITATION_ITEM {
+            break;
+        }
+        ensure!(tag == ITEM, UnexpectedItemSnafu { tag, position });
+        let end = position + 8 + u64::from(len);
+        fragments.push(position..end);
+        position = end;
+    }
+    if fragments.len() == frames as usize {
+        Ok(fragments)
+    } else if frames == 1 && !fragments.is_empty() {
+        let frame = first_fragment..position;
+        Ok(vec![frame])
+    } else {
+        UnknownFrameBoundariesSnafu {
+            frames,
+            fragments: fragments.len(),
+        }
+        .fail()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::FileMetaTableBuilder;
+    use crate::FileDicomObject;
+    use dicom_core::value::{PrimitiveValue, Value, C};
+    use dicom_core::{DataElement, VR};
+
+    /// An in-memory source which keeps track of the bytes fetched.
+    struct CountingSource {
+        data: Vec<u8>,
+        fetched: Cell<u64>,
+    }
+
+    impl RangeSource for CountingSource {
+        fn size(&self) -> std::io::Result<u64> {
+            self.data.size()
+        }
+
+        fn read_range(&self, range: Range<u64>) -> std::io::Result<Vec<u8>> {
+            let bytes = self.data.read_range(range)?;
+            self.fetched.set(self.fetched.get() + bytes.len() as u64);
+            Ok(bytes)
+        }
+    }
+
+    fn image(ts: &str, frames: u32, pixel_data: Value<InMemDicomObject, Vec<u8>>) -> Vec<u8> {
+        let vr = match pixel_data {
+            Value::PixelSequence { .. } => VR::OB,
+            _ => VR::OW,
+        };
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("P1")),
+            DataElement::new(
+                tags::NUMBER_OF_FRAMES,
+                VR::IS,
+                PrimitiveValue::from(frames.to_string()),
+            ),
+            DataElement::new(tags::SAMPLES_PER_PIXEL, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(256_u16)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(256_u16)),
+            DataElement::new(tags::BITS_ALLOCATED, VR::US, PrimitiveValue::from(16_u16)),
+            DataElement::new(tags::PIXEL_DATA, vr, pixel_data),
+        ]);
+        let obj: FileDicomObject<_> = obj
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                    .media_storage_sop_instance_uid("1.2.3.4")
+                    .transfer_syntax(ts),
+            )
+            .unwrap();
+        let mut out = Vec::new();
+        obj.write_all(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn remote_native_frames() {
+        let frame_size = 256 * 256 * 2;
+        let pixels: Vec<u8> = (0..frame_size * 3)
+            .map(|i| (i / frame_size) as u8)
+            .collect();
+        let data = image(
+            "1.2.840.10008.1.2.1",
+            3,
+            PrimitiveValue::from(pixels).into(),
+        );
+        let source = CountingSource {
+            data,
+            fetched: Cell::new(0),
+        };
+
+        let obj = RemoteDicomObject::open(&source).unwrap();
+        assert_eq!(obj.meta().transfer_syntax(), "1.2.840.10008.1.2.1");
+        assert_eq!(
+            obj.header()
+                .element(tags::PATIENT_ID)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "P1"
+        );
+        assert!(!obj.header().has_element(tags::PIXEL_DATA));
+        assert_eq!(obj.number_of_frames(), 3);
+
+        let fetched = source.fetched.get();
+        let frame = obj.frame(2).unwrap();
+        assert_eq!(frame.len(), frame_size);
+        assert!(frame.iter().all(|&b| b == 2));
+        assert_eq!(source.fetched.get() - fetched, frame_size as u64);
+        assert!(source.fetched.get() < source.data.len() as u64);
+
+        assert!(matches!(
+            obj.frame(3),
+            Err(Error::NoSuchFrame { index: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn remote_encapsulated_frames() {
+        let fragments = vec![vec![1; 100], vec![2; 60], vec![3; 30], vec![4; 10]];
+
+        // without offset table, one fragment per frame
+        let data = image(
+            "1.2.840.10008.1.2.4.50",
+            4,
+            Value::PixelSequence {
+                offset_table: C::new(),
+                fragments: fragments.clone().into(),
+            },
+        );
+        let obj = RemoteDicomObject::open(data).unwrap();
+        assert_eq!(obj.number_of_frames(), 4);
+        for (i, fragment) in fragments.iter().enumerate() {
+            assert_eq!(&obj.frame(i as u32).unwrap(), fragment);
+        }
+
+        // with basic offset table, two fragments per frame
+        let data = image(
+            "1.2.840.10008.1.2.4.50",
+            2,
+            Value::PixelSequence {
+                offset_table: vec![0, 108 + 68].into(),
+                fragments: fragments.clone().into(),
+            },
+        );
+        let obj = RemoteDicomObject::open(data).unwrap();
+        assert_eq!(obj.number_of_frames(), 2);
+        assert_eq!(
+            obj.frame(0).unwrap(),
+            [&fragments[0][..], &fragments[1]].concat()
+        );
+        assert_eq!(
+            obj.frame(1).unwrap(),
+            [&fragments[2][..], &fragments[3]].concat()
+        );
+
+        // without offset table, several fragments in a single frame
+        let data = image(
+            "1.2.840.10008.1.2.4.50",
+            1,
+            Value::PixelSequence {
+                offset_table: C::new(),
+                fragments: fragments.clone().into(),
+            },
+        );
+        let obj = RemoteDicomObject::open(data).unwrap();
+        assert_eq!(obj.frame(0).unwrap(), fragments.concat());
+
+        // unless the frames cannot be told apart
+        let data = image(
+            "1.2.840.10008.1.2.4.50",
+            3,
+            Value::PixelSequence {
+                offset_table: C::new(),
+                fragments: fragments.into(),
+            },
+        );
+        assert!(matches!(
+            RemoteDicomObject::open(data),
+            Err(Error::UnknownFrameBoundaries {
+                frames: 3,
+                fragments: 4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn remote_object_with_large_header() {
+        // attributes beyond the initial fetch are fetched too
+        let text = "x".repeat(INITIAL_FETCH as usize * 3);
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::IMAGE_COMMENTS,
+            VR::LT,
+            PrimitiveValue::from(text.clone()),
+        ));
+        let obj = obj
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                    .media_storage_sop_instance_uid("1.2.3.4")
+                    .transfer_syntax("1.2.840.10008.1.2"),
+            )
+            .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+
+        let obj = RemoteDicomObject::open(data).unwrap();
+        assert_eq!(obj.number_of_frames(), 0);
+        assert_eq!(
+            obj.header()
+                .element(tags::IMAGE_COMMENTS)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            text
+        );
+    }
+}
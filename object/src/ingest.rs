@@ -0,0 +1,539 @@
+//! Pipelined ingestion of DICOM files.
+//!
+//! Importing an archive usually requires
+//! a checksum of each file for deduplication and integrity,
+//! as well as a few attributes of its header for indexing.
+//! [`ingest_files`] reads each file only once,
+//! handing the same chunks of bytes to a hashing worker
+//! and to a parsing worker,
+//! so that I/O, hashing and parsing run concurrently
+//! on separate threads.
+//!
+//! Parsing stops at the _Pixel Data_ attribute,
+//! while hashing always covers the entire file.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dicom_object::ingest::ingest_files;
+//!
+//! ingest_files(&["0001.dcm", "0002.dcm"], |file| {
+//!     match &file.attributes {
+//!         Ok(attributes) => println!(
+//!             "{}: {:?} ({} bytes)",
+//!             file.path.display(),
+//!             attributes.sop_instance_uid,
+//!             file.size,
+//!         ),
+//!         Err(e) => eprintln!("{}: {}", file.path.display(), e),
+//!     }
+//! });
+//! ```
+use crate::file::OpenFileOptions;
+use crate::progress::{Cancelled, NoProgress, Progress, ProgressStatus};
+use crate::DefaultDicomObject;
+use dicom_core::Tag;
+use dicom_dictionary_std::tags;
+use sha2::{Digest, Sha256};
+use snafu::{Backtrace, ResultExt, Snafu};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+
+/// The size of each chunk read from a file.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The number of chunks which may be pending for each worker.
+const PENDING_CHUNKS: usize = 16;
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum IngestError {
+    /// Could not read the file
+    ReadFile {
+        source: io::Error,
+        backtrace: Backtrace,
+    },
+    /// Could not parse the file
+    ParseFile {
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+}
+
+/// The attributes of an ingested file which are relevant for indexing.
+///
+/// Attributes which are missing or cannot be read as text are `None`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct IndexAttributes {
+    /// The _Transfer Syntax UID_ of the file meta group
+    pub transfer_syntax: String,
+    /// _SOP Class UID_, or _Media Storage SOP Class UID_ if absent
+    pub sop_class_uid: Option<String>,
+    /// _SOP Instance UID_, or _Media Storage SOP Instance UID_ if absent
+    pub sop_instance_uid: Option<String>,
+    pub study_instance_uid: Option<String>,
+    pub series_instance_uid: Option<String>,
+    pub patient_id: Option<String>,
+    pub modality: Option<String>,
+}
+
+impl IndexAttributes {
+    /// Collect the index attributes of a file object.
+    pub fn from_object(obj: &DefaultDicomObject) -> Self {
+        let text = |tag: Tag| {
+            obj.element_opt(tag)
+                .ok()
+                .flatten()
+                .and_then(|e| e.to_str().ok())
+                .map(|s| s.trim_end_matches([' ', '\0']).to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let meta = obj.meta();
+        IndexAttributes {
+            transfer_syntax: meta.transfer_syntax().to_string(),
+            sop_class_uid: text(tags::SOP_CLASS_UID)
+                .or_else(|| Some(meta.media_storage_sop_class_uid().to_string())),
+            sop_instance_uid: text(tags::SOP_INSTANCE_UID)
+                .or_else(|| Some(meta.media_storage_sop_instance_uid().to_string())),
+            study_instance_uid: text(tags::STUDY_INSTANCE_UID),
+            series_instance_uid: text(tags::SERIES_INSTANCE_UID),
+            patient_id: text(tags::PATIENT_ID),
+            modality: text(tags::MODALITY),
+        }
+    }
+}
+
+/// The outcome of ingesting a single file.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct IngestedFile {
+    /// The path to the file
+    pub path: PathBuf,
+    /// The number of bytes read from the file
+    pub size: u64,
+    /// The SHA-256 digest of the file's contents,
+    /// or `None` if the file could not be read in full
+    pub digest: Option<[u8; 32]>,
+    /// The index attributes of the file,
+    /// or the reason why they could not be obtained
+    pub attributes: Result<IndexAttributes, IngestError>,
+}
+
+/// A piece of a file sent to the workers.
+enum Chunk {
+    Data(Arc<[u8]>),
+    /// The file was read to the end
+    End,
+    /// The file could not be read to the end
+    Failed,
+}
+
+/// Ingest the given files,
+/// passing the outcome for each file to `sink`,
+/// in the same order as the given paths.
+///
+/// Each file is read only once,
+/// while a hashing worker computes its SHA-256 digest
+/// and a parsing worker reads its header
+/// (up to the _Pixel Data_ attribute)
+/// to extract the [index attributes](IndexAttributes).
+/// The sink is called on the current thread.
+///
+/// Failing to read or parse a file does not stop the ingestion:
+/// the error is reported in [`IngestedFile::attributes`] instead.
+pub fn ingest_files<I, P, F>(paths: I, sink: F)
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+    F: FnMut(IngestedFile),
+{
+    // never cancelled
+    let _ = ingest_files_with_progress(paths, sink, NoProgress);
+}
+
+/// Ingest the given files as in [`ingest_files`],
+/// reporting progress after each file is passed to `sink`.
+///
+/// The status counts the files ingested
+/// and the bytes read from them,
+/// out of the number of files given
+/// and the sum of their sizes.
+/// If the observer cancels the operation,
+/// no more files are passed to the sink
+/// and the function returns [`Cancelled`].
+pub fn ingest_files_with_progress<I, P, F, Pr>(
+    paths: I,
+    mut sink: F,
+    mut progress: Pr,
+) -> Result<(), Cancelled>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+    F: FnMut(IngestedFile),
+    Pr: Progress,
+{
+    let paths: Vec<PathBuf> = paths
+        .into_iter()
+        .map(|p| p.as_ref().to_path_buf())
+        .collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let bytes_total = paths
+        .iter()
+        .map(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let mut status = ProgressStatus::new(Some(bytes_total), Some(paths.len() as u64));
+
+    let (hash_tx, hash_rx) = sync_channel(PENDING_CHUNKS);
+    let (parse_tx, parse_rx) = sync_channel(PENDING_CHUNKS);
+    let (read_result_tx, read_result_rx) = channel();
+    let (hash_result_tx, hash_result_rx) = channel();
+    let (parse_result_tx, parse_result_rx) = channel();
+
+    let reader = {
+        let paths = paths.clone();
+        thread::spawn(move || {
+            for path in paths {
+                let result = read_file(&path, &hash_tx, &parse_tx);
+                if read_result_tx.send(result).is_err() {
+                    break;
+                }
+            }
+        })
+    };
+
+    let hasher = thread::spawn(move || loop {
+        let mut hasher = Sha256::new();
+        let digest = loop {
+            match hash_rx.recv() {
+                Ok(Chunk::Data(data)) => hasher.update(&data),
+                Ok(Chunk::End) => break Some(hasher.finalize().into()),
+                Ok(Chunk::Failed) => break None,
+                Err(_) => return,
+            }
+        };
+        if hash_result_tx.send(digest).is_err() {
+            return;
+        }
+    });
+
+    let parser = thread::spawn(move || {
+        let mut source = ChunkReader::new(parse_rx);
+        while source.next_file() {
+            let result = OpenFileOptions::new()
+                .read_until(tags::PIXEL_DATA)
+                .from_reader(&mut source)
+                .map(|obj| IndexAttributes::from_object(&obj));
+            source.drain();
+            if parse_result_tx.send(result).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut outcome = Ok(());
+    for path in paths {
+        let (read_result, digest, parse_result) = match (
+            read_result_rx.recv(),
+            hash_result_rx.recv(),
+            parse_result_rx.recv(),
+        ) {
+            (Ok(r), Ok(d), Ok(p)) => (r, d, p),
+            _ => break,
+        };
+        let (size, attributes) = match read_result {
+            Ok(size) => (size, parse_result.context(ParseFileSnafu)),
+            Err((size, source)) => (size, Err(source).context(ReadFileSnafu)),
+        };
+        sink(IngestedFile {
+            path,
+            size,
+            digest,
+            attributes,
+        });
+        status.bytes_processed += size;
+        status.items_completed += 1;
+        if progress.update(&status).is_cancel() {
+            outcome = Err(Cancelled);
+            break;
+        }
+    }
+
+    // the workers stop once all chunks are consumed
+    // or the results are no longer received
+    drop((read_result_rx, hash_result_rx, parse_result_rx));
+    let _ = reader.join();
+    let _ = hasher.join();
+    let _ = parser.join();
+    outcome
+}
+
+/// Read a whole file, sending its chunks to both workers.
+///
+/// Returns the number of bytes read,
+/// or the number of bytes read until an error occurred.
+fn read_file(
+    path: &Path,
+    hash_tx: &SyncSender<Chunk>,
+    parse_tx: &SyncSender<Chunk>,
+) -> Result<u64, (u64, io::Error)> {
+    let send = |chunk: &dyn Fn() -> Chunk| {
+        // the workers only hang up when the ingestion is over
+        let _ = hash_tx.send(chunk());
+        let _ = parse_tx.send(chunk());
+    };
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            send(&|| Chunk::Failed);
+            return Err((0, e));
+        }
+    };
+    let mut size = 0;
+    loop {
+        let mut buf = vec![0; CHUNK_SIZE];
+        match file.read(&mut buf) {
+            Ok(0) => {
+                send(&|| Chunk::End);
+                return Ok(size);
+            }
+            Ok(n) => {
+                size += n as u64;
+                buf.truncate(n);
+                let data: Arc<[u8]> = buf.into();
+                send(&|| Chunk::Data(Arc::clone(&data)));
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                send(&|| Chunk::Failed);
+                return Err((size, e));
+            }
+        }
+    }
+}
+
+/// A byte source over the chunks of one file at a time.
+struct ChunkReader {
+    chunks: Receiver<Chunk>,
+    current: Arc<[u8]>,
+    position: usize,
+    /// Whether the end of the current file was reached
+    done: bool,
+}
+
+impl ChunkReader {
+    fn new(chunks: Receiver<Chunk>) -> Self {
+        ChunkReader {
+            chunks,
+            current: Arc::new([]),
+            position: 0,
+            done: true,
+        }
+    }
+
+    /// Start reading the next file,
+    /// returning `false` if there are no more files.
+    fn next_file(&mut self) -> bool {
+        match self.chunks.recv() {
+            Ok(chunk) => {
+                self.current = Arc::new([]);
+                self.position = 0;
+                self.done = false;
+                // read failures are reported by the reader
+                let _ = self.accept(chunk);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Take in the next chunk of the current file.
+    fn accept(&mut self, chunk: Chunk) -> io::Result<()> {
+        match chunk {
+            Chunk::Data(data) => {
+                self.current = data;
+                self.position = 0;
+                Ok(())
+            }
+            Chunk::End => {
+                self.done = true;
+                Ok(())
+            }
+            Chunk::Failed => {
+                self.done = true;
+                Err(io::Error::other("file read failed"))
+            }
+        }
+    }
+
+    /// Skip the rest of the current file.
+    fn drain(&mut self) {
+        while !self.done {
+            match self.chunks.recv() {
+                Ok(chunk) => {
+                    let _ = self.accept(chunk);
+                }
+                Err(_) => self.done = true,
+            }
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.current.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.chunks.recv() {
+                Ok(chunk) => self.accept(chunk)?,
+                Err(_) => self.done = true,
+            }
+        }
+        let n = buf.len().min(self.current.len() - self.position);
+        buf[..n].copy_from_slice(&self.current[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressControl;
+    use crate::{FileMetaTableBuilder, InMemDicomObject};
+    use dicom_core::{DataElement, PrimitiveValue, VR};
+
+    fn write_test_file(path: &Path, sop_instance_uid: &str, pixel_data_len: usize) {
+        let obj = InMemDicomObject::from_element_iter(vec![
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7"),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(sop_instance_uid),
+            ),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("OT")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("P-0001")),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                PrimitiveValue::from(vec![0x55_u8; pixel_data_len]),
+            ),
+        ]);
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax("1.2.840.10008.1.2.1")
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7"),
+        )
+        .unwrap()
+        .write_to_file(path)
+        .unwrap();
+    }
+
+    #[test]
+    fn ingest_files_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small.dcm");
+        let large = dir.path().join("large.dcm");
+        let garbage = dir.path().join("garbage.dcm");
+        let missing = dir.path().join("missing.dcm");
+        write_test_file(&small, "1.2.3.4.1", 16);
+        // spans several chunks
+        write_test_file(&large, "1.2.3.4.2", 5 * CHUNK_SIZE / 2);
+        std::fs::write(&garbage, vec![0xAB; CHUNK_SIZE + 10]).unwrap();
+
+        let mut ingested = Vec::new();
+        ingest_files(vec![&small, &garbage, &missing, &large], |file| {
+            ingested.push(file)
+        });
+        assert_eq!(ingested.len(), 4);
+
+        for (file, path) in ingested.iter().zip(&[&small, &garbage, &missing, &large]) {
+            assert_eq!(&&file.path, path);
+        }
+
+        for &(file, uid) in &[(&ingested[0], "1.2.3.4.1"), (&ingested[3], "1.2.3.4.2")] {
+            let contents = std::fs::read(&file.path).unwrap();
+            assert_eq!(file.size, contents.len() as u64);
+            assert_eq!(file.digest.unwrap()[..], Sha256::digest(&contents)[..],);
+            let attributes = file.attributes.as_ref().unwrap();
+            assert_eq!(attributes.transfer_syntax, "1.2.840.10008.1.2.1");
+            assert_eq!(attributes.sop_instance_uid.as_deref(), Some(uid));
+            assert_eq!(
+                attributes.sop_class_uid.as_deref(),
+                Some("1.2.840.10008.5.1.4.1.1.7")
+            );
+            assert_eq!(attributes.patient_id.as_deref(), Some("P-0001"));
+            assert_eq!(attributes.modality.as_deref(), Some("OT"));
+            assert_eq!(attributes.study_instance_uid, None);
+        }
+
+        // not DICOM, but still hashed
+        let file = &ingested[1];
+        assert_eq!(file.size, CHUNK_SIZE as u64 + 10);
+        assert!(file.digest.is_some());
+        assert!(matches!(
+            file.attributes,
+            Err(IngestError::ParseFile { .. })
+        ));
+
+        let file = &ingested[2];
+        assert_eq!(file.size, 0);
+        assert_eq!(file.digest, None);
+        assert!(matches!(file.attributes, Err(IngestError::ReadFile { .. })));
+    }
+
+    #[test]
+    fn ingest_files_reports_progress_and_cancels() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = (1..=3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.dcm", i));
+                write_test_file(&path, &format!("1.2.3.4.{}", i), 64);
+                path
+            })
+            .collect();
+        let sizes: Vec<u64> = paths
+            .iter()
+            .map(|p| std::fs::metadata(p).unwrap().len())
+            .collect();
+
+        let mut updates = Vec::new();
+        let outcome = ingest_files_with_progress(
+            &paths,
+            |_| {},
+            |status: &ProgressStatus| {
+                updates.push(*status);
+                ProgressControl::Continue
+            },
+        );
+        assert!(outcome.is_ok());
+        assert_eq!(updates.len(), 3);
+        let last = updates.last().unwrap();
+        assert_eq!(last.items_completed, 3);
+        assert_eq!(last.items_total, Some(3));
+        assert_eq!(last.bytes_processed, sizes.iter().sum::<u64>());
+        assert_eq!(last.bytes_total, Some(sizes.iter().sum::<u64>()));
+
+        let mut ingested = 0;
+        let outcome = ingest_files_with_progress(
+            &paths,
+            |_| ingested += 1,
+            |_: &ProgressStatus| ProgressControl::Cancel,
+        );
+        assert_eq!(outcome, Err(Cancelled));
+        assert_eq!(ingested, 1);
+    }
+}
@@ -0,0 +1,586 @@
+//! Creation of new DICOM instances.
+//!
+//! [`InstanceBuilder`] assembles a complete instance
+//! from a few identifying attributes and the pixel data of an image:
+//! the file meta group,
+//! new SOP Instance, Study Instance and Series Instance UIDs
+//! (unless given),
+//! the mandatory attributes of the Patient, General Study,
+//! General Series, General Image and Image Pixel modules,
+//! and the current date and time as the study and content date-time.
+//! This allows applications to store synthesized images or screenshots
+//! as Secondary Capture images,
+//! which is the default SOP class.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_dictionary_std::tags;
+//! use dicom_object::instance::InstanceBuilder;
+//!
+//! // a 2x2 grayscale image
+//! let obj = InstanceBuilder::secondary_capture()
+//!     .patient_name("Doe^John")
+//!     .patient_id("12345")
+//!     .series_description("Screenshot")
+//!     .monochrome8(2, 2, vec![0, 64, 128, 255])
+//!     .build()?;
+//!
+//! assert_eq!(obj.element(tags::MODALITY)?.to_str()?, "OT");
+//! assert_eq!(obj.element(tags::ROWS)?.to_int::<u16>()?, 2);
+//! assert_eq!(
+//!     obj.meta().media_storage_sop_instance_uid(),
+//!     obj.element(tags::SOP_INSTANCE_UID)?.to_str()?,
+//! );
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::InMemDicomObject;
+use crate::meta::FileMetaTableBuilder;
+use crate::uid::generate_uid;
+use crate::DefaultDicomObject;
+use dicom_core::chrono::{DateTime, FixedOffset, Local};
+use dicom_core::value::PrimitiveValue;
+use dicom_core::{DataElement, Tag, VR};
+use dicom_dictionary_std::tags;
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
+
+/// The SOP class UID of the Secondary Capture Image Storage.
+const SECONDARY_CAPTURE_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.7";
+
+/// The UID of the Explicit VR Little Endian transfer syntax.
+const EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Could not generate a new UID"))]
+    GenerateUid {
+        #[snafu(backtrace)]
+        source: crate::uid::Error,
+    },
+    #[snafu(display("Missing pixel data"))]
+    MissingPixelData { backtrace: Backtrace },
+    #[snafu(display("Pixel data has {} samples, but the image needs {}", actual, expected))]
+    PixelDataLength {
+        expected: usize,
+        actual: usize,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not create the file meta group"))]
+    CreateMeta {
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The samples of an image, as given to the builder.
+#[derive(Debug, Clone, PartialEq)]
+enum Samples {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+}
+
+/// The pixel data of an image and its description.
+#[derive(Debug, Clone, PartialEq)]
+struct Image {
+    rows: u16,
+    columns: u16,
+    samples_per_pixel: u16,
+    photometric_interpretation: &'static str,
+    samples: Samples,
+}
+
+/// A builder for a new DICOM instance with an image.
+///
+/// Only the pixel data is required.
+/// UIDs which are not given are generated under the UID root,
+/// which is `2.25` by default.
+/// Type 2 attributes which are not given,
+/// such as the patient's name and ID,
+/// are included with an empty value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceBuilder {
+    sop_class_uid: String,
+    uid_root: String,
+    sop_instance_uid: Option<String>,
+    study_instance_uid: Option<String>,
+    series_instance_uid: Option<String>,
+    patient_name: String,
+    patient_id: String,
+    patient_birth_date: String,
+    patient_sex: String,
+    study_id: String,
+    accession_number: String,
+    study_description: Option<String>,
+    series_description: Option<String>,
+    modality: String,
+    conversion_type: Option<String>,
+    series_number: u32,
+    instance_number: u32,
+    date_time: Option<DateTime<FixedOffset>>,
+    image: Option<Image>,
+}
+
+impl InstanceBuilder {
+    /// Create a builder for an instance of the given SOP class,
+    /// with the modality `OT` (other).
+    pub fn new(sop_class_uid: impl Into<String>) -> Self {
+        InstanceBuilder {
+            sop_class_uid: sop_class_uid.into(),
+            uid_root: "2.25".to_string(),
+            sop_instance_uid: None,
+            study_instance_uid: None,
+            series_instance_uid: None,
+            patient_name: String::new(),
+            patient_id: String::new(),
+            patient_birth_date: String::new(),
+            patient_sex: String::new(),
+            study_id: String::new(),
+            accession_number: String::new(),
+            study_description: None,
+            series_description: None,
+            modality: "OT".to_string(),
+            conversion_type: None,
+            series_number: 1,
+            instance_number: 1,
+            date_time: None,
+            image: None,
+        }
+    }
+
+    /// Create a builder for a Secondary Capture image,
+    /// produced by a workstation (_Conversion Type_ `WSD`).
+    pub fn secondary_capture() -> Self {
+        InstanceBuilder::new(SECONDARY_CAPTURE_IMAGE_STORAGE).conversion_type("WSD")
+    }
+
+    /// Set the root under which new UIDs are generated.
+    pub fn uid_root(mut self, uid_root: impl Into<String>) -> Self {
+        self.uid_root = uid_root.into();
+        self
+    }
+
+    /// Set the SOP Instance UID,
+    /// instead of generating a new one.
+    pub fn sop_instance_uid(mut self, uid: impl Into<String>) -> Self {
+        self.sop_instance_uid = Some(uid.into());
+        self
+    }
+
+    /// Set the Study Instance UID,
+    /// so that the instance is added to an existing study.
+    pub fn study_instance_uid(mut self, uid: impl Into<String>) -> Self {
+        self.study_instance_uid = Some(uid.into());
+        self
+    }
+
+    /// Set the Series Instance UID,
+    /// so that the instance is added to an existing series.
+    pub fn series_instance_uid(mut self, uid: impl Into<String>) -> Self {
+        self.series_instance_uid = Some(uid.into());
+        self
+    }
+
+    /// Set the patient's name.
+    pub fn patient_name(mut self, name: impl Into<String>) -> Self {
+        self.patient_name = name.into();
+        self
+    }
+
+    /// Set the patient ID.
+    pub fn patient_id(mut self, id: impl Into<String>) -> Self {
+        self.patient_id = id.into();
+        self
+    }
+
+    /// Set the patient's birth date, in the `YYYYMMDD` format.
+    pub fn patient_birth_date(mut self, date: impl Into<String>) -> Self {
+        self.patient_birth_date = date.into();
+        self
+    }
+
+    /// Set the patient's sex (`M`, `F` or `O`).
+    pub fn patient_sex(mut self, sex: impl Into<String>) -> Self {
+        self.patient_sex = sex.into();
+        self
+    }
+
+    /// Set the study ID.
+    pub fn study_id(mut self, id: impl Into<String>) -> Self {
+        self.study_id = id.into();
+        self
+    }
+
+    /// Set the accession number.
+    pub fn accession_number(mut self, number: impl Into<String>) -> Self {
+        self.accession_number = number.into();
+        self
+    }
+
+    /// Set the study description.
+    pub fn study_description(mut self, description: impl Into<String>) -> Self {
+        self.study_description = Some(description.into());
+        self
+    }
+
+    /// Set the series description.
+    pub fn series_description(mut self, description: impl Into<String>) -> Self {
+        self.series_description = Some(description.into());
+        self
+    }
+
+    /// Set the modality.
+    pub fn modality(mut self, modality: impl Into<String>) -> Self {
+        self.modality = modality.into();
+        self
+    }
+
+    /// Set the _Conversion Type_ of a Secondary Capture image.
+    pub fn conversion_type(mut self, conversion_type: impl Into<String>) -> Self {
+        self.conversion_type = Some(conversion_type.into());
+        self
+    }
+
+    /// Set the series number, which is 1 by default.
+    pub fn series_number(mut self, number: u32) -> Self {
+        self.series_number = number;
+        self
+    }
+
+    /// Set the instance number, which is 1 by default.
+    pub fn instance_number(mut self, number: u32) -> Self {
+        self.instance_number = number;
+        self
+    }
+
+    /// Set the date-time of the study and of the content,
+    /// instead of the current date-time.
+    pub fn date_time(mut self, date_time: DateTime<FixedOffset>) -> Self {
+        self.date_time = Some(date_time);
+        self
+    }
+
+    /// Set the pixel data to a grayscale image with 8 bits per sample,
+    /// given row by row.
+    pub fn monochrome8(self, rows: u16, columns: u16, data: Vec<u8>) -> Self {
+        self.image(rows, columns, 1, "MONOCHROME2", Samples::U8(data))
+    }
+
+    /// Set the pixel data to a grayscale image with 16 bits per sample,
+    /// given row by row.
+    pub fn monochrome16(self, rows: u16, columns: u16, data: Vec<u16>) -> Self {
+        self.image(rows, columns, 1, "MONOCHROME2", Samples::U16(data))
+    }
+
+    /// Set the pixel data to a color image with 8 bits per sample,
+    /// given row by row, with the samples of each pixel interleaved
+    /// (`R1 G1 B1 R2 G2 B2 ...`).
+    pub fn rgb8(self, rows: u16, columns: u16, data: Vec<u8>) -> Self {
+        self.image(rows, columns, 3, "RGB", Samples::U8(data))
+    }
+
+    fn image(
+        mut self,
+        rows: u16,
+        columns: u16,
+        samples_per_pixel: u16,
+        photometric_interpretation: &'static str,
+        samples: Samples,
+    ) -> Self {
+        self.image = Some(Image {
+            rows,
+            columns,
+            samples_per_pixel,
+            photometric_interpretation,
+            samples,
+        });
+        self
+    }
+
+    /// Create the instance,
+    /// with a file meta group for the Explicit VR Little Endian
+    /// transfer syntax.
+    ///
+    /// Fails if no pixel data was given,
+    /// or if its number of samples does not match the image size.
+    pub fn build(self) -> Result<DefaultDicomObject> {
+        let image = self.image.context(MissingPixelDataSnafu)?;
+        let expected = usize::from(image.rows)
+            * usize::from(image.columns)
+            * usize::from(image.samples_per_pixel);
+        let actual = match &image.samples {
+            Samples::U8(data) => data.len(),
+            Samples::U16(data) => data.len(),
+        };
+        ensure!(
+            actual == expected,
+            PixelDataLengthSnafu { expected, actual }
+        );
+
+        let uid_root = &self.uid_root;
+        let new_uid = |uid: Option<String>| match uid {
+            Some(uid) => Ok(uid),
+            None => generate_uid(uid_root).context(GenerateUidSnafu),
+        };
+        let sop_instance_uid = new_uid(self.sop_instance_uid)?;
+        let study_instance_uid = new_uid(self.study_instance_uid)?;
+        let series_instance_uid = new_uid(self.series_instance_uid)?;
+
+        let date_time = self.date_time.unwrap_or_else(|| Local::now().into());
+        let date = date_time.format("%Y%m%d").to_string();
+        let time = date_time.format("%H%M%S%.6f").to_string();
+
+        let text =
+            |tag: Tag, vr: VR, value: &str| DataElement::new(tag, vr, PrimitiveValue::from(value));
+        let mut obj = InMemDicomObject::from_element_iter([
+            text(tags::SPECIFIC_CHARACTER_SET, VR::CS, "ISO_IR 192"),
+            DataElement::new(
+                tags::IMAGE_TYPE,
+                VR::CS,
+                PrimitiveValue::Strs(
+                    ["DERIVED", "SECONDARY"]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect(),
+                ),
+            ),
+            text(tags::INSTANCE_CREATION_DATE, VR::DA, &date),
+            text(tags::INSTANCE_CREATION_TIME, VR::TM, &time),
+            text(tags::SOP_CLASS_UID, VR::UI, &self.sop_class_uid),
+            text(tags::SOP_INSTANCE_UID, VR::UI, &sop_instance_uid),
+            text(tags::STUDY_DATE, VR::DA, &date),
+            text(tags::CONTENT_DATE, VR::DA, &date),
+            text(tags::STUDY_TIME, VR::TM, &time),
+            text(tags::CONTENT_TIME, VR::TM, &time),
+            text(tags::ACCESSION_NUMBER, VR::SH, &self.accession_number),
+            text(tags::MODALITY, VR::CS, &self.modality),
+            text(tags::REFERRING_PHYSICIAN_NAME, VR::PN, ""),
+            text(tags::PATIENT_NAME, VR::PN, &self.patient_name),
+            text(tags::PATIENT_ID, VR::LO, &self.patient_id),
+            text(tags::PATIENT_BIRTH_DATE, VR::DA, &self.patient_birth_date),
+            text(tags::PATIENT_SEX, VR::CS, &self.patient_sex),
+            text(tags::STUDY_INSTANCE_UID, VR::UI, &study_instance_uid),
+            text(tags::SERIES_INSTANCE_UID, VR::UI, &series_instance_uid),
+            text(tags::STUDY_ID, VR::SH, &self.study_id),
+            text(tags::SERIES_NUMBER, VR::IS, &self.series_number.to_string()),
+            text(
+                tags::INSTANCE_NUMBER,
+                VR::IS,
+                &self.instance_number.to_string(),
+            ),
+            text(tags::PATIENT_ORIENTATION, VR::CS, ""),
+            DataElement::new(
+                tags::SAMPLES_PER_PIXEL,
+                VR::US,
+                PrimitiveValue::from(image.samples_per_pixel),
+            ),
+            text(
+                tags::PHOTOMETRIC_INTERPRETATION,
+                VR::CS,
+                image.photometric_interpretation,
+            ),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(image.rows)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(image.columns)),
+            DataElement::new(
+                tags::PIXEL_REPRESENTATION,
+                VR::US,
+                PrimitiveValue::from(0_u16),
+            ),
+        ]);
+        let optional = [
+            (tags::CONVERSION_TYPE, VR::CS, &self.conversion_type),
+            (tags::STUDY_DESCRIPTION, VR::LO, &self.study_description),
+            (tags::SERIES_DESCRIPTION, VR::LO, &self.series_description),
+        ];
+        for (tag, vr, value) in optional {
+            if let Some(value) = value {
+                obj.put(text(tag, vr, value));
+            }
+        }
+        if image.samples_per_pixel > 1 {
+            obj.put(DataElement::new(
+                tags::PLANAR_CONFIGURATION,
+                VR::US,
+                PrimitiveValue::from(0_u16),
+            ));
+        }
+
+        let (bits, vr, value) = match image.samples {
+            Samples::U8(data) => (8_u16, VR::OB, PrimitiveValue::from(data)),
+            Samples::U16(data) => (16, VR::OW, PrimitiveValue::U16(data.into())),
+        };
+        obj.put(DataElement::new(
+            tags::BITS_ALLOCATED,
+            VR::US,
+            PrimitiveValue::from(bits),
+        ));
+        obj.put(DataElement::new(
+            tags::BITS_STORED,
+            VR::US,
+            PrimitiveValue::from(bits),
+        ));
+        obj.put(DataElement::new(
+            tags::HIGH_BIT,
+            VR::US,
+            PrimitiveValue::from(bits - 1),
+        ));
+        obj.put(DataElement::new(tags::PIXEL_DATA, vr, value));
+
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax(EXPLICIT_VR_LITTLE_ENDIAN)
+                .media_storage_sop_class_uid(self.sop_class_uid),
+        )
+        .context(CreateMetaSnafu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iod::check_conformance;
+    use crate::OpenFileOptions;
+    use dicom_core::chrono::TimeZone;
+
+    #[test]
+    fn build_conformant_secondary_capture() {
+        let date_time = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 1, 10, 30, 0)
+            .unwrap();
+        let obj = InstanceBuilder::secondary_capture()
+            .patient_name("Doe^John")
+            .patient_id("12345")
+            .study_instance_uid("2.25.100")
+            .date_time(date_time)
+            .rgb8(2, 1, vec![255, 0, 0, 0, 255, 0])
+            .build()
+            .unwrap();
+
+        let report = check_conformance(&obj).unwrap();
+        assert!(report.is_conformant(), "{:?}", report.findings());
+
+        assert_eq!(
+            obj.meta().media_storage_sop_class_uid(),
+            SECONDARY_CAPTURE_IMAGE_STORAGE
+        );
+        assert_eq!(obj.meta().transfer_syntax(), EXPLICIT_VR_LITTLE_ENDIAN);
+        assert_eq!(
+            obj.element(tags::STUDY_INSTANCE_UID)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "2.25.100"
+        );
+        let series_uid = obj
+            .element(tags::SERIES_INSTANCE_UID)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(series_uid.starts_with("2.25."));
+        assert_ne!(series_uid, "2.25.100");
+        assert_eq!(
+            obj.element(tags::STUDY_DATE).unwrap().to_str().unwrap(),
+            "20240301"
+        );
+        assert_eq!(
+            obj.element(tags::CONTENT_TIME).unwrap().to_str().unwrap(),
+            "103000.000000"
+        );
+        assert_eq!(
+            obj.element(tags::PLANAR_CONFIGURATION)
+                .unwrap()
+                .to_int::<u16>()
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            obj.element(tags::CONVERSION_TYPE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "WSD"
+        );
+
+        // the instance can be written and read back
+        let mut bytes = Vec::new();
+        obj.write_all(&mut bytes).unwrap();
+        let read = OpenFileOptions::new().from_reader(&bytes[..]).unwrap();
+        assert_eq!(
+            read.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Doe^John"
+        );
+        assert_eq!(
+            read.element(tags::PIXEL_DATA)
+                .unwrap()
+                .to_bytes()
+                .unwrap()
+                .len(),
+            6
+        );
+    }
+
+    #[test]
+    fn build_monochrome16_with_new_uids() {
+        let first = InstanceBuilder::secondary_capture()
+            .uid_root("1.2.3")
+            .monochrome16(1, 2, vec![1000, 4095])
+            .build()
+            .unwrap();
+        let second = InstanceBuilder::secondary_capture()
+            .uid_root("1.2.3")
+            .monochrome16(1, 2, vec![1000, 4095])
+            .build()
+            .unwrap();
+
+        let uid =
+            |obj: &DefaultDicomObject, tag| obj.element(tag).unwrap().to_str().unwrap().to_string();
+        assert!(uid(&first, tags::SOP_INSTANCE_UID).starts_with("1.2.3."));
+        assert_ne!(
+            uid(&first, tags::SOP_INSTANCE_UID),
+            uid(&second, tags::SOP_INSTANCE_UID)
+        );
+        assert_ne!(
+            uid(&first, tags::STUDY_INSTANCE_UID),
+            uid(&second, tags::STUDY_INSTANCE_UID)
+        );
+        assert_eq!(
+            first
+                .element(tags::BITS_ALLOCATED)
+                .unwrap()
+                .to_int::<u16>()
+                .unwrap(),
+            16
+        );
+        assert_eq!(
+            first
+                .element(tags::HIGH_BIT)
+                .unwrap()
+                .to_int::<u16>()
+                .unwrap(),
+            15
+        );
+        assert_eq!(first.element(tags::PIXEL_DATA).unwrap().vr(), VR::OW);
+        assert!(check_conformance(&first).unwrap().is_conformant());
+    }
+
+    #[test]
+    fn reject_missing_or_inconsistent_pixel_data() {
+        assert!(matches!(
+            InstanceBuilder::secondary_capture().build(),
+            Err(Error::MissingPixelData { .. })
+        ));
+        assert!(matches!(
+            InstanceBuilder::secondary_capture()
+                .monochrome8(2, 2, vec![0; 3])
+                .build(),
+            Err(Error::PixelDataLength {
+                expected: 4,
+                actual: 3,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,986 @@
+//! Conformance validation against Information Object Definitions.
+//!
+//! An [`Iod`] lists the modules of a composite IOD (PS3.3 Annex A),
+//! each with the rules of its attributes:
+//! the attribute type (1, 1C, 2, 2C or 3, see [`crate::validation`]),
+//! the value multiplicity
+//! and the enumerated values, if any.
+//! The value representation of each attribute
+//! is checked against the standard data dictionary.
+//!
+//! [`check_conformance`] picks the IOD from the object's SOP Class UID
+//! and returns a [`ConformanceReport`] with all findings.
+//! Only the mandatory modules of the IOD are checked,
+//! with the attributes most relevant to interoperability.
+//! The following IODs are currently defined:
+//!
+//! - [CT Image](CT_IMAGE_IOD)
+//! - [MR Image](MR_IMAGE_IOD)
+//! - [Secondary Capture Image](SC_IMAGE_IOD)
+//! - [US Image](US_IMAGE_IOD)
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::iod::{check_conformance, FindingKind};
+//!
+//! let obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(
+//!         tags::SOP_CLASS_UID,
+//!         VR::UI,
+//!         PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7"),
+//!     ),
+//!     DataElement::new(tags::PATIENT_SEX, VR::CS, PrimitiveValue::from("X")),
+//! ]);
+//!
+//! let report = check_conformance(&obj)?;
+//! assert_eq!(report.iod().name, "Secondary Capture Image");
+//! assert!(!report.is_conformant());
+//! assert!(report.findings().iter().any(|f| f.tag == tags::PATIENT_SEX
+//!     && matches!(f.kind, FindingKind::InvalidValue { .. })));
+//! # Ok::<(), dicom_object::iod::Error>(())
+//! ```
+use crate::mem::InMemDicomObject;
+use crate::validation::AttributeType;
+use crate::StandardDataDictionary;
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::value::equality::{is_textual, normalize_text};
+use dicom_core::value::Value;
+use dicom_core::{Tag, VR};
+use dicom_dictionary_std::tags;
+use snafu::{Backtrace, OptionExt, Snafu};
+use std::fmt;
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Missing SOP Class UID"))]
+    MissingSopClassUid { backtrace: Backtrace },
+    #[snafu(display("No IOD defined for SOP Class UID `{}`", uid))]
+    UnsupportedSopClass { uid: String, backtrace: Backtrace },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The range of values allowed in a multi-valued attribute.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Multiplicity {
+    /// The minimum number of values.
+    pub min: u32,
+    /// The maximum number of values, or `None` if unbounded.
+    pub max: Option<u32>,
+}
+
+impl Multiplicity {
+    /// A value multiplicity of exactly `n`.
+    pub const fn exactly(n: u32) -> Self {
+        Multiplicity {
+            min: n,
+            max: Some(n),
+        }
+    }
+
+    /// A value multiplicity from `min` to `max`.
+    pub const fn range(min: u32, max: u32) -> Self {
+        Multiplicity {
+            min,
+            max: Some(max),
+        }
+    }
+
+    /// A value multiplicity of at least `min`.
+    pub const fn at_least(min: u32) -> Self {
+        Multiplicity { min, max: None }
+    }
+
+    /// Check whether the given number of values is allowed.
+    pub fn contains(&self, n: u32) -> bool {
+        n >= self.min && self.max.map(|max| n <= max).unwrap_or(true)
+    }
+}
+
+impl fmt::Display for Multiplicity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{}-{}", self.min, max),
+            None => write!(f, "{}-n", self.min),
+        }
+    }
+}
+
+/// The condition under which a Type 1C or 2C attribute is required.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum Condition {
+    /// The given attribute is present.
+    Present(Tag),
+    /// The given attribute is not present.
+    Absent(Tag),
+    /// The given attribute is present with the given value.
+    Equals(Tag, &'static str),
+    /// The given attribute is present with a value other than the given one.
+    NotEquals(Tag, &'static str),
+}
+
+impl Condition {
+    /// Evaluate the condition on the given data set.
+    pub fn applies<D>(&self, obj: &InMemDicomObject<D>) -> bool
+    where
+        D: DataDictionary + Clone,
+    {
+        let value_of = |tag: Tag| {
+            obj.element_opt(tag)
+                .ok()
+                .flatten()
+                .map(|e| e.to_str().map(|s| s.trim().to_string()).unwrap_or_default())
+        };
+        match *self {
+            Condition::Present(tag) => value_of(tag).is_some(),
+            Condition::Absent(tag) => value_of(tag).is_none(),
+            Condition::Equals(tag, value) => value_of(tag).as_deref() == Some(value),
+            Condition::NotEquals(tag, value) => value_of(tag).is_some_and(|v| v != value),
+        }
+    }
+}
+
+/// The rule of an attribute in a module.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct AttributeRule {
+    /// The attribute tag.
+    pub tag: Tag,
+    /// The attribute type.
+    pub attribute_type: AttributeType,
+    /// The condition of a Type 1C or 2C attribute.
+    pub condition: Option<Condition>,
+    /// The allowed value multiplicity.
+    pub vm: Multiplicity,
+    /// The enumerated values, or empty if any value is allowed.
+    pub enumerated_values: &'static [&'static str],
+}
+
+impl AttributeRule {
+    /// Create a rule with the given attribute type
+    /// and a value multiplicity of 1.
+    pub const fn new(tag: Tag, attribute_type: AttributeType) -> Self {
+        AttributeRule {
+            tag,
+            attribute_type,
+            condition: None,
+            vm: Multiplicity::exactly(1),
+            enumerated_values: &[],
+        }
+    }
+
+    /// Create a Type 1 attribute rule.
+    pub const fn type1(tag: Tag) -> Self {
+        Self::new(tag, AttributeType::Type1)
+    }
+
+    /// Create a Type 2 attribute rule.
+    pub const fn type2(tag: Tag) -> Self {
+        Self::new(tag, AttributeType::Type2)
+    }
+
+    /// Create a Type 3 attribute rule.
+    pub const fn type3(tag: Tag) -> Self {
+        Self::new(tag, AttributeType::Type3)
+    }
+
+    /// Make the attribute required only under the given condition,
+    /// turning a Type 1 or 2 rule into Type 1C or 2C.
+    pub const fn when(self, condition: Condition) -> Self {
+        AttributeRule {
+            condition: Some(condition),
+            ..self
+        }
+    }
+
+    /// Set the allowed value multiplicity.
+    pub const fn vm(self, vm: Multiplicity) -> Self {
+        AttributeRule { vm, ..self }
+    }
+
+    /// Set the enumerated values.
+    pub const fn values(self, enumerated_values: &'static [&'static str]) -> Self {
+        AttributeRule {
+            enumerated_values,
+            ..self
+        }
+    }
+}
+
+/// A module of an IOD.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Module {
+    /// The name of the module.
+    pub name: &'static str,
+    /// The rules of the attributes in the module.
+    pub attributes: &'static [AttributeRule],
+}
+
+/// A composite Information Object Definition.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Iod {
+    /// The name of the IOD.
+    pub name: &'static str,
+    /// The SOP Class UIDs of the storage SOP classes using the IOD.
+    pub sop_class_uids: &'static [&'static str],
+    /// The mandatory modules of the IOD.
+    pub modules: &'static [Module],
+}
+
+/// The kind of a conformance finding.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub enum FindingKind {
+    /// A required attribute is missing.
+    Missing {
+        /// The type of the attribute.
+        attribute_type: AttributeType,
+        /// Whether the attribute is only required under a condition.
+        conditional: bool,
+    },
+    /// A Type 1 or 1C attribute is present but empty.
+    Empty {
+        /// Whether the attribute is only required under a condition.
+        conditional: bool,
+    },
+    /// The attribute does not have the VR of the data dictionary.
+    InvalidVr {
+        /// The VR in the data dictionary.
+        expected: VR,
+        /// The VR of the attribute.
+        found: VR,
+    },
+    /// The attribute has a number of values out of the allowed range.
+    InvalidVm {
+        /// The allowed value multiplicity.
+        expected: Multiplicity,
+        /// The number of values of the attribute.
+        found: u32,
+    },
+    /// The attribute has a value which is not one of its enumerated values.
+    InvalidValue {
+        /// The offending value.
+        value: String,
+    },
+}
+
+/// A problem found in an object,
+/// in an attribute of a module of its IOD.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Finding {
+    /// The name of the module.
+    pub module: &'static str,
+    /// The attribute tag.
+    pub tag: Tag,
+    /// What is wrong with the attribute.
+    pub kind: FindingKind,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = |conditional: bool| if conditional { "C" } else { "" };
+        write!(f, "{}: ", self.module)?;
+        match &self.kind {
+            FindingKind::Missing {
+                attribute_type,
+                conditional,
+            } => write!(
+                f,
+                "missing {}{} attribute {}",
+                attribute_type,
+                c(*conditional),
+                self.tag
+            ),
+            FindingKind::Empty { conditional } => {
+                write!(f, "empty Type 1{} attribute {}", c(*conditional), self.tag)
+            }
+            FindingKind::InvalidVr { expected, found } => write!(
+                f,
+                "attribute {} has VR {}, expected {}",
+                self.tag, found, expected
+            ),
+            FindingKind::InvalidVm { expected, found } => write!(
+                f,
+                "attribute {} has {} value(s), expected {}",
+                self.tag, found, expected
+            ),
+            FindingKind::InvalidValue { value } => write!(
+                f,
+                "attribute {} has value `{}`, which is not an enumerated value",
+                self.tag, value
+            ),
+        }
+    }
+}
+
+/// The result of checking an object against its IOD.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceReport {
+    iod: &'static Iod,
+    findings: Vec<Finding>,
+}
+
+impl ConformanceReport {
+    /// The IOD which the object was checked against.
+    pub fn iod(&self) -> &'static Iod {
+        self.iod
+    }
+
+    /// All findings, in order of module and attribute.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Check whether no problems were found.
+    pub fn is_conformant(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+/// Find the IOD for the given SOP Class UID.
+pub fn find_iod(sop_class_uid: &str) -> Option<&'static Iod> {
+    let uid = sop_class_uid.trim_end_matches(|c: char| c.is_whitespace() || c == '\0');
+    IODS.iter()
+        .copied()
+        .find(|iod| iod.sop_class_uids.contains(&uid))
+}
+
+/// Check the given object against the IOD of its SOP Class,
+/// as found in the _SOP Class UID_ attribute.
+///
+/// Fails if the SOP Class UID is missing
+/// or there is no IOD defined for it.
+pub fn check_conformance<D>(obj: &InMemDicomObject<D>) -> Result<ConformanceReport>
+where
+    D: DataDictionary + Clone,
+{
+    let uid = obj
+        .element_opt(tags::SOP_CLASS_UID)
+        .ok()
+        .flatten()
+        .and_then(|e| e.to_str().ok())
+        .context(MissingSopClassUidSnafu)?;
+    let iod = find_iod(&uid).context(UnsupportedSopClassSnafu { uid: uid.as_ref() })?;
+    Ok(iod.check(obj))
+}
+
+impl Iod {
+    /// Check the given object against the modules of this IOD.
+    ///
+    /// Attributes defined in more than one module
+    /// are only reported once for each problem.
+    pub fn check<D>(&'static self, obj: &InMemDicomObject<D>) -> ConformanceReport
+    where
+        D: DataDictionary + Clone,
+    {
+        let mut findings: Vec<Finding> = Vec::new();
+        for module in self.modules {
+            for rule in module.attributes {
+                for kind in check_rule(obj, rule) {
+                    if !findings.iter().any(|f| f.tag == rule.tag && f.kind == kind) {
+                        findings.push(Finding {
+                            module: module.name,
+                            tag: rule.tag,
+                            kind,
+                        });
+                    }
+                }
+            }
+        }
+        ConformanceReport {
+            iod: self,
+            findings,
+        }
+    }
+}
+
+fn check_rule<D>(obj: &InMemDicomObject<D>, rule: &AttributeRule) -> Vec<FindingKind>
+where
+    D: DataDictionary + Clone,
+{
+    let required = rule.attribute_type != AttributeType::Type3
+        && rule.condition.is_none_or(|c| c.applies(obj));
+    let conditional = rule.condition.is_some();
+
+    let elem = match obj.element_opt(rule.tag).ok().flatten() {
+        Some(elem) => elem,
+        None if required => {
+            return vec![FindingKind::Missing {
+                attribute_type: rule.attribute_type,
+                conditional,
+            }]
+        }
+        None => return Vec::new(),
+    };
+
+    let mut findings = Vec::new();
+    if let Some(entry) = StandardDataDictionary.by_tag(rule.tag) {
+        if !vr_compatible(entry.vr(), elem.vr()) {
+            findings.push(FindingKind::InvalidVr {
+                expected: entry.vr(),
+                found: elem.vr(),
+            });
+        }
+    }
+
+    let values = match elem.value() {
+        Value::Primitive(value) if is_textual(elem.vr()) || elem.vr() == VR::UN => value
+            .to_multi_str()
+            .iter()
+            .map(|v| normalize_text(elem.vr(), v).to_string())
+            .collect(),
+        Value::Primitive(value) if !is_binary(elem.vr()) => value.to_multi_str().into_owned(),
+        _ => Vec::new(),
+    };
+    let empty =
+        elem.is_empty_value() || (!values.is_empty() && values.iter().all(|v| v.is_empty()));
+    if empty {
+        if required && rule.attribute_type == AttributeType::Type1 {
+            findings.push(FindingKind::Empty { conditional });
+        }
+        return findings;
+    }
+    if matches!(elem.value(), Value::Primitive(_)) && !is_binary(elem.vr()) {
+        let found = values.len() as u32;
+        if !rule.vm.contains(found) {
+            findings.push(FindingKind::InvalidVm {
+                expected: rule.vm,
+                found,
+            });
+        }
+    }
+    if !rule.enumerated_values.is_empty() {
+        for value in values {
+            if !rule.enumerated_values.contains(&value.as_str()) {
+                findings.push(FindingKind::InvalidValue { value });
+            }
+        }
+    }
+    findings
+}
+
+fn is_binary(vr: VR) -> bool {
+    matches!(
+        vr,
+        VR::OB | VR::OD | VR::OF | VR::OL | VR::OV | VR::OW | VR::UN
+    )
+}
+
+/// Check whether an attribute of the given VR in the dictionary
+/// may be encoded with the given VR,
+/// including the VRs which depend on other attributes
+/// and the unknown VR.
+fn vr_compatible(expected: VR, found: VR) -> bool {
+    expected == found
+        || found == VR::UN
+        || matches!(
+            (expected, found),
+            (VR::US, VR::SS) | (VR::SS, VR::US) | (VR::OB, VR::OW) | (VR::OW, VR::OB)
+        )
+}
+
+const MONOCHROME: &[&str] = &["MONOCHROME1", "MONOCHROME2"];
+
+/// The Patient Module (PS3.3 C.7.1.1).
+pub const PATIENT: Module = Module {
+    name: "Patient",
+    attributes: &[
+        AttributeRule::type2(tags::PATIENT_NAME),
+        AttributeRule::type2(tags::PATIENT_ID),
+        AttributeRule::type2(tags::PATIENT_BIRTH_DATE),
+        AttributeRule::type2(tags::PATIENT_SEX).values(&["M", "F", "O"]),
+    ],
+};
+
+/// The General Study Module (PS3.3 C.7.2.1).
+pub const GENERAL_STUDY: Module = Module {
+    name: "General Study",
+    attributes: &[
+        AttributeRule::type1(tags::STUDY_INSTANCE_UID),
+        AttributeRule::type2(tags::STUDY_DATE),
+        AttributeRule::type2(tags::STUDY_TIME),
+        AttributeRule::type2(tags::REFERRING_PHYSICIAN_NAME),
+        AttributeRule::type2(tags::STUDY_ID),
+        AttributeRule::type2(tags::ACCESSION_NUMBER),
+    ],
+};
+
+/// The General Series Module (PS3.3 C.7.3.1).
+pub const GENERAL_SERIES: Module = Module {
+    name: "General Series",
+    attributes: &[
+        AttributeRule::type1(tags::MODALITY),
+        AttributeRule::type1(tags::SERIES_INSTANCE_UID),
+        AttributeRule::type2(tags::SERIES_NUMBER),
+        AttributeRule::type3(tags::LATERALITY).values(&["R", "L"]),
+    ],
+};
+
+/// The Frame of Reference Module (PS3.3 C.7.4.1).
+pub const FRAME_OF_REFERENCE: Module = Module {
+    name: "Frame of Reference",
+    attributes: &[
+        AttributeRule::type1(tags::FRAME_OF_REFERENCE_UID),
+        AttributeRule::type2(tags::POSITION_REFERENCE_INDICATOR),
+    ],
+};
+
+/// The General Equipment Module (PS3.3 C.7.5.1).
+pub const GENERAL_EQUIPMENT: Module = Module {
+    name: "General Equipment",
+    attributes: &[AttributeRule::type2(tags::MANUFACTURER)],
+};
+
+/// The General Image Module (PS3.3 C.7.6.1).
+pub const GENERAL_IMAGE: Module = Module {
+    name: "General Image",
+    attributes: &[
+        AttributeRule::type2(tags::INSTANCE_NUMBER),
+        AttributeRule::type2(tags::PATIENT_ORIENTATION)
+            .when(Condition::Absent(tags::IMAGE_ORIENTATION_PATIENT))
+            .vm(Multiplicity::exactly(2)),
+        AttributeRule::type3(tags::IMAGE_TYPE).vm(Multiplicity::at_least(2)),
+        AttributeRule::type3(tags::LOSSY_IMAGE_COMPRESSION).values(&["00", "01"]),
+    ],
+};
+
+/// The Image Plane Module (PS3.3 C.7.6.2).
+pub const IMAGE_PLANE: Module = Module {
+    name: "Image Plane",
+    attributes: &[
+        AttributeRule::type1(tags::PIXEL_SPACING).vm(Multiplicity::exactly(2)),
+        AttributeRule::type1(tags::IMAGE_ORIENTATION_PATIENT).vm(Multiplicity::exactly(6)),
+        AttributeRule::type1(tags::IMAGE_POSITION_PATIENT).vm(Multiplicity::exactly(3)),
+        AttributeRule::type2(tags::SLICE_THICKNESS),
+    ],
+};
+
+/// The Image Pixel Module (PS3.3 C.7.6.3).
+pub const IMAGE_PIXEL: Module = Module {
+    name: "Image Pixel",
+    attributes: &[
+        AttributeRule::type1(tags::SAMPLES_PER_PIXEL),
+        AttributeRule::type1(tags::PHOTOMETRIC_INTERPRETATION),
+        AttributeRule::type1(tags::ROWS),
+        AttributeRule::type1(tags::COLUMNS),
+        AttributeRule::type1(tags::BITS_ALLOCATED),
+        AttributeRule::type1(tags::BITS_STORED),
+        AttributeRule::type1(tags::HIGH_BIT),
+        AttributeRule::type1(tags::PIXEL_REPRESENTATION).values(&["0", "1"]),
+        AttributeRule::type1(tags::PLANAR_CONFIGURATION)
+            .when(Condition::NotEquals(tags::SAMPLES_PER_PIXEL, "1"))
+            .values(&["0", "1"]),
+        AttributeRule::type1(tags::PIXEL_DATA)
+            .when(Condition::Absent(tags::PIXEL_DATA_PROVIDER_URL)),
+    ],
+};
+
+/// The SOP Common Module (PS3.3 C.12.1).
+pub const SOP_COMMON: Module = Module {
+    name: "SOP Common",
+    attributes: &[
+        AttributeRule::type1(tags::SOP_CLASS_UID),
+        AttributeRule::type1(tags::SOP_INSTANCE_UID),
+    ],
+};
+
+/// The CT Image Module (PS3.3 C.8.2.1).
+pub const CT_IMAGE: Module = Module {
+    name: "CT Image",
+    attributes: &[
+        AttributeRule::type1(tags::IMAGE_TYPE).vm(Multiplicity::at_least(2)),
+        AttributeRule::type1(tags::SAMPLES_PER_PIXEL).values(&["1"]),
+        AttributeRule::type1(tags::PHOTOMETRIC_INTERPRETATION).values(MONOCHROME),
+        AttributeRule::type1(tags::BITS_ALLOCATED).values(&["16"]),
+        AttributeRule::type1(tags::BITS_STORED).values(&["12", "13", "14", "15", "16"]),
+        AttributeRule::type1(tags::HIGH_BIT).values(&["11", "12", "13", "14", "15"]),
+        AttributeRule::type1(tags::RESCALE_INTERCEPT),
+        AttributeRule::type1(tags::RESCALE_SLOPE),
+        AttributeRule::type2(tags::KVP),
+        AttributeRule::type2(tags::ACQUISITION_NUMBER),
+    ],
+};
+
+/// The MR Image Module (PS3.3 C.8.3.1).
+pub const MR_IMAGE: Module = Module {
+    name: "MR Image",
+    attributes: &[
+        AttributeRule::type1(tags::IMAGE_TYPE).vm(Multiplicity::at_least(2)),
+        AttributeRule::type1(tags::SAMPLES_PER_PIXEL).values(&["1"]),
+        AttributeRule::type1(tags::PHOTOMETRIC_INTERPRETATION).values(MONOCHROME),
+        AttributeRule::type1(tags::BITS_ALLOCATED).values(&["16"]),
+        AttributeRule::type1(tags::SCANNING_SEQUENCE)
+            .vm(Multiplicity::at_least(1))
+            .values(&["SE", "IR", "GR", "EP", "RM"]),
+        AttributeRule::type1(tags::SEQUENCE_VARIANT)
+            .vm(Multiplicity::at_least(1))
+            .values(&["SK", "MTC", "SS", "TRSS", "SP", "MP", "OSP", "NONE"]),
+        AttributeRule::type2(tags::SCAN_OPTIONS).vm(Multiplicity::at_least(1)),
+        AttributeRule::type2(tags::MR_ACQUISITION_TYPE).values(&["2D", "3D"]),
+        AttributeRule::type2(tags::REPETITION_TIME)
+            .when(Condition::NotEquals(tags::SCANNING_SEQUENCE, "EP")),
+        AttributeRule::type2(tags::ECHO_TIME),
+        AttributeRule::type2(tags::ECHO_TRAIN_LENGTH),
+        AttributeRule::type2(tags::INVERSION_TIME)
+            .when(Condition::Equals(tags::SCANNING_SEQUENCE, "IR")),
+    ],
+};
+
+/// The SC Equipment Module (PS3.3 C.8.6.1).
+pub const SC_EQUIPMENT: Module = Module {
+    name: "SC Equipment",
+    attributes: &[AttributeRule::type1(tags::CONVERSION_TYPE)
+        .values(&["DV", "DI", "DF", "WSD", "SD", "SI", "DRW", "SYN"])],
+};
+
+/// The US Image Module (PS3.3 C.8.5.6.1).
+pub const US_IMAGE: Module = Module {
+    name: "US Image",
+    attributes: &[
+        AttributeRule::type1(tags::SAMPLES_PER_PIXEL).values(&["1", "3"]),
+        AttributeRule::type1(tags::PHOTOMETRIC_INTERPRETATION).values(&[
+            "MONOCHROME2",
+            "PALETTE COLOR",
+            "RGB",
+            "YBR_FULL",
+            "YBR_FULL_422",
+            "YBR_PARTIAL_422",
+            "YBR_PARTIAL_420",
+            "YBR_ICT",
+            "YBR_RCT",
+        ]),
+        AttributeRule::type1(tags::BITS_ALLOCATED).values(&["8", "16"]),
+        AttributeRule::type1(tags::PIXEL_REPRESENTATION).values(&["0"]),
+        AttributeRule::type2(tags::IMAGE_TYPE).vm(Multiplicity::range(2, 4)),
+    ],
+};
+
+/// The CT Image IOD (PS3.3 A.3).
+pub static CT_IMAGE_IOD: Iod = Iod {
+    name: "CT Image",
+    sop_class_uids: &["1.2.840.10008.5.1.4.1.1.2"],
+    modules: &[
+        PATIENT,
+        GENERAL_STUDY,
+        GENERAL_SERIES,
+        FRAME_OF_REFERENCE,
+        GENERAL_EQUIPMENT,
+        GENERAL_IMAGE,
+        IMAGE_PLANE,
+        IMAGE_PIXEL,
+        CT_IMAGE,
+        SOP_COMMON,
+    ],
+};
+
+/// The MR Image IOD (PS3.3 A.4).
+pub static MR_IMAGE_IOD: Iod = Iod {
+    name: "MR Image",
+    sop_class_uids: &["1.2.840.10008.5.1.4.1.1.4"],
+    modules: &[
+        PATIENT,
+        GENERAL_STUDY,
+        GENERAL_SERIES,
+        FRAME_OF_REFERENCE,
+        GENERAL_EQUIPMENT,
+        GENERAL_IMAGE,
+        IMAGE_PLANE,
+        IMAGE_PIXEL,
+        MR_IMAGE,
+        SOP_COMMON,
+    ],
+};
+
+/// The Secondary Capture Image IOD (PS3.3 A.8.1).
+pub static SC_IMAGE_IOD: Iod = Iod {
+    name: "Secondary Capture Image",
+    sop_class_uids: &["1.2.840.10008.5.1.4.1.1.7"],
+    modules: &[
+        PATIENT,
+        GENERAL_STUDY,
+        GENERAL_SERIES,
+        SC_EQUIPMENT,
+        GENERAL_IMAGE,
+        IMAGE_PIXEL,
+        SOP_COMMON,
+    ],
+};
+
+/// The US Image IOD (PS3.3 A.6).
+pub static US_IMAGE_IOD: Iod = Iod {
+    name: "US Image",
+    sop_class_uids: &["1.2.840.10008.5.1.4.1.1.6.1"],
+    modules: &[
+        PATIENT,
+        GENERAL_STUDY,
+        GENERAL_SERIES,
+        GENERAL_EQUIPMENT,
+        GENERAL_IMAGE,
+        IMAGE_PIXEL,
+        US_IMAGE,
+        SOP_COMMON,
+    ],
+};
+
+/// All IODs defined in this module.
+pub static IODS: &[&Iod] = &[&CT_IMAGE_IOD, &MR_IMAGE_IOD, &SC_IMAGE_IOD, &US_IMAGE_IOD];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{dicom_value, DataElement, PrimitiveValue};
+
+    fn ct_image() -> InMemDicomObject {
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.2"),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.1"),
+            ),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.2"),
+            ),
+            DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.3"),
+            ),
+            DataElement::new(
+                tags::FRAME_OF_REFERENCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.4"),
+            ),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+            DataElement::new(tags::PATIENT_SEX, VR::CS, PrimitiveValue::from("F")),
+            DataElement::new(
+                tags::IMAGE_TYPE,
+                VR::CS,
+                dicom_value!(Strs, ["ORIGINAL", "PRIMARY", "AXIAL"]),
+            ),
+            DataElement::new(
+                tags::PIXEL_SPACING,
+                VR::DS,
+                dicom_value!(Strs, ["0.5", "0.5"]),
+            ),
+            DataElement::new(
+                tags::IMAGE_ORIENTATION_PATIENT,
+                VR::DS,
+                dicom_value!(Strs, ["1", "0", "0", "0", "1", "0"]),
+            ),
+            DataElement::new(
+                tags::IMAGE_POSITION_PATIENT,
+                VR::DS,
+                dicom_value!(Strs, ["0", "0", "0"]),
+            ),
+            DataElement::new(tags::SAMPLES_PER_PIXEL, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(
+                tags::PHOTOMETRIC_INTERPRETATION,
+                VR::CS,
+                PrimitiveValue::from("MONOCHROME2"),
+            ),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(2_u16)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(2_u16)),
+            DataElement::new(tags::BITS_ALLOCATED, VR::US, PrimitiveValue::from(16_u16)),
+            DataElement::new(tags::BITS_STORED, VR::US, PrimitiveValue::from(12_u16)),
+            DataElement::new(tags::HIGH_BIT, VR::US, PrimitiveValue::from(11_u16)),
+            DataElement::new(
+                tags::PIXEL_REPRESENTATION,
+                VR::US,
+                PrimitiveValue::from(1_u16),
+            ),
+            DataElement::new(
+                tags::RESCALE_INTERCEPT,
+                VR::DS,
+                PrimitiveValue::from("-1024"),
+            ),
+            DataElement::new(tags::RESCALE_SLOPE, VR::DS, PrimitiveValue::from("1")),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OW,
+                PrimitiveValue::U16(vec![0; 4].into()),
+            ),
+        ]);
+        for (tag, vr) in [
+            (tags::PATIENT_NAME, VR::PN),
+            (tags::PATIENT_ID, VR::LO),
+            (tags::PATIENT_BIRTH_DATE, VR::DA),
+            (tags::STUDY_DATE, VR::DA),
+            (tags::STUDY_TIME, VR::TM),
+            (tags::REFERRING_PHYSICIAN_NAME, VR::PN),
+            (tags::STUDY_ID, VR::SH),
+            (tags::ACCESSION_NUMBER, VR::SH),
+            (tags::SERIES_NUMBER, VR::IS),
+            (tags::POSITION_REFERENCE_INDICATOR, VR::LO),
+            (tags::MANUFACTURER, VR::LO),
+            (tags::INSTANCE_NUMBER, VR::IS),
+            (tags::SLICE_THICKNESS, VR::DS),
+            (tags::KVP, VR::DS),
+            (tags::ACQUISITION_NUMBER, VR::IS),
+        ] {
+            obj.put_empty(tag, vr);
+        }
+        obj
+    }
+
+    #[test]
+    fn conformant_ct_image() {
+        let report = check_conformance(&ct_image()).unwrap();
+        assert_eq!(report.iod(), &CT_IMAGE_IOD);
+        assert_eq!(report.findings(), &[]);
+        assert!(report.is_conformant());
+    }
+
+    #[test]
+    fn report_ct_image_findings() {
+        let mut obj = ct_image();
+        obj.remove_element(tags::MANUFACTURER);
+        obj.remove_element(tags::SAMPLES_PER_PIXEL);
+        obj.put_empty(tags::SERIES_INSTANCE_UID, VR::UI);
+        obj.put(DataElement::new(
+            tags::PIXEL_SPACING,
+            VR::DS,
+            PrimitiveValue::from("0.5"),
+        ));
+        obj.put(DataElement::new(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            VR::CS,
+            PrimitiveValue::from("RGB"),
+        ));
+        obj.put(DataElement::new(
+            tags::ROWS,
+            VR::UL,
+            PrimitiveValue::from(2_u32),
+        ));
+        // no longer required
+        obj.remove_element(tags::PIXEL_DATA);
+        obj.put(DataElement::new(
+            tags::PIXEL_DATA_PROVIDER_URL,
+            VR::UR,
+            PrimitiveValue::from("http://example.com/pixels"),
+        ));
+
+        let report = check_conformance(&obj).unwrap();
+        let findings: Vec<_> = report
+            .findings()
+            .iter()
+            .map(|f| (f.module, f.tag, f.kind.clone()))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    "General Series",
+                    tags::SERIES_INSTANCE_UID,
+                    FindingKind::Empty { conditional: false }
+                ),
+                (
+                    "General Equipment",
+                    tags::MANUFACTURER,
+                    FindingKind::Missing {
+                        attribute_type: AttributeType::Type2,
+                        conditional: false
+                    }
+                ),
+                (
+                    "Image Plane",
+                    tags::PIXEL_SPACING,
+                    FindingKind::InvalidVm {
+                        expected: Multiplicity::exactly(2),
+                        found: 1
+                    }
+                ),
+                (
+                    "Image Pixel",
+                    tags::SAMPLES_PER_PIXEL,
+                    FindingKind::Missing {
+                        attribute_type: AttributeType::Type1,
+                        conditional: false
+                    }
+                ),
+                (
+                    "Image Pixel",
+                    tags::ROWS,
+                    FindingKind::InvalidVr {
+                        expected: VR::US,
+                        found: VR::UL
+                    }
+                ),
+                (
+                    "CT Image",
+                    tags::PHOTOMETRIC_INTERPRETATION,
+                    FindingKind::InvalidValue {
+                        value: "RGB".to_string()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            report.findings()[2].to_string(),
+            "Image Plane: attribute (0028,0030) has 1 value(s), expected 2"
+        );
+    }
+
+    #[test]
+    fn conditional_attributes() {
+        let mut obj = ct_image();
+        // Patient Orientation is only required without Image Orientation
+        obj.remove_element(tags::IMAGE_ORIENTATION_PATIENT);
+        // Planar Configuration is required for color images
+        obj.put(DataElement::new(
+            tags::SAMPLES_PER_PIXEL,
+            VR::US,
+            PrimitiveValue::from(3_u16),
+        ));
+        let report = CT_IMAGE_IOD.check(&obj);
+        let kinds: Vec<_> = report.findings().iter().map(|f| (f.tag, &f.kind)).collect();
+        assert!(kinds.contains(&(
+            tags::PATIENT_ORIENTATION,
+            &FindingKind::Missing {
+                attribute_type: AttributeType::Type2,
+                conditional: true
+            }
+        )));
+        assert!(kinds.contains(&(
+            tags::PLANAR_CONFIGURATION,
+            &FindingKind::Missing {
+                attribute_type: AttributeType::Type1,
+                conditional: true
+            }
+        )));
+        assert!(kinds.contains(&(
+            tags::SAMPLES_PER_PIXEL,
+            &FindingKind::InvalidValue {
+                value: "3".to_string()
+            }
+        )));
+    }
+
+    #[test]
+    fn find_iod_by_sop_class() {
+        assert_eq!(
+            find_iod("1.2.840.10008.5.1.4.1.1.4\0").map(|iod| iod.name),
+            Some("MR Image")
+        );
+        assert_eq!(
+            find_iod("1.2.840.10008.5.1.4.1.1.6.1").map(|iod| iod.name),
+            Some("US Image")
+        );
+        assert!(find_iod("1.2.3.4").is_none());
+
+        assert!(matches!(
+            check_conformance(&InMemDicomObject::new_empty()),
+            Err(Error::MissingSopClassUid { .. })
+        ));
+    }
+}
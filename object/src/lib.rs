@@ -25,13 +25,18 @@
 //!
 //! The current default implementation places the full DICOM object in memory.
 //! The pixel data and following elements can be ignored
-//! by using [`OpenFileOptions`]:
+//! by using [`OpenFileOptions`],
+//! which also gathers the other options for reading a file,
+//! such as the assumed character set
+//! and how deviations from the standard are handled:
 //!
 //! ```no_run
+//! use dicom_object::file::ReadMode;
 //! use dicom_object::OpenFileOptions;
 //!
 //! let obj = OpenFileOptions::new()
-//!     .read_until(dicom_dictionary_std::tags::PIXEL_DATA)
+//!     .skip_pixel_data(true)
+//!     .mode(ReadMode::Lenient)
 //!     .open_file("0002.dcm")?;
 //! # Result::<(), dicom_object::Error>::Ok(())
 //! ```
@@ -108,21 +113,51 @@
 //! # }
 //! # run().unwrap();
 //! ```
+pub mod anonymize;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod bulk;
+pub mod compact;
+pub mod derived;
+pub mod encryption;
+pub mod equipment;
 pub mod file;
+pub mod fingerprint;
+pub mod hierarchy;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod ingest;
+pub mod instance;
+pub mod iod;
+pub mod matching;
 pub mod mem;
 pub mod meta;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod model;
+pub mod modification;
+pub mod patch;
+pub mod path;
 #[deprecated(
     since = "0.5.0",
     note = "This is a stub, use the `dicom-pixeldata` crate instead"
 )]
 pub mod pixeldata;
+pub mod progress;
+pub mod reinterpret;
 pub mod tokens;
+pub mod uid;
+pub mod validation;
+pub mod walk;
+pub mod xml;
 
+mod original;
 mod util;
 
-pub use crate::file::{from_reader, open_file, OpenFileOptions};
+pub use crate::file::{from_reader, open_file, OpenFileOptions, WriteOptions};
 pub use crate::mem::InMemDicomObject;
 pub use crate::meta::{FileMetaTable, FileMetaTableBuilder};
+pub use crate::path::TagPath;
 use dicom_core::DataDictionary;
 pub use dicom_core::Tag;
 pub use dicom_dictionary_std::StandardDataDictionary;
@@ -130,9 +165,12 @@ pub use dicom_dictionary_std::StandardDataDictionary;
 /// The default implementation of a root DICOM object.
 pub type DefaultDicomObject<D = StandardDataDictionary> = FileDicomObject<mem::InMemDicomObject<D>>;
 
+use crate::file::is_tiff_header;
+use crate::tokens::{ExplicitLengthTokens, GroupLengthStrategy, GroupLengthTokens};
 use dicom_core::header::Header;
 use dicom_encoding::adapters::{PixelDataObject, RawPixelData};
 use dicom_encoding::{text::SpecificCharacterSet, transfer_syntax::TransferSyntaxIndex};
+use dicom_parser::dataset::adapt::AdaptedWriter;
 use dicom_parser::dataset::{DataSetWriter, IntoTokens};
 use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
 use smallvec::SmallVec;
@@ -198,6 +236,11 @@ pub enum Error {
         backtrace: Backtrace,
         source: std::io::Error,
     },
+    /// Could not read data set bytes
+    ReadDataSetBytes {
+        backtrace: Backtrace,
+        source: std::io::Error,
+    },
     #[snafu(display("Could not parse meta group data set"))]
     ParseMetaDataSet {
         #[snafu(backtrace)]
@@ -213,6 +256,11 @@ pub enum Error {
         #[snafu(backtrace)]
         source: dicom_parser::dataset::read::Error,
     },
+    #[snafu(display("Could not read data set with bulk data"))]
+    BulkData {
+        #[snafu(backtrace)]
+        source: crate::bulk::Error,
+    },
     #[snafu(display("Could not write to file '{}'", filename.display()))]
     WriteFile {
         filename: std::path::PathBuf,
@@ -244,8 +292,17 @@ pub enum Error {
         #[snafu(backtrace)]
         source: dicom_parser::dataset::write::Error,
     },
+    #[snafu(display("Could not write data set"))]
+    WriteDataSet {
+        backtrace: Backtrace,
+        source: std::io::Error,
+    },
     #[snafu(display("Unsupported transfer syntax `{}`", uid))]
     UnsupportedTransferSyntax { uid: String, backtrace: Backtrace },
+    /// Could not detect the transfer syntax of a raw data set
+    UndetectedTransferSyntax { backtrace: Backtrace },
+    /// Operation cancelled
+    Cancelled { backtrace: Backtrace },
     #[snafu(display("No such data element with tag {}", tag))]
     NoSuchDataElementTag { tag: Tag, backtrace: Backtrace },
     #[snafu(display("No such data element {} (with tag {})", alias, tag))]
@@ -254,8 +311,35 @@ pub enum Error {
         alias: String,
         backtrace: Backtrace,
     },
+    #[snafu(display("No item #{} in sequence {}", index, tag))]
+    NoSuchItem {
+        tag: Tag,
+        index: u32,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("No such data element at {}", path))]
+    NoSuchDataElementPath { path: TagPath, backtrace: Backtrace },
+    #[snafu(display("Invalid tag path"))]
+    InvalidTagPath {
+        #[snafu(backtrace)]
+        source: crate::path::ParseTagPathError,
+    },
+    #[snafu(display("Data element {} is not a sequence", tag))]
+    NotASequence { tag: Tag, backtrace: Backtrace },
     #[snafu(display("Unknown data attribute named `{}`", name))]
     NoSuchAttributeName { name: String, backtrace: Backtrace },
+    #[snafu(display("Unknown data attribute with tag {}", tag))]
+    NoSuchAttributeTag { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Group {:04X} is not a private group", group))]
+    NotPrivateGroup { group: u16, backtrace: Backtrace },
+    #[snafu(display("No private creator `{}` in group {:04X}", creator, group))]
+    NoSuchPrivateCreator {
+        group: u16,
+        creator: String,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("No free private block in group {:04X}", group))]
+    NoFreePrivateBlock { group: u16, backtrace: Backtrace },
     #[snafu(display("Missing element value"))]
     MissingElementValue { backtrace: Backtrace },
     #[snafu(display("Unexpected token {:?}", token))]
@@ -265,6 +349,19 @@ pub enum Error {
     },
     #[snafu(display("Premature data set end"))]
     PrematureEnd { backtrace: Backtrace },
+    #[snafu(display("Data element {} repeats a previous element", tag))]
+    DuplicateTag { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Data element {} comes after data element {}", tag, previous))]
+    TagOutOfOrder {
+        tag: Tag,
+        previous: Tag,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Deviation from the standard: {}", warning))]
+    Deviation {
+        warning: dicom_parser::dataset::read::ReadWarning,
+        backtrace: Backtrace,
+    },
     /// Could not build file meta table
     BuildMetaTable {
         #[snafu(backtrace)]
@@ -275,6 +372,42 @@ pub enum Error {
         source: dicom_core::value::CastValueError,
         backtrace: Backtrace,
     },
+    #[snafu(display("Could not retrieve value of element {}", tag))]
+    CastValue {
+        tag: Tag,
+        source: dicom_core::value::CastValueError,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not convert value of element {}", tag))]
+    ConvertValue {
+        tag: Tag,
+        source: dicom_core::value::ConvertValueError,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not retrieve value of element at {}", path))]
+    CastValueAt {
+        path: TagPath,
+        source: dicom_core::value::CastValueError,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not convert value of element at {}", path))]
+    ConvertValueAt {
+        path: Box<TagPath>,
+        #[snafu(source(from(dicom_core::value::ConvertValueError, Box::from)))]
+        source: Box<dicom_core::value::ConvertValueError>,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Invalid timezone offset `{}`", value))]
+    InvalidTimezoneOffset {
+        value: String,
+        #[snafu(backtrace)]
+        source: dicom_core::value::DeserializeError,
+    },
+    /// Could not combine date and time into a date-time
+    CombineDateTime {
+        #[snafu(backtrace)]
+        source: dicom_core::value::partial::Error,
+    },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -287,10 +420,28 @@ pub type RootDicomObject<O> = FileDicomObject<O>;
 /// A root DICOM object retrieved from a standard DICOM file,
 /// containing additional information from the file meta group
 /// in a separate table value.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// A TIFF header kept in the preamble refers to offsets in the rest of the file,
+/// so it is only written back while the object has not been modified
+/// through [`meta_mut`](FileDicomObject::meta_mut) or mutable dereferencing.
+/// Otherwise, a zeroed preamble is written in its place.
+#[derive(Debug, Clone)]
 pub struct FileDicomObject<O> {
     meta: FileMetaTable,
     obj: O,
+    preamble: [u8; 128],
+    /// whether the meta group or the data set
+    /// may have changed since the preamble was set
+    modified: bool,
+}
+
+impl<O> PartialEq for FileDicomObject<O>
+where
+    O: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.meta == other.meta && self.obj == other.obj && self.preamble == other.preamble
+    }
 }
 
 impl<O> FileDicomObject<O> {
@@ -304,13 +455,54 @@ impl<O> FileDicomObject<O> {
     /// Considerable care should be taken when modifying this table,
     /// as it may influence object reading and writing operations.
     pub fn meta_mut(&mut self) -> &mut FileMetaTable {
+        self.modified = true;
         &mut self.meta
     }
 
+    /// Retrieve the 128-byte file preamble.
+    ///
+    /// This is the preamble read from the source,
+    /// or all zeros if the source did not have one.
+    /// Files with a dual personality,
+    /// such as whole slide images which are also valid TIFF files,
+    /// keep the TIFF header in the preamble.
+    pub fn preamble(&self) -> &[u8; 128] {
+        &self.preamble
+    }
+
+    /// Replace the 128-byte file preamble to write before the magic code.
+    ///
+    /// The new preamble is assumed to describe the current contents,
+    /// so a TIFF header set here is written
+    /// even if the object was modified before.
+    pub fn set_preamble(&mut self, preamble: [u8; 128]) {
+        self.preamble = preamble;
+        self.modified = false;
+    }
+
+    /// Check whether the file preamble starts with a TIFF header,
+    /// meaning that the file is both a DICOM file and a TIFF file.
+    pub fn has_tiff_preamble(&self) -> bool {
+        is_tiff_header(&self.preamble)
+    }
+
     /// Retrieve the inner DICOM object structure, discarding the meta table.
     pub fn into_inner(self) -> O {
         self.obj
     }
+
+    /// The preamble to write before the magic code,
+    /// leaving out a TIFF header which may no longer match the contents.
+    fn preamble_to_write(&self) -> [u8; 128] {
+        if self.modified && self.has_tiff_preamble() {
+            tracing::warn!(
+                "Object was modified, writing a zeroed preamble instead of the TIFF header"
+            );
+            [0; 128]
+        } else {
+            self.preamble
+        }
+    }
 }
 
 impl<O> FileDicomObject<O>
@@ -322,12 +514,25 @@ where
     /// Preamble, magic code, and file meta group will be included
     /// before the inner object.
     pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_to_file_with_options(path, &WriteOptions::default())
+    }
+
+    /// Write the entire object as a DICOM file
+    /// into the given file path,
+    /// using the given writing options.
+    /// Preamble, magic code, and file meta group will be included
+    /// before the inner object.
+    pub fn write_to_file_with_options<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<()> {
         let path = path.as_ref();
         let file = File::create(path).context(WriteFileSnafu { filename: path })?;
         let mut to = BufWriter::new(file);
 
         // write preamble
-        to.write_all(&[0_u8; 128][..])
+        to.write_all(&self.preamble_to_write())
             .context(WriteFileSnafu { filename: path })?;
 
         // write magic sequence
@@ -335,24 +540,10 @@ where
             .context(WriteFileSnafu { filename: path })?;
 
         // write meta group
-        self.meta.write(&mut to).context(PrintMetaDataSetSnafu)?;
-
-        // prepare encoder
-        let registry = TransferSyntaxRegistry::default();
-        let ts = registry.get(&self.meta.transfer_syntax).with_context(|| {
-            UnsupportedTransferSyntaxSnafu {
-                uid: self.meta.transfer_syntax.clone(),
-            }
-        })?;
-        let cs = SpecificCharacterSet::Default;
-        let mut dset_writer = DataSetWriter::with_ts_cs(to, ts, cs).context(CreatePrinterSnafu)?;
+        self.write_meta_impl(&mut to, options)?;
 
         // write object
-        dset_writer
-            .write_sequence((&self.obj).into_tokens())
-            .context(PrintDataSetSnafu)?;
-
-        Ok(())
+        self.write_dataset_impl(to, options)
     }
 
     /// Write the entire object as a DICOM file
@@ -360,33 +551,29 @@ where
     /// Preamble, magic code, and file meta group will be included
     /// before the inner object.
     pub fn write_all<W: Write>(&self, to: W) -> Result<()> {
+        self.write_all_with_options(to, &WriteOptions::default())
+    }
+
+    /// Write the entire object as a DICOM file
+    /// into the given writer,
+    /// using the given writing options.
+    /// Preamble, magic code, and file meta group will be included
+    /// before the inner object.
+    pub fn write_all_with_options<W: Write>(&self, to: W, options: &WriteOptions) -> Result<()> {
         let mut to = BufWriter::new(to);
 
         // write preamble
-        to.write_all(&[0_u8; 128][..]).context(WritePreambleSnafu)?;
+        to.write_all(&self.preamble_to_write())
+            .context(WritePreambleSnafu)?;
 
         // write magic sequence
         to.write_all(b"DICM").context(WriteMagicCodeSnafu)?;
 
         // write meta group
-        self.meta.write(&mut to).context(PrintMetaDataSetSnafu)?;
-
-        // prepare encoder
-        let registry = TransferSyntaxRegistry::default();
-        let ts = registry.get(&self.meta.transfer_syntax).with_context(|| {
-            UnsupportedTransferSyntaxSnafu {
-                uid: self.meta.transfer_syntax.clone(),
-            }
-        })?;
-        let cs = SpecificCharacterSet::Default;
-        let mut dset_writer = DataSetWriter::with_ts_cs(to, ts, cs).context(CreatePrinterSnafu)?;
+        self.write_meta_impl(&mut to, options)?;
 
         // write object
-        dset_writer
-            .write_sequence((&self.obj).into_tokens())
-            .context(PrintDataSetSnafu)?;
-
-        Ok(())
+        self.write_dataset_impl(to, options)
     }
 
     /// Write the file meta group set into the given writer.
@@ -401,8 +588,33 @@ where
     ///
     /// The transfer syntax is selected from the file meta table.
     pub fn write_dataset<W: Write>(&self, to: W) -> Result<()> {
-        let to = BufWriter::new(to);
+        self.write_dataset_with_options(to, &WriteOptions::default())
+    }
+
+    /// Write the inner data set into the given writer,
+    /// without preamble, magic code, nor file meta group,
+    /// using the given writing options.
+    ///
+    /// The transfer syntax is selected from the file meta table.
+    pub fn write_dataset_with_options<W: Write>(
+        &self,
+        to: W,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        self.write_dataset_impl(BufWriter::new(to), options)
+    }
+
+    fn write_meta_impl<W: Write>(&self, to: W, options: &WriteOptions) -> Result<()> {
+        if options.get_group_length_strategy() == GroupLengthStrategy::Recalculate {
+            let mut meta = self.meta.clone();
+            meta.update_information_group_length();
+            meta.write(to).context(PrintMetaDataSetSnafu)
+        } else {
+            self.meta.write(to).context(PrintMetaDataSetSnafu)
+        }
+    }
 
+    fn write_dataset_impl<W: Write>(&self, to: W, options: &WriteOptions) -> Result<()> {
         // prepare encoder
         let registry = TransferSyntaxRegistry::default();
         let ts = registry.get(&self.meta.transfer_syntax).with_context(|| {
@@ -411,12 +623,29 @@ where
             }
         })?;
         let cs = SpecificCharacterSet::Default;
-        let mut dset_writer = DataSetWriter::with_ts_cs(to, ts, cs).context(CreatePrinterSnafu)?;
+        // apply the data set codec of the transfer syntax, if any
+        let mut to = match options.get_compression_level() {
+            Some(level) => AdaptedWriter::with_compression_level(to, ts, level),
+            None => AdaptedWriter::new(to, ts),
+        };
+        let mut dset_writer =
+            DataSetWriter::with_ts_cs(&mut to, ts, cs).context(CreatePrinterSnafu)?;
 
         // write object
-        dset_writer
-            .write_sequence((&self.obj).into_tokens())
-            .context(PrintDataSetSnafu)?;
+        let tokens = ExplicitLengthTokens::new(
+            (&self.obj).into_tokens(),
+            ts,
+            cs,
+            options.get_explicit_length_sq_item_strategy(),
+        );
+        let tokens = GroupLengthTokens::new(tokens, ts, cs, options.get_group_length_strategy());
+        for token in tokens {
+            dset_writer
+                .write(token.context(PrintDataSetSnafu)?)
+                .context(PrintDataSetSnafu)?;
+        }
+        drop(dset_writer);
+        to.finish().context(WriteDataSetSnafu)?;
 
         Ok(())
     }
@@ -432,6 +661,7 @@ impl<O> ::std::ops::Deref for FileDicomObject<O> {
 
 impl<O> ::std::ops::DerefMut for FileDicomObject<O> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.modified = true;
         &mut self.obj
     }
 }
@@ -544,6 +774,39 @@ where
             .ok()
     }
 
+    /// Return the BitsStored attribute or None if it is not set
+    fn bits_stored(&self) -> Option<u16> {
+        self.element(dicom_dictionary_std::tags::BITS_STORED)
+            .ok()?
+            .uint16()
+            .ok()
+    }
+
+    /// Return the PixelRepresentation attribute or None if it is not set
+    fn pixel_representation(&self) -> Option<u16> {
+        self.element(dicom_dictionary_std::tags::PIXEL_REPRESENTATION)
+            .ok()?
+            .uint16()
+            .ok()
+    }
+
+    /// Return the PlanarConfiguration attribute or None if it is not set
+    fn planar_configuration(&self) -> Option<u16> {
+        self.element(dicom_dictionary_std::tags::PLANAR_CONFIGURATION)
+            .ok()?
+            .uint16()
+            .ok()
+    }
+
+    /// Return the PhotometricInterpretation attribute or None if it is not set
+    fn photometric_interpretation(&self) -> Option<&str> {
+        self.element(dicom_dictionary_std::tags::PHOTOMETRIC_INTERPRETATION)
+            .ok()?
+            .string()
+            .ok()
+            .map(|s| s.trim_end_matches(|c: char| c.is_whitespace() || c == '\0'))
+    }
+
     /// Return the NumberOfFrames attribute or None if it is not set
     fn number_of_frames(&self) -> Option<u16> {
         self.element(dicom_dictionary_std::tags::NUMBER_OF_FRAMES)
@@ -604,6 +867,28 @@ where
             dicom_core::DicomValue::Sequence { items: _, size: _ } => None,
         }
     }
+
+    /// Return the frame offsets from the _Extended Offset Table_,
+    /// or from the basic offset table if it is absent.
+    /// Returns None if there is no offset table
+    fn frame_offsets(&self) -> Option<Vec<u64>> {
+        if let Some(table) = self
+            .element_opt(dicom_dictionary_std::tags::EXTENDED_OFFSET_TABLE)
+            .ok()
+            .flatten()
+        {
+            return table.to_multi_int::<u64>().ok();
+        }
+        let pixel_data = self.element(dicom_dictionary_std::tags::PIXEL_DATA).ok()?;
+        match pixel_data.value() {
+            dicom_core::DicomValue::PixelSequence { offset_table, .. }
+                if !offset_table.is_empty() =>
+            {
+                Some(offset_table.iter().map(|&o| u64::from(o)).collect())
+            }
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -637,6 +922,627 @@ mod tests {
         let _ = std::fs::remove_file(FILE_NAME);
     }
 
+    /// A file in Deflated Explicit VR Little Endian
+    /// is written with a compressed data set
+    /// and can be read back.
+    #[test]
+    fn deflated_file_roundtrip() {
+        use crate::WriteOptions;
+
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(64);
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                dicom_dictionary_std::tags::PATIENT_NAME,
+                VR::PN,
+                PrimitiveValue::from("Doe^John"),
+            ),
+            DataElement::new(
+                dicom_dictionary_std::tags::TEXT_VALUE,
+                VR::UT,
+                PrimitiveValue::from(text.as_str()),
+            ),
+        ]);
+        let obj = obj
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.88.11")
+                    .media_storage_sop_instance_uid("1.2.23456789")
+                    .transfer_syntax(
+                        dicom_transfer_syntax_registry::entries::DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN
+                            .uid(),
+                    ),
+            )
+            .unwrap();
+
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+
+        // data set is compressed
+        assert!(data.len() < text.len());
+        assert!(!data.windows(8).any(|w| w == b"Doe^John"));
+
+        let obj2 = FileDicomObject::from_reader(&data[128..]).unwrap();
+        assert_eq!(obj2.meta().transfer_syntax(), "1.2.840.10008.1.2.1.99");
+        assert_eq!(
+            obj2.element(dicom_dictionary_std::tags::PATIENT_NAME)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Doe^John",
+        );
+        assert_eq!(
+            obj2.element(dicom_dictionary_std::tags::TEXT_VALUE)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .trim_end(),
+            text.trim_end(),
+        );
+
+        // the compression level can be chosen
+        let options = WriteOptions::new().compression_level(0);
+        let mut stored = Vec::new();
+        obj.write_all_with_options(&mut stored, &options).unwrap();
+        assert!(stored.len() > text.len());
+        assert!(stored.windows(8).any(|w| w == b"Doe^John"));
+        let obj3 = FileDicomObject::from_reader(&stored[128..]).unwrap();
+        assert_eq!(
+            obj3.element(dicom_dictionary_std::tags::PATIENT_NAME)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Doe^John",
+        );
+
+        let options = WriteOptions::new().compression_level(9);
+        let mut best = Vec::new();
+        obj.write_all_with_options(&mut best, &options).unwrap();
+        assert!(best.len() <= data.len());
+    }
+
+    /// A deflated data set is written to a plain writer
+    /// as it is compressed, in a single pass.
+    #[test]
+    fn deflated_file_streams_to_writer() {
+        /// A writer which only keeps the size of each write
+        #[derive(Default)]
+        struct ChunkSizes(Vec<usize>);
+
+        impl std::io::Write for ChunkSizes {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // poorly compressible data
+        let mut state = 0x2545_f491_u32;
+        let data: Vec<u8> = (0..1_000_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let obj = InMemDicomObject::from_element_iter([DataElement::new(
+            dicom_core::Tag(0x0009, 0x1001),
+            VR::OB,
+            PrimitiveValue::from(data),
+        )])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.23456789")
+                .transfer_syntax(
+                    dicom_transfer_syntax_registry::entries::DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN
+                        .uid(),
+                ),
+        )
+        .unwrap();
+
+        let mut sizes = ChunkSizes::default();
+        obj.write_all(&mut sizes).unwrap();
+        let total: usize = sizes.0.iter().sum();
+        assert!(total > 900_000);
+        // no single write carries the whole data set
+        assert!(sizes.0.iter().all(|&size| size < total / 2));
+    }
+
+    /// A file read with a progress observer
+    /// reports all of its bytes, and can be cancelled.
+    #[test]
+    fn read_file_with_progress() {
+        use crate::progress::{ProgressControl, ProgressStatus};
+        use crate::OpenFileOptions;
+
+        let obj = InMemDicomObject::from_element_iter([DataElement::new(
+            dicom_dictionary_std::tags::PIXEL_DATA,
+            VR::OB,
+            PrimitiveValue::from(vec![0x55_u8; 32_000]),
+        )])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.23456789")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+        let len = data.len() as u64;
+
+        let mut last = ProgressStatus::default();
+        let obj2 = OpenFileOptions::new()
+            .from_reader_with_progress(&data[..], Some(len), |status: &ProgressStatus| {
+                last = *status;
+                ProgressControl::Continue
+            })
+            .unwrap();
+        assert_eq!(obj2.meta().transfer_syntax(), "1.2.840.10008.1.2.1");
+        assert_eq!(last.bytes_processed, len);
+        assert_eq!(last.bytes_total, Some(len));
+        assert_eq!(last.items_completed, 1);
+
+        let err = OpenFileOptions::new()
+            .from_reader_with_progress(&data[..], Some(len), |status: &ProgressStatus| {
+                if status.bytes_processed > 1024 {
+                    ProgressControl::Cancel
+                } else {
+                    ProgressControl::Continue
+                }
+            })
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled { .. }));
+    }
+
+    /// A slightly broken file can be read
+    /// while retrieving what is wrong with it.
+    #[test]
+    fn read_file_with_warnings() {
+        use crate::OpenFileOptions;
+        use dicom_core::Tag;
+        use dicom_parser::dataset::read::ReadWarning;
+
+        let obj = InMemDicomObject::from_element_iter([DataElement::new(
+            dicom_dictionary_std::tags::PATIENT_NAME,
+            VR::PN,
+            PrimitiveValue::from("Doe^John"),
+        )])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.23456789")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+        // (0008,0060) Modality, out of order and with an odd length
+        data.extend_from_slice(&[0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x01, 0x00, b'M']);
+
+        let (obj2, warnings) = OpenFileOptions::new()
+            .from_reader_with_warnings(&data[128..])
+            .unwrap();
+        assert_eq!(
+            obj2.element(dicom_dictionary_std::tags::MODALITY)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "M",
+        );
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            warnings[0],
+            ReadWarning::TagOutOfOrder {
+                tag: Tag(0x0008, 0x0060),
+                ..
+            }
+        ));
+        assert!(matches!(
+            warnings[1],
+            ReadWarning::OddValueLength { len: 1, .. }
+        ));
+
+        // well-formed files do not have warnings
+        let (_, warnings) = OpenFileOptions::new()
+            .from_reader_with_warnings(&data[128..data.len() - 9])
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    /// Elements with a repeated tag are handled according to the policy.
+    #[test]
+    fn read_file_with_duplicate_tags() {
+        use crate::file::DuplicateTagPolicy;
+        use crate::OpenFileOptions;
+        use dicom_core::Tag;
+        use dicom_dictionary_std::tags;
+        use dicom_parser::dataset::read::ReadWarning;
+
+        let obj = InMemDicomObject::new_empty()
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                    .media_storage_sop_instance_uid("1.2.23456789")
+                    .transfer_syntax("1.2.840.10008.1.2.1"),
+            )
+            .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+        #[rustfmt::skip]
+        let dataset: &[&[u8]] = &[
+            &[0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x02, 0x00], b"MR",
+            &[0x10, 0x00, 0x20, 0x00, b'L', b'O', 0x02, 0x00], b"ID",
+            &[0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x02, 0x00], b"CT",
+        ];
+        let meta_len = data.len();
+        data.extend(dataset.concat());
+
+        let read = |policy| {
+            OpenFileOptions::new()
+                .duplicate_tags(policy)
+                .from_reader_with_warnings(&data[128..])
+        };
+        let modality = |obj: &crate::DefaultDicomObject| {
+            obj.element(tags::MODALITY)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .into_owned()
+        };
+
+        let (obj, warnings) = read(DuplicateTagPolicy::KeepLast).unwrap();
+        assert_eq!(modality(&obj), "CT");
+        assert!(obj.duplicate_elements().is_empty());
+        assert_eq!(
+            warnings,
+            vec![ReadWarning::DuplicateTag {
+                tag: tags::MODALITY,
+                offset: 20,
+            }],
+        );
+
+        let (obj, _) = read(DuplicateTagPolicy::KeepFirst).unwrap();
+        assert_eq!(modality(&obj), "MR");
+
+        let (obj, _) = read(DuplicateTagPolicy::CollectAll).unwrap();
+        assert_eq!(modality(&obj), "CT");
+        assert_eq!(obj.duplicate_elements().len(), 1);
+        assert_eq!(obj.duplicate_elements()[0].to_str().unwrap(), "MR");
+
+        assert!(matches!(
+            read(DuplicateTagPolicy::Fail),
+            Err(Error::DuplicateTag {
+                tag: Tag(0x0008, 0x0060),
+                ..
+            })
+        ));
+
+        // tags out of order are sorted, unless failing
+        let data = [&data[..meta_len], &dataset[2..].concat()].concat();
+        let (obj, warnings) = OpenFileOptions::new()
+            .from_reader_with_warnings(&data[128..])
+            .unwrap();
+        assert_eq!(modality(&obj), "CT");
+        assert!(matches!(warnings[..], [ReadWarning::TagOutOfOrder { .. }]));
+        assert!(matches!(
+            OpenFileOptions::new()
+                .duplicate_tags(DuplicateTagPolicy::Fail)
+                .from_reader_with_warnings(&data[128..]),
+            Err(Error::TagOutOfOrder {
+                tag: Tag(0x0008, 0x0060),
+                previous: Tag(0x0010, 0x0020),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn read_and_write_with_original_encoding() {
+        use crate::OpenFileOptions;
+        use dicom_core::Tag;
+        use dicom_dictionary_std::tags;
+
+        let obj = InMemDicomObject::new_empty()
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                    .media_storage_sop_instance_uid("1.2.23456789")
+                    .transfer_syntax("1.2.840.10008.1.2.1"),
+            )
+            .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+        #[rustfmt::skip]
+        let dataset: &[&[u8]] = &[
+            &[0x08, 0x00, 0x05, 0x00, b'C', b'S', 0x0a, 0x00], b"ISO_IR 100",
+            &[0x08, 0x00, 0x16, 0x00, b'U', b'I', 0x04, 0x00], b"1.2\0",
+            &[0x08, 0x00, 0x18, 0x00, b'U', b'I', 0x04, 0x00], b"1.2 ",
+            // ReferencedImageSequence, undefined length
+            &[0x08, 0x00, 0x40, 0x11, b'S', b'Q', 0x00, 0x00, 0xff, 0xff, 0xff, 0xff],
+            &[0xfe, 0xff, 0x00, 0xe0, 0xff, 0xff, 0xff, 0xff],
+            &[0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x02, 0x00], b"\xe9 ",
+            &[0xfe, 0xff, 0x0d, 0xe0, 0x00, 0x00, 0x00, 0x00],
+            &[0xfe, 0xff, 0xdd, 0xe0, 0x00, 0x00, 0x00, 0x00],
+            &[0x09, 0x00, 0x10, 0x00, b'L', b'O', 0x04, 0x00], b"ACME",
+            // private element of an odd length
+            &[0x09, 0x00, 0x01, 0x10, b'U', b'N', 0x00, 0x00, 0x03, 0x00, 0x00, 0x00], b"xyz",
+            // text in ISO-IR 100, with extra padding
+            &[0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x06, 0x00], b"Jos\xe9  ",
+            &[0x10, 0x00, 0x20, 0x00, b'L', b'O', 0x06, 0x00], b" AB\\  ",
+            &[0x18, 0x00, 0x50, 0x00, b'D', b'S', 0x06, 0x00], b"1.50  ",
+            &[0x20, 0x00, 0x13, 0x00, b'I', b'S', 0x02, 0x00], b" 3",
+            &[0x28, 0x00, 0x10, 0x00, b'U', b'S', 0x02, 0x00], &[0x01, 0x02],
+        ];
+        for chunk in dataset {
+            data.extend_from_slice(chunk);
+        }
+
+        // values are written back exactly as read
+        let obj = OpenFileOptions::new()
+            .preserve_encoding(true)
+            .from_reader(&data[..])
+            .unwrap();
+        let mut out = Vec::new();
+        obj.write_all(&mut out).unwrap();
+        assert_eq!(out, data);
+
+        // which is not the case by default
+        let obj = OpenFileOptions::new().from_reader(&data[..]).unwrap();
+        let mut out = Vec::new();
+        obj.write_all(&mut out).unwrap();
+        assert_ne!(out, data);
+
+        // modified values are encoded anew
+        let mut obj = OpenFileOptions::new()
+            .preserve_encoding(true)
+            .from_reader(&data[..])
+            .unwrap();
+        obj.put(DataElement::new(
+            Tag(0x0009, 0x1001),
+            VR::UN,
+            PrimitiveValue::from(b"xy".to_vec()),
+        ));
+        let mut out = Vec::new();
+        obj.write_all(&mut out).unwrap();
+        let pos = out.len() - (data.len() - 3);
+        assert_eq!(&out[..pos], &data[..pos]);
+
+        // changing the character set discards all original encodings
+        obj.put(DataElement::new(
+            tags::SPECIFIC_CHARACTER_SET,
+            VR::CS,
+            PrimitiveValue::from("ISO_IR 192"),
+        ));
+        let mut out = Vec::new();
+        obj.write_all(&mut out).unwrap();
+        let obj2 = OpenFileOptions::new().from_reader(&out[..]).unwrap();
+        assert_eq!(
+            obj2.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Jos\u{e9}",
+        );
+        let item = &obj2
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            item.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "\u{e9}",
+        );
+    }
+
+    #[test]
+    fn read_with_text_transcoded_to_utf8() {
+        use crate::OpenFileOptions;
+        use dicom_dictionary_std::tags;
+
+        let obj = InMemDicomObject::new_empty()
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                    .media_storage_sop_instance_uid("1.2.23456789")
+                    .transfer_syntax("1.2.840.10008.1.2.1"),
+            )
+            .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+        #[rustfmt::skip]
+        let dataset: &[&[u8]] = &[
+            &[0x08, 0x00, 0x05, 0x00, b'C', b'S', 0x0a, 0x00], b"ISO_IR 100",
+            // ReferencedImageSequence, undefined length
+            &[0x08, 0x00, 0x40, 0x11, b'S', b'Q', 0x00, 0x00, 0xff, 0xff, 0xff, 0xff],
+            &[0xfe, 0xff, 0x00, 0xe0, 0xff, 0xff, 0xff, 0xff],
+            &[0x08, 0x00, 0x05, 0x00, b'C', b'S', 0x0a, 0x00], b"ISO_IR 100",
+            &[0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x02, 0x00], b"\xe9 ",
+            &[0xfe, 0xff, 0x0d, 0xe0, 0x00, 0x00, 0x00, 0x00],
+            &[0xfe, 0xff, 0xdd, 0xe0, 0x00, 0x00, 0x00, 0x00],
+            &[0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x06, 0x00], b"Jos\xe9  ",
+        ];
+        for chunk in dataset {
+            data.extend_from_slice(chunk);
+        }
+
+        let obj = OpenFileOptions::new()
+            .preserve_encoding(true)
+            .transcode_to_utf8(true)
+            .from_reader(&data[..])
+            .unwrap();
+        let item = &obj
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        for charset in [
+            obj.element(tags::SPECIFIC_CHARACTER_SET).unwrap(),
+            item.element(tags::SPECIFIC_CHARACTER_SET).unwrap(),
+        ] {
+            assert_eq!(charset.to_str().unwrap(), "ISO_IR 192");
+        }
+        assert_eq!(
+            obj.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Jos\u{e9}",
+        );
+
+        // text is written as UTF-8
+        let mut out = Vec::new();
+        obj.write_all(&mut out).unwrap();
+        assert!(out.windows(5).any(|w| w == b"Jos\xc3\xa9"));
+        let obj2 = OpenFileOptions::new().from_reader(&out[..]).unwrap();
+        assert_eq!(
+            obj2.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Jos\u{e9}",
+        );
+    }
+
+    #[test]
+    fn read_with_file_options() {
+        use crate::file::ReadMode;
+        use crate::OpenFileOptions;
+        use dicom_dictionary_std::tags;
+        use dicom_encoding::text::SpecificCharacterSet;
+
+        let obj = InMemDicomObject::new_empty()
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                    .media_storage_sop_instance_uid("1.2.23456789")
+                    .transfer_syntax("1.2.840.10008.1.2.1"),
+            )
+            .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+        #[rustfmt::skip]
+        let dataset: &[&[u8]] = &[
+            // text in ISO-IR 100, without Specific Character Set
+            &[0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x06, 0x00], b"Jos\xe9  ",
+            &[0x28, 0x00, 0x10, 0x00, b'U', b'S', 0x02, 0x00], &[0x01, 0x00],
+            &[0xe0, 0x7f, 0x10, 0x00, b'O', b'B', 0x00, 0x00, 0x02, 0x00, 0x00, 0x00], &[0xff, 0xff],
+            // data set trailing padding
+            &[0xfc, 0xff, 0xfc, 0xff, b'O', b'B', 0x00, 0x00, 0x02, 0x00, 0x00, 0x00], &[0, 0],
+        ];
+        for chunk in dataset {
+            data.extend_from_slice(chunk);
+        }
+
+        let (obj, warnings) = OpenFileOptions::new()
+            .from_reader_with_warnings(&data[..])
+            .unwrap();
+        assert!(obj.element(tags::PIXEL_DATA).is_ok());
+        assert!(obj.element(tags::DATA_SET_TRAILING_PADDING).is_ok());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].tag(), Some(tags::PATIENT_NAME));
+
+        // reading ends before the pixel data
+        let obj = OpenFileOptions::new()
+            .skip_pixel_data(true)
+            .charset(SpecificCharacterSet::IsoIr100)
+            .from_reader(&data[..])
+            .unwrap();
+        assert_eq!(
+            obj.tags().collect::<Vec<_>>(),
+            [tags::PATIENT_NAME, tags::ROWS]
+        );
+        assert_eq!(
+            obj.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Jos\u{e9}"
+        );
+        let obj = OpenFileOptions::new()
+            .read_until(tags::ROWS)
+            .skip_pixel_data(true)
+            .from_reader(&data[..])
+            .unwrap();
+        assert_eq!(obj.tags().collect::<Vec<_>>(), [tags::PATIENT_NAME]);
+
+        // deviations are errors in strict mode
+        let err = OpenFileOptions::new()
+            .mode(ReadMode::Strict)
+            .from_reader(&data[..])
+            .unwrap_err();
+        assert!(matches!(err, Error::Deviation { .. }));
+        OpenFileOptions::new()
+            .mode(ReadMode::Strict)
+            .charset(SpecificCharacterSet::IsoIr100)
+            .from_reader(&data[..])
+            .unwrap();
+    }
+
+    /// Image pixel attributes are available
+    /// through the pixel data object API.
+    #[test]
+    fn pixel_data_object_attributes() {
+        use dicom_dictionary_std::tags;
+        use dicom_encoding::adapters::PixelDataObject;
+
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::PHOTOMETRIC_INTERPRETATION,
+                VR::CS,
+                PrimitiveValue::from("YBR_FULL "),
+            ),
+            DataElement::new(
+                tags::PLANAR_CONFIGURATION,
+                VR::US,
+                PrimitiveValue::from(1_u16),
+            ),
+            DataElement::new(
+                tags::PIXEL_REPRESENTATION,
+                VR::US,
+                PrimitiveValue::from(0_u16),
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.23456789")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap();
+        assert_eq!(obj.photometric_interpretation(), Some("YBR_FULL"));
+        assert_eq!(obj.planar_configuration(), Some(1));
+        assert_eq!(obj.pixel_representation(), Some(0));
+
+        obj.remove_element(tags::PHOTOMETRIC_INTERPRETATION);
+        assert_eq!(obj.photometric_interpretation(), None);
+    }
+
+    /// Frame offsets come from the extended offset table
+    /// if present, then from the basic offset table.
+    #[test]
+    fn frame_offsets_prefer_extended_offset_table() {
+        use dicom_dictionary_std::tags;
+        use dicom_encoding::adapters::PixelDataObject;
+
+        let mut obj = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OB,
+            dicom_core::value::Value::PixelSequence {
+                offset_table: vec![0, 12].into(),
+                fragments: vec![vec![1, 2, 3, 4], vec![5, 6]].into(),
+            },
+        )])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.23456789")
+                .transfer_syntax("1.2.840.10008.1.2.4.50"),
+        )
+        .unwrap();
+        assert_eq!(obj.frame_offsets(), Some(vec![0, 12]));
+
+        obj.put(DataElement::new(
+            tags::EXTENDED_OFFSET_TABLE,
+            VR::OV,
+            PrimitiveValue::U64(vec![0, 0x1_0000_0000].into()),
+        ));
+        assert_eq!(obj.frame_offsets(), Some(vec![0, 0x1_0000_0000]));
+    }
+
     /// A FileDicomObject<InMemDicomObject>
     /// can be used like a DICOM object.
     #[test]
@@ -0,0 +1,1054 @@
+//! Query attribute matching.
+//!
+//! This module implements the attribute matching semantics
+//! of the Query/Retrieve service (PS3.4 C.2.2.2)
+//! over in-memory DICOM objects.
+//! A [`QueryMatcher`] takes an identifier data set,
+//! as received in a C-FIND request,
+//! and evaluates whether candidate objects match it.
+//!
+//! The following kinds of matching are currently supported:
+//!
+//! - universal matching, for keys with an empty value
+//!   or a single `*`;
+//! - single value matching, following the equality semantics
+//!   of the attribute's value representation
+//!   (see [`dicom_core::value::equality`]);
+//! - wildcard matching with `*` and `?`,
+//!   for keys of textual value representations
+//!   such as LO, PN and SH;
+//! - list of UID matching, for UI keys with multiple values;
+//! - sequence matching, for sequence keys with one item,
+//!   which match if any item of the candidate's sequence
+//!   matches all keys in that item;
+//! - range matching of DA, TM and DT attributes;
+//! - combined date-time range matching of pairs of DA and TM attributes,
+//!   such as _Study Date_ and _Study Time_;
+//! - timezone query adjustment,
+//!   based on _Timezone Offset From UTC_ (0008,0201)
+//!   in both the identifier and the candidate objects.
+//!
+//! Hierarchical query identifiers can be checked with
+//! [`QueryMatcher::validate`].
+//! Relational queries lift these restrictions.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::matching::{MatchOptions, QueryMatcher};
+//!
+//! let identifier = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::QUERY_RETRIEVE_LEVEL, VR::CS, PrimitiveValue::from("STUDY")),
+//!     DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from("20060705-20060707")),
+//!     DataElement::new(tags::STUDY_TIME, VR::TM, PrimitiveValue::from("1000-1800")),
+//!     DataElement::new(tags::STUDY_INSTANCE_UID, VR::UI, PrimitiveValue::Empty),
+//! ]);
+//!
+//! let study = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from("20060706")),
+//!     DataElement::new(tags::STUDY_TIME, VR::TM, PrimitiveValue::from("213000")),
+//!     DataElement::new(tags::STUDY_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.2.3.4")),
+//! ]);
+//!
+//! // combined date-time matching: any moment
+//! // from 2006-07-05 10:00 to 2006-07-07 18:00 matches
+//! let matcher = QueryMatcher::new(&identifier);
+//! matcher.validate()?;
+//! assert!(matcher.matches(&study)?);
+//!
+//! // without it, the date and time ranges are matched separately
+//! let matcher = QueryMatcher::new(&identifier)
+//!     .with_options(MatchOptions::new().combine_date_time(false));
+//! assert!(!matcher.matches(&study)?);
+//! # Ok::<_, dicom_object::matching::Error>(())
+//! ```
+use crate::mem::InMemDicomObject;
+use crate::StandardDataDictionary;
+use dicom_core::chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use dicom_core::dictionary::DataDictionary;
+use dicom_core::header::Header;
+use dicom_core::value::deserialize::{
+    parse_date_partial, parse_datetime_partial, parse_time_partial,
+};
+use dicom_core::value::equality::{is_case_insensitive, normalize_text, text_eq, value_eq};
+use dicom_core::value::range::{self, parse_date_range, parse_datetime_range, parse_time_range};
+use dicom_core::value::{
+    AsRange, CastValueError, DateRange, DateTimeRange, DicomDate, DicomDateTime, DicomTime,
+    TimeRange, Value,
+};
+use dicom_core::{Tag, VR};
+use dicom_dictionary_std::tags;
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Could not read value of key {}", tag))]
+    ReadKey {
+        tag: Tag,
+        source: CastValueError,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Invalid value in key {}", tag))]
+    InvalidKeyValue {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: dicom_core::value::DeserializeError,
+    },
+    #[snafu(display("Invalid range in key {}", tag))]
+    InvalidKeyRange {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: range::Error,
+    },
+    /// Invalid timezone offset in identifier
+    InvalidTimezone {
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+    #[snafu(display("Unsupported query/retrieve level `{}`", value))]
+    UnsupportedLevel { value: String, backtrace: Backtrace },
+    #[snafu(display(
+        "Key {} is not allowed in a hierarchical query at the {:?} level",
+        tag,
+        level
+    ))]
+    KeyNotAllowed {
+        tag: Tag,
+        level: QueryRetrieveLevel,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Unique key {} must be a single value in a hierarchical query", tag))]
+    InvalidUniqueKey { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Sequence key {} must have at most one item", tag))]
+    InvalidSequenceKey { tag: Tag, backtrace: Backtrace },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A level of the Query/Retrieve information model,
+/// as specified in _Query/Retrieve Level_ (0008,0052).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum QueryRetrieveLevel {
+    Patient,
+    Study,
+    Series,
+    Image,
+}
+
+impl QueryRetrieveLevel {
+    /// Obtain the level from its code string,
+    /// or `None` if the code is not recognized.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim_matches([' ', '\0']) {
+            "PATIENT" => Some(QueryRetrieveLevel::Patient),
+            "STUDY" => Some(QueryRetrieveLevel::Study),
+            "SERIES" => Some(QueryRetrieveLevel::Series),
+            "IMAGE" => Some(QueryRetrieveLevel::Image),
+            _ => None,
+        }
+    }
+
+    /// Retrieve the code string of this level.
+    pub fn code(self) -> &'static str {
+        match self {
+            QueryRetrieveLevel::Patient => "PATIENT",
+            QueryRetrieveLevel::Study => "STUDY",
+            QueryRetrieveLevel::Series => "SERIES",
+            QueryRetrieveLevel::Image => "IMAGE",
+        }
+    }
+
+    /// Retrieve the unique key attribute of this level.
+    pub fn unique_key(self) -> Tag {
+        match self {
+            QueryRetrieveLevel::Patient => tags::PATIENT_ID,
+            QueryRetrieveLevel::Study => tags::STUDY_INSTANCE_UID,
+            QueryRetrieveLevel::Series => tags::SERIES_INSTANCE_UID,
+            QueryRetrieveLevel::Image => tags::SOP_INSTANCE_UID,
+        }
+    }
+
+    /// Determine the level of the information model
+    /// to which the given attribute belongs.
+    ///
+    /// Returns `None` for attributes not known to this table.
+    pub fn of_attribute(tag: Tag) -> Option<Self> {
+        match tag {
+            tags::PATIENT_ID
+            | tags::PATIENT_NAME
+            | tags::ISSUER_OF_PATIENT_ID
+            | tags::PATIENT_BIRTH_DATE
+            | tags::PATIENT_BIRTH_TIME
+            | tags::PATIENT_SEX
+            | tags::OTHER_PATIENT_I_DS_SEQUENCE
+            | tags::NUMBER_OF_PATIENT_RELATED_STUDIES
+            | tags::NUMBER_OF_PATIENT_RELATED_SERIES
+            | tags::NUMBER_OF_PATIENT_RELATED_INSTANCES => Some(QueryRetrieveLevel::Patient),
+            tags::STUDY_INSTANCE_UID
+            | tags::STUDY_DATE
+            | tags::STUDY_TIME
+            | tags::ACCESSION_NUMBER
+            | tags::STUDY_ID
+            | tags::STUDY_DESCRIPTION
+            | tags::REFERRING_PHYSICIAN_NAME
+            | tags::MODALITIES_IN_STUDY
+            | tags::SOP_CLASSES_IN_STUDY
+            | tags::PATIENT_AGE
+            | tags::PATIENT_SIZE
+            | tags::PATIENT_WEIGHT
+            | tags::NUMBER_OF_STUDY_RELATED_SERIES
+            | tags::NUMBER_OF_STUDY_RELATED_INSTANCES => Some(QueryRetrieveLevel::Study),
+            tags::SERIES_INSTANCE_UID
+            | tags::MODALITY
+            | tags::SERIES_NUMBER
+            | tags::SERIES_DATE
+            | tags::SERIES_TIME
+            | tags::SERIES_DESCRIPTION
+            | tags::BODY_PART_EXAMINED
+            | tags::NUMBER_OF_SERIES_RELATED_INSTANCES => Some(QueryRetrieveLevel::Series),
+            tags::SOP_INSTANCE_UID
+            | tags::SOP_CLASS_UID
+            | tags::INSTANCE_NUMBER
+            | tags::CONTENT_DATE
+            | tags::CONTENT_TIME
+            | tags::ACQUISITION_DATE
+            | tags::ACQUISITION_TIME
+            | tags::ACQUISITION_DATE_TIME => Some(QueryRetrieveLevel::Image),
+            _ => None,
+        }
+    }
+}
+
+/// Pairs of DA and TM attributes
+/// which are matched together under combined date-time matching.
+pub const DATE_TIME_PAIRS: &[(Tag, Tag)] = &[
+    (tags::PATIENT_BIRTH_DATE, tags::PATIENT_BIRTH_TIME),
+    (tags::STUDY_DATE, tags::STUDY_TIME),
+    (tags::SERIES_DATE, tags::SERIES_TIME),
+    (tags::ACQUISITION_DATE, tags::ACQUISITION_TIME),
+    (tags::CONTENT_DATE, tags::CONTENT_TIME),
+    (tags::INSTANCE_CREATION_DATE, tags::INSTANCE_CREATION_TIME),
+    (
+        tags::SCHEDULED_PROCEDURE_STEP_START_DATE,
+        tags::SCHEDULED_PROCEDURE_STEP_START_TIME,
+    ),
+    (
+        tags::PERFORMED_PROCEDURE_STEP_START_DATE,
+        tags::PERFORMED_PROCEDURE_STEP_START_TIME,
+    ),
+];
+
+/// Options for query attribute matching.
+///
+/// The default options enable combined date-time matching
+/// and timezone query adjustment,
+/// and expect hierarchical queries.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MatchOptions {
+    combine_date_time: bool,
+    timezone_adjustment: bool,
+    relational: bool,
+    default_offset: FixedOffset,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            combine_date_time: true,
+            timezone_adjustment: true,
+            relational: false,
+            default_offset: FixedOffset::east_opt(0).unwrap(),
+        }
+    }
+}
+
+impl MatchOptions {
+    /// Create a new set of matching options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Derive matching options from the service-class-application-information
+    /// field of a SOP Class Extended Negotiation sub-item
+    /// for a C-FIND SOP class (PS3.4 C.5.1.1).
+    ///
+    /// Each feature is only enabled
+    /// if the corresponding byte is present and set to 1.
+    pub fn from_extended_negotiation(application_info: &[u8]) -> Self {
+        let flag = |i: usize| application_info.get(i) == Some(&1);
+        MatchOptions {
+            relational: flag(0),
+            combine_date_time: flag(1),
+            timezone_adjustment: flag(3),
+            ..Self::default()
+        }
+    }
+
+    /// Set whether pairs of DA and TM keys
+    /// (listed in [`DATE_TIME_PAIRS`])
+    /// are matched as a single date-time range.
+    ///
+    /// For example, with combined matching,
+    /// a _Study Date_ of `20060705-20060707`
+    /// and a _Study Time_ of `1000-1800`
+    /// match any study from 10:00 on July 5
+    /// until 18:00 on July 7.
+    /// Otherwise, only studies from 10:00 to 18:00
+    /// within each of those days would match.
+    pub fn combine_date_time(mut self, enabled: bool) -> Self {
+        self.combine_date_time = enabled;
+        self
+    }
+
+    /// Set whether date-time keys are adjusted
+    /// to the timezones of the identifier and candidates.
+    ///
+    /// When enabled, DT keys and combined DA-TM keys
+    /// are interpreted in the timezone of
+    /// _Timezone Offset From UTC_ (0008,0201) in the identifier,
+    /// and the values of each candidate in its own declared timezone.
+    /// When disabled, or when the attribute is absent,
+    /// the default offset is used.
+    pub fn timezone_adjustment(mut self, enabled: bool) -> Self {
+        self.timezone_adjustment = enabled;
+        self
+    }
+
+    /// Set whether the query is relational rather than hierarchical.
+    ///
+    /// Relational queries may contain keys of any level
+    /// without the unique keys of the levels above.
+    pub fn relational(mut self, enabled: bool) -> Self {
+        self.relational = enabled;
+        self
+    }
+
+    /// Set the offset from UTC to assume for date-time values
+    /// without an explicit timezone.
+    pub fn default_offset(mut self, offset: FixedOffset) -> Self {
+        self.default_offset = offset;
+        self
+    }
+}
+
+/// A query matching engine for a single identifier.
+#[derive(Debug, Clone)]
+pub struct QueryMatcher<'a, D = StandardDataDictionary> {
+    identifier: &'a InMemDicomObject<D>,
+    options: MatchOptions,
+}
+
+impl<'a, D> QueryMatcher<'a, D>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    /// Create a matcher for the given identifier with the default options.
+    pub fn new(identifier: &'a InMemDicomObject<D>) -> Self {
+        QueryMatcher {
+            identifier,
+            options: MatchOptions::default(),
+        }
+    }
+
+    /// Replace the matching options.
+    pub fn with_options(mut self, options: MatchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Retrieve the query/retrieve level of the identifier, if present.
+    pub fn level(&self) -> Result<Option<QueryRetrieveLevel>> {
+        let elem = match self.identifier.element_opt(tags::QUERY_RETRIEVE_LEVEL) {
+            Ok(Some(elem)) => elem,
+            _ => return Ok(None),
+        };
+        let value = elem.to_str().context(ReadKeySnafu {
+            tag: tags::QUERY_RETRIEVE_LEVEL,
+        })?;
+        QueryRetrieveLevel::from_code(&value)
+            .map(Some)
+            .ok_or_else(|| {
+                UnsupportedLevelSnafu {
+                    value: value.trim(),
+                }
+                .build()
+            })
+    }
+
+    /// Check that the identifier is valid
+    /// under the rules of hierarchical queries,
+    /// unless relational queries are enabled.
+    ///
+    /// In a hierarchical query,
+    /// no keys of levels below the query/retrieve level are allowed,
+    /// and the unique keys of the levels above,
+    /// when present, must be single values.
+    pub fn validate(&self) -> Result<()> {
+        if self.options.relational {
+            return Ok(());
+        }
+        let level = match self.level()? {
+            Some(level) => level,
+            None => return Ok(()),
+        };
+
+        for elem in self.identifier.iter() {
+            let tag = elem.tag();
+            let key_level = match QueryRetrieveLevel::of_attribute(tag) {
+                Some(key_level) => key_level,
+                None => continue,
+            };
+            ensure!(key_level <= level, KeyNotAllowedSnafu { tag, level });
+
+            if key_level < level && tag == key_level.unique_key() {
+                let value = elem.to_str().context(ReadKeySnafu { tag })?;
+                let value = value.trim_matches([' ', '\0']);
+                ensure!(
+                    !value.is_empty() && !value.contains(['\\', '*', '?']),
+                    InvalidUniqueKeySnafu { tag }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate whether the candidate object matches the identifier.
+    ///
+    /// Returns an error if a key in the identifier is invalid.
+    /// Invalid values in the candidate never match non-universal keys.
+    pub fn matches(&self, candidate: &InMemDicomObject<D>) -> Result<bool> {
+        let query_offset = if self.options.timezone_adjustment {
+            self.identifier
+                .timezone_offset()
+                .context(InvalidTimezoneSnafu)?
+                .unwrap_or(self.options.default_offset)
+        } else {
+            self.options.default_offset
+        };
+        let candidate_offset = if self.options.timezone_adjustment {
+            candidate
+                .timezone_offset()
+                .ok()
+                .flatten()
+                .unwrap_or(self.options.default_offset)
+        } else {
+            self.options.default_offset
+        };
+        self.matches_with_offsets(candidate, query_offset, candidate_offset)
+    }
+
+    /// Evaluate whether the candidate object matches the identifier,
+    /// with the given offsets for the keys and the candidate values.
+    fn matches_with_offsets(
+        &self,
+        candidate: &InMemDicomObject<D>,
+        query_offset: FixedOffset,
+        candidate_offset: FixedOffset,
+    ) -> Result<bool> {
+        let mut combined = Vec::new();
+        if self.options.combine_date_time {
+            for &(date_tag, time_tag) in DATE_TIME_PAIRS {
+                let date_key = self.key_text(date_tag)?;
+                let time_key = self.key_text(time_tag)?;
+                if let (Some(date_key), Some(time_key)) = (date_key, time_key) {
+                    let range =
+                        combined_key_range(date_tag, &date_key, time_tag, &time_key, query_offset)?;
+                    if !match_combined(candidate, date_tag, time_tag, &range, candidate_offset) {
+                        return Ok(false);
+                    }
+                    combined.push(date_tag);
+                    combined.push(time_tag);
+                }
+            }
+        }
+
+        for elem in self.identifier.iter() {
+            let tag = elem.tag();
+            if combined.contains(&tag) || is_not_a_key(tag) {
+                continue;
+            }
+
+            if let Value::Sequence { items, .. } = elem.value() {
+                ensure!(items.len() <= 1, InvalidSequenceKeySnafu { tag });
+                let item = match items.first() {
+                    Some(item) if item.iter().next().is_some() => item,
+                    // universal matching of sequences
+                    _ => continue,
+                };
+                let item_matcher = QueryMatcher {
+                    identifier: item,
+                    options: self.options,
+                };
+                let candidate_items = candidate
+                    .element_opt(tag)
+                    .ok()
+                    .flatten()
+                    .and_then(|e| e.items())
+                    .unwrap_or_default();
+                let mut is_match = false;
+                for candidate_item in candidate_items {
+                    if item_matcher.matches_with_offsets(
+                        candidate_item,
+                        query_offset,
+                        candidate_offset,
+                    )? {
+                        is_match = true;
+                        break;
+                    }
+                }
+                if !is_match {
+                    return Ok(false);
+                }
+                continue;
+            }
+
+            let key = match self.key_text(tag)? {
+                Some(key) => key,
+                // universal matching
+                None => continue,
+            };
+
+            let vr = elem.vr();
+            if key == "*" && !matches!(vr, VR::DA | VR::TM | VR::DT) {
+                // universal matching
+                continue;
+            }
+            let is_match = match vr {
+                VR::DA => {
+                    let range = date_key_range(tag, &key)?;
+                    candidate_values(candidate, tag).iter().any(|v| {
+                        parse_date_partial(v.trim().as_bytes())
+                            .ok()
+                            .and_then(|(date, _)| date_bounds(&date))
+                            .map(|(start, end)| overlaps(range.start(), range.end(), start, end))
+                            .unwrap_or(false)
+                    })
+                }
+                VR::TM => {
+                    let range = time_key_range(tag, &key)?;
+                    candidate_values(candidate, tag).iter().any(|v| {
+                        parse_time_partial(v.trim().as_bytes())
+                            .ok()
+                            .and_then(|(time, _)| time_bounds(&time))
+                            .map(|(start, end)| overlaps(range.start(), range.end(), start, end))
+                            .unwrap_or(false)
+                    })
+                }
+                VR::DT => {
+                    let range = datetime_key_range(tag, &key, query_offset)?;
+                    candidate_values(candidate, tag).iter().any(|v| {
+                        parse_datetime_partial(v.trim().as_bytes(), candidate_offset)
+                            .ok()
+                            .and_then(|dt| datetime_bounds(&dt))
+                            .map(|(start, end)| overlaps(range.start(), range.end(), start, end))
+                            .unwrap_or(false)
+                    })
+                }
+                VR::UI => {
+                    // list of UID matching
+                    let values = candidate_values(candidate, tag);
+                    key.split('\\')
+                        .any(|uid| values.iter().any(|v| text_eq(vr, uid, v)))
+                }
+                _ if supports_wildcards(vr) && key.contains(['*', '?']) => {
+                    candidate_values(candidate, tag)
+                        .iter()
+                        .any(|v| wildcard_match(vr, &key, v))
+                }
+                _ => match_single_value(elem.value(), vr, candidate, tag),
+            };
+            if !is_match {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Retrieve the trimmed text of a key in the identifier,
+    /// or `None` if the key is absent or empty.
+    fn key_text(&self, tag: Tag) -> Result<Option<String>> {
+        let elem = match self.identifier.element_opt(tag) {
+            Ok(Some(elem)) => elem,
+            _ => return Ok(None),
+        };
+        let text = elem.to_str().context(ReadKeySnafu { tag })?;
+        let text = text.trim_matches([' ', '\0']);
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(text.to_string()))
+        }
+    }
+}
+
+/// Check whether the attribute is part of the identifier
+/// without being a matching key.
+fn is_not_a_key(tag: Tag) -> bool {
+    tag.element() == 0
+        || tag == tags::SPECIFIC_CHARACTER_SET
+        || tag == tags::QUERY_RETRIEVE_LEVEL
+        || tag == tags::TIMEZONE_OFFSET_FROM_UTC
+}
+
+fn candidate_values<D>(candidate: &InMemDicomObject<D>, tag: Tag) -> Vec<String>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    candidate
+        .element_opt(tag)
+        .ok()
+        .flatten()
+        .and_then(|e| e.to_multi_str().ok())
+        .map(|values| values.into_owned())
+        .unwrap_or_default()
+}
+
+fn match_single_value<D>(
+    key: &Value<InMemDicomObject<D>, crate::mem::InMemFragment>,
+    vr: VR,
+    candidate: &InMemDicomObject<D>,
+    tag: Tag,
+) -> bool
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    let candidate = match candidate.element_opt(tag).ok().flatten() {
+        Some(candidate) => candidate,
+        None => return false,
+    };
+    let (key, value) = match (key.primitive(), candidate.value().primitive()) {
+        (Some(key), Some(value)) => (key, value),
+        _ => return false,
+    };
+
+    if key.multiplicity() == 1 && value.multiplicity() > 1 {
+        // any of the candidate's values may match
+        let key = key.to_str();
+        return value.to_multi_str().iter().any(|v| text_eq(vr, &key, v));
+    }
+    value_eq(vr, key, value)
+}
+
+/// Check whether wildcard matching applies to keys
+/// of the given value representation.
+fn supports_wildcards(vr: VR) -> bool {
+    matches!(
+        vr,
+        VR::AE | VR::CS | VR::LO | VR::LT | VR::PN | VR::SH | VR::ST | VR::UC | VR::UR | VR::UT
+    )
+}
+
+/// Match a single value against a key with wildcards,
+/// where `*` matches any sequence of characters, including none,
+/// and `?` matches any single character.
+fn wildcard_match(vr: VR, pattern: &str, value: &str) -> bool {
+    let fold = |s: &str| -> Vec<char> {
+        if is_case_insensitive(vr) {
+            s.chars().flat_map(char::to_lowercase).collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+    let pattern = fold(normalize_text(vr, pattern));
+    let value = fold(normalize_text(vr, value));
+
+    // backtrack to the last `*` on mismatch
+    let (mut p, mut v) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match star {
+                Some((star_p, star_v)) => {
+                    p = star_p + 1;
+                    v = star_v + 1;
+                    star = Some((star_p, star_v + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn match_combined<D>(
+    candidate: &InMemDicomObject<D>,
+    date_tag: Tag,
+    time_tag: Tag,
+    range: &DateTimeRange,
+    offset: FixedOffset,
+) -> bool
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    let date = candidate_values(candidate, date_tag)
+        .first()
+        .and_then(|v| parse_date_partial(v.trim().as_bytes()).ok())
+        .map(|(date, _)| date);
+    let date = match date {
+        Some(date) => date,
+        None => return false,
+    };
+    let time = candidate_values(candidate, time_tag)
+        .first()
+        .and_then(|v| parse_time_partial(v.trim().as_bytes()).ok())
+        .map(|(time, _)| time);
+
+    let datetime = match time {
+        Some(time) => match DicomDateTime::from_date_and_time(date, time, offset) {
+            Ok(datetime) => datetime,
+            Err(_) => return false,
+        },
+        None => DicomDateTime::from_date(date, offset),
+    };
+    datetime_bounds(&datetime)
+        .map(|(start, end)| overlaps(range.start(), range.end(), start, end))
+        .unwrap_or(false)
+}
+
+/// Check whether the interval from `start` to `end`
+/// overlaps with the given range.
+fn overlaps<T: PartialOrd>(
+    range_start: Option<&T>,
+    range_end: Option<&T>,
+    start: T,
+    end: T,
+) -> bool {
+    range_end.map(|e| &start <= e).unwrap_or(true) && range_start.map(|s| &end >= s).unwrap_or(true)
+}
+
+fn date_bounds(date: &DicomDate) -> Option<(NaiveDate, NaiveDate)> {
+    Some((date.earliest().ok()?, date.latest().ok()?))
+}
+
+fn time_bounds(time: &DicomTime) -> Option<(NaiveTime, NaiveTime)> {
+    Some((time.earliest().ok()?, time.latest().ok()?))
+}
+
+fn datetime_bounds(
+    datetime: &DicomDateTime,
+) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    Some((datetime.earliest().ok()?, datetime.latest().ok()?))
+}
+
+fn date_key_range(tag: Tag, key: &str) -> Result<DateRange> {
+    if key.contains('-') {
+        parse_date_range(key.as_bytes()).context(InvalidKeyRangeSnafu { tag })
+    } else {
+        let (date, _) = parse_date_partial(key.as_bytes()).context(InvalidKeyValueSnafu { tag })?;
+        date.range().context(InvalidKeyRangeSnafu { tag })
+    }
+}
+
+fn time_key_range(tag: Tag, key: &str) -> Result<TimeRange> {
+    if key.contains('-') {
+        parse_time_range(key.as_bytes()).context(InvalidKeyRangeSnafu { tag })
+    } else {
+        let (time, _) = parse_time_partial(key.as_bytes()).context(InvalidKeyValueSnafu { tag })?;
+        time.range().context(InvalidKeyRangeSnafu { tag })
+    }
+}
+
+fn datetime_key_range(tag: Tag, key: &str, offset: FixedOffset) -> Result<DateTimeRange> {
+    if key.contains('-') {
+        if let Ok(range) = parse_datetime_range(key.as_bytes(), offset) {
+            return Ok(range);
+        }
+        // may be a single value with a negative UTC offset
+    }
+    let datetime =
+        parse_datetime_partial(key.as_bytes(), offset).context(InvalidKeyValueSnafu { tag })?;
+    datetime.range().context(InvalidKeyRangeSnafu { tag })
+}
+
+fn combined_key_range(
+    date_tag: Tag,
+    date_key: &str,
+    time_tag: Tag,
+    time_key: &str,
+    offset: FixedOffset,
+) -> Result<DateTimeRange> {
+    let date_range = date_key_range(date_tag, date_key)?;
+    let time_range = time_key_range(time_tag, time_key)?;
+    DateTimeRange::from_date_and_time_range(date_range, time_range, offset)
+        .context(InvalidKeyRangeSnafu { tag: date_tag })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, PrimitiveValue};
+
+    fn study(date: &str, time: &str) -> InMemDicomObject {
+        InMemDicomObject::from_element_iter([
+            DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from(date)),
+            DataElement::new(tags::STUDY_TIME, VR::TM, PrimitiveValue::from(time)),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.3.4"),
+            ),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+        ])
+    }
+
+    fn query(elements: Vec<(Tag, VR, &str)>) -> InMemDicomObject {
+        InMemDicomObject::from_element_iter(
+            elements
+                .into_iter()
+                .map(|(tag, vr, value)| DataElement::new(tag, vr, PrimitiveValue::from(value))),
+        )
+    }
+
+    #[test]
+    fn universal_and_single_value_matching() {
+        let identifier = query(vec![
+            (tags::STUDY_INSTANCE_UID, VR::UI, ""),
+            (tags::MODALITY, VR::CS, "ct"),
+        ]);
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&study("20200101", "120000")).unwrap());
+
+        let identifier = query(vec![(tags::MODALITY, VR::CS, "MR")]);
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(!matcher.matches(&study("20200101", "120000")).unwrap());
+    }
+
+    #[test]
+    fn wildcard_matching() {
+        let mut candidate = study("20200101", "120000");
+        candidate.put(DataElement::new(
+            tags::PATIENT_NAME,
+            VR::PN,
+            PrimitiveValue::from("Doe^John"),
+        ));
+
+        for (key, expected) in [
+            ("Doe*", true),
+            ("doe^jo?n", true),
+            ("*^John", true),
+            ("D*e^*", true),
+            ("Doe^J?", false),
+            ("Smith*", false),
+            ("*", true),
+        ] {
+            let identifier = query(vec![(tags::PATIENT_NAME, VR::PN, key)]);
+            let matcher = QueryMatcher::new(&identifier);
+            assert_eq!(matcher.matches(&candidate).unwrap(), expected, "{}", key);
+        }
+
+        // a single `*` is universal, even if the attribute is absent
+        let identifier = query(vec![(tags::ACCESSION_NUMBER, VR::SH, "*")]);
+        assert!(QueryMatcher::new(&identifier).matches(&candidate).unwrap());
+        let identifier = query(vec![(tags::ACCESSION_NUMBER, VR::SH, "A*")]);
+        assert!(!QueryMatcher::new(&identifier).matches(&candidate).unwrap());
+    }
+
+    #[test]
+    fn list_of_uid_matching() {
+        let candidate = study("20200101", "120000");
+        let identifier = query(vec![(tags::STUDY_INSTANCE_UID, VR::UI, "1.2.3\\1.2.3.4")]);
+        assert!(QueryMatcher::new(&identifier).matches(&candidate).unwrap());
+        let identifier = query(vec![(tags::STUDY_INSTANCE_UID, VR::UI, "1.2.3\\1.2.3.5")]);
+        assert!(!QueryMatcher::new(&identifier).matches(&candidate).unwrap());
+    }
+
+    #[test]
+    fn sequence_matching() {
+        let code = |value: &str, scheme: &str| {
+            query(vec![
+                (tags::CODE_VALUE, VR::SH, value),
+                (tags::CODING_SCHEME_DESIGNATOR, VR::SH, scheme),
+            ])
+        };
+        let sequence = |items: Vec<InMemDicomObject>| {
+            DataElement::new(
+                tags::PROCEDURE_CODE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: items.into(),
+                    size: dicom_core::Length::UNDEFINED,
+                },
+            )
+        };
+        let mut candidate = study("20200101", "120000");
+        candidate.put(sequence(vec![code("A1", "99X"), code("B2", "LN")]));
+
+        let mut identifier = query(vec![(tags::MODALITY, VR::CS, "CT")]);
+        identifier.put(sequence(vec![code("B2", "")]));
+        assert!(QueryMatcher::new(&identifier).matches(&candidate).unwrap());
+
+        // all keys must match within the same item
+        identifier.put(sequence(vec![code("B2", "99X")]));
+        assert!(!QueryMatcher::new(&identifier).matches(&candidate).unwrap());
+
+        // universal matching of sequences
+        identifier.put(sequence(vec![InMemDicomObject::new_empty()]));
+        assert!(QueryMatcher::new(&identifier).matches(&candidate).unwrap());
+
+        identifier.put(sequence(vec![code("A1", ""), code("B2", "")]));
+        assert!(matches!(
+            QueryMatcher::new(&identifier).matches(&candidate),
+            Err(Error::InvalidSequenceKey { .. })
+        ));
+    }
+
+    #[test]
+    fn date_and_time_range_matching() {
+        let identifier = query(vec![(tags::STUDY_DATE, VR::DA, "20200101-20200131")]);
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&study("20200115", "")).unwrap());
+        assert!(matcher.matches(&study("20200131", "")).unwrap());
+        assert!(!matcher.matches(&study("20200201", "")).unwrap());
+
+        let identifier = query(vec![(tags::STUDY_DATE, VR::DA, "-2019")]);
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&study("20191231", "")).unwrap());
+        assert!(!matcher.matches(&study("20200101", "")).unwrap());
+
+        let identifier = query(vec![(tags::STUDY_TIME, VR::TM, "0800-")]);
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&study("20200101", "083000")).unwrap());
+        assert!(!matcher.matches(&study("20200101", "0759")).unwrap());
+
+        // invalid key
+        let identifier = query(vec![(tags::STUDY_DATE, VR::DA, "2020-2019")]);
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matches!(
+            matcher.matches(&study("20200101", "")),
+            Err(Error::InvalidKeyRange { .. })
+        ));
+    }
+
+    #[test]
+    fn combined_date_time_matching() {
+        let identifier = query(vec![
+            (tags::STUDY_DATE, VR::DA, "20060705-20060707"),
+            (tags::STUDY_TIME, VR::TM, "1000-1800"),
+        ]);
+
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&study("20060706", "213000")).unwrap());
+        assert!(matcher.matches(&study("20060705", "1000")).unwrap());
+        assert!(!matcher.matches(&study("20060705", "0959")).unwrap());
+        assert!(!matcher.matches(&study("20060707", "1801")).unwrap());
+
+        let matcher = QueryMatcher::new(&identifier)
+            .with_options(MatchOptions::new().combine_date_time(false));
+        assert!(!matcher.matches(&study("20060706", "213000")).unwrap());
+        assert!(matcher.matches(&study("20060706", "120000")).unwrap());
+    }
+
+    #[test]
+    fn timezone_query_adjustment() {
+        let mut identifier = query(vec![
+            (tags::STUDY_DATE, VR::DA, "20200101"),
+            (tags::STUDY_TIME, VR::TM, "0900-1000"),
+            (tags::TIMEZONE_OFFSET_FROM_UTC, VR::SH, "+0100"),
+        ]);
+
+        // 08:30 UTC is 09:30 at +01:00
+        let candidate = study("20200101", "083000");
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&candidate).unwrap());
+
+        // without adjustment, times are compared as they are
+        let matcher = QueryMatcher::new(&identifier)
+            .with_options(MatchOptions::new().timezone_adjustment(false));
+        assert!(!matcher.matches(&candidate).unwrap());
+
+        // candidate in its own timezone: 03:30 at -05:00 is 09:30 at +01:00
+        let mut candidate = study("20200101", "033000");
+        candidate.put(DataElement::new(
+            tags::TIMEZONE_OFFSET_FROM_UTC,
+            VR::SH,
+            PrimitiveValue::from("-0500"),
+        ));
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&candidate).unwrap());
+
+        // DT keys are adjusted too
+        identifier = query(vec![
+            (
+                tags::ACQUISITION_DATE_TIME,
+                VR::DT,
+                "20200101090000-20200101100000",
+            ),
+            (tags::TIMEZONE_OFFSET_FROM_UTC, VR::SH, "+0100"),
+        ]);
+        candidate.put(DataElement::new(
+            tags::ACQUISITION_DATE_TIME,
+            VR::DT,
+            PrimitiveValue::from("20200101083000+0000"),
+        ));
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matcher.matches(&candidate).unwrap());
+    }
+
+    #[test]
+    fn hierarchical_and_relational_queries() {
+        let identifier = query(vec![
+            (tags::QUERY_RETRIEVE_LEVEL, VR::CS, "STUDY"),
+            (tags::STUDY_INSTANCE_UID, VR::UI, ""),
+            (tags::MODALITY, VR::CS, "CT"),
+        ]);
+
+        // series level key in a study level query
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matches!(
+            matcher.validate(),
+            Err(Error::KeyNotAllowed {
+                level: QueryRetrieveLevel::Study,
+                ..
+            })
+        ));
+
+        let matcher =
+            QueryMatcher::new(&identifier).with_options(MatchOptions::new().relational(true));
+        matcher.validate().unwrap();
+        assert!(matcher.matches(&study("20200101", "120000")).unwrap());
+
+        // unique keys above the query level must be single values
+        let identifier = query(vec![
+            (tags::QUERY_RETRIEVE_LEVEL, VR::CS, "SERIES"),
+            (tags::STUDY_INSTANCE_UID, VR::UI, "1.2.*"),
+            (tags::MODALITY, VR::CS, "CT"),
+        ]);
+        let matcher = QueryMatcher::new(&identifier);
+        assert!(matches!(
+            matcher.validate(),
+            Err(Error::InvalidUniqueKey { .. })
+        ));
+    }
+
+    #[test]
+    fn options_from_extended_negotiation() {
+        let options = MatchOptions::from_extended_negotiation(&[1, 1, 0, 1]);
+        assert_eq!(options, MatchOptions::new().relational(true));
+
+        let options = MatchOptions::from_extended_negotiation(&[1]);
+        assert_eq!(
+            options,
+            MatchOptions::new()
+                .relational(true)
+                .combine_date_time(false)
+                .timezone_adjustment(false)
+        );
+    }
+}
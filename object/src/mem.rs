@@ -2,28 +2,49 @@
 
 use itertools::Itertools;
 use smallvec::SmallVec;
-use snafu::{OptionExt, ResultExt};
+use snafu::{ensure, IntoError, OptionExt, ResultExt};
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
+use std::str::FromStr;
 use std::{collections::BTreeMap, io::Write};
 
-use crate::file::ReadPreamble;
+use crate::bulk::{BulkDataRef, BulkDataSink, BulkDataTokens, PendingBulkData};
+use crate::file::{
+    detect_dataset_transfer_syntax, detect_preamble, DuplicateTagPolicy, ReadPreamble, ReadSettings,
+};
+use crate::original::{OriginalValue, RecordingReader, ValueRecorder};
+use crate::path::{IntoTagPath, TagPath};
+use crate::tokens::{ByteCounter, ExplicitLengthTokens, GroupLengthTokens};
 use crate::{meta::FileMetaTable, FileMetaTableBuilder};
 use crate::{
-    BuildMetaTableSnafu, CreateParserSnafu, CreatePrinterSnafu, DicomObject, FileDicomObject,
-    MissingElementValueSnafu, NoSuchAttributeNameSnafu, NoSuchDataElementAliasSnafu,
-    NoSuchDataElementTagSnafu, OpenFileSnafu, ParseMetaDataSetSnafu, PrematureEndSnafu,
-    PrepareMetaTableSnafu, PrintDataSetSnafu, ReadFileSnafu, ReadPreambleBytesSnafu,
-    ReadTokenSnafu, Result, UnexpectedTokenSnafu, UnsupportedTransferSyntaxSnafu,
+    BuildMetaTableSnafu, BulkDataSnafu, CastValueAtSnafu, CastValueSnafu, CombineDateTimeSnafu,
+    ConvertValueAtSnafu, ConvertValueSnafu, CreateParserSnafu, CreatePrinterSnafu, DicomObject,
+    DuplicateTagSnafu, FileDicomObject, InvalidTagPathSnafu, InvalidTimezoneOffsetSnafu,
+    MissingElementValueSnafu, NoFreePrivateBlockSnafu, NoSuchAttributeNameSnafu,
+    NoSuchAttributeTagSnafu, NoSuchDataElementAliasSnafu, NoSuchDataElementPathSnafu,
+    NoSuchDataElementTagSnafu, NoSuchItemSnafu, NoSuchPrivateCreatorSnafu, NotASequenceSnafu,
+    NotPrivateGroupSnafu, OpenFileSnafu, ParseMetaDataSetSnafu, PrematureEndSnafu,
+    PrepareMetaTableSnafu, PrintDataSetSnafu, ReadDataSetBytesSnafu, ReadFileSnafu,
+    ReadPreambleBytesSnafu, ReadTokenSnafu, Result, TagOutOfOrderSnafu,
+    UndetectedTransferSyntaxSnafu, UnexpectedTokenSnafu, UnsupportedTransferSyntaxSnafu,
+    WriteDataSetSnafu, WriteOptions,
 };
+use dicom_core::chrono::FixedOffset;
 use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
-use dicom_core::header::{HasLength, Header};
-use dicom_core::value::{Value, C};
+use dicom_core::header::{GroupNumber, HasLength, Header};
+use dicom_core::value::deserialize::parse_utc_offset;
+use dicom_core::value::equality::is_textual;
+use dicom_core::value::{
+    ConvertValueError, DicomDate, DicomDateTime, DicomValueType, PrimitiveValue, Value, C,
+};
 use dicom_core::{DataElement, Length, Tag, VR};
 use dicom_dictionary_std::{tags, StandardDataDictionary};
 use dicom_encoding::transfer_syntax::TransferSyntaxIndex;
 use dicom_encoding::{encode::EncodeTo, text::SpecificCharacterSet, TransferSyntax};
+use dicom_parser::dataset::adapt::{AdaptedReader, AdaptedWriter};
+use dicom_parser::dataset::read::ReadWarning;
 use dicom_parser::dataset::{DataSetReader, DataToken};
 use dicom_parser::{
     dataset::{read::Error as ParserError, DataSetWriter, IntoTokens},
@@ -37,7 +58,48 @@ pub type InMemElement<D = StandardDataDictionary> = DataElement<InMemDicomObject
 /// The type of a pixel data fragment.
 pub type InMemFragment = Vec<u8>;
 
-type ParserResult<T> = std::result::Result<T, ParserError>;
+/// An error yielded by a source of data set tokens
+/// from which an in-memory object is built.
+pub(crate) trait TokenSourceError {
+    /// Convert the error into an error of this crate.
+    fn into_error(self) -> crate::Error;
+}
+
+impl TokenSourceError for ParserError {
+    fn into_error(self) -> crate::Error {
+        ReadTokenSnafu.into_error(self)
+    }
+}
+
+impl TokenSourceError for crate::bulk::Error {
+    fn into_error(self) -> crate::Error {
+        BulkDataSnafu.into_error(self)
+    }
+}
+
+/// The options of building an in-memory object from data set tokens.
+#[derive(Debug, Default, Copy, Clone)]
+struct BuildOptions<'a> {
+    /// the tag of the element before which building ends, if any
+    read_until: Option<Tag>,
+    /// the recorder of the original encoding of values, if any
+    recorder: Option<&'a ValueRecorder>,
+    /// the reference of the last value externalized, if any
+    bulk: Option<&'a PendingBulkData>,
+    /// how elements with a repeated tag are handled
+    duplicate_tags: DuplicateTagPolicy,
+}
+
+impl<'a> BuildOptions<'a> {
+    fn new(settings: &ReadSettings, recorder: Option<&'a ValueRecorder>) -> Self {
+        BuildOptions {
+            read_until: settings.read_until,
+            recorder,
+            bulk: None,
+            duplicate_tags: settings.duplicate_tags,
+        }
+    }
+}
 
 /** A DICOM object that is fully contained in memory.
  */
@@ -46,15 +108,27 @@ pub struct InMemDicomObject<D = StandardDataDictionary> {
     /// the element map
     entries: BTreeMap<Tag, InMemElement<D>>,
     /// the data dictionary
-    dict: D,
+    pub(crate) dict: D,
     /// The length of the DICOM object in bytes.
     /// It is usually undefined, unless it is part of an item
     /// in a sequence with a specified length in its item header.
     len: Length,
+    /// the original encoding of values read from a data set,
+    /// if requested
+    pub(crate) originals: BTreeMap<Tag, OriginalValue>,
+    /// the references to element values
+    /// written to a bulk data sink while reading, if requested
+    pub(crate) bulk_data: BTreeMap<Tag, BulkDataRef>,
+    /// the elements replaced by a later element with the same tag
+    /// while reading, if requested
+    pub(crate) duplicates: Vec<InMemElement<D>>,
 }
 
 impl<D> PartialEq for InMemDicomObject<D> {
-    // This implementation ignores the data dictionary.
+    // This implementation ignores the data dictionary,
+    // the original encoding of the values,
+    // the references to externalized bulk data,
+    // and the duplicate elements collected while reading.
     fn eq(&self, other: &Self) -> bool {
         self.entries == other.entries
     }
@@ -96,8 +170,11 @@ impl FileDicomObject<InMemDicomObject<StandardDataDictionary>> {
 
     /// Create a DICOM object by reading from a byte source.
     ///
-    /// This function assumes the standard file encoding structure without the
-    /// preamble: file meta group, followed by the rest of the data set.
+    /// This function assumes the standard file encoding structure:
+    /// magic code, file meta group, and the rest of the data set.
+    /// The 128-byte preamble before the magic code is optional:
+    /// if present, it is detected and kept,
+    /// including the TIFF header of a dual personality file.
     pub fn from_reader<S>(src: S) -> Result<Self>
     where
         S: Read,
@@ -119,6 +196,9 @@ impl InMemDicomObject<StandardDataDictionary> {
             entries: BTreeMap::new(),
             dict: StandardDataDictionary,
             len: Length::UNDEFINED,
+            originals: BTreeMap::new(),
+            bulk_data: BTreeMap::new(),
+            duplicates: Vec::new(),
         }
     }
 
@@ -206,7 +286,12 @@ where
                 entries: BTreeMap::new(),
                 dict,
                 len: Length::UNDEFINED,
+                originals: BTreeMap::new(),
+                bulk_data: BTreeMap::new(),
+                duplicates: Vec::new(),
             },
+            preamble: [0; 128],
+            modified: false,
         }
     }
 
@@ -233,15 +318,21 @@ where
         P: AsRef<Path>,
         R: TransferSyntaxIndex,
     {
-        Self::open_file_with_all_options(path, dict, ts_index, None, ReadPreamble::Auto)
+        Self::open_file_with_all_options(
+            path,
+            dict,
+            ts_index,
+            &ReadSettings::default(),
+            &mut Vec::new(),
+        )
     }
 
-    pub(crate) fn open_file_with_all_options<P: AsRef<Path>, R>(
+    pub(crate) fn open_file_with_all_options<P, R>(
         path: P,
         dict: D,
         ts_index: R,
-        read_until: Option<Tag>,
-        read_preamble: ReadPreamble,
+        settings: &ReadSettings,
+        warnings: &mut Vec<ReadWarning>,
     ) -> Result<Self>
     where
         P: AsRef<Path>,
@@ -251,31 +342,40 @@ where
         let mut file =
             BufReader::new(File::open(path).with_context(|_| OpenFileSnafu { filename: path })?);
 
-        if read_preamble == ReadPreamble::Auto || read_preamble == ReadPreamble::Always {
-            let mut buf = [0u8; 128];
-            // skip the preamble
-            file.read_exact(&mut buf)
-                .with_context(|_| ReadFileSnafu { filename: path })?;
-        }
+        let (preamble, magic_read) = detect_preamble(&mut file, settings.read_preamble)
+            .with_context(|_| ReadFileSnafu { filename: path })?;
 
         // read metadata header
-        let meta = FileMetaTable::from_reader(&mut file).context(ParseMetaDataSetSnafu)?;
+        let meta = read_meta(&mut file, magic_read)?;
 
         // read rest of data according to metadata, feed it to object
         if let Some(ts) = ts_index.get(&meta.transfer_syntax) {
-            let cs = SpecificCharacterSet::Default;
-            let mut dataset =
-                DataSetReader::new_with_ts_cs(file, ts, cs).context(CreateParserSnafu)?;
+            let file =
+                AdaptedReader::new(file, ts).with_context(|_| ReadFileSnafu { filename: path })?;
+            let recorder = Some(ValueRecorder::default()).filter(|_| settings.preserve_encoding);
+            let file = RecordingReader::new(file, recorder.clone());
+            let mut dataset = DataSetReader::new_with_ts_cs_options(
+                file,
+                ts,
+                settings.charset,
+                settings.reader_options(),
+            )
+            .context(CreateParserSnafu)?;
+
+            let obj = InMemDicomObject::build_object(
+                &mut dataset,
+                dict,
+                false,
+                Length::UNDEFINED,
+                BuildOptions::new(settings, recorder.as_ref()),
+            );
+            settings.check_warnings(dataset.take_warnings(), warnings)?;
 
             Ok(FileDicomObject {
                 meta,
-                obj: InMemDicomObject::build_object(
-                    &mut dataset,
-                    dict,
-                    false,
-                    Length::UNDEFINED,
-                    read_until,
-                )?,
+                obj: obj?,
+                preamble,
+                modified: false,
             })
         } else {
             UnsupportedTransferSyntaxSnafu {
@@ -287,8 +387,11 @@ where
 
     /// Create a DICOM object by reading from a byte source.
     ///
-    /// This function assumes the standard file encoding structure without the
-    /// preamble: file meta group, followed by the rest of the data set.
+    /// This function assumes the standard file encoding structure:
+    /// magic code, file meta group, and the rest of the data set.
+    /// The 128-byte preamble before the magic code is optional:
+    /// if present, it is detected and kept,
+    /// including the TIFF header of a dual personality file.
     pub fn from_reader_with_dict<S>(src: S, dict: D) -> Result<Self>
     where
         S: Read,
@@ -298,58 +401,129 @@ where
 
     /// Create a DICOM object by reading from a byte source.
     ///
-    /// This function assumes the standard file encoding structure without the
-    /// preamble: file meta group, followed by the rest of the data set.
+    /// This function assumes the standard file encoding structure:
+    /// magic code, file meta group, and the rest of the data set.
+    /// The 128-byte preamble before the magic code is optional:
+    /// if present, it is detected and kept,
+    /// including the TIFF header of a dual personality file.
     ///
     /// This function allows you to choose a different transfer syntax index,
     /// but its use is only advised when the built-in transfer syntax registry
     /// is insufficient. Otherwise, please use [`from_reader_with_dict`] instead.
     ///
     /// [`from_reader_with_dict`]: #method.from_reader_with_dict
-    pub fn from_reader_with<'s, S: 's, R>(src: S, dict: D, ts_index: R) -> Result<Self>
+    pub fn from_reader_with<'s, S, R>(src: S, dict: D, ts_index: R) -> Result<Self>
     where
-        S: Read,
+        S: Read + 's,
         R: TransferSyntaxIndex,
     {
-        Self::from_reader_with_all_options(src, dict, ts_index, None, ReadPreamble::Auto)
+        Self::from_reader_with_all_options(
+            src,
+            dict,
+            ts_index,
+            &ReadSettings::default(),
+            &mut Vec::new(),
+        )
     }
 
-    pub(crate) fn from_reader_with_all_options<'s, S: 's, R>(
+    pub(crate) fn from_reader_with_all_options<'s, S, R>(
         src: S,
         dict: D,
         ts_index: R,
-        read_until: Option<Tag>,
-        read_preamble: ReadPreamble,
+        settings: &ReadSettings,
+        warnings: &mut Vec<ReadWarning>,
     ) -> Result<Self>
     where
-        S: Read,
+        S: Read + 's,
         R: TransferSyntaxIndex,
     {
         let mut file = BufReader::new(src);
 
-        if read_preamble == ReadPreamble::Always {
-            // skip preamble
-            let mut buf = [0u8; 128];
-            // skip the preamble
-            file.read_exact(&mut buf).context(ReadPreambleBytesSnafu)?;
-        }
+        let (preamble, magic_read) =
+            detect_preamble(&mut file, settings.read_preamble).context(ReadPreambleBytesSnafu)?;
 
         // read metadata header
-        let meta = FileMetaTable::from_reader(&mut file).context(ParseMetaDataSetSnafu)?;
+        let meta = read_meta(&mut file, magic_read)?;
 
         // read rest of data according to metadata, feed it to object
         if let Some(ts) = ts_index.get(&meta.transfer_syntax) {
-            let cs = SpecificCharacterSet::Default;
-            let mut dataset =
-                DataSetReader::new_with_ts_cs(file, ts, cs).context(CreateParserSnafu)?;
+            let file = AdaptedReader::new(file, ts).context(ReadDataSetBytesSnafu)?;
+            let recorder = Some(ValueRecorder::default()).filter(|_| settings.preserve_encoding);
+            let file = RecordingReader::new(file, recorder.clone());
+            let mut dataset = DataSetReader::new_with_ts_cs_options(
+                file,
+                ts,
+                settings.charset,
+                settings.reader_options(),
+            )
+            .context(CreateParserSnafu)?;
+            let obj = InMemDicomObject::build_object(
+                &mut dataset,
+                dict,
+                false,
+                Length::UNDEFINED,
+                BuildOptions::new(settings, recorder.as_ref()),
+            );
+            settings.check_warnings(dataset.take_warnings(), warnings)?;
+            Ok(FileDicomObject {
+                meta,
+                obj: obj?,
+                preamble,
+                modified: false,
+            })
+        } else {
+            UnsupportedTransferSyntaxSnafu {
+                uid: meta.transfer_syntax,
+            }
+            .fail()
+        }
+    }
+
+    pub(crate) fn from_reader_with_bulk_data<S, R, K>(
+        src: S,
+        dict: D,
+        ts_index: R,
+        settings: &ReadSettings,
+        (threshold, sink): (u32, K),
+    ) -> Result<Self>
+    where
+        S: Read,
+        R: TransferSyntaxIndex,
+        K: BulkDataSink,
+    {
+        let mut file = BufReader::new(src);
+
+        let (preamble, magic_read) =
+            detect_preamble(&mut file, settings.read_preamble).context(ReadPreambleBytesSnafu)?;
+
+        // read metadata header
+        let meta = read_meta(&mut file, magic_read)?;
+
+        // read rest of data according to metadata,
+        // leaving large values to the sink
+        if let Some(ts) = ts_index.get(&meta.transfer_syntax) {
+            let file = AdaptedReader::new(file, ts).context(ReadDataSetBytesSnafu)?;
+            let recorder = Some(ValueRecorder::default()).filter(|_| settings.preserve_encoding);
+            let file = RecordingReader::new(file, recorder.clone());
+            let mut dataset = BulkDataTokens::new_with_ts(file, ts, settings, sink, threshold)
+                .context(BulkDataSnafu)?;
+            let pending = dataset.pending();
             let obj = InMemDicomObject::build_object(
                 &mut dataset,
                 dict,
                 false,
                 Length::UNDEFINED,
-                read_until,
+                BuildOptions {
+                    bulk: Some(&pending),
+                    ..BuildOptions::new(settings, recorder.as_ref())
+                },
             )?;
-            Ok(FileDicomObject { meta, obj })
+            Ok(FileDicomObject {
+                meta,
+                obj,
+                preamble,
+                modified: false,
+            })
         } else {
             UnsupportedTransferSyntaxSnafu {
                 uid: meta.transfer_syntax,
@@ -357,6 +531,76 @@ where
             .fail()
         }
     }
+
+    pub(crate) fn from_reader_or_dataset_with_all_options<'s, S, R>(
+        src: S,
+        dict: D,
+        ts_index: R,
+        settings: &ReadSettings,
+        warnings: &mut Vec<ReadWarning>,
+    ) -> Result<Self>
+    where
+        S: Read + 's,
+        R: TransferSyntaxIndex,
+    {
+        let mut file = BufReader::new(src);
+
+        // look ahead for the magic code, with or without preamble
+        let mut head = Vec::with_capacity(132);
+        (&mut file)
+            .take(132)
+            .read_to_end(&mut head)
+            .context(ReadPreambleBytesSnafu)?;
+        let source = (&head[..]).chain(file);
+        if head.get(..4) == Some(b"DICM") || head.get(128..132) == Some(b"DICM") {
+            return Self::from_reader_with_all_options(source, dict, ts_index, settings, warnings);
+        }
+
+        let ts_uid =
+            detect_dataset_transfer_syntax(&head).context(UndetectedTransferSyntaxSnafu)?;
+        if head.get(..2) == Some(&[0x02, 0x00]) {
+            // file meta group without the magic code
+            return Self::from_reader_with_all_options(
+                (&b"DICM"[..]).chain(source),
+                dict,
+                ts_index,
+                &ReadSettings {
+                    read_preamble: ReadPreamble::Auto,
+                    ..*settings
+                },
+                warnings,
+            );
+        }
+
+        let ts = ts_index
+            .get(ts_uid)
+            .context(UnsupportedTransferSyntaxSnafu { uid: ts_uid })?;
+        let recorder = Some(ValueRecorder::default()).filter(|_| settings.preserve_encoding);
+        let source = RecordingReader::new(source, recorder.clone());
+        let mut dataset = DataSetReader::new_with_ts_cs_options(
+            source,
+            ts,
+            settings.charset,
+            settings.reader_options(),
+        )
+        .context(CreateParserSnafu)?;
+        let obj = InMemDicomObject::build_object(
+            &mut dataset,
+            dict,
+            false,
+            Length::UNDEFINED,
+            BuildOptions::new(settings, recorder.as_ref()),
+        );
+        settings.check_warnings(dataset.take_warnings(), warnings)?;
+        let obj = obj?;
+
+        let mut meta = FileMetaTableBuilder::new().transfer_syntax(ts_uid);
+        if let Some(elem) = obj.element_opt(tags::SOP_CLASS_UID)? {
+            meta = meta
+                .media_storage_sop_class_uid(elem.value().to_str().context(PrepareMetaTableSnafu)?);
+        }
+        obj.with_meta(meta)
+    }
 }
 
 impl FileDicomObject<InMemDicomObject<StandardDataDictionary>> {
@@ -368,7 +612,12 @@ impl FileDicomObject<InMemDicomObject<StandardDataDictionary>> {
                 entries: BTreeMap::new(),
                 dict: StandardDataDictionary,
                 len: Length::UNDEFINED,
+                originals: BTreeMap::new(),
+                bulk_data: BTreeMap::new(),
+                duplicates: Vec::new(),
             },
+            preamble: [0; 128],
+            modified: false,
         }
     }
 }
@@ -384,6 +633,9 @@ where
             entries: BTreeMap::new(),
             dict,
             len: Length::UNDEFINED,
+            originals: BTreeMap::new(),
+            bulk_data: BTreeMap::new(),
+            duplicates: Vec::new(),
         }
     }
 
@@ -397,6 +649,9 @@ where
             entries: entries?,
             dict,
             len: Length::UNDEFINED,
+            originals: BTreeMap::new(),
+            bulk_data: BTreeMap::new(),
+            duplicates: Vec::new(),
         })
     }
 
@@ -410,6 +665,9 @@ where
             entries,
             dict,
             len: Length::UNDEFINED,
+            originals: BTreeMap::new(),
+            bulk_data: BTreeMap::new(),
+            duplicates: Vec::new(),
         }
     }
 
@@ -422,7 +680,13 @@ where
         D: DataDictionary,
     {
         let mut dataset = DataSetReader::new(decoder, Default::default());
-        InMemDicomObject::build_object(&mut dataset, dict, false, Length::UNDEFINED, None)
+        InMemDicomObject::build_object(
+            &mut dataset,
+            dict,
+            false,
+            Length::UNDEFINED,
+            BuildOptions::default(),
+        )
     }
 
     /// Read an object from a source,
@@ -453,9 +717,15 @@ where
         S: Read,
         D: DataDictionary,
     {
-        let from = BufReader::new(from);
+        let from = AdaptedReader::new(BufReader::new(from), ts).context(ReadDataSetBytesSnafu)?;
         let mut dataset = DataSetReader::new_with_ts_cs(from, ts, cs).context(CreateParserSnafu)?;
-        InMemDicomObject::build_object(&mut dataset, dict, false, Length::UNDEFINED, None)
+        InMemDicomObject::build_object(
+            &mut dataset,
+            dict,
+            false,
+            Length::UNDEFINED,
+            BuildOptions::default(),
+        )
     }
 
     // Standard methods follow. They are not placed as a trait implementation
@@ -536,6 +806,182 @@ where
         }
     }
 
+    /// Retrieve a DICOM element by its path,
+    /// which may go through items of nested sequences.
+    ///
+    /// Fails if any sequence item along the path
+    /// or the element itself does not exist.
+    pub fn element_at(&self, path: &TagPath) -> Result<&InMemElement<D>> {
+        let mut obj = self;
+        for &(tag, index) in path.items() {
+            obj = obj
+                .element(tag)?
+                .items()
+                .and_then(|items| items.get(index as usize))
+                .context(NoSuchItemSnafu { tag, index })?;
+        }
+        obj.element(path.tag())
+    }
+
+    /// Retrieve the value of a DICOM element by its path,
+    /// which may go through items of nested sequences.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_core::{dicom_value, DataElement, Length, VR};
+    /// # use dicom_core::value::Value;
+    /// # use dicom_dictionary_std::tags;
+    /// # use dicom_object::InMemDicomObject;
+    /// let measures = InMemDicomObject::from_element_iter([DataElement::new(
+    ///     tags::PIXEL_SPACING,
+    ///     VR::DS,
+    ///     dicom_value!(Strs, ["0.5", "0.5"]),
+    /// )]);
+    /// let group = InMemDicomObject::from_element_iter([DataElement::new(
+    ///     tags::PIXEL_MEASURES_SEQUENCE,
+    ///     VR::SQ,
+    ///     Value::Sequence {
+    ///         items: vec![measures].into(),
+    ///         size: Length::UNDEFINED,
+    ///     },
+    /// )]);
+    /// let obj = InMemDicomObject::from_element_iter([DataElement::new(
+    ///     tags::SHARED_FUNCTIONAL_GROUPS_SEQUENCE,
+    ///     VR::SQ,
+    ///     Value::Sequence {
+    ///         items: vec![group].into(),
+    ///         size: Length::UNDEFINED,
+    ///     },
+    /// )]);
+    ///
+    /// let spacing = obj.value_at(
+    ///     &"SharedFunctionalGroupsSequence[0].PixelMeasuresSequence[0].PixelSpacing".parse()?,
+    /// )?;
+    /// assert_eq!(spacing.to_multi_float64()?, vec![0.5, 0.5]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn value_at(&self, path: &TagPath) -> Result<&Value<InMemDicomObject<D>, InMemFragment>> {
+        Ok(self.element_at(path)?.value())
+    }
+
+    /// Retrieve the value of a DICOM element at the given path
+    /// as a single string, with no trailing whitespace.
+    ///
+    /// The path may be a tag, a [`TagPath`],
+    /// or the text of a path,
+    /// in which keywords are resolved with the object's data dictionary.
+    /// Errors name the full path of the element.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_core::{DataElement, PrimitiveValue, VR};
+    /// # use dicom_dictionary_std::tags;
+    /// # use dicom_object::InMemDicomObject;
+    /// let item = InMemDicomObject::from_element_iter([
+    ///     DataElement::new(tags::REFERENCED_SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.2.3.4")),
+    /// ]);
+    /// let mut obj = InMemDicomObject::from_element_iter([
+    ///     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John ")),
+    ///     DataElement::new(tags::SERIES_NUMBER, VR::IS, PrimitiveValue::from("7")),
+    /// ]);
+    /// obj.push_item(tags::REFERENCED_IMAGE_SEQUENCE, item)?;
+    ///
+    /// assert_eq!(obj.get_str(tags::PATIENT_NAME)?, "Doe^John");
+    /// assert_eq!(obj.get_int::<i32>("SeriesNumber")?, 7);
+    /// assert_eq!(
+    ///     obj.get_str("ReferencedImageSequence[0].ReferencedSOPInstanceUID")?,
+    ///     "1.2.3.4",
+    /// );
+    /// assert_eq!(obj.get_seq(tags::REFERENCED_IMAGE_SEQUENCE)?.len(), 1);
+    ///
+    /// let e = obj.get_str("ReferencedImageSequence[0].SOPClassUID").unwrap_err();
+    /// assert_eq!(e.to_string(), "No such data element at (0008,1140)[0].(0008,0016)");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn get_str(&self, path: impl IntoTagPath) -> Result<Cow<'_, str>> {
+        let (path, elem) = self.resolve_path(path)?;
+        elem.to_str().context(CastValueAtSnafu { path })
+    }
+
+    /// Retrieve the value of a DICOM element at the given path
+    /// as a single integer.
+    ///
+    /// See [`get_str`](InMemDicomObject::get_str) for the accepted paths.
+    pub fn get_int<T>(&self, path: impl IntoTagPath) -> Result<T>
+    where
+        T: Clone,
+        T: num_traits::NumCast,
+        T: FromStr<Err = std::num::ParseIntError>,
+    {
+        let (path, elem) = self.resolve_path(path)?;
+        elem.to_int().context(ConvertValueAtSnafu { path })
+    }
+
+    /// Retrieve all values of a DICOM element at the given path
+    /// as floating point numbers.
+    ///
+    /// See [`get_str`](InMemDicomObject::get_str) for the accepted paths.
+    pub fn get_floats(&self, path: impl IntoTagPath) -> Result<Vec<f64>> {
+        let (path, elem) = self.resolve_path(path)?;
+        elem.to_multi_float64()
+            .context(ConvertValueAtSnafu { path })
+    }
+
+    /// Retrieve the value of a DICOM element at the given path
+    /// as a date.
+    ///
+    /// See [`get_str`](InMemDicomObject::get_str) for the accepted paths.
+    pub fn get_date(&self, path: impl IntoTagPath) -> Result<DicomDate> {
+        let (path, elem) = self.resolve_path(path)?;
+        elem.to_date().context(ConvertValueAtSnafu { path })
+    }
+
+    /// Retrieve the items of the sequence at the given path.
+    ///
+    /// See [`get_str`](InMemDicomObject::get_str) for the accepted paths.
+    pub fn get_seq(&self, path: impl IntoTagPath) -> Result<&[InMemDicomObject<D>]> {
+        let (path, elem) = self.resolve_path(path)?;
+        elem.items().context(NotASequenceSnafu { tag: path.tag() })
+    }
+
+    /// Resolve a path given to one of the typed getters,
+    /// reporting a missing element with its full path.
+    fn resolve_path(&self, path: impl IntoTagPath) -> Result<(TagPath, &InMemElement<D>)> {
+        let path = path
+            .into_tag_path(&self.dict)
+            .context(InvalidTagPathSnafu)?;
+        match self.element_at(&path) {
+            Ok(elem) => Ok((path, elem)),
+            Err(crate::Error::NoSuchDataElementTag { .. }) => {
+                NoSuchDataElementPathSnafu { path }.fail()
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Insert a data element with the given VR and value at the given path,
+    /// replacing (and returning) any previous element of the same attribute
+    /// in the same sequence item.
+    ///
+    /// Fails if any sequence item along the path does not exist.
+    pub fn put_at<V>(&mut self, path: &TagPath, vr: VR, value: V) -> Result<Option<InMemElement<D>>>
+    where
+        V: Into<Value<InMemDicomObject<D>, InMemFragment>>,
+    {
+        let elem = DataElement::new(path.tag(), vr, value);
+        self.update_item_at(path.items(), |obj| obj.put_element(elem))
+    }
+
+    /// Remove a DICOM element by its path,
+    /// reporting whether it was present.
+    ///
+    /// Fails if any sequence item along the path does not exist.
+    pub fn remove_at(&mut self, path: &TagPath) -> Result<bool> {
+        self.update_item_at(path.items(), |obj| obj.remove_element(path.tag()))
+    }
+
     /// Insert a data element to the object, replacing (and returning) any
     /// previous element of the same attribute.
     pub fn put(&mut self, elt: InMemElement<D>) -> Option<InMemElement<D>> {
@@ -545,24 +991,256 @@ where
     /// Insert a data element to the object, replacing (and returning) any
     /// previous element of the same attribute.
     pub fn put_element(&mut self, elt: InMemElement<D>) -> Option<InMemElement<D>> {
+        if elt.tag() == tags::SPECIFIC_CHARACTER_SET {
+            self.discard_original_encoding();
+        }
+        self.bulk_data.remove(&elt.tag());
         self.entries.insert(elt.tag(), elt)
     }
 
+    /// Insert a data element with the given value to the object,
+    /// replacing (and returning) any previous element of the same attribute.
+    ///
+    /// The VR of the element is the one of the attribute
+    /// in the object's data dictionary,
+    /// and the value is converted to suit it:
+    /// numbers are turned into text for textual VRs
+    /// (such as `IS` and `DS`),
+    /// and text or other numbers are parsed or cast
+    /// into the binary number type of numeric VRs
+    /// (such as `US` and `FD`).
+    /// Fails if the attribute is not in the dictionary
+    /// or the value cannot be converted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_core::VR;
+    /// # use dicom_dictionary_std::tags;
+    /// # use dicom_object::InMemDicomObject;
+    /// let mut obj = InMemDicomObject::new_empty();
+    /// obj.put_value(tags::ROWS, 512)?;
+    /// obj.put_value_by_name("SliceThickness", 1.5)?;
+    ///
+    /// let rows = obj.element(tags::ROWS)?;
+    /// assert_eq!(rows.vr(), VR::US);
+    /// assert_eq!(rows.to_int::<u16>()?, 512);
+    /// let thickness = obj.element(tags::SLICE_THICKNESS)?;
+    /// assert_eq!(thickness.vr(), VR::DS);
+    /// assert_eq!(thickness.to_str()?, "1.5");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn put_value<V>(&mut self, tag: Tag, value: V) -> Result<Option<InMemElement<D>>>
+    where
+        V: Into<PrimitiveValue>,
+    {
+        let vr = self
+            .dict
+            .by_tag(tag)
+            .context(NoSuchAttributeTagSnafu { tag })?
+            .vr();
+        let value = value_for_vr(vr, value.into()).context(ConvertValueSnafu { tag })?;
+        Ok(self.put_element(DataElement::new(tag, vr, value)))
+    }
+
+    /// Insert a data element with the given value to the object
+    /// by the attribute's keyword,
+    /// replacing (and returning) any previous element of the same attribute.
+    ///
+    /// See [`put_value`](InMemDicomObject::put_value)
+    /// for how the VR and value are determined.
+    pub fn put_value_by_name<V>(&mut self, name: &str, value: V) -> Result<Option<InMemElement<D>>>
+    where
+        V: Into<PrimitiveValue>,
+    {
+        let tag = self.lookup_name(name)?;
+        self.put_value(tag, value)
+    }
+
+    /// Insert an empty data element to the object,
+    /// replacing (and returning) any previous element of the same attribute.
+    ///
+    /// The element is written with a length of zero,
+    /// as expected of a Type 2 attribute without a known value.
+    /// If `vr` is `SQ`, the element is a sequence without any items.
+    pub fn put_empty(&mut self, tag: Tag, vr: VR) -> Option<InMemElement<D>> {
+        self.put_element(DataElement::empty(tag, vr))
+    }
+
+    /// Insert a sequence element without any items to the object,
+    /// replacing (and returning) any previous element of the same attribute.
+    ///
+    /// The sequence has an undefined length,
+    /// so that items can be added to it afterwards.
+    pub fn put_empty_sequence(&mut self, tag: Tag) -> Option<InMemElement<D>> {
+        self.put_element(DataElement::new(
+            tag,
+            VR::SQ,
+            Value::Sequence {
+                items: C::new(),
+                size: Length::UNDEFINED,
+            },
+        ))
+    }
+
+    /// Retrieve the items of the sequence element with the given tag
+    /// for in-place modification.
+    ///
+    /// Fails if the element does not exist or is not a sequence.
+    pub fn items_mut(&mut self, tag: Tag) -> Result<&mut [InMemDicomObject<D>]> {
+        Ok(self.sequence_mut(tag)?)
+    }
+
+    /// Add an item to the end of the sequence element with the given tag.
+    ///
+    /// An empty sequence is created first if the element does not exist.
+    /// Fails if the element exists but is not a sequence.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_core::{DataElement, PrimitiveValue, VR};
+    /// # use dicom_dictionary_std::tags;
+    /// # use dicom_object::InMemDicomObject;
+    /// let mut obj = InMemDicomObject::new_empty();
+    /// for uid in ["1.2.3.4", "1.2.3.5"] {
+    ///     obj.push_item(
+    ///         tags::REFERENCED_SERIES_SEQUENCE,
+    ///         InMemDicomObject::from_element_iter([DataElement::new(
+    ///             tags::SERIES_INSTANCE_UID,
+    ///             VR::UI,
+    ///             PrimitiveValue::from(uid),
+    ///         )]),
+    ///     )?;
+    /// }
+    ///
+    /// let removed = obj.remove_item(tags::REFERENCED_SERIES_SEQUENCE, 0)?;
+    /// assert_eq!(removed.element(tags::SERIES_INSTANCE_UID)?.to_str()?, "1.2.3.4");
+    /// for item in obj.items_mut(tags::REFERENCED_SERIES_SEQUENCE)? {
+    ///     item.put_value(tags::SERIES_NUMBER, 2)?;
+    /// }
+    ///
+    /// let items = obj.element(tags::REFERENCED_SERIES_SEQUENCE)?.items().unwrap();
+    /// assert_eq!(items.len(), 1);
+    /// assert_eq!(items[0].element(tags::SERIES_NUMBER)?.to_int::<i32>()?, 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn push_item(&mut self, tag: Tag, item: InMemDicomObject<D>) -> Result<()> {
+        if !self.entries.contains_key(&tag) {
+            self.put_empty_sequence(tag);
+        }
+        self.sequence_mut(tag)?.push(item);
+        Ok(())
+    }
+
+    /// Insert an item at position `index`
+    /// of the sequence element with the given tag,
+    /// shifting all items after it.
+    ///
+    /// Fails if the element does not exist or is not a sequence,
+    /// or if `index` is greater than the number of items.
+    pub fn insert_item(&mut self, tag: Tag, index: usize, item: InMemDicomObject<D>) -> Result<()> {
+        let items = self.sequence_mut(tag)?;
+        ensure!(
+            index <= items.len(),
+            NoSuchItemSnafu {
+                tag,
+                index: index as u32
+            }
+        );
+        items.insert(index, item);
+        Ok(())
+    }
+
+    /// Remove and return the item at position `index`
+    /// of the sequence element with the given tag,
+    /// shifting all items after it.
+    ///
+    /// Fails if the element does not exist or is not a sequence,
+    /// or if there is no item at that position.
+    pub fn remove_item(&mut self, tag: Tag, index: usize) -> Result<InMemDicomObject<D>> {
+        let items = self.sequence_mut(tag)?;
+        ensure!(
+            index < items.len(),
+            NoSuchItemSnafu {
+                tag,
+                index: index as u32
+            }
+        );
+        Ok(items.remove(index))
+    }
+
+    /// Obtain the entry of the data element with the given tag,
+    /// for in-place inspection and conditional insertion or modification.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_core::{DataElement, PrimitiveValue, VR};
+    /// # use dicom_core::value::Value;
+    /// # use dicom_dictionary_std::tags;
+    /// # use dicom_object::InMemDicomObject;
+    /// let mut obj = InMemDicomObject::from_element_iter([
+    ///     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+    /// ]);
+    ///
+    /// // set the patient ID if missing
+    /// obj.entry(tags::PATIENT_ID)
+    ///     .or_insert(VR::LO, PrimitiveValue::from("UNKNOWN"));
+    /// // change the patient name if present
+    /// obj.entry(tags::PATIENT_NAME).and_modify(|value| {
+    ///     *value = Value::from(PrimitiveValue::from("DOE^JOHN"));
+    /// });
+    ///
+    /// assert_eq!(obj.element(tags::PATIENT_ID)?.to_str()?, "UNKNOWN");
+    /// assert_eq!(obj.element(tags::PATIENT_NAME)?.to_str()?, "DOE^JOHN");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn entry(&mut self, tag: Tag) -> Entry<'_, D> {
+        if self.entries.contains_key(&tag) {
+            Entry::Occupied(OccupiedEntry { obj: self, tag })
+        } else {
+            Entry::Vacant(VacantEntry { obj: self, tag })
+        }
+    }
+
+    /// Check whether the object contains a data element with the given tag,
+    /// even if its value is empty.
+    ///
+    /// Use [`InMemElement::is_empty_value`] on the retrieved element
+    /// to distinguish present but empty attributes.
+    pub fn has_element(&self, tag: Tag) -> bool {
+        self.entries.contains_key(&tag)
+    }
+
+    /// Retrieve a particular DICOM element by its tag,
+    /// only if it is present with a non-empty value.
+    ///
+    /// Returns `None` both when the element is absent
+    /// and when it is present but empty.
+    pub fn non_empty_element(&self, tag: Tag) -> Option<&InMemElement<D>> {
+        self.entries.get(&tag).filter(|e| !e.is_empty_value())
+    }
+
     /// Remove a DICOM element by its tag,
     /// reporting whether it was present.
     pub fn remove_element(&mut self, tag: Tag) -> bool {
-        self.entries.remove(&tag).is_some()
+        self.take_element(tag).is_ok()
     }
 
     /// Remove a DICOM element by its keyword,
     /// reporting whether it was present.
     pub fn remove_element_by_name(&mut self, name: &str) -> Result<bool> {
         let tag = self.lookup_name(name)?;
-        Ok(self.entries.remove(&tag).is_some())
+        Ok(self.remove_element(tag))
     }
 
     /// Remove and return a particular DICOM element by its tag.
     pub fn take_element(&mut self, tag: Tag) -> Result<InMemElement<D>> {
+        if tag == tags::SPECIFIC_CHARACTER_SET {
+            self.discard_original_encoding();
+        }
+        self.bulk_data.remove(&tag);
         self.entries
             .remove(&tag)
             .context(NoSuchDataElementTagSnafu { tag })
@@ -571,6 +1249,10 @@ where
     /// Remove and return a particular DICOM element by its name.
     pub fn take_element_by_name(&mut self, name: &str) -> Result<InMemElement<D>> {
         let tag = self.lookup_name(name)?;
+        if tag == tags::SPECIFIC_CHARACTER_SET {
+            self.discard_original_encoding();
+        }
+        self.bulk_data.remove(&tag);
         self.entries
             .remove(&tag)
             .with_context(|| NoSuchDataElementAliasSnafu {
@@ -579,6 +1261,85 @@ where
             })
     }
 
+    /// Forget the original encoding of all values in this object,
+    /// including those in nested sequences,
+    /// so that they are all encoded anew when written.
+    ///
+    /// The original encoding is only retained
+    /// when reading with [`preserve_encoding`](crate::OpenFileOptions::preserve_encoding).
+    pub fn discard_original_encoding(&mut self) {
+        self.originals.clear();
+
+        let sequences: Vec<Tag> = self
+            .entries
+            .values()
+            .filter(|e| {
+                e.items()
+                    .map(|items| items.iter().any(|item| item.has_original_encoding()))
+                    .unwrap_or(false)
+            })
+            .map(|e| e.tag())
+            .collect();
+        for tag in sequences {
+            let elem = match self.entries.remove(&tag) {
+                Some(elem) => elem,
+                None => continue,
+            };
+            let len = elem.length();
+            let vr = elem.vr();
+            let value = match elem.into_value() {
+                Value::Sequence { mut items, size } => {
+                    for item in items.iter_mut() {
+                        item.discard_original_encoding();
+                    }
+                    Value::Sequence { items, size }
+                }
+                value => value,
+            };
+            self.entries
+                .insert(tag, DataElement::new_with_len(tag, vr, len, value));
+        }
+    }
+
+    /// Declare UTF-8 as the character set of all text in this object,
+    /// by setting _Specific Character Set_ to `ISO_IR 192`
+    /// in the root data set
+    /// and in the items of nested sequences which declare their own.
+    ///
+    /// Text values are kept in memory as Unicode strings
+    /// regardless of the character set they were read with,
+    /// so they are then written as UTF-8.
+    /// This also discards the original encoding of all values.
+    pub fn transcode_to_utf8(&mut self) {
+        self.put_element(DataElement::new(
+            tags::SPECIFIC_CHARACTER_SET,
+            VR::CS,
+            PrimitiveValue::from("ISO_IR 192"),
+        ));
+        crate::walk::walk_mut(self, |path, elem| {
+            if !path.items().is_empty() && elem.tag() == tags::SPECIFIC_CHARACTER_SET {
+                crate::walk::Edit::Replace(Box::new(DataElement::new(
+                    tags::SPECIFIC_CHARACTER_SET,
+                    VR::CS,
+                    PrimitiveValue::from("ISO_IR 192"),
+                )))
+            } else {
+                crate::walk::Edit::Keep
+            }
+        });
+    }
+
+    /// Check whether the original encoding of any value
+    /// in this object or in its nested sequences is retained.
+    fn has_original_encoding(&self) -> bool {
+        !self.originals.is_empty()
+            || self.entries.values().any(|e| {
+                e.items()
+                    .map(|items| items.iter().any(|item| item.has_original_encoding()))
+                    .unwrap_or(false)
+            })
+    }
+
     /// Modify the object by
     /// retaining only the DICOM data elements specified by the predicate.
     ///
@@ -586,35 +1347,338 @@ where
     /// and those for which `f(&element)` returns `false` are removed.
     pub fn retain(&mut self, mut f: impl FnMut(&InMemElement<D>) -> bool) {
         self.entries.retain(|_, elem| f(elem));
+        let entries = &self.entries;
+        self.bulk_data.retain(|tag, _| entries.contains_key(tag));
     }
 
-    /// Write this object's data set into the given writer,
-    /// with the given encoder specifications,
-    /// without preamble, magic code, nor file meta group.
+    /// Modify the object by
+    /// retaining only the DICOM data elements specified by the predicate,
+    /// in the root data set and in the items of all nested sequences.
     ///
-    /// The text encoding to use will be the default character set
-    /// until _Specific Character Set_ is found in the data set,
-    /// in which then that character set will be used.
+    /// The elements are visited in depth-first order,
+    /// each one with the path leading to it,
+    /// and those for which `f(&path, &element)` returns `false` are removed.
+    /// The items of a removed sequence are not visited.
+    /// Returns the number of elements removed.
     ///
-    /// Note: [`write_dataset_with_ts`] and [`write_dataset_with_ts_cs`]
-    /// may be easier to use.
+    /// # Example
     ///
-    /// [`write_dataset_with_ts`]: #method.write_dataset_with_ts
-    /// [`write_dataset_with_ts_cs`]: #method.write_dataset_with_ts_cs
-    pub fn write_dataset<W, E>(&self, to: W, encoder: E) -> Result<()>
-    where
-        W: Write,
-        E: EncodeTo<W>,
-    {
-        // prepare data set writer
-        let mut dset_writer = DataSetWriter::new(to, encoder);
-
-        // write object
-        dset_writer
-            .write_sequence(self.into_tokens())
-            .context(PrintDataSetSnafu)?;
-
-        Ok(())
+    /// ```
+    /// # use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+    /// # use dicom_core::header::Header;
+    /// # use dicom_dictionary_std::tags;
+    /// # use dicom_object::InMemDicomObject;
+    /// let item = InMemDicomObject::from_element_iter([
+    ///     DataElement::new(tags::CODE_VALUE, VR::SH, PrimitiveValue::from("T-D0050")),
+    ///     DataElement::new(Tag(0x0009, 0x0010), VR::LO, PrimitiveValue::from("ACME")),
+    /// ]);
+    /// let mut obj = InMemDicomObject::from_element_iter([
+    ///     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+    ///     DataElement::new(Tag(0x0009, 0x0010), VR::LO, PrimitiveValue::from("ACME")),
+    ///     DataElement::new(Tag(0x0009, 0x1001), VR::LO, PrimitiveValue::from("secret")),
+    /// ]);
+    /// obj.push_item(tags::ANATOMIC_REGION_SEQUENCE, item)?;
+    ///
+    /// assert_eq!(obj.remove_private(), 3);
+    /// assert_eq!(obj.get_seq(tags::ANATOMIC_REGION_SEQUENCE)?[0].tags().count(), 1);
+    ///
+    /// // remove patient attributes, wherever they are
+    /// let removed = obj.retain_recursive(|_path, elem| elem.tag().group() != 0x0010);
+    /// assert_eq!(removed, 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn retain_recursive(
+        &mut self,
+        mut f: impl FnMut(&TagPath, &InMemElement<D>) -> bool,
+    ) -> usize {
+        let mut removed = 0;
+        crate::walk::walk_mut(self, |path, elem| {
+            if f(path, elem) {
+                crate::walk::Edit::Keep
+            } else {
+                removed += 1;
+                crate::walk::Edit::Remove
+            }
+        });
+        removed
+    }
+
+    /// Remove all data elements of the given group,
+    /// in the root data set and in the items of all nested sequences.
+    ///
+    /// Returns the number of elements removed.
+    pub fn remove_group(&mut self, group: GroupNumber) -> usize {
+        self.retain_recursive(|_, elem| elem.tag().group() != group)
+    }
+
+    /// Remove all private data elements,
+    /// including private creators,
+    /// in the root data set and in the items of all nested sequences.
+    ///
+    /// Returns the number of elements removed.
+    pub fn remove_private(&mut self) -> usize {
+        self.retain_recursive(|_, elem| elem.tag().group() % 2 == 0)
+    }
+
+    /// Find the private block reserved by the given private creator
+    /// in a private group,
+    /// returning the block number (`0x10` to `0xFF`) if it exists.
+    ///
+    /// The block is reserved by the _Private Creator_ element
+    /// `(gggg,00xx)` whose value matches `creator`,
+    /// ignoring trailing padding.
+    pub fn private_block(&self, group: GroupNumber, creator: &str) -> Result<Option<u8>> {
+        ensure!(group & 1 == 1, NotPrivateGroupSnafu { group });
+        let creator = creator.trim_end_matches([' ', '\0']);
+        Ok(self
+            .entries
+            .range(Tag(group, 0x0010)..=Tag(group, 0x00FF))
+            .find(|(_, elem)| {
+                matches!(
+                    elem.to_str(),
+                    Ok(value) if value.trim_end_matches([' ', '\0']) == creator
+                )
+            })
+            .map(|(tag, _)| tag.element() as u8))
+    }
+
+    /// Retrieve a private data element
+    /// by its group, private creator, and element number within the block.
+    ///
+    /// The resolved tag is `(gggg,xxee)`,
+    /// where `xx` is the block reserved by `creator` in this object
+    /// and `ee` is the given `element` byte.
+    /// This avoids hard-coding block offsets,
+    /// which may differ between files.
+    ///
+    /// An error is returned if the private creator
+    /// or the element do not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_core::{PrimitiveValue, Tag, VR};
+    /// # use dicom_core::header::Header;
+    /// # use dicom_object::InMemDicomObject;
+    /// let mut obj = InMemDicomObject::new_empty();
+    /// obj.put_private_element(0x0029, "ACME 1.0", 0x10, VR::LO, PrimitiveValue::from("foo"))?;
+    ///
+    /// let elem = obj.private_element(0x0029, "ACME 1.0", 0x10)?;
+    /// assert_eq!(elem.tag(), Tag(0x0029, 0x1010));
+    /// assert_eq!(elem.to_str()?, "foo");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn private_element(
+        &self,
+        group: GroupNumber,
+        creator: &str,
+        element: u8,
+    ) -> Result<&InMemElement<D>> {
+        let block =
+            self.private_block(group, creator)?
+                .with_context(|| NoSuchPrivateCreatorSnafu {
+                    group,
+                    creator: creator.to_string(),
+                })?;
+        self.element(private_tag(group, block, element))
+    }
+
+    /// Reserve a private block for the given private creator,
+    /// returning the block number.
+    ///
+    /// If the creator already holds a block in the group,
+    /// that block is returned.
+    /// Otherwise, a _Private Creator_ element is inserted
+    /// at the first free block.
+    pub fn reserve_private_block(&mut self, group: GroupNumber, creator: &str) -> Result<u8> {
+        if let Some(block) = self.private_block(group, creator)? {
+            return Ok(block);
+        }
+        let block = (0x10..=0xFF)
+            .find(|&block| !self.entries.contains_key(&Tag(group, block)))
+            .context(NoFreePrivateBlockSnafu { group })?;
+        self.put_element(DataElement::new(
+            Tag(group, block),
+            VR::LO,
+            PrimitiveValue::from(creator),
+        ));
+        Ok(block as u8)
+    }
+
+    /// Insert a private data element
+    /// by its group, private creator, and element number within the block,
+    /// replacing (and returning) any previous element of the same attribute.
+    ///
+    /// A private block is reserved for `creator`
+    /// if the object does not have one yet
+    /// (see [`reserve_private_block`](InMemDicomObject::reserve_private_block)).
+    pub fn put_private_element<V>(
+        &mut self,
+        group: GroupNumber,
+        creator: &str,
+        element: u8,
+        vr: VR,
+        value: V,
+    ) -> Result<Option<InMemElement<D>>>
+    where
+        V: Into<Value<InMemDicomObject<D>, InMemFragment>>,
+    {
+        let block = self.reserve_private_block(group, creator)?;
+        let tag = private_tag(group, block, element);
+        Ok(self.put_element(DataElement::new(tag, vr, value)))
+    }
+
+    /// Retrieve the reference to the value of the element with the given tag,
+    /// if the value was written to a bulk data sink while reading
+    /// (see [`open_file_with_bulk_data`](crate::OpenFileOptions::open_file_with_bulk_data)).
+    ///
+    /// The element itself is kept in the object with an empty value.
+    /// The reference is forgotten once the element is replaced or removed.
+    pub fn bulk_data(&self, tag: Tag) -> Option<&BulkDataRef> {
+        self.bulk_data.get(&tag)
+    }
+
+    /// Retrieve the elements which were replaced while reading
+    /// by a later element with the same tag,
+    /// in the root of this object,
+    /// in the order in which they were read.
+    ///
+    /// These are only collected with
+    /// [`DuplicateTagPolicy::CollectAll`](crate::file::DuplicateTagPolicy::CollectAll),
+    /// and are not written with the object.
+    pub fn duplicate_elements(&self) -> &[InMemElement<D>] {
+        &self.duplicates
+    }
+
+    /// Iterate over the references to the values
+    /// written to a bulk data sink while reading,
+    /// in the root of this object.
+    pub fn iter_bulk_data(&self) -> impl Iterator<Item = (Tag, &BulkDataRef)> + '_ {
+        self.bulk_data.iter().map(|(tag, bulk)| (*tag, bulk))
+    }
+
+    /// Place the value of an element back into the object,
+    /// given the bytes which were written to a bulk data sink,
+    /// and return the reference which it replaces.
+    ///
+    /// The bytes are kept as they are,
+    /// in the transfer syntax of the source which was read.
+    /// Returns `None` and leaves the object unchanged
+    /// if there is no reference to the element's value.
+    pub fn restore_bulk_data(&mut self, tag: Tag, bytes: Vec<u8>) -> Option<BulkDataRef> {
+        let bulk = self.bulk_data.remove(&tag)?;
+        self.entries.insert(
+            tag,
+            DataElement::new(tag, bulk.vr(), PrimitiveValue::from(bytes)),
+        );
+        Some(bulk)
+    }
+
+    /// Retrieve the offset from UTC declared by this object's
+    /// _Timezone Offset From UTC_ (0008,0201) attribute.
+    ///
+    /// Returns `None` if the attribute is absent or empty.
+    pub fn timezone_offset(&self) -> Result<Option<FixedOffset>> {
+        let tag = tags::TIMEZONE_OFFSET_FROM_UTC;
+        let elem = match self.element_opt(tag)? {
+            Some(elem) => elem,
+            None => return Ok(None),
+        };
+        let value = elem.to_str().context(CastValueSnafu { tag })?;
+        let value = value.trim_matches([' ', '\0']);
+        if value.is_empty() {
+            return Ok(None);
+        }
+        parse_utc_offset(value.as_bytes())
+            .map(Some)
+            .context(InvalidTimezoneOffsetSnafu { value })
+    }
+
+    /// Retrieve the value of a DT element by its tag
+    /// as a timezone-aware date-time.
+    ///
+    /// If the value does not specify its own offset from UTC,
+    /// the one in _Timezone Offset From UTC_ (0008,0201) is used.
+    /// `default_offset` is only used
+    /// when neither of them is present.
+    ///
+    /// Values already held as a [`DicomDateTime`]
+    /// are returned as is.
+    pub fn to_datetime_with_timezone(
+        &self,
+        tag: Tag,
+        default_offset: FixedOffset,
+    ) -> Result<DicomDateTime> {
+        let offset = self.timezone_offset()?.unwrap_or(default_offset);
+        self.element(tag)?
+            .to_datetime(offset)
+            .context(ConvertValueSnafu { tag })
+    }
+
+    /// Combine the values of a DA element and a TM element
+    /// into a timezone-aware date-time,
+    /// such as _Study Date_ (0008,0020) and _Study Time_ (0008,0030).
+    ///
+    /// The offset from UTC is taken from
+    /// _Timezone Offset From UTC_ (0008,0201) if present,
+    /// or `default_offset` otherwise.
+    /// If the time element is absent or empty,
+    /// the resulting date-time will only contain the date.
+    pub fn to_date_time_with_timezone(
+        &self,
+        date_tag: Tag,
+        time_tag: Tag,
+        default_offset: FixedOffset,
+    ) -> Result<DicomDateTime> {
+        let offset = self.timezone_offset()?.unwrap_or(default_offset);
+        let date = self
+            .element(date_tag)?
+            .to_date()
+            .context(ConvertValueSnafu { tag: date_tag })?;
+
+        let time = match self.element_opt(time_tag)? {
+            Some(elem) if !is_empty_text(elem) => Some(
+                elem.to_time()
+                    .context(ConvertValueSnafu { tag: time_tag })?,
+            ),
+            _ => None,
+        };
+
+        match time {
+            Some(time) => {
+                DicomDateTime::from_date_and_time(date, time, offset).context(CombineDateTimeSnafu)
+            }
+            None => Ok(DicomDateTime::from_date(date, offset)),
+        }
+    }
+
+    /// Write this object's data set into the given writer,
+    /// with the given encoder specifications,
+    /// without preamble, magic code, nor file meta group.
+    ///
+    /// The text encoding to use will be the default character set
+    /// until _Specific Character Set_ is found in the data set,
+    /// in which then that character set will be used.
+    ///
+    /// Note: [`write_dataset_with_ts`] and [`write_dataset_with_ts_cs`]
+    /// may be easier to use.
+    ///
+    /// [`write_dataset_with_ts`]: #method.write_dataset_with_ts
+    /// [`write_dataset_with_ts_cs`]: #method.write_dataset_with_ts_cs
+    pub fn write_dataset<W, E>(&self, to: W, encoder: E) -> Result<()>
+    where
+        W: Write,
+        E: EncodeTo<W>,
+    {
+        // prepare data set writer
+        let mut dset_writer = DataSetWriter::new(to, encoder);
+
+        // write object
+        dset_writer
+            .write_sequence(self.into_tokens())
+            .context(PrintDataSetSnafu)?;
+
+        Ok(())
     }
 
     /// Write this object's data set into the given printer,
@@ -623,6 +1687,9 @@ where
     ///
     /// If the attribute _Specific Character Set_ is found in the data set,
     /// the last parameter is overridden accordingly.
+    ///
+    /// The lengths of sequences and items with an explicit length
+    /// are recalculated from their contents.
     pub fn write_dataset_with_ts_cs<W>(
         &self,
         to: W,
@@ -632,13 +1699,50 @@ where
     where
         W: Write,
     {
+        self.write_dataset_with_ts_cs_options(to, ts, cs, &WriteOptions::default())
+    }
+
+    /// Write this object's data set into the given printer,
+    /// with the specified transfer syntax, character set,
+    /// and writing options,
+    /// without preamble, magic code, nor file meta group.
+    ///
+    /// If the attribute _Specific Character Set_ is found in the data set,
+    /// the character set parameter is overridden accordingly.
+    pub fn write_dataset_with_ts_cs_options<W>(
+        &self,
+        to: W,
+        ts: &TransferSyntax,
+        cs: SpecificCharacterSet,
+        options: &WriteOptions,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        // apply the data set codec of the transfer syntax, if any
+        let mut to = match options.get_compression_level() {
+            Some(level) => AdaptedWriter::with_compression_level(to, ts, level),
+            None => AdaptedWriter::new(to, ts),
+        };
         // prepare data set writer
-        let mut dset_writer = DataSetWriter::with_ts_cs(to, ts, cs).context(CreatePrinterSnafu)?;
+        let mut dset_writer =
+            DataSetWriter::with_ts_cs(&mut to, ts, cs).context(CreatePrinterSnafu)?;
 
-        // write object
-        dset_writer
-            .write_sequence(self.into_tokens())
-            .context(PrintDataSetSnafu)?;
+        // write object, with sequence and item lengths as requested
+        let tokens = ExplicitLengthTokens::new(
+            self.into_tokens(),
+            ts,
+            cs,
+            options.get_explicit_length_sq_item_strategy(),
+        );
+        let tokens = GroupLengthTokens::new(tokens, ts, cs, options.get_group_length_strategy());
+        for token in tokens {
+            dset_writer
+                .write(token.context(PrintDataSetSnafu)?)
+                .context(PrintDataSetSnafu)?;
+        }
+        drop(dset_writer);
+        to.finish().context(WriteDataSetSnafu)?;
 
         Ok(())
     }
@@ -657,13 +1761,53 @@ where
         self.write_dataset_with_ts_cs(to, ts, SpecificCharacterSet::Default)
     }
 
+    /// Calculate the exact number of bytes of this object's data set
+    /// when written with the given transfer syntax and options,
+    /// without preamble, magic code, nor file meta group.
+    ///
+    /// The data set is encoded as by
+    /// [`write_dataset_with_ts_cs_options`](Self::write_dataset_with_ts_cs_options)
+    /// with the default character set,
+    /// but the encoded bytes are only counted, not kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use dicom_core::{DataElement, PrimitiveValue, VR};
+    /// # use dicom_dictionary_std::tags;
+    /// # use dicom_object::{InMemDicomObject, WriteOptions};
+    /// # use dicom_transfer_syntax_registry::entries::EXPLICIT_VR_LITTLE_ENDIAN;
+    /// let obj = InMemDicomObject::from_element_iter([
+    ///     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+    /// ]);
+    /// let len = obj.encoded_len(&EXPLICIT_VR_LITTLE_ENDIAN.erased(), &WriteOptions::new())?;
+    /// // 8 bytes of header and 8 bytes of value
+    /// assert_eq!(len, 16);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn encoded_len(&self, ts: &TransferSyntax, options: &WriteOptions) -> Result<u64> {
+        let mut counter = ByteCounter(0);
+        self.write_dataset_with_ts_cs_options(
+            &mut counter,
+            ts,
+            SpecificCharacterSet::Default,
+            options,
+        )?;
+        Ok(counter.0)
+    }
+
     /// Encapsulate this object to contain a file meta group
     /// as described exactly by the given table.
     ///
     /// **Note:** this method will not adjust the file meta group
     /// to be semantically valid for the object.
     pub fn with_exact_meta(self, meta: FileMetaTable) -> FileDicomObject<Self> {
-        FileDicomObject { meta, obj: self }
+        FileDicomObject {
+            meta,
+            obj: self,
+            preamble: [0; 128],
+            modified: false,
+        }
     }
 
     /// Encapsulate this object to contain a file meta group,
@@ -683,6 +1827,8 @@ where
         Ok(FileDicomObject {
             meta: meta.build().context(BuildMetaTableSnafu)?,
             obj: self,
+            preamble: [0; 128],
+            modified: false,
         })
     }
 
@@ -699,20 +1845,33 @@ where
     // private methods
 
     /// Build an object by consuming a data set parser.
-    fn build_object<I: ?Sized>(
+    fn build_object<I, E>(
         dataset: &mut I,
         dict: D,
         in_item: bool,
         len: Length,
-        read_until: Option<Tag>,
+        options: BuildOptions<'_>,
     ) -> Result<Self>
     where
-        I: Iterator<Item = ParserResult<DataToken>>,
+        I: ?Sized + Iterator<Item = std::result::Result<DataToken, E>>,
+        E: TokenSourceError,
     {
+        let BuildOptions {
+            read_until,
+            recorder,
+            bulk,
+            duplicate_tags,
+        } = options;
         let mut entries: BTreeMap<Tag, InMemElement<D>> = BTreeMap::new();
+        let mut originals: BTreeMap<Tag, OriginalValue> = BTreeMap::new();
+        let mut bulk_data: BTreeMap<Tag, BulkDataRef> = BTreeMap::new();
+        let mut duplicates = Vec::new();
+        let mut last_tag = None;
         // perform a structured parsing of incoming tokens
         while let Some(token) = dataset.next() {
-            let elem = match token.context(ReadTokenSnafu)? {
+            let mut original = None;
+            let mut bulk_ref = None;
+            let elem = match token.map_err(E::into_error)? {
                 DataToken::PixelSequenceStart => {
                     // stop reading if reached `read_until` tag
                     if read_until
@@ -724,21 +1883,58 @@ where
                     let value = InMemDicomObject::build_encapsulated_data(&mut *dataset)?;
                     DataElement::new(Tag(0x7fe0, 0x0010), VR::OB, value)
                 }
+                DataToken::FragmentSequenceStart { tag, vr } => {
+                    // stop reading if reached `read_until` tag
+                    if read_until.map(|t| t <= tag).unwrap_or(false) {
+                        break;
+                    }
+                    // fragments are collected in the same way as pixel data
+                    let value = InMemDicomObject::build_encapsulated_data(&mut *dataset)?;
+                    DataElement::new(tag, vr, value)
+                }
                 DataToken::ElementHeader(header) => {
                     // stop reading if reached `read_until` tag
                     if read_until.map(|t| t <= header.tag).unwrap_or(false) {
                         break;
                     }
 
+                    // record the encoded value if it may not be reproduced,
+                    // except for the character set which governs the others
+                    let recorder = recorder.filter(|_| {
+                        header.tag != tags::SPECIFIC_CHARACTER_SET
+                            && OriginalValue::should_keep(header.vr, header.len.0)
+                    });
+                    if let Some(recorder) = recorder {
+                        recorder.start();
+                    }
+
                     // fetch respective value, place it in the entries
                     let next_token = dataset.next().context(MissingElementValueSnafu)?;
-                    match next_token.context(ReadTokenSnafu)? {
-                        DataToken::PrimitiveValue(v) => InMemElement::new_with_len(
-                            header.tag,
-                            header.vr,
-                            header.len,
-                            Value::Primitive(v),
-                        ),
+                    match next_token.map_err(E::into_error)? {
+                        DataToken::PrimitiveValue(v) => {
+                            // externalized values are kept as empty elements
+                            if let Some(r) = bulk.and_then(PendingBulkData::take) {
+                                if let Some(recorder) = recorder {
+                                    recorder.take();
+                                }
+                                bulk_ref = Some(r);
+                                DataElement::empty(header.tag, header.vr)
+                            } else {
+                                if let Some(recorder) = recorder {
+                                    original = Some(OriginalValue {
+                                        vr: header.vr,
+                                        value: v.clone(),
+                                        bytes: recorder.take(),
+                                    });
+                                }
+                                InMemElement::new_with_len(
+                                    header.tag,
+                                    header.vr,
+                                    header.len,
+                                    Value::Primitive(v),
+                                )
+                            }
+                        }
                         token => {
                             return UnexpectedTokenSnafu { token }.fail();
                         }
@@ -751,7 +1947,7 @@ where
                     }
 
                     // delegate sequence building to another function
-                    let items = Self::build_sequence(tag, len, &mut *dataset, &dict)?;
+                    let items = Self::build_sequence(tag, len, &mut *dataset, &dict, options)?;
                     DataElement::new_with_len(
                         tag,
                         VR::SQ,
@@ -761,21 +1957,64 @@ where
                 }
                 DataToken::ItemEnd if in_item => {
                     // end of item, leave now
-                    return Ok(InMemDicomObject { entries, dict, len });
+                    return Ok(InMemDicomObject {
+                        entries,
+                        dict,
+                        len,
+                        originals,
+                        bulk_data,
+                        duplicates,
+                    });
                 }
                 token => return UnexpectedTokenSnafu { token }.fail(),
             };
-            entries.insert(elem.tag(), elem);
+
+            let tag = elem.tag();
+            let previous = last_tag.replace(tag);
+            if entries.contains_key(&tag) {
+                match duplicate_tags {
+                    DuplicateTagPolicy::KeepFirst => continue,
+                    DuplicateTagPolicy::Fail => return DuplicateTagSnafu { tag }.fail(),
+                    DuplicateTagPolicy::KeepLast | DuplicateTagPolicy::CollectAll => {}
+                }
+            } else if duplicate_tags == DuplicateTagPolicy::Fail {
+                if let Some(previous) = previous.filter(|previous| *previous > tag) {
+                    return TagOutOfOrderSnafu { tag, previous }.fail();
+                }
+            }
+            match original {
+                Some(original) => originals.insert(tag, original),
+                None => originals.remove(&tag),
+            };
+            match bulk_ref {
+                Some(bulk_ref) => bulk_data.insert(tag, bulk_ref),
+                None => bulk_data.remove(&tag),
+            };
+            if let Some(replaced) = entries.insert(tag, elem) {
+                if duplicate_tags == DuplicateTagPolicy::CollectAll {
+                    duplicates.push(replaced);
+                }
+            }
         }
 
-        Ok(InMemDicomObject { entries, dict, len })
+        Ok(InMemDicomObject {
+            entries,
+            dict,
+            len,
+            originals,
+            bulk_data,
+            duplicates,
+        })
     }
 
     /// Build an encapsulated pixel data by collecting all fragments into an
     /// in-memory DICOM value.
-    fn build_encapsulated_data<I>(dataset: I) -> Result<Value<InMemDicomObject<D>, InMemFragment>>
+    fn build_encapsulated_data<I, E>(
+        dataset: I,
+    ) -> Result<Value<InMemDicomObject<D>, InMemFragment>>
     where
-        I: Iterator<Item = ParserResult<DataToken>>,
+        I: Iterator<Item = std::result::Result<DataToken, E>>,
+        E: TokenSourceError,
     {
         // continue fetching tokens to retrieve:
         // - the offset table
@@ -790,7 +2029,7 @@ where
         let mut fragments = C::new();
 
         for token in dataset {
-            match token.context(ReadTokenSnafu)? {
+            match token.map_err(E::into_error)? {
                 DataToken::OffsetTable(table) => {
                     offset_table = Some(table);
                 }
@@ -813,8 +2052,10 @@ where
                 // the following variants are unexpected
                 token @ DataToken::ElementHeader(_)
                 | token @ DataToken::PixelSequenceStart
+                | token @ DataToken::FragmentSequenceStart { .. }
                 | token @ DataToken::SequenceStart { .. }
-                | token @ DataToken::PrimitiveValue(_) => {
+                | token @ DataToken::PrimitiveValue(_)
+                | token @ DataToken::RawValue(_) => {
                     return UnexpectedTokenSnafu { token }.fail();
                 }
             }
@@ -827,25 +2068,30 @@ where
     }
 
     /// Build a DICOM sequence by consuming a data set parser.
-    fn build_sequence<I: ?Sized>(
+    fn build_sequence<I, E>(
         _tag: Tag,
         _len: Length,
         dataset: &mut I,
         dict: &D,
+        options: BuildOptions<'_>,
     ) -> Result<C<InMemDicomObject<D>>>
     where
-        I: Iterator<Item = ParserResult<DataToken>>,
+        I: ?Sized + Iterator<Item = std::result::Result<DataToken, E>>,
+        E: TokenSourceError,
     {
         let mut items: C<_> = SmallVec::new();
         while let Some(token) = dataset.next() {
-            match token.context(ReadTokenSnafu)? {
+            match token.map_err(E::into_error)? {
                 DataToken::ItemStart { len } => {
                     items.push(Self::build_object(
                         &mut *dataset,
                         dict.clone(),
                         true,
                         len,
-                        None,
+                        BuildOptions {
+                            read_until: None,
+                            ..options
+                        },
                     )?);
                 }
                 DataToken::SequenceEnd => {
@@ -859,6 +2105,47 @@ where
         PrematureEndSnafu.fail()
     }
 
+    /// Apply a modification to the sequence item
+    /// reached through the given sequence tags and item indices.
+    fn update_item_at<T>(
+        &mut self,
+        items: &[(Tag, u32)],
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> Result<T> {
+        let (&(tag, index), rest) = match items.split_first() {
+            Some(step) => step,
+            None => return Ok(f(self)),
+        };
+
+        let elem = self
+            .entries
+            .remove(&tag)
+            .context(NoSuchDataElementTagSnafu { tag })?;
+        let vr = elem.vr();
+        let len = elem.length();
+        let mut result = None;
+        let value = match elem.into_value() {
+            Value::Sequence { mut items, size } => {
+                if let Some(item) = items.get_mut(index as usize) {
+                    result = Some(item.update_item_at(rest, f));
+                }
+                Value::Sequence { items, size }
+            }
+            value => value,
+        };
+        self.entries
+            .insert(tag, DataElement::new_with_len(tag, vr, len, value));
+        result.unwrap_or_else(|| NoSuchItemSnafu { tag, index }.fail())
+    }
+
+    fn sequence_mut(&mut self, tag: Tag) -> Result<&mut C<InMemDicomObject<D>>> {
+        self.entries
+            .get_mut(&tag)
+            .context(NoSuchDataElementTagSnafu { tag })?
+            .items_mut()
+            .context(NotASequenceSnafu { tag })
+    }
+
     fn lookup_name(&self, name: &str) -> Result<Tag> {
         self.dict
             .by_name(name)
@@ -867,6 +2154,51 @@ where
     }
 }
 
+/// The tag of a private element within the given private block.
+fn private_tag(group: GroupNumber, block: u8, element: u8) -> Tag {
+    Tag(group, u16::from(block) << 8 | u16::from(element))
+}
+
+/// Convert a primitive value into a representation suitable for the given VR.
+fn value_for_vr(vr: VR, value: PrimitiveValue) -> Result<PrimitiveValue, ConvertValueError> {
+    use PrimitiveValue::*;
+
+    let is_number = matches!(
+        value,
+        I16(_) | U16(_) | I32(_) | U32(_) | I64(_) | U64(_) | F32(_) | F64(_)
+    );
+    Ok(match (vr, value) {
+        (_, Empty) => Empty,
+        (VR::SQ, value) => {
+            return Err(ConvertValueError {
+                requested: "sequence",
+                original: value.value_type(),
+                cause: None,
+            })
+        }
+        (vr, value) if is_number && is_textual(vr) => {
+            Strs(value.to_multi_str().into_owned().into())
+        }
+        (VR::US, value @ U16(_))
+        | (VR::SS, value @ I16(_))
+        | (VR::UL, value @ U32(_))
+        | (VR::SL, value @ I32(_))
+        | (VR::UV, value @ U64(_))
+        | (VR::SV, value @ I64(_))
+        | (VR::FL, value @ F32(_))
+        | (VR::FD, value @ F64(_)) => value,
+        (VR::US, value) => U16(value.to_multi_int()?.into()),
+        (VR::SS, value) => I16(value.to_multi_int()?.into()),
+        (VR::UL, value) => U32(value.to_multi_int()?.into()),
+        (VR::SL, value) => I32(value.to_multi_int()?.into()),
+        (VR::UV, value) => U64(value.to_multi_int()?.into()),
+        (VR::SV, value) => I64(value.to_multi_int()?.into()),
+        (VR::FL, value) => F32(value.to_multi_float32()?.into()),
+        (VR::FD, value) => F64(value.to_multi_float64()?.into()),
+        (_, value) => value,
+    })
+}
+
 impl<'a, D> IntoIterator for &'a InMemDicomObject<D> {
     type Item = &'a InMemElement<D>;
     type IntoIter = ::std::collections::btree_map::Values<'a, Tag, InMemElement<D>>;
@@ -918,6 +2250,175 @@ impl<D> Extend<InMemElement<D>> for InMemDicomObject<D> {
     }
 }
 
+/// A view into a single data element of an in-memory DICOM object,
+/// which may either be present or absent.
+///
+/// This is obtained through [`InMemDicomObject::entry`].
+/// Unlike the entries of standard maps,
+/// elements are not exposed mutably,
+/// so that changes go through the object
+/// and its bookkeeping of character sets and bulk data.
+#[derive(Debug)]
+pub enum Entry<'a, D> {
+    /// The element is present.
+    Occupied(OccupiedEntry<'a, D>),
+    /// The element is absent.
+    Vacant(VacantEntry<'a, D>),
+}
+
+/// A view into a data element which is present in the object.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, D> {
+    obj: &'a mut InMemDicomObject<D>,
+    tag: Tag,
+}
+
+/// A view into a data element which is absent from the object.
+#[derive(Debug)]
+pub struct VacantEntry<'a, D> {
+    obj: &'a mut InMemDicomObject<D>,
+    tag: Tag,
+}
+
+impl<'a, D> Entry<'a, D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Obtain the tag of the entry's element.
+    pub fn tag(&self) -> Tag {
+        match self {
+            Entry::Occupied(entry) => entry.tag,
+            Entry::Vacant(entry) => entry.tag,
+        }
+    }
+
+    /// Insert an element with the given VR and value if it is absent,
+    /// and return the element in the object.
+    pub fn or_insert<V>(self, vr: VR, value: V) -> &'a InMemElement<D>
+    where
+        V: Into<Value<InMemDicomObject<D>, InMemFragment>>,
+    {
+        self.or_insert_with(vr, || value)
+    }
+
+    /// Insert an element with the given VR
+    /// and the value produced by `f` if it is absent,
+    /// and return the element in the object.
+    ///
+    /// The function is not called if the element is present.
+    pub fn or_insert_with<V, F>(self, vr: VR, f: F) -> &'a InMemElement<D>
+    where
+        V: Into<Value<InMemDicomObject<D>, InMemFragment>>,
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(vr, f()),
+        }
+    }
+
+    /// Modify the value of the element if it is present,
+    /// keeping its VR.
+    ///
+    /// The element's length is updated to the modified value.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut Value<InMemDicomObject<D>, InMemFragment>),
+    {
+        match self {
+            Entry::Occupied(entry) => {
+                let tag = entry.tag;
+                let obj = entry.obj;
+                if let Some(elem) = obj.entries.remove(&tag) {
+                    let vr = elem.vr();
+                    let mut value = elem.into_value();
+                    f(&mut value);
+                    obj.put_element(DataElement::new(tag, vr, value));
+                }
+                Entry::Occupied(OccupiedEntry { obj, tag })
+            }
+            entry => entry,
+        }
+    }
+}
+
+impl<'a, D> OccupiedEntry<'a, D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Obtain the tag of the entry's element.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Retrieve the element.
+    pub fn get(&self) -> &InMemElement<D> {
+        &self.obj.entries[&self.tag]
+    }
+
+    /// Convert the entry into a reference to the element in the object.
+    pub fn into_ref(self) -> &'a InMemElement<D> {
+        &self.obj.entries[&self.tag]
+    }
+
+    /// Replace the element with one of the given VR and value,
+    /// returning the previous element.
+    pub fn insert<V>(&mut self, vr: VR, value: V) -> InMemElement<D>
+    where
+        V: Into<Value<InMemDicomObject<D>, InMemFragment>>,
+    {
+        self.obj
+            .put_element(DataElement::new(self.tag, vr, value))
+            .expect("occupied entry should have an element")
+    }
+
+    /// Remove the element from the object and return it.
+    pub fn remove(self) -> InMemElement<D> {
+        self.obj
+            .take_element(self.tag)
+            .expect("occupied entry should have an element")
+    }
+}
+
+impl<'a, D> VacantEntry<'a, D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Obtain the tag of the entry's element.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Insert an element with the given VR and value,
+    /// and return the element in the object.
+    pub fn insert<V>(self, vr: VR, value: V) -> &'a InMemElement<D>
+    where
+        V: Into<Value<InMemDicomObject<D>, InMemFragment>>,
+    {
+        self.obj.put_element(DataElement::new(self.tag, vr, value));
+        &self.obj.entries[&self.tag]
+    }
+}
+
+/// Check whether the element's value is empty
+/// or consists of padding characters only.
+fn is_empty_text<D>(elem: &InMemElement<D>) -> bool {
+    elem.to_str()
+        .map(|s| s.trim_matches([' ', '\0']).is_empty())
+        .unwrap_or(false)
+}
+
+/// Read the file meta group from the given source,
+/// taking into account whether the magic code was already read.
+fn read_meta<S: Read>(file: &mut S, magic_read: bool) -> Result<FileMetaTable> {
+    if magic_read {
+        FileMetaTable::from_reader((&b"DICM"[..]).chain(file))
+    } else {
+        FileMetaTable::from_reader(file)
+    }
+    .context(ParseMetaDataSetSnafu)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -990,6 +2491,194 @@ mod tests {
         assert_eq!(obj, gt);
     }
 
+    #[test]
+    fn inmem_object_explicit_vr_big_endian_roundtrip() {
+        use smallvec::smallvec;
+
+        let item = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::REFERENCED_FRAME_NUMBER,
+            VR::IS,
+            Value::Primitive("1".into()),
+        )]);
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::PATIENT_NAME,
+                VR::PN,
+                Value::Primitive("Doe^John".into()),
+            ),
+            DataElement::new(tags::ROWS, VR::US, Value::Primitive(2_u16.into())),
+            DataElement::new(
+                tags::REFERENCED_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: smallvec![item],
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OW,
+                Value::Primitive(PrimitiveValue::U16([0x0102, 0x0304].as_ref().into())),
+            ),
+        ]);
+
+        let ts = TransferSyntaxRegistry.get("1.2.840.10008.1.2.2").unwrap();
+        let mut data = Vec::new();
+        obj.write_dataset_with_ts(&mut data, &ts).unwrap();
+
+        // values are written in big endian
+        let rows_pos = data
+            .windows(4)
+            .position(|w| w == [0x00, 0x28, 0x00, 0x10])
+            .expect("Rows should be encoded with a big endian tag");
+        assert_eq!(&data[rows_pos + 4..rows_pos + 10], b"US\x00\x02\x00\x02");
+
+        let read_obj = InMemDicomObject::read_dataset_with_ts(&data[..], &ts).unwrap();
+        assert_eq!(
+            read_obj
+                .element(tags::PATIENT_NAME)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Doe^John"
+        );
+        assert_eq!(
+            read_obj
+                .element(tags::ROWS)
+                .unwrap()
+                .to_int::<u16>()
+                .unwrap(),
+            2
+        );
+        let items = read_obj
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0]
+                .element(tags::REFERENCED_FRAME_NUMBER)
+                .unwrap()
+                .to_int::<i32>()
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            read_obj
+                .element(tags::PIXEL_DATA)
+                .unwrap()
+                .to_multi_int::<u16>()
+                .unwrap(),
+            vec![0x0102, 0x0304]
+        );
+    }
+
+    #[test]
+    fn inmem_object_read_raw_dataset() {
+        use crate::file::detect_dataset_transfer_syntax;
+        use crate::OpenFileOptions;
+
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                Value::Primitive("1.2.840.10008.5.1.4.1.1.7\0".into()),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                Value::Primitive("2.25.1234\0".into()),
+            ),
+            DataElement::new(
+                tags::PATIENT_NAME,
+                VR::PN,
+                Value::Primitive("Doe^John".into()),
+            ),
+        ]);
+
+        for ts_uid in [
+            "1.2.840.10008.1.2",
+            "1.2.840.10008.1.2.1",
+            "1.2.840.10008.1.2.2",
+        ] {
+            let ts = TransferSyntaxRegistry.get(ts_uid).unwrap();
+            let mut data = Vec::new();
+            obj.write_dataset_with_ts(&mut data, ts).unwrap();
+            assert_eq!(detect_dataset_transfer_syntax(&data), Some(ts_uid));
+
+            let file_obj = OpenFileOptions::new()
+                .from_reader_or_dataset(&data[..])
+                .unwrap();
+            assert_eq!(file_obj.meta().transfer_syntax(), ts_uid);
+            assert_eq!(
+                file_obj.meta().media_storage_sop_class_uid(),
+                "1.2.840.10008.5.1.4.1.1.7"
+            );
+            assert_eq!(
+                file_obj.meta().media_storage_sop_instance_uid(),
+                "2.25.1234"
+            );
+            assert_eq!(
+                file_obj
+                    .element(tags::PATIENT_NAME)
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+                "Doe^John"
+            );
+        }
+
+        // not enough bytes for an element header
+        assert_eq!(detect_dataset_transfer_syntax(&[0x08, 0x00, 0x16]), None);
+    }
+
+    #[test]
+    fn inmem_object_read_file_or_dataset() {
+        use crate::OpenFileOptions;
+
+        let file_obj = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PATIENT_NAME,
+            VR::PN,
+            Value::Primitive("Doe^John".into()),
+        )])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("2.25.1234")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap();
+
+        // with preamble
+        let mut data = Vec::new();
+        file_obj.write_all(&mut data).unwrap();
+        let obj = OpenFileOptions::new()
+            .from_reader_or_dataset(&data[..])
+            .unwrap();
+        assert_eq!(obj.meta().transfer_syntax(), "1.2.840.10008.1.2.1");
+        assert_eq!(
+            obj.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Doe^John"
+        );
+
+        // without preamble
+        let obj = OpenFileOptions::new()
+            .from_reader_or_dataset(&data[128..])
+            .unwrap();
+        assert_eq!(obj.meta().media_storage_sop_instance_uid(), "2.25.1234");
+
+        // file meta group without the magic code
+        let obj = OpenFileOptions::new()
+            .from_reader_or_dataset(&data[132..])
+            .unwrap();
+        assert_eq!(obj.meta().media_storage_sop_instance_uid(), "2.25.1234");
+        assert_eq!(
+            obj.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Doe^John"
+        );
+    }
+
     #[test]
     fn inmem_object_read_dataset_with_ts_cs() {
         let data_in = [
@@ -1101,28 +2790,417 @@ mod tests {
     }
 
     #[test]
-    fn inmem_object_write_dataset_with_ts_cs() {
-        let mut obj = InMemDicomObject::new_empty();
-
-        let patient_name =
-            DataElement::new(Tag(0x0010, 0x0010), VR::PN, dicom_value!(Str, "Doe^John"));
-        obj.put(patient_name);
+    fn inmem_object_write_dataset_with_ts_cs() {
+        let mut obj = InMemDicomObject::new_empty();
+
+        let patient_name =
+            DataElement::new(Tag(0x0010, 0x0010), VR::PN, dicom_value!(Str, "Doe^John"));
+        obj.put(patient_name);
+
+        let mut out = Vec::new();
+
+        let ts = TransferSyntaxRegistry.get("1.2.840.10008.1.2").unwrap();
+        let cs = SpecificCharacterSet::Default;
+
+        obj.write_dataset_with_ts_cs(&mut out, &ts, cs).unwrap();
+
+        assert_eq!(
+            out,
+            &[
+                0x10, 0x00, 0x10, 0x00, // Tag(0x0010, 0x0010)
+                0x08, 0x00, 0x00, 0x00, // Length: 8
+                b'D', b'o', b'e', b'^', b'J', b'o', b'h', b'n',
+            ][..],
+        );
+    }
+
+    /// editing an element inside an explicit length sequence
+    /// should not corrupt the data set when written
+    #[test]
+    fn inmem_object_write_edited_explicit_length_sequence() {
+        #[rustfmt::skip]
+        let data: &[u8] = &[
+            0x18, 0x00, 0x11, 0x60, // Tag(0x0018, 0x6011)
+            b'S', b'Q', 0x00, 0x00, // VR: SQ, reserved
+            0x14, 0x00, 0x00, 0x00, // Length: 20
+            0xfe, 0xff, 0x00, 0xe0, // item start
+            0x0c, 0x00, 0x00, 0x00, // item length: 12
+            0x20, 0x00, 0x00, 0x40, // Tag(0x0020, 0x4000)
+            b'L', b'T', 0x04, 0x00, // VR: LT, length: 4
+            b'a', b'b', b'c', b'd',
+            0x20, 0x00, 0x13, 0x00, // Tag(0x0020, 0x0013)
+            b'I', b'S', 0x02, 0x00, // VR: IS, length: 2
+            b'1', b' ',
+        ];
+        let ts = TransferSyntaxRegistry.get("1.2.840.10008.1.2.1").unwrap();
+        let mut obj = InMemDicomObject::read_dataset_with_ts(data, ts).unwrap();
+
+        // replace the comment in the item with a longer one
+        let sequence = obj.take_element(Tag(0x0018, 0x6011)).unwrap();
+        let size = sequence.length();
+        assert_eq!(size, Length(20));
+        let mut items = sequence.items().unwrap().to_vec();
+        items[0].put(DataElement::new(
+            Tag(0x0020, 0x4000),
+            VR::LT,
+            PrimitiveValue::from("a much longer comment"),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x0018, 0x6011),
+            VR::SQ,
+            Value::Sequence {
+                items: items.into(),
+                size,
+            },
+        ));
+
+        let mut out = Vec::new();
+        obj.write_dataset_with_ts(&mut out, ts).unwrap();
+
+        // lengths were recalculated, remaining explicit
+        assert_eq!(&out[8..12], &[0x26, 0x00, 0x00, 0x00]);
+        assert_eq!(&out[16..20], &[0x1e, 0x00, 0x00, 0x00]);
+
+        let obj2 = InMemDicomObject::read_dataset_with_ts(&out[..], ts).unwrap();
+        let items = obj2.element(Tag(0x0018, 0x6011)).unwrap().items().unwrap();
+        assert_eq!(
+            items[0]
+                .element(Tag(0x0020, 0x4000))
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "a much longer comment",
+        );
+        assert_eq!(
+            obj2.element(Tag(0x0020, 0x0013))
+                .unwrap()
+                .to_int::<i32>()
+                .unwrap(),
+            1,
+        );
+    }
+
+    /// values can be put with the VR from the data dictionary
+    #[test]
+    fn inmem_object_put_value_with_dictionary_vr() {
+        let mut obj = InMemDicomObject::new_empty();
+
+        assert!(obj.put_value(tags::ROWS, 512).unwrap().is_none());
+        obj.put_value_by_name("Columns", "256").unwrap();
+        obj.put_value(tags::INSTANCE_NUMBER, 7_u16).unwrap();
+        obj.put_value(tags::PIXEL_SPACING, [0.5, 0.25]).unwrap();
+        obj.put_value(tags::PATIENT_NAME, "Doe^John").unwrap();
+
+        let rows = obj.element(tags::ROWS).unwrap();
+        assert_eq!(rows.vr(), VR::US);
+        assert_eq!(rows.value(), &PrimitiveValue::from(512_u16).into());
+        let columns = obj.element(tags::COLUMNS).unwrap();
+        assert_eq!(columns.vr(), VR::US);
+        assert_eq!(columns.value(), &PrimitiveValue::from(256_u16).into());
+        let number = obj.element(tags::INSTANCE_NUMBER).unwrap();
+        assert_eq!(number.vr(), VR::IS);
+        assert_eq!(number.value(), &dicom_value!(Strs, ["7"]).into());
+        let spacing = obj.element(tags::PIXEL_SPACING).unwrap();
+        assert_eq!(spacing.vr(), VR::DS);
+        assert_eq!(spacing.value(), &dicom_value!(Strs, ["0.5", "0.25"]).into());
+        assert_eq!(obj.element(tags::PATIENT_NAME).unwrap().vr(), VR::PN);
+
+        // replaces the previous element
+        let old = obj.put_value(tags::ROWS, 1024_u32).unwrap().unwrap();
+        assert_eq!(old.to_int::<u16>().unwrap(), 512);
+        assert_eq!(
+            obj.element(tags::ROWS).unwrap().to_int::<u16>().unwrap(),
+            1024
+        );
+
+        // values which do not fit the VR
+        assert!(matches!(
+            obj.put_value(tags::ROWS, "many"),
+            Err(Error::ConvertValue { .. })
+        ));
+        assert!(matches!(
+            obj.put_value(tags::ROWS, 70_000),
+            Err(Error::ConvertValue { .. })
+        ));
+        assert!(matches!(
+            obj.put_value(tags::REFERENCED_IMAGE_SEQUENCE, "1"),
+            Err(Error::ConvertValue { .. })
+        ));
+        // private attributes are not in the dictionary
+        assert!(matches!(
+            obj.put_value(Tag(0x0009, 0x1001), "1"),
+            Err(Error::NoSuchAttributeTag { .. })
+        ));
+        assert!(matches!(
+            obj.put_value_by_name("NotAnAttribute", "1"),
+            Err(Error::NoSuchAttributeName { .. })
+        ));
+        assert_eq!(
+            obj.element(tags::ROWS).unwrap().to_int::<u16>().unwrap(),
+            1024
+        );
+    }
+
+    /// elements in nested sequences can be accessed and modified by path
+    #[test]
+    fn inmem_object_typed_getters() {
+        let measures = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PIXEL_SPACING,
+            VR::DS,
+            dicom_value!(Strs, ["0.5", "0.25"]),
+        )]);
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from("20210315")),
+            DataElement::new(tags::SERIES_NUMBER, VR::IS, PrimitiveValue::from("abc")),
+        ]);
+        obj.push_item(tags::PIXEL_MEASURES_SEQUENCE, measures)
+            .unwrap();
+
+        assert_eq!(
+            obj.get_date(tags::STUDY_DATE).unwrap(),
+            DicomDate::from_ymd(2021, 3, 15).unwrap(),
+        );
+        assert_eq!(
+            obj.get_floats("PixelMeasuresSequence[0].PixelSpacing")
+                .unwrap(),
+            vec![0.5, 0.25],
+        );
+        let path: TagPath = "PixelMeasuresSequence[0].PixelSpacing".parse().unwrap();
+        assert_eq!(obj.get_str(&path).unwrap(), "0.5\\0.25");
+        assert_eq!(obj.get_seq("PixelMeasuresSequence").unwrap().len(), 1);
+
+        assert!(matches!(
+            obj.get_int::<i32>(tags::SERIES_NUMBER),
+            Err(Error::ConvertValueAt { path, .. }) if *path == TagPath::new(tags::SERIES_NUMBER)
+        ));
+        assert!(matches!(
+            obj.get_seq(tags::STUDY_DATE),
+            Err(Error::NotASequence {
+                tag: tags::STUDY_DATE,
+                ..
+            })
+        ));
+        assert!(matches!(
+            obj.get_str("PixelMeasuresSequence[1].PixelSpacing"),
+            Err(Error::NoSuchItem { index: 1, .. })
+        ));
+        assert!(matches!(
+            obj.get_str("NotAnAttribute"),
+            Err(Error::InvalidTagPath { .. })
+        ));
+        assert!(matches!(
+            obj.get_str(&"PixelMeasuresSequence[0].SliceThickness".to_string()),
+            Err(Error::NoSuchDataElementPath { .. })
+        ));
+    }
+
+    #[test]
+    fn inmem_object_access_by_path() {
+        let measures = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PIXEL_SPACING,
+            VR::DS,
+            dicom_value!(Strs, ["0.5", "0.5"]),
+        )]);
+        let group = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PIXEL_MEASURES_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: vec![measures].into(),
+                size: Length::UNDEFINED,
+            },
+        )]);
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new_with_len(
+                tags::SHARED_FUNCTIONAL_GROUPS_SEQUENCE,
+                VR::SQ,
+                Length(30),
+                Value::Sequence {
+                    items: vec![group].into(),
+                    size: Length(30),
+                },
+            ),
+        ]);
+
+        let spacing: TagPath =
+            "SharedFunctionalGroupsSequence[0].PixelMeasuresSequence[0].PixelSpacing"
+                .parse()
+                .unwrap();
+        let thickness = TagPath::from_parts(spacing.items().to_vec(), tags::SLICE_THICKNESS);
+
+        assert_eq!(
+            obj.value_at(&spacing).unwrap().to_multi_float64().unwrap(),
+            vec![0.5, 0.5],
+        );
+        assert_eq!(
+            obj.value_at(&TagPath::new(tags::PATIENT_NAME))
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Doe^John",
+        );
+        assert!(matches!(
+            obj.element_at(&thickness),
+            Err(Error::NoSuchDataElementTag { .. })
+        ));
+
+        // insert and replace in the nested item
+        assert_eq!(
+            obj.put_at(&thickness, VR::DS, PrimitiveValue::from("1.0"))
+                .unwrap(),
+            None,
+        );
+        let old = obj
+            .put_at(&spacing, VR::DS, dicom_value!(Strs, ["0.25", "0.25"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(old.to_multi_float64().unwrap(), vec![0.5, 0.5]);
+        assert_eq!(
+            obj.element_at(&spacing)
+                .unwrap()
+                .to_multi_float64()
+                .unwrap(),
+            vec![0.25, 0.25],
+        );
+        assert_eq!(
+            obj.element_at(&thickness).unwrap().to_float64().unwrap(),
+            1.0,
+        );
+        // the sequence keeps its place and length
+        let sequence = obj
+            .element(tags::SHARED_FUNCTIONAL_GROUPS_SEQUENCE)
+            .unwrap();
+        assert_eq!(sequence.length(), Length(30));
+        assert_eq!(sequence.items().unwrap().len(), 1);
+
+        // remove from the nested item
+        assert!(obj.remove_at(&thickness).unwrap());
+        assert!(!obj.remove_at(&thickness).unwrap());
+
+        // items out of range are reported
+        let missing: TagPath =
+            "SharedFunctionalGroupsSequence[1].PixelMeasuresSequence[0].PixelSpacing"
+                .parse()
+                .unwrap();
+        assert!(matches!(
+            obj.value_at(&missing),
+            Err(Error::NoSuchItem { index: 1, .. })
+        ));
+        assert!(matches!(
+            obj.put_at(&missing, VR::DS, PrimitiveValue::from("1")),
+            Err(Error::NoSuchItem { index: 1, .. })
+        ));
+        // not a sequence
+        let not_sequence: TagPath = "PatientName[0].PatientID".parse().unwrap();
+        assert!(matches!(
+            obj.remove_at(&not_sequence),
+            Err(Error::NoSuchItem { .. })
+        ));
+        assert!(obj.element(tags::PATIENT_NAME).is_ok());
+    }
+
+    /// sequence and item lengths can be chosen on each write
+    #[test]
+    fn inmem_object_write_sequence_length_strategy() {
+        use crate::tokens::ExplicitLengthSqItemStrategy;
+
+        #[rustfmt::skip]
+        let data: &[u8] = &[
+            0x18, 0x00, 0x11, 0x60, // Tag(0x0018, 0x6011)
+            b'S', b'Q', 0x00, 0x00, // VR: SQ, reserved
+            0xff, 0xff, 0xff, 0xff, // Length: undefined
+            0xfe, 0xff, 0x00, 0xe0, // item start
+            0xff, 0xff, 0xff, 0xff, // item length: undefined
+            0x20, 0x00, 0x00, 0x40, // Tag(0x0020, 0x4000)
+            b'L', b'T', 0x04, 0x00, // VR: LT, length: 4
+            b'a', b'b', b'c', b'd',
+            0xfe, 0xff, 0x0d, 0xe0, // item end
+            0x00, 0x00, 0x00, 0x00,
+            0xfe, 0xff, 0xdd, 0xe0, // sequence end
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        let ts = TransferSyntaxRegistry.get("1.2.840.10008.1.2.1").unwrap();
+        let obj = InMemDicomObject::read_dataset_with_ts(data, ts).unwrap();
 
+        let options = WriteOptions::new()
+            .explicit_length_sq_item_strategy(ExplicitLengthSqItemStrategy::SetExplicit);
         let mut out = Vec::new();
+        obj.write_dataset_with_ts_cs_options(&mut out, ts, SpecificCharacterSet::Default, &options)
+            .unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&out[..], &[
+            0x18, 0x00, 0x11, 0x60, b'S', b'Q', 0x00, 0x00,
+            0x14, 0x00, 0x00, 0x00, // Length: 20
+            0xfe, 0xff, 0x00, 0xe0,
+            0x0c, 0x00, 0x00, 0x00, // item length: 12
+            0x20, 0x00, 0x00, 0x40, b'L', b'T', 0x04, 0x00,
+            b'a', b'b', b'c', b'd',
+        ][..]);
+
+        let options = WriteOptions::new()
+            .explicit_length_sq_item_strategy(ExplicitLengthSqItemStrategy::SetUndefined);
+        let mut out2 = Vec::new();
+        obj.write_dataset_with_ts_cs_options(
+            &mut out2,
+            ts,
+            SpecificCharacterSet::Default,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(out2, data);
+    }
 
-        let ts = TransferSyntaxRegistry.get("1.2.840.10008.1.2").unwrap();
-        let cs = SpecificCharacterSet::Default;
+    /// the encoded length matches the bytes actually written
+    #[test]
+    fn inmem_object_encoded_len() {
+        use crate::tokens::{ExplicitLengthSqItemStrategy, GroupLengthStrategy};
 
-        obj.write_dataset_with_ts_cs(&mut out, &ts, cs).unwrap();
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("ABC")),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+        ]);
+        obj.push_item(
+            tags::REFERENCED_SERIES_SEQUENCE,
+            InMemDicomObject::from_element_iter([DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.3.4"),
+            )]),
+        )
+        .unwrap();
 
-        assert_eq!(
-            out,
-            &[
-                0x10, 0x00, 0x10, 0x00, // Tag(0x0010, 0x0010)
-                0x08, 0x00, 0x00, 0x00, // Length: 8
-                b'D', b'o', b'e', b'^', b'J', b'o', b'h', b'n',
-            ][..],
-        );
+        let options = [
+            WriteOptions::new(),
+            WriteOptions::new()
+                .explicit_length_sq_item_strategy(ExplicitLengthSqItemStrategy::SetExplicit),
+            WriteOptions::new().group_length(GroupLengthStrategy::Recalculate),
+        ];
+        for uid in [
+            "1.2.840.10008.1.2",
+            "1.2.840.10008.1.2.1",
+            "1.2.840.10008.1.2.2",
+            "1.2.840.10008.1.2.1.99",
+        ] {
+            let ts = TransferSyntaxRegistry.get(uid).unwrap();
+            for options in &options {
+                let mut out = Vec::new();
+                obj.write_dataset_with_ts_cs_options(
+                    &mut out,
+                    ts,
+                    SpecificCharacterSet::Default,
+                    options,
+                )
+                .unwrap();
+                assert_eq!(
+                    obj.encoded_len(ts, options).unwrap(),
+                    out.len() as u64,
+                    "{} with {:?}",
+                    uid,
+                    options
+                );
+            }
+        }
     }
 
     /// writing a DICOM date time into an object
@@ -1220,6 +3298,141 @@ mod tests {
         assert_eq!(file_object, saved_object);
     }
 
+    /// Reads a dual personality file,
+    /// with a TIFF header in the preamble,
+    /// and writes it back with the same preamble.
+    #[test]
+    fn inmem_read_write_tiff_preamble() {
+        let sop_uid = "1.4.645.313131";
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(sop_uid),
+        ));
+        let mut file_object = obj
+            .with_meta(
+                FileMetaTableBuilder::default()
+                    // Explicit VR Little Endian
+                    .transfer_syntax("1.2.840.10008.1.2.1")
+                    // VL Whole Slide Microscopy Image Storage
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.77.1.6"),
+            )
+            .unwrap();
+        assert!(!file_object.has_tiff_preamble());
+
+        // little endian TIFF header, first IFD at offset 8
+        let mut preamble = [0; 128];
+        preamble[..8].copy_from_slice(b"II*\0\x08\0\0\0");
+        file_object.set_preamble(preamble);
+        assert!(file_object.has_tiff_preamble());
+
+        let mut data = Vec::new();
+        file_object.write_all(&mut data).unwrap();
+        assert_eq!(&data[..8], b"II*\0\x08\0\0\0");
+        assert_eq!(&data[128..132], b"DICM");
+
+        // the preamble is detected from a byte source
+        let saved_object = FileDicomObject::from_reader(&data[..]).unwrap();
+        assert!(saved_object.has_tiff_preamble());
+        assert_eq!(saved_object.preamble(), &preamble);
+        assert_eq!(saved_object, file_object);
+
+        let mut rewritten = Vec::new();
+        saved_object.write_all(&mut rewritten).unwrap();
+        assert_eq!(rewritten, data);
+
+        // sources without the preamble are still accepted
+        let saved_object = FileDicomObject::from_reader(&data[128..]).unwrap();
+        assert!(!saved_object.has_tiff_preamble());
+        assert_eq!(saved_object.preamble(), &[0; 128]);
+    }
+
+    /// The TIFF header in the preamble of a modified object
+    /// is not written back, as its offsets no longer match the file.
+    #[test]
+    fn inmem_write_modified_tiff_preamble() {
+        let mut file_object = InMemDicomObject::new_empty()
+            .with_meta(
+                FileMetaTableBuilder::default()
+                    .transfer_syntax("1.2.840.10008.1.2.1")
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.77.1.6")
+                    .media_storage_sop_instance_uid("1.4.645.313131"),
+            )
+            .unwrap();
+        let mut preamble = [0; 128];
+        preamble[..8].copy_from_slice(b"II*\0\x08\0\0\0");
+        file_object.set_preamble(preamble);
+
+        file_object.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.4.645.313131"),
+        ));
+        // the preamble is kept in memory
+        assert!(file_object.has_tiff_preamble());
+
+        let mut data = Vec::new();
+        file_object.write_all(&mut data).unwrap();
+        assert_eq!(&data[..128], &[0; 128]);
+        assert_eq!(&data[128..132], b"DICM");
+
+        // setting the preamble again writes it as is
+        file_object.set_preamble(preamble);
+        let mut data = Vec::new();
+        file_object.write_all(&mut data).unwrap();
+        assert_eq!(&data[..128], &preamble);
+
+        file_object.meta_mut().update_information_group_length();
+        let mut data = Vec::new();
+        file_object.write_all(&mut data).unwrap();
+        assert_eq!(&data[..128], &[0; 128]);
+    }
+
+    /// Byte sources are read with or without the preamble,
+    /// as configured in the open file options.
+    #[test]
+    fn from_reader_read_preamble() {
+        use crate::{from_reader, OpenFileOptions};
+
+        let mut file_object = InMemDicomObject::new_empty()
+            .with_meta(
+                FileMetaTableBuilder::default()
+                    .transfer_syntax("1.2.840.10008.1.2.1")
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.77.1.6")
+                    .media_storage_sop_instance_uid("1.4.645.313131"),
+            )
+            .unwrap();
+        let mut preamble = [0; 128];
+        preamble[..8].copy_from_slice(b"MM\0*\0\0\0\x08");
+        file_object.set_preamble(preamble);
+        let mut data = Vec::new();
+        file_object.write_all(&mut data).unwrap();
+
+        // preamble detected by default
+        let obj = from_reader(&data[..]).unwrap();
+        assert_eq!(obj.preamble(), &preamble);
+        let obj = from_reader(&data[128..]).unwrap();
+        assert_eq!(obj.preamble(), &[0; 128]);
+
+        let obj = OpenFileOptions::new()
+            .read_preamble(ReadPreamble::Always)
+            .from_reader(&data[..])
+            .unwrap();
+        assert!(obj.has_tiff_preamble());
+        assert_eq!(obj, file_object);
+
+        let obj = OpenFileOptions::new()
+            .read_preamble(ReadPreamble::Never)
+            .from_reader(&data[128..])
+            .unwrap();
+        assert_eq!(obj.preamble(), &[0; 128]);
+        assert!(OpenFileOptions::new()
+            .read_preamble(ReadPreamble::Never)
+            .from_reader(&data[..])
+            .is_err());
+    }
+
     /// Creating a file DICOM object from an in-mem DICOM object
     /// infers the SOP instance UID.
     #[test]
@@ -1422,6 +3635,81 @@ mod tests {
         assert_eq!(obj.remove_element_by_name("PatientName").unwrap(), false);
     }
 
+    /// Empty elements are written with a length of zero,
+    /// including empty sequences.
+    #[test]
+    fn inmem_object_write_empty_elements() {
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put_empty(Tag(0x0008, 0x1110), VR::SQ);
+        obj.put_empty(Tag(0x0010, 0x0010), VR::PN);
+
+        let mut out = Vec::new();
+        let ts = TransferSyntaxRegistry.get("1.2.840.10008.1.2.1").unwrap();
+        obj.write_dataset_with_ts(&mut out, &ts).unwrap();
+
+        assert_eq!(
+            out,
+            &[
+                0x08, 0x00, 0x10, 0x11, // Tag(0x0008, 0x1110)
+                b'S', b'Q', // VR: SQ
+                0x00, 0x00, // reserved
+                0x00, 0x00, 0x00, 0x00, // Length: 0
+                0x10, 0x00, 0x10, 0x00, // Tag(0x0010, 0x0010)
+                b'P', b'N', // VR: PN
+                0x00, 0x00, // Length: 0
+            ][..],
+        );
+    }
+
+    /// Empty elements of any VR are preserved
+    /// as present but empty after a roundtrip.
+    #[test]
+    fn inmem_object_empty_elements_roundtrip() {
+        let elements = [
+            (tags::REFERENCED_STUDY_SEQUENCE, VR::SQ),
+            (tags::PATIENT_NAME, VR::PN),
+            (tags::PATIENT_ID, VR::LO),
+            (tags::PATIENT_BIRTH_DATE, VR::DA),
+            (tags::PATIENT_SEX, VR::CS),
+            (tags::STUDY_TIME, VR::TM),
+            (tags::ACQUISITION_DATE_TIME, VR::DT),
+            (tags::PATIENT_AGE, VR::AS),
+            (tags::PATIENT_WEIGHT, VR::DS),
+            (tags::SERIES_NUMBER, VR::IS),
+            (tags::ACCESSION_NUMBER, VR::SH),
+            (tags::STUDY_INSTANCE_UID, VR::UI),
+            (tags::ROWS, VR::US),
+            (tags::PIXEL_SPACING_CALIBRATION_DESCRIPTION, VR::LO),
+            (tags::FRAME_INCREMENT_POINTER, VR::AT),
+            (tags::ICON_IMAGE_SEQUENCE, VR::SQ),
+        ];
+
+        let mut obj = InMemDicomObject::new_empty();
+        for (tag, vr) in elements {
+            obj.put_empty(tag, vr);
+        }
+
+        for ts_uid in ["1.2.840.10008.1.2", "1.2.840.10008.1.2.1"] {
+            let ts = TransferSyntaxRegistry.get(ts_uid).unwrap();
+            let mut out = Vec::new();
+            obj.write_dataset_with_ts(&mut out, ts).unwrap();
+
+            let obj2 = InMemDicomObject::read_dataset_with_ts(&out[..], ts).unwrap();
+            for (tag, vr) in elements {
+                assert!(obj2.has_element(tag), "{} should be present", tag);
+                let elem = obj2.element(tag).unwrap();
+                assert_eq!(elem.vr(), vr);
+                assert!(elem.is_empty_value(), "{} should be empty", tag);
+                assert!(obj2.non_empty_element(tag).is_none());
+            }
+        }
+
+        // absent elements are distinguishable from empty ones
+        assert!(!obj.has_element(tags::PATIENT_COMMENTS));
+        assert!(obj.element_opt(tags::PATIENT_COMMENTS).unwrap().is_none());
+        assert!(obj.element_opt(tags::PATIENT_NAME).unwrap().is_some());
+    }
+
     /// Elements are traversed in tag order.
     #[test]
     fn inmem_traverse_elements() {
@@ -1538,11 +3826,11 @@ mod tests {
         ]);
 
         let obj = InMemDicomObject::build_object(
-            &mut tokens.into_iter().map(Result::Ok),
+            &mut tokens.into_iter().map(Ok::<_, ParserError>),
             StandardDataDictionary,
             false,
             Length::UNDEFINED,
-            None,
+            BuildOptions::default(),
         )
         .unwrap();
 
@@ -1654,11 +3942,11 @@ mod tests {
         ];
 
         let obj = InMemDicomObject::build_object(
-            &mut tokens.into_iter().map(Result::Ok),
+            &mut tokens.into_iter().map(Ok::<_, ParserError>),
             StandardDataDictionary,
             false,
             Length::UNDEFINED,
-            None,
+            BuildOptions::default(),
         )
         .unwrap();
 
@@ -1762,11 +4050,11 @@ mod tests {
         ];
 
         let obj = InMemDicomObject::build_object(
-            &mut tokens.into_iter().map(Result::Ok),
+            &mut tokens.into_iter().map(Ok::<_, ParserError>),
             StandardDataDictionary,
             false,
             Length::UNDEFINED,
-            None,
+            BuildOptions::default(),
         )
         .unwrap();
 
@@ -1801,4 +4089,401 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn inmem_fragment_sequence_from_and_into_tokens() {
+        use smallvec::smallvec;
+
+        let gt_obj = InMemDicomObject::from_element_iter(vec![DataElement::new(
+            Tag(0x0009, 0x1010),
+            VR::OB,
+            Value::PixelSequence {
+                fragments: smallvec![vec![0x33; 4], vec![0x44; 2]],
+                offset_table: Default::default(),
+            },
+        )]);
+
+        let tokens: Vec<_> = vec![
+            DataToken::FragmentSequenceStart {
+                tag: Tag(0x0009, 0x1010),
+                vr: VR::OB,
+            },
+            DataToken::ItemStart { len: Length(4) },
+            DataToken::ItemValue(vec![0x33; 4]),
+            DataToken::ItemEnd,
+            DataToken::ItemStart { len: Length(2) },
+            DataToken::ItemValue(vec![0x44; 2]),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+        ];
+
+        let obj = InMemDicomObject::build_object(
+            &mut tokens.clone().into_iter().map(Ok::<_, ParserError>),
+            StandardDataDictionary,
+            false,
+            Length::UNDEFINED,
+            BuildOptions::default(),
+        )
+        .unwrap();
+
+        assert_obj_eq(&obj, &gt_obj);
+
+        // no offset table item is produced
+        let out_tokens: Vec<_> = obj.into_tokens().collect();
+        assert_eq!(out_tokens, tokens);
+    }
+
+    #[test]
+    fn inmem_datetime_with_timezone_offset() {
+        let default_offset = FixedOffset::east_opt(0).unwrap();
+        let mut obj = InMemDicomObject::from_element_iter(vec![
+            DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from("20210315")),
+            DataElement::new(tags::STUDY_TIME, VR::TM, PrimitiveValue::from("101530")),
+            DataElement::new(
+                tags::ACQUISITION_DATE_TIME,
+                VR::DT,
+                PrimitiveValue::from("20210315101530"),
+            ),
+        ]);
+
+        // no timezone offset in data set, use the default
+        assert_eq!(obj.timezone_offset().unwrap(), None);
+        let dt = obj
+            .to_datetime_with_timezone(tags::ACQUISITION_DATE_TIME, default_offset)
+            .unwrap();
+        assert_eq!(dt.offset(), &default_offset);
+
+        obj.put(DataElement::new(
+            tags::TIMEZONE_OFFSET_FROM_UTC,
+            VR::SH,
+            PrimitiveValue::from("-0500"),
+        ));
+        let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+        assert_eq!(obj.timezone_offset().unwrap(), Some(offset));
+
+        let dt = obj
+            .to_datetime_with_timezone(tags::ACQUISITION_DATE_TIME, default_offset)
+            .unwrap();
+        assert_eq!(
+            dt,
+            DicomDateTime::from_date_and_time(
+                DicomDate::from_ymd(2021, 3, 15).unwrap(),
+                DicomTime::from_hms(10, 15, 30).unwrap(),
+                offset,
+            )
+            .unwrap()
+        );
+
+        let dt = obj
+            .to_date_time_with_timezone(tags::STUDY_DATE, tags::STUDY_TIME, default_offset)
+            .unwrap();
+        assert_eq!(
+            dt,
+            DicomDateTime::from_date_and_time(
+                DicomDate::from_ymd(2021, 3, 15).unwrap(),
+                DicomTime::from_hms(10, 15, 30).unwrap(),
+                offset,
+            )
+            .unwrap()
+        );
+
+        // explicit offset in the DT value takes precedence
+        obj.put(DataElement::new(
+            tags::ACQUISITION_DATE_TIME,
+            VR::DT,
+            PrimitiveValue::from("20210315101530+0100"),
+        ));
+        let dt = obj
+            .to_datetime_with_timezone(tags::ACQUISITION_DATE_TIME, default_offset)
+            .unwrap();
+        assert_eq!(dt.offset(), &FixedOffset::east_opt(3600).unwrap());
+
+        // empty study time results in a date-only value
+        obj.put(DataElement::new(
+            tags::STUDY_TIME,
+            VR::TM,
+            PrimitiveValue::from(""),
+        ));
+        let dt = obj
+            .to_date_time_with_timezone(tags::STUDY_DATE, tags::STUDY_TIME, default_offset)
+            .unwrap();
+        assert_eq!(
+            dt,
+            DicomDateTime::from_date(DicomDate::from_ymd(2021, 3, 15).unwrap(), offset)
+        );
+
+        // malformed offset
+        obj.put(DataElement::new(
+            tags::TIMEZONE_OFFSET_FROM_UTC,
+            VR::SH,
+            PrimitiveValue::from("0500"),
+        ));
+        assert!(matches!(
+            obj.timezone_offset(),
+            Err(Error::InvalidTimezoneOffset { .. })
+        ));
+    }
+
+    #[test]
+    fn inmem_remove_group_and_private_recursively() {
+        let private = |element: u16, value: &str| {
+            DataElement::new(Tag(0x0029, element), VR::LO, PrimitiveValue::from(value))
+        };
+        let inner = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::CODE_VALUE, VR::SH, PrimitiveValue::from("A")),
+            DataElement::new(tags::CODE_MEANING, VR::LO, PrimitiveValue::from("Meaning")),
+            private(0x0010, "ACME"),
+        ]);
+        let mut outer = InMemDicomObject::from_element_iter([private(0x0010, "ACME")]);
+        outer
+            .push_item(tags::CONCEPT_NAME_CODE_SEQUENCE, inner)
+            .unwrap();
+        let mut private_seq = InMemDicomObject::new_empty();
+        private_seq
+            .push_item(
+                tags::CONCEPT_NAME_CODE_SEQUENCE,
+                InMemDicomObject::new_empty(),
+            )
+            .unwrap();
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("123")),
+            private(0x0010, "ACME"),
+            private(0x1001, "vendor data"),
+        ]);
+        obj.push_item(tags::CONTENT_SEQUENCE, outer).unwrap();
+        obj.push_item(Tag(0x0029, 0x1002), private_seq).unwrap();
+
+        // the elements in the removed private sequence are not counted
+        assert_eq!(obj.remove_private(), 5);
+        assert_eq!(
+            obj.tags().collect::<Vec<_>>(),
+            [tags::PATIENT_ID, tags::CONTENT_SEQUENCE]
+        );
+        let inner = obj
+            .get_seq("ContentSequence[0].ConceptNameCodeSequence")
+            .unwrap();
+        assert_eq!(
+            inner[0].tags().collect::<Vec<_>>(),
+            [tags::CODE_VALUE, tags::CODE_MEANING]
+        );
+
+        assert_eq!(obj.remove_group(0x0008), 2);
+        assert_eq!(obj.remove_group(0x0008), 0);
+        assert!(obj
+            .get_seq("ContentSequence[0].ConceptNameCodeSequence")
+            .unwrap()[0]
+            .tags()
+            .next()
+            .is_none());
+
+        let mut paths = Vec::new();
+        let removed = obj.retain_recursive(|path, _| {
+            paths.push(path.to_string());
+            path.tag() != tags::CONTENT_SEQUENCE
+        });
+        assert_eq!(removed, 1);
+        assert_eq!(paths, ["(0010,0020)", "(0040,A730)"]);
+        assert_eq!(obj.tags().collect::<Vec<_>>(), [tags::PATIENT_ID]);
+    }
+
+    #[test]
+    fn inmem_private_element_by_creator() {
+        let mut obj = InMemDicomObject::from_element_iter(vec![
+            // block 0x10 is taken by another creator
+            DataElement::new(Tag(0x0029, 0x0010), VR::LO, PrimitiveValue::from("OTHER ")),
+            DataElement::new(Tag(0x0029, 0x1010), VR::LO, PrimitiveValue::from("other")),
+            DataElement::new(
+                Tag(0x0029, 0x0011),
+                VR::LO,
+                PrimitiveValue::from("ACME 1.0"),
+            ),
+            DataElement::new(Tag(0x0029, 0x1110), VR::LO, PrimitiveValue::from("acme")),
+        ]);
+
+        assert_eq!(obj.private_block(0x0029, "OTHER").unwrap(), Some(0x10));
+        assert_eq!(obj.private_block(0x0029, "ACME 1.0").unwrap(), Some(0x11));
+        let elem = obj.private_element(0x0029, "ACME 1.0", 0x10).unwrap();
+        assert_eq!(elem.tag(), Tag(0x0029, 0x1110));
+        assert_eq!(elem.to_str().unwrap(), "acme");
+
+        assert!(matches!(
+            obj.private_element(0x0029, "NOBODY", 0x10),
+            Err(Error::NoSuchPrivateCreator { .. })
+        ));
+        assert!(matches!(
+            obj.private_element(0x0029, "ACME 1.0", 0x20),
+            Err(Error::NoSuchDataElementTag { .. })
+        ));
+        assert!(matches!(
+            obj.private_element(0x0028, "ACME 1.0", 0x10),
+            Err(Error::NotPrivateGroup { .. })
+        ));
+
+        // existing block is reused
+        obj.put_private_element(
+            0x0029,
+            "ACME 1.0",
+            0x20,
+            VR::US,
+            PrimitiveValue::from(7_u16),
+        )
+        .unwrap();
+        assert!(obj.has_element(Tag(0x0029, 0x1120)));
+
+        // new creator reserves the first free block
+        obj.put_private_element(0x0029, "NEW", 0x01, VR::LO, PrimitiveValue::from("new"))
+            .unwrap();
+        assert_eq!(
+            obj.element(Tag(0x0029, 0x0012)).unwrap().to_str().unwrap(),
+            "NEW"
+        );
+        assert_eq!(
+            obj.private_element(0x0029, "NEW", 0x01).unwrap().tag(),
+            Tag(0x0029, 0x1201)
+        );
+    }
+
+    #[test]
+    fn inmem_entry_insert_and_modify() {
+        let mut obj = InMemDicomObject::from_element_iter(vec![DataElement::new(
+            tags::PATIENT_ID,
+            VR::LO,
+            PrimitiveValue::from("123"),
+        )]);
+
+        // present elements are kept
+        let elem = obj
+            .entry(tags::PATIENT_ID)
+            .or_insert_with(VR::LO, || -> PrimitiveValue {
+                panic!("should not be called")
+            });
+        assert_eq!(elem.to_str().unwrap(), "123");
+
+        // absent elements are inserted
+        let elem = obj
+            .entry(tags::PATIENT_NAME)
+            .and_modify(|_| panic!("should not be called"))
+            .or_insert(VR::PN, PrimitiveValue::from("Doe^John"));
+        assert_eq!(elem.vr(), VR::PN);
+        assert_eq!(
+            obj.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Doe^John"
+        );
+
+        // the length follows the modified value
+        obj.entry(tags::PATIENT_ID).and_modify(|value| {
+            *value = PrimitiveValue::from("12345").into();
+        });
+        let elem = obj.element(tags::PATIENT_ID).unwrap();
+        assert_eq!(elem.vr(), VR::LO);
+        assert_eq!(elem.to_str().unwrap(), "12345");
+        assert_eq!(elem.length(), Length(5));
+
+        match obj.entry(tags::PATIENT_ID) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.get().to_str().unwrap(), "12345");
+                let old = entry.insert(VR::LO, PrimitiveValue::from("6"));
+                assert_eq!(old.to_str().unwrap(), "12345");
+                assert_eq!(entry.remove().to_str().unwrap(), "6");
+            }
+            Entry::Vacant(_) => panic!("entry should be occupied"),
+        }
+        assert!(matches!(obj.entry(tags::PATIENT_ID), Entry::Vacant(_)));
+    }
+
+    #[test]
+    fn inmem_sequence_item_editing() {
+        let item = |uid: &str| {
+            InMemDicomObject::from_element_iter([DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(uid),
+            )])
+        };
+        let uids = |obj: &InMemDicomObject| {
+            obj.element(tags::REFERENCED_SERIES_SEQUENCE)
+                .unwrap()
+                .items()
+                .unwrap()
+                .iter()
+                .map(|item| {
+                    item.element(tags::SERIES_INSTANCE_UID)
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put_empty_sequence(tags::REFERENCED_SERIES_SEQUENCE);
+        let elem = obj.element(tags::REFERENCED_SERIES_SEQUENCE).unwrap();
+        assert_eq!(elem.vr(), VR::SQ);
+        assert_eq!(elem.items().map(|items| items.len()), Some(0));
+
+        obj.push_item(tags::REFERENCED_SERIES_SEQUENCE, item("1.2.3"))
+            .unwrap();
+        obj.insert_item(tags::REFERENCED_SERIES_SEQUENCE, 0, item("1.2.1"))
+            .unwrap();
+        obj.insert_item(tags::REFERENCED_SERIES_SEQUENCE, 1, item("1.2.2"))
+            .unwrap();
+        assert_eq!(uids(&obj), ["1.2.1", "1.2.2", "1.2.3"]);
+
+        assert!(matches!(
+            obj.insert_item(tags::REFERENCED_SERIES_SEQUENCE, 4, item("1.2.4")),
+            Err(Error::NoSuchItem { index: 4, .. })
+        ));
+        assert!(matches!(
+            obj.remove_item(tags::REFERENCED_SERIES_SEQUENCE, 3),
+            Err(Error::NoSuchItem { index: 3, .. })
+        ));
+        let removed = obj
+            .remove_item(tags::REFERENCED_SERIES_SEQUENCE, 1)
+            .unwrap();
+        assert_eq!(
+            removed
+                .element(tags::SERIES_INSTANCE_UID)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "1.2.2"
+        );
+
+        for item in obj.items_mut(tags::REFERENCED_SERIES_SEQUENCE).unwrap() {
+            item.put_value(tags::SERIES_NUMBER, 1).unwrap();
+        }
+        let items = obj
+            .element(tags::REFERENCED_SERIES_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items
+            .iter()
+            .all(|item| item.has_element(tags::SERIES_NUMBER)));
+
+        // not a sequence, or absent
+        obj.put(DataElement::new(
+            tags::MODALITY,
+            VR::CS,
+            PrimitiveValue::from("CT"),
+        ));
+        assert!(matches!(
+            obj.push_item(tags::MODALITY, item("1.2.5")),
+            Err(Error::NotASequence { .. })
+        ));
+        assert!(matches!(
+            obj.items_mut(tags::REQUEST_ATTRIBUTES_SEQUENCE),
+            Err(Error::NoSuchDataElementTag { .. })
+        ));
+
+        // pushing to an absent sequence creates it
+        obj.push_item(tags::REQUEST_ATTRIBUTES_SEQUENCE, item("1.2.6"))
+            .unwrap();
+        assert_eq!(
+            obj.element(tags::REQUEST_ATTRIBUTES_SEQUENCE).unwrap().vr(),
+            VR::SQ
+        );
+    }
 }
@@ -2,7 +2,7 @@
 use byteordered::byteorder::{ByteOrder, LittleEndian};
 use dicom_core::dicom_value;
 use dicom_core::header::{DataElement, EmptyObject, HasLength, Header};
-use dicom_core::value::{PrimitiveValue, Value};
+use dicom_core::value::{AeTitle, AeTitleError, PrimitiveValue, Value};
 use dicom_core::{Length, Tag, VR};
 use dicom_encoding::decode::{self, DecodeFrom};
 use dicom_encoding::encode::explicit_le::ExplicitVRLittleEndianEncoder;
@@ -92,6 +92,30 @@ pub enum Error {
     #[snafu(display("Undefined value length for data element tagged {}", tag))]
     UndefinedValueLength { tag: Tag, backtrace: Backtrace },
 
+    /// An application entity title in the file meta group is invalid.
+    #[snafu(display("Invalid value for data element `{}`", alias))]
+    InvalidAeTitle {
+        alias: &'static str,
+        #[snafu(backtrace)]
+        source: AeTitleError,
+    },
+
+    /// A UID in the file meta group is not valid.
+    #[snafu(display("Invalid UID `{}` for data element `{}`: {}", value, alias, reason))]
+    InvalidUid {
+        alias: &'static str,
+        value: String,
+        reason: &'static str,
+        backtrace: Backtrace,
+    },
+
+    /// The implementation version name is longer than 16 characters.
+    #[snafu(display(
+        "Invalid value for data element `ImplementationVersionName`: {} characters long, at most 16 allowed",
+        length
+    ))]
+    ImplementationVersionNameTooLong { length: usize, backtrace: Backtrace },
+
     /// The file meta group data set could not be written.
     #[snafu(display("Could not write file meta group data set"))]
     WriteSet {
@@ -483,7 +507,7 @@ impl FileMetaTable {
                 .saturating_add(elem_len);
         }
 
-        builder.build()
+        builder.build_unchecked()
     }
 
     /// Create an iterator over the defined data elements
@@ -583,7 +607,66 @@ impl FileMetaTable {
         self.clone().into_element_iter()
     }
 
+    /// Check that the values of the table are valid:
+    /// that the UIDs are well formed,
+    /// the application entity titles are valid,
+    /// and the implementation version name is not too long.
+    ///
+    /// Tables created by [`FileMetaTableBuilder`] are already validated.
+    /// Tables read from a file are not,
+    /// so that files with slightly wrong values can still be opened.
+    pub fn validate(&self) -> Result<()> {
+        check_uid(&self.media_storage_sop_class_uid, "MediaStorageSOPClassUID")?;
+        check_uid(
+            &self.media_storage_sop_instance_uid,
+            "MediaStorageSOPInstanceUID",
+        )?;
+        check_uid(&self.transfer_syntax, "TransferSyntax")?;
+        check_uid(&self.implementation_class_uid, "ImplementationClassUID")?;
+        if let Some(uid) = &self.private_information_creator_uid {
+            check_uid(uid, "PrivateInformationCreatorUID")?;
+        }
+        if let Some(name) = &self.implementation_version_name {
+            let length = name.trim_end_matches([' ', '\0']).chars().count();
+            ensure!(
+                length <= 16,
+                ImplementationVersionNameTooLongSnafu { length }
+            );
+        }
+        check_ae_title(
+            self.source_application_entity_title.as_deref(),
+            "SourceApplicationEntityTitle",
+        )?;
+        check_ae_title(
+            self.sending_application_entity_title.as_deref(),
+            "SendingApplicationEntityTitle",
+        )?;
+        check_ae_title(
+            self.receiving_application_entity_title.as_deref(),
+            "ReceivingApplicationEntityTitle",
+        )
+    }
+
+    /// Write the file meta group data set to the given writer,
+    /// in _Explicit VR Little Endian_.
+    ///
+    /// Fails before writing anything
+    /// if any of the required UIDs is empty.
     pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        // do not write a file meta group without its required UIDs
+        for (value, alias) in [
+            (
+                self.media_storage_sop_class_uid(),
+                "MediaStorageSOPClassUID",
+            ),
+            (
+                self.media_storage_sop_instance_uid(),
+                "MediaStorageSOPInstanceUID",
+            ),
+            (self.transfer_syntax(), "TransferSyntax"),
+        ] {
+            ensure!(!value.is_empty(), MissingElementSnafu { alias });
+        }
         let mut dset = DataSetWriter::new(
             writer,
             EncoderFor::new(ExplicitVRLittleEndianEncoder::default()),
@@ -766,7 +849,27 @@ impl FileMetaTableBuilder {
     }
 
     /// Build the table.
+    ///
+    /// The information group length is always computed
+    /// from the other attributes.
+    /// If the implementation class UID is not defined,
+    /// it is filled in along with the implementation version name
+    /// of this library.
+    ///
+    /// Fails if any of the media storage SOP class UID,
+    /// media storage SOP instance UID, or transfer syntax UID
+    /// is missing, or if any value is not valid
+    /// (see [`FileMetaTable::validate`]).
     pub fn build(self) -> Result<FileMetaTable> {
+        let table = self.build_unchecked()?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Build the table
+    /// without checking the syntax of its UIDs and other values,
+    /// other than application entity titles.
+    fn build_unchecked(self) -> Result<FileMetaTable> {
         let information_version = self.information_version.unwrap_or(
             // Missing information version, will assume (00H, 01H). See #28
             [0, 1],
@@ -784,6 +887,18 @@ impl FileMetaTableBuilder {
         let transfer_syntax = self.transfer_syntax.context(MissingElementSnafu {
             alias: "TransferSyntax",
         })?;
+        check_ae_title(
+            self.source_application_entity_title.as_deref(),
+            "SourceApplicationEntityTitle",
+        )?;
+        check_ae_title(
+            self.sending_application_entity_title.as_deref(),
+            "SendingApplicationEntityTitle",
+        )?;
+        check_ae_title(
+            self.receiving_application_entity_title.as_deref(),
+            "ReceivingApplicationEntityTitle",
+        )?;
         let mut implementation_version_name = self.implementation_version_name;
         let implementation_class_uid = self.implementation_class_uid.unwrap_or_else(|| {
             // override implementation version name
@@ -813,6 +928,42 @@ impl FileMetaTableBuilder {
     }
 }
 
+/// Ensure that the given value is a valid UID,
+/// ignoring trailing padding.
+fn check_uid(value: &str, alias: &'static str) -> Result<()> {
+    let uid = value.trim_end_matches([' ', '\0']);
+    let reason = if uid.is_empty() {
+        "empty"
+    } else if uid.len() > 64 {
+        "longer than 64 characters"
+    } else if !uid.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        "contains characters other than digits and periods"
+    } else if uid.split('.').any(str::is_empty) {
+        "has an empty component"
+    } else {
+        return Ok(());
+    };
+    InvalidUidSnafu {
+        alias,
+        value: uid,
+        reason,
+    }
+    .fail()
+}
+
+/// Ensure that the given application entity title,
+/// if present and not empty,
+/// is a valid AE title.
+fn check_ae_title(value: Option<&str>, alias: &'static str) -> Result<()> {
+    match value {
+        Some(v) if !v.trim_matches([' ', '\0']).is_empty() => {
+            AeTitle::new(v).context(InvalidAeTitleSnafu { alias })?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
 fn dicom_len<T: AsRef<str>>(x: T) -> u32 {
     (x.as_ref().len() as u32 + 1) & !1
 }
@@ -821,7 +972,7 @@ fn dicom_len<T: AsRef<str>>(x: T) -> u32 {
 mod tests {
     use crate::{IMPLEMENTATION_CLASS_UID, IMPLEMENTATION_VERSION_NAME};
 
-    use super::{dicom_len, FileMetaTable, FileMetaTableBuilder};
+    use super::{dicom_len, Error, FileMetaTable, FileMetaTableBuilder};
     use dicom_core::value::Value;
     use dicom_core::{dicom_value, DataElement, Tag, VR};
 
@@ -939,6 +1090,50 @@ mod tests {
         assert_eq!(table, gt);
     }
 
+    #[test]
+    fn builder_rejects_invalid_ae_titles() {
+        let builder = FileMetaTableBuilder::new()
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.1")
+            .media_storage_sop_instance_uid("1.2.3.4.5.6")
+            .transfer_syntax("1.2.840.10008.1.2.1");
+
+        let table = builder
+            .clone()
+            .source_application_entity_title("STORE-SCU")
+            .receiving_application_entity_title("PACS")
+            .build()
+            .unwrap();
+        assert_eq!(
+            table.source_application_entity_title.as_deref(),
+            Some("STORE-SCU ")
+        );
+
+        let err = builder
+            .clone()
+            .sending_application_entity_title("A-VERY-LONG-AE-TITLE")
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidAeTitle {
+                alias: "SendingApplicationEntityTitle",
+                ..
+            }
+        ));
+
+        let err = builder
+            .receiving_application_entity_title("PACS\\1")
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidAeTitle {
+                alias: "ReceivingApplicationEntityTitle",
+                ..
+            }
+        ));
+    }
+
     /// Build a file meta table with the minimum set of parameters.
     #[test]
     fn create_meta_table_with_builder_minimal() {
@@ -1060,4 +1255,92 @@ mod tests {
 
         assert_eq!(table.information_group_length, 200);
     }
+
+    #[test]
+    fn builder_validates_values() {
+        let builder = FileMetaTableBuilder::new()
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.1")
+            .media_storage_sop_instance_uid("1.2.3.4")
+            .transfer_syntax("1.2.840.10008.1.2.1");
+
+        let err = builder
+            .clone()
+            .media_storage_sop_instance_uid("1.2.3.abc")
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidUid {
+                alias: "MediaStorageSOPInstanceUID",
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Invalid UID `1.2.3.abc` for data element `MediaStorageSOPInstanceUID`: \
+             contains characters other than digits and periods"
+        );
+
+        let err = builder
+            .clone()
+            .transfer_syntax("1.2..840")
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidUid {
+                alias: "TransferSyntax",
+                reason: "has an empty component",
+                ..
+            }
+        ));
+
+        let err = builder
+            .clone()
+            .media_storage_sop_class_uid("")
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidUid {
+                alias: "MediaStorageSOPClassUID",
+                reason: "empty",
+                ..
+            }
+        ));
+
+        let err = builder
+            .clone()
+            .implementation_class_uid("1.2.3")
+            .implementation_version_name("A_VERSION_NAME_TOO_LONG")
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ImplementationVersionNameTooLong { length: 23, .. }
+        ));
+
+        // the implementation is filled in
+        let table = builder.build().unwrap();
+        assert_eq!(table.implementation_class_uid, IMPLEMENTATION_CLASS_UID);
+        assert_eq!(
+            table.implementation_version_name.as_deref(),
+            Some(IMPLEMENTATION_VERSION_NAME)
+        );
+        table.validate().unwrap();
+
+        // tables without the required UIDs are not written
+        let mut table = table;
+        table.media_storage_sop_instance_uid = String::new();
+        let mut out = Vec::new();
+        let err = table.write(&mut out).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::MissingElement {
+                alias: "MediaStorageSOPInstanceUID",
+                ..
+            }
+        ));
+        assert!(out.is_empty());
+    }
 }
@@ -0,0 +1,632 @@
+//! Memory-mapped DICOM objects.
+//!
+//! A [`MmapDicomObject`] maps a DICOM file into memory
+//! and only indexes the positions of its data elements,
+//! so that element values are retrieved as slices of the mapped file
+//! without copying them.
+//! Values are only decoded on demand.
+//! This suits the extraction of a few attributes
+//! from a large number of files,
+//! where reading every value into memory would dominate the run time.
+//!
+//! Data sets in a transfer syntax with a data set codec
+//! (such as _Deflated Explicit VR Little Endian_)
+//! cannot be mapped.
+//! The file must not be modified while it is mapped,
+//! which is why opening a mapped object is `unsafe`.
+//!
+//! This module is only available with the `mmap` feature enabled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dicom_dictionary_std::tags;
+//! use dicom_object::mmap::MmapDicomObject;
+//!
+//! // safety: the file is not modified while it is mapped
+//! let obj = unsafe { MmapDicomObject::open_file("path/to/file.dcm")? };
+//! let root = obj.root();
+//! if let Some(elem) = root.element(tags::PATIENT_ID) {
+//!     // decoded on demand
+//!     let patient_id = obj.value(root, elem)?;
+//!     println!("{}", patient_id.to_str());
+//! }
+//! if let Some(elem) = root.element(tags::PIXEL_DATA) {
+//!     // a slice of the mapped file
+//!     let pixel_data = obj.bytes(elem);
+//!     println!("{} bytes of pixel data", pixel_data.map(|b| b.len()).unwrap_or(0));
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::file::{detect_preamble, ReadPreamble};
+use crate::meta::FileMetaTable;
+use dicom_core::header::{DataElementHeader, Header};
+use dicom_core::value::PrimitiveValue;
+use dicom_core::{Length, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_encoding::text::SpecificCharacterSet;
+use dicom_encoding::transfer_syntax::{Codec, TransferSyntaxIndex};
+use dicom_encoding::TransferSyntax;
+use dicom_parser::dataset::lazy_read::{Error as LazyReadError, LazyDataSetReader};
+use dicom_parser::dataset::LazyDataToken;
+use dicom_parser::stateful::decode::{DynStatefulDecoder, Error as DecodeError, StatefulDecode};
+use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+use memmap2::Mmap;
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Could not open file '{}'", filename.display()))]
+    OpenFile {
+        filename: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not map file '{}' into memory", filename.display()))]
+    MapFile {
+        filename: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not read preamble"))]
+    ReadPreamble {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not parse meta group data set"))]
+    ParseMetaDataSet {
+        #[snafu(backtrace)]
+        source: crate::meta::Error,
+    },
+    #[snafu(display("Unsupported transfer syntax `{}`", uid))]
+    UnsupportedTransferSyntax { uid: String, backtrace: Backtrace },
+    #[snafu(display("Could not create data set reader"))]
+    CreateReader {
+        #[snafu(backtrace)]
+        source: LazyReadError,
+    },
+    #[snafu(display("Could not read data set token"))]
+    ReadToken {
+        #[snafu(backtrace)]
+        source: LazyReadError,
+    },
+    #[snafu(display("Could not skip value"))]
+    SkipValue {
+        #[snafu(backtrace)]
+        source: dicom_parser::dataset::Error,
+    },
+    #[snafu(display("Unexpected token {}", token))]
+    UnexpectedToken {
+        token: &'static str,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Premature data set end"))]
+    PrematureEnd { backtrace: Backtrace },
+    #[snafu(display("Element {} does not have a primitive value", tag))]
+    NotPrimitive { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Could not decode value of element {}", tag))]
+    DecodeValue {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: DecodeError,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The indexed value of a data element in a mapped file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MmapValue {
+    /// A primitive value, at the given byte range of the file
+    Primitive(Range<usize>),
+    /// A sequence of items
+    Sequence(Vec<MmapDataSet>),
+    /// Encapsulated pixel data or another fragmented value,
+    /// at the given byte ranges of the file
+    PixelSequence {
+        /// the basic offset table, if the value is pixel data
+        offset_table: Option<Range<usize>>,
+        /// the fragments
+        fragments: Vec<Range<usize>>,
+    },
+}
+
+/// A data element indexed in a mapped file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MmapElement {
+    header: DataElementHeader,
+    value: MmapValue,
+}
+
+impl MmapElement {
+    /// The header of the element, as read from the file.
+    pub fn header(&self) -> &DataElementHeader {
+        &self.header
+    }
+
+    /// The tag of the element.
+    pub fn tag(&self) -> Tag {
+        self.header.tag
+    }
+
+    /// The value representation of the element.
+    pub fn vr(&self) -> VR {
+        self.header.vr
+    }
+
+    /// The indexed value of the element.
+    pub fn value(&self) -> &MmapValue {
+        &self.value
+    }
+
+    /// The items of the element, if it is a sequence.
+    pub fn items(&self) -> Option<&[MmapDataSet]> {
+        match &self.value {
+            MmapValue::Sequence(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// A data set indexed in a mapped file,
+/// either the root data set or a sequence item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MmapDataSet {
+    entries: BTreeMap<Tag, MmapElement>,
+    charset: SpecificCharacterSet,
+}
+
+impl MmapDataSet {
+    /// Retrieve a data element by its tag.
+    pub fn element(&self, tag: Tag) -> Option<&MmapElement> {
+        self.entries.get(&tag)
+    }
+
+    /// Iterate over the data elements in tag order.
+    pub fn iter(&self) -> impl Iterator<Item = &MmapElement> + '_ {
+        self.entries.values()
+    }
+
+    /// The number of data elements in the data set.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the data set has no data elements.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The character set in use for the text values of this data set.
+    pub fn character_set(&self) -> SpecificCharacterSet {
+        self.charset
+    }
+}
+
+/// A DICOM file mapped into memory,
+/// with the positions of its data elements indexed.
+pub struct MmapDicomObject {
+    meta: FileMetaTable,
+    ts: &'static TransferSyntax,
+    root: MmapDataSet,
+    // declared last so that it outlives any borrow in the fields above
+    map: Mmap,
+}
+
+impl std::fmt::Debug for MmapDicomObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapDicomObject")
+            .field("meta", &self.meta)
+            .field("len", &self.map.len())
+            .field("root", &self.root)
+            .finish()
+    }
+}
+
+impl MmapDicomObject {
+    /// Map the DICOM file at the given path into memory
+    /// and index its data elements.
+    ///
+    /// The file may start with or without the 128-byte preamble.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified, truncated or removed
+    /// by this or any other process while the object is alive,
+    /// as its values are read directly from the mapped file.
+    /// Doing so is undefined behavior.
+    /// See [`memmap2::Mmap::map`] for the details.
+    pub unsafe fn open_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).context(OpenFileSnafu { filename: path })?;
+        // safety: upheld by the caller
+        let map = Mmap::map(&file).context(MapFileSnafu { filename: path })?;
+
+        let mut source = &map[..];
+        let (_, magic_read) =
+            detect_preamble(&mut source, ReadPreamble::Auto).context(ReadPreambleSnafu)?;
+        let meta = if magic_read {
+            FileMetaTable::from_reader((&b"DICM"[..]).chain(&mut source))
+        } else {
+            FileMetaTable::from_reader(&mut source)
+        }
+        .context(ParseMetaDataSetSnafu)?;
+        let start = map.len() - source.len();
+
+        let ts = TransferSyntaxRegistry
+            .get(meta.transfer_syntax())
+            .filter(|ts| !matches!(ts.codec(), Codec::Dataset(_)))
+            .context(UnsupportedTransferSyntaxSnafu {
+                uid: meta.transfer_syntax(),
+            })?;
+
+        let root = Indexer {
+            data: &map,
+            start,
+            ts,
+        }
+        .index_root()?;
+
+        Ok(MmapDicomObject {
+            meta,
+            ts,
+            root,
+            map,
+        })
+    }
+
+    /// The file meta group of the object.
+    pub fn meta(&self) -> &FileMetaTable {
+        &self.meta
+    }
+
+    /// The root data set of the object.
+    pub fn root(&self) -> &MmapDataSet {
+        &self.root
+    }
+
+    /// Retrieve the bytes of a primitive value
+    /// as a slice of the mapped file, without copying them.
+    ///
+    /// Returns `None` if the element does not have a primitive value.
+    pub fn bytes(&self, elem: &MmapElement) -> Option<&[u8]> {
+        match &elem.value {
+            MmapValue::Primitive(range) => Some(&self.map[range.clone()]),
+            _ => None,
+        }
+    }
+
+    /// Retrieve the bytes of each fragment of an encapsulated value
+    /// as slices of the mapped file, without copying them.
+    ///
+    /// Returns `None` if the element is not encapsulated.
+    pub fn fragments(&self, elem: &MmapElement) -> Option<Vec<&[u8]>> {
+        match &elem.value {
+            MmapValue::PixelSequence { fragments, .. } => Some(
+                fragments
+                    .iter()
+                    .map(|range| &self.map[range.clone()])
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Decode the primitive value of an element
+    /// of the given data set in this object.
+    ///
+    /// Text is decoded with the character set of the data set.
+    pub fn value(&self, data_set: &MmapDataSet, elem: &MmapElement) -> Result<PrimitiveValue> {
+        let tag = elem.tag();
+        let bytes = self.bytes(elem).context(NotPrimitiveSnafu { tag })?;
+        decode_value(bytes, &elem.header, self.ts, data_set.charset)
+    }
+}
+
+/// Decode a primitive value from its bytes.
+fn decode_value(
+    bytes: &[u8],
+    header: &DataElementHeader,
+    ts: &TransferSyntax,
+    charset: SpecificCharacterSet,
+) -> Result<PrimitiveValue> {
+    let tag = header.tag();
+    let header = DataElementHeader::new(tag, header.vr(), Length(bytes.len() as u32));
+    let mut decoder =
+        DynStatefulDecoder::new_with(bytes, ts, charset, 0).context(DecodeValueSnafu { tag })?;
+    decoder
+        .read_value_preserved(&header)
+        .context(DecodeValueSnafu { tag })
+}
+
+type MapReader<'a> = LazyDataSetReader<DynStatefulDecoder<Cursor<&'a [u8]>>>;
+
+/// Builds the index of a mapped data set.
+struct Indexer<'a> {
+    /// the whole file
+    data: &'a [u8],
+    /// the position of the data set in the file
+    start: usize,
+    ts: &'a TransferSyntax,
+}
+
+impl Indexer<'_> {
+    /// The byte range of a value in the file,
+    /// which must not go past the end of the file.
+    fn range(&self, offset: u64, len: u32) -> Result<Range<usize>> {
+        let begin = self.start + offset as usize;
+        let end = begin + len as usize;
+        ensure!(end <= self.data.len(), PrematureEndSnafu);
+        Ok(begin..end)
+    }
+
+    /// Index the root data set.
+    fn index_root(&self) -> Result<MmapDataSet> {
+        let mut reader = LazyDataSetReader::new_with_ts_cs(
+            Cursor::new(&self.data[self.start..]),
+            self.ts,
+            SpecificCharacterSet::Default,
+        )
+        .context(CreateReaderSnafu)?;
+        self.index_data_set(&mut reader, SpecificCharacterSet::Default, false)
+    }
+
+    /// Index the data set up to its end, or the end of the item.
+    fn index_data_set(
+        &self,
+        reader: &mut MapReader<'_>,
+        mut charset: SpecificCharacterSet,
+        in_item: bool,
+    ) -> Result<MmapDataSet> {
+        let mut entries = BTreeMap::new();
+        loop {
+            let token = match reader.next_deferring(0) {
+                Some(token) => token.context(ReadTokenSnafu)?,
+                None if in_item => return PrematureEndSnafu.fail(),
+                None => break,
+            };
+            let elem = match token {
+                LazyDataToken::ElementHeader(header) => {
+                    let token = reader
+                        .next_deferring(0)
+                        .context(PrematureEndSnafu)?
+                        .context(ReadTokenSnafu)?;
+                    let range = match token {
+                        LazyDataToken::DeferredValue { header, offset } => {
+                            self.range(offset, header.len.0)?
+                        }
+                        token @ LazyDataToken::LazyValue { .. } => {
+                            // empty values are not deferred
+                            token.skip().context(SkipValueSnafu)?;
+                            self.start..self.start
+                        }
+                        _ => return UnexpectedTokenSnafu { token: "non-value" }.fail(),
+                    };
+                    if header.tag == tags::SPECIFIC_CHARACTER_SET {
+                        let value =
+                            decode_value(&self.data[range.clone()], &header, self.ts, charset)?;
+                        charset = SpecificCharacterSet::from_codes(
+                            value.to_multi_str().iter().map(|c| c.trim()),
+                        )
+                        .unwrap_or(charset);
+                    }
+                    MmapElement {
+                        header,
+                        value: MmapValue::Primitive(range),
+                    }
+                }
+                LazyDataToken::SequenceStart { tag, len } => {
+                    let mut items = Vec::new();
+                    loop {
+                        let token = reader
+                            .next_deferring(0)
+                            .context(PrematureEndSnafu)?
+                            .context(ReadTokenSnafu)?;
+                        match token {
+                            LazyDataToken::ItemStart { .. } => {
+                                items.push(self.index_data_set(reader, charset, true)?);
+                            }
+                            LazyDataToken::SequenceEnd => break,
+                            _ => return UnexpectedTokenSnafu { token: "non-item" }.fail(),
+                        }
+                    }
+                    MmapElement {
+                        header: DataElementHeader::new(tag, VR::SQ, len),
+                        value: MmapValue::Sequence(items),
+                    }
+                }
+                LazyDataToken::PixelSequenceStart => {
+                    let fragments = self.index_fragments(reader)?;
+                    let mut fragments = fragments.into_iter();
+                    let offset_table = fragments.next();
+                    MmapElement {
+                        header: DataElementHeader::new(
+                            Tag(0x7FE0, 0x0010),
+                            VR::OB,
+                            Length::UNDEFINED,
+                        ),
+                        value: MmapValue::PixelSequence {
+                            offset_table,
+                            fragments: fragments.collect(),
+                        },
+                    }
+                }
+                LazyDataToken::FragmentSequenceStart { tag, vr } => MmapElement {
+                    header: DataElementHeader::new(tag, vr, Length::UNDEFINED),
+                    value: MmapValue::PixelSequence {
+                        offset_table: None,
+                        fragments: self.index_fragments(reader)?,
+                    },
+                },
+                LazyDataToken::ItemEnd if in_item => break,
+                _ => {
+                    return UnexpectedTokenSnafu {
+                        token: "out of place",
+                    }
+                    .fail()
+                }
+            };
+            entries.insert(elem.tag(), elem);
+        }
+        Ok(MmapDataSet { entries, charset })
+    }
+
+    /// Index the items of an encapsulated value up to its end.
+    fn index_fragments(&self, reader: &mut MapReader<'_>) -> Result<Vec<Range<usize>>> {
+        let mut fragments = Vec::new();
+        loop {
+            let token = reader
+                .next_deferring(0)
+                .context(PrematureEndSnafu)?
+                .context(ReadTokenSnafu)?;
+            match token {
+                LazyDataToken::ItemStart { len } if len.0 == 0 => {
+                    // empty items are not followed by a value
+                    fragments.push(self.start..self.start);
+                }
+                LazyDataToken::ItemStart { .. } | LazyDataToken::ItemEnd => {}
+                LazyDataToken::DeferredItemValue { len, offset } => {
+                    fragments.push(self.range(offset, len)?);
+                }
+                token @ LazyDataToken::LazyItemValue { .. } => {
+                    // empty items are not deferred
+                    token.skip().context(SkipValueSnafu)?;
+                    fragments.push(self.start..self.start);
+                }
+                LazyDataToken::SequenceEnd => return Ok(fragments),
+                _ => {
+                    return UnexpectedTokenSnafu {
+                        token: "non-fragment",
+                    }
+                    .fail()
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, MmapDicomObject, MmapValue};
+    use crate::meta::FileMetaTableBuilder;
+    use crate::InMemDicomObject;
+    use dicom_core::value::Value;
+    use dicom_core::{DataElement, Length, PrimitiveValue, VR};
+    use dicom_dictionary_std::tags;
+
+    #[test]
+    fn map_file_and_read_values() {
+        let item = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::REFERENCED_SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4"),
+        )]);
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::SPECIFIC_CHARACTER_SET,
+                VR::CS,
+                PrimitiveValue::from("ISO_IR 192"),
+            ),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Simões")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::Empty),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+            DataElement::new(
+                tags::REFERENCED_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                Value::PixelSequence {
+                    offset_table: Default::default(),
+                    fragments: vec![vec![1, 2, 3, 4], vec![5, 6]].into(),
+                },
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.23456789")
+                .transfer_syntax("1.2.840.10008.1.2.4.50"),
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mmap.dcm");
+        obj.write_to_file(&path).unwrap();
+
+        // safety: the file is not modified while it is mapped
+        let obj = unsafe { MmapDicomObject::open_file(&path) }.unwrap();
+        assert_eq!(obj.meta().transfer_syntax(), "1.2.840.10008.1.2.4.50");
+        let root = obj.root();
+        assert_eq!(root.len(), 6);
+
+        let name = root.element(tags::PATIENT_NAME).unwrap();
+        assert_eq!(obj.bytes(name), Some("Simões ".as_bytes()));
+        assert_eq!(obj.value(root, name).unwrap().to_str(), "Simões");
+        let id = root.element(tags::PATIENT_ID).unwrap();
+        assert_eq!(obj.bytes(id), Some(&[][..]));
+        let rows = root.element(tags::ROWS).unwrap();
+        assert_eq!(obj.bytes(rows), Some(&[0x00, 0x02][..]));
+        assert_eq!(obj.value(root, rows).unwrap().to_int::<u16>().unwrap(), 512);
+
+        let items = root
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        let uid = items[0].element(tags::REFERENCED_SOP_INSTANCE_UID).unwrap();
+        assert_eq!(obj.value(&items[0], uid).unwrap().to_str(), "1.2.3.4");
+
+        let pixel_data = root.element(tags::PIXEL_DATA).unwrap();
+        assert!(matches!(
+            pixel_data.value(),
+            MmapValue::PixelSequence { .. }
+        ));
+        assert_eq!(
+            obj.fragments(pixel_data).unwrap(),
+            vec![&[1, 2, 3, 4][..], &[5, 6][..]]
+        );
+        assert!(obj.bytes(pixel_data).is_none());
+    }
+    #[test]
+    fn reject_truncated_file() {
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(8_u16)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(8_u16)),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                PrimitiveValue::from(vec![7_u8; 64]),
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.23456789")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap();
+        let mut data = Vec::new();
+        obj.write_all(&mut data).unwrap();
+
+        // cut the file in the middle of the pixel data value
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("truncated.dcm");
+        std::fs::write(&path, &data[..data.len() - 32]).unwrap();
+
+        // safety: the file is not modified while it is mapped
+        let result = unsafe { MmapDicomObject::open_file(&path) };
+        assert!(matches!(result, Err(Error::PrematureEnd { .. })));
+    }
+}
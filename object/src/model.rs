@@ -0,0 +1,540 @@
+//! Mapping of Rust types to DICOM data sets.
+//!
+//! A type implementing [`DicomModel`]
+//! can be read from and written to an in-memory DICOM object,
+//! with each of its fields mapped to one attribute.
+//! Rather than implementing the trait by hand,
+//! enable the `derive` feature and derive it:
+//!
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # {
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::model::DicomModel;
+//!
+//! #[derive(Debug, PartialEq, DicomModel)]
+//! struct Series {
+//!     // keyword derived from the field name: "Modality"
+//!     modality: String,
+//!     #[dicom(keyword = "SeriesNumber")]
+//!     number: Option<i32>,
+//!     #[dicom(tag = "(0008,103E)")]
+//!     description: Option<String>,
+//! }
+//!
+//! let obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("MR")),
+//!     DataElement::new(tags::SERIES_NUMBER, VR::IS, PrimitiveValue::from("3")),
+//! ]);
+//! let series = Series::from_dicom(&obj)?;
+//! assert_eq!(series, Series { modality: "MR".to_string(), number: Some(3), description: None });
+//!
+//! let obj = series.to_dicom()?;
+//! assert_eq!(obj.element(tags::SERIES_NUMBER)?.vr(), VR::IS);
+//! # }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Each field type must implement [`FieldValue`],
+//! which is provided for strings, numbers, dates and times,
+//! vectors of those for multi-valued attributes,
+//! and vectors of other models for sequences.
+//! Optional fields (`Option<T>`) are `None`
+//! when the attribute is absent or empty.
+use crate::mem::{InMemDicomObject, InMemElement};
+use crate::StandardDataDictionary;
+use dicom_core::dictionary::DataDictionary;
+use dicom_core::value::{
+    CastValueError, ConvertValueError, DicomDate, DicomDateTime, DicomTime, PrimitiveValue, Value,
+};
+use dicom_core::{DataElement, Length, Tag, VR};
+use snafu::{Backtrace, ResultExt, Snafu};
+
+#[cfg(feature = "derive")]
+pub use dicom_derive::DicomModel;
+
+/// Items used by the code generated by the derive macro.
+#[doc(hidden)]
+pub mod __private {
+    pub use dicom_core::dictionary::DataDictionary;
+    pub use dicom_core::{Tag, VR};
+}
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Missing attribute {}", tag))]
+    MissingAttribute { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Could not read attribute {}", tag))]
+    CastField {
+        tag: Tag,
+        source: CastValueError,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not convert attribute {}", tag))]
+    ConvertField {
+        tag: Tag,
+        source: ConvertValueError,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Attribute {} is not a sequence", tag))]
+    NotASequence { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Could not write attribute {}", tag))]
+    WriteField {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A type which maps to a DICOM data set.
+pub trait DicomModel: Sized {
+    /// Read a value of this type from the given object.
+    fn from_dicom<D>(obj: &InMemDicomObject<D>) -> Result<Self>
+    where
+        D: DataDictionary + Clone;
+
+    /// Write the fields of this value to the given object,
+    /// replacing existing attributes.
+    fn write_dicom<D>(&self, obj: &mut InMemDicomObject<D>) -> Result<()>
+    where
+        D: DataDictionary + Clone;
+
+    /// Create a new object with the fields of this value.
+    fn to_dicom(&self) -> Result<InMemDicomObject<StandardDataDictionary>> {
+        let mut obj = InMemDicomObject::new_empty();
+        self.write_dicom(&mut obj)?;
+        Ok(obj)
+    }
+}
+
+/// A type which maps to the value of a single attribute.
+pub trait FieldValue: Sized {
+    /// Read the field from the attribute's element,
+    /// or `None` if the attribute is absent.
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone;
+
+    /// Write the field as the given attribute of the object.
+    ///
+    /// The VR is the given one,
+    /// or otherwise the one in the object's data dictionary.
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone;
+}
+
+/// Retrieve the element of a required field.
+fn required<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<&InMemElement<D>> {
+    elem.ok_or_else(|| MissingAttributeSnafu { tag }.build())
+}
+
+/// Write a primitive value, with the dictionary VR if none is given.
+fn put_primitive<D>(
+    obj: &mut InMemDicomObject<D>,
+    tag: Tag,
+    vr: Option<VR>,
+    value: PrimitiveValue,
+) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    match vr {
+        Some(vr) => {
+            obj.put(DataElement::new(tag, vr, value));
+        }
+        None => {
+            obj.put_value(tag, value).context(WriteFieldSnafu { tag })?;
+        }
+    }
+    Ok(())
+}
+
+impl FieldValue for String {
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        let value = required(tag, elem)?
+            .to_str()
+            .context(CastFieldSnafu { tag })?;
+        Ok(value.trim_end_matches([' ', '\0']).to_string())
+    }
+
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        put_primitive(obj, tag, vr, PrimitiveValue::from(self.as_str()))
+    }
+}
+
+impl FieldValue for Vec<String> {
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        let values = required(tag, elem)?
+            .to_multi_str()
+            .context(CastFieldSnafu { tag })?;
+        Ok(values
+            .iter()
+            .map(|v| v.trim_end_matches([' ', '\0']).to_string())
+            .collect())
+    }
+
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        put_primitive(
+            obj,
+            tag,
+            vr,
+            PrimitiveValue::Strs(self.iter().cloned().collect()),
+        )
+    }
+}
+
+macro_rules! impl_int_field {
+    ($($t: ty => $variant: ident),*) => {
+        $(
+            impl FieldValue for $t {
+                fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    required(tag, elem)?.to_int().context(ConvertFieldSnafu { tag })
+                }
+
+                fn write_field<D>(
+                    &self,
+                    obj: &mut InMemDicomObject<D>,
+                    tag: Tag,
+                    vr: Option<VR>,
+                ) -> Result<()>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    put_primitive(obj, tag, vr, PrimitiveValue::from(*self))
+                }
+            }
+
+            impl FieldValue for Vec<$t> {
+                fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    required(tag, elem)?.to_multi_int().context(ConvertFieldSnafu { tag })
+                }
+
+                fn write_field<D>(
+                    &self,
+                    obj: &mut InMemDicomObject<D>,
+                    tag: Tag,
+                    vr: Option<VR>,
+                ) -> Result<()>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    put_primitive(obj, tag, vr, PrimitiveValue::$variant(self.iter().copied().collect()))
+                }
+            }
+        )*
+    };
+}
+
+impl_int_field!(i16 => I16, u16 => U16, i32 => I32, u32 => U32, i64 => I64, u64 => U64);
+
+macro_rules! impl_float_field {
+    ($($t: ty => $variant: ident, $single: ident, $multi: ident);*) => {
+        $(
+            impl FieldValue for $t {
+                fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    required(tag, elem)?.$single().context(ConvertFieldSnafu { tag })
+                }
+
+                fn write_field<D>(
+                    &self,
+                    obj: &mut InMemDicomObject<D>,
+                    tag: Tag,
+                    vr: Option<VR>,
+                ) -> Result<()>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    put_primitive(obj, tag, vr, PrimitiveValue::from(*self))
+                }
+            }
+
+            impl FieldValue for Vec<$t> {
+                fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    required(tag, elem)?.$multi().context(ConvertFieldSnafu { tag })
+                }
+
+                fn write_field<D>(
+                    &self,
+                    obj: &mut InMemDicomObject<D>,
+                    tag: Tag,
+                    vr: Option<VR>,
+                ) -> Result<()>
+                where
+                    D: DataDictionary + Clone,
+                {
+                    put_primitive(obj, tag, vr, PrimitiveValue::$variant(self.iter().copied().collect()))
+                }
+            }
+        )*
+    };
+}
+
+impl_float_field!(
+    f32 => F32, to_float32, to_multi_float32;
+    f64 => F64, to_float64, to_multi_float64
+);
+
+impl FieldValue for DicomDate {
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        required(tag, elem)?
+            .to_date()
+            .context(ConvertFieldSnafu { tag })
+    }
+
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        let value = PrimitiveValue::Date(std::iter::once(*self).collect());
+        put_primitive(obj, tag, vr, value)
+    }
+}
+
+impl FieldValue for DicomTime {
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        required(tag, elem)?
+            .to_time()
+            .context(ConvertFieldSnafu { tag })
+    }
+
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        let value = PrimitiveValue::Time(std::iter::once(*self).collect());
+        put_primitive(obj, tag, vr, value)
+    }
+}
+
+impl FieldValue for DicomDateTime {
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        // assume UTC for date-times without a timezone
+        let offset = dicom_core::chrono::FixedOffset::east_opt(0).unwrap();
+        required(tag, elem)?
+            .to_datetime(offset)
+            .context(ConvertFieldSnafu { tag })
+    }
+
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        let value = PrimitiveValue::DateTime(std::iter::once(*self).collect());
+        put_primitive(obj, tag, vr, value)
+    }
+}
+
+impl<T> FieldValue for Option<T>
+where
+    T: FieldValue,
+{
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        match elem {
+            None => Ok(None),
+            Some(elem) if elem.is_empty_value() => Ok(None),
+            elem => T::read_field(tag, elem).map(Some),
+        }
+    }
+
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        match self {
+            Some(value) => value.write_field(obj, tag, vr),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sequences of items mapped to another model.
+impl<M> FieldValue for Vec<M>
+where
+    M: DicomModel,
+{
+    fn read_field<D>(tag: Tag, elem: Option<&InMemElement<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        required(tag, elem)?
+            .items()
+            .ok_or_else(|| NotASequenceSnafu { tag }.build())?
+            .iter()
+            .map(M::from_dicom)
+            .collect()
+    }
+
+    fn write_field<D>(&self, obj: &mut InMemDicomObject<D>, tag: Tag, vr: Option<VR>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        let items = self
+            .iter()
+            .map(|model| {
+                let mut item = InMemDicomObject::new_empty_with_dict(obj.dict.clone());
+                model.write_dicom(&mut item)?;
+                Ok(item)
+            })
+            .collect::<Result<_>>()?;
+        obj.put(DataElement::new(
+            tag,
+            vr.unwrap_or(VR::SQ),
+            Value::Sequence {
+                items,
+                size: Length::UNDEFINED,
+            },
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_dictionary_std::tags;
+
+    #[derive(Debug, PartialEq)]
+    struct Code {
+        value: String,
+        meaning: Option<String>,
+    }
+
+    impl DicomModel for Code {
+        fn from_dicom<D>(obj: &InMemDicomObject<D>) -> Result<Self>
+        where
+            D: DataDictionary + Clone,
+        {
+            Ok(Code {
+                value: FieldValue::read_field(
+                    tags::CODE_VALUE,
+                    obj.element_opt(tags::CODE_VALUE).ok().flatten(),
+                )?,
+                meaning: FieldValue::read_field(
+                    tags::CODE_MEANING,
+                    obj.element_opt(tags::CODE_MEANING).ok().flatten(),
+                )?,
+            })
+        }
+
+        fn write_dicom<D>(&self, obj: &mut InMemDicomObject<D>) -> Result<()>
+        where
+            D: DataDictionary + Clone,
+        {
+            self.value.write_field(obj, tags::CODE_VALUE, None)?;
+            self.meaning.write_field(obj, tags::CODE_MEANING, None)
+        }
+    }
+
+    #[test]
+    fn field_values_round_trip() {
+        let mut obj = InMemDicomObject::new_empty();
+        512_u16.write_field(&mut obj, tags::ROWS, None).unwrap();
+        vec![0.5_f64, 0.25]
+            .write_field(&mut obj, tags::PIXEL_SPACING, None)
+            .unwrap();
+        DicomDate::from_ymd(2023, 1, 31)
+            .unwrap()
+            .write_field(&mut obj, tags::STUDY_DATE, None)
+            .unwrap();
+        vec!["ORIGINAL".to_string(), "PRIMARY".to_string()]
+            .write_field(&mut obj, tags::IMAGE_TYPE, None)
+            .unwrap();
+        "ACME"
+            .to_string()
+            .write_field(&mut obj, Tag(0x0009, 0x0010), Some(VR::LO))
+            .unwrap();
+        None::<String>
+            .write_field(&mut obj, tags::STUDY_DESCRIPTION, None)
+            .unwrap();
+        vec![Code {
+            value: "T-A0100".to_string(),
+            meaning: None,
+        }]
+        .write_field(&mut obj, tags::ANATOMIC_REGION_SEQUENCE, None)
+        .unwrap();
+
+        assert_eq!(obj.element(tags::PIXEL_SPACING).unwrap().vr(), VR::DS);
+        assert!(obj.element_opt(tags::STUDY_DESCRIPTION).unwrap().is_none());
+
+        let read = |tag| obj.element_opt(tag).ok().flatten();
+        assert_eq!(u16::read_field(tags::ROWS, read(tags::ROWS)).unwrap(), 512);
+        assert_eq!(
+            Vec::<f64>::read_field(tags::PIXEL_SPACING, read(tags::PIXEL_SPACING)).unwrap(),
+            vec![0.5, 0.25]
+        );
+        assert_eq!(
+            DicomDate::read_field(tags::STUDY_DATE, read(tags::STUDY_DATE)).unwrap(),
+            DicomDate::from_ymd(2023, 1, 31).unwrap()
+        );
+        assert_eq!(
+            Vec::<String>::read_field(tags::IMAGE_TYPE, read(tags::IMAGE_TYPE)).unwrap(),
+            vec!["ORIGINAL", "PRIMARY"]
+        );
+        assert_eq!(
+            String::read_field(Tag(0x0009, 0x0010), read(Tag(0x0009, 0x0010))).unwrap(),
+            "ACME"
+        );
+        assert_eq!(
+            Option::<String>::read_field(tags::STUDY_DESCRIPTION, read(tags::STUDY_DESCRIPTION))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            Vec::<Code>::read_field(
+                tags::ANATOMIC_REGION_SEQUENCE,
+                read(tags::ANATOMIC_REGION_SEQUENCE)
+            )
+            .unwrap(),
+            vec![Code {
+                value: "T-A0100".to_string(),
+                meaning: None,
+            }]
+        );
+        assert!(matches!(
+            String::read_field(tags::PATIENT_ID, read(tags::PATIENT_ID)),
+            Err(Error::MissingAttribute { .. })
+        ));
+    }
+}
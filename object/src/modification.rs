@@ -0,0 +1,417 @@
+//! Tracked modification of attributes.
+//!
+//! Some workflows, such as the correction of patient information,
+//! must keep the values which an instance had before being modified
+//! in the _Original Attributes Sequence_ (PS3.3 C.12.1.1.9).
+//! A [`TrackedEdit`], obtained through
+//! [`tracked_edit`](InMemDicomObject::tracked_edit),
+//! puts and removes elements like the object itself,
+//! while remembering the prior value of each modified attribute.
+//! Once [finished](TrackedEdit::finish),
+//! a new item describing the modification is appended
+//! to the _Original Attributes Sequence_, with:
+//!
+//! - the prior values in the _Modified Attributes Sequence_,
+//!   where attributes which were absent have an empty value;
+//! - the date and time of the modification;
+//! - the modifying system and the reason for the modification.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::modification::{Modification, ModificationReason};
+//!
+//! let mut obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^Jon")),
+//! ]);
+//!
+//! let modification = Modification::new("MY PACS").reason(ModificationReason::Correct);
+//! let mut edit = obj.tracked_edit(&modification);
+//! edit.put(DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")));
+//! edit.finish();
+//!
+//! assert_eq!(obj.element(tags::PATIENT_NAME)?.to_str()?, "Doe^John");
+//! let original = &obj.element(tags::ORIGINAL_ATTRIBUTES_SEQUENCE)?.items().unwrap()[0];
+//! let modified = &original.element(tags::MODIFIED_ATTRIBUTES_SEQUENCE)?.items().unwrap()[0];
+//! assert_eq!(modified.element(tags::PATIENT_NAME)?.to_str()?, "Doe^Jon");
+//! assert_eq!(original.element(tags::MODIFYING_SYSTEM)?.to_str()?, "MY PACS");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::{InMemDicomObject, InMemElement};
+use dicom_core::chrono::{DateTime, FixedOffset, Local};
+use dicom_core::header::Header;
+use dicom_core::value::{PrimitiveValue, Value, C};
+use dicom_core::{DataDictionary, DataElement, Length, Tag, VR};
+use dicom_dictionary_std::tags;
+use std::collections::BTreeMap;
+
+/// The reason for modifying the attributes of an instance,
+/// as in _Reason for the Attribute Modification_ (0400,0565).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ModificationReason {
+    /// The values were changed to conform to the receiving system's needs,
+    /// such as those of a local patient identifier domain.
+    Coerce,
+    /// The values were corrected to fix an error.
+    Correct,
+}
+
+impl ModificationReason {
+    /// Obtain the defined term of this reason.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModificationReason::Coerce => "COERCE",
+            ModificationReason::Correct => "CORRECT",
+        }
+    }
+}
+
+/// The description of a modification of the attributes of an instance,
+/// recorded along with the prior values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modification {
+    modifying_system: String,
+    reason: ModificationReason,
+    source_of_previous_values: String,
+    date_time: Option<DateTime<FixedOffset>>,
+}
+
+impl Modification {
+    /// Create a modification made by the given system,
+    /// recorded in _Modifying System_.
+    ///
+    /// The reason is [`Correct`](ModificationReason::Correct) by default.
+    pub fn new(modifying_system: impl Into<String>) -> Self {
+        Modification {
+            modifying_system: modifying_system.into(),
+            reason: ModificationReason::Correct,
+            source_of_previous_values: String::new(),
+            date_time: None,
+        }
+    }
+
+    /// Set the reason for the modification.
+    pub fn reason(mut self, reason: ModificationReason) -> Self {
+        self.reason = reason;
+        self
+    }
+
+    /// Set the source which provided the prior values,
+    /// such as the AE title of the sending system.
+    /// Left empty if not set.
+    pub fn source_of_previous_values(mut self, source: impl Into<String>) -> Self {
+        self.source_of_previous_values = source.into();
+        self
+    }
+
+    /// Set the date and time of the modification,
+    /// which is the moment the edit is finished by default.
+    pub fn date_time(mut self, date_time: DateTime<FixedOffset>) -> Self {
+        self.date_time = Some(date_time);
+        self
+    }
+}
+
+/// A set of changes to an object
+/// whose prior values are recorded in the _Original Attributes Sequence_.
+///
+/// The changes are applied to the object immediately,
+/// but the modification is only recorded by [`finish`](TrackedEdit::finish).
+#[must_use = "the modification is only recorded by `finish`"]
+#[derive(Debug)]
+pub struct TrackedEdit<'a, D> {
+    obj: &'a mut InMemDicomObject<D>,
+    modification: Modification,
+    originals: BTreeMap<Tag, InMemElement<D>>,
+}
+
+impl<'a, D> TrackedEdit<'a, D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Retrieve the object being modified.
+    pub fn object(&self) -> &InMemDicomObject<D> {
+        self.obj
+    }
+
+    /// Insert a data element to the object, replacing (and returning) any
+    /// previous element of the same attribute.
+    pub fn put(&mut self, elt: InMemElement<D>) -> Option<InMemElement<D>> {
+        let tag = elt.tag();
+        let vr = elt.vr();
+        let prior = self.obj.put(elt);
+        self.record(tag, vr, prior.as_ref());
+        prior
+    }
+
+    /// Insert a data element with the given value to the object,
+    /// replacing (and returning) any previous element of the same attribute.
+    ///
+    /// See [`InMemDicomObject::put_value`]
+    /// for how the VR and value are determined.
+    pub fn put_value<V>(&mut self, tag: Tag, value: V) -> crate::Result<Option<InMemElement<D>>>
+    where
+        V: Into<PrimitiveValue>,
+    {
+        let prior = self.obj.put_value(tag, value)?;
+        let vr = self.obj.element(tag)?.vr();
+        self.record(tag, vr, prior.as_ref());
+        Ok(prior)
+    }
+
+    /// Remove a DICOM element by its tag,
+    /// reporting whether it was present.
+    pub fn remove(&mut self, tag: Tag) -> bool {
+        match self.obj.take_element(tag) {
+            Ok(prior) => {
+                self.record(tag, prior.vr(), Some(&prior));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Record the modification in the _Original Attributes Sequence_
+    /// of the object.
+    ///
+    /// Nothing is recorded if no element was put or removed.
+    pub fn finish(self) {
+        let TrackedEdit {
+            obj,
+            modification,
+            originals,
+        } = self;
+        if originals.is_empty() {
+            return;
+        }
+        let dict = obj.dict.clone();
+        let date_time = modification
+            .date_time
+            .unwrap_or_else(|| Local::now().into());
+
+        let modified = InMemDicomObject::from_iter_with_dict(originals.into_values(), dict.clone());
+        let text =
+            |tag: Tag, vr: VR, value: &str| DataElement::new(tag, vr, PrimitiveValue::from(value));
+        let item = InMemDicomObject::from_iter_with_dict(
+            [
+                text(
+                    tags::SOURCE_OF_PREVIOUS_VALUES,
+                    VR::LO,
+                    &modification.source_of_previous_values,
+                ),
+                text(
+                    tags::ATTRIBUTE_MODIFICATION_DATE_TIME,
+                    VR::DT,
+                    &date_time.format("%Y%m%d%H%M%S%.6f%z").to_string(),
+                ),
+                text(
+                    tags::MODIFYING_SYSTEM,
+                    VR::LO,
+                    &modification.modifying_system,
+                ),
+                text(
+                    tags::REASON_FOR_THE_ATTRIBUTE_MODIFICATION,
+                    VR::CS,
+                    modification.reason.as_str(),
+                ),
+                DataElement::new(
+                    tags::MODIFIED_ATTRIBUTES_SEQUENCE,
+                    VR::SQ,
+                    Value::Sequence {
+                        items: vec![modified].into(),
+                        size: Length::UNDEFINED,
+                    },
+                ),
+            ],
+            dict,
+        );
+
+        let mut items = match obj
+            .take_element(tags::ORIGINAL_ATTRIBUTES_SEQUENCE)
+            .map(|e| e.into_value())
+        {
+            Ok(Value::Sequence { items, .. }) => items,
+            _ => C::new(),
+        };
+        items.push(item);
+        obj.put(DataElement::new(
+            tags::ORIGINAL_ATTRIBUTES_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items,
+                size: Length::UNDEFINED,
+            },
+        ));
+    }
+
+    /// Keep the value of the attribute before the first change made to it.
+    fn record(&mut self, tag: Tag, vr: VR, prior: Option<&InMemElement<D>>) {
+        self.originals.entry(tag).or_insert_with(|| match prior {
+            Some(prior) => prior.clone(),
+            None => DataElement::empty(tag, vr),
+        });
+    }
+}
+
+impl<D> InMemDicomObject<D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Start a set of changes to this object
+    /// whose prior values are recorded
+    /// in the _Original Attributes Sequence_,
+    /// as described in the [module documentation](crate::modification).
+    pub fn tracked_edit(&mut self, modification: &Modification) -> TrackedEdit<'_, D> {
+        TrackedEdit {
+            obj: self,
+            modification: modification.clone(),
+            originals: BTreeMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::chrono::TimeZone;
+
+    fn text(obj: &InMemDicomObject, tag: Tag) -> String {
+        obj.element(tag).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn tracked_edit_records_prior_values() {
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^Jon")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("123")),
+            DataElement::new(
+                tags::OTHER_PATIENT_I_DS_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: C::new(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+        ]);
+        let date_time = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 2, 1, 10, 30, 0)
+            .unwrap();
+        let modification = Modification::new("MY PACS")
+            .reason(ModificationReason::Coerce)
+            .source_of_previous_values("REMOTE")
+            .date_time(date_time);
+
+        let mut edit = obj.tracked_edit(&modification);
+        edit.put(DataElement::new(
+            tags::PATIENT_NAME,
+            VR::PN,
+            PrimitiveValue::from("Doe^John"),
+        ));
+        // only the first prior value is kept
+        edit.put(DataElement::new(
+            tags::PATIENT_NAME,
+            VR::PN,
+            PrimitiveValue::from("Doe^Johnny"),
+        ));
+        edit.put_value(tags::ISSUER_OF_PATIENT_ID, "LOCAL").unwrap();
+        assert!(edit.remove(tags::OTHER_PATIENT_I_DS_SEQUENCE));
+        assert!(!edit.remove(tags::PATIENT_BIRTH_DATE));
+        edit.finish();
+
+        assert_eq!(text(&obj, tags::PATIENT_NAME), "Doe^Johnny");
+        assert_eq!(text(&obj, tags::ISSUER_OF_PATIENT_ID), "LOCAL");
+        assert!(obj.element(tags::OTHER_PATIENT_I_DS_SEQUENCE).is_err());
+
+        let originals = obj
+            .element(tags::ORIGINAL_ATTRIBUTES_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(originals.len(), 1);
+        let original = &originals[0];
+        assert_eq!(text(original, tags::MODIFYING_SYSTEM), "MY PACS");
+        assert_eq!(text(original, tags::SOURCE_OF_PREVIOUS_VALUES), "REMOTE");
+        assert_eq!(
+            text(original, tags::REASON_FOR_THE_ATTRIBUTE_MODIFICATION),
+            "COERCE"
+        );
+        assert_eq!(
+            text(original, tags::ATTRIBUTE_MODIFICATION_DATE_TIME),
+            "20240201103000.000000+0100"
+        );
+
+        let modified = &original
+            .element(tags::MODIFIED_ATTRIBUTES_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            modified.tags().collect::<Vec<_>>(),
+            [
+                tags::PATIENT_NAME,
+                tags::ISSUER_OF_PATIENT_ID,
+                tags::OTHER_PATIENT_I_DS_SEQUENCE,
+            ]
+        );
+        assert_eq!(text(modified, tags::PATIENT_NAME), "Doe^Jon");
+        let issuer = modified.element(tags::ISSUER_OF_PATIENT_ID).unwrap();
+        assert_eq!(issuer.vr(), VR::LO);
+        assert!(issuer.is_empty_value());
+        assert_eq!(
+            modified
+                .element(tags::OTHER_PATIENT_I_DS_SEQUENCE)
+                .unwrap()
+                .vr(),
+            VR::SQ
+        );
+    }
+
+    #[test]
+    fn tracked_edits_append_items() {
+        let mut obj = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::PATIENT_ID,
+            VR::LO,
+            PrimitiveValue::from("1"),
+        )]);
+        let modification = Modification::new("MY PACS");
+
+        // nothing is recorded without changes
+        obj.tracked_edit(&modification).finish();
+        assert!(obj.element(tags::ORIGINAL_ATTRIBUTES_SEQUENCE).is_err());
+
+        for id in ["2", "3"] {
+            let mut edit = obj.tracked_edit(&modification);
+            edit.put(DataElement::new(
+                tags::PATIENT_ID,
+                VR::LO,
+                PrimitiveValue::from(id),
+            ));
+            edit.finish();
+        }
+
+        let originals = obj
+            .element(tags::ORIGINAL_ATTRIBUTES_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        let prior_id = |i: usize| {
+            let modified = &originals[i]
+                .element(tags::MODIFIED_ATTRIBUTES_SEQUENCE)
+                .unwrap()
+                .items()
+                .unwrap()[0];
+            text(modified, tags::PATIENT_ID)
+        };
+        assert_eq!(originals.len(), 2);
+        assert_eq!(prior_id(0), "1");
+        assert_eq!(prior_id(1), "2");
+        assert_eq!(
+            text(&originals[0], tags::REASON_FOR_THE_ATTRIBUTE_MODIFICATION),
+            "CORRECT"
+        );
+    }
+}
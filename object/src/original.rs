@@ -0,0 +1,105 @@
+//! Retention of the original encoding of data element values.
+//!
+//! When reading an object with
+//! [`preserve_encoding`](crate::OpenFileOptions::preserve_encoding),
+//! the encoded bytes of values which may not be reproduced exactly
+//! from their decoded form
+//! (text in any character set, and values of an odd length)
+//! are kept alongside the object.
+//! Elements which were not modified since
+//! are then written back with these same bytes.
+use dicom_core::value::PrimitiveValue;
+use dicom_core::VR;
+use std::cell::RefCell;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+/// The original encoding of a primitive data element value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OriginalValue {
+    /// the value representation of the element as read
+    pub vr: VR,
+    /// the value as read, to detect modifications
+    pub value: PrimitiveValue,
+    /// the value in its encoded form
+    pub bytes: Vec<u8>,
+}
+
+impl OriginalValue {
+    /// Check whether the original encoding of a value
+    /// with the given VR and length should be kept,
+    /// as it might not be reproduced from the decoded value.
+    ///
+    /// Only encodings which do not depend on the byte order are considered.
+    pub fn should_keep(vr: VR, len: u32) -> bool {
+        match vr {
+            VR::AE
+            | VR::AS
+            | VR::CS
+            | VR::DA
+            | VR::DS
+            | VR::DT
+            | VR::IS
+            | VR::LO
+            | VR::LT
+            | VR::PN
+            | VR::SH
+            | VR::ST
+            | VR::TM
+            | VR::UC
+            | VR::UI
+            | VR::UR
+            | VR::UT => len > 0,
+            VR::OB | VR::UN => len % 2 == 1,
+            _ => false,
+        }
+    }
+}
+
+/// A shared handle to the bytes recorded by a [`RecordingReader`].
+///
+/// Bytes are only recorded between a call to [`start`](Self::start)
+/// and the following call to [`take`](Self::take).
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ValueRecorder(Rc<RefCell<Option<Vec<u8>>>>);
+
+impl ValueRecorder {
+    /// Start recording the bytes read.
+    pub fn start(&self) {
+        *self.0.borrow_mut() = Some(Vec::new());
+    }
+
+    /// Stop recording and retrieve the bytes read since the start.
+    pub fn take(&self) -> Vec<u8> {
+        self.0.borrow_mut().take().unwrap_or_default()
+    }
+}
+
+/// A reader which records the bytes read through it, on demand.
+pub(crate) struct RecordingReader<R> {
+    inner: R,
+    recorder: Option<ValueRecorder>,
+}
+
+impl<R> RecordingReader<R> {
+    /// Wrap the given reader,
+    /// recording through the given handle if any.
+    pub fn new(inner: R, recorder: Option<ValueRecorder>) -> Self {
+        RecordingReader { inner, recorder }
+    }
+}
+
+impl<R> Read for RecordingReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(recorder) = &self.recorder {
+            if let Some(record) = recorder.0.borrow_mut().as_mut() {
+                record.extend_from_slice(&buf[..n]);
+            }
+        }
+        Ok(n)
+    }
+}
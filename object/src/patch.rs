@@ -0,0 +1,519 @@
+//! Application of change sets to DICOM objects.
+//!
+//! A [`Patch`] is an ordered list of changes
+//! (insertions, replacements and removals of data elements,
+//! possibly in items of nested sequences)
+//! which can be applied to any number of objects,
+//! such as all instances of a series.
+//! A patch can also be built from a template data set,
+//! so that its elements are merged into each object.
+//!
+//! The [`ConflictPolicy`] decides what happens
+//! when a change would replace an existing element
+//! with a different value.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::{InMemDicomObject, TagPath};
+//! use dicom_object::patch::{ConflictPolicy, Patch};
+//!
+//! let patch = Patch::new()
+//!     .put(TagPath::new(tags::INSTITUTION_NAME), VR::LO, PrimitiveValue::from("Hospital"))
+//!     .remove(TagPath::new(tags::OPERATORS_NAME));
+//!
+//! let mut obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::INSTITUTION_NAME, VR::LO, PrimitiveValue::from("Clinic")),
+//!     DataElement::new(tags::OPERATORS_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+//! ]);
+//!
+//! // existing values are kept
+//! assert_eq!(obj.apply_patch(&patch, ConflictPolicy::KeepExisting)?, 1);
+//! assert_eq!(obj.element(tags::INSTITUTION_NAME)?.to_str()?, "Clinic");
+//! assert!(obj.element_opt(tags::OPERATORS_NAME)?.is_none());
+//!
+//! // existing values are replaced
+//! assert_eq!(obj.apply_patch(&patch, ConflictPolicy::Overwrite)?, 1);
+//! assert_eq!(obj.element(tags::INSTITUTION_NAME)?.to_str()?, "Hospital");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::{InMemDicomObject, InMemFragment};
+use crate::path::TagPath;
+use crate::StandardDataDictionary;
+use dicom_core::dictionary::DataDictionary;
+use dicom_core::header::Header;
+use dicom_core::value::equality::value_eq;
+use dicom_core::value::Value;
+use dicom_core::VR;
+use snafu::{Backtrace, ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// A change would replace an element with a different value
+    #[snafu(display("Conflicting value at {}", path))]
+    Conflict { path: TagPath, backtrace: Backtrace },
+    /// A change could not be applied to the object
+    #[snafu(display("Could not apply change at {}", path))]
+    Apply {
+        path: TagPath,
+        #[snafu(backtrace)]
+        #[snafu(source(from(crate::Error, Box::from)))]
+        source: Box<crate::Error>,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The policy for changes which would replace an existing element
+/// with a different value.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConflictPolicy {
+    /// Replace the existing element
+    #[default]
+    Overwrite,
+    /// Keep the existing element and skip the change
+    KeepExisting,
+    /// Fail without applying any change
+    Fail,
+}
+
+/// A single change to a DICOM object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<D = StandardDataDictionary> {
+    /// Insert or replace the element at the given path
+    Put {
+        path: TagPath,
+        vr: VR,
+        value: Box<Value<InMemDicomObject<D>, InMemFragment>>,
+    },
+    /// Remove the element at the given path, if present
+    Remove { path: TagPath },
+}
+
+impl<D> Change<D> {
+    /// The path of the element affected by the change.
+    pub fn path(&self) -> &TagPath {
+        match self {
+            Change::Put { path, .. } | Change::Remove { path } => path,
+        }
+    }
+}
+
+/// An ordered set of changes to DICOM objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patch<D = StandardDataDictionary> {
+    changes: Vec<Change<D>>,
+}
+
+impl<D> Default for Patch<D> {
+    fn default() -> Self {
+        Patch {
+            changes: Vec::new(),
+        }
+    }
+}
+
+impl<D> Patch<D> {
+    /// Create an empty patch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a patch which puts every element of the given template
+    /// into the root data set of the object.
+    ///
+    /// Sequences in the template replace the whole sequence in the object.
+    pub fn from_template(template: &InMemDicomObject<D>) -> Self
+    where
+        D: DataDictionary + Clone,
+    {
+        Patch {
+            changes: template
+                .iter()
+                .map(|e| Change::Put {
+                    path: TagPath::new(e.tag()),
+                    vr: e.vr(),
+                    value: Box::new(e.value().clone()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Add a change which inserts or replaces
+    /// the element at the given path.
+    pub fn put<V>(mut self, path: TagPath, vr: VR, value: V) -> Self
+    where
+        V: Into<Value<InMemDicomObject<D>, InMemFragment>>,
+    {
+        self.changes.push(Change::Put {
+            path,
+            vr,
+            value: Box::new(value.into()),
+        });
+        self
+    }
+
+    /// Add a change which removes the element at the given path.
+    pub fn remove(mut self, path: TagPath) -> Self {
+        self.changes.push(Change::Remove { path });
+        self
+    }
+
+    /// Add a change to the end of the patch.
+    pub fn push(&mut self, change: Change<D>) {
+        self.changes.push(change);
+    }
+
+    /// The changes in the patch, in order of application.
+    pub fn changes(&self) -> &[Change<D>] {
+        &self.changes
+    }
+
+    /// Check whether the patch has no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl<D> InMemDicomObject<D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Apply the changes of the given patch to this object, in order,
+    /// returning the number of changes which modified the object.
+    ///
+    /// Putting an element with the same value as the existing one
+    /// (following the equality semantics of its VR),
+    /// or removing an element which is not present,
+    /// leaves the object unchanged.
+    /// Any other replacement is resolved by the given conflict policy.
+    /// With [`ConflictPolicy::Fail`],
+    /// conflicts are checked before applying any change.
+    ///
+    /// Fails if a sequence item along the path of a change does not exist,
+    /// in which case the preceding changes remain applied.
+    pub fn apply_patch(&mut self, patch: &Patch<D>, policy: ConflictPolicy) -> Result<usize> {
+        if policy == ConflictPolicy::Fail {
+            for change in &patch.changes {
+                if let Change::Put { path, vr, value } = change {
+                    if self.conflicts(path, *vr, value) {
+                        return ConflictSnafu { path: path.clone() }.fail();
+                    }
+                }
+            }
+        }
+
+        let mut applied = 0;
+        for change in &patch.changes {
+            match change {
+                Change::Put { path, vr, value } => {
+                    if self.is_unchanged(path, *vr, value)
+                        || (policy == ConflictPolicy::KeepExisting
+                            && self.conflicts(path, *vr, value))
+                    {
+                        continue;
+                    }
+                    self.put_at(path, *vr, (**value).clone())
+                        .context(ApplySnafu { path: path.clone() })?;
+                }
+                Change::Remove { path } => {
+                    let removed = self
+                        .remove_at(path)
+                        .context(ApplySnafu { path: path.clone() })?;
+                    if !removed {
+                        continue;
+                    }
+                }
+            }
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Merge the elements of the given template into this object,
+    /// returning the number of elements which modified the object.
+    ///
+    /// This is equivalent to applying [`Patch::from_template`].
+    pub fn merge(
+        &mut self,
+        template: &InMemDicomObject<D>,
+        policy: ConflictPolicy,
+    ) -> Result<usize> {
+        self.apply_patch(&Patch::from_template(template), policy)
+    }
+
+    /// Check whether the element at the path has the given VR and value.
+    fn is_unchanged(
+        &self,
+        path: &TagPath,
+        vr: VR,
+        value: &Value<InMemDicomObject<D>, InMemFragment>,
+    ) -> bool {
+        match self.element_at(path) {
+            Ok(elem) => elem.vr() == vr && same_value(vr, elem.value(), value),
+            Err(_) => false,
+        }
+    }
+
+    /// Check whether putting the given value at the path
+    /// would replace an existing element with a different value.
+    fn conflicts(
+        &self,
+        path: &TagPath,
+        vr: VR,
+        value: &Value<InMemDicomObject<D>, InMemFragment>,
+    ) -> bool {
+        match self.element_at(path) {
+            Ok(_) => !self.is_unchanged(path, vr, value),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Compare two values by content,
+/// regardless of the recorded lengths of sequences.
+fn same_value<D>(
+    vr: VR,
+    a: &Value<InMemDicomObject<D>, InMemFragment>,
+    b: &Value<InMemDicomObject<D>, InMemFragment>,
+) -> bool
+where
+    D: DataDictionary + Clone,
+{
+    match (a, b) {
+        (Value::Primitive(a), Value::Primitive(b)) => value_eq(vr, a, b),
+        (Value::Sequence { items: a, .. }, Value::Sequence { items: b, .. }) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_item(a, b))
+        }
+        (
+            Value::PixelSequence {
+                offset_table: t1,
+                fragments: f1,
+            },
+            Value::PixelSequence {
+                offset_table: t2,
+                fragments: f2,
+            },
+        ) => t1 == t2 && f1 == f2,
+        _ => false,
+    }
+}
+
+fn same_item<D>(a: &InMemDicomObject<D>, b: &InMemDicomObject<D>) -> bool
+where
+    D: DataDictionary + Clone,
+{
+    a.iter().count() == b.iter().count()
+        && a.iter().zip(b.iter()).all(|(e1, e2)| {
+            e1.tag() == e2.tag()
+                && e1.vr() == e2.vr()
+                && same_value(e1.vr(), e1.value(), e2.value())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConflictPolicy, Error, Patch};
+    use crate::{InMemDicomObject, TagPath};
+    use dicom_core::value::Value;
+    use dicom_core::{DataElement, Length, PrimitiveValue, VR};
+    use dicom_dictionary_std::tags;
+
+    fn series_instance(number: i32) -> InMemDicomObject {
+        let item = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::REFERENCED_SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4"),
+        )]);
+        InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::INSTANCE_NUMBER,
+                VR::IS,
+                PrimitiveValue::from(number.to_string()),
+            ),
+            DataElement::new(
+                tags::SERIES_DESCRIPTION,
+                VR::LO,
+                PrimitiveValue::from("AXIAL"),
+            ),
+            DataElement::new(
+                tags::BODY_PART_EXAMINED,
+                VR::CS,
+                PrimitiveValue::from("HEAD"),
+            ),
+            DataElement::new(
+                tags::REFERENCED_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn apply_patch_with_conflict_policies() {
+        let patch = Patch::new()
+            .put(
+                TagPath::new(tags::SERIES_DESCRIPTION),
+                VR::LO,
+                PrimitiveValue::from("AXIAL T1"),
+            )
+            // same value, insignificant padding
+            .put(
+                TagPath::new(tags::BODY_PART_EXAMINED),
+                VR::CS,
+                PrimitiveValue::from(" HEAD "),
+            )
+            .put(
+                TagPath::new(tags::PROTOCOL_NAME),
+                VR::LO,
+                PrimitiveValue::from("BRAIN"),
+            )
+            .put(
+                "ReferencedImageSequence[0].ReferencedFrameNumber"
+                    .parse()
+                    .unwrap(),
+                VR::IS,
+                PrimitiveValue::from("1"),
+            )
+            .remove(TagPath::new(tags::OPERATORS_NAME));
+
+        // overwrite, over the whole series
+        for i in 1..=3 {
+            let mut obj = series_instance(i);
+            assert_eq!(
+                obj.apply_patch(&patch, ConflictPolicy::Overwrite).unwrap(),
+                3
+            );
+            assert_eq!(
+                obj.element(tags::SERIES_DESCRIPTION)
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+                "AXIAL T1"
+            );
+            assert_eq!(
+                obj.element(tags::BODY_PART_EXAMINED)
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+                "HEAD"
+            );
+            assert_eq!(
+                obj.element(tags::PROTOCOL_NAME).unwrap().to_str().unwrap(),
+                "BRAIN"
+            );
+            assert_eq!(
+                obj.element_at(
+                    &"ReferencedImageSequence[0].ReferencedFrameNumber"
+                        .parse()
+                        .unwrap()
+                )
+                .unwrap()
+                .to_int::<i32>()
+                .unwrap(),
+                1
+            );
+            assert_eq!(
+                obj.element(tags::INSTANCE_NUMBER)
+                    .unwrap()
+                    .to_int::<i32>()
+                    .unwrap(),
+                i
+            );
+        }
+
+        // keep existing
+        let mut obj = series_instance(1);
+        assert_eq!(
+            obj.apply_patch(&patch, ConflictPolicy::KeepExisting)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            obj.element(tags::SERIES_DESCRIPTION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "AXIAL"
+        );
+        assert!(obj.element_opt(tags::PROTOCOL_NAME).unwrap().is_some());
+
+        // fail, without changes
+        let mut obj = series_instance(1);
+        let err = obj.apply_patch(&patch, ConflictPolicy::Fail).unwrap_err();
+        assert!(
+            matches!(err, Error::Conflict { ref path, .. } if path.tag() == tags::SERIES_DESCRIPTION)
+        );
+        assert!(obj.element_opt(tags::PROTOCOL_NAME).unwrap().is_none());
+
+        // missing item
+        let patch = Patch::new().put(
+            "ReferencedImageSequence[1].ReferencedFrameNumber"
+                .parse()
+                .unwrap(),
+            VR::IS,
+            PrimitiveValue::from("1"),
+        );
+        let err = obj
+            .apply_patch(&patch, ConflictPolicy::Overwrite)
+            .unwrap_err();
+        assert!(matches!(err, Error::Apply { .. }));
+    }
+
+    #[test]
+    fn merge_template_into_object() {
+        let template = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::SERIES_DESCRIPTION,
+                VR::LO,
+                PrimitiveValue::from("CORONAL"),
+            ),
+            DataElement::new(
+                tags::INSTITUTION_NAME,
+                VR::LO,
+                PrimitiveValue::from("Hospital"),
+            ),
+        ]);
+
+        let mut obj = series_instance(1);
+        assert_eq!(
+            obj.merge(&template, ConflictPolicy::KeepExisting).unwrap(),
+            1
+        );
+        assert_eq!(
+            obj.element(tags::SERIES_DESCRIPTION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "AXIAL"
+        );
+        assert_eq!(
+            obj.element(tags::INSTITUTION_NAME)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Hospital"
+        );
+
+        assert_eq!(obj.merge(&template, ConflictPolicy::Overwrite).unwrap(), 1);
+        assert_eq!(
+            obj.element(tags::SERIES_DESCRIPTION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "CORONAL"
+        );
+        // nothing left to change
+        assert_eq!(obj.merge(&template, ConflictPolicy::Fail).unwrap(), 0);
+        // including sequences
+        let template = series_instance(1);
+        let mut obj = series_instance(1);
+        assert_eq!(obj.merge(&template, ConflictPolicy::Fail).unwrap(), 0);
+    }
+}
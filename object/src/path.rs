@@ -0,0 +1,290 @@
+//! Paths to data elements in nested sequences.
+//!
+//! A [`TagPath`] selects a data element in an object
+//! or in an item of one of its sequences,
+//! at any depth.
+//! Paths can be parsed from text,
+//! in which each sequence is followed by the index of an item
+//! and steps are separated by a dot.
+//! Attributes are written either by keyword or by tag.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::Tag;
+//! # use dicom_dictionary_std::tags;
+//! use dicom_object::path::TagPath;
+//!
+//! let path: TagPath =
+//!     "SharedFunctionalGroupsSequence[0].PixelMeasuresSequence[0].PixelSpacing".parse()?;
+//! assert_eq!(
+//!     path.items(),
+//!     &[
+//!         (tags::SHARED_FUNCTIONAL_GROUPS_SEQUENCE, 0),
+//!         (tags::PIXEL_MEASURES_SEQUENCE, 0),
+//!     ]
+//! );
+//! assert_eq!(path.tag(), tags::PIXEL_SPACING);
+//!
+//! // tags are accepted as well
+//! let path2: TagPath = "(5200,9229)[0].(0028,9110)[0].(0028,0030)".parse()?;
+//! assert_eq!(path, path2);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::Tag;
+use dicom_dictionary_std::StandardDataDictionary;
+use snafu::{ensure, Backtrace, OptionExt, Snafu};
+use std::fmt;
+use std::str::FromStr;
+
+/// An error parsing a [`TagPath`] from text
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ParseTagPathError {
+    /// The path does not have any attribute
+    #[snafu(display("Empty tag path"))]
+    Empty { backtrace: Backtrace },
+    /// An attribute in the path is neither a tag nor a known keyword
+    #[snafu(display("Unknown attribute `{}` in tag path", name))]
+    UnknownAttribute { name: String, backtrace: Backtrace },
+    /// A sequence in the path is not followed by an item index
+    #[snafu(display("Missing item index after `{}` in tag path", step))]
+    MissingItemIndex { step: String, backtrace: Backtrace },
+    /// An item index in the path is malformed
+    #[snafu(display("Invalid item index in `{}`", step))]
+    InvalidItemIndex { step: String, backtrace: Backtrace },
+    /// The last attribute in the path is followed by an item index
+    #[snafu(display("Unexpected item index in last step `{}`", step))]
+    UnexpectedItemIndex { step: String, backtrace: Backtrace },
+}
+
+/// A path to a data element,
+/// possibly in an item of a nested sequence.
+///
+/// The path is made of zero or more sequence steps,
+/// each with a sequence tag and an item index (starting at 0),
+/// followed by the tag of the data element.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TagPath {
+    /// the sequences to enter, with the index of the item
+    items: Vec<(Tag, u32)>,
+    /// the tag of the data element
+    tag: Tag,
+}
+
+impl TagPath {
+    /// Create a path to a data element in the root data set.
+    pub fn new(tag: Tag) -> Self {
+        TagPath {
+            items: Vec::new(),
+            tag,
+        }
+    }
+
+    /// Create a path to a data element
+    /// through the given sequence tags and item indices.
+    pub fn from_parts<I>(items: I, tag: Tag) -> Self
+    where
+        I: IntoIterator<Item = (Tag, u32)>,
+    {
+        TagPath {
+            items: items.into_iter().collect(),
+            tag,
+        }
+    }
+
+    /// Parse a path from text,
+    /// resolving attribute keywords with the given data dictionary.
+    pub fn parse_with_dict<D>(text: &str, dict: &D) -> Result<Self, ParseTagPathError>
+    where
+        D: DataDictionary,
+    {
+        ensure!(!text.trim().is_empty(), EmptySnafu);
+
+        let mut steps = text.split('.').map(str::trim).peekable();
+        let mut items = Vec::new();
+        while let Some(step) = steps.next() {
+            let (name, index) = match step.find('[') {
+                Some(pos) => {
+                    ensure!(step.ends_with(']'), InvalidItemIndexSnafu { step });
+                    let index = step[pos + 1..step.len() - 1]
+                        .trim()
+                        .parse::<u32>()
+                        .ok()
+                        .context(InvalidItemIndexSnafu { step })?;
+                    (step[..pos].trim(), Some(index))
+                }
+                None => (step, None),
+            };
+            let tag = resolve_tag(name, dict)?;
+
+            if steps.peek().is_none() {
+                ensure!(index.is_none(), UnexpectedItemIndexSnafu { step });
+                return Ok(TagPath { items, tag });
+            }
+            let index = index.context(MissingItemIndexSnafu { step })?;
+            items.push((tag, index));
+        }
+        EmptySnafu.fail()
+    }
+
+    /// The sequence tags and item indices to traverse,
+    /// from the root data set.
+    pub fn items(&self) -> &[(Tag, u32)] {
+        &self.items
+    }
+
+    /// The tag of the data element at the end of the path.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+}
+
+fn resolve_tag<D>(name: &str, dict: &D) -> Result<Tag, ParseTagPathError>
+where
+    D: DataDictionary,
+{
+    if let Ok(tag) = name.parse() {
+        return Ok(tag);
+    }
+    dict.by_name(name)
+        .map(|entry| entry.tag())
+        .context(UnknownAttributeSnafu { name })
+}
+
+/// A value which can be turned into a [`TagPath`],
+/// such as a tag, a path, or the text of a path.
+///
+/// Text is parsed with the data dictionary given,
+/// which is usually the one of the object being accessed.
+pub trait IntoTagPath {
+    /// Turn this value into a tag path.
+    fn into_tag_path<D>(self, dict: &D) -> Result<TagPath, ParseTagPathError>
+    where
+        D: DataDictionary;
+}
+
+impl IntoTagPath for Tag {
+    fn into_tag_path<D>(self, _dict: &D) -> Result<TagPath, ParseTagPathError>
+    where
+        D: DataDictionary,
+    {
+        Ok(TagPath::new(self))
+    }
+}
+
+impl IntoTagPath for TagPath {
+    fn into_tag_path<D>(self, _dict: &D) -> Result<TagPath, ParseTagPathError>
+    where
+        D: DataDictionary,
+    {
+        Ok(self)
+    }
+}
+
+impl IntoTagPath for &TagPath {
+    fn into_tag_path<D>(self, _dict: &D) -> Result<TagPath, ParseTagPathError>
+    where
+        D: DataDictionary,
+    {
+        Ok(self.clone())
+    }
+}
+
+impl IntoTagPath for &str {
+    fn into_tag_path<D>(self, dict: &D) -> Result<TagPath, ParseTagPathError>
+    where
+        D: DataDictionary,
+    {
+        TagPath::parse_with_dict(self, dict)
+    }
+}
+
+impl IntoTagPath for &String {
+    fn into_tag_path<D>(self, dict: &D) -> Result<TagPath, ParseTagPathError>
+    where
+        D: DataDictionary,
+    {
+        TagPath::parse_with_dict(self, dict)
+    }
+}
+
+impl From<Tag> for TagPath {
+    fn from(tag: Tag) -> Self {
+        TagPath::new(tag)
+    }
+}
+
+/// Parses a path using the standard data dictionary.
+impl FromStr for TagPath {
+    type Err = ParseTagPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TagPath::parse_with_dict(s, &StandardDataDictionary)
+    }
+}
+
+/// Displays the path with tags,
+/// in a form which can be parsed back.
+impl fmt::Display for TagPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (tag, index) in &self.items {
+            write!(f, "{}[{}].", tag, index)?;
+        }
+        write!(f, "{}", self.tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParseTagPathError, TagPath};
+    use dicom_core::Tag;
+    use dicom_dictionary_std::tags;
+
+    #[test]
+    fn parse_and_display_paths() {
+        let path: TagPath = "PatientName".parse().unwrap();
+        assert_eq!(path, TagPath::new(tags::PATIENT_NAME));
+        assert_eq!(path.to_string(), "(0010,0010)");
+
+        let path: TagPath = "ReferencedImageSequence[2].(0008,1155)".parse().unwrap();
+        assert_eq!(
+            path,
+            TagPath::from_parts(
+                vec![(tags::REFERENCED_IMAGE_SEQUENCE, 2)],
+                tags::REFERENCED_SOP_INSTANCE_UID
+            )
+        );
+        assert_eq!(path.to_string(), "(0008,1140)[2].(0008,1155)");
+        assert_eq!(path.to_string().parse::<TagPath>().unwrap(), path);
+
+        // private attributes by tag
+        let path: TagPath = "0009,1001".parse().unwrap();
+        assert_eq!(path.tag(), Tag(0x0009, 0x1001));
+    }
+
+    #[test]
+    fn parse_invalid_paths() {
+        assert!(matches!(
+            "".parse::<TagPath>(),
+            Err(ParseTagPathError::Empty { .. })
+        ));
+        assert!(matches!(
+            "NotAnAttribute".parse::<TagPath>(),
+            Err(ParseTagPathError::UnknownAttribute { .. })
+        ));
+        assert!(matches!(
+            "ReferencedImageSequence.ReferencedSOPInstanceUID".parse::<TagPath>(),
+            Err(ParseTagPathError::MissingItemIndex { .. })
+        ));
+        assert!(matches!(
+            "ReferencedImageSequence[x].ReferencedSOPInstanceUID".parse::<TagPath>(),
+            Err(ParseTagPathError::InvalidItemIndex { .. })
+        ));
+        assert!(matches!(
+            "ReferencedImageSequence[0]".parse::<TagPath>(),
+            Err(ParseTagPathError::UnexpectedItemIndex { .. })
+        ));
+    }
+}
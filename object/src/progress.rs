@@ -0,0 +1,234 @@
+//! Progress reporting and cancellation of long-running operations.
+//!
+//! Operations which may take a while,
+//! such as opening a large file,
+//! transcoding its pixel data (in `dicom-pixeldata`),
+//! ingesting a batch of files,
+//! or sending them over the network,
+//! accept an implementation of [`Progress`].
+//! It is called with a [`ProgressStatus`] as the operation advances,
+//! and may request the operation to stop
+//! by returning [`ProgressControl::Cancel`].
+//!
+//! Any closure of the form `FnMut(&ProgressStatus) -> ProgressControl`
+//! implements [`Progress`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dicom_object::OpenFileOptions;
+//! use dicom_object::progress::{ProgressControl, ProgressStatus};
+//!
+//! let obj = OpenFileOptions::new().open_file_with_progress(
+//!     "path/to/file.dcm",
+//!     |status: &ProgressStatus| {
+//!         if let Some(total) = status.bytes_total {
+//!             println!("{}/{} bytes", status.bytes_processed, total);
+//!         }
+//!         ProgressControl::Continue
+//!     },
+//! )?;
+//! # Result::<(), Box<dyn std::error::Error>>::Ok(())
+//! ```
+use std::fmt;
+use std::io::{self, Read};
+
+/// A snapshot of the progress of an operation.
+///
+/// Each operation documents which of the fields it fills in.
+/// Totals are only an estimate, and are `None` if unknown.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct ProgressStatus {
+    /// The number of bytes processed so far
+    pub bytes_processed: u64,
+    /// The estimated total number of bytes to process
+    pub bytes_total: Option<u64>,
+    /// The number of items (such as files) completed so far
+    pub items_completed: u64,
+    /// The estimated total number of items to complete
+    pub items_total: Option<u64>,
+}
+
+impl ProgressStatus {
+    /// Create a progress status for an operation
+    /// with the given estimated totals.
+    pub fn new(bytes_total: Option<u64>, items_total: Option<u64>) -> Self {
+        ProgressStatus {
+            bytes_processed: 0,
+            bytes_total,
+            items_completed: 0,
+            items_total,
+        }
+    }
+}
+
+/// The decision of a progress observer on how to proceed.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum ProgressControl {
+    /// Carry on with the operation
+    Continue,
+    /// Stop the operation as soon as possible
+    Cancel,
+}
+
+impl ProgressControl {
+    /// Check whether the operation should be cancelled.
+    pub fn is_cancel(self) -> bool {
+        self == ProgressControl::Cancel
+    }
+}
+
+/// An observer of the progress of a long-running operation.
+pub trait Progress {
+    /// Report the current progress of the operation,
+    /// returning whether it should carry on.
+    fn update(&mut self, status: &ProgressStatus) -> ProgressControl;
+}
+
+impl<F> Progress for F
+where
+    F: FnMut(&ProgressStatus) -> ProgressControl,
+{
+    fn update(&mut self, status: &ProgressStatus) -> ProgressControl {
+        self(status)
+    }
+}
+
+/// A progress observer which ignores all updates
+/// and never cancels the operation.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn update(&mut self, _status: &ProgressStatus) -> ProgressControl {
+        ProgressControl::Continue
+    }
+}
+
+/// Error type for an operation which was cancelled
+/// by its progress observer.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A byte source which reports the number of bytes read
+/// to a progress observer.
+///
+/// Once the observer cancels the operation,
+/// all further reads fail with an I/O error
+/// wrapping [`Cancelled`].
+#[derive(Debug)]
+pub struct ProgressReader<R, P> {
+    inner: R,
+    progress: P,
+    status: ProgressStatus,
+    cancelled: bool,
+}
+
+impl<R, P> ProgressReader<R, P>
+where
+    P: Progress,
+{
+    /// Wrap the given source,
+    /// which is expected to provide about `bytes_total` bytes.
+    ///
+    /// The reader counts as a single item,
+    /// which is completed by calling [`finish`](ProgressReader::finish).
+    pub fn new(inner: R, bytes_total: Option<u64>, progress: P) -> Self {
+        ProgressReader {
+            inner,
+            progress,
+            status: ProgressStatus::new(bytes_total, Some(1)),
+            cancelled: false,
+        }
+    }
+
+    /// Check whether the progress observer cancelled the operation.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// The number of bytes read so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.status.bytes_processed
+    }
+
+    /// Report the item as completed,
+    /// returning the underlying source.
+    pub fn finish(mut self) -> R {
+        self.status.items_completed = 1;
+        self.progress.update(&self.status);
+        self.inner
+    }
+}
+
+impl<R, P> Read for ProgressReader<R, P>
+where
+    R: Read,
+    P: Progress,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cancelled {
+            return Err(io::Error::other(Cancelled));
+        }
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.status.bytes_processed += n as u64;
+            if self.progress.update(&self.status).is_cancel() {
+                self.cancelled = true;
+                return Err(io::Error::other(Cancelled));
+            }
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_reader_reports_bytes() {
+        let data = [0x55_u8; 100];
+        let mut updates = Vec::new();
+        let mut reader = ProgressReader::new(&data[..], Some(100), |status: &ProgressStatus| {
+            updates.push(*status);
+            ProgressControl::Continue
+        });
+        let mut buf = [0; 40];
+        while reader.read(&mut buf).unwrap() > 0 {}
+        assert_eq!(reader.bytes_read(), 100);
+        assert!(!reader.is_cancelled());
+        reader.finish();
+
+        let bytes: Vec<_> = updates.iter().map(|s| s.bytes_processed).collect();
+        assert_eq!(bytes, vec![40, 80, 100, 100]);
+        assert!(updates.iter().all(|s| s.bytes_total == Some(100)));
+        assert_eq!(updates.last().unwrap().items_completed, 1);
+    }
+
+    #[test]
+    fn progress_reader_cancels() {
+        let data = [0x55_u8; 100];
+        let mut reader = ProgressReader::new(&data[..], None, |status: &ProgressStatus| {
+            if status.bytes_processed >= 50 {
+                ProgressControl::Cancel
+            } else {
+                ProgressControl::Continue
+            }
+        });
+        let mut buf = Vec::new();
+        let err = reader.read_to_end(&mut buf).unwrap_err();
+        assert!(err.get_ref().unwrap().is::<Cancelled>());
+        assert!(reader.is_cancelled());
+        assert!(reader.read(&mut [0; 8]).is_err());
+    }
+}
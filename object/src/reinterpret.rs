@@ -0,0 +1,326 @@
+//! Re-interpretation of element values of unknown value representation.
+//!
+//! Elements may be read with the VR UN (unknown)
+//! when their VR could not be determined,
+//! such as private attributes in an implicit VR data set,
+//! or when a writer did not know the VR of an attribute.
+//! The value of such an element is kept as raw bytes.
+//! As permitted by the standard (PS3.5 Section 6.2.2),
+//! these bytes can be parsed again once the VR is known,
+//! for instance from a data dictionary.
+//! The value of a UN element is always encoded in little endian,
+//! and sequences within are encoded in Implicit VR Little Endian.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+//! # use dicom_object::mem::InMemElement;
+//! use dicom_object::reinterpret::Reinterpret;
+//!
+//! let elem: InMemElement = DataElement::new(
+//!     Tag(0x0009, 0x1001),
+//!     VR::UN,
+//!     PrimitiveValue::from(vec![0x01_u8, 0x00, 0x02, 0x00]),
+//! );
+//! let elem = elem.reinterpret_as(VR::US)?;
+//! assert_eq!(elem.vr(), VR::US);
+//! assert_eq!(elem.to_multi_int::<u16>()?, vec![1, 2]);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::{InMemDicomObject, InMemElement};
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::header::{DataElementHeader, HasLength, Header};
+use dicom_core::value::{CastValueError, Value};
+use dicom_core::{DataElement, Length, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_encoding::decode::basic::LittleEndianBasicDecoder;
+use dicom_encoding::decode::explicit_le::ExplicitVRLittleEndianDecoder;
+use dicom_encoding::text::SpecificCharacterSet;
+use dicom_parser::stateful::decode::{Error as DecodeError, StatefulDecoder};
+use dicom_parser::StatefulDecode;
+use dicom_transfer_syntax_registry::entries::IMPLICIT_VR_LITTLE_ENDIAN;
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ReinterpretError {
+    /// The element does not have an unknown VR
+    #[snafu(display("Element {} has VR {} instead of UN", tag, vr))]
+    NotUnknown {
+        tag: Tag,
+        vr: VR,
+        backtrace: Backtrace,
+    },
+    /// The value of the element is not a sequence of bytes
+    #[snafu(display("Value of element {} is not raw data", tag))]
+    NotRawData {
+        tag: Tag,
+        source: CastValueError,
+        backtrace: Backtrace,
+    },
+    /// Could not decode the value with the new VR
+    #[snafu(display("Could not decode element {} as {}", tag, vr))]
+    DecodeValue {
+        tag: Tag,
+        vr: VR,
+        #[snafu(backtrace)]
+        source: DecodeError,
+    },
+    /// Could not read the value as a sequence
+    #[snafu(display("Could not read element {} as a sequence", tag))]
+    ReadSequence {
+        tag: Tag,
+        #[snafu(backtrace)]
+        source: crate::Error,
+    },
+}
+
+pub type Result<T, E = ReinterpretError> = std::result::Result<T, E>;
+
+/// Re-interpretation of elements of unknown VR (UN)
+/// with a known value representation.
+pub trait Reinterpret: Sized {
+    /// Parse the raw value of this element again as a value of the given VR,
+    /// decoding text with the default character set.
+    ///
+    /// Fails if the element does not have the VR UN.
+    fn reinterpret_as(&self, vr: VR) -> Result<Self> {
+        self.reinterpret_as_with_cs(vr, SpecificCharacterSet::Default)
+    }
+
+    /// Parse the raw value of this element again as a value of the given VR,
+    /// decoding text with the given character set.
+    ///
+    /// Fails if the element does not have the VR UN.
+    fn reinterpret_as_with_cs(&self, vr: VR, cs: SpecificCharacterSet) -> Result<Self>;
+}
+
+impl<D> Reinterpret for InMemElement<D>
+where
+    D: DataDictionary + Clone + Default,
+{
+    fn reinterpret_as_with_cs(&self, vr: VR, cs: SpecificCharacterSet) -> Result<Self> {
+        let tag = self.tag();
+        ensure!(self.vr() == VR::UN, NotUnknownSnafu { tag, vr: self.vr() });
+        let data = self.to_bytes().context(NotRawDataSnafu { tag })?;
+
+        match vr {
+            VR::UN => Ok(self.clone()),
+            VR::SQ => {
+                // read the items in a data set with a single sequence
+                let mut dataset = Vec::with_capacity(data.len() + 16);
+                dataset.extend_from_slice(&tag.group().to_le_bytes());
+                dataset.extend_from_slice(&tag.element().to_le_bytes());
+                dataset.extend_from_slice(&[0xFF; 4]);
+                dataset.extend_from_slice(&data);
+                // sequence delimitation item
+                dataset.extend_from_slice(&[0xFE, 0xFF, 0xDD, 0xE0, 0x00, 0x00, 0x00, 0x00]);
+
+                let mut obj = InMemDicomObject::read_dataset_with_dict_ts_cs(
+                    &dataset[..],
+                    D::default(),
+                    &IMPLICIT_VR_LITTLE_ENDIAN.erased(),
+                    cs,
+                )
+                .context(ReadSequenceSnafu { tag })?;
+                obj.take_element(tag).context(ReadSequenceSnafu { tag })
+            }
+            _ => {
+                let header = DataElementHeader::new(tag, vr, Length(data.len() as u32));
+                let mut decoder = StatefulDecoder::new(
+                    &data[..],
+                    ExplicitVRLittleEndianDecoder::default(),
+                    LittleEndianBasicDecoder,
+                    cs,
+                );
+                let value = decoder
+                    .read_value_preserved(&header)
+                    .context(DecodeValueSnafu { tag, vr })?;
+                Ok(DataElement::new(tag, vr, value))
+            }
+        }
+    }
+}
+
+impl<D> InMemDicomObject<D>
+where
+    D: DataDictionary + Clone + Default,
+{
+    /// Re-interpret all elements of unknown VR (UN) in this object,
+    /// including those in nested sequences,
+    /// with the VR of the respective attribute in the data dictionary.
+    ///
+    /// Elements which are not in the dictionary are left untouched.
+    /// Text is decoded with the object's _Specific Character Set_.
+    pub fn reinterpret_unknown(&mut self) -> Result<()> {
+        self.reinterpret_unknown_with_cs(SpecificCharacterSet::Default)
+    }
+
+    fn reinterpret_unknown_with_cs(&mut self, cs: SpecificCharacterSet) -> Result<()> {
+        // the character set may be overridden in each item
+        let cs = self
+            .element_opt(tags::SPECIFIC_CHARACTER_SET)
+            .ok()
+            .flatten()
+            .and_then(|e| e.to_multi_str().ok())
+            .and_then(|codes| SpecificCharacterSet::from_codes(codes.iter().map(|c| c.trim())))
+            .unwrap_or(cs);
+
+        let unknown_or_sequence: Vec<Tag> = self
+            .iter()
+            .filter(|e| e.vr() == VR::UN || e.vr() == VR::SQ)
+            .map(|e| e.tag())
+            .collect();
+
+        for tag in unknown_or_sequence {
+            let mut elem = match self.take_element(tag) {
+                Ok(elem) => elem,
+                Err(_) => continue,
+            };
+            if elem.vr() == VR::UN {
+                let vr = self.dict.by_tag(tag).map(|entry| entry.vr());
+                if let Some(vr) = vr.filter(|vr| *vr != VR::UN) {
+                    match elem.reinterpret_as_with_cs(vr, cs) {
+                        Ok(e) => elem = e,
+                        Err(e) => {
+                            // keep the element before failing
+                            self.put(elem);
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+            if elem.vr() == VR::SQ {
+                let len = elem.length();
+                match elem.into_value() {
+                    Value::Sequence { mut items, size } => {
+                        let mut result = Ok(());
+                        for item in items.iter_mut() {
+                            result = result.and_then(|_| item.reinterpret_unknown_with_cs(cs));
+                        }
+                        self.put(DataElement::new_with_len(
+                            tag,
+                            VR::SQ,
+                            len,
+                            Value::Sequence { items, size },
+                        ));
+                        result?;
+                    }
+                    value => {
+                        self.put(DataElement::new_with_len(tag, VR::SQ, len, value));
+                    }
+                }
+            } else {
+                self.put(elem);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Reinterpret;
+    use crate::mem::{InMemDicomObject, InMemElement};
+    use dicom_core::value::Value;
+    use dicom_core::{DataElement, Length, PrimitiveValue, Tag, VR};
+    use dicom_dictionary_std::tags;
+
+    #[test]
+    fn reinterpret_primitive_values() {
+        let elem: InMemElement = DataElement::new(
+            tags::PATIENT_NAME,
+            VR::UN,
+            PrimitiveValue::from(b"Doe^John".to_vec()),
+        );
+        let elem = elem.reinterpret_as(VR::PN).unwrap();
+        assert_eq!(elem.vr(), VR::PN);
+        assert_eq!(elem.to_str().unwrap(), "Doe^John");
+
+        let elem: InMemElement = DataElement::new(
+            tags::SLICE_THICKNESS,
+            VR::UN,
+            PrimitiveValue::from(1.5_f64.to_le_bytes().to_vec()),
+        );
+        let elem = elem.reinterpret_as(VR::FD).unwrap();
+        assert_eq!(elem.to_float64().unwrap(), 1.5);
+
+        // only elements of unknown VR can be reinterpreted
+        assert!(elem.reinterpret_as(VR::OB).is_err());
+    }
+
+    #[test]
+    fn reinterpret_sequence() {
+        #[rustfmt::skip]
+        let data = vec![
+            0xfe, 0xff, 0x00, 0xe0, // item start tag
+            0x0e, 0x00, 0x00, 0x00, // item length: 14
+            0x10, 0x00, 0x20, 0x00, // (0010,0020) PatientID
+            0x06, 0x00, 0x00, 0x00, // length: 6
+            b'I', b'D', b'0', b'0', b'0', b'1',
+        ];
+        let elem: InMemElement = DataElement::new(
+            tags::OTHER_PATIENT_I_DS_SEQUENCE,
+            VR::UN,
+            PrimitiveValue::from(data),
+        );
+        let elem = elem.reinterpret_as(VR::SQ).unwrap();
+        assert_eq!(elem.vr(), VR::SQ);
+        let items = elem.items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0]
+                .element(tags::PATIENT_ID)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "ID0001"
+        );
+    }
+
+    #[test]
+    fn reinterpret_unknown_in_object() {
+        let item = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::REFERENCED_FRAME_NUMBER,
+            VR::UN,
+            PrimitiveValue::from(b"2 ".to_vec()),
+        )]);
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::ROWS,
+                VR::UN,
+                PrimitiveValue::from(512_u16.to_le_bytes().to_vec()),
+            ),
+            DataElement::new(
+                Tag(0x0009, 0x1001),
+                VR::UN,
+                PrimitiveValue::from(vec![0x01_u8, 0x02]),
+            ),
+            DataElement::new(
+                tags::REFERENCED_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+        ]);
+
+        obj.reinterpret_unknown().unwrap();
+
+        let rows = obj.element(tags::ROWS).unwrap();
+        assert_eq!(rows.vr(), VR::US);
+        assert_eq!(rows.to_int::<u16>().unwrap(), 512);
+        // private elements are not in the dictionary
+        assert_eq!(obj.element(Tag(0x0009, 0x1001)).unwrap().vr(), VR::UN);
+        let items = obj
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        let frame = items[0].element(tags::REFERENCED_FRAME_NUMBER).unwrap();
+        assert_eq!(frame.vr(), VR::IS);
+        assert_eq!(frame.to_int::<i32>().unwrap(), 2);
+    }
+}
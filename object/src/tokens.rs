@@ -1,8 +1,15 @@
 //! Convertion of DICOM objects into tokens.
 use crate::mem::InMemDicomObject;
-use dicom_core::DataElement;
-use dicom_parser::dataset::{DataToken, IntoTokens};
-use std::collections::VecDeque;
+use crate::original::OriginalValue;
+use dicom_core::header::Header;
+use dicom_core::value::Value;
+use dicom_core::{DataElement, DataElementHeader, Length, PrimitiveValue, Tag, VR};
+use dicom_encoding::text::SpecificCharacterSet;
+use dicom_encoding::TransferSyntax;
+use dicom_parser::dataset::write::Error as WriteError;
+use dicom_parser::dataset::{DataSetWriter, DataToken, IntoTokens};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
 
 /// A stream of tokens from a DICOM object.
 pub struct InMemObjectTokens<E> {
@@ -12,6 +19,8 @@ pub struct InMemObjectTokens<E> {
     elem_iter: E,
     /// whether the tokens are done
     fused: bool,
+    /// the original encoding of the values, by tag
+    originals: BTreeMap<Tag, OriginalValue>,
 }
 
 impl<E> InMemObjectTokens<E>
@@ -19,6 +28,15 @@ where
     E: Iterator,
 {
     pub fn new<T>(obj: T) -> Self
+    where
+        T: IntoIterator<IntoIter = E, Item = E::Item>,
+    {
+        Self::with_originals(obj, BTreeMap::new())
+    }
+
+    /// Create the tokens of an object,
+    /// writing the given original encoding of values which were not modified.
+    pub(crate) fn with_originals<T>(obj: T, originals: BTreeMap<Tag, OriginalValue>) -> Self
     where
         T: IntoIterator<IntoIter = E, Item = E::Item>,
     {
@@ -26,6 +44,7 @@ where
             tokens_pending: Default::default(),
             elem_iter: obj.into_iter(),
             fused: false,
+            originals,
         }
     }
 }
@@ -49,6 +68,25 @@ where
 
         // otherwise, expand next element, recurse
         if let Some(elem) = self.elem_iter.next() {
+            // write the original encoding if the value was not modified
+            if let Some(original) = self.originals.remove(&elem.tag()) {
+                match elem.value() {
+                    Value::Primitive(v) if elem.vr() == original.vr && *v == original.value => {
+                        self.tokens_pending.push_back(DataToken::ElementHeader(
+                            DataElementHeader::new(
+                                elem.tag(),
+                                original.vr,
+                                Length(original.bytes.len() as u32),
+                            ),
+                        ));
+                        self.tokens_pending
+                            .push_back(DataToken::RawValue(original.bytes));
+                        return self.next();
+                    }
+                    _ => {}
+                }
+            }
+
             // TODO eventually optimize this to be less eager
             self.tokens_pending = elem.into_tokens().collect();
 
@@ -69,8 +107,9 @@ where
 impl<D> IntoTokens for InMemDicomObject<D> {
     type Iter = InMemObjectTokens<<InMemDicomObject<D> as IntoIterator>::IntoIter>;
 
-    fn into_tokens(self) -> Self::Iter {
-        InMemObjectTokens::new(self)
+    fn into_tokens(mut self) -> Self::Iter {
+        let originals = std::mem::take(&mut self.originals);
+        InMemObjectTokens::with_originals(self, originals)
     }
 }
 
@@ -82,6 +121,761 @@ where
         InMemObjectTokens<std::iter::Cloned<<&'a InMemDicomObject<D> as IntoIterator>::IntoIter>>;
 
     fn into_tokens(self) -> Self::Iter {
-        InMemObjectTokens::new(self.into_iter().cloned())
+        InMemObjectTokens::with_originals(self.into_iter().cloned(), self.originals.clone())
+    }
+}
+
+/// Strategy for writing sequences and items
+/// which were recorded with an explicit length.
+///
+/// An explicit length is only correct for as long as
+/// the contents of the sequence or item remain untouched,
+/// so that editing a nested data set may turn it stale.
+///
+/// See also [`WriteOptions`](crate::WriteOptions).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExplicitLengthSqItemStrategy {
+    /// _Default behavior:_
+    /// recalculate the length of each explicit length sequence and item
+    /// from its contents in the encoding used for writing.
+    #[default]
+    Recalculate,
+    /// Write all sequences and items with an undefined length,
+    /// followed by the respective delimiter.
+    ///
+    /// The items of encapsulated pixel data are not affected.
+    SetUndefined,
+    /// Write all sequences and items with an explicit length,
+    /// calculated from their contents in the encoding used for writing,
+    /// and without delimiters.
+    ///
+    /// Encapsulated pixel data is always written with an undefined length,
+    /// and its items keep their length.
+    SetExplicit,
+}
+
+/// A token stream adapter which ensures that
+/// sequences and items with an explicit length
+/// are written with a correct length,
+/// according to an [`ExplicitLengthSqItemStrategy`].
+///
+/// To recalculate lengths,
+/// the tokens of each outermost sequence or item with an explicit length
+/// are gathered into memory and measured in their encoded form,
+/// using the given transfer syntax and character set.
+/// All other tokens are passed through as they arrive.
+pub struct ExplicitLengthTokens<'t, I> {
+    tokens: I,
+    ts: &'t TransferSyntax,
+    charset: SpecificCharacterSet,
+    strategy: ExplicitLengthSqItemStrategy,
+    /// tokens ready to be emitted
+    pending: VecDeque<DataToken>,
+    /// whether each open sequence is an encapsulated pixel data sequence
+    sequences: Vec<bool>,
+    /// the header of the last element,
+    /// kept to follow changes in the character set
+    last_header: Option<DataElementHeader>,
+}
+
+impl<'t, I> ExplicitLengthTokens<'t, I>
+where
+    I: Iterator<Item = DataToken>,
+{
+    /// Create a new adapter over the given tokens,
+    /// which are to be encoded with the given transfer syntax
+    /// and initial character set.
+    pub fn new<T>(
+        tokens: T,
+        ts: &'t TransferSyntax,
+        charset: SpecificCharacterSet,
+        strategy: ExplicitLengthSqItemStrategy,
+    ) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        ExplicitLengthTokens {
+            tokens: tokens.into_iter(),
+            ts,
+            charset,
+            strategy,
+            pending: VecDeque::new(),
+            sequences: Vec::new(),
+            last_header: None,
+        }
+    }
+
+    fn in_pixel_sequence(&self) -> bool {
+        self.sequences.last().copied().unwrap_or(false)
+    }
+
+    /// Collect the remaining tokens of a sequence or item
+    /// which started with the given token.
+    fn collect_container(&mut self, start: DataToken) -> Vec<DataToken> {
+        let mut out = vec![start];
+        let mut depth = 1;
+        for token in self.tokens.by_ref() {
+            match token {
+                DataToken::SequenceStart { .. }
+                | DataToken::PixelSequenceStart
+                | DataToken::FragmentSequenceStart { .. }
+                | DataToken::ItemStart { .. } => depth += 1,
+                DataToken::SequenceEnd | DataToken::ItemEnd => depth -= 1,
+                _ => {}
+            }
+            out.push(token);
+            if depth == 0 {
+                break;
+            }
+        }
+        out
+    }
+
+    /// Recalculate the explicit lengths of the sequence or item
+    /// spanning the given tokens,
+    /// as well as of all sequences and items within.
+    fn recalculate(
+        &self,
+        tokens: &mut [DataToken],
+        in_pixel_sequence: bool,
+    ) -> Result<(), WriteError> {
+        let is_pixel_sequence = matches!(
+            tokens[0],
+            DataToken::PixelSequenceStart | DataToken::FragmentSequenceStart { .. }
+        );
+        let last = tokens.len() - 1;
+
+        // recalculate nested sequences and items first
+        let mut i = 1;
+        while i < last {
+            match tokens[i] {
+                DataToken::SequenceStart { .. }
+                | DataToken::PixelSequenceStart
+                | DataToken::FragmentSequenceStart { .. }
+                | DataToken::ItemStart { .. } => {
+                    let end = i + container_len(&tokens[i..]);
+                    self.recalculate(&mut tokens[i..end], is_pixel_sequence)?;
+                    i = end;
+                }
+                _ => i += 1,
+            }
+        }
+
+        let set_explicit = self.strategy == ExplicitLengthSqItemStrategy::SetExplicit;
+        match &tokens[0] {
+            DataToken::SequenceStart { len, .. } if len.is_defined() || set_explicit => {}
+            DataToken::ItemStart { len }
+                if (len.is_defined() || set_explicit) && !in_pixel_sequence => {}
+            _ => return Ok(()),
+        }
+
+        let len = Length(self.measure(&tokens[1..last])?);
+        match &mut tokens[0] {
+            DataToken::SequenceStart { len: l, .. } | DataToken::ItemStart { len: l } => *l = len,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Calculate the number of bytes of the given tokens in encoded form.
+    fn measure(&self, tokens: &[DataToken]) -> Result<u32, WriteError> {
+        encoded_len(tokens, self.ts, self.charset)
+    }
+
+    /// Keep track of the character set in use,
+    /// as the data set writer would.
+    fn track_character_set(&mut self, token: &DataToken) {
+        track_character_set(&mut self.last_header, &mut self.charset, token);
+    }
+}
+
+impl<'t, I> Iterator for ExplicitLengthTokens<'t, I>
+where
+    I: Iterator<Item = DataToken>,
+{
+    type Item = Result<DataToken, WriteError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(Ok(token));
+        }
+
+        let token = self.tokens.next()?;
+        self.track_character_set(&token);
+
+        let token = match (self.strategy, token) {
+            (ExplicitLengthSqItemStrategy::SetUndefined, DataToken::SequenceStart { tag, .. }) => {
+                DataToken::SequenceStart {
+                    tag,
+                    len: Length::UNDEFINED,
+                }
+            }
+            (ExplicitLengthSqItemStrategy::SetUndefined, DataToken::ItemStart { .. })
+                if !self.in_pixel_sequence() =>
+            {
+                DataToken::ItemStart {
+                    len: Length::UNDEFINED,
+                }
+            }
+            (
+                ExplicitLengthSqItemStrategy::Recalculate,
+                token @ DataToken::SequenceStart { len, .. },
+            ) if len.is_defined() => {
+                return Some(self.recalculate_container(token));
+            }
+            (ExplicitLengthSqItemStrategy::Recalculate, token @ DataToken::ItemStart { len })
+                if len.is_defined() && !self.in_pixel_sequence() =>
+            {
+                return Some(self.recalculate_container(token));
+            }
+            (
+                ExplicitLengthSqItemStrategy::SetExplicit,
+                token @ DataToken::SequenceStart { .. },
+            ) => {
+                return Some(self.recalculate_container(token));
+            }
+            (ExplicitLengthSqItemStrategy::SetExplicit, token @ DataToken::ItemStart { .. })
+                if !self.in_pixel_sequence() =>
+            {
+                return Some(self.recalculate_container(token));
+            }
+            (_, token) => token,
+        };
+
+        match token {
+            DataToken::SequenceStart { .. } => self.sequences.push(false),
+            DataToken::PixelSequenceStart | DataToken::FragmentSequenceStart { .. } => {
+                self.sequences.push(true)
+            }
+            DataToken::SequenceEnd => {
+                self.sequences.pop();
+            }
+            _ => {}
+        }
+
+        Some(Ok(token))
+    }
+}
+
+impl<'t, I> ExplicitLengthTokens<'t, I>
+where
+    I: Iterator<Item = DataToken>,
+{
+    fn recalculate_container(&mut self, start: DataToken) -> Result<DataToken, WriteError> {
+        let in_pixel_sequence = self.in_pixel_sequence();
+        let mut tokens = self.collect_container(start);
+        self.recalculate(&mut tokens, in_pixel_sequence)?;
+        self.pending.extend(tokens);
+        Ok(self.pending.pop_front().unwrap())
+    }
+}
+
+/// Strategy for writing group length elements (gggg,0000)
+/// in the root data set of an object.
+///
+/// Group length elements are retired outside of the file meta group,
+/// but some applications still expect them.
+///
+/// See also [`WriteOptions`](crate::WriteOptions).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GroupLengthStrategy {
+    /// _Default behavior:_
+    /// write the group length elements in the object as they are.
+    #[default]
+    Keep,
+    /// Write a group length element at the start of each group,
+    /// with the length of the remaining elements of the group
+    /// in the encoding used for writing.
+    /// Existing group length elements are replaced.
+    ///
+    /// When writing a file,
+    /// the file meta group length is recalculated as well.
+    Recalculate,
+    /// Leave out all group length elements.
+    Remove,
+}
+
+/// A token stream adapter which writes group length elements
+/// in the root data set
+/// according to a [`GroupLengthStrategy`].
+///
+/// To recalculate group lengths,
+/// the tokens of each group in the root data set
+/// are gathered into memory and measured in their encoded form,
+/// using the given transfer syntax and character set.
+/// Tokens of nested data sets are passed through as they arrive.
+pub struct GroupLengthTokens<'t, I> {
+    tokens: I,
+    ts: &'t TransferSyntax,
+    charset: SpecificCharacterSet,
+    strategy: GroupLengthStrategy,
+    /// tokens ready to be emitted
+    pending: VecDeque<DataToken>,
+    /// the first token of the next group, if already read
+    lookahead: Option<DataToken>,
+    /// the number of open sequences
+    depth: u32,
+    /// the header of the last element,
+    /// kept to follow changes in the character set
+    last_header: Option<DataElementHeader>,
+}
+
+impl<'t, I> GroupLengthTokens<'t, I>
+where
+    I: Iterator<Item = Result<DataToken, WriteError>>,
+{
+    /// Create a new adapter over the given tokens,
+    /// which are to be encoded with the given transfer syntax
+    /// and initial character set.
+    pub fn new<T>(
+        tokens: T,
+        ts: &'t TransferSyntax,
+        charset: SpecificCharacterSet,
+        strategy: GroupLengthStrategy,
+    ) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        GroupLengthTokens {
+            tokens: tokens.into_iter(),
+            ts,
+            charset,
+            strategy,
+            pending: VecDeque::new(),
+            lookahead: None,
+            depth: 0,
+            last_header: None,
+        }
+    }
+
+    /// Retrieve the next token and whether it is in the root data set,
+    /// leaving out group length elements of the root data set
+    /// unless they are kept.
+    fn next_token(&mut self) -> Option<Result<(bool, DataToken), WriteError>> {
+        if let Some(token) = self.lookahead.take() {
+            return Some(Ok((true, token)));
+        }
+        loop {
+            let token = match self.tokens.next()? {
+                Ok(token) => token,
+                Err(e) => return Some(Err(e)),
+            };
+            let at_root = self.depth == 0;
+            match &token {
+                DataToken::ElementHeader(header)
+                    if at_root
+                        && header.tag.element() == 0
+                        && self.strategy != GroupLengthStrategy::Keep =>
+                {
+                    // also leave out its value
+                    if let Some(Err(e)) = self.tokens.next() {
+                        return Some(Err(e));
+                    }
+                    continue;
+                }
+                DataToken::SequenceStart { .. }
+                | DataToken::PixelSequenceStart
+                | DataToken::FragmentSequenceStart { .. } => self.depth += 1,
+                DataToken::SequenceEnd => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+            return Some(Ok((at_root, token)));
+        }
+    }
+
+    /// Collect the remaining tokens of the group
+    /// which started with the given token,
+    /// and queue them after the group length element.
+    fn recalculate_group(&mut self, start: DataToken, group: u16) -> Result<(), WriteError> {
+        // measure with the character set in use at the start of the group
+        let charset = self.charset;
+        self.track_character_set(&start);
+        let mut tokens = vec![start];
+        while let Some(next) = self.next_token() {
+            let (at_root, token) = next?;
+            if at_root && matches!(element_tag(&token), Some(tag) if tag.group() != group) {
+                self.lookahead = Some(token);
+                break;
+            }
+            self.track_character_set(&token);
+            tokens.push(token);
+        }
+
+        let len = encoded_len(&tokens, self.ts, charset)?;
+        self.pending
+            .push_back(DataToken::ElementHeader(DataElementHeader::new(
+                Tag(group, 0x0000),
+                VR::UL,
+                Length(4),
+            )));
+        self.pending
+            .push_back(DataToken::PrimitiveValue(PrimitiveValue::from(len)));
+        self.pending.extend(tokens);
+        Ok(())
+    }
+
+    /// Keep track of the character set in use,
+    /// as the data set writer would.
+    fn track_character_set(&mut self, token: &DataToken) {
+        track_character_set(&mut self.last_header, &mut self.charset, token);
+    }
+}
+
+impl<'t, I> Iterator for GroupLengthTokens<'t, I>
+where
+    I: Iterator<Item = Result<DataToken, WriteError>>,
+{
+    type Item = Result<DataToken, WriteError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(Ok(token));
+        }
+
+        let (at_root, token) = match self.next_token()? {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+        match element_tag(&token) {
+            Some(tag) if at_root && self.strategy == GroupLengthStrategy::Recalculate => {
+                if let Err(e) = self.recalculate_group(token, tag.group()) {
+                    return Some(Err(e));
+                }
+                self.pending.pop_front().map(Ok)
+            }
+            _ => {
+                self.track_character_set(&token);
+                Some(Ok(token))
+            }
+        }
+    }
+}
+
+/// Obtain the tag of the element which starts with the given token, if any.
+fn element_tag(token: &DataToken) -> Option<Tag> {
+    match token {
+        DataToken::ElementHeader(header) => Some(header.tag),
+        DataToken::SequenceStart { tag, .. } | DataToken::FragmentSequenceStart { tag, .. } => {
+            Some(*tag)
+        }
+        DataToken::PixelSequenceStart => Some(Tag(0x7FE0, 0x0010)),
+        _ => None,
+    }
+}
+
+/// Calculate the number of bytes of the given tokens in encoded form.
+fn encoded_len(
+    tokens: &[DataToken],
+    ts: &TransferSyntax,
+    charset: SpecificCharacterSet,
+) -> Result<u32, WriteError> {
+    let mut counter = ByteCounter(0);
+    DataSetWriter::with_ts_cs(&mut counter, ts, charset)?.write_sequence(tokens.iter().cloned())?;
+    Ok(counter.0 as u32)
+}
+
+/// Keep track of the character set in use after the given token,
+/// as the data set writer would.
+fn track_character_set(
+    last_header: &mut Option<DataElementHeader>,
+    charset: &mut SpecificCharacterSet,
+    token: &DataToken,
+) {
+    match token {
+        DataToken::ElementHeader(header) => *last_header = Some(*header),
+        DataToken::PrimitiveValue(value) => {
+            if let Some(header) = last_header.take() {
+                if header.tag == Tag(0x0008, 0x0005) {
+                    let codes = value.to_str();
+                    if let Some(cs) = SpecificCharacterSet::from_codes(codes.split('\\')) {
+                        *charset = cs;
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Determine the number of tokens of the sequence or item
+/// which starts at the beginning of the given tokens.
+fn container_len(tokens: &[DataToken]) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            DataToken::SequenceStart { .. }
+            | DataToken::PixelSequenceStart
+            | DataToken::FragmentSequenceStart { .. }
+            | DataToken::ItemStart { .. } => depth += 1,
+            DataToken::SequenceEnd | DataToken::ItemEnd => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// A writer which only counts the bytes written to it.
+pub(crate) struct ByteCounter(pub(crate) u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{PrimitiveValue, VR};
+    use dicom_transfer_syntax_registry::entries::EXPLICIT_VR_LITTLE_ENDIAN;
+
+    /// tokens of a sequence with one item,
+    /// with explicit lengths which do not match the contents
+    fn stale_sequence_tokens() -> Vec<DataToken> {
+        vec![
+            DataToken::SequenceStart {
+                tag: Tag(0x0018, 0x6011),
+                len: Length(18),
+            },
+            DataToken::ItemStart { len: Length(10) },
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0018, 0x6012),
+                VR::US,
+                Length(2),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from(1_u16)),
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0020, 0x4000),
+                VR::LT,
+                Length(8),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from("comment")),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+        ]
+    }
+
+    #[test]
+    fn recalculate_explicit_lengths() {
+        let ts = EXPLICIT_VR_LITTLE_ENDIAN.erased();
+        let tokens: Vec<_> = ExplicitLengthTokens::new(
+            stale_sequence_tokens(),
+            &ts,
+            SpecificCharacterSet::Default,
+            ExplicitLengthSqItemStrategy::Recalculate,
+        )
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+        // item: 10 bytes for US, 8 + 8 bytes for LT
+        assert_eq!(
+            &tokens[..2],
+            &[
+                DataToken::SequenceStart {
+                    tag: Tag(0x0018, 0x6011),
+                    len: Length(34),
+                },
+                DataToken::ItemStart { len: Length(26) },
+            ]
+        );
+        assert_eq!(&tokens[2..], &stale_sequence_tokens()[2..]);
+    }
+
+    #[test]
+    fn set_explicit_lengths_undefined() {
+        let ts = EXPLICIT_VR_LITTLE_ENDIAN.erased();
+        let mut input = stale_sequence_tokens();
+        // encapsulated pixel data items keep their length
+        input.extend([
+            DataToken::PixelSequenceStart,
+            DataToken::ItemStart { len: Length(0) },
+            DataToken::ItemEnd,
+            DataToken::ItemStart { len: Length(4) },
+            DataToken::ItemValue(vec![1, 2, 3, 4]),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+        ]);
+
+        let tokens: Vec<_> = ExplicitLengthTokens::new(
+            input,
+            &ts,
+            SpecificCharacterSet::Default,
+            ExplicitLengthSqItemStrategy::SetUndefined,
+        )
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+        assert_eq!(
+            tokens[0],
+            DataToken::SequenceStart {
+                tag: Tag(0x0018, 0x6011),
+                len: Length::UNDEFINED,
+            }
+        );
+        assert_eq!(
+            tokens[1],
+            DataToken::ItemStart {
+                len: Length::UNDEFINED
+            }
+        );
+        assert_eq!(tokens[9], DataToken::ItemStart { len: Length(0) });
+        assert_eq!(tokens[11], DataToken::ItemStart { len: Length(4) });
+    }
+
+    #[test]
+    fn set_explicit_lengths() {
+        let ts = EXPLICIT_VR_LITTLE_ENDIAN.erased();
+        let mut input: Vec<_> = stale_sequence_tokens()
+            .into_iter()
+            .map(|token| match token {
+                DataToken::SequenceStart { tag, .. } => DataToken::SequenceStart {
+                    tag,
+                    len: Length::UNDEFINED,
+                },
+                DataToken::ItemStart { .. } => DataToken::ItemStart {
+                    len: Length::UNDEFINED,
+                },
+                token => token,
+            })
+            .collect();
+        let pixel_sequence = vec![
+            DataToken::PixelSequenceStart,
+            DataToken::ItemStart { len: Length(0) },
+            DataToken::ItemEnd,
+            DataToken::ItemStart { len: Length(4) },
+            DataToken::ItemValue(vec![1, 2, 3, 4]),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+        ];
+        input.extend(pixel_sequence.clone());
+
+        let tokens: Vec<_> = ExplicitLengthTokens::new(
+            input,
+            &ts,
+            SpecificCharacterSet::Default,
+            ExplicitLengthSqItemStrategy::SetExplicit,
+        )
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+        assert_eq!(
+            &tokens[..2],
+            &[
+                DataToken::SequenceStart {
+                    tag: Tag(0x0018, 0x6011),
+                    len: Length(34),
+                },
+                DataToken::ItemStart { len: Length(26) },
+            ]
+        );
+        assert_eq!(&tokens[2..8], &stale_sequence_tokens()[2..]);
+        assert_eq!(&tokens[8..], &pixel_sequence[..]);
+
+        // no delimiters are written for the sequence
+        let len = encoded_len(&tokens[..8], &ts, SpecificCharacterSet::Default).unwrap();
+        assert_eq!(len, 12 + 34);
+    }
+
+    #[test]
+    fn recalculate_and_remove_group_lengths() {
+        let ts = EXPLICIT_VR_LITTLE_ENDIAN.erased();
+        let group_length = |group, len| {
+            vec![
+                DataToken::ElementHeader(DataElementHeader::new(
+                    Tag(group, 0x0000),
+                    VR::UL,
+                    Length(4),
+                )),
+                DataToken::PrimitiveValue(PrimitiveValue::from(len)),
+            ]
+        };
+        let modality = [
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0008, 0x0060),
+                VR::CS,
+                Length(2),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from("MR")),
+        ];
+        let patient_name = [
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0010, 0x0010),
+                VR::PN,
+                Length(8),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from("Doe^John")),
+        ];
+        // group lengths in nested data sets are left alone
+        let mut sequence = vec![
+            DataToken::SequenceStart {
+                tag: Tag(0x0018, 0x6011),
+                len: Length::UNDEFINED,
+            },
+            DataToken::ItemStart {
+                len: Length::UNDEFINED,
+            },
+        ];
+        sequence.extend(group_length(0x0018, 99_u32));
+        sequence.extend([
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0018, 0x6012),
+                VR::US,
+                Length(2),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from(1_u16)),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+        ]);
+
+        // stale group length for group 0008, none for the others
+        let input: Vec<_> = group_length(0x0008, 1234_u32)
+            .into_iter()
+            .chain(modality.clone())
+            .chain(patient_name.clone())
+            .chain(sequence.clone())
+            .collect();
+
+        let run = |strategy| -> Vec<DataToken> {
+            GroupLengthTokens::new(
+                input.iter().cloned().map(Ok),
+                &ts,
+                SpecificCharacterSet::Default,
+                strategy,
+            )
+            .collect::<Result<_, _>>()
+            .unwrap()
+        };
+
+        assert_eq!(run(GroupLengthStrategy::Keep), input);
+
+        let expected: Vec<_> = modality
+            .iter()
+            .chain(&patient_name)
+            .chain(&sequence)
+            .cloned()
+            .collect();
+        assert_eq!(run(GroupLengthStrategy::Remove), expected);
+
+        let expected: Vec<_> = group_length(0x0008, 10_u32)
+            .into_iter()
+            .chain(modality)
+            .chain(group_length(0x0010, 16_u32))
+            .chain(patient_name)
+            .chain(group_length(0x0018, 58_u32))
+            .chain(sequence)
+            .collect();
+        assert_eq!(run(GroupLengthStrategy::Recalculate), expected);
     }
 }
@@ -0,0 +1,854 @@
+//! Bulk re-rooting of unique identifiers.
+//!
+//! Archives which mandate their own UID root
+//! need to replace the UIDs of external studies on import.
+//! [`reroot_uids`] replaces the instance UIDs in a batch of objects
+//! according to a [`RerootStrategy`],
+//! applying the same mapping to every occurrence of a UID,
+//! so that references between the objects
+//! (such as in _Referenced SOP Instance UID_)
+//! are preserved.
+//! When the objects of a study arrive one at a time,
+//! a [`UidMapper`] keeps the mapping across them.
+//! New UIDs for created instances are obtained with [`generate_uid`].
+//!
+//! Class UIDs (SOP classes, transfer syntaxes, coding schemes, ...)
+//! and UIDs under the DICOM standard root `1.2.840.10008`
+//! are never replaced.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::uid::{reroot_uids, RerootStrategy};
+//!
+//! let mut objects = vec![InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::SOP_CLASS_UID, VR::UI, PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7")),
+//!     DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.3.6.1.4.99.1.2.3")),
+//!     DataElement::new(tags::STUDY_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.3.6.1.4.99.1.2")),
+//! ])];
+//!
+//! let strategy = RerootStrategy::prefix("1.3.6.1.4.99", "2.25.1234")?;
+//! // the closure checks the archive for UIDs which already exist
+//! let mapping = reroot_uids(&mut objects, &strategy, |_uid| false)?;
+//!
+//! assert_eq!(mapping["1.3.6.1.4.99.1.2.3"], "2.25.1234.1.2.3");
+//! assert_eq!(
+//!     objects[0].element(tags::STUDY_INSTANCE_UID)?.to_str()?,
+//!     "2.25.1234.1.2",
+//! );
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::InMemDicomObject;
+use crate::FileDicomObject;
+use dicom_core::dictionary::DataDictionary;
+use dicom_core::header::Header;
+use dicom_core::value::{PrimitiveValue, Value};
+use dicom_core::{DataElement, Tag, VR};
+use dicom_dictionary_std::tags;
+use sha2::{Digest, Sha256};
+use snafu::{ensure, Backtrace, Snafu};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The maximum length of a UID.
+const MAX_UID_LENGTH: usize = 64;
+
+/// The UID root of the DICOM standard.
+const DICOM_ROOT: &str = "1.2.840.10008";
+
+/// Attributes holding UIDs which identify classes rather than instances,
+/// and are therefore never re-rooted.
+const CLASS_UID_TAGS: &[Tag] = &[
+    tags::SOP_CLASS_UID,
+    tags::MEDIA_STORAGE_SOP_CLASS_UID,
+    tags::AFFECTED_SOP_CLASS_UID,
+    tags::REQUESTED_SOP_CLASS_UID,
+    tags::REFERENCED_SOP_CLASS_UID,
+    tags::REFERENCED_SOP_CLASS_UID_IN_FILE,
+    tags::RELATED_GENERAL_SOP_CLASS_UID,
+    tags::ORIGINAL_SPECIALIZED_SOP_CLASS_UID,
+    tags::TRANSFER_SYNTAX_UID,
+    tags::REFERENCED_TRANSFER_SYNTAX_UID_IN_FILE,
+    tags::IMPLEMENTATION_CLASS_UID,
+    tags::PRIVATE_INFORMATION_CREATOR_UID,
+    tags::CODING_SCHEME_UID,
+    tags::CONTEXT_UID,
+    tags::CONTEXT_GROUP_EXTENSION_CREATOR_UID,
+    tags::MAPPING_RESOURCE_UID,
+];
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Invalid UID root `{}`", root))]
+    InvalidRoot { root: String, backtrace: Backtrace },
+    #[snafu(display("Re-rooted UID `{}` is longer than 64 characters", uid))]
+    UidTooLong { uid: String, backtrace: Backtrace },
+    #[snafu(display("UID `{}` would be re-rooted to `{}`, which already exists", old, new))]
+    Collision {
+        old: String,
+        new: String,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The strategy for producing new UIDs from the original ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RerootStrategy {
+    /// Replace the leading `old_root` of UIDs with `new_root`,
+    /// keeping the remaining components.
+    /// UIDs outside of `old_root` are kept as is.
+    Prefix { old_root: String, new_root: String },
+    /// Replace every UID outside of `new_root`
+    /// with `new_root` followed by a component
+    /// derived from a hash of the original UID.
+    ///
+    /// The same original UID always results in the same new UID,
+    /// so separate batches of the same study are re-rooted consistently.
+    Hash { new_root: String },
+}
+
+impl RerootStrategy {
+    /// Create a prefix replacement strategy,
+    /// validating both roots.
+    pub fn prefix(old_root: impl Into<String>, new_root: impl Into<String>) -> Result<Self> {
+        let old_root = old_root.into();
+        let new_root = new_root.into();
+        check_root(&old_root)?;
+        check_root(&new_root)?;
+        Ok(RerootStrategy::Prefix { old_root, new_root })
+    }
+
+    /// Create a hash-based strategy,
+    /// validating the new root.
+    pub fn hash(new_root: impl Into<String>) -> Result<Self> {
+        let new_root = new_root.into();
+        check_root(&new_root)?;
+        // leave room for a reasonably large hash component
+        ensure!(
+            new_root.len() + 1 + 20 <= MAX_UID_LENGTH,
+            InvalidRootSnafu { root: new_root }
+        );
+        Ok(RerootStrategy::Hash { new_root })
+    }
+
+    /// Determine the new UID for the given one,
+    /// or `None` if it should be kept as is.
+    pub fn reroot(&self, uid: &str) -> Result<Option<String>> {
+        if is_under(uid, DICOM_ROOT) {
+            return Ok(None);
+        }
+        let new_uid = match self {
+            RerootStrategy::Prefix { old_root, new_root } => {
+                if !is_under(uid, old_root) {
+                    return Ok(None);
+                }
+                format!("{}{}", new_root, &uid[old_root.len()..])
+            }
+            RerootStrategy::Hash { new_root } => {
+                if is_under(uid, new_root) {
+                    return Ok(None);
+                }
+                let digest = Sha256::digest(uid.as_bytes());
+                let mut bytes = [0; 16];
+                bytes.copy_from_slice(&digest[..16]);
+                let mut component = u128::from_be_bytes(bytes).to_string();
+                component.truncate(MAX_UID_LENGTH - new_root.len() - 1);
+                // components must not have leading zeros
+                let component = match component.trim_start_matches('0') {
+                    "" => "0",
+                    c => c,
+                };
+                format!("{}.{}", new_root, component)
+            }
+        };
+        ensure!(
+            new_uid.len() <= MAX_UID_LENGTH,
+            UidTooLongSnafu { uid: new_uid }
+        );
+        Ok(Some(new_uid))
+    }
+}
+
+/// Generate a new UID under the given root,
+/// as needed for new instances, series and studies.
+///
+/// The last component is derived from a hash
+/// of the current time, the process ID and a counter,
+/// so that every call produces a different UID.
+///
+/// # Example
+///
+/// ```
+/// use dicom_object::uid::generate_uid;
+///
+/// let uid = generate_uid("2.25")?;
+/// assert!(uid.starts_with("2.25."));
+/// assert_ne!(generate_uid("2.25")?, uid);
+/// # Ok::<_, dicom_object::uid::Error>(())
+/// ```
+pub fn generate_uid(root: &str) -> Result<String> {
+    let strategy = RerootStrategy::hash(root)?;
+    // the input is not a UID, so it is never kept as is
+    let input = format!("new:{}", unique_salt());
+    Ok(strategy.reroot(&input)?.unwrap_or_default())
+}
+
+/// Produce a string which differs on every call,
+/// for deriving new UIDs.
+fn unique_salt() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "{}.{}.{}",
+        nanos,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Re-root the instance UIDs of all given objects,
+/// including those in nested sequences.
+///
+/// `exists` is called for every new UID,
+/// and should return `true` if the UID is already in use
+/// (for instance, if it is present in the archive's index).
+/// Collisions with existing UIDs,
+/// with UIDs in the batch which are not re-rooted,
+/// or between two re-rooted UIDs
+/// result in an error.
+///
+/// The objects are only modified if all UIDs can be re-rooted.
+/// On success, the mapping from old to new UIDs is returned.
+pub fn reroot_uids<D, F>(
+    objects: &mut [InMemDicomObject<D>],
+    strategy: &RerootStrategy,
+    exists: F,
+) -> Result<BTreeMap<String, String>>
+where
+    D: DataDictionary + Clone,
+    F: FnMut(&str) -> bool,
+{
+    let mut uids = Vec::new();
+    for obj in objects.iter() {
+        collect_uids(obj, &mut uids);
+    }
+    let mapping = plan(uids, strategy, exists)?;
+
+    for obj in objects.iter_mut() {
+        apply(obj, &mapping);
+    }
+    Ok(mapping)
+}
+
+/// Re-root the instance UIDs of all given file objects,
+/// as in [`reroot_uids`],
+/// also updating the _Media Storage SOP Instance UID_
+/// of each file meta group.
+pub fn reroot_file_uids<D, F>(
+    objects: &mut [FileDicomObject<InMemDicomObject<D>>],
+    strategy: &RerootStrategy,
+    exists: F,
+) -> Result<BTreeMap<String, String>>
+where
+    D: DataDictionary + Clone,
+    F: FnMut(&str) -> bool,
+{
+    let mut uids = Vec::new();
+    for obj in objects.iter() {
+        uids.push(obj.meta().media_storage_sop_instance_uid().to_string());
+        collect_uids(obj, &mut uids);
+    }
+    let mapping = plan(uids, strategy, exists)?;
+
+    for obj in objects.iter_mut() {
+        let meta = obj.meta_mut();
+        if let Some(new_uid) = mapping.get(meta.media_storage_sop_instance_uid()) {
+            meta.media_storage_sop_instance_uid = new_uid.clone();
+            meta.update_information_group_length();
+        }
+        apply(obj, &mapping);
+    }
+    Ok(mapping)
+}
+
+/// A mapping from original to new instance UIDs
+/// which grows as objects are processed.
+///
+/// Unlike [`reroot_uids`], which needs the whole batch at once,
+/// the mapper can be fed the files of a study one at a time,
+/// such as when streaming them from a storage service.
+/// Every occurrence of a UID in the processed objects,
+/// including references in nested sequences,
+/// is replaced with the same new UID,
+/// so the study, series, frame of reference and instance structure
+/// is preserved across files.
+///
+/// # Example
+///
+/// ```
+/// # use dicom_core::{DataElement, PrimitiveValue, VR};
+/// # use dicom_dictionary_std::tags;
+/// # use dicom_object::InMemDicomObject;
+/// use dicom_object::uid::UidMapper;
+///
+/// let instance = |sop: &str| InMemDicomObject::from_element_iter([
+///     DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from(sop)),
+///     DataElement::new(tags::STUDY_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.3.6.1.4.99.1")),
+/// ]);
+/// let mut first = instance("1.3.6.1.4.99.1.1");
+/// let mut second = instance("1.3.6.1.4.99.1.2");
+///
+/// // copies of a study receive UIDs distinct from any other copy
+/// let mut mapper = UidMapper::unique("2.25")?;
+/// mapper.map_object(&mut first)?;
+/// mapper.map_object(&mut second)?;
+///
+/// let study_uid = first.element(tags::STUDY_INSTANCE_UID)?.to_str()?;
+/// assert!(study_uid.starts_with("2.25."));
+/// assert_eq!(second.element(tags::STUDY_INSTANCE_UID)?.to_str()?, study_uid);
+/// assert_eq!(mapper.get("1.3.6.1.4.99.1"), Some(&*study_uid));
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct UidMapper {
+    strategy: RerootStrategy,
+    /// extra input to the hash of each UID,
+    /// so that new UIDs differ from those of other mappers
+    salt: Option<String>,
+    mapping: BTreeMap<String, String>,
+    /// the original UID of each new UID
+    origins: BTreeMap<String, String>,
+}
+
+impl UidMapper {
+    /// Create a mapper producing new UIDs with the given strategy.
+    ///
+    /// With a [hash strategy](RerootStrategy::Hash),
+    /// separate mappers still map the same UID to the same new UID.
+    pub fn new(strategy: RerootStrategy) -> Self {
+        UidMapper {
+            strategy,
+            salt: None,
+            mapping: BTreeMap::new(),
+            origins: BTreeMap::new(),
+        }
+    }
+
+    /// Create a mapper producing new UIDs under `new_root`
+    /// which are unique to this mapper,
+    /// as needed when duplicating a study.
+    pub fn unique(new_root: impl Into<String>) -> Result<Self> {
+        let strategy = RerootStrategy::hash(new_root)?;
+        Ok(UidMapper {
+            salt: Some(unique_salt()),
+            ..UidMapper::new(strategy)
+        })
+    }
+
+    /// Determine the new UID for the given one,
+    /// recording it in the mapping.
+    ///
+    /// UIDs which are not replaced by the strategy are returned as is.
+    /// Two different UIDs resulting in the same new UID
+    /// is an error.
+    pub fn map_uid(&mut self, uid: &str) -> Result<String> {
+        let uid = uid.trim_end_matches(|c: char| c.is_whitespace() || c == '\0');
+        if let Some(new_uid) = self.mapping.get(uid) {
+            return Ok(new_uid.clone());
+        }
+        let new_uid = match self.strategy.reroot(uid)? {
+            Some(new_uid) => match (&self.salt, &self.strategy) {
+                (Some(salt), RerootStrategy::Hash { .. }) => self
+                    .strategy
+                    .reroot(&format!("{}.{}", uid, salt))?
+                    .unwrap_or(new_uid),
+                _ => new_uid,
+            },
+            None => return Ok(uid.to_string()),
+        };
+        if let Some(old) = self.origins.get(&new_uid) {
+            return CollisionSnafu {
+                old: old.as_str(),
+                new: new_uid,
+            }
+            .fail();
+        }
+        self.origins.insert(new_uid.clone(), uid.to_string());
+        self.mapping.insert(uid.to_string(), new_uid.clone());
+        Ok(new_uid)
+    }
+
+    /// Replace the instance UIDs of the given object,
+    /// including those in nested sequences.
+    ///
+    /// The object and the mapping are left unchanged
+    /// if any of its UIDs cannot be mapped.
+    pub fn map_object<D>(&mut self, obj: &mut InMemDicomObject<D>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        let mut uids = Vec::new();
+        collect_uids(obj, &mut uids);
+        self.map_all(&uids)?;
+        apply(obj, &self.mapping);
+        Ok(())
+    }
+
+    /// Replace the instance UIDs of the given file object,
+    /// as in [`map_object`](UidMapper::map_object),
+    /// also updating the _Media Storage SOP Instance UID_
+    /// of the file meta group.
+    pub fn map_file<D>(&mut self, obj: &mut FileDicomObject<InMemDicomObject<D>>) -> Result<()>
+    where
+        D: DataDictionary + Clone,
+    {
+        let mut uids = vec![obj.meta().media_storage_sop_instance_uid().to_string()];
+        collect_uids(obj, &mut uids);
+        self.map_all(&uids)?;
+
+        let meta = obj.meta_mut();
+        if let Some(new_uid) = self.mapping.get(meta.media_storage_sop_instance_uid()) {
+            meta.media_storage_sop_instance_uid = new_uid.clone();
+            meta.update_information_group_length();
+        }
+        apply(obj, &self.mapping);
+        Ok(())
+    }
+
+    /// Retrieve the new UID of the given original UID,
+    /// if it has been mapped.
+    pub fn get(&self, uid: &str) -> Option<&str> {
+        self.mapping.get(uid).map(String::as_str)
+    }
+
+    /// Retrieve the mapping from original to new UIDs so far.
+    pub fn mapping(&self) -> &BTreeMap<String, String> {
+        &self.mapping
+    }
+
+    /// Take the mapping from original to new UIDs.
+    pub fn into_mapping(self) -> BTreeMap<String, String> {
+        self.mapping
+    }
+
+    /// Map all the given UIDs, or none of them.
+    fn map_all(&mut self, uids: &[String]) -> Result<()> {
+        let (mapping, origins) = (self.mapping.clone(), self.origins.clone());
+        for uid in uids {
+            if let Err(e) = self.map_uid(uid) {
+                self.mapping = mapping;
+                self.origins = origins;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build the UID mapping for the given UIDs, checking for collisions.
+fn plan<F>(
+    uids: Vec<String>,
+    strategy: &RerootStrategy,
+    mut exists: F,
+) -> Result<BTreeMap<String, String>>
+where
+    F: FnMut(&str) -> bool,
+{
+    let mut mapping = BTreeMap::new();
+    let mut kept = Vec::new();
+    for uid in uids {
+        if mapping.contains_key(&uid) {
+            continue;
+        }
+        match strategy.reroot(&uid)? {
+            Some(new_uid) => {
+                mapping.insert(uid, new_uid);
+            }
+            None => kept.push(uid),
+        }
+    }
+
+    let mut new_uids: BTreeMap<&str, &str> = BTreeMap::new();
+    for (old, new) in &mapping {
+        let clash = new_uids.insert(new, old).is_some() || kept.contains(new) || exists(new);
+        ensure!(
+            !clash,
+            CollisionSnafu {
+                old: old.as_str(),
+                new: new.as_str(),
+            }
+        );
+    }
+
+    Ok(mapping)
+}
+
+fn collect_uids<D>(obj: &InMemDicomObject<D>, uids: &mut Vec<String>)
+where
+    D: DataDictionary + Clone,
+{
+    for elem in obj {
+        match elem.value() {
+            Value::Primitive(value) if is_instance_uid(elem.tag(), elem.vr()) => {
+                uids.extend(uid_values(value).map(str::to_string));
+            }
+            Value::Sequence { items, .. } => {
+                for item in items {
+                    collect_uids(item, uids);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn apply<D>(obj: &mut InMemDicomObject<D>, mapping: &BTreeMap<String, String>)
+where
+    D: DataDictionary + Clone,
+{
+    let tags: Vec<_> = obj
+        .iter()
+        .filter(|e| {
+            matches!(e.value(), Value::Sequence { .. })
+                || (is_instance_uid(e.tag(), e.vr())
+                    && matches!(e.value(), Value::Primitive(v) if uid_values(v).any(|uid| mapping.contains_key(uid))))
+        })
+        .map(|e| e.tag())
+        .collect();
+
+    for tag in tags {
+        let elem = match obj.take_element(tag) {
+            Ok(elem) => elem,
+            Err(_) => continue,
+        };
+        let vr = elem.vr();
+        let value = match elem.into_value() {
+            Value::Sequence { mut items, size } => {
+                for item in items.iter_mut() {
+                    apply(item, mapping);
+                }
+                Value::Sequence { items, size }
+            }
+            Value::Primitive(value) => {
+                let uids: Vec<String> = uid_values(&value)
+                    .map(|uid| {
+                        mapping
+                            .get(uid)
+                            .map(String::as_str)
+                            .unwrap_or(uid)
+                            .to_string()
+                    })
+                    .collect();
+                Value::Primitive(PrimitiveValue::Strs(uids.into()))
+            }
+            value => value,
+        };
+        obj.put(DataElement::new(tag, vr, value));
+    }
+}
+
+/// Whether the element is a UID which may be re-rooted.
+fn is_instance_uid(tag: Tag, vr: VR) -> bool {
+    vr == VR::UI && !CLASS_UID_TAGS.contains(&tag)
+}
+
+/// Iterate over the UIDs in a value, without padding.
+fn uid_values(value: &PrimitiveValue) -> impl Iterator<Item = &str> {
+    let values: &[String] = match value {
+        PrimitiveValue::Str(s) => std::slice::from_ref(s),
+        PrimitiveValue::Strs(s) => s,
+        _ => &[],
+    };
+    values
+        .iter()
+        .flat_map(|s| s.split('\\'))
+        .map(|s| s.trim_end_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|s| !s.is_empty())
+}
+
+/// Whether the UID is equal to the given root or one of its descendants.
+fn is_under(uid: &str, root: &str) -> bool {
+    uid.strip_prefix(root)
+        .map(|rest| rest.is_empty() || rest.starts_with('.'))
+        .unwrap_or(false)
+}
+
+fn check_root(root: &str) -> Result<()> {
+    let valid = !root.is_empty()
+        && root.len() < MAX_UID_LENGTH
+        && root.split('.').all(|c| {
+            !c.is_empty()
+                && c.bytes().all(|b| b.is_ascii_digit())
+                && (c == "0" || !c.starts_with('0'))
+        });
+    ensure!(valid, InvalidRootSnafu { root });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::FileMetaTableBuilder;
+
+    fn instance(study: &str, series: &str, sop: &str) -> InMemDicomObject {
+        InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.2"),
+            ),
+            DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from(sop)),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(study),
+            ),
+            DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(series),
+            ),
+        ])
+    }
+
+    #[test]
+    fn reroot_prefix_preserves_references() {
+        let mut referenced = instance("1.9.1", "1.9.1.1", "1.9.1.1.1");
+        let mut referencing = instance("1.9.1", "1.9.1.2", "1.9.1.2.1");
+        referencing.put(DataElement::new(
+            tags::REFERENCED_IMAGE_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: vec![InMemDicomObject::from_element_iter([
+                    DataElement::new(
+                        tags::REFERENCED_SOP_CLASS_UID,
+                        VR::UI,
+                        PrimitiveValue::from("1.9.2"),
+                    ),
+                    DataElement::new(
+                        tags::REFERENCED_SOP_INSTANCE_UID,
+                        VR::UI,
+                        PrimitiveValue::from("1.9.1.1.1\0"),
+                    ),
+                ])]
+                .into(),
+                size: dicom_core::Length::UNDEFINED,
+            },
+        ));
+        // already under the new root
+        referenced.put(DataElement::new(
+            tags::FRAME_OF_REFERENCE_UID,
+            VR::UI,
+            PrimitiveValue::from("2.25.7.9"),
+        ));
+
+        let mut objects = vec![referenced, referencing];
+        let strategy = RerootStrategy::prefix("1.9", "2.25.7").unwrap();
+        let mapping = reroot_uids(&mut objects, &strategy, |_| false).unwrap();
+        assert_eq!(mapping.len(), 5);
+
+        let uid =
+            |obj: &InMemDicomObject, tag| obj.element(tag).unwrap().to_str().unwrap().to_string();
+        assert_eq!(uid(&objects[0], tags::SOP_INSTANCE_UID), "2.25.7.1.1.1");
+        assert_eq!(uid(&objects[0], tags::FRAME_OF_REFERENCE_UID), "2.25.7.9");
+        assert_eq!(uid(&objects[1], tags::STUDY_INSTANCE_UID), "2.25.7.1");
+        assert_eq!(
+            uid(&objects[1], tags::SOP_CLASS_UID),
+            "1.2.840.10008.5.1.4.1.1.2"
+        );
+        let item = &objects[1]
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(uid(item, tags::REFERENCED_SOP_INSTANCE_UID), "2.25.7.1.1.1");
+        // class UIDs are kept, even under the old root
+        assert_eq!(uid(item, tags::REFERENCED_SOP_CLASS_UID), "1.9.2");
+    }
+
+    #[test]
+    fn reroot_detects_collisions() {
+        // 1.9.1 becomes 2.25.7.1, which is already in the batch
+        let objects = vec![
+            instance("1.9.1", "1.9.1.1", "1.9.1.1.1"),
+            instance("2.25.7.1", "2.25.7.1.1", "2.25.7.1.1.1"),
+        ];
+        let strategy = RerootStrategy::prefix("1.9", "2.25.7").unwrap();
+        let mut batch = objects.clone();
+        assert!(matches!(
+            reroot_uids(&mut batch, &strategy, |_| false),
+            Err(Error::Collision { .. })
+        ));
+        // objects are left untouched
+        assert_eq!(batch, objects);
+
+        // collision against the index
+        let mut batch = vec![objects[0].clone()];
+        let err = reroot_uids(&mut batch, &strategy, |uid| uid == "2.25.7.1.1").unwrap_err();
+        assert!(matches!(err, Error::Collision { old, .. } if old == "1.9.1.1"));
+
+        assert!(RerootStrategy::prefix("1.09", "2.25").is_err());
+        assert!(RerootStrategy::prefix("1.2.", "2.25").is_err());
+    }
+
+    #[test]
+    fn reroot_hash_is_deterministic() {
+        let strategy = RerootStrategy::hash("1.2.3.4.5").unwrap();
+        let uid = strategy
+            .reroot("1.3.6.1.4.1.9590.100.1.2")
+            .unwrap()
+            .unwrap();
+        assert!(uid.starts_with("1.2.3.4.5."));
+        assert!(uid.len() <= 64);
+        assert_eq!(
+            strategy
+                .reroot("1.3.6.1.4.1.9590.100.1.2")
+                .unwrap()
+                .unwrap(),
+            uid
+        );
+        assert_ne!(
+            strategy
+                .reroot("1.3.6.1.4.1.9590.100.1.3")
+                .unwrap()
+                .unwrap(),
+            uid
+        );
+        assert_eq!(strategy.reroot("1.2.3.4.5.6").unwrap(), None);
+        assert_eq!(strategy.reroot("1.2.840.10008.1.2.1").unwrap(), None);
+
+        let mut objects = vec![instance("1.9.1", "1.9.1.1", "1.9.1.1.1")
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .transfer_syntax("1.2.840.10008.1.2.1")
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.2"),
+            )
+            .unwrap()];
+        let mapping = reroot_file_uids(&mut objects, &strategy, |_| false).unwrap();
+        let new_uid = &mapping["1.9.1.1.1"];
+        assert_eq!(objects[0].meta().media_storage_sop_instance_uid(), new_uid);
+        assert_eq!(
+            objects[0]
+                .element(tags::SOP_INSTANCE_UID)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            new_uid.as_str()
+        );
+    }
+
+    #[test]
+    fn mapper_is_consistent_across_objects() {
+        let mut referenced = instance("1.9.1", "1.9.1.1", "1.9.1.1.1");
+        referenced.put(DataElement::new(
+            tags::FRAME_OF_REFERENCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.9.5"),
+        ));
+        let mut referencing = instance("1.9.1", "1.9.1.2", "1.9.1.2.1")
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .transfer_syntax("1.2.840.10008.1.2.1")
+                    .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.2"),
+            )
+            .unwrap();
+        referencing.put(DataElement::new(
+            tags::REFERENCED_IMAGE_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: vec![InMemDicomObject::from_element_iter([
+                    DataElement::new(
+                        tags::REFERENCED_SOP_INSTANCE_UID,
+                        VR::UI,
+                        PrimitiveValue::from("1.9.1.1.1"),
+                    ),
+                    DataElement::new(
+                        tags::REFERENCED_FRAME_OF_REFERENCE_UID,
+                        VR::UI,
+                        PrimitiveValue::from("1.9.5"),
+                    ),
+                ])]
+                .into(),
+                size: dicom_core::Length::UNDEFINED,
+            },
+        ));
+
+        let mut mapper = UidMapper::unique("2.25").unwrap();
+        mapper.map_object(&mut referenced).unwrap();
+        mapper.map_file(&mut referencing).unwrap();
+        assert_eq!(mapper.mapping().len(), 6);
+
+        let uid =
+            |obj: &InMemDicomObject, tag| obj.element(tag).unwrap().to_str().unwrap().to_string();
+        let study_uid = uid(&referenced, tags::STUDY_INSTANCE_UID);
+        assert!(study_uid.starts_with("2.25."));
+        assert_eq!(uid(&referencing, tags::STUDY_INSTANCE_UID), study_uid);
+        assert_ne!(
+            uid(&referencing, tags::SERIES_INSTANCE_UID),
+            uid(&referenced, tags::SERIES_INSTANCE_UID)
+        );
+        assert_eq!(
+            referencing.meta().media_storage_sop_instance_uid(),
+            uid(&referencing, tags::SOP_INSTANCE_UID)
+        );
+        let item = &referencing
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            uid(item, tags::REFERENCED_SOP_INSTANCE_UID),
+            uid(&referenced, tags::SOP_INSTANCE_UID)
+        );
+        assert_eq!(
+            uid(item, tags::REFERENCED_FRAME_OF_REFERENCE_UID),
+            uid(&referenced, tags::FRAME_OF_REFERENCE_UID)
+        );
+
+        // unique mappers do not reproduce each other's UIDs,
+        // unlike hash-based ones
+        let mut other = UidMapper::unique("2.25").unwrap();
+        assert_ne!(other.map_uid("1.9.1").unwrap(), study_uid);
+        let strategy = RerootStrategy::hash("2.25").unwrap();
+        assert_eq!(
+            UidMapper::new(strategy.clone()).map_uid("1.9.1").unwrap(),
+            UidMapper::new(strategy).map_uid("1.9.1").unwrap()
+        );
+    }
+
+    #[test]
+    fn mapper_detects_collisions() {
+        let mut mapper = UidMapper::new(RerootStrategy::prefix("1.9", "2.25.7").unwrap());
+        assert_eq!(mapper.map_uid("1.9.1").unwrap(), "2.25.7.1");
+        assert_eq!(mapper.map_uid("1.8.1").unwrap(), "1.8.1");
+
+        let mut obj = instance("1.9.1", "1.9.3", "1.9.1.1");
+        mapper.map_object(&mut obj).unwrap();
+        assert_eq!(mapper.get("1.9.3"), Some("2.25.7.3"));
+
+        // pretend that another UID was already mapped to 2.25.7.4
+        mapper
+            .origins
+            .insert("2.25.7.4".to_string(), "1.7.4".to_string());
+        let original = instance("1.9.1", "1.9.5", "1.9.4");
+        let mut obj = original.clone();
+        assert!(matches!(
+            mapper.map_object(&mut obj),
+            Err(Error::Collision { old, .. }) if old == "1.7.4"
+        ));
+        // neither the object nor the mapping are changed
+        assert_eq!(obj, original);
+        assert_eq!(mapper.get("1.9.5"), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! Validation of attribute requirements.
+//!
+//! The attributes of a DICOM module are classified by type
+//! (PS3.5 Section 7.4):
+//!
+//! - Type 1 attributes must be present with a non-empty value;
+//! - Type 2 attributes must be present,
+//!   but may be empty (zero length) if the value is unknown;
+//! - Type 3 attributes are optional.
+//!
+//! Conditional attributes (Types 1C and 2C)
+//! can be checked as Type 1 or Type 2 respectively,
+//! by including them in the list of requirements
+//! only when their condition applies.
+//!
+//! Empty Type 2 attributes can be inserted with
+//! [`InMemDicomObject::put_empty`].
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::validation::{check_requirements, PATIENT_MODULE, Violation};
+//!
+//! let mut obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+//!     DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("ID0001")),
+//!     DataElement::new(tags::PATIENT_SEX, VR::CS, PrimitiveValue::from("M")),
+//! ]);
+//!
+//! // Patient's Birth Date is Type 2
+//! let violations = check_requirements(&obj, PATIENT_MODULE);
+//! assert!(matches!(&violations[..], [Violation::Missing { tag: tags::PATIENT_BIRTH_DATE, .. }]));
+//!
+//! // it may be present but empty
+//! obj.put_empty(tags::PATIENT_BIRTH_DATE, VR::DA);
+//! assert!(check_requirements(&obj, PATIENT_MODULE).is_empty());
+//! ```
+use crate::mem::InMemDicomObject;
+use dicom_core::dictionary::DataDictionary;
+use dicom_core::Tag;
+use dicom_dictionary_std::tags;
+use snafu::{ensure, Backtrace, Snafu};
+use std::fmt;
+
+/// The type of an attribute in a module,
+/// which determines whether it must be present
+/// and whether it may be empty.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum AttributeType {
+    /// Required, with a non-empty value.
+    Type1,
+    /// Required, but may be empty.
+    Type2,
+    /// Optional.
+    Type3,
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeType::Type1 => f.write_str("Type 1"),
+            AttributeType::Type2 => f.write_str("Type 2"),
+            AttributeType::Type3 => f.write_str("Type 3"),
+        }
+    }
+}
+
+/// A requirement of an attribute in a module.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct AttributeRequirement {
+    /// The attribute tag.
+    pub tag: Tag,
+    /// The attribute type.
+    pub attribute_type: AttributeType,
+}
+
+impl AttributeRequirement {
+    /// Create a new attribute requirement.
+    pub const fn new(tag: Tag, attribute_type: AttributeType) -> Self {
+        AttributeRequirement {
+            tag,
+            attribute_type,
+        }
+    }
+
+    /// Create a Type 1 attribute requirement.
+    pub const fn type1(tag: Tag) -> Self {
+        Self::new(tag, AttributeType::Type1)
+    }
+
+    /// Create a Type 2 attribute requirement.
+    pub const fn type2(tag: Tag) -> Self {
+        Self::new(tag, AttributeType::Type2)
+    }
+
+    /// Create a Type 3 attribute requirement.
+    pub const fn type3(tag: Tag) -> Self {
+        Self::new(tag, AttributeType::Type3)
+    }
+}
+
+/// The required attributes of the Patient Module
+/// (PS3.3 C.7.1.1).
+pub const PATIENT_MODULE: &[AttributeRequirement] = &[
+    AttributeRequirement::type2(tags::PATIENT_NAME),
+    AttributeRequirement::type2(tags::PATIENT_ID),
+    AttributeRequirement::type2(tags::PATIENT_BIRTH_DATE),
+    AttributeRequirement::type2(tags::PATIENT_SEX),
+];
+
+/// The required attributes of the General Study Module
+/// (PS3.3 C.7.2.1).
+pub const GENERAL_STUDY_MODULE: &[AttributeRequirement] = &[
+    AttributeRequirement::type1(tags::STUDY_INSTANCE_UID),
+    AttributeRequirement::type2(tags::STUDY_DATE),
+    AttributeRequirement::type2(tags::STUDY_TIME),
+    AttributeRequirement::type2(tags::REFERRING_PHYSICIAN_NAME),
+    AttributeRequirement::type2(tags::STUDY_ID),
+    AttributeRequirement::type2(tags::ACCESSION_NUMBER),
+];
+
+/// A violation of an attribute requirement.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum Violation {
+    /// A Type 1 or Type 2 attribute is missing.
+    Missing {
+        /// The attribute tag.
+        tag: Tag,
+        /// The type of the missing attribute.
+        attribute_type: AttributeType,
+    },
+    /// A Type 1 attribute is present but empty.
+    Empty {
+        /// The attribute tag.
+        tag: Tag,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Missing {
+                tag,
+                attribute_type,
+            } => write!(f, "missing {} attribute {}", attribute_type, tag),
+            Violation::Empty { tag } => write!(f, "empty Type 1 attribute {}", tag),
+        }
+    }
+}
+
+/// Error type for a data set which does not satisfy
+/// its attribute requirements.
+#[derive(Debug, Snafu)]
+#[snafu(display("{} attribute requirement(s) not satisfied", violations.len()))]
+pub struct ValidationError {
+    violations: Vec<Violation>,
+    backtrace: Backtrace,
+}
+
+impl ValidationError {
+    /// Retrieve the requirement violations found.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+/// Check the given object against a list of attribute requirements,
+/// returning all violations found in order.
+///
+/// Requirements apply to the top level of the object only.
+pub fn check_requirements<D>(
+    obj: &InMemDicomObject<D>,
+    requirements: &[AttributeRequirement],
+) -> Vec<Violation>
+where
+    D: DataDictionary + Clone,
+{
+    requirements
+        .iter()
+        .filter_map(|req| {
+            let AttributeRequirement {
+                tag,
+                attribute_type,
+            } = *req;
+            if attribute_type == AttributeType::Type3 {
+                return None;
+            }
+            match obj.element_opt(tag).ok().flatten() {
+                None => Some(Violation::Missing {
+                    tag,
+                    attribute_type,
+                }),
+                Some(e) if attribute_type == AttributeType::Type1 && e.is_empty_value() => {
+                    Some(Violation::Empty { tag })
+                }
+                Some(_) => None,
+            }
+        })
+        .collect()
+}
+
+/// Validate the given object against a list of attribute requirements.
+///
+/// Returns an error with all violations found,
+/// if any requirement is not satisfied.
+pub fn validate<D>(
+    obj: &InMemDicomObject<D>,
+    requirements: &[AttributeRequirement],
+) -> Result<(), ValidationError>
+where
+    D: DataDictionary + Clone,
+{
+    let violations = check_requirements(obj, requirements);
+    ensure!(violations.is_empty(), ValidationSnafu { violations });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, PrimitiveValue, VR};
+
+    #[test]
+    fn check_type1_and_type2_requirements() {
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::STUDY_INSTANCE_UID, VR::UI, PrimitiveValue::Empty),
+            DataElement::new(tags::STUDY_DATE, VR::DA, PrimitiveValue::from("20230101")),
+        ]);
+        obj.put_empty(tags::STUDY_TIME, VR::TM);
+        obj.put_empty(tags::REFERRING_PHYSICIAN_NAME, VR::PN);
+
+        let violations = check_requirements(&obj, GENERAL_STUDY_MODULE);
+        assert_eq!(
+            violations,
+            vec![
+                Violation::Empty {
+                    tag: tags::STUDY_INSTANCE_UID
+                },
+                Violation::Missing {
+                    tag: tags::STUDY_ID,
+                    attribute_type: AttributeType::Type2,
+                },
+                Violation::Missing {
+                    tag: tags::ACCESSION_NUMBER,
+                    attribute_type: AttributeType::Type2,
+                },
+            ]
+        );
+
+        let err = validate(&obj, GENERAL_STUDY_MODULE).unwrap_err();
+        assert_eq!(err.violations().len(), 3);
+
+        obj.put(DataElement::new(
+            tags::STUDY_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4"),
+        ));
+        obj.put_empty(tags::STUDY_ID, VR::SH);
+        obj.put_empty(tags::ACCESSION_NUMBER, VR::SH);
+        assert!(validate(&obj, GENERAL_STUDY_MODULE).is_ok());
+    }
+
+    #[test]
+    fn type3_attributes_are_optional() {
+        let obj = InMemDicomObject::new_empty();
+        let requirements = [AttributeRequirement::type3(tags::PATIENT_COMMENTS)];
+        assert!(check_requirements(&obj, &requirements).is_empty());
+    }
+
+    #[test]
+    fn empty_sequences_satisfy_type2() {
+        let mut obj = InMemDicomObject::new_empty();
+        let requirements = [AttributeRequirement::type2(tags::REFERENCED_STUDY_SEQUENCE)];
+        obj.put_empty(tags::REFERENCED_STUDY_SEQUENCE, VR::SQ);
+        assert!(check_requirements(&obj, &requirements).is_empty());
+
+        let requirements = [AttributeRequirement::type1(tags::REFERENCED_STUDY_SEQUENCE)];
+        assert_eq!(
+            check_requirements(&obj, &requirements),
+            vec![Violation::Empty {
+                tag: tags::REFERENCED_STUDY_SEQUENCE
+            }]
+        );
+    }
+}
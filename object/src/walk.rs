@@ -0,0 +1,347 @@
+//! Depth-first traversal of data sets.
+//!
+//! [`walk`] visits every data element of an object,
+//! including those in the items of nested sequences,
+//! along with the [`TagPath`] leading to it.
+//! Each sequence element is visited before the elements of its items.
+//! The [`Visitor`] decides whether to continue,
+//! skip the items of a sequence,
+//! or stop the traversal.
+//! Closures taking the path and the element can be used as visitors.
+//!
+//! [`walk_mut`] traverses an object in the same order,
+//! keeping, removing or replacing each element
+//! according to the returned [`Edit`].
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, Length, PrimitiveValue, VR};
+//! # use dicom_core::header::Header;
+//! # use dicom_core::value::Value;
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::walk::{walk, walk_mut, Edit, Walk};
+//!
+//! let item = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::PERSON_NAME, VR::PN, PrimitiveValue::from("Doe^Jane")),
+//! ]);
+//! let mut obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+//!     DataElement::new(
+//!         tags::VERIFYING_OBSERVER_SEQUENCE,
+//!         VR::SQ,
+//!         Value::Sequence { items: vec![item].into(), size: Length::UNDEFINED },
+//!     ),
+//! ]);
+//!
+//! // collect the paths of all person names
+//! let mut names = Vec::new();
+//! walk(&obj, &mut |path: &_, elem: &dicom_object::mem::InMemElement| {
+//!     if elem.vr() == VR::PN {
+//!         names.push(format!("{}", path));
+//!     }
+//!     Walk::Continue
+//! });
+//! assert_eq!(names, ["(0010,0010)", "(0040,A073)[0].(0040,A123)"]);
+//!
+//! // redact them
+//! walk_mut(&mut obj, |_path, elem| match elem.vr() {
+//!     VR::PN => Edit::Replace(Box::new(DataElement::empty(elem.tag(), VR::PN))),
+//!     _ => Edit::Keep,
+//! });
+//! assert!(obj.element(tags::PATIENT_NAME)?.is_empty_value());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::{InMemDicomObject, InMemElement};
+use crate::path::TagPath;
+use dicom_core::header::{HasLength, Header};
+use dicom_core::value::Value;
+use dicom_core::{DataDictionary, DataElement, Tag};
+
+/// The decision of a visitor on how to continue a traversal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Walk {
+    /// Continue with the items of the element, if any,
+    /// then with the next element.
+    Continue,
+    /// Continue with the next element,
+    /// without visiting the items of this one.
+    SkipItems,
+    /// Stop the traversal.
+    Stop,
+}
+
+/// A visitor of the data elements of an object.
+///
+/// This is implemented for closures
+/// taking the path and the element, and returning a [`Walk`].
+pub trait Visitor<D> {
+    /// Visit a data element at the given path,
+    /// before visiting the items of its value, if any.
+    fn visit(&mut self, path: &TagPath, elem: &InMemElement<D>) -> Walk;
+
+    /// Called when entering the item at `index`
+    /// of the sequence at the given path.
+    fn enter_item(&mut self, _sequence: &TagPath, _index: u32) {}
+
+    /// Called after visiting all elements
+    /// of the item at `index` of the sequence at the given path.
+    fn leave_item(&mut self, _sequence: &TagPath, _index: u32) {}
+}
+
+impl<D, F> Visitor<D> for F
+where
+    F: FnMut(&TagPath, &InMemElement<D>) -> Walk,
+{
+    fn visit(&mut self, path: &TagPath, elem: &InMemElement<D>) -> Walk {
+        self(path, elem)
+    }
+}
+
+/// Visit all data elements of the object in depth-first order.
+///
+/// Returns [`Walk::Stop`] if the visitor stopped the traversal,
+/// and [`Walk::Continue`] otherwise.
+pub fn walk<D, V>(obj: &InMemDicomObject<D>, visitor: &mut V) -> Walk
+where
+    V: Visitor<D> + ?Sized,
+{
+    walk_items(obj, &mut Vec::new(), visitor)
+}
+
+fn walk_items<D, V>(obj: &InMemDicomObject<D>, items: &mut Vec<(Tag, u32)>, visitor: &mut V) -> Walk
+where
+    V: Visitor<D> + ?Sized,
+{
+    for elem in obj {
+        let path = TagPath::from_parts(items.iter().copied(), elem.tag());
+        match visitor.visit(&path, elem) {
+            Walk::Continue => {}
+            Walk::SkipItems => continue,
+            Walk::Stop => return Walk::Stop,
+        }
+        for (index, item) in elem.items().unwrap_or_default().iter().enumerate() {
+            let index = index as u32;
+            visitor.enter_item(&path, index);
+            items.push((elem.tag(), index));
+            let flow = walk_items(item, items, visitor);
+            items.pop();
+            if flow == Walk::Stop {
+                return Walk::Stop;
+            }
+            visitor.leave_item(&path, index);
+        }
+    }
+    Walk::Continue
+}
+
+/// The change to apply to a data element in [`walk_mut`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit<D> {
+    /// Keep the element,
+    /// continuing with the items of its value, if any.
+    Keep,
+    /// Remove the element.
+    Remove,
+    /// Replace the element with another one.
+    /// The items of the new element are not visited.
+    Replace(Box<InMemElement<D>>),
+}
+
+/// Visit all data elements of the object in depth-first order,
+/// applying the edit returned by `f` to each of them.
+///
+/// Replacing an element with one of a different tag
+/// removes the original element.
+pub fn walk_mut<D, F>(obj: &mut InMemDicomObject<D>, mut f: F)
+where
+    D: DataDictionary + Clone,
+    F: FnMut(&TagPath, &InMemElement<D>) -> Edit<D>,
+{
+    walk_items_mut(obj, &mut Vec::new(), &mut f)
+}
+
+fn walk_items_mut<D, F>(obj: &mut InMemDicomObject<D>, items: &mut Vec<(Tag, u32)>, f: &mut F)
+where
+    D: DataDictionary + Clone,
+    F: FnMut(&TagPath, &InMemElement<D>) -> Edit<D>,
+{
+    let tags: Vec<Tag> = obj.tags().collect();
+    for tag in tags {
+        let elem = match obj.element(tag) {
+            Ok(elem) => elem,
+            Err(_) => continue,
+        };
+        let path = TagPath::from_parts(items.iter().copied(), tag);
+        match f(&path, elem) {
+            Edit::Keep => {}
+            Edit::Remove => {
+                obj.remove_element(tag);
+                continue;
+            }
+            Edit::Replace(new_elem) => {
+                if new_elem.tag() != tag {
+                    obj.remove_element(tag);
+                }
+                obj.put(*new_elem);
+                continue;
+            }
+        }
+        if elem.items().is_none() {
+            continue;
+        }
+
+        // update the items of the sequence
+        let elem = match obj.take_element(tag) {
+            Ok(elem) => elem,
+            Err(_) => continue,
+        };
+        let vr = elem.vr();
+        let len = elem.length();
+        let value = match elem.into_value() {
+            Value::Sequence {
+                items: mut seq_items,
+                size,
+            } => {
+                for (index, item) in seq_items.iter_mut().enumerate() {
+                    items.push((tag, index as u32));
+                    walk_items_mut(item, items, f);
+                    items.pop();
+                }
+                Value::Sequence {
+                    items: seq_items,
+                    size,
+                }
+            }
+            value => value,
+        };
+        obj.put(DataElement::new_with_len(tag, vr, len, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::value::PrimitiveValue;
+    use dicom_core::VR;
+    use dicom_dictionary_std::tags;
+
+    fn sample() -> InMemDicomObject {
+        let code = |value: &str| {
+            InMemDicomObject::from_element_iter([DataElement::new(
+                tags::CODE_VALUE,
+                VR::SH,
+                PrimitiveValue::from(value),
+            )])
+        };
+        InMemDicomObject::from_element_iter([
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new(
+                tags::PROCEDURE_CODE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![code("A"), code("B")].into(),
+                    size: dicom_core::Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("123")),
+        ])
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Visitor<dicom_dictionary_std::StandardDataDictionary> for Recorder {
+        fn visit(&mut self, path: &TagPath, _elem: &InMemElement) -> Walk {
+            self.events.push(path.to_string());
+            if path.tag() == tags::PATIENT_ID {
+                Walk::Stop
+            } else {
+                Walk::Continue
+            }
+        }
+
+        fn enter_item(&mut self, sequence: &TagPath, index: u32) {
+            self.events.push(format!("enter {}[{}]", sequence, index));
+        }
+
+        fn leave_item(&mut self, sequence: &TagPath, index: u32) {
+            self.events.push(format!("leave {}[{}]", sequence, index));
+        }
+    }
+
+    #[test]
+    fn walk_visits_nested_items_in_order() {
+        let obj = sample();
+        let mut recorder = Recorder::default();
+        assert_eq!(walk(&obj, &mut recorder), Walk::Stop);
+        assert_eq!(
+            recorder.events,
+            [
+                "(0008,1032)",
+                "enter (0008,1032)[0]",
+                "(0008,1032)[0].(0008,0100)",
+                "leave (0008,1032)[0]",
+                "enter (0008,1032)[1]",
+                "(0008,1032)[1].(0008,0100)",
+                "leave (0008,1032)[1]",
+                "(0010,0010)",
+                "(0010,0020)",
+            ]
+        );
+
+        // skipping the items of a sequence
+        let mut count = 0;
+        let flow = walk(&obj, &mut |_: &TagPath, elem: &InMemElement| {
+            count += 1;
+            if elem.vr() == VR::SQ {
+                Walk::SkipItems
+            } else {
+                Walk::Continue
+            }
+        });
+        assert_eq!(flow, Walk::Continue);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn walk_mut_edits_nested_elements() {
+        let mut obj = sample();
+        walk_mut(&mut obj, |path, elem| {
+            if path.tag() == tags::PATIENT_ID {
+                Edit::Remove
+            } else if path.items() == [(tags::PROCEDURE_CODE_SEQUENCE, 1)] {
+                Edit::Replace(Box::new(DataElement::new(
+                    elem.tag(),
+                    VR::SH,
+                    PrimitiveValue::from("C"),
+                )))
+            } else {
+                Edit::Keep
+            }
+        });
+
+        assert!(obj.element(tags::PATIENT_ID).is_err());
+        assert_eq!(
+            obj.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Doe^John"
+        );
+        let items = obj
+            .element(tags::PROCEDURE_CODE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        let code = |i: usize| {
+            items[i]
+                .element(tags::CODE_VALUE)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        };
+        assert_eq!(code(0), "A");
+        assert_eq!(code(1), "C");
+    }
+}
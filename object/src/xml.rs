@@ -0,0 +1,950 @@
+//! Native DICOM Model XML.
+//!
+//! This module converts in-memory DICOM objects
+//! to and from the XML representation of the Native DICOM Model
+//! (PS3.19 A.1),
+//! as used by application hosting and by some export pipelines.
+//!
+//! Values are mapped as follows:
+//!
+//! - textual values, numbers and attribute tags
+//!   are written as `Value` elements,
+//!   without insignificant padding;
+//! - person names are written as `PersonName` elements,
+//!   split into component groups and components;
+//! - values of the binary VRs (OB, OD, OF, OL, OV, OW and UN)
+//!   are written as `InlineBinary` elements,
+//!   in little endian base64;
+//! - sequence items are written as `Item` elements.
+//!
+//! Bulk data references (`BulkData` elements)
+//! and encapsulated pixel data are not supported.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElement, PrimitiveValue, VR};
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_object::xml::{from_xml_str, to_xml_string};
+//!
+//! let obj = InMemDicomObject::from_element_iter([
+//!     DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+//!     DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+//! ]);
+//!
+//! let xml = to_xml_string(&obj)?;
+//! assert!(xml.contains(r#"<DicomAttribute tag="00280010" vr="US" keyword="Rows">"#));
+//! assert!(xml.contains("<FamilyName>Doe</FamilyName>"));
+//!
+//! let obj2 = from_xml_str(&xml)?;
+//! assert_eq!(obj2.element(tags::ROWS)?.to_int::<u16>()?, 512);
+//! assert_eq!(obj2.element(tags::PATIENT_NAME)?.to_str()?, "Doe^John");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::mem::{InMemDicomObject, InMemElement};
+use crate::StandardDataDictionary;
+use base64::Engine;
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::header::Header;
+use dicom_core::value::equality::normalize_text;
+use dicom_core::value::{PrimitiveValue, Value, C};
+use dicom_core::{DataElement, Length, Tag, VR};
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Could not write XML document"))]
+    WriteXml {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not read XML document"))]
+    ReadXml {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Encapsulated pixel data in {} is not supported", tag))]
+    UnsupportedPixelSequence { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Bulk data reference in {} is not supported", tag))]
+    UnsupportedBulkData { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Malformed XML at byte {}: {}", position, message))]
+    MalformedXml {
+        position: usize,
+        message: &'static str,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Unexpected element `{}`", name))]
+    UnexpectedElement { name: String, backtrace: Backtrace },
+    #[snafu(display("Missing attribute `{}` in element `{}`", attribute, element))]
+    MissingAttribute {
+        element: &'static str,
+        attribute: &'static str,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Invalid attribute tag `{}`", text))]
+    InvalidTag { text: String, backtrace: Backtrace },
+    #[snafu(display("Invalid value representation `{}`", text))]
+    InvalidVr { text: String, backtrace: Backtrace },
+    #[snafu(display("Invalid value `{}` in {}", text, tag))]
+    InvalidValue {
+        tag: Tag,
+        text: String,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Invalid inline binary in {}", tag))]
+    InvalidInlineBinary {
+        tag: Tag,
+        source: base64::DecodeError,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The Native DICOM Model representation of a primitive value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NativeValue {
+    /// no value
+    Empty,
+    /// textual values, numbers or attribute tags
+    Values(Vec<String>),
+    /// person names
+    PersonNames(Vec<String>),
+    /// binary data, in little endian
+    Binary(Vec<u8>),
+}
+
+impl NativeValue {
+    /// Map a primitive value of the given VR to its native representation.
+    pub fn from_primitive(vr: VR, value: &PrimitiveValue) -> Self {
+        if value.multiplicity() == 0 {
+            return NativeValue::Empty;
+        }
+        match vr {
+            VR::OB | VR::OD | VR::OF | VR::OL | VR::OV | VR::OW | VR::UN => {
+                NativeValue::Binary(binary_bytes(value))
+            }
+            VR::AT => match value {
+                PrimitiveValue::Tags(tags) => NativeValue::Values(
+                    tags.iter()
+                        .map(|t| format!("{:04X}{:04X}", t.group(), t.element()))
+                        .collect(),
+                ),
+                _ => NativeValue::Values(value.to_multi_str().into_owned()),
+            },
+            VR::PN => NativeValue::PersonNames(
+                value
+                    .to_multi_str()
+                    .iter()
+                    .map(|v| normalize_text(vr, v).to_string())
+                    .collect(),
+            ),
+            _ => NativeValue::Values(
+                value
+                    .to_multi_str()
+                    .iter()
+                    .map(|v| normalize_text(vr, v).to_string())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Map a native representation to a primitive value of the given VR.
+    pub fn into_primitive(self, tag: Tag, vr: VR) -> Result<PrimitiveValue> {
+        match self {
+            NativeValue::Empty => Ok(PrimitiveValue::Empty),
+            NativeValue::PersonNames(values) => Ok(PrimitiveValue::Strs(values.into())),
+            NativeValue::Binary(bytes) => Ok(match vr {
+                VR::OD => PrimitiveValue::F64(
+                    bytes
+                        .chunks_exact(8)
+                        .map(|c| {
+                            f64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]])
+                        })
+                        .collect(),
+                ),
+                VR::OF => PrimitiveValue::F32(
+                    bytes
+                        .chunks_exact(4)
+                        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                        .collect(),
+                ),
+                VR::OL => PrimitiveValue::U32(
+                    bytes
+                        .chunks_exact(4)
+                        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                        .collect(),
+                ),
+                VR::OV => PrimitiveValue::U64(
+                    bytes
+                        .chunks_exact(8)
+                        .map(|c| {
+                            u64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]])
+                        })
+                        .collect(),
+                ),
+                VR::OW => PrimitiveValue::U16(
+                    bytes
+                        .chunks_exact(2)
+                        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                        .collect(),
+                ),
+                _ => PrimitiveValue::U8(bytes.into()),
+            }),
+            NativeValue::Values(values) => match vr {
+                VR::AT => values
+                    .iter()
+                    .map(|v| parse_tag(v))
+                    .collect::<Result<C<_>>>()
+                    .map(PrimitiveValue::Tags),
+                VR::US => parse_numbers(tag, &values).map(PrimitiveValue::U16),
+                VR::SS => parse_numbers(tag, &values).map(PrimitiveValue::I16),
+                VR::UL => parse_numbers(tag, &values).map(PrimitiveValue::U32),
+                VR::SL => parse_numbers(tag, &values).map(PrimitiveValue::I32),
+                VR::UV => parse_numbers(tag, &values).map(PrimitiveValue::U64),
+                VR::SV => parse_numbers(tag, &values).map(PrimitiveValue::I64),
+                VR::FL => parse_numbers(tag, &values).map(PrimitiveValue::F32),
+                VR::FD => parse_numbers(tag, &values).map(PrimitiveValue::F64),
+                _ => Ok(PrimitiveValue::Strs(values.into())),
+            },
+        }
+    }
+}
+
+/// The bytes of a binary value, in little endian.
+fn binary_bytes(value: &PrimitiveValue) -> Vec<u8> {
+    match value {
+        PrimitiveValue::U16(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        PrimitiveValue::I16(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        PrimitiveValue::U32(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        PrimitiveValue::I32(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        PrimitiveValue::U64(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        PrimitiveValue::I64(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        PrimitiveValue::F32(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        PrimitiveValue::F64(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        _ => value.to_bytes().into_owned(),
+    }
+}
+
+fn parse_numbers<T>(tag: Tag, values: &[String]) -> Result<C<T>>
+where
+    T: std::str::FromStr,
+{
+    values
+        .iter()
+        .map(|v| {
+            v.trim().parse().ok().context(InvalidValueSnafu {
+                tag,
+                text: v.as_str(),
+            })
+        })
+        .collect()
+}
+
+fn parse_tag(text: &str) -> Result<Tag> {
+    let text = text.trim();
+    ensure!(text.len() == 8 && text.is_ascii(), InvalidTagSnafu { text });
+    let group = u16::from_str_radix(&text[..4], 16);
+    let element = u16::from_str_radix(&text[4..], 16);
+    match (group, element) {
+        (Ok(group), Ok(element)) => Ok(Tag(group, element)),
+        _ => InvalidTagSnafu { text }.fail(),
+    }
+}
+
+const PERSON_NAME_GROUPS: [&str; 3] = ["Alphabetic", "Ideographic", "Phonetic"];
+const PERSON_NAME_COMPONENTS: [&str; 5] = [
+    "FamilyName",
+    "GivenName",
+    "MiddleName",
+    "NamePrefix",
+    "NameSuffix",
+];
+
+/// Encode a DICOM object in the Native DICOM Model XML representation.
+pub fn to_xml_string<D>(obj: &InMemDicomObject<D>) -> Result<String>
+where
+    D: DataDictionary + Clone,
+{
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<NativeDicomModel xml:space=\"preserve\">\n",
+    );
+    write_data_set(&mut out, obj, 1)?;
+    out.push_str("</NativeDicomModel>\n");
+    Ok(out)
+}
+
+/// Write a DICOM object to the given writer
+/// in the Native DICOM Model XML representation.
+pub fn write_xml<W, D>(mut to: W, obj: &InMemDicomObject<D>) -> Result<()>
+where
+    W: Write,
+    D: DataDictionary + Clone,
+{
+    to.write_all(to_xml_string(obj)?.as_bytes())
+        .context(WriteXmlSnafu)
+}
+
+fn write_data_set<D>(out: &mut String, obj: &InMemDicomObject<D>, depth: usize) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let indent = "  ".repeat(depth);
+    for elem in obj.iter() {
+        let tag = elem.tag();
+        let vr = elem.vr();
+        let _ = write!(
+            out,
+            "{}<DicomAttribute tag=\"{:04X}{:04X}\" vr=\"{}\"",
+            indent,
+            tag.group(),
+            tag.element(),
+            vr
+        );
+        if let Some(creator) = private_creator(obj, tag) {
+            let _ = write!(out, " privateCreator=\"{}\"", escape(&creator));
+        } else if let Some(entry) = StandardDataDictionary.by_tag(tag) {
+            let _ = write!(out, " keyword=\"{}\"", entry.alias());
+        }
+        out.push_str(">\n");
+        write_value(out, elem, depth + 1)?;
+        let _ = writeln!(out, "{}</DicomAttribute>", indent);
+    }
+    Ok(())
+}
+
+fn write_value<D>(out: &mut String, elem: &InMemElement<D>, depth: usize) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let indent = "  ".repeat(depth);
+    match elem.value() {
+        Value::Sequence { items, .. } => {
+            for (i, item) in items.iter().enumerate() {
+                let _ = writeln!(out, "{}<Item number=\"{}\">", indent, i + 1);
+                write_data_set(out, item, depth + 1)?;
+                let _ = writeln!(out, "{}</Item>", indent);
+            }
+        }
+        Value::PixelSequence { .. } => {
+            return UnsupportedPixelSequenceSnafu { tag: elem.tag() }.fail();
+        }
+        Value::Primitive(value) => match NativeValue::from_primitive(elem.vr(), value) {
+            NativeValue::Empty => {}
+            NativeValue::Values(values) => {
+                for (i, v) in values.iter().enumerate() {
+                    let _ = writeln!(
+                        out,
+                        "{}<Value number=\"{}\">{}</Value>",
+                        indent,
+                        i + 1,
+                        escape(v)
+                    );
+                }
+            }
+            NativeValue::PersonNames(names) => {
+                for (i, name) in names.iter().enumerate() {
+                    let _ = writeln!(out, "{}<PersonName number=\"{}\">", indent, i + 1);
+                    for (group, text) in PERSON_NAME_GROUPS.iter().zip(name.split('=')) {
+                        if text.is_empty() {
+                            continue;
+                        }
+                        let _ = write!(out, "{}  <{}>", indent, group);
+                        for (component, text) in PERSON_NAME_COMPONENTS.iter().zip(text.split('^'))
+                        {
+                            if !text.is_empty() {
+                                let _ =
+                                    write!(out, "<{0}>{1}</{0}>", component, escape(text.trim()));
+                            }
+                        }
+                        let _ = writeln!(out, "</{}>", group);
+                    }
+                    let _ = writeln!(out, "{}</PersonName>", indent);
+                }
+            }
+            NativeValue::Binary(bytes) => {
+                let _ = writeln!(
+                    out,
+                    "{}<InlineBinary>{}</InlineBinary>",
+                    indent,
+                    base64::engine::general_purpose::STANDARD.encode(bytes)
+                );
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Find the private creator of a private data element in the same data set.
+fn private_creator<D>(obj: &InMemDicomObject<D>, tag: Tag) -> Option<String>
+where
+    D: DataDictionary + Clone,
+{
+    if tag.group() & 1 == 0 || tag.element() < 0x1000 {
+        return None;
+    }
+    let creator = obj.element(Tag(tag.group(), tag.element() >> 8)).ok()?;
+    Some(creator.to_str().ok()?.trim().to_string())
+}
+
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Decode a DICOM object from its Native DICOM Model XML representation.
+///
+/// Attributes without a `vr` are given the VR
+/// of the attribute in the standard data dictionary,
+/// or UN if it is not known.
+pub fn from_xml_str(text: &str) -> Result<InMemDicomObject> {
+    let root = XmlParser { text, pos: 0 }.parse_document()?;
+    ensure!(
+        root.name == "NativeDicomModel",
+        UnexpectedElementSnafu { name: root.name }
+    );
+    read_data_set(&root)
+}
+
+/// Read a DICOM object
+/// in the Native DICOM Model XML representation
+/// from the given reader.
+pub fn read_xml<R>(mut from: R) -> Result<InMemDicomObject>
+where
+    R: Read,
+{
+    let mut text = String::new();
+    from.read_to_string(&mut text).context(ReadXmlSnafu)?;
+    from_xml_str(&text)
+}
+
+fn read_data_set(node: &XmlNode) -> Result<InMemDicomObject> {
+    let mut obj = InMemDicomObject::new_empty();
+    for child in &node.children {
+        ensure!(
+            child.name == "DicomAttribute",
+            UnexpectedElementSnafu {
+                name: child.name.as_str()
+            }
+        );
+        obj.put(read_attribute(child)?);
+    }
+    Ok(obj)
+}
+
+fn read_attribute(node: &XmlNode) -> Result<InMemElement> {
+    let tag = node.attribute("tag").context(MissingAttributeSnafu {
+        element: "DicomAttribute",
+        attribute: "tag",
+    })?;
+    let tag = parse_tag(tag)?;
+    let vr = match node.attribute("vr") {
+        Some(vr) => vr
+            .trim()
+            .parse()
+            .ok()
+            .context(InvalidVrSnafu { text: vr })?,
+        None => StandardDataDictionary
+            .by_tag(tag)
+            .map(|e| e.vr())
+            .unwrap_or(VR::UN),
+    };
+
+    if vr == VR::SQ {
+        let items = node
+            .numbered_children("Item")?
+            .into_iter()
+            .map(read_data_set)
+            .collect::<Result<C<_>>>()?;
+        return Ok(DataElement::new(
+            tag,
+            vr,
+            Value::Sequence {
+                items,
+                size: Length::UNDEFINED,
+            },
+        ));
+    }
+
+    let value = if let Some(child) = node.children.first() {
+        match child.name.as_str() {
+            "BulkData" => return UnsupportedBulkDataSnafu { tag }.fail(),
+            "InlineBinary" => NativeValue::Binary(
+                base64::engine::general_purpose::STANDARD
+                    .decode(child.text.trim())
+                    .context(InvalidInlineBinarySnafu { tag })?,
+            ),
+            "PersonName" => NativeValue::PersonNames(
+                node.numbered_children("PersonName")?
+                    .into_iter()
+                    .map(read_person_name)
+                    .collect(),
+            ),
+            _ => NativeValue::Values(
+                node.numbered_children("Value")?
+                    .into_iter()
+                    .map(|v| v.text.clone())
+                    .collect(),
+            ),
+        }
+    } else {
+        NativeValue::Empty
+    };
+    Ok(DataElement::new(tag, vr, value.into_primitive(tag, vr)?))
+}
+
+fn read_person_name(node: &XmlNode) -> String {
+    let groups: Vec<String> = PERSON_NAME_GROUPS
+        .iter()
+        .map(|group| match node.child(group) {
+            Some(group) => {
+                let components: Vec<&str> = PERSON_NAME_COMPONENTS
+                    .iter()
+                    .map(|c| group.child(c).map(|c| c.text.as_str()).unwrap_or(""))
+                    .collect();
+                components.join("^").trim_end_matches('^').to_string()
+            }
+            None => String::new(),
+        })
+        .collect();
+    groups.join("=").trim_end_matches('=').to_string()
+}
+
+/// An element of an XML document.
+#[derive(Debug, Default)]
+struct XmlNode {
+    /// the local name of the element
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlNode>,
+    /// the text content of the element, without its child elements
+    text: String,
+}
+
+impl XmlNode {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&XmlNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Collect the child elements of the given name,
+    /// in the order of their `number` attribute.
+    fn numbered_children(&self, name: &'static str) -> Result<Vec<&XmlNode>> {
+        let mut children = Vec::new();
+        for child in &self.children {
+            ensure!(
+                child.name == name,
+                UnexpectedElementSnafu {
+                    name: child.name.as_str()
+                }
+            );
+            let number: u32 = child
+                .attribute("number")
+                .and_then(|n| n.trim().parse().ok())
+                .context(MissingAttributeSnafu {
+                    element: name,
+                    attribute: "number",
+                })?;
+            children.push((number, child));
+        }
+        children.sort_by_key(|(number, _)| *number);
+        Ok(children.into_iter().map(|(_, child)| child).collect())
+    }
+}
+
+/// A minimal XML parser,
+/// sufficient for the Native DICOM Model.
+///
+/// Processing instructions, comments and document type declarations
+/// are skipped,
+/// and namespace prefixes are removed from element names.
+struct XmlParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &'static str) -> Error {
+        MalformedXmlSnafu {
+            position: self.pos,
+            message,
+        }
+        .build()
+    }
+
+    fn fail<T>(&self, message: &'static str) -> Result<T> {
+        Err(self.error(message))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skip past the given terminator.
+    fn skip_past(&mut self, terminator: &str) -> Result<()> {
+        match self.rest().find(terminator) {
+            Some(i) => {
+                self.pos += i + terminator.len();
+                Ok(())
+            }
+            None => self.fail("unterminated markup"),
+        }
+    }
+
+    /// Skip markup which is not an element, returning whether any was found.
+    fn skip_misc(&mut self) -> Result<bool> {
+        if self.rest().starts_with("<?") {
+            self.skip_past("?>")?;
+        } else if self.rest().starts_with("<!--") {
+            self.skip_past("-->")?;
+        } else if self.rest().starts_with("<!DOCTYPE") {
+            self.skip_past(">")?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn parse_document(mut self) -> Result<XmlNode> {
+        loop {
+            self.skip_whitespace();
+            if !self.skip_misc()? {
+                break;
+            }
+        }
+        if !self.rest().starts_with('<') {
+            return self.fail("expected root element");
+        }
+        let root = self.parse_element()?;
+        loop {
+            self.skip_whitespace();
+            if !self.skip_misc()? {
+                break;
+            }
+        }
+        if !self.rest().is_empty() {
+            return self.fail("unexpected content after root element");
+        }
+        Ok(root)
+    }
+
+    fn parse_name(&mut self) -> Result<&str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return self.fail("expected name");
+        }
+        let name = &self.text[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(name)
+    }
+
+    fn parse_element(&mut self) -> Result<XmlNode> {
+        // skip '<'
+        self.pos += 1;
+        let qualified_name = self.parse_name()?.to_string();
+        let mut node = XmlNode {
+            name: local_name(&qualified_name).to_string(),
+            ..Default::default()
+        };
+
+        // attributes
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(node);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let name = self.parse_name()?.to_string();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return self.fail("expected `=`");
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(q @ '"') | Some(q @ '\'') => q,
+                _ => return self.fail("expected quoted attribute value"),
+            };
+            self.pos += 1;
+            let len = match self.rest().find(quote) {
+                Some(len) => len,
+                None => return self.fail("unterminated attribute value"),
+            };
+            let value = unescape(&self.text[self.pos..self.pos + len])
+                .map_err(|message| self.error(message))?;
+            self.pos += len + 1;
+            node.attributes.push((name, value));
+        }
+
+        // content
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.pos += 2;
+                if self.parse_name()? != qualified_name {
+                    return self.fail("mismatched end tag");
+                }
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return self.fail("expected `>`");
+                }
+                self.pos += 1;
+                return Ok(node);
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let len = match self.rest().find("]]>") {
+                    Some(len) => len,
+                    None => return self.fail("unterminated CDATA section"),
+                };
+                node.text.push_str(&self.text[self.pos..self.pos + len]);
+                self.pos += len + 3;
+            } else if self.skip_misc()? {
+                // skipped
+            } else if rest.starts_with('<') {
+                node.children.push(self.parse_element()?);
+            } else if rest.is_empty() {
+                return self.fail("unexpected end of document");
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                let text = unescape(&rest[..len]).map_err(|message| self.error(message))?;
+                node.text.push_str(&text);
+                self.pos += len;
+            }
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Replace the entity and character references in XML text.
+fn unescape(text: &str) -> std::result::Result<String, &'static str> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let end = rest.find(';').ok_or("unterminated reference")?;
+        let reference = &rest[..end];
+        let c = match reference {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = reference.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = reference.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(std::char::from_u32)
+                    .ok_or("invalid reference")?
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_xml_str, to_xml_string, Error};
+    use crate::InMemDicomObject;
+    use dicom_core::header::HasLength;
+    use dicom_core::value::Value;
+    use dicom_core::{dicom_value, DataElement, Length, PrimitiveValue, Tag, VR};
+    use dicom_dictionary_std::tags;
+
+    #[test]
+    fn write_and_read_native_model() {
+        let item = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::REFERENCED_SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4\0"),
+        )]);
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::PATIENT_NAME,
+                VR::PN,
+                PrimitiveValue::from("Yamada^Tarou=山田^太郎"),
+            ),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::Empty),
+            DataElement::new(
+                tags::STUDY_DESCRIPTION,
+                VR::LO,
+                PrimitiveValue::from("Head & <Neck> "),
+            ),
+            DataElement::new(
+                tags::IMAGE_TYPE,
+                VR::CS,
+                dicom_value!(Strs, ["ORIGINAL", "PRIMARY"]),
+            ),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+            DataElement::new(
+                tags::PIXEL_SPACING,
+                VR::DS,
+                dicom_value!(Strs, ["0.5", "0.5"]),
+            ),
+            DataElement::new(
+                tags::FRAME_INCREMENT_POINTER,
+                VR::AT,
+                PrimitiveValue::Tags(vec![tags::FRAME_TIME].into()),
+            ),
+            DataElement::new(
+                tags::REFERENCED_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(
+                Tag(0x0009, 0x0010),
+                VR::LO,
+                PrimitiveValue::from("ACME 1.0"),
+            ),
+            DataElement::new(
+                Tag(0x0009, 0x1001),
+                VR::OW,
+                PrimitiveValue::U16(vec![1, 0x0203].into()),
+            ),
+        ]);
+
+        let xml = to_xml_string(&obj).unwrap();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<NativeDicomModel"));
+        assert!(xml.contains(concat!(
+            "<DicomAttribute tag=\"00100010\" vr=\"PN\" keyword=\"PatientName\">\n",
+            "    <PersonName number=\"1\">\n",
+            "      <Alphabetic><FamilyName>Yamada</FamilyName><GivenName>Tarou</GivenName></Alphabetic>\n",
+            "      <Ideographic><FamilyName>山田</FamilyName><GivenName>太郎</GivenName></Ideographic>\n",
+            "    </PersonName>\n",
+        )));
+        assert!(xml.contains("<Value number=\"1\">Head &amp; &lt;Neck&gt;</Value>"));
+        assert!(xml.contains("<Value number=\"2\">PRIMARY</Value>"));
+        assert!(xml.contains("<Value number=\"1\">00181063</Value>"));
+        assert!(xml.contains("<Value number=\"1\">1.2.3.4</Value>"));
+        assert!(
+            xml.contains("<DicomAttribute tag=\"00091001\" vr=\"OW\" privateCreator=\"ACME 1.0\">")
+        );
+        assert!(xml.contains("<InlineBinary>AQADAg==</InlineBinary>"));
+
+        let obj2 = from_xml_str(&xml).unwrap();
+        assert_eq!(obj2.iter().count(), obj.iter().count());
+        assert_eq!(
+            obj2.element(tags::PATIENT_NAME).unwrap().to_str().unwrap(),
+            "Yamada^Tarou=山田^太郎"
+        );
+        assert!(obj2.element(tags::PATIENT_ID).unwrap().is_empty());
+        assert_eq!(
+            obj2.element(tags::STUDY_DESCRIPTION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Head & <Neck>"
+        );
+        assert_eq!(
+            obj2.element(tags::IMAGE_TYPE).unwrap().value(),
+            &Value::from(dicom_value!(Strs, ["ORIGINAL", "PRIMARY"]))
+        );
+        assert_eq!(
+            obj2.element(tags::ROWS).unwrap().value(),
+            &Value::from(PrimitiveValue::from(512_u16))
+        );
+        assert_eq!(
+            obj2.element(tags::FRAME_INCREMENT_POINTER).unwrap().value(),
+            &Value::from(PrimitiveValue::Tags(vec![tags::FRAME_TIME].into()))
+        );
+        assert_eq!(
+            obj2.element_at(
+                &"ReferencedImageSequence[0].ReferencedSOPInstanceUID"
+                    .parse()
+                    .unwrap()
+            )
+            .unwrap()
+            .to_str()
+            .unwrap(),
+            "1.2.3.4"
+        );
+        assert_eq!(
+            obj2.element(Tag(0x0009, 0x1001)).unwrap().value(),
+            &Value::from(PrimitiveValue::U16(vec![1, 0x0203].into()))
+        );
+    }
+
+    #[test]
+    fn read_native_model_variants() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- exported -->
+<dcm:NativeDicomModel xmlns:dcm="http://dicom.nema.org/PS3.19/models/NativeDICOM">
+  <dcm:DicomAttribute tag="00200013">
+    <dcm:Value number="2">2</dcm:Value>
+    <dcm:Value number="1">&#49;</dcm:Value>
+  </dcm:DicomAttribute>
+  <dcm:DicomAttribute tag="00080060" vr='CS'><dcm:Value number="1"><![CDATA[CT]]></dcm:Value></dcm:DicomAttribute>
+  <dcm:DicomAttribute tag="00100020" vr="LO"/>
+</dcm:NativeDicomModel>
+"#;
+        let obj = from_xml_str(xml).unwrap();
+        let number = obj.element(tags::INSTANCE_NUMBER).unwrap();
+        assert_eq!(number.vr(), VR::IS);
+        assert_eq!(number.to_multi_int::<i32>().unwrap(), vec![1, 2]);
+        assert_eq!(obj.element(tags::MODALITY).unwrap().to_str().unwrap(), "CT");
+        assert!(obj.element(tags::PATIENT_ID).unwrap().is_empty());
+
+        assert!(matches!(
+            from_xml_str(
+                r#"<NativeDicomModel><DicomAttribute tag="00100010" vr="PN"><BulkData uri="x"/></DicomAttribute></NativeDicomModel>"#
+            ),
+            Err(Error::UnsupportedBulkData { .. })
+        ));
+        assert!(matches!(
+            from_xml_str("<NativeDicomModel><DicomAttribute tag=\"0010\"/></NativeDicomModel>"),
+            Err(Error::InvalidTag { .. })
+        ));
+        assert!(matches!(
+            from_xml_str("<NativeDicomModel><DicomAttribute></NativeDicomModel>"),
+            Err(Error::MalformedXml { .. })
+        ));
+    }
+}
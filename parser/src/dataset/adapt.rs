@@ -0,0 +1,329 @@
+//! Data set stream adapters.
+//!
+//! Some transfer syntaxes,
+//! such as _Deflated Explicit VR Little Endian_,
+//! encode the data set as a whole into a different byte stream.
+//! The types in this module apply the data set codec
+//! of a transfer syntax (if any)
+//! to an underlying reader or writer,
+//! so that [`DataSetReader`](super::DataSetReader)
+//! and [`DataSetWriter`](super::DataSetWriter)
+//! can work on the plain data set encoding.
+//! Transfer syntaxes without a data set codec
+//! pass the bytes through unchanged.
+use dicom_encoding::transfer_syntax::Codec;
+use dicom_encoding::TransferSyntax;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{Cursor, Read, Result, Write};
+use std::rc::Rc;
+
+/// A reader of a data set,
+/// which undoes the data set codec of a transfer syntax.
+pub enum AdaptedReader<R> {
+    /// The source is read as is.
+    Plain(R),
+    /// The source was adapted by the transfer syntax' data set codec.
+    Adapted(Box<dyn Read>),
+}
+
+impl<R> AdaptedReader<R>
+where
+    R: Read,
+{
+    /// Wrap the given source of a data set encoded in the given transfer syntax.
+    ///
+    /// If the transfer syntax has a data set codec,
+    /// the remaining contents of the source are read into memory first,
+    /// and then adapted.
+    /// Otherwise, the source is read without any changes.
+    pub fn new(mut source: R, ts: &TransferSyntax) -> Result<Self> {
+        match ts.codec() {
+            Codec::Dataset(adapter) => {
+                let mut data = Vec::new();
+                source.read_to_end(&mut data)?;
+                Ok(AdaptedReader::Adapted(
+                    adapter.adapt_reader(Box::new(Cursor::new(data))),
+                ))
+            }
+            _ => Ok(AdaptedReader::Plain(source)),
+        }
+    }
+
+    /// Check whether the data set is read through a data set codec.
+    pub fn is_adapted(&self) -> bool {
+        matches!(self, AdaptedReader::Adapted(_))
+    }
+}
+
+impl<R> fmt::Debug for AdaptedReader<R>
+where
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdaptedReader::Plain(source) => f.debug_tuple("Plain").field(source).finish(),
+            AdaptedReader::Adapted(_) => f.debug_tuple("Adapted").field(&"..").finish(),
+        }
+    }
+}
+
+impl<R> Read for AdaptedReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self {
+            AdaptedReader::Plain(source) => source.read(buf),
+            AdaptedReader::Adapted(source) => source.read(buf),
+        }
+    }
+}
+
+/// A byte buffer shared between an adapted writer and its owner.
+#[derive(Debug, Default, Clone)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// Move all bytes in the buffer to the given writer.
+    fn drain_into<W: ?Sized + Write>(&self, to: &mut W) -> Result<()> {
+        let mut data = self.0.borrow_mut();
+        if !data.is_empty() {
+            to.write_all(&data)?;
+            data.clear();
+        }
+        Ok(())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A writer of a data set,
+/// which applies the data set codec of a transfer syntax.
+///
+/// When a data set codec is involved,
+/// the adapted output is passed on to the underlying writer
+/// as soon as the codec produces it,
+/// so the data set is written in a single pass
+/// without keeping it all in memory
+/// or seeking back in the destination.
+/// The codec may hold back the end of its output
+/// until [`finish`](AdaptedWriter::finish) is called.
+/// Dropping this writer without finishing it
+/// leaves the adapted output incomplete.
+pub struct AdaptedWriter<W> {
+    to: W,
+    adapted: Option<(Box<dyn Write>, SharedBuffer)>,
+}
+
+impl<W> AdaptedWriter<W>
+where
+    W: Write,
+{
+    /// Wrap the given destination of a data set
+    /// to be encoded in the given transfer syntax.
+    pub fn new(to: W, ts: &TransferSyntax) -> Self {
+        let adapted = match ts.codec() {
+            Codec::Dataset(adapter) => {
+                let buffer = SharedBuffer::default();
+                Some((adapter.adapt_writer(Box::new(buffer.clone())), buffer))
+            }
+            _ => None,
+        };
+        AdaptedWriter { to, adapted }
+    }
+
+    /// Wrap the given destination of a data set
+    /// to be encoded in the given transfer syntax,
+    /// using the given compression level
+    /// from 0 (no compression) to 9 (best compression)
+    /// if the data set codec compresses the data set.
+    pub fn with_compression_level(to: W, ts: &TransferSyntax, level: u32) -> Self {
+        let adapted = match ts.codec() {
+            Codec::Dataset(adapter) => {
+                let buffer = SharedBuffer::default();
+                let writer = adapter.adapt_writer_with_level(Box::new(buffer.clone()), level);
+                Some((writer, buffer))
+            }
+            _ => None,
+        };
+        AdaptedWriter { to, adapted }
+    }
+
+    /// Check whether the data set is written through a data set codec.
+    pub fn is_adapted(&self) -> bool {
+        self.adapted.is_some()
+    }
+
+    /// Retrieve a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.to
+    }
+
+    /// Finish the adapted output, write the rest of it to the underlying writer,
+    /// and return the underlying writer.
+    pub fn finish(self) -> Result<W> {
+        let mut to = self.to;
+        if let Some((mut writer, buffer)) = self.adapted {
+            writer.flush()?;
+            // adapted writers finish their output when dropped
+            drop(writer);
+            buffer.drain_into(&mut to)?;
+        }
+        Ok(to)
+    }
+}
+
+impl<W> fmt::Debug for AdaptedWriter<W>
+where
+    W: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdaptedWriter")
+            .field("to", &self.to)
+            .field("adapted", &self.adapted.is_some())
+            .finish()
+    }
+}
+
+impl<W> Write for AdaptedWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match &mut self.adapted {
+            Some((writer, buffer)) => {
+                let n = writer.write(buf)?;
+                buffer.drain_into(&mut self.to)?;
+                Ok(n)
+            }
+            None => self.to.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some((writer, buffer)) = &mut self.adapted {
+            writer.flush()?;
+            buffer.drain_into(&mut self.to)?;
+        }
+        self.to.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdaptedReader, AdaptedWriter};
+    use dicom_encoding::transfer_syntax::{AdapterFreeTransferSyntax, Codec, DataRWAdapter};
+    use dicom_encoding::{Endianness, NeverPixelAdapter, TransferSyntax};
+    use std::io::{Read, Write};
+
+    /// A data set adapter which inverts all bits.
+    #[derive(Debug)]
+    struct NotAdapter;
+
+    struct NotReader<R>(R);
+
+    impl<R: Read> Read for NotReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.read(buf)?;
+            buf[..n].iter_mut().for_each(|b| *b = !*b);
+            Ok(n)
+        }
+    }
+
+    struct NotWriter<W>(W);
+
+    impl<W: Write> Write for NotWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let data: Vec<u8> = buf.iter().map(|b| !b).collect();
+            self.0.write_all(&data)?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    impl<R: 'static, W: 'static> DataRWAdapter<R, W> for NotAdapter {
+        type Reader = Box<dyn Read>;
+        type Writer = Box<dyn Write>;
+
+        fn adapt_reader(&self, reader: R) -> Self::Reader
+        where
+            R: Read,
+        {
+            Box::new(NotReader(reader))
+        }
+
+        fn adapt_writer(&self, writer: W) -> Self::Writer
+        where
+            W: Write,
+        {
+            Box::new(NotWriter(writer))
+        }
+    }
+
+    fn not_ts() -> TransferSyntax {
+        TransferSyntax::new(
+            "1.2.840.10008.9999.9999.2",
+            "Inverted Explicit VR Little Endian",
+            Endianness::Little,
+            true,
+            Codec::Dataset::<_, NeverPixelAdapter>(NotAdapter),
+        )
+        .erased()
+    }
+
+    #[test]
+    fn adapted_read_write_roundtrip() {
+        let ts = not_ts();
+
+        let mut writer = AdaptedWriter::new(Vec::new(), &ts);
+        assert!(writer.is_adapted());
+        writer.write_all(&[0x00, 0x0F, 0xF0]).unwrap();
+        // adapted output is passed on without waiting for the end
+        assert_eq!(writer.get_ref(), &vec![0xFF, 0xF0, 0x0F]);
+        let out = writer.finish().unwrap();
+        assert_eq!(out, vec![0xFF, 0xF0, 0x0F]);
+
+        let mut reader = AdaptedReader::new(&out[..], &ts).unwrap();
+        assert!(reader.is_adapted());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, vec![0x00, 0x0F, 0xF0]);
+    }
+
+    #[test]
+    fn plain_read_write_passthrough() {
+        let ts = AdapterFreeTransferSyntax::new(
+            "1.2.840.10008.1.2.1",
+            "Explicit VR Little Endian",
+            Endianness::Little,
+            true,
+            Codec::None,
+        )
+        .erased();
+
+        let mut writer = AdaptedWriter::new(Vec::new(), &ts);
+        assert!(!writer.is_adapted());
+        writer.write_all(&[1, 2, 3]).unwrap();
+        let out = writer.finish().unwrap();
+        assert_eq!(out, vec![1, 2, 3]);
+
+        let mut reader = AdaptedReader::new(&out[..], &ts).unwrap();
+        assert!(!reader.is_adapted());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,377 @@
+//! This module contains an asynchronous writer of DICOM data sets,
+//! available with the `async` feature.
+//!
+//! The [`AsyncDataSetWriter`] is the counterpart of the
+//! [`PushDataSetReader`](super::push::PushDataSetReader):
+//! data set tokens are encoded into memory as they are fed,
+//! and the resulting bytes are written to an [`AsyncWrite`] destination
+//! in chunks of a bounded size,
+//! without blocking the current thread.
+//! This allows a data set to be streamed
+//! into a network connection (such as a sequence of P-DATA values)
+//! or the body of an HTTP request.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::{DataElementHeader, Length, PrimitiveValue, Tag, VR};
+//! # use dicom_encoding::text::SpecificCharacterSet;
+//! # use dicom_encoding::transfer_syntax::{AdapterFreeTransferSyntax, Codec, Endianness};
+//! use dicom_parser::dataset::async_write::AsyncDataSetWriter;
+//! use dicom_parser::dataset::DataToken;
+//!
+//! # let ts = AdapterFreeTransferSyntax::new(
+//! #     "1.2.840.10008.1.2.1",
+//! #     "Explicit VR Little Endian",
+//! #     Endianness::Little,
+//! #     true,
+//! #     Codec::None,
+//! # ).erased();
+//! # futures_executor::block_on(async {
+//! let mut writer = AsyncDataSetWriter::with_ts_cs(Vec::new(), &ts, SpecificCharacterSet::Default)?
+//!     .chunk_size(16_384);
+//! writer
+//!     .write_sequence(vec![
+//!         DataToken::ElementHeader(DataElementHeader::new(
+//!             Tag(0x0010, 0x0010),
+//!             VR::PN,
+//!             Length(4),
+//!         )),
+//!         DataToken::PrimitiveValue(PrimitiveValue::from("Doe")),
+//!     ])
+//!     .await?;
+//! writer.flush().await?;
+//! assert_eq!(writer.into_inner().len(), 12);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! # })?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::dataset::write::{DataSetWriter, Error as WriteError};
+use crate::dataset::DataToken;
+use dicom_encoding::text::SpecificCharacterSet;
+use dicom_encoding::transfer_syntax::{Codec, DynEncoder, TransferSyntax};
+use futures_io::AsyncWrite;
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Transfer syntax {} cannot be written asynchronously", ts))]
+    UnsupportedTransferSyntax {
+        ts: &'static str,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not create data set writer"))]
+    CreateWriter {
+        #[snafu(backtrace)]
+        source: WriteError,
+    },
+    #[snafu(display("Could not encode data set token"))]
+    EncodeToken {
+        #[snafu(backtrace)]
+        source: WriteError,
+    },
+    #[snafu(display("Could not write data set bytes"))]
+    WriteData {
+        source: io::Error,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The default maximum number of bytes in each write to the destination.
+const DEFAULT_CHUNK_SIZE: usize = 16_384;
+
+/// An asynchronous writer of DICOM data set tokens.
+///
+/// Tokens are given to the writer through [`write`](Self::write),
+/// which completes once all bytes encoded so far
+/// were accepted by the destination.
+/// Each write to the destination is at most
+/// [`chunk_size`](Self::chunk_size) bytes long.
+///
+/// Data sets in a transfer syntax
+/// which requires a data set codec (such as deflate)
+/// are not supported.
+pub struct AsyncDataSetWriter<W> {
+    /// the asynchronous destination
+    to: W,
+    /// the underlying writer, encoding into memory
+    writer: DataSetWriter<Vec<u8>, DynEncoder<'static, Vec<u8>>>,
+    /// the maximum number of bytes in each write
+    chunk_size: usize,
+}
+
+impl<W> fmt::Debug for AsyncDataSetWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncDataSetWriter")
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
+
+impl<W> AsyncDataSetWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Create a new asynchronous writer
+    /// for a data set in the given transfer syntax,
+    /// using the given initial character set.
+    pub fn with_ts_cs(to: W, ts: &TransferSyntax, cs: SpecificCharacterSet) -> Result<Self> {
+        ensure!(
+            !matches!(ts.codec(), Codec::Dataset(_)),
+            UnsupportedTransferSyntaxSnafu { ts: ts.name() }
+        );
+        let writer = DataSetWriter::with_ts_cs(Vec::new(), ts, cs).context(CreateWriterSnafu)?;
+        Ok(AsyncDataSetWriter {
+            to,
+            writer,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        })
+    }
+
+    /// Set the maximum number of bytes in each write to the destination,
+    /// such as the maximum length of a presentation data value.
+    ///
+    /// The default is 16 KiB.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Feed the given data set token for writing.
+    pub async fn write(&mut self, token: DataToken) -> Result<()> {
+        self.writer.write(token).context(EncodeTokenSnafu)?;
+        self.write_pending().await
+    }
+
+    /// Feed the given sequence of tokens which are part of the same data set.
+    pub async fn write_sequence<I>(&mut self, tokens: I) -> Result<()>
+    where
+        I: IntoIterator<Item = DataToken>,
+    {
+        for token in tokens {
+            self.writer.write(token).context(EncodeTokenSnafu)?;
+            if self.writer.printer_mut().writer_mut().len() >= self.chunk_size {
+                self.write_pending().await?;
+            }
+        }
+        self.write_pending().await
+    }
+
+    /// Flush the destination,
+    /// ensuring that all bytes written so far reach it.
+    pub async fn flush(&mut self) -> Result<()> {
+        Flush { to: &mut self.to }.await.context(WriteDataSnafu)
+    }
+
+    /// Retrieve a reference to the destination.
+    pub fn get_ref(&self) -> &W {
+        &self.to
+    }
+
+    /// Retrieve a mutable reference to the destination.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.to
+    }
+
+    /// Recover the destination.
+    pub fn into_inner(self) -> W {
+        self.to
+    }
+
+    /// Write all encoded bytes to the destination, chunk by chunk.
+    async fn write_pending(&mut self) -> Result<()> {
+        let buffer = std::mem::take(self.writer.printer_mut().writer_mut());
+        for chunk in buffer.chunks(self.chunk_size) {
+            WriteAll {
+                to: &mut self.to,
+                buf: chunk,
+            }
+            .await
+            .context(WriteDataSnafu)?;
+        }
+        // reuse the allocation for the next tokens
+        let mut buffer = buffer;
+        buffer.clear();
+        *self.writer.printer_mut().writer_mut() = buffer;
+        Ok(())
+    }
+}
+
+/// Future writing a whole buffer to an asynchronous destination.
+struct WriteAll<'a, W> {
+    to: &'a mut W,
+    buf: &'a [u8],
+}
+
+impl<W> Future for WriteAll<'_, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        while !this.buf.is_empty() {
+            match Pin::new(&mut *this.to).poll_write(cx, this.buf) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => this.buf = &this.buf[n..],
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Future flushing an asynchronous destination.
+struct Flush<'a, W> {
+    to: &'a mut W,
+}
+
+impl<W> Future for Flush<'_, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.to).poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncDataSetWriter;
+    use crate::dataset::write::DataSetWriter;
+    use crate::dataset::DataToken;
+    use dicom_core::header::{DataElementHeader, Length};
+    use dicom_core::value::PrimitiveValue;
+    use dicom_core::{Tag, VR};
+    use dicom_encoding::text::SpecificCharacterSet;
+    use dicom_encoding::transfer_syntax::{
+        AdapterFreeTransferSyntax, Codec, Endianness, TransferSyntax,
+    };
+    use futures_io::AsyncWrite;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    fn explicit_vr_le() -> TransferSyntax {
+        AdapterFreeTransferSyntax::new(
+            "1.2.840.10008.1.2.1",
+            "Explicit VR Little Endian",
+            Endianness::Little,
+            true,
+            Codec::None,
+        )
+        .erased()
+    }
+
+    fn tokens() -> Vec<DataToken> {
+        vec![
+            DataToken::SequenceStart {
+                tag: Tag(0x0018, 0x6011),
+                len: Length::UNDEFINED,
+            },
+            DataToken::ItemStart {
+                len: Length::UNDEFINED,
+            },
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0018, 0x6012),
+                VR::US,
+                Length(2),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from(1_u16)),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0020, 0x4000),
+                VR::LT,
+                Length(4),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from("TEST")),
+        ]
+    }
+
+    /// A destination which records the size of each write,
+    /// and is only ready every other poll.
+    #[derive(Default)]
+    struct ChunkRecorder {
+        data: Vec<u8>,
+        writes: Vec<usize>,
+        ready: bool,
+    }
+
+    impl AsyncWrite for ChunkRecorder {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.data.extend_from_slice(buf);
+            self.writes.push(buf.len());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn write_tokens_in_chunks() {
+        let ts = explicit_vr_le();
+        let mut ground_truth = Vec::new();
+        DataSetWriter::with_ts_cs(&mut ground_truth, &ts, SpecificCharacterSet::Default)
+            .unwrap()
+            .write_sequence(tokens())
+            .unwrap();
+        assert_eq!(ground_truth.len(), 58);
+
+        let mut writer = AsyncDataSetWriter::with_ts_cs(
+            ChunkRecorder::default(),
+            &ts,
+            SpecificCharacterSet::Default,
+        )
+        .unwrap()
+        .chunk_size(16);
+        futures_executor::block_on(async {
+            writer.write_sequence(tokens()).await.unwrap();
+            writer.flush().await.unwrap();
+        });
+        let recorder = writer.into_inner();
+        assert_eq!(recorder.data, ground_truth);
+        assert!(recorder.writes.iter().all(|&len| len <= 16));
+    }
+
+    #[test]
+    fn write_token_by_token() {
+        let ts = explicit_vr_le();
+        let mut writer =
+            AsyncDataSetWriter::with_ts_cs(Vec::new(), &ts, SpecificCharacterSet::Default).unwrap();
+        futures_executor::block_on(async {
+            for token in tokens() {
+                writer.write(token).await.unwrap();
+            }
+        });
+        // nothing is held back in memory
+        assert_eq!(writer.get_ref().len(), 58);
+    }
+}
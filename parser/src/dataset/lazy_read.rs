@@ -12,6 +12,12 @@
 //! - skip the value altogether, by reading into a sink;
 //! - copying the bytes of the value into another writer,
 //!   such as a previously allocated buffer.
+//!
+//! Values can also be skipped without reading them at all,
+//! by seeking past them in the source
+//! (see [`LazyDataSetReader::next_deferring`]).
+//! This is useful for indexing the attributes of many files
+//! without paying the cost of reading large values such as pixel data.
 use crate::stateful::decode::{DynStatefulDecoder, Error as DecoderError, StatefulDecode};
 use crate::util::ReadSeek;
 use dicom_core::header::{DataElementHeader, Header, Length, SequenceItemHeader};
@@ -19,7 +25,10 @@ use dicom_core::{Tag, VR};
 use dicom_encoding::text::SpecificCharacterSet;
 use dicom_encoding::transfer_syntax::TransferSyntax;
 use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
-use std::{cmp::Ordering, io::SeekFrom};
+use std::{
+    cmp::Ordering,
+    io::{Seek, SeekFrom},
+};
 
 use super::{LazyDataToken, SeqTokenType};
 
@@ -73,6 +82,12 @@ pub enum Error {
         bytes_read: u64,
         backtrace: Backtrace,
     },
+    #[snafu(display("Could not skip value at {} bytes", bytes_read))]
+    SkipValue {
+        bytes_read: u64,
+        #[snafu(backtrace)]
+        source: DecoderError,
+    },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -90,6 +105,9 @@ struct SeqToken {
     /// The number of bytes the parser has read until it reached the
     /// beginning of the sequence or item value data.
     base_offset: u64,
+    /// The character set in use before the sequence or item started,
+    /// restored once it ends.
+    charset: SpecificCharacterSet,
 }
 
 /// An attached iterator for retrieving DICOM object element markers
@@ -182,7 +200,7 @@ where
                                 token = LazyDataToken::ItemEnd;
                             }
                         }
-                        self.seq_delimiters.pop();
+                        self.pop_sequence_token();
                         return Ok(Some(token));
                     }
                     Ordering::Less => {
@@ -207,9 +225,18 @@ where
             pixel_data,
             len,
             base_offset: self.parser.position(),
+            charset: self.parser.character_set(),
         })
     }
 
+    /// Leave the current sequence or item,
+    /// restoring the character set in use before it started.
+    fn pop_sequence_token(&mut self) {
+        if let Some(token) = self.seq_delimiters.pop() {
+            self.parser.set_character_set(token.charset);
+        }
+    }
+
     /** Advance and retrieve the next DICOM data token.
      *
      * **Note:** For the data set to be successfully parsed,
@@ -258,7 +285,7 @@ where
                         }
                         SequenceItemHeader::ItemDelimiter => {
                             // closed an item
-                            self.seq_delimiters.pop();
+                            self.pop_sequence_token();
                             self.in_sequence = true;
                             // sequences can end after an item delimiter
                             self.delimiter_check_pending = true;
@@ -266,7 +293,7 @@ where
                         }
                         SequenceItemHeader::SequenceDelimiter => {
                             // closed a sequence
-                            self.seq_delimiters.pop();
+                            self.pop_sequence_token();
                             self.in_sequence = false;
                             // items can end after a nested sequence ends
                             self.delimiter_check_pending = true;
@@ -303,11 +330,11 @@ where
                 decoder: &mut self.parser,
             }))
         } else if let Some(header) = self.last_header {
-            if header.is_encapsulated_pixeldata() {
+            if header.len.is_undefined() {
                 self.push_sequence_token(SeqTokenType::Sequence, Length::UNDEFINED, true);
                 self.last_header = None;
 
-                // encapsulated pixel data, expecting offset table
+                // encapsulated pixel data or other fragment sequence
                 match self.parser.decode_item_header() {
                     Ok(header) => match header {
                         SequenceItemHeader::Item { len } => {
@@ -321,8 +348,8 @@ where
                             Some(Ok(LazyDataToken::ItemStart { len }))
                         }
                         SequenceItemHeader::SequenceDelimiter => {
-                            // empty pixel data
-                            self.seq_delimiters.pop();
+                            // no fragments
+                            self.pop_sequence_token();
                             self.in_sequence = false;
                             Some(Ok(LazyDataToken::SequenceEnd))
                         }
@@ -372,7 +399,7 @@ where
                 }) => {
                     self.in_sequence = true;
                     // pop item delimiter
-                    self.seq_delimiters.pop();
+                    self.pop_sequence_token();
                     // sequences can end after this token
                     self.delimiter_check_pending = true;
                     Some(Ok(LazyDataToken::ItemEnd))
@@ -385,6 +412,17 @@ where
                     self.last_header = Some(header);
                     Some(Ok(LazyDataToken::PixelSequenceStart))
                 }
+                Ok(header) if header.is_fragment_sequence() => {
+                    // binary data with undefined length other than pixel data:
+                    // expect a sequence of fragments
+
+                    // save it for the next step
+                    self.last_header = Some(header);
+                    Some(Ok(LazyDataToken::FragmentSequenceStart {
+                        tag: header.tag,
+                        vr: header.vr,
+                    }))
+                }
                 Ok(header) if header.len.is_undefined() => {
                     // treat other undefined length elements
                     // as data set sequences,
@@ -422,11 +460,74 @@ where
     }
 }
 
+impl<S> LazyDataSetReader<S>
+where
+    S: StatefulDecode,
+    S::Reader: Seek,
+{
+    /** Advance and retrieve the next DICOM data token,
+     * skipping over values longer than `threshold` bytes.
+     *
+     * Element and item values which exceed the threshold
+     * are not read from the source:
+     * the reader seeks past them instead,
+     * and yields a [`DeferredValue`](LazyDataToken::DeferredValue)
+     * or [`DeferredItemValue`](LazyDataToken::DeferredItemValue) token
+     * with the position and length of the value,
+     * so that it can be fetched later if needed.
+     * All other tokens are the same as in [`next`](Self::next).
+     */
+    pub fn next_deferring(&mut self, threshold: u32) -> Option<Result<LazyDataToken<&mut S>>> {
+        if self.hard_break || self.delimiter_check_pending || self.in_sequence {
+            return self.next();
+        }
+        let offset = self.parser.position();
+
+        if let Some(SeqToken {
+            typ: SeqTokenType::Item,
+            pixel_data: true,
+            len,
+            ..
+        }) = self.seq_delimiters.last()
+        {
+            match len.get() {
+                Some(len) if len > threshold => {
+                    if let Err(e) = self.parser.skip_bytes_seek(len) {
+                        self.hard_break = true;
+                        return Some(Err(e).context(SkipValueSnafu { bytes_read: offset }));
+                    }
+                    // need to pop item delimiter on the next iteration
+                    self.delimiter_check_pending = true;
+                    Some(Ok(LazyDataToken::DeferredItemValue { len, offset }))
+                }
+                _ => self.next(),
+            }
+        } else {
+            match self.last_header {
+                Some(header) if !header.len.is_undefined() => match header.len.get() {
+                    Some(len) if len > threshold => {
+                        self.last_header = None;
+                        if let Err(e) = self.parser.skip_bytes_seek(len) {
+                            self.hard_break = true;
+                            return Some(Err(e).context(SkipValueSnafu { bytes_read: offset }));
+                        }
+                        // sequences can end after this token
+                        self.delimiter_check_pending = true;
+                        Some(Ok(LazyDataToken::DeferredValue { header, offset }))
+                    }
+                    _ => self.next(),
+                },
+                _ => self.next(),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{LazyDataSetReader, StatefulDecode};
     use crate::{
-        dataset::{DataToken, LazyDataToken},
+        dataset::{DataToken, LazyDataToken, LazyDataTokenRepr},
         StatefulDecoder,
     };
     use dicom_core::value::PrimitiveValue;
@@ -1146,4 +1247,92 @@ mod tests {
             "unexpected number of tokens remaining"
         );
     }
+
+    #[test]
+    fn lazy_read_deferring_large_values() {
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            0x28, 0x00, 0x10, 0x00, // (0028,0010) Rows
+            b'U', b'S', // VR
+            0x02, 0x00, // length: 2
+            0x00, 0x02, // 512
+            // -- 10 --
+            0x29, 0x00, 0x10, 0x10, // (0029,1010) private element
+            b'O', b'B', // VR
+            0x00, 0x00, // reserved
+            0x14, 0x00, 0x00, 0x00, // length: 20
+            // -- 22 --
+            0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55,
+            0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55,
+            // -- 42 --
+            0xe0, 0x7f, 0x10, 0x00, // (7FE0, 0010) PixelData
+            b'O', b'B', // VR
+            0x00, 0x00, // reserved
+            0xff, 0xff, 0xff, 0xff, // length: undefined
+            // -- 54 -- Basic offset table
+            0xfe, 0xff, 0x00, 0xe0, // item start tag
+            0x04, 0x00, 0x00, 0x00, // item length: 4
+            0x00, 0x00, 0x00, 0x00,
+            // -- 66 -- First fragment of pixel data
+            0xfe, 0xff, 0x00, 0xe0, // item start tag
+            0x20, 0x00, 0x00, 0x00, // item length: 32
+            // -- 74 --
+            0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99,
+            0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99,
+            0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99,
+            0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99, 0x99,
+            // -- 106 -- End of pixel data
+            0xfe, 0xff, 0xdd, 0xe0, // sequence end tag
+            0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let parser = StatefulDecoder::new(
+            std::io::Cursor::new(DATA),
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+        );
+        let mut dset_reader = LazyDataSetReader::new(parser);
+
+        let mut tokens = Vec::new();
+        while let Some(token) = dset_reader.next_deferring(16) {
+            let token = token.expect("should parse without an error");
+            tokens.push(token.repr());
+            // values below the threshold still need to be consumed
+            token.skip().unwrap();
+        }
+
+        let rows = DataElementHeader::new(Tag(0x0028, 0x0010), VR::US, Length(2));
+        let private = DataElementHeader::new(Tag(0x0029, 0x1010), VR::OB, Length(20));
+        assert_eq!(
+            tokens,
+            vec![
+                LazyDataTokenRepr::ElementHeader(rows),
+                LazyDataTokenRepr::LazyValue { header: rows },
+                LazyDataTokenRepr::ElementHeader(private),
+                LazyDataTokenRepr::DeferredValue {
+                    header: private,
+                    offset: 22,
+                },
+                LazyDataTokenRepr::PixelSequenceStart,
+                LazyDataTokenRepr::ItemStart { len: Length(4) },
+                LazyDataTokenRepr::LazyItemValue { len: 4 },
+                LazyDataTokenRepr::ItemEnd,
+                LazyDataTokenRepr::ItemStart { len: Length(32) },
+                LazyDataTokenRepr::DeferredItemValue {
+                    len: 32,
+                    offset: 74
+                },
+                LazyDataTokenRepr::ItemEnd,
+                LazyDataTokenRepr::SequenceEnd,
+            ]
+        );
+        assert_eq!(dset_reader.parser.position(), DATA.len() as u64);
+
+        // deferred values can be fetched afterwards
+        dset_reader.parser.seek(74).unwrap();
+        let mut fragment = Vec::new();
+        dset_reader.parser.read_to_vec(32, &mut fragment).unwrap();
+        assert_eq!(fragment, vec![0x99; 32]);
+    }
 }
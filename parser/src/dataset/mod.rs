@@ -6,8 +6,14 @@ use dicom_core::{value::Value, DataElement, Tag};
 use snafu::{OptionExt, ResultExt, Snafu};
 use std::fmt;
 
+pub mod adapt;
+#[cfg(feature = "async")]
+pub mod async_write;
 pub mod lazy_read;
+pub mod push;
 pub mod read;
+pub mod shared;
+pub mod transform;
 pub mod write;
 
 pub use self::read::DataSetReader;
@@ -40,6 +46,13 @@ pub enum DataToken {
     SequenceStart { tag: Tag, len: Length },
     /// The beginning of an encapsulated pixel data element.
     PixelSequenceStart,
+    /// The beginning of a binary element other than pixel data
+    /// with an undefined length,
+    /// the value of which is a sequence of fragments.
+    ///
+    /// Unlike in encapsulated pixel data,
+    /// the first item of the sequence is not an offset table.
+    FragmentSequenceStart { tag: Tag, vr: VR },
     /// The ending delimiter of a sequence or encapsulated pixel data.
     SequenceEnd,
     /// The beginning of a new item in the sequence.
@@ -62,6 +75,15 @@ pub enum DataToken {
     /// for each frame in the sequence of items,
     /// as per PS 3.5, Section A.4.
     OffsetTable(Vec<u32>),
+    /// A primitive data element value in its original encoded form.
+    ///
+    /// The bytes are written as they are,
+    /// without any padding,
+    /// and the length of the preceding element header
+    /// is the exact number of bytes.
+    /// This variant is used to reproduce
+    /// the original encoding of an unmodified element.
+    RawValue(Vec<u8>),
 }
 
 impl fmt::Display for DataToken {
@@ -100,10 +122,15 @@ impl PartialEq<Self> for DataToken {
                     len: len2,
                 },
             ) => tag1 == tag2 && len1.inner_eq(*len2),
+            (
+                FragmentSequenceStart { tag: tag1, vr: vr1 },
+                FragmentSequenceStart { tag: tag2, vr: vr2 },
+            ) => tag1 == tag2 && vr1 == vr2,
             (ItemStart { len: len1 }, ItemStart { len: len2 }) => len1.inner_eq(*len2),
             (PrimitiveValue(v1), PrimitiveValue(v2)) => v1 == v2,
             (ItemValue(v1), ItemValue(v2)) => v1 == v2,
             (OffsetTable(v1), OffsetTable(v2)) => v1 == v2,
+            (RawValue(v1), RawValue(v2)) => v1 == v2,
             (ItemEnd, ItemEnd)
             | (SequenceEnd, SequenceEnd)
             | (PixelSequenceStart, PixelSequenceStart) => true,
@@ -118,6 +145,12 @@ impl From<DataElementHeader> for DataToken {
             (VR::OB, Tag(0x7fe0, 0x0010)) if header.len.is_undefined() => {
                 DataToken::PixelSequenceStart
             }
+            (VR::OB, _) | (VR::OW, _) if header.len.is_undefined() => {
+                DataToken::FragmentSequenceStart {
+                    tag: header.tag,
+                    vr: header.vr,
+                }
+            }
             (VR::SQ, _) => DataToken::SequenceStart {
                 tag: header.tag,
                 len: header.len,
@@ -160,6 +193,13 @@ pub enum LazyDataToken<D> {
     SequenceStart { tag: Tag, len: Length },
     /// The beginning of an encapsulated pixel data element.
     PixelSequenceStart,
+    /// The beginning of a binary element other than pixel data
+    /// with an undefined length,
+    /// the value of which is a sequence of fragments.
+    ///
+    /// Unlike in encapsulated pixel data,
+    /// the first item of the sequence is not an offset table.
+    FragmentSequenceStart { tag: Tag, vr: VR },
     /// The ending delimiter of a sequence or encapsulated pixel data.
     SequenceEnd,
     /// The beginning of a new item in the sequence.
@@ -180,6 +220,22 @@ pub enum LazyDataToken<D> {
         /// the stateful decoder for fetching the bytes of the value
         decoder: D,
     },
+    /// An element value which was skipped over without reading it,
+    /// and can be fetched later from its position in the source
+    DeferredValue {
+        /// the header of the respective value
+        header: DataElementHeader,
+        /// the position of the first byte of the value in the source
+        offset: u64,
+    },
+    /// An item value which was skipped over without reading it,
+    /// and can be fetched later from its position in the source
+    DeferredItemValue {
+        /// the full length of the value, always well defined
+        len: u32,
+        /// the position of the first byte of the value in the source
+        offset: u64,
+    },
 }
 
 impl<D> LazyDataToken<D> {
@@ -200,6 +256,11 @@ impl<D> LazyDataToken<D>
 where
     D: decode::StatefulDecode,
 {
+    /// Skip the value of this token, if any,
+    /// by reading it into a sink.
+    ///
+    /// Deferred values were already skipped,
+    /// so this is a no-op for them.
     pub fn skip(self) -> Result<()> {
         match self {
             LazyDataToken::LazyValue {
@@ -227,12 +288,17 @@ where
     /// If the token represents a lazy element value,
     /// the inner decoder is read
     /// with the given value reading strategy.
+    /// The operation fails if the token represents a deferred value,
+    /// which can no longer be read through the decoder.
     pub fn into_owned_with_strategy(self, strategy: ValueReadStrategy) -> Result<DataToken> {
         match self {
             LazyDataToken::ElementHeader(header) => Ok(DataToken::ElementHeader(header)),
             LazyDataToken::ItemEnd => Ok(DataToken::ItemEnd),
             LazyDataToken::ItemStart { len } => Ok(DataToken::ItemStart { len }),
             LazyDataToken::PixelSequenceStart => Ok(DataToken::PixelSequenceStart),
+            LazyDataToken::FragmentSequenceStart { tag, vr } => {
+                Ok(DataToken::FragmentSequenceStart { tag, vr })
+            }
             LazyDataToken::SequenceEnd => Ok(DataToken::SequenceEnd),
             LazyDataToken::SequenceStart { tag, len } => Ok(DataToken::SequenceStart { tag, len }),
             LazyDataToken::LazyValue {
@@ -260,6 +326,9 @@ where
                     .context(ReadItemValueSnafu)?;
                 Ok(DataToken::ItemValue(data))
             }
+            LazyDataToken::DeferredValue { .. } | LazyDataToken::DeferredItemValue { .. } => {
+                UnexpectedTokenTypeSnafu.fail()
+            }
         }
     }
 
@@ -348,6 +417,9 @@ impl<D> From<LazyDataToken<D>> for LazyDataTokenRepr {
                 LazyDataTokenRepr::SequenceStart { tag, len }
             }
             LazyDataToken::PixelSequenceStart => LazyDataTokenRepr::PixelSequenceStart,
+            LazyDataToken::FragmentSequenceStart { tag, vr } => {
+                LazyDataTokenRepr::FragmentSequenceStart { tag, vr }
+            }
             LazyDataToken::SequenceEnd => LazyDataTokenRepr::SequenceEnd,
             LazyDataToken::ItemStart { len } => LazyDataTokenRepr::ItemStart { len },
             LazyDataToken::ItemEnd => LazyDataTokenRepr::ItemEnd,
@@ -357,6 +429,12 @@ impl<D> From<LazyDataToken<D>> for LazyDataTokenRepr {
             LazyDataToken::LazyItemValue { len, decoder: _ } => {
                 LazyDataTokenRepr::LazyItemValue { len }
             }
+            LazyDataToken::DeferredValue { header, offset } => {
+                LazyDataTokenRepr::DeferredValue { header, offset }
+            }
+            LazyDataToken::DeferredItemValue { len, offset } => {
+                LazyDataTokenRepr::DeferredItemValue { len, offset }
+            }
         }
     }
 }
@@ -369,6 +447,9 @@ impl<D> From<&LazyDataToken<D>> for LazyDataTokenRepr {
                 LazyDataTokenRepr::SequenceStart { tag, len }
             }
             LazyDataToken::PixelSequenceStart => LazyDataTokenRepr::PixelSequenceStart,
+            LazyDataToken::FragmentSequenceStart { tag, vr } => {
+                LazyDataTokenRepr::FragmentSequenceStart { tag, vr }
+            }
             LazyDataToken::SequenceEnd => LazyDataTokenRepr::SequenceEnd,
             LazyDataToken::ItemStart { len } => LazyDataTokenRepr::ItemStart { len },
             LazyDataToken::ItemEnd => LazyDataTokenRepr::ItemEnd,
@@ -378,6 +459,12 @@ impl<D> From<&LazyDataToken<D>> for LazyDataTokenRepr {
             LazyDataToken::LazyItemValue { len, decoder: _ } => {
                 LazyDataTokenRepr::LazyItemValue { len }
             }
+            LazyDataToken::DeferredValue { header, offset } => {
+                LazyDataTokenRepr::DeferredValue { header, offset }
+            }
+            LazyDataToken::DeferredItemValue { len, offset } => {
+                LazyDataTokenRepr::DeferredItemValue { len, offset }
+            }
         }
     }
 }
@@ -392,6 +479,13 @@ pub enum LazyDataTokenRepr {
     SequenceStart { tag: Tag, len: Length },
     /// The beginning of an encapsulated pixel data element.
     PixelSequenceStart,
+    /// The beginning of a binary element other than pixel data
+    /// with an undefined length,
+    /// the value of which is a sequence of fragments.
+    ///
+    /// Unlike in encapsulated pixel data,
+    /// the first item of the sequence is not an offset table.
+    FragmentSequenceStart { tag: Tag, vr: VR },
     /// The ending delimiter of a sequence or encapsulated pixel data.
     SequenceEnd,
     /// The beginning of a new item in the sequence.
@@ -408,6 +502,20 @@ pub enum LazyDataTokenRepr {
         /// the full length of the value, always well defined
         len: u32,
     },
+    /// An element value which was skipped over without reading it
+    DeferredValue {
+        /// the header of the respective value
+        header: DataElementHeader,
+        /// the position of the first byte of the value in the source
+        offset: u64,
+    },
+    /// An item value which was skipped over without reading it
+    DeferredItemValue {
+        /// the full length of the value, always well defined
+        len: u32,
+        /// the position of the first byte of the value in the source
+        offset: u64,
+    },
 }
 
 /// The type of delimiter: sequence or item.
@@ -527,6 +635,18 @@ where
                             Value::Primitive(_) | Value::Sequence { .. } => unreachable!(),
                         }
                     }
+                    DataToken::FragmentSequenceStart { .. } => match elem.into_value() {
+                        Value::PixelSequence { fragments, .. } => {
+                            // other fragment sequences have no offset table
+                            let fragments: dicom_core::value::C<_> =
+                                fragments.into_iter().map(ItemValue).collect();
+                            (
+                                Some(token),
+                                DataElementTokens::PixelDataFragments(fragments.into_tokens()),
+                            )
+                        }
+                        Value::Primitive(_) | Value::Sequence { .. } => unreachable!(),
+                    },
                     _ => (
                         Some(DataToken::ElementHeader(*elem.header())),
                         DataElementTokens::Header(Some(elem)),
@@ -0,0 +1,311 @@
+//! This module contains a push-based reader of DICOM data sets,
+//! which does not perform any I/O by itself.
+//!
+//! Unlike the [`DataSetReader`](super::read::DataSetReader),
+//! which pulls bytes from a blocking [`Read`](std::io::Read) source,
+//! the [`PushDataSetReader`] is given chunks of bytes as they become available,
+//! and produces all data set tokens which can be fully decoded so far.
+//! The remaining bytes are kept until more data is fed.
+//! This allows the data set to be parsed
+//! from buffers managed by an asynchronous runtime,
+//! a network protocol layer,
+//! or a WebAssembly host.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_encoding::text::SpecificCharacterSet;
+//! # use dicom_encoding::transfer_syntax::{AdapterFreeTransferSyntax, Codec, Endianness};
+//! use dicom_parser::dataset::push::PushDataSetReader;
+//!
+//! # let ts = AdapterFreeTransferSyntax::new(
+//! #     "1.2.840.10008.1.2.1",
+//! #     "Explicit VR Little Endian",
+//! #     Endianness::Little,
+//! #     true,
+//! #     Codec::None,
+//! # ).erased();
+//! let mut reader =
+//!     PushDataSetReader::new(&ts, SpecificCharacterSet::Default, Default::default())?;
+//!
+//! // (0010,0010) PatientName, arriving in two chunks
+//! let tokens = reader.feed(&[0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x04, 0x00])?;
+//! // not enough bytes to tell whether the header is complete
+//! assert!(tokens.is_empty());
+//! let tokens = reader.feed(b"Doe ")?;
+//! // element header and value
+//! assert_eq!(tokens.len(), 2);
+//! assert!(reader.finish()?.is_empty());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::dataset::read::{DataSetReader, DataSetReaderOptions, Error as ReadError, NextRead};
+use crate::dataset::DataToken;
+use crate::stateful::decode::{DynStatefulDecoder, StatefulDecode};
+use dicom_encoding::text::SpecificCharacterSet;
+use dicom_encoding::transfer_syntax::{Codec, TransferSyntax};
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Transfer syntax {} cannot be read incrementally", ts))]
+    UnsupportedTransferSyntax {
+        ts: &'static str,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Could not create data set reader"))]
+    CreateReader {
+        #[snafu(backtrace)]
+        source: ReadError,
+    },
+    #[snafu(display("Could not read data set token"))]
+    ReadToken {
+        #[snafu(backtrace)]
+        source: ReadError,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A push-based reader of DICOM data set tokens.
+///
+/// Bytes are given to the reader through [`feed`](Self::feed),
+/// and the end of the data set is declared through [`finish`](Self::finish).
+/// Reading never blocks:
+/// a token is only produced once all of its bytes are available.
+///
+/// Data sets in a transfer syntax
+/// which requires a data set codec (such as deflate)
+/// are not supported.
+pub struct PushDataSetReader {
+    /// the underlying reader, pulling from the buffered bytes
+    reader: DataSetReader<DynStatefulDecoder<VecDeque<u8>>>,
+    /// whether element headers may be in explicit VR
+    explicit_vr: bool,
+}
+
+impl fmt::Debug for PushDataSetReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PushDataSetReader")
+            .field("position", &self.position())
+            .field("buffered_len", &self.buffered_len())
+            .field("explicit_vr", &self.explicit_vr)
+            .finish()
+    }
+}
+
+impl PushDataSetReader {
+    /// Create a new push-based reader
+    /// for a data set in the given transfer syntax,
+    /// using the given initial character set and options.
+    pub fn new(
+        ts: &TransferSyntax,
+        cs: SpecificCharacterSet,
+        options: DataSetReaderOptions,
+    ) -> Result<Self> {
+        ensure!(
+            !matches!(ts.codec(), Codec::Dataset(_)),
+            UnsupportedTransferSyntaxSnafu { ts: ts.name() }
+        );
+        let explicit_vr = ts.explicit_vr() || options.detect_vr_mismatch;
+        let reader = DataSetReader::new_with_ts_cs_options(VecDeque::new(), ts, cs, options)
+            .context(CreateReaderSnafu)?;
+        Ok(PushDataSetReader {
+            reader,
+            explicit_vr,
+        })
+    }
+
+    /// Provide the next chunk of bytes of the data set,
+    /// returning all tokens which could be fully read.
+    ///
+    /// Once an error is returned,
+    /// the reader does not produce any more tokens.
+    pub fn feed(&mut self, data: &[u8]) -> Result<Vec<DataToken>> {
+        self.source().extend(data);
+        self.read_tokens(false)
+    }
+
+    /// Declare the end of the data set,
+    /// returning the remaining tokens.
+    ///
+    /// Fails if the data set ends in the middle of a token.
+    pub fn finish(mut self) -> Result<Vec<DataToken>> {
+        self.read_tokens(true)
+    }
+
+    /// The number of bytes fed to the reader
+    /// which are not part of a token yet.
+    pub fn buffered_len(&self) -> usize {
+        // the reader only holds the source through its decoder
+        self.reader.parser_ref().reader_ref().len()
+    }
+
+    /// The number of bytes of the data set read into tokens so far.
+    pub fn position(&self) -> u64 {
+        self.reader.parser_ref().position()
+    }
+
+    fn source(&mut self) -> &mut VecDeque<u8> {
+        self.reader.parser_mut().reader_mut()
+    }
+
+    fn read_tokens(&mut self, at_end: bool) -> Result<Vec<DataToken>> {
+        let mut tokens = Vec::new();
+        while at_end || self.next_is_available() {
+            match self.reader.next() {
+                Some(token) => tokens.push(token.context(ReadTokenSnafu)?),
+                None => break,
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Check whether the bytes needed for the next token have been fed.
+    fn next_is_available(&self) -> bool {
+        let available = self.buffered_len();
+        match self.reader.next_read() {
+            NextRead::Nothing => true,
+            NextRead::ItemHeader => available >= 8,
+            // some explicit VR headers take 12 bytes,
+            // which may only be known after the end of the data set
+            NextRead::ElementHeader if self.explicit_vr => available >= 12,
+            NextRead::ElementHeader => available >= 8,
+            NextRead::Value(len) => available >= len as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PushDataSetReader;
+    use crate::dataset::read::{DataSetReader, DataSetReaderOptions};
+    use crate::dataset::DataToken;
+    use crate::stateful::decode::StatefulDecoder;
+    use dicom_core::header::{DataElementHeader, Length};
+    use dicom_core::value::PrimitiveValue;
+    use dicom_core::{Tag, VR};
+    use dicom_encoding::decode::basic::LittleEndianBasicDecoder;
+    use dicom_encoding::decode::explicit_le::ExplicitVRLittleEndianDecoder;
+    use dicom_encoding::text::SpecificCharacterSet;
+    use dicom_encoding::transfer_syntax::{
+        AdapterFreeTransferSyntax, Codec, Endianness, TransferSyntax,
+    };
+
+    #[rustfmt::skip]
+    static DATA: &[u8] = &[
+        0x18, 0x00, 0x11, 0x60, // sequence tag: (0018,6011) SequenceOfUltrasoundRegions
+        b'S', b'Q', // VR
+        0x00, 0x00, // reserved
+        0xff, 0xff, 0xff, 0xff, // length: undefined
+        // -- 12 --
+        0xfe, 0xff, 0x00, 0xe0, // item start tag
+        0x14, 0x00, 0x00, 0x00, // item length: 20
+        // -- 20 --
+        0x18, 0x00, 0x12, 0x60, b'U', b'S', 0x02, 0x00, 0x01, 0x00, // (0018, 6012) RegionSpatialformat, len = 2, value = 1
+        // -- 30 --
+        0x18, 0x00, 0x14, 0x60, b'U', b'S', 0x02, 0x00, 0x02, 0x00, // (0018, 6014) RegionDataType, len = 2, value = 2
+        // -- 40 --
+        0xfe, 0xff, 0xdd, 0xe0, 0x00, 0x00, 0x00, 0x00, // sequence end
+        // -- 48 --
+        0x20, 0x00, 0x00, 0x40, b'L', b'T', 0x04, 0x00, // (0020,4000) ImageComments, len = 4
+        b'T', b'E', b'S', b'T', // value = "TEST"
+        // -- 60 --
+        0x20, 0x00, 0x10, 0x40, b'L', b'T', 0x00, 0x00, // (0020,4010) empty, len = 0
+    ];
+
+    fn explicit_vr_le() -> TransferSyntax {
+        AdapterFreeTransferSyntax::new(
+            "1.2.840.10008.1.2.1",
+            "Explicit VR Little Endian",
+            Endianness::Little,
+            true,
+            Codec::None,
+        )
+        .erased()
+    }
+
+    fn reference_tokens() -> Vec<DataToken> {
+        let mut cursor = DATA;
+        let parser = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder,
+            SpecificCharacterSet::Default,
+        );
+        DataSetReader::new(parser, Default::default())
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn push_tokens_in_chunks() {
+        let ground_truth = reference_tokens();
+        assert_eq!(
+            &ground_truth[..3],
+            &[
+                DataToken::SequenceStart {
+                    tag: Tag(0x0018, 0x6011),
+                    len: Length::UNDEFINED,
+                },
+                DataToken::ItemStart { len: Length(20) },
+                DataToken::ElementHeader(DataElementHeader {
+                    tag: Tag(0x0018, 0x6012),
+                    vr: VR::US,
+                    len: Length(2),
+                }),
+            ]
+        );
+        assert_eq!(
+            ground_truth[ground_truth.len() - 3],
+            DataToken::PrimitiveValue(PrimitiveValue::Str("TEST".into())),
+        );
+
+        let ts = explicit_vr_le();
+        for chunk_size in [1, 3, 7, DATA.len()] {
+            let mut reader =
+                PushDataSetReader::new(&ts, SpecificCharacterSet::Default, Default::default())
+                    .unwrap();
+            let mut tokens = Vec::new();
+            let mut fed = 0;
+            for chunk in DATA.chunks(chunk_size) {
+                tokens.extend(reader.feed(chunk).unwrap());
+                fed += chunk.len();
+                assert_eq!(reader.position() + reader.buffered_len() as u64, fed as u64);
+            }
+            // the trailing empty element is only known at the end
+            assert_eq!(reader.buffered_len(), 8);
+            tokens.extend(reader.finish().unwrap());
+            assert_eq!(tokens, ground_truth, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn push_truncated_data_fails_on_finish() {
+        let ts = explicit_vr_le();
+        let mut reader =
+            PushDataSetReader::new(&ts, SpecificCharacterSet::Default, Default::default()).unwrap();
+        // stop in the middle of the "TEST" value
+        let tokens = reader.feed(&DATA[..58]).unwrap();
+        assert_eq!(tokens.last(), Some(&DataToken::SequenceEnd));
+        assert_eq!(reader.position(), 48);
+        assert!(reader.finish().is_err());
+    }
+
+    #[test]
+    fn push_value_over_limit_fails_early() {
+        let ts = explicit_vr_le();
+        let options = DataSetReaderOptions::default().max_value_length(1024);
+        let mut reader =
+            PushDataSetReader::new(&ts, SpecificCharacterSet::Default, options).unwrap();
+        // (7FE0,0010) PixelData claiming about 2 GiB of data
+        #[rustfmt::skip]
+        let header = [
+            0xe0, 0x7f, 0x10, 0x00, b'O', b'B', 0x00, 0x00, 0xf0, 0xff, 0xff, 0x7f,
+        ];
+        // fails without waiting for the value to be fed
+        assert!(reader.feed(&header).is_err());
+    }
+}
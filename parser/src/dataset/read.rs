@@ -4,13 +4,19 @@
 //! The rest of the crate is used to obtain DICOM element headers and values.
 //! At this level, headers and values are treated as tokens which can be used
 //! to form a syntax tree of a full data set.
-use crate::stateful::decode::{DynStatefulDecoder, Error as DecoderError, StatefulDecode};
+use crate::stateful::decode::{
+    DynStatefulDecoder, Error as DecoderError, OddLengthStrategy, StatefulDecode,
+};
 use dicom_core::header::{DataElementHeader, Header, Length, SequenceItemHeader};
 use dicom_core::{PrimitiveValue, Tag, VR};
+use dicom_encoding::decode::auto_le::{
+    AutoVRLittleEndianDecoder, VrDeviationLog, VrEncodingDeviation,
+};
 use dicom_encoding::text::SpecificCharacterSet;
-use dicom_encoding::transfer_syntax::TransferSyntax;
-use snafu::{Backtrace, ResultExt, Snafu};
+use dicom_encoding::transfer_syntax::{Endianness, TransferSyntax};
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
 use std::cmp::Ordering;
+use std::fmt;
 use std::io::Read;
 use std::iter::Iterator;
 
@@ -30,26 +36,36 @@ pub enum Error {
         #[snafu(backtrace)]
         source: DecoderError,
     },
-    #[snafu(display("Could not read item header"))]
+    #[snafu(display("Could not read item header at offset {:#x}", offset))]
     ReadItemHeader {
+        offset: u64,
         #[snafu(backtrace)]
         source: DecoderError,
     },
-    #[snafu(display("Could not read element header"))]
+    #[snafu(display("Could not read element header at offset {:#x}", offset))]
     ReadHeader {
+        offset: u64,
         #[snafu(backtrace)]
         source: DecoderError,
     },
-    #[snafu(display("Could not read {} value bytes for element tagged {}", len, tag))]
+    #[snafu(display(
+        "Could not read {} value bytes for element tagged {} at offset {:#x}",
+        len,
+        tag,
+        offset
+    ))]
     ReadValue {
         len: u32,
         tag: Tag,
+        /// the offset of the element header
+        offset: u64,
         #[snafu(backtrace)]
         source: DecoderError,
     },
-    #[snafu(display("Could not read {} bytes for item value", len))]
+    #[snafu(display("Could not read {} bytes for item value at offset {:#x}", len, offset))]
     ReadItemValue {
         len: u32,
+        offset: u64,
         #[snafu(backtrace)]
         source: DecoderError,
     },
@@ -67,23 +83,233 @@ pub enum Error {
     UnexpectedItemTag { tag: Tag, backtrace: Backtrace },
     /// Undefined pixel item length
     UndefinedItemLength,
+    #[snafu(display(
+        "Value of {} bytes at offset {:#x} exceeds the maximum length of {} bytes",
+        len,
+        offset,
+        max
+    ))]
+    ValueTooLong {
+        len: u32,
+        max: u32,
+        /// the offset of the element or item header
+        offset: u64,
+        backtrace: Backtrace,
+    },
+    #[snafu(display(
+        "Sequence at offset {:#x} exceeds the maximum nesting depth of {}",
+        offset,
+        max
+    ))]
+    NestingTooDeep {
+        max: u32,
+        offset: u64,
+        backtrace: Backtrace,
+    },
+    #[snafu(display(
+        "Value of {} bytes at offset {:#x} exceeds the maximum of {} bytes read in total",
+        len,
+        offset,
+        max
+    ))]
+    TotalLengthExceeded {
+        len: u32,
+        max: u64,
+        /// the offset of the element or item header
+        offset: u64,
+        backtrace: Backtrace,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// A non-fatal deviation from the standard
+/// found by the data set reader.
+///
+/// The reader recovers from these deviations and keeps reading,
+/// so that slightly broken data sets can still be loaded.
+/// Warnings are collected as tokens are read,
+/// and can be retrieved through [`DataSetReader::warnings`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ReadWarning {
+    /// An element value has an odd length,
+    /// and so it is missing its padding.
+    OddValueLength {
+        tag: Tag,
+        len: u32,
+        /// the offset of the element header
+        offset: u64,
+    },
+    /// An element was encoded in a VR encoding
+    /// other than the one declared by the transfer syntax.
+    ///
+    /// These are only detected with
+    /// [`detect_vr_mismatch`](DataSetReaderOptions::detect_vr_mismatch).
+    VrEncodingMismatch {
+        tag: Tag,
+        /// whether the element was found to be in explicit VR
+        /// (in implicit VR otherwise)
+        explicit_vr: bool,
+        /// the offset of the element header
+        offset: u64,
+    },
+    /// An element does not come after the previous element
+    /// in the same data set in ascending tag order.
+    TagOutOfOrder {
+        tag: Tag,
+        /// the tag of the previous element
+        previous: Tag,
+        /// the offset of the element header
+        offset: u64,
+    },
+    /// An element has the same tag
+    /// as a previous element in the same data set.
+    DuplicateTag {
+        tag: Tag,
+        /// the offset of the element header
+        offset: u64,
+    },
+    /// A text value contains characters
+    /// which are not valid in the character set in use.
+    InvalidCharacters {
+        tag: Tag,
+        /// the offset of the element header
+        offset: u64,
+    },
+    /// An item delimitation item was found outside of a sequence,
+    /// and was ignored.
+    UnexpectedItemDelimiter {
+        /// the offset of the delimiter
+        offset: u64,
+    },
+}
+
+impl ReadWarning {
+    /// The offset of the element or delimiter
+    /// in which the deviation was found.
+    pub fn offset(&self) -> u64 {
+        match self {
+            ReadWarning::OddValueLength { offset, .. }
+            | ReadWarning::VrEncodingMismatch { offset, .. }
+            | ReadWarning::TagOutOfOrder { offset, .. }
+            | ReadWarning::DuplicateTag { offset, .. }
+            | ReadWarning::InvalidCharacters { offset, .. }
+            | ReadWarning::UnexpectedItemDelimiter { offset } => *offset,
+        }
+    }
+
+    /// The tag of the element in which the deviation was found, if any.
+    pub fn tag(&self) -> Option<Tag> {
+        match self {
+            ReadWarning::OddValueLength { tag, .. }
+            | ReadWarning::VrEncodingMismatch { tag, .. }
+            | ReadWarning::TagOutOfOrder { tag, .. }
+            | ReadWarning::DuplicateTag { tag, .. }
+            | ReadWarning::InvalidCharacters { tag, .. } => Some(*tag),
+            ReadWarning::UnexpectedItemDelimiter { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ReadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadWarning::OddValueLength { tag, len, offset } => write!(
+                f,
+                "Element {} at offset {:#x} has odd value length {}",
+                tag, offset, len
+            ),
+            ReadWarning::VrEncodingMismatch {
+                tag,
+                explicit_vr,
+                offset,
+            } => write!(
+                f,
+                "Element {} at offset {:#x} is encoded in {} VR",
+                tag,
+                offset,
+                if *explicit_vr { "explicit" } else { "implicit" }
+            ),
+            ReadWarning::TagOutOfOrder {
+                tag,
+                previous,
+                offset,
+            } => write!(
+                f,
+                "Element {} at offset {:#x} comes after element {}",
+                tag, offset, previous
+            ),
+            ReadWarning::DuplicateTag { tag, offset } => write!(
+                f,
+                "Element {} at offset {:#x} repeats a previous element",
+                tag, offset
+            ),
+            ReadWarning::InvalidCharacters { tag, offset } => write!(
+                f,
+                "Element {} at offset {:#x} contains invalid characters",
+                tag, offset
+            ),
+            ReadWarning::UnexpectedItemDelimiter { offset } => write!(
+                f,
+                "Item delimitation item at offset {:#x} outside of a sequence",
+                offset
+            ),
+        }
+    }
+}
+
 /// A reader-specific token representing a sequence or item start.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 struct SeqToken {
     /// Whether it is the start of a sequence or the start of an item.
     typ: SeqTokenType,
     /// The length of the value, as indicated by the starting element,
     /// can be unknown.
     len: Length,
-    /// Whether this sequence token is part of an encapsulated pixel data.
+    /// Whether this sequence token is part of an encapsulated pixel data
+    /// or another sequence of fragments.
     pixel_data: bool,
     /// The number of bytes the parser has read until it reached the
     /// beginning of the sequence or item value data.
     base_offset: u64,
+    /// The character set in use before the sequence or item started,
+    /// restored once it ends.
+    charset: SpecificCharacterSet,
+    /// The tags of the elements read in this item.
+    tags: TagLog,
+}
+
+/// The tags of the elements read so far in a data set or item,
+/// to detect elements out of order and duplicate elements.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct TagLog {
+    /// the tag of the last element read
+    last: Option<Tag>,
+    /// the tags of all elements read, in ascending order
+    seen: Vec<Tag>,
+}
+
+impl TagLog {
+    /// Record the tag of the next element,
+    /// returning the deviation in tag order found, if any.
+    fn record(&mut self, tag: Tag, offset: u64) -> Option<ReadWarning> {
+        let previous = self.last.replace(tag);
+        match self.seen.last() {
+            Some(max) if *max >= tag => match self.seen.binary_search(&tag) {
+                Ok(_) => return Some(ReadWarning::DuplicateTag { tag, offset }),
+                Err(pos) => self.seen.insert(pos, tag),
+            },
+            _ => self.seen.push(tag),
+        }
+        previous
+            .filter(|previous| *previous > tag)
+            .map(|previous| ReadWarning::TagOutOfOrder {
+                tag,
+                previous,
+                offset,
+            })
+    }
 }
 
 /// The value reading strategy for the data set reader.
@@ -126,14 +352,56 @@ impl Default for ValueReadStrategy {
     }
 }
 
+/// A condition for the data set reader to stop reading early,
+/// evaluated on the elements at the root of the data set.
+///
+/// Once the condition is met,
+/// the reader does not produce any more tokens,
+/// leaving the rest of the source unread.
+/// The header of the element which met the condition
+/// is consumed from the source, but its value is not.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum StopCondition {
+    /// Stop before the first element with a tag
+    /// greater than or equal to the given tag.
+    BeforeTag(Tag),
+    /// Stop before the first element in a group
+    /// greater than the given group.
+    AfterGroup(u16),
+}
+
+impl StopCondition {
+    /// Check whether an element with the given tag meets the condition.
+    pub fn is_met(self, tag: Tag) -> bool {
+        match self {
+            StopCondition::BeforeTag(stop_tag) => tag >= stop_tag,
+            StopCondition::AfterGroup(group) => tag.group() > group,
+        }
+    }
+}
+
 /// The set of options for the data set reader.
 #[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
 #[non_exhaustive]
 pub struct DataSetReaderOptions {
     /// the value reading strategy
     pub value_read: ValueReadStrategy,
-    /// the position of the reader as received at building time
+    /// the position of the reader as received at building time,
+    /// from which token offsets and error offsets are counted
     pub base_offset: u64,
+    /// the condition to stop reading early, if any
+    pub stop: Option<StopCondition>,
+    /// how to handle element values with an odd length
+    pub odd_length: OddLengthStrategy,
+    /// the maximum length of a single value in bytes, if any
+    pub max_value_length: Option<u32>,
+    /// the maximum number of nested sequences, if any
+    pub max_nesting_depth: Option<u32>,
+    /// the maximum number of value bytes read in total, if any
+    pub max_total_length: Option<u64>,
+    /// whether to read elements in the other VR encoding
+    /// when they do not make sense in the declared one
+    pub detect_vr_mismatch: bool,
 }
 
 impl DataSetReaderOptions {
@@ -147,6 +415,67 @@ impl DataSetReaderOptions {
         self.base_offset = base_offset;
         self
     }
+    /// Stop reading before the first element at the root of the data set
+    /// with a tag greater than or equal to the given tag.
+    ///
+    /// This allows callers to read only the attributes
+    /// which precede an element such as _Pixel Data_.
+    pub fn stop_before(mut self, tag: Tag) -> Self {
+        self.stop = Some(StopCondition::BeforeTag(tag));
+        self
+    }
+    /// Stop reading before the first element at the root of the data set
+    /// in a group greater than the given group.
+    pub fn stop_after_group(mut self, group: u16) -> Self {
+        self.stop = Some(StopCondition::AfterGroup(group));
+        self
+    }
+    /// Replace the strategy for handling element values with an odd length.
+    ///
+    /// This only applies to readers which create their own decoder,
+    /// such as through [`DataSetReader::new_with_ts_cs_options`].
+    pub fn odd_length(mut self, odd_length: OddLengthStrategy) -> Self {
+        self.odd_length = odd_length;
+        self
+    }
+    /// Fail on element values and item values
+    /// longer than the given number of bytes,
+    /// before reading them.
+    ///
+    /// This protects readers of untrusted data
+    /// from allocating memory based on a crafted length field.
+    pub fn max_value_length(mut self, max: u32) -> Self {
+        self.max_value_length = Some(max);
+        self
+    }
+    /// Fail on sequences nested deeper than the given number of levels.
+    ///
+    /// A sequence at the root of the data set is at level 1,
+    /// and encapsulated pixel data counts as a sequence.
+    pub fn max_nesting_depth(mut self, max: u32) -> Self {
+        self.max_nesting_depth = Some(max);
+        self
+    }
+    /// Fail once the values read from the data set
+    /// would exceed the given number of bytes in total,
+    /// before reading the value which exceeds it.
+    pub fn max_total_length(mut self, max: u64) -> Self {
+        self.max_total_length = Some(max);
+        self
+    }
+    /// Set whether to detect elements encoded in implicit VR
+    /// in a data set declared as explicit VR, and vice versa,
+    /// reading each of them in the encoding they were found in.
+    ///
+    /// Detected elements can be retrieved through
+    /// [`DataSetReader::vr_deviations`].
+    /// This only applies to readers which create their own decoder
+    /// for a little endian transfer syntax,
+    /// such as through [`DataSetReader::new_with_ts_cs_options`].
+    pub fn detect_vr_mismatch(mut self, detect: bool) -> Self {
+        self.detect_vr_mismatch = detect;
+        self
+    }
 }
 
 /// A higher-level reader for retrieving structure in a DICOM data set from an
@@ -170,6 +499,19 @@ pub struct DataSetReader<S> {
     hard_break: bool,
     /// last decoded header
     last_header: Option<DataElementHeader>,
+    /// the offset of the last decoded header
+    last_header_offset: u64,
+    /// the offset of the last token read
+    token_offset: u64,
+    /// the number of value bytes read so far
+    total_length: u64,
+    /// the elements read in a VR encoding other than the declared one,
+    /// not yet turned into warnings
+    vr_deviations: VrDeviationLog,
+    /// the tags of the elements read at the root of the data set
+    tags: TagLog,
+    /// the deviations found so far
+    warnings: Vec<ReadWarning>,
 }
 
 impl<R> DataSetReader<DynStatefulDecoder<R>> {
@@ -204,7 +546,24 @@ impl<R> DataSetReader<DynStatefulDecoder<R>> {
     where
         R: Read,
     {
-        let parser = DynStatefulDecoder::new_with(source, ts, cs, 0).context(CreateDecoderSnafu)?;
+        let (mut parser, vr_deviations) =
+            if options.detect_vr_mismatch && ts.endianness() == Endianness::Little {
+                let decoder = AutoVRLittleEndianDecoder::new(ts.explicit_vr());
+                let vr_deviations = decoder.deviations();
+                let parser = DynStatefulDecoder::new_with_position(
+                    source,
+                    Box::new(decoder),
+                    ts.basic_decoder(),
+                    cs,
+                    options.base_offset,
+                );
+                (parser, vr_deviations)
+            } else {
+                let parser = DynStatefulDecoder::new_with(source, ts, cs, options.base_offset)
+                    .context(CreateDecoderSnafu)?;
+                (parser, VrDeviationLog::default())
+            };
+        parser.set_odd_length_strategy(options.odd_length);
 
         is_stateful_decode(&parser);
 
@@ -217,10 +576,113 @@ impl<R> DataSetReader<DynStatefulDecoder<R>> {
             in_sequence: false,
             hard_break: false,
             last_header: None,
+            last_header_offset: 0,
+            token_offset: 0,
+            total_length: 0,
+            vr_deviations,
+            tags: TagLog::default(),
+            warnings: Vec::new(),
         })
     }
 }
 
+impl<S> DataSetReader<S>
+where
+    S: StatefulDecode,
+{
+    /// Retrieve the next token,
+    /// along with its offset in the source in bytes.
+    ///
+    /// The offset of an element header, item header or delimiter token
+    /// is the position at which its header begins,
+    /// whereas the offset of a value token is where the value begins.
+    /// The end of a sequence or item of defined length
+    /// is at the position where its value ends.
+    /// Offsets are relative to the position
+    /// at which the underlying decoder started.
+    pub fn next_with_offset(&mut self) -> Option<Result<(u64, DataToken)>> {
+        let token = self.next()?;
+        Some(token.map(|token| (self.token_offset, token)))
+    }
+
+    /// Retrieve the offset of the last token read.
+    ///
+    /// See [`next_with_offset`](Self::next_with_offset)
+    /// for the meaning of token offsets.
+    pub fn last_token_offset(&self) -> u64 {
+        self.token_offset
+    }
+
+    /// Determine what needs to be read from the source
+    /// to produce the next token.
+    ///
+    /// Values which exceed the reader's limits
+    /// fail without reading them.
+    pub(crate) fn next_read(&self) -> NextRead {
+        match self.next_read_unchecked() {
+            NextRead::Value(len) if self.exceeds_limits(len) => NextRead::Nothing,
+            next => next,
+        }
+    }
+
+    fn next_read_unchecked(&self) -> NextRead {
+        if self.hard_break {
+            return NextRead::Nothing;
+        }
+        if self.delimiter_check_pending {
+            if let Some(sd) = self.seq_delimiters.last() {
+                if let Some(len) = sd.len.get() {
+                    if sd.base_offset + u64::from(len) <= self.parser.position() {
+                        // a sequence or item ends here
+                        return NextRead::Nothing;
+                    }
+                }
+            }
+        }
+        if self.in_sequence {
+            return NextRead::ItemHeader;
+        }
+        if let Some(SeqToken {
+            typ: SeqTokenType::Item,
+            pixel_data: true,
+            len,
+            ..
+        }) = self.seq_delimiters.last()
+        {
+            return len.get().map_or(NextRead::Nothing, NextRead::Value);
+        }
+        match self.last_header {
+            Some(header) if header.len.is_undefined() => NextRead::ItemHeader,
+            Some(header) => header.len.get().map_or(NextRead::Nothing, NextRead::Value),
+            None => NextRead::ElementHeader,
+        }
+    }
+
+    /// Retrieve a reference to the underlying decoder.
+    pub(crate) fn parser_ref(&self) -> &S {
+        &self.parser
+    }
+
+    /// Retrieve a mutable reference to the underlying decoder.
+    pub(crate) fn parser_mut(&mut self) -> &mut S {
+        &mut self.parser
+    }
+}
+
+/// What the data set reader needs from its source
+/// to produce the next token.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum NextRead {
+    /// Nothing needs to be read
+    Nothing,
+    /// An item header or delimiter
+    ItemHeader,
+    /// An element header
+    ElementHeader,
+    /// A value of the given length
+    Value(u32),
+}
+
 impl<S> DataSetReader<S> {
     /// Create a new iterator with the given stateful decoder and options.
     pub fn new(decoder: S, options: DataSetReaderOptions) -> Self {
@@ -233,8 +695,43 @@ impl<S> DataSetReader<S> {
             in_sequence: false,
             hard_break: false,
             last_header: None,
+            last_header_offset: 0,
+            token_offset: 0,
+            total_length: 0,
+            vr_deviations: VrDeviationLog::default(),
+            tags: TagLog::default(),
+            warnings: Vec::new(),
         }
     }
+
+    /// Retrieve the elements read so far
+    /// in a VR encoding other than the one declared by the transfer syntax.
+    ///
+    /// This is always empty unless the reader was created
+    /// with [`detect_vr_mismatch`](DataSetReaderOptions::detect_vr_mismatch).
+    /// Elements reported in warnings which were already taken
+    /// through [`take_warnings`](Self::take_warnings) are not included.
+    pub fn vr_deviations(&self) -> Vec<VrEncodingDeviation> {
+        self.warnings
+            .iter()
+            .filter_map(|warning| match *warning {
+                ReadWarning::VrEncodingMismatch {
+                    tag, explicit_vr, ..
+                } => Some(VrEncodingDeviation { tag, explicit_vr }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Retrieve the non-fatal deviations found so far.
+    pub fn warnings(&self) -> &[ReadWarning] {
+        &self.warnings
+    }
+
+    /// Remove and return the non-fatal deviations found so far.
+    pub fn take_warnings(&mut self) -> Vec<ReadWarning> {
+        std::mem::take(&mut self.warnings)
+    }
 }
 
 impl<S> Iterator for DataSetReader<S>
@@ -247,6 +744,7 @@ where
         if self.hard_break {
             return None;
         }
+        self.token_offset = self.parser.position();
 
         // item or sequence delimitation logic for explicit lengths
         if self.delimiter_check_pending {
@@ -283,7 +781,7 @@ where
                         }
                         SequenceItemHeader::ItemDelimiter => {
                             // closed an item
-                            self.seq_delimiters.pop();
+                            self.pop_sequence_token();
                             self.in_sequence = true;
                             // sequences can end after an item delimiter
                             self.delimiter_check_pending = true;
@@ -291,7 +789,7 @@ where
                         }
                         SequenceItemHeader::SequenceDelimiter => {
                             // closed a sequence
-                            self.seq_delimiters.pop();
+                            self.pop_sequence_token();
                             self.in_sequence = false;
                             // items can end after a nested sequence ends
                             self.delimiter_check_pending = true;
@@ -301,7 +799,9 @@ where
                 }
                 Err(e) => {
                     self.hard_break = true;
-                    Some(Err(e).context(ReadItemHeaderSnafu))
+                    Some(Err(e).context(ReadItemHeaderSnafu {
+                        offset: self.token_offset,
+                    }))
                 }
             }
         } else if let Some(SeqToken {
@@ -315,6 +815,11 @@ where
                 Some(len) => len as usize,
                 None => return Some(UndefinedItemLengthSnafu.fail()),
             };
+            // the item header precedes the value
+            if let Err(e) = self.check_value_length(len as u32, self.token_offset - 8) {
+                self.hard_break = true;
+                return Some(Err(e));
+            }
 
             if self.offset_table_next {
                 // offset table
@@ -328,7 +833,10 @@ where
                 Some(
                     match self.parser.read_u32_to_vec(len as u32, &mut offset_table) {
                         Ok(()) => Ok(DataToken::OffsetTable(offset_table)),
-                        Err(e) => Err(e).context(ReadItemValueSnafu { len: len as u32 }),
+                        Err(e) => Err(e).context(ReadItemValueSnafu {
+                            len: len as u32,
+                            offset: self.token_offset,
+                        }),
                     },
                 )
             } else {
@@ -341,15 +849,22 @@ where
                     self.parser
                         .read_to_vec(len as u32, &mut value)
                         .map(|_| Ok(DataToken::ItemValue(value)))
-                        .unwrap_or_else(|e| Err(e).context(ReadItemValueSnafu { len: len as u32 })),
+                        .unwrap_or_else(|e| {
+                            Err(e).context(ReadItemValueSnafu {
+                                len: len as u32,
+                                offset: self.token_offset,
+                            })
+                        }),
                 )
             }
         } else if let Some(header) = self.last_header {
-            if header.is_encapsulated_pixeldata() {
+            if header.len.is_undefined() {
                 self.push_sequence_token(SeqTokenType::Sequence, Length::UNDEFINED, true);
                 self.last_header = None;
 
-                // encapsulated pixel data, expecting offset table
+                // encapsulated pixel data expects an offset table,
+                // other fragment sequences only have fragments
+                let offset_table_next = header.is_encapsulated_pixeldata();
                 match self.parser.decode_item_header() {
                     Ok(header) => match header {
                         SequenceItemHeader::Item { len } => {
@@ -360,13 +875,13 @@ where
                             if len == Length(0) {
                                 self.delimiter_check_pending = true;
                             } else {
-                                self.offset_table_next = true;
+                                self.offset_table_next = offset_table_next;
                             }
                             Some(Ok(DataToken::ItemStart { len }))
                         }
                         SequenceItemHeader::SequenceDelimiter => {
-                            // empty pixel data
-                            self.seq_delimiters.pop();
+                            // no fragments
+                            self.pop_sequence_token();
                             self.in_sequence = false;
                             Some(Ok(DataToken::SequenceEnd))
                         }
@@ -377,12 +892,17 @@ where
                     },
                     Err(e) => {
                         self.hard_break = true;
-                        Some(Err(e).context(ReadItemHeaderSnafu))
+                        Some(Err(e).context(ReadItemHeaderSnafu {
+                            offset: self.token_offset,
+                        }))
                     }
                 }
             } else {
                 // a plain element header was read, so a value is expected
-                let value = match self.read_value(&header) {
+                let value = match self
+                    .check_value_length(header.len.0, self.last_header_offset)
+                    .and_then(|_| self.read_value(&header))
+                {
                     Ok(v) => v,
                     Err(e) => {
                         self.hard_break = true;
@@ -392,6 +912,7 @@ where
                 };
 
                 self.last_header = None;
+                self.check_value(&header, &value);
 
                 // sequences can end after this token
                 self.delimiter_check_pending = true;
@@ -400,7 +921,36 @@ where
             }
         } else {
             // a data element header or item delimiter is expected
-            match self.parser.decode_header() {
+            let header = self.parser.decode_header();
+            let stop = matches!(
+                &header,
+                Ok(header) if self.seq_delimiters.is_empty()
+                    && matches!(self.options.stop, Some(stop) if stop.is_met(header.tag))
+            );
+            if let (Ok(header), false) = (&header, stop) {
+                self.check_header(header);
+            }
+            match header {
+                Ok(_) if stop => {
+                    // stop condition met at the root of the data set
+                    self.hard_break = true;
+                    None
+                }
+                Ok(header)
+                    if (header.vr == VR::SQ || header.len.is_undefined())
+                        && self.nesting_limit_reached() =>
+                {
+                    // sequences and encapsulated pixel data
+                    // would go past the maximum depth
+                    self.hard_break = true;
+                    Some(
+                        NestingTooDeepSnafu {
+                            max: self.options.max_nesting_depth.unwrap_or_default(),
+                            offset: self.token_offset,
+                        }
+                        .fail(),
+                    )
+                }
                 Ok(DataElementHeader {
                     tag,
                     vr: VR::SQ,
@@ -425,6 +975,9 @@ where
                         "Item delimitation item outside of a sequence in position {}",
                         self.parser.position()
                     );
+                    self.warnings.push(ReadWarning::UnexpectedItemDelimiter {
+                        offset: self.token_offset,
+                    });
                     // return a new token by calling the method again
                     self.next()
                 }
@@ -434,7 +987,7 @@ where
                 }) => {
                     self.in_sequence = true;
                     // pop item delimiter
-                    self.seq_delimiters.pop();
+                    self.pop_sequence_token();
                     // sequences can end after this token
                     self.delimiter_check_pending = true;
                     Some(Ok(DataToken::ItemEnd))
@@ -445,8 +998,21 @@ where
 
                     // save it for the next step
                     self.last_header = Some(header);
+                    self.last_header_offset = self.token_offset;
                     Some(Ok(DataToken::PixelSequenceStart))
                 }
+                Ok(header) if header.is_fragment_sequence() => {
+                    // binary data with undefined length other than pixel data:
+                    // expect a sequence of fragments without an offset table
+
+                    // save it for the next step
+                    self.last_header = Some(header);
+                    self.last_header_offset = self.token_offset;
+                    Some(Ok(DataToken::FragmentSequenceStart {
+                        tag: header.tag,
+                        vr: header.vr,
+                    }))
+                }
                 Ok(header) if header.len.is_undefined() => {
                     // treat other undefined length elements
                     // as data set sequences,
@@ -461,6 +1027,7 @@ where
                 Ok(header) => {
                     // save it for the next step
                     self.last_header = Some(header);
+                    self.last_header_offset = self.token_offset;
                     Some(Ok(DataToken::ElementHeader(header)))
                 }
                 Err(DecoderError::DecodeElementHeader {
@@ -477,7 +1044,9 @@ where
                 }
                 Err(e) => {
                     self.hard_break = true;
-                    Some(Err(e).context(ReadHeaderSnafu))
+                    Some(Err(e).context(ReadHeaderSnafu {
+                        offset: self.token_offset,
+                    }))
                 }
             }
         }
@@ -507,7 +1076,7 @@ where
                                 token = DataToken::ItemEnd;
                             }
                         }
-                        self.seq_delimiters.pop();
+                        self.pop_sequence_token();
                         return Ok(Some(token));
                     }
                     Ordering::Less => {
@@ -532,9 +1101,120 @@ where
             pixel_data,
             len,
             base_offset: self.parser.position(),
+            charset: self.parser.character_set(),
+            tags: TagLog::default(),
         })
     }
 
+    /// Leave the current sequence or item,
+    /// restoring the character set in use before it started.
+    fn pop_sequence_token(&mut self) {
+        if let Some(token) = self.seq_delimiters.pop() {
+            self.parser.set_character_set(token.charset);
+        }
+    }
+
+    /// Check a value of the given length against the reader's limits
+    /// before reading it,
+    /// adding it to the total length read.
+    fn check_value_length(&mut self, len: u32, offset: u64) -> Result<()> {
+        if let Some(max) = self.options.max_value_length {
+            ensure!(len <= max, ValueTooLongSnafu { len, max, offset });
+        }
+        let total_length = self.total_length + u64::from(len);
+        if let Some(max) = self.options.max_total_length {
+            ensure!(
+                total_length <= max,
+                TotalLengthExceededSnafu { len, max, offset }
+            );
+        }
+        self.total_length = total_length;
+        Ok(())
+    }
+
+    /// Check whether a value of the given length
+    /// would exceed the reader's limits.
+    fn exceeds_limits(&self, len: u32) -> bool {
+        matches!(self.options.max_value_length, Some(max) if len > max)
+            || matches!(
+                self.options.max_total_length,
+                Some(max) if self.total_length + u64::from(len) > max
+            )
+    }
+
+    /// Check whether starting another sequence
+    /// would exceed the maximum nesting depth.
+    fn nesting_limit_reached(&self) -> bool {
+        match self.options.max_nesting_depth {
+            Some(max) => {
+                let depth = self
+                    .seq_delimiters
+                    .iter()
+                    .filter(|token| token.typ == SeqTokenType::Sequence)
+                    .count();
+                depth >= max as usize
+            }
+            None => false,
+        }
+    }
+
+    /// Record the deviations found in the header of an element.
+    fn check_header(&mut self, header: &DataElementHeader) {
+        let offset = self.token_offset;
+        for deviation in self.vr_deviations.take() {
+            self.warnings.push(ReadWarning::VrEncodingMismatch {
+                tag: deviation.tag,
+                explicit_vr: deviation.explicit_vr,
+                offset,
+            });
+        }
+        let tag = header.tag;
+        if tag.group() == 0xFFFE {
+            // delimiters are not elements
+            return;
+        }
+
+        let tags = match self.seq_delimiters.last_mut() {
+            Some(token) => &mut token.tags,
+            None => &mut self.tags,
+        };
+        if let Some(warning) = tags.record(tag, offset) {
+            self.warnings.push(warning);
+        }
+
+        if header.vr != VR::SQ && matches!(header.len.get(), Some(len) if len % 2 == 1) {
+            self.warnings.push(ReadWarning::OddValueLength {
+                tag,
+                len: header.len.0,
+                offset,
+            });
+        }
+    }
+
+    /// Record whether a text value which was just read
+    /// has characters not valid in the character set in use.
+    fn check_value(&mut self, header: &DataElementHeader, value: &PrimitiveValue) {
+        let cs = self.parser.character_set();
+        let is_valid = |c: char| match c {
+            '\u{FFFD}' => false,
+            '\u{1B}' | '\r' | '\n' | '\u{0C}' | '\t' => true,
+            c if c.is_control() => false,
+            // the default repertoire is limited to ASCII
+            c => cs != SpecificCharacterSet::Default || c.is_ascii(),
+        };
+        let is_valid = match value {
+            PrimitiveValue::Str(text) => text.chars().all(is_valid),
+            PrimitiveValue::Strs(texts) => texts.iter().all(|text| text.chars().all(is_valid)),
+            _ => true,
+        };
+        if !is_valid {
+            self.warnings.push(ReadWarning::InvalidCharacters {
+                tag: header.tag,
+                offset: self.last_header_offset,
+            });
+        }
+    }
+
     fn read_value(&mut self, header: &DataElementHeader) -> Result<PrimitiveValue> {
         match self.options.value_read {
             ValueReadStrategy::Interpreted => self.parser.read_value(header),
@@ -544,13 +1224,14 @@ where
         .context(ReadValueSnafu {
             len: header.len.0,
             tag: header.tag,
+            offset: self.last_header_offset,
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{DataSetReader, DataToken, StatefulDecode};
+    use super::{DataSetReader, DataSetReaderOptions, DataToken, StatefulDecode};
     use crate::stateful::decode::StatefulDecoder;
     use dicom_core::header::{DataElementHeader, Length};
     use dicom_core::value::PrimitiveValue;
@@ -1020,6 +1701,55 @@ mod tests {
         validate_dataset_reader_explicit_vr(DATA, ground_truth);
     }
 
+    #[test]
+    fn read_fragment_sequence_outside_pixeldata() {
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            0x09, 0x00, 0x10, 0x10, // (0009, 1010) private element
+            b'O', b'B', // VR
+            0x00, 0x00, // reserved
+            0xff, 0xff, 0xff, 0xff, // length: undefined
+            // -- 12 -- First fragment, not an offset table
+            0xfe, 0xff, 0x00, 0xe0, // item start tag
+            0x04, 0x00, 0x00, 0x00, // item length: 4
+            0x01, 0x02, 0x03, 0x04,
+            // -- 24 -- Second fragment
+            0xfe, 0xff, 0x00, 0xe0, // item start tag
+            0x02, 0x00, 0x00, 0x00, // item length: 2
+            0x05, 0x06,
+            // -- 34 -- End of fragments
+            0xfe, 0xff, 0xdd, 0xe0, // sequence end tag
+            0x00, 0x00, 0x00, 0x00,
+            // -- 42 --
+            0x10, 0x00, 0x10, 0x00, // (0010, 0010) PatientName
+            b'P', b'N', // VR
+            0x04, 0x00, // length: 4
+            b'D', b'o', b'e', b' ',
+        ];
+
+        let ground_truth = vec![
+            DataToken::FragmentSequenceStart {
+                tag: Tag(0x0009, 0x1010),
+                vr: VR::OB,
+            },
+            DataToken::ItemStart { len: Length(4) },
+            DataToken::ItemValue(vec![0x01, 0x02, 0x03, 0x04]),
+            DataToken::ItemEnd,
+            DataToken::ItemStart { len: Length(2) },
+            DataToken::ItemValue(vec![0x05, 0x06]),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+            DataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0010, 0x0010),
+                VR::PN,
+                Length(4),
+            )),
+            DataToken::PrimitiveValue(PrimitiveValue::from("Doe")),
+        ];
+
+        validate_dataset_reader_explicit_vr(DATA, ground_truth);
+    }
+
     #[test]
     fn read_dataset_in_dataset() {
         #[rustfmt::skip]
@@ -1119,4 +1849,420 @@ mod tests {
 
         validate_dataset_reader_implicit_vr(DATA, ground_truth);
     }
+
+    #[test]
+    fn read_character_set_in_items() {
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            // (0008,0005) SpecificCharacterSet: "\ISO 2022 IR 87 "
+            0x08, 0x00, 0x05, 0x00, b'C', b'S', 0x10, 0x00,
+            b'\\', b'I', b'S', b'O', b' ', b'2', b'0', b'2', b'2',
+            b' ', b'I', b'R', b' ', b'8', b'7', b' ',
+            // (0008,1115) ReferencedSeriesSequence, length 40
+            0x08, 0x00, 0x15, 0x11, b'S', b'Q', 0x00, 0x00, 0x28, 0x00, 0x00, 0x00,
+            // Item start, length 32
+            0xfe, 0xff, 0x00, 0xe0, 0x20, 0x00, 0x00, 0x00,
+            // (0008,0005) SpecificCharacterSet: "ISO_IR 192", only for this item
+            0x08, 0x00, 0x05, 0x00, b'C', b'S', 0x0a, 0x00,
+            b'I', b'S', b'O', b'_', b'I', b'R', b' ', b'1', b'9', b'2',
+            // (0010,0010) PatientName: "Jöe^J"
+            0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x06, 0x00,
+            b'J', 0xc3, 0xb6, b'e', b'^', b'J',
+            // (0010,0010) PatientName: "山田\山田", with escape sequences
+            0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x16, 0x00,
+            0x1b, b'$', b'B', b';', b'3', b'E', b'D', 0x1b, b'(', b'B', b'\\',
+            0x1b, b'$', b'B', b';', b'3', b'E', b'D', 0x1b, b'(', b'B', b' ',
+        ];
+
+        let mut cursor = DATA;
+        let parser = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+        );
+        let tokens = DataSetReader::new(parser, Default::default())
+            .collect::<Result<Vec<_>, _>>()
+            .expect("should read tokens without an error");
+        let names: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                DataToken::PrimitiveValue(v @ PrimitiveValue::Strs(_)) => Some(v.to_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "\\ISO 2022 IR 87",
+                "ISO_IR 192",
+                "Jöe^J",
+                // the data set's character set was restored after the item
+                "山田\\山田",
+            ]
+        );
+    }
+
+    #[test]
+    fn read_tokens_with_offsets() {
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            // 0: (0010,0010) PatientName
+            0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x04, 0x00, b'D', b'o', b'e', b' ',
+            // 12: (0010,1100) ReferencedPatientPhotoSequence, length 18
+            0x10, 0x00, 0x00, 0x11, b'S', b'Q', 0x00, 0x00, 0x12, 0x00, 0x00, 0x00,
+            // 24: Item start, length 10
+            0xfe, 0xff, 0x00, 0xe0, 0x0a, 0x00, 0x00, 0x00,
+            // 32: (0020,0010) StudyID
+            0x20, 0x00, 0x10, 0x00, b'S', b'H', 0x02, 0x00, b'1', b' ',
+            // 42: (0020,0011) SeriesNumber, truncated
+            0x20, 0x00, 0x11, 0x00, b'I', b'S', 0x04, 0x00, b'1',
+        ];
+
+        let mut cursor = DATA;
+        let parser = StatefulDecoder::new_with_position(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+            0x100,
+        );
+        let mut dset_reader = DataSetReader::new(parser, Default::default());
+
+        let mut offsets = Vec::new();
+        let err = loop {
+            match dset_reader.next_with_offset() {
+                Some(Ok((offset, _token))) => offsets.push(offset),
+                Some(Err(e)) => break e,
+                None => panic!("should fail on the truncated value"),
+            }
+        };
+        assert_eq!(
+            offsets,
+            vec![
+                0x100, // PatientName header
+                0x108, // PatientName value
+                0x10C, // SequenceStart
+                0x118, // ItemStart
+                0x120, // StudyID header
+                0x128, // StudyID value
+                0x12A, // ItemEnd
+                0x12A, // SequenceEnd
+                0x12A, // SeriesNumber header
+            ]
+        );
+        assert_eq!(dset_reader.last_token_offset(), 0x132);
+
+        // the error reports the offset of the element
+        assert!(matches!(
+            err,
+            super::Error::ReadValue {
+                tag: Tag(0x0020, 0x0011),
+                offset: 0x12A,
+                ..
+            }
+        ));
+        assert!(err.to_string().ends_with("at offset 0x12a"));
+    }
+
+    #[test]
+    fn read_until_stop_condition() {
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            // 0: (0010,0010) PatientName
+            0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x04, 0x00, b'D', b'o', b'e', b' ',
+            // 12: (0010,1100) ReferencedPatientPhotoSequence, length 24
+            0x10, 0x00, 0x00, 0x11, b'S', b'Q', 0x00, 0x00, 0x18, 0x00, 0x00, 0x00,
+            // 24: Item start, length 16
+            0xfe, 0xff, 0x00, 0xe0, 0x10, 0x00, 0x00, 0x00,
+            // 32: (0020,000D) StudyInstanceUID, nested in the sequence
+            0x20, 0x00, 0x0d, 0x00, b'U', b'I', 0x08, 0x00,
+            b'1', b'.', b'2', b'.', b'3', b'.', b'4', 0x00,
+            // 48: (0020,0010) StudyID
+            0x20, 0x00, 0x10, 0x00, b'S', b'H', 0x02, 0x00, b'1', b' ',
+            // 58: (7FE0,0010) PixelData
+            0xe0, 0x7f, 0x10, 0x00, b'O', b'B', 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+            0x01, 0x02, 0x03, 0x04,
+        ];
+
+        let read_tokens = |options: DataSetReaderOptions| -> (Vec<DataToken>, u64) {
+            let mut cursor = DATA;
+            let parser = StatefulDecoder::new(
+                &mut cursor,
+                ExplicitVRLittleEndianDecoder::default(),
+                LittleEndianBasicDecoder::default(),
+                SpecificCharacterSet::Default,
+            );
+            let mut dset_reader = DataSetReader::new(parser, options);
+            let tokens = (&mut dset_reader)
+                .collect::<Result<Vec<_>, _>>()
+                .expect("should read tokens without an error");
+            (tokens, dset_reader.parser.position())
+        };
+
+        // stop before pixel data
+        let options = DataSetReaderOptions::default().stop_before(Tag(0x7FE0, 0x0010));
+        let (tokens, position) = read_tokens(options);
+        assert_eq!(tokens.len(), 10);
+        assert_eq!(
+            tokens.last(),
+            Some(&DataToken::PrimitiveValue(PrimitiveValue::from("1 ")))
+        );
+        // only the header of pixel data was read
+        assert_eq!(position, 70);
+
+        // stop after the patient group,
+        // not affected by elements nested in a sequence
+        let options = DataSetReaderOptions::default().stop_after_group(0x0010);
+        let (tokens, position) = read_tokens(options);
+        assert_eq!(tokens.len(), 8);
+        assert_eq!(tokens.last(), Some(&DataToken::SequenceEnd));
+        assert_eq!(position, 56);
+    }
+
+    #[test]
+    fn read_with_resource_limits() {
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            // 0: (0010,0010) PatientName
+            0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x04, 0x00, b'D', b'o', b'e', b' ',
+            // 12: (0010,1100) ReferencedPatientPhotoSequence, length 24
+            0x10, 0x00, 0x00, 0x11, b'S', b'Q', 0x00, 0x00, 0x18, 0x00, 0x00, 0x00,
+            // 24: Item start, length 16
+            0xfe, 0xff, 0x00, 0xe0, 0x10, 0x00, 0x00, 0x00,
+            // 32: (0020,000D) StudyInstanceUID, nested in the sequence
+            0x20, 0x00, 0x0d, 0x00, b'U', b'I', 0x08, 0x00,
+            b'1', b'.', b'2', b'.', b'3', b'.', b'4', 0x00,
+            // 48: (0020,0010) StudyID
+            0x20, 0x00, 0x10, 0x00, b'S', b'H', 0x02, 0x00, b'1', b' ',
+            // 58: (7FE0,0010) PixelData
+            0xe0, 0x7f, 0x10, 0x00, b'O', b'B', 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+            0x01, 0x02, 0x03, 0x04,
+        ];
+
+        let read_tokens = |options: DataSetReaderOptions| {
+            let mut cursor = DATA;
+            let parser = StatefulDecoder::new(
+                &mut cursor,
+                ExplicitVRLittleEndianDecoder::default(),
+                LittleEndianBasicDecoder::default(),
+                SpecificCharacterSet::Default,
+            );
+            DataSetReader::new(parser, options).collect::<Result<Vec<_>, _>>()
+        };
+
+        // all limits are met
+        let options = DataSetReaderOptions::default()
+            .max_value_length(8)
+            .max_nesting_depth(1)
+            .max_total_length(18);
+        assert_eq!(read_tokens(options).unwrap().len(), 12);
+
+        let options = DataSetReaderOptions::default().max_value_length(4);
+        assert!(matches!(
+            read_tokens(options),
+            Err(super::Error::ValueTooLong {
+                len: 8,
+                max: 4,
+                offset: 32,
+                ..
+            })
+        ));
+
+        let options = DataSetReaderOptions::default().max_nesting_depth(0);
+        assert!(matches!(
+            read_tokens(options),
+            Err(super::Error::NestingTooDeep {
+                max: 0,
+                offset: 12,
+                ..
+            })
+        ));
+
+        let options = DataSetReaderOptions::default().max_total_length(16);
+        assert!(matches!(
+            read_tokens(options),
+            Err(super::Error::TotalLengthExceeded {
+                len: 4,
+                max: 16,
+                offset: 58,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn read_with_warnings() {
+        use super::ReadWarning;
+
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            // 0: (0010,0020) PatientID, odd length 3
+            0x10, 0x00, 0x20, 0x00, b'L', b'O', 0x03, 0x00, b'I', b'D', b'1',
+            // 11: (0010,0010) PatientName, out of order, not in the default repertoire
+            0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x04, 0x00, b'D', 0xe9, b'e', b' ',
+            // 23: item delimitation item outside of a sequence
+            0xfe, 0xff, 0x0d, 0xe0, 0x00, 0x00, 0x00, 0x00,
+            // 31: (0010,1100) ReferencedPatientPhotoSequence, length 18
+            0x10, 0x00, 0x00, 0x11, b'S', b'Q', 0x00, 0x00, 0x12, 0x00, 0x00, 0x00,
+            // 43: Item start, length 10
+            0xfe, 0xff, 0x00, 0xe0, 0x0a, 0x00, 0x00, 0x00,
+            // 51: (0008,0060) Modality, in order within the item
+            0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x02, 0x00, b'M', b'R',
+        ];
+
+        let mut cursor = DATA;
+        let parser = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+        );
+        let mut reader = DataSetReader::new(parser, Default::default());
+        let tokens: Vec<_> = (&mut reader).collect::<Result<_, _>>().unwrap();
+        assert_eq!(tokens.len(), 10);
+        assert_eq!(
+            reader.warnings(),
+            &[
+                ReadWarning::OddValueLength {
+                    tag: Tag(0x0010, 0x0020),
+                    len: 3,
+                    offset: 0,
+                },
+                ReadWarning::TagOutOfOrder {
+                    tag: Tag(0x0010, 0x0010),
+                    previous: Tag(0x0010, 0x0020),
+                    offset: 11,
+                },
+                ReadWarning::InvalidCharacters {
+                    tag: Tag(0x0010, 0x0010),
+                    offset: 11,
+                },
+                ReadWarning::UnexpectedItemDelimiter { offset: 23 },
+            ][..]
+        );
+        assert_eq!(
+            reader.warnings()[1].to_string(),
+            "Element (0010,0010) at offset 0xb comes after element (0010,0020)"
+        );
+
+        assert_eq!(reader.take_warnings().len(), 4);
+        assert!(reader.warnings().is_empty());
+    }
+
+    #[test]
+    fn read_with_duplicate_tags() {
+        use super::ReadWarning;
+
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            // 0: (0008,0060) Modality
+            0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x02, 0x00, b'M', b'R',
+            // 10: (0010,0020) PatientID
+            0x10, 0x00, 0x20, 0x00, b'L', b'O', 0x02, 0x00, b'I', b'D',
+            // 20: (0008,0060) Modality, repeated and out of order
+            0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x02, 0x00, b'C', b'T',
+            // 30: (0008,0050) AccessionNumber, out of order
+            0x08, 0x00, 0x50, 0x00, b'S', b'H', 0x02, 0x00, b'A', b'N',
+            // 40: (0010,0020) PatientID, repeated after an ascending element
+            0x10, 0x00, 0x20, 0x00, b'L', b'O', 0x02, 0x00, b'I', b'D',
+        ];
+
+        let mut cursor = DATA;
+        let parser = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+        );
+        let mut reader = DataSetReader::new(parser, Default::default());
+        let tokens: Vec<_> = (&mut reader).collect::<Result<_, _>>().unwrap();
+        assert_eq!(tokens.len(), 10);
+        assert_eq!(
+            reader.warnings(),
+            &[
+                ReadWarning::DuplicateTag {
+                    tag: Tag(0x0008, 0x0060),
+                    offset: 20,
+                },
+                ReadWarning::TagOutOfOrder {
+                    tag: Tag(0x0008, 0x0050),
+                    previous: Tag(0x0008, 0x0060),
+                    offset: 30,
+                },
+                ReadWarning::DuplicateTag {
+                    tag: Tag(0x0010, 0x0020),
+                    offset: 40,
+                },
+            ][..]
+        );
+        assert_eq!(
+            reader.warnings()[0].to_string(),
+            "Element (0008,0060) at offset 0x14 repeats a previous element"
+        );
+    }
+
+    #[test]
+    fn read_with_vr_mismatch_detection() {
+        use dicom_encoding::decode::auto_le::VrEncodingDeviation;
+        use dicom_encoding::transfer_syntax::{AdapterFreeTransferSyntax, Codec, Endianness};
+
+        #[rustfmt::skip]
+        static DATA: &[u8] = &[
+            // (0008,0060) Modality, CS, length 2
+            0x08, 0x00, 0x60, 0x00, b'C', b'S', 0x02, 0x00, b'M', b'R',
+            // (0010,0010) PatientName, in implicit VR, length 4
+            0x10, 0x00, 0x10, 0x00, 0x04, 0x00, 0x00, 0x00, b'D', b'o', b'e', b' ',
+        ];
+
+        let ts = AdapterFreeTransferSyntax::new(
+            "1.2.840.10008.1.2.1",
+            "Explicit VR Little Endian",
+            Endianness::Little,
+            true,
+            Codec::None,
+        )
+        .erased();
+
+        let options = DataSetReaderOptions::default().detect_vr_mismatch(true);
+        let mut reader = DataSetReader::new_with_ts_cs_options(
+            DATA,
+            &ts,
+            SpecificCharacterSet::Default,
+            options,
+        )
+        .unwrap();
+        let tokens: Vec<_> = (&mut reader).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                DataToken::ElementHeader(DataElementHeader::new(
+                    Tag(0x0008, 0x0060),
+                    VR::CS,
+                    Length(2),
+                )),
+                DataToken::PrimitiveValue(PrimitiveValue::from("MR")),
+                DataToken::ElementHeader(DataElementHeader::new(
+                    Tag(0x0010, 0x0010),
+                    VR::PN,
+                    Length(4),
+                )),
+                DataToken::PrimitiveValue(PrimitiveValue::from("Doe")),
+            ]
+        );
+        assert_eq!(
+            reader.vr_deviations(),
+            vec![VrEncodingDeviation {
+                tag: Tag(0x0010, 0x0010),
+                explicit_vr: false,
+            }]
+        );
+
+        // without detection, the element is misread
+        let reader =
+            DataSetReader::new_with_ts_cs(DATA, &ts, SpecificCharacterSet::Default).unwrap();
+        let tokens: Vec<_> = reader.collect();
+        assert_eq!(tokens.len(), 5);
+        assert!(tokens[4].is_err());
+    }
 }
@@ -0,0 +1,561 @@
+//! This module contains a zero-copy reader of DICOM data sets
+//! which are already fully loaded in memory.
+//!
+//! The data set is held in a [`SharedBytes`] buffer,
+//! a cheaply cloneable and reference counted view over a byte array.
+//! Element values and pixel data fragments are yielded
+//! as slices of that same buffer,
+//! so scanning a data set does not allocate for each value,
+//! and values can be kept around after the reader is dropped.
+//! This is useful when processing many files which are already in memory,
+//! or data sets received over the network.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_encoding::text::SpecificCharacterSet;
+//! # use dicom_encoding::transfer_syntax::{AdapterFreeTransferSyntax, Codec, Endianness};
+//! use dicom_parser::dataset::shared::{SharedBytes, SharedDataSetReader, SharedDataToken};
+//!
+//! # let ts = AdapterFreeTransferSyntax::new(
+//! #     "1.2.840.10008.1.2.1",
+//! #     "Explicit VR Little Endian",
+//! #     Endianness::Little,
+//! #     true,
+//! #     Codec::None,
+//! # ).erased();
+//! // (0010,0010) PatientName
+//! let data = SharedBytes::from(vec![
+//!     0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x04, 0x00, b'D', b'o', b'e', b' ',
+//! ]);
+//! let mut reader =
+//!     SharedDataSetReader::new_with_ts_cs(data.clone(), &ts, SpecificCharacterSet::Default)?;
+//!
+//! assert!(matches!(reader.next(), Some(Ok(SharedDataToken::ElementHeader(_)))));
+//! match reader.next() {
+//!     Some(Ok(SharedDataToken::RawValue { data: value, .. })) => {
+//!         assert_eq!(&value[..], b"Doe ");
+//!         // the value points to the original buffer
+//!         assert_eq!(value.as_ptr(), data[8..].as_ptr());
+//!     }
+//!     token => panic!("unexpected token {:?}", token),
+//! }
+//! assert!(reader.next().is_none());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::dataset::lazy_read::{Error as LazyReadError, LazyDataSetReader};
+use crate::dataset::LazyDataToken;
+use crate::stateful::decode::{DynStatefulDecoder, StatefulDecode};
+use dicom_core::header::{DataElementHeader, Length, VR};
+use dicom_core::Tag;
+use dicom_encoding::text::SpecificCharacterSet;
+use dicom_encoding::transfer_syntax::TransferSyntax;
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+use std::fmt;
+use std::io::Cursor;
+use std::ops::{Bound, Deref, RangeBounds};
+use std::sync::Arc;
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(display("Could not create data set reader"))]
+    CreateReader {
+        #[snafu(backtrace)]
+        source: LazyReadError,
+    },
+    #[snafu(display("Could not read data set token"))]
+    ReadToken {
+        #[snafu(backtrace)]
+        source: LazyReadError,
+    },
+    #[snafu(display(
+        "Value of {} bytes at {:#x} goes past the end of the buffer",
+        len,
+        offset
+    ))]
+    UnexpectedEndOfBuffer {
+        offset: u64,
+        len: u32,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A cheaply cloneable, immutable view over a shared byte array.
+///
+/// Cloning and slicing only increment a reference count,
+/// without copying the bytes.
+#[derive(Clone)]
+pub struct SharedBytes {
+    data: Arc<[u8]>,
+    start: usize,
+    end: usize,
+}
+
+impl SharedBytes {
+    /// Create a view over the whole given byte array.
+    pub fn new(data: impl Into<Arc<[u8]>>) -> Self {
+        let data = data.into();
+        let end = data.len();
+        SharedBytes {
+            data,
+            start: 0,
+            end,
+        }
+    }
+
+    /// The number of bytes in view.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Check whether there are no bytes in view.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Retrieve the bytes in view.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[self.start..self.end]
+    }
+
+    /// Create a view over a range of these bytes,
+    /// sharing the same byte array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for length {}",
+            start,
+            end,
+            self.len()
+        );
+        SharedBytes {
+            data: Arc::clone(&self.data),
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+
+    /// Copy the bytes in view into a new vector.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_slice().to_vec()
+    }
+}
+
+impl Deref for SharedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl fmt::Debug for SharedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedBytes")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl PartialEq for SharedBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for SharedBytes {}
+
+impl From<Vec<u8>> for SharedBytes {
+    fn from(data: Vec<u8>) -> Self {
+        SharedBytes::new(data)
+    }
+}
+
+impl From<Arc<[u8]>> for SharedBytes {
+    fn from(data: Arc<[u8]>) -> Self {
+        SharedBytes::new(data)
+    }
+}
+
+impl From<&[u8]> for SharedBytes {
+    fn from(data: &[u8]) -> Self {
+        SharedBytes::new(data)
+    }
+}
+
+/// A token of a DICOM data set read from a shared buffer,
+/// in which values are slices of the buffer.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum SharedDataToken {
+    /// A data header of a primitive value.
+    ElementHeader(DataElementHeader),
+    /// The beginning of a sequence element.
+    SequenceStart { tag: Tag, len: Length },
+    /// The beginning of an encapsulated pixel data element.
+    PixelSequenceStart,
+    /// The beginning of a binary element other than pixel data
+    /// with an undefined length,
+    /// the value of which is a sequence of fragments.
+    FragmentSequenceStart { tag: Tag, vr: VR },
+    /// The ending delimiter of a sequence or encapsulated pixel data.
+    SequenceEnd,
+    /// The beginning of a new item in the sequence.
+    ItemStart { len: Length },
+    /// The ending delimiter of an item.
+    ItemEnd,
+    /// The encoded bytes of an element value,
+    /// following the header of the same element.
+    RawValue {
+        /// the header of the respective value
+        header: DataElementHeader,
+        /// the bytes of the value, as encoded in the data set
+        data: SharedBytes,
+    },
+    /// The bytes of an item value,
+    /// such as a pixel data fragment or the basic offset table.
+    RawItemValue(SharedBytes),
+}
+
+/// This implementation treats undefined lengths as equal.
+impl PartialEq<Self> for SharedDataToken {
+    fn eq(&self, other: &Self) -> bool {
+        use SharedDataToken::*;
+        let header_eq = |h1: &DataElementHeader, h2: &DataElementHeader| {
+            h1.tag == h2.tag && h1.vr == h2.vr && h1.len.inner_eq(h2.len)
+        };
+        match (self, other) {
+            (ElementHeader(h1), ElementHeader(h2)) => header_eq(h1, h2),
+            (
+                SequenceStart {
+                    tag: tag1,
+                    len: len1,
+                },
+                SequenceStart {
+                    tag: tag2,
+                    len: len2,
+                },
+            ) => tag1 == tag2 && len1.inner_eq(*len2),
+            (
+                FragmentSequenceStart { tag: tag1, vr: vr1 },
+                FragmentSequenceStart { tag: tag2, vr: vr2 },
+            ) => tag1 == tag2 && vr1 == vr2,
+            (ItemStart { len: len1 }, ItemStart { len: len2 }) => len1.inner_eq(*len2),
+            (
+                RawValue {
+                    header: h1,
+                    data: d1,
+                },
+                RawValue {
+                    header: h2,
+                    data: d2,
+                },
+            ) => header_eq(h1, h2) && d1 == d2,
+            (RawItemValue(d1), RawItemValue(d2)) => d1 == d2,
+            (ItemEnd, ItemEnd)
+            | (SequenceEnd, SequenceEnd)
+            | (PixelSequenceStart, PixelSequenceStart) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A reader of DICOM data set tokens from a shared buffer
+/// which does not copy element values.
+///
+/// Values are not decoded:
+/// they are yielded as they are encoded in the data set,
+/// in the byte order of the transfer syntax.
+/// Changes of _Specific Character Set_ are not tracked either,
+/// so textual values should be decoded by the consumer.
+pub struct SharedDataSetReader {
+    /// the buffer holding the full data set
+    buffer: SharedBytes,
+    /// the underlying lazy reader over the same buffer
+    reader: LazyDataSetReader<DynStatefulDecoder<Cursor<SharedBytes>>>,
+    /// fuse the iteration process if true
+    hard_break: bool,
+}
+
+impl fmt::Debug for SharedDataSetReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedDataSetReader")
+            .field("buffer", &self.buffer)
+            .finish()
+    }
+}
+
+impl SharedDataSetReader {
+    /// Create a new reader over the given buffer,
+    /// which holds a data set in the given transfer syntax
+    /// starting at its first byte.
+    pub fn new_with_ts_cs(
+        buffer: impl Into<SharedBytes>,
+        ts: &TransferSyntax,
+        cs: SpecificCharacterSet,
+    ) -> Result<Self> {
+        let buffer = buffer.into();
+        let reader = LazyDataSetReader::new_with_ts_cs(Cursor::new(buffer.clone()), ts, cs)
+            .context(CreateReaderSnafu)?;
+        Ok(SharedDataSetReader {
+            buffer,
+            reader,
+            hard_break: false,
+        })
+    }
+
+    /// Retrieve the buffer holding the data set.
+    pub fn buffer(&self) -> &SharedBytes {
+        &self.buffer
+    }
+
+    /// Advance and retrieve the next DICOM data token.
+    ///
+    /// The reader stops producing tokens after the first error.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<SharedDataToken>> {
+        if self.hard_break {
+            return None;
+        }
+        let buffer = &self.buffer;
+        // values are skipped over, and then sliced from the buffer
+        let token = match self.reader.next_deferring(0)? {
+            Ok(token) => token,
+            Err(e) => {
+                self.hard_break = true;
+                return Some(Err(e).context(ReadTokenSnafu));
+            }
+        };
+        let token = match token {
+            LazyDataToken::ElementHeader(header) => Ok(SharedDataToken::ElementHeader(header)),
+            LazyDataToken::SequenceStart { tag, len } => {
+                Ok(SharedDataToken::SequenceStart { tag, len })
+            }
+            LazyDataToken::PixelSequenceStart => Ok(SharedDataToken::PixelSequenceStart),
+            LazyDataToken::FragmentSequenceStart { tag, vr } => {
+                Ok(SharedDataToken::FragmentSequenceStart { tag, vr })
+            }
+            LazyDataToken::SequenceEnd => Ok(SharedDataToken::SequenceEnd),
+            LazyDataToken::ItemStart { len } => Ok(SharedDataToken::ItemStart { len }),
+            LazyDataToken::ItemEnd => Ok(SharedDataToken::ItemEnd),
+            LazyDataToken::DeferredValue { header, offset } => {
+                slice_value(buffer, offset, header.len.0)
+                    .map(|data| SharedDataToken::RawValue { header, data })
+            }
+            LazyDataToken::DeferredItemValue { len, offset } => {
+                slice_value(buffer, offset, len).map(SharedDataToken::RawItemValue)
+            }
+            // empty values are not deferred
+            LazyDataToken::LazyValue { header, decoder } => {
+                skip_value(buffer, decoder, header.len.0)
+                    .map(|data| SharedDataToken::RawValue { header, data })
+            }
+            LazyDataToken::LazyItemValue { len, decoder } => {
+                skip_value(buffer, decoder, len).map(SharedDataToken::RawItemValue)
+            }
+        };
+        self.hard_break = token.is_err();
+        Some(token)
+    }
+}
+
+impl Iterator for SharedDataSetReader {
+    type Item = Result<SharedDataToken>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        SharedDataSetReader::next(self)
+    }
+}
+
+/// Slice a value out of the buffer,
+/// checking that it does not go past its end.
+fn slice_value(buffer: &SharedBytes, offset: u64, len: u32) -> Result<SharedBytes> {
+    let end = offset + u64::from(len);
+    ensure!(
+        end <= buffer.len() as u64,
+        UnexpectedEndOfBufferSnafu { offset, len }
+    );
+    Ok(buffer.slice(offset as usize..end as usize))
+}
+
+/// Read past a value which was not deferred,
+/// slicing it from the buffer.
+fn skip_value<D>(buffer: &SharedBytes, mut decoder: D, len: u32) -> Result<SharedBytes>
+where
+    D: StatefulDecode,
+{
+    let offset = decoder.position();
+    decoder
+        .skip_bytes(len)
+        .map_err(|source| LazyReadError::SkipValue {
+            bytes_read: offset,
+            source,
+        })
+        .context(ReadTokenSnafu)?;
+    slice_value(buffer, offset, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, SharedBytes, SharedDataSetReader, SharedDataToken};
+    use dicom_core::header::{DataElementHeader, Length};
+    use dicom_core::{Tag, VR};
+    use dicom_encoding::text::SpecificCharacterSet;
+    use dicom_encoding::transfer_syntax::{
+        AdapterFreeTransferSyntax, Codec, Endianness, TransferSyntax,
+    };
+
+    #[rustfmt::skip]
+    static DATA: &[u8] = &[
+        0x18, 0x00, 0x11, 0x60, // sequence tag: (0018,6011) SequenceOfUltrasoundRegions
+        b'S', b'Q', // VR
+        0x00, 0x00, // reserved
+        0xff, 0xff, 0xff, 0xff, // length: undefined
+        // -- 12 --
+        0xfe, 0xff, 0x00, 0xe0, // item start tag
+        0x0a, 0x00, 0x00, 0x00, // item length: 10
+        // -- 20 --
+        0x18, 0x00, 0x12, 0x60, b'U', b'S', 0x02, 0x00, 0x01, 0x00, // (0018, 6012) RegionSpatialformat, len = 2, value = 1
+        // -- 30 --
+        0xfe, 0xff, 0xdd, 0xe0, 0x00, 0x00, 0x00, 0x00, // sequence end
+        // -- 38 --
+        0x20, 0x00, 0x10, 0x40, b'L', b'T', 0x00, 0x00, // (0020,4010) empty, len = 0
+        // -- 46 --
+        0xe0, 0x7f, 0x10, 0x00, // (7FE0,0010) PixelData
+        b'O', b'B', // VR
+        0x00, 0x00, // reserved
+        0xff, 0xff, 0xff, 0xff, // length: undefined
+        // -- 58 --
+        0xfe, 0xff, 0x00, 0xe0, 0x00, 0x00, 0x00, 0x00, // basic offset table, empty
+        // -- 66 --
+        0xfe, 0xff, 0x00, 0xe0, 0x04, 0x00, 0x00, 0x00, // fragment, len = 4
+        0x99, 0x88, 0x77, 0x66,
+        // -- 78 --
+        0xfe, 0xff, 0xdd, 0xe0, 0x00, 0x00, 0x00, 0x00, // sequence end
+    ];
+
+    fn explicit_vr_le() -> TransferSyntax {
+        AdapterFreeTransferSyntax::new(
+            "1.2.840.10008.1.2.1",
+            "Explicit VR Little Endian",
+            Endianness::Little,
+            true,
+            Codec::None,
+        )
+        .erased()
+    }
+
+    #[test]
+    fn read_values_as_shared_slices() {
+        let buffer = SharedBytes::from(DATA);
+        let reader = SharedDataSetReader::new_with_ts_cs(
+            buffer.clone(),
+            &explicit_vr_le(),
+            SpecificCharacterSet::Default,
+        )
+        .unwrap();
+        let tokens: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+
+        let us_header = DataElementHeader::new(Tag(0x0018, 0x6012), VR::US, Length(2));
+        let lt_header = DataElementHeader::new(Tag(0x0020, 0x4010), VR::LT, Length(0));
+        assert_eq!(
+            tokens,
+            vec![
+                SharedDataToken::SequenceStart {
+                    tag: Tag(0x0018, 0x6011),
+                    len: Length::UNDEFINED,
+                },
+                SharedDataToken::ItemStart { len: Length(10) },
+                SharedDataToken::ElementHeader(us_header),
+                SharedDataToken::RawValue {
+                    header: us_header,
+                    data: buffer.slice(28..30),
+                },
+                SharedDataToken::ItemEnd,
+                SharedDataToken::SequenceEnd,
+                SharedDataToken::ElementHeader(lt_header),
+                SharedDataToken::RawValue {
+                    header: lt_header,
+                    data: SharedBytes::from(vec![]),
+                },
+                SharedDataToken::PixelSequenceStart,
+                // empty basic offset table
+                SharedDataToken::ItemStart { len: Length(0) },
+                SharedDataToken::ItemEnd,
+                SharedDataToken::ItemStart { len: Length(4) },
+                SharedDataToken::RawItemValue(buffer.slice(74..78)),
+                SharedDataToken::ItemEnd,
+                SharedDataToken::SequenceEnd,
+            ]
+        );
+
+        // values point into the original buffer
+        match &tokens[12] {
+            SharedDataToken::RawItemValue(data) => {
+                assert_eq!(&data[..], &[0x99, 0x88, 0x77, 0x66]);
+                assert_eq!(data.as_ptr(), buffer[74..].as_ptr());
+            }
+            token => panic!("unexpected token {:?}", token),
+        }
+    }
+
+    #[test]
+    fn truncated_value_is_an_error() {
+        let mut reader = SharedDataSetReader::new_with_ts_cs(
+            &DATA[..76],
+            &explicit_vr_le(),
+            SpecificCharacterSet::Default,
+        )
+        .unwrap();
+        let err = loop {
+            match reader.next() {
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => break e,
+                None => panic!("truncated data set should fail"),
+            }
+        };
+        assert!(matches!(
+            err,
+            Error::UnexpectedEndOfBuffer {
+                offset: 74,
+                len: 4,
+                ..
+            }
+        ));
+        // the reader is fused after an error
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn slice_shared_bytes() {
+        let bytes = SharedBytes::from(vec![1, 2, 3, 4, 5]);
+        let slice = bytes.slice(1..4);
+        assert_eq!(&slice[..], &[2, 3, 4]);
+        assert_eq!(&slice.slice(1..)[..], &[3, 4]);
+        assert_eq!(slice.slice(..=0).to_vec(), vec![2]);
+        assert!(slice.slice(3..).is_empty());
+    }
+}
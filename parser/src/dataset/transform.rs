@@ -0,0 +1,520 @@
+//! Transformation of data set token streams.
+//!
+//! The adapters in this module sit between
+//! a source of data set tokens, such as a [`DataSetReader`](super::DataSetReader),
+//! and a consumer, such as a [`DataSetWriter`](super::DataSetWriter),
+//! so that a data set can be streamed through
+//! while dropping, rewriting, or adding elements,
+//! without building an in-memory object.
+//! They are available to any iterator of `Result<DataToken, E>`
+//! through the [`TokenTransform`] extension trait,
+//! and errors from the source are passed through as is.
+//!
+//! Removing elements or changing values
+//! would invalidate the defined lengths of the sequences and items
+//! which contain them,
+//! so [`filter_elements`](TokenTransform::filter_elements)
+//! and [`map_values`](TokenTransform::map_values)
+//! turn them into sequences and items of undefined length.
+//! Element lengths are recalculated by the data set writer.
+//!
+//! # Example
+//!
+//! Strip all private attributes from a data set
+//! while copying it to another writer:
+//!
+//! ```no_run
+//! # use dicom_encoding::text::SpecificCharacterSet;
+//! # use dicom_encoding::TransferSyntax;
+//! use dicom_parser::dataset::transform::TokenTransform;
+//! use dicom_parser::dataset::{DataSetReader, DataSetWriter};
+//! # fn run(source: &[u8], ts: &TransferSyntax) -> Result<(), Box<dyn std::error::Error>> {
+//! let reader = DataSetReader::new_with_ts_cs(source, ts, SpecificCharacterSet::Default)?;
+//! let mut out = Vec::new();
+//! let mut writer = DataSetWriter::with_ts_cs(&mut out, ts, SpecificCharacterSet::Default)?;
+//! for token in reader.filter_elements(|header| header.tag.group() % 2 == 0) {
+//!     writer.write(token?)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use super::{DataToken, IntoTokens};
+use dicom_core::header::{DataElementHeader, HasLength, Header, Length};
+use dicom_core::value::PrimitiveValue;
+use dicom_core::{DataElement, Tag, VR};
+use std::collections::VecDeque;
+use std::iter::Fuse;
+
+/// Extension trait for transforming a stream of data set tokens.
+pub trait TokenTransform<E>: Iterator<Item = Result<DataToken, E>> + Sized {
+    /// Keep only the elements for which the given predicate returns `true`,
+    /// at any depth of the data set.
+    ///
+    /// The predicate is called with the header of each element,
+    /// including sequences (with VR `SQ`)
+    /// and encapsulated pixel data (with an undefined length).
+    /// Rejected elements are dropped as a whole,
+    /// along with their value or nested items.
+    fn filter_elements<F>(self, predicate: F) -> FilterElements<Self, F>
+    where
+        F: FnMut(&DataElementHeader) -> bool,
+    {
+        FilterElements {
+            inner: self,
+            predicate,
+            lengths: UndefineLengths::default(),
+        }
+    }
+
+    /// Replace the value of each primitive element, at any depth,
+    /// with the output of the given function.
+    ///
+    /// The function is called with the header of the element
+    /// and its original value.
+    /// The length of the element header which precedes the value
+    /// is updated to the length of the new value.
+    fn map_values<F>(self, f: F) -> MapValues<Self, F>
+    where
+        F: FnMut(&DataElementHeader, PrimitiveValue) -> PrimitiveValue,
+    {
+        MapValues {
+            inner: self,
+            f,
+            pending: None,
+            lengths: UndefineLengths::default(),
+        }
+    }
+
+    /// Insert the given elements at the root of the data set,
+    /// in ascending tag order.
+    ///
+    /// An element in the data set with the same tag as an injected element
+    /// is replaced by it.
+    /// This assumes that the root elements of the stream
+    /// are in ascending tag order, as in any valid data set.
+    fn inject<I, P>(self, elements: Vec<DataElement<I, P>>) -> Inject<Self>
+    where
+        I: IntoTokens + HasLength,
+        P: AsRef<[u8]>,
+    {
+        let mut elements: Vec<_> = elements
+            .into_iter()
+            .map(|e| (e.tag(), e.into_tokens().collect()))
+            .collect();
+        // descending tag order, so that the next one to inject is at the end
+        elements.sort_by(|(a, _), (b, _)| b.cmp(a));
+        Inject {
+            inner: self.fuse(),
+            elements,
+            depth: 0,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<T, E> TokenTransform<E> for T where T: Iterator<Item = Result<DataToken, E>> {}
+
+/// An iterator adapter which drops elements from a token stream,
+/// created by [`TokenTransform::filter_elements`].
+#[derive(Debug)]
+pub struct FilterElements<T, F> {
+    inner: T,
+    predicate: F,
+    lengths: UndefineLengths,
+}
+
+impl<T, F, E> Iterator for FilterElements<T, F>
+where
+    T: Iterator<Item = Result<DataToken, E>>,
+    F: FnMut(&DataElementHeader) -> bool,
+{
+    type Item = Result<DataToken, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let token = match self.inner.next()? {
+                Ok(token) => token,
+                Err(e) => return Some(Err(e)),
+            };
+            match element_header(&token) {
+                Some(header) if !(self.predicate)(&header) => {
+                    if let Err(e) = skip_element(&mut self.inner, &token) {
+                        return Some(Err(e));
+                    }
+                }
+                _ => return Some(Ok(self.lengths.apply(token))),
+            }
+        }
+    }
+}
+
+/// An iterator adapter which rewrites the primitive values of a token stream,
+/// created by [`TokenTransform::map_values`].
+#[derive(Debug)]
+pub struct MapValues<T, F> {
+    inner: T,
+    f: F,
+    /// the token to yield after an element header
+    pending: Option<DataToken>,
+    lengths: UndefineLengths,
+}
+
+impl<T, F, E> Iterator for MapValues<T, F>
+where
+    T: Iterator<Item = Result<DataToken, E>>,
+    F: FnMut(&DataElementHeader, PrimitiveValue) -> PrimitiveValue,
+{
+    type Item = Result<DataToken, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.take() {
+            return Some(Ok(token));
+        }
+        let token = match self.inner.next()? {
+            Ok(token) => token,
+            Err(e) => return Some(Err(e)),
+        };
+        match token {
+            DataToken::ElementHeader(header) => {
+                // fetch the value right away to update the header
+                let value = match self.inner.next() {
+                    Some(Ok(DataToken::PrimitiveValue(value))) => value,
+                    Some(Ok(token)) => {
+                        // not a primitive value, leave it as is
+                        self.pending = Some(self.lengths.apply(token));
+                        return Some(Ok(DataToken::ElementHeader(header)));
+                    }
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => return Some(Ok(DataToken::ElementHeader(header))),
+                };
+                let value = (self.f)(&header, value);
+                let len = Length(value.calculate_byte_len() as u32);
+                self.pending = Some(DataToken::PrimitiveValue(value));
+                Some(Ok(DataToken::ElementHeader(DataElementHeader {
+                    len,
+                    ..header
+                })))
+            }
+            token => Some(Ok(self.lengths.apply(token))),
+        }
+    }
+}
+
+/// An iterator adapter which inserts elements at the root of a token stream,
+/// created by [`TokenTransform::inject`].
+#[derive(Debug)]
+pub struct Inject<T> {
+    inner: Fuse<T>,
+    /// the tokens of the elements left to inject, in descending tag order
+    elements: Vec<(Tag, Vec<DataToken>)>,
+    /// the current sequence depth of the stream
+    depth: u32,
+    /// tokens ready to be yielded
+    queue: VecDeque<DataToken>,
+}
+
+impl<T, E> Iterator for Inject<T>
+where
+    T: Iterator<Item = Result<DataToken, E>>,
+{
+    type Item = Result<DataToken, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.queue.pop_front() {
+            return Some(Ok(token));
+        }
+        let token = match self.inner.next() {
+            Some(Ok(token)) => token,
+            Some(Err(e)) => return Some(Err(e)),
+            None => {
+                // inject the remaining elements at the end
+                let (_, tokens) = self.elements.pop()?;
+                self.queue.extend(tokens);
+                return self.next();
+            }
+        };
+
+        if self.depth == 0 {
+            if let Some(header) = element_header(&token) {
+                let mut replaced = false;
+                while let Some((tag, _)) = self.elements.last() {
+                    if *tag > header.tag {
+                        break;
+                    }
+                    replaced |= *tag == header.tag;
+                    let (_, tokens) = self.elements.pop().unwrap();
+                    self.queue.extend(tokens);
+                }
+                if replaced {
+                    if let Err(e) = skip_element(&mut self.inner, &token) {
+                        return Some(Err(e));
+                    }
+                    return self.next();
+                }
+            }
+        }
+
+        match token {
+            DataToken::SequenceStart { .. }
+            | DataToken::PixelSequenceStart
+            | DataToken::FragmentSequenceStart { .. } => self.depth += 1,
+            DataToken::SequenceEnd => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        if self.queue.is_empty() {
+            Some(Ok(token))
+        } else {
+            self.queue.push_back(token);
+            self.next()
+        }
+    }
+}
+
+/// Retrieve the header of the element which starts with the given token,
+/// if it starts an element.
+fn element_header(token: &DataToken) -> Option<DataElementHeader> {
+    match token {
+        DataToken::ElementHeader(header) => Some(*header),
+        DataToken::SequenceStart { tag, len } => Some(DataElementHeader::new(*tag, VR::SQ, *len)),
+        DataToken::PixelSequenceStart => Some(DataElementHeader::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OB,
+            Length::UNDEFINED,
+        )),
+        DataToken::FragmentSequenceStart { tag, vr } => {
+            Some(DataElementHeader::new(*tag, *vr, Length::UNDEFINED))
+        }
+        _ => None,
+    }
+}
+
+/// Consume the rest of the element which starts with the given token.
+fn skip_element<T, E>(tokens: &mut T, first: &DataToken) -> Result<(), E>
+where
+    T: Iterator<Item = Result<DataToken, E>>,
+{
+    match first {
+        DataToken::ElementHeader(_) => {
+            // the value follows
+            tokens.next().transpose()?;
+        }
+        DataToken::SequenceStart { .. }
+        | DataToken::PixelSequenceStart
+        | DataToken::FragmentSequenceStart { .. } => {
+            let mut depth = 1;
+            while depth > 0 {
+                match tokens.next().transpose()? {
+                    Some(DataToken::SequenceStart { .. })
+                    | Some(DataToken::PixelSequenceStart)
+                    | Some(DataToken::FragmentSequenceStart { .. }) => depth += 1,
+                    Some(DataToken::SequenceEnd) => depth -= 1,
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Turns the lengths of sequences and items into undefined lengths,
+/// except for the items of encapsulated pixel data
+/// and other fragment sequences.
+#[derive(Debug, Default)]
+struct UndefineLengths {
+    in_pixel_sequence: bool,
+}
+
+impl UndefineLengths {
+    fn apply(&mut self, token: DataToken) -> DataToken {
+        match token {
+            DataToken::SequenceStart { tag, .. } => DataToken::SequenceStart {
+                tag,
+                len: Length::UNDEFINED,
+            },
+            DataToken::ItemStart { .. } if !self.in_pixel_sequence => DataToken::ItemStart {
+                len: Length::UNDEFINED,
+            },
+            DataToken::PixelSequenceStart | DataToken::FragmentSequenceStart { .. } => {
+                self.in_pixel_sequence = true;
+                token
+            }
+            DataToken::SequenceEnd => {
+                // fragment sequences do not nest
+                self.in_pixel_sequence = false;
+                token
+            }
+            token => token,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenTransform;
+    use crate::dataset::{DataSetReader, DataSetWriter, DataToken};
+    use crate::stateful::decode::StatefulDecoder;
+    use dicom_core::header::{DataElementHeader, Length};
+    use dicom_core::value::PrimitiveValue;
+    use dicom_core::{DataElement, Tag, VR};
+    use dicom_encoding::decode::basic::LittleEndianBasicDecoder;
+    use dicom_encoding::decode::explicit_le::ExplicitVRLittleEndianDecoder;
+    use dicom_encoding::encode::explicit_le::ExplicitVRLittleEndianEncoder;
+    use dicom_encoding::encode::EncoderFor;
+    use dicom_encoding::text::SpecificCharacterSet;
+
+    #[rustfmt::skip]
+    static DATA: &[u8] = &[
+        // 0: (0009,0010) private creator
+        0x09, 0x00, 0x10, 0x00, b'L', b'O', 0x04, 0x00, b'A', b'C', b'M', b'E',
+        // 12: (0010,0010) PatientName
+        0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x04, 0x00, b'D', b'o', b'e', b' ',
+        // 24: (0010,1100) ReferencedPatientPhotoSequence, length 30
+        0x10, 0x00, 0x00, 0x11, b'S', b'Q', 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00,
+        // 36: Item start, length 22
+        0xfe, 0xff, 0x00, 0xe0, 0x16, 0x00, 0x00, 0x00,
+        // 44: (0020,0010) StudyID
+        0x20, 0x00, 0x10, 0x00, b'S', b'H', 0x02, 0x00, b'1', b' ',
+        // 54: (0029,0010) private creator, nested in the sequence
+        0x29, 0x00, 0x10, 0x00, b'L', b'O', 0x04, 0x00, b'A', b'C', b'M', b'E',
+        // 66: (0011,0010) private creator
+        0x11, 0x00, 0x10, 0x00, b'L', b'O', 0x04, 0x00, b'A', b'C', b'M', b'E',
+    ];
+
+    fn read_tokens(
+        data: &[u8],
+    ) -> impl Iterator<Item = Result<DataToken, crate::dataset::read::Error>> + '_ {
+        let parser = StatefulDecoder::new(
+            data,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder,
+            SpecificCharacterSet::Default,
+        );
+        DataSetReader::new(parser, Default::default())
+    }
+
+    fn header(tag: Tag, vr: VR, len: u32) -> DataToken {
+        DataToken::ElementHeader(DataElementHeader::new(tag, vr, Length(len)))
+    }
+
+    #[test]
+    fn filter_private_elements_and_rewrite() {
+        let mut out = Vec::new();
+        let encoder = EncoderFor::new(ExplicitVRLittleEndianEncoder::default());
+        let mut writer = DataSetWriter::new(&mut out, encoder);
+        for token in read_tokens(DATA).filter_elements(|header| header.tag.group() % 2 == 0) {
+            writer.write(token.unwrap()).unwrap();
+        }
+
+        let tokens: Vec<_> = read_tokens(&out).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                header(Tag(0x0010, 0x0010), VR::PN, 4),
+                DataToken::PrimitiveValue(PrimitiveValue::from("Doe")),
+                DataToken::SequenceStart {
+                    tag: Tag(0x0010, 0x1100),
+                    len: Length::UNDEFINED,
+                },
+                DataToken::ItemStart {
+                    len: Length::UNDEFINED,
+                },
+                header(Tag(0x0020, 0x0010), VR::SH, 2),
+                DataToken::PrimitiveValue(PrimitiveValue::from("1")),
+                DataToken::ItemEnd,
+                DataToken::SequenceEnd,
+            ]
+        );
+        // the sequence and item are now delimited
+        assert_eq!(out.len(), 12 + 12 + 8 + 10 + 8 + 8);
+    }
+
+    #[test]
+    fn map_values_updates_headers() {
+        let tokens: Vec<_> = read_tokens(DATA)
+            .map_values(|header, value| match header.tag {
+                Tag(0x0010, 0x0010) => PrimitiveValue::from("Doe^John"),
+                _ => value,
+            })
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tokens.len(), 14);
+        assert_eq!(tokens[2], header(Tag(0x0010, 0x0010), VR::PN, 8));
+        assert_eq!(
+            tokens[3],
+            DataToken::PrimitiveValue(PrimitiveValue::from("Doe^John"))
+        );
+        // nested values are mapped as well
+        assert_eq!(
+            tokens[5],
+            DataToken::ItemStart {
+                len: Length::UNDEFINED
+            }
+        );
+        assert_eq!(tokens[6], header(Tag(0x0020, 0x0010), VR::SH, 2));
+    }
+
+    #[test]
+    fn inject_elements_at_root() {
+        let elements: Vec<DataElement> = vec![
+            // after the end of the data set
+            DataElement::new(Tag(0x0020, 0x000D), VR::UI, PrimitiveValue::from("1.2.3")),
+            // replaces the existing patient name
+            DataElement::new(Tag(0x0010, 0x0010), VR::PN, PrimitiveValue::from("Anon")),
+            // before the patient sex, which is not in the data set
+            DataElement::new(Tag(0x0010, 0x0040), VR::CS, PrimitiveValue::from("O")),
+        ];
+        let tokens: Vec<_> = read_tokens(DATA)
+            .inject(elements)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let tags: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                DataToken::ElementHeader(header) => Some(header.tag),
+                DataToken::SequenceStart { tag, .. } => Some(*tag),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                Tag(0x0009, 0x0010),
+                Tag(0x0010, 0x0010),
+                Tag(0x0010, 0x0040),
+                Tag(0x0010, 0x1100),
+                Tag(0x0020, 0x0010),
+                Tag(0x0029, 0x0010),
+                Tag(0x0011, 0x0010),
+                Tag(0x0020, 0x000D),
+            ]
+        );
+        assert_eq!(
+            tokens[3],
+            DataToken::PrimitiveValue(PrimitiveValue::from("Anon"))
+        );
+        // lengths are left untouched
+        assert_eq!(
+            tokens[6],
+            DataToken::SequenceStart {
+                tag: Tag(0x0010, 0x1100),
+                len: Length(30),
+            }
+        );
+    }
+
+    #[test]
+    fn transform_passes_errors_through() {
+        let tokens = vec![
+            Ok(header(Tag(0x0009, 0x0010), VR::LO, 4)),
+            Err("broken value"),
+        ];
+        let mut filtered = tokens
+            .into_iter()
+            .filter_elements(|header| header.tag.group() % 2 == 0);
+        assert_eq!(filtered.next(), Some(Err("broken value")));
+        assert_eq!(filtered.next(), None);
+    }
+}
@@ -122,6 +122,12 @@ impl<W, E, T> DataSetWriter<W, E, T> {
             last_de: None,
         }
     }
+
+    /// Retrieve a mutable reference to the underlying encoder.
+    #[cfg(feature = "async")]
+    pub(crate) fn printer_mut(&mut self) -> &mut StatefulEncoder<W, E, T> {
+        &mut self.printer
+    }
 }
 
 impl<W, E> DataSetWriter<W, E>
@@ -190,7 +196,8 @@ where
                 // postpone writing the header until the value token is given
                 Ok(())
             }
-            token @ DataToken::PixelSequenceStart => {
+            token @ DataToken::PixelSequenceStart
+            | token @ DataToken::FragmentSequenceStart { .. } => {
                 self.seq_tokens.push(SeqToken {
                     typ: SeqTokenType::Sequence,
                     len: Length::UNDEFINED,
@@ -199,7 +206,8 @@ where
             }
             token @ DataToken::ItemValue(_)
             | token @ DataToken::PrimitiveValue(_)
-            | token @ DataToken::OffsetTable(_) => self.write_impl(&token),
+            | token @ DataToken::OffsetTable(_)
+            | token @ DataToken::RawValue(_) => self.write_impl(&token),
         }
     }
 
@@ -221,14 +229,24 @@ where
                     .encode_element_header(DataElementHeader::new(tag, VR::OB, Length::UNDEFINED))
                     .context(WriteHeaderSnafu { tag })?;
             }
+            DataToken::FragmentSequenceStart { tag, vr } => {
+                self.printer
+                    .encode_element_header(DataElementHeader::new(*tag, *vr, Length::UNDEFINED))
+                    .context(WriteHeaderSnafu { tag: *tag })?;
+            }
             DataToken::SequenceEnd => {
                 self.printer
                     .encode_sequence_delimiter()
                     .context(WriteSequenceDelimiterSnafu)?;
             }
             DataToken::ItemStart { len } => {
+                // item values with an odd length are padded on write
+                let len = match len.get() {
+                    Some(len) if len % 2 == 1 => len + 1,
+                    _ => len.0,
+                };
                 self.printer
-                    .encode_item_header(len.0)
+                    .encode_item_header(len)
                     .context(WriteItemHeaderSnafu)?;
             }
             DataToken::ItemEnd => {
@@ -254,6 +272,15 @@ where
             DataToken::ItemValue(data) => {
                 self.printer.write_bytes(data).context(WriteValueSnafu)?;
             }
+            DataToken::RawValue(data) => {
+                let last_de = self.last_de.take().with_context(|| UnexpectedTokenSnafu {
+                    token: token.clone(),
+                })?;
+
+                self.printer
+                    .encode_raw_element(&last_de, data)
+                    .context(WriteValueSnafu)?;
+            }
         }
         Ok(())
     }
@@ -385,6 +412,37 @@ mod tests {
         validate_dataset_writer(tokens, GROUND_TRUTH);
     }
 
+    #[test]
+    fn write_raw_value_as_is() {
+        let tokens = vec![
+            DataToken::ElementHeader(DataElementHeader {
+                tag: Tag(0x0009, 0x1001),
+                vr: VR::UN,
+                len: Length(3),
+            }),
+            DataToken::RawValue(b"xyz".to_vec()),
+            DataToken::ElementHeader(DataElementHeader {
+                tag: Tag(0x0010, 0x0010),
+                vr: VR::PN,
+                len: Length(4),
+            }),
+            DataToken::RawValue(b"Doe\0".to_vec()),
+        ];
+
+        #[rustfmt::skip]
+        static GROUND_TRUTH: &[u8] = &[
+            0x09, 0x00, 0x01, 0x10, // (0009,1001)
+            b'U', b'N', 0x00, 0x00, // VR, reserved
+            0x03, 0x00, 0x00, 0x00, // length: 3, without padding
+            b'x', b'y', b'z',
+            0x10, 0x00, 0x10, 0x00, // (0010,0010) PatientName
+            b'P', b'N', 0x04, 0x00, // VR, length: 4
+            b'D', b'o', b'e', 0x00, // value, padding kept
+        ];
+
+        validate_dataset_writer(tokens, GROUND_TRUTH);
+    }
+
     #[test]
     fn write_sequence_implicit() {
         let tokens = vec![
@@ -579,4 +637,110 @@ mod tests {
 
         validate_dataset_writer(tokens, GROUND_TRUTH);
     }
+
+    #[test]
+    fn write_odd_length_item_value() {
+        let tokens = vec![
+            DataToken::PixelSequenceStart,
+            DataToken::ItemStart { len: Length(0) },
+            DataToken::ItemEnd,
+            DataToken::ItemStart { len: Length(3) },
+            DataToken::ItemValue(vec![0x99; 3]),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+        ];
+
+        #[rustfmt::skip]
+        static GROUND_TRUTH: &[u8] = &[
+            0xe0, 0x7f, 0x10, 0x00, // (7FE0, 0010) PixelData
+            b'O', b'B', // VR
+            0x00, 0x00, // reserved
+            0xff, 0xff, 0xff, 0xff, // length: undefined
+            // -- 12 -- Basic offset table
+            0xfe, 0xff, 0x00, 0xe0, // item start tag
+            0x00, 0x00, 0x00, 0x00, // item length: 0
+            // -- 20 -- First fragment of pixel data
+            0xfe, 0xff, 0x00, 0xe0, // item start tag
+            0x04, 0x00, 0x00, 0x00, // item length: 4 (padded)
+            0x99, 0x99, 0x99, 0x00,
+            // -- 32 -- End of pixel data
+            0xfe, 0xff, 0xdd, 0xe0, // sequence end tag
+            0x00, 0x00, 0x00, 0x00,
+        ];
+
+        validate_dataset_writer(tokens, GROUND_TRUTH);
+    }
+
+    #[test]
+    fn write_explicit_vr_big_endian() {
+        use crate::dataset::read::DataSetReader;
+        use crate::stateful::decode::StatefulDecoder;
+        use dicom_encoding::decode::basic::BigEndianBasicDecoder;
+        use dicom_encoding::decode::explicit_be::ExplicitVRBigEndianDecoder;
+        use dicom_encoding::encode::explicit_be::ExplicitVRBigEndianEncoder;
+        use dicom_encoding::text::SpecificCharacterSet;
+
+        let tokens = vec![
+            DataToken::SequenceStart {
+                tag: Tag(0x0018, 0x6011),
+                len: Length::UNDEFINED,
+            },
+            DataToken::ItemStart {
+                len: Length::UNDEFINED,
+            },
+            DataToken::ElementHeader(DataElementHeader {
+                tag: Tag(0x0018, 0x6012),
+                vr: VR::US,
+                len: Length(2),
+            }),
+            DataToken::PrimitiveValue(PrimitiveValue::U16([1].as_ref().into())),
+            DataToken::ItemEnd,
+            DataToken::SequenceEnd,
+            DataToken::ElementHeader(DataElementHeader {
+                tag: Tag(0x0020, 0x4000),
+                vr: VR::LT,
+                len: Length(4),
+            }),
+            DataToken::PrimitiveValue(PrimitiveValue::Str("TEST".into())),
+        ];
+
+        #[rustfmt::skip]
+        static GROUND_TRUTH: &[u8] = &[
+            0x00, 0x18, 0x60, 0x11, // sequence tag: (0018,6011) SequenceOfUltrasoundRegions
+            b'S', b'Q', // VR
+            0x00, 0x00, // reserved
+            0xff, 0xff, 0xff, 0xff, // length: undefined
+            // -- 12 --
+            0xff, 0xfe, 0xe0, 0x00, // item start tag
+            0xff, 0xff, 0xff, 0xff, // item length: undefined
+            // -- 20 --
+            0x00, 0x18, 0x60, 0x12, b'U', b'S', 0x00, 0x02, 0x00, 0x01, // (0018, 6012) RegionSpatialformat, len = 2, value = 1
+            // -- 30 --
+            0xff, 0xfe, 0xe0, 0x0d, 0x00, 0x00, 0x00, 0x00, // item end
+            // -- 38 --
+            0xff, 0xfe, 0xe0, 0xdd, 0x00, 0x00, 0x00, 0x00, // sequence end
+            // -- 46 --
+            0x00, 0x20, 0x40, 0x00, b'L', b'T', 0x00, 0x04, // (0020,4000) ImageComments, len = 4
+            b'T', b'E', b'S', b'T', // value = "TEST"
+        ];
+
+        let mut raw_out: Vec<u8> = vec![];
+        let encoder = EncoderFor::new(ExplicitVRBigEndianEncoder::default());
+        let mut dset_writer = DataSetWriter::new(&mut raw_out, encoder);
+        dset_writer.write_sequence(tokens.clone()).unwrap();
+        assert_eq!(raw_out, GROUND_TRUTH);
+
+        // the data set can be read back
+        let mut cursor = &raw_out[..];
+        let parser = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRBigEndianDecoder::default(),
+            BigEndianBasicDecoder,
+            SpecificCharacterSet::Default,
+        );
+        let read_tokens = DataSetReader::new(parser, Default::default())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read_tokens, tokens);
+    }
 }
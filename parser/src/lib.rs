@@ -6,6 +6,14 @@
 //!
 //! For the time being, all APIs are based on synchronous I/O.
 //!
+//! This crate does not enable the default features of its DICOM dependencies.
+//! Together with `dicom-core` and `dicom-transfer-syntax-registry`
+//! without default features,
+//! it can be built without `chrono` and without pixel data codecs.
+//! The standard data dictionary is still included through `dicom-encoding`,
+//! since decoding implicit VR data sets relies on it
+//! to resolve value representations.
+//!
 //! For a more intuitive, object-oriented API, please see the `dicom-object`
 //! crate.
 pub mod dataset;
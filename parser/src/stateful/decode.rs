@@ -2,12 +2,11 @@
 //! which also supports text decoding.
 
 use crate::util::n_times;
-use chrono::FixedOffset;
 use dicom_core::header::{DataElementHeader, HasLength, Length, SequenceItemHeader, Tag, VR};
 use dicom_core::value::deserialize::{
     parse_date_partial, parse_datetime_partial, parse_time_partial,
 };
-use dicom_core::value::PrimitiveValue;
+use dicom_core::value::{FixedOffset, PrimitiveValue};
 use dicom_encoding::decode::basic::{BasicDecoder, LittleEndianBasicDecoder};
 use dicom_encoding::decode::explicit_le::ExplicitVRLittleEndianDecoder;
 use dicom_encoding::decode::{BasicDecode, DecodeFrom};
@@ -17,7 +16,7 @@ use dicom_encoding::text::{
 };
 use dicom_encoding::transfer_syntax::{DynDecoder, TransferSyntax};
 use smallvec::smallvec;
-use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
 use std::io::Read;
 use std::iter::Iterator;
 use std::{fmt::Debug, io::Seek, io::SeekFrom};
@@ -51,6 +50,19 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Odd value length {} of element tagged {} at position {}",
+        len,
+        tag,
+        position
+    ))]
+    OddValueLength {
+        tag: Tag,
+        len: u32,
+        position: u64,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Could not decode element header at position {}", position))]
     DecodeElementHeader {
         position: u64,
@@ -146,8 +158,8 @@ pub trait StatefulDecode {
     /// value. When reading values in text form, a conversion to a more
     /// maleable type is attempted. Namely, numbers in text form (IS, DS) are
     /// converted to the corresponding binary number types, and date/time
-    /// instances are decoded into binary date/time objects of types defined in
-    /// the `chrono` crate. To avoid this conversion, see
+    /// instances are decoded into the partial precision date/time types
+    /// defined in `dicom_core`. To avoid this conversion, see
     /// `read_value_preserved`.
     ///
     /// # Errors
@@ -202,10 +214,29 @@ pub trait StatefulDecode {
     where
         Self::Reader: Seek;
 
+    /// Skip the given number of bytes by seeking the reader forward,
+    /// without reading them.
+    ///
+    /// Unlike [`seek`](StatefulDecode::seek),
+    /// the number of bytes read is updated
+    /// as if the bytes had been read.
+    fn skip_bytes_seek(&mut self, length: u32) -> Result<()>
+    where
+        Self::Reader: Seek;
+
     /// Retrieve the known position of the inner reader source.
     /// If the stateful decoder was constructed at the beginning of the reader,
     /// this equals to the number of bytes read so far.
     fn position(&self) -> u64;
+
+    /// Retrieve the specific character set currently used to decode text.
+    fn character_set(&self) -> SpecificCharacterSet;
+
+    /// Replace the specific character set used to decode text.
+    ///
+    /// The decoder also switches character sets by itself
+    /// whenever it reads a _Specific Character Set_ element.
+    fn set_character_set(&mut self, charset: SpecificCharacterSet);
 }
 
 /// Alias for a dynamically resolved DICOM stateful decoder. Although the data
@@ -216,6 +247,49 @@ pub type DynStatefulDecoder<S> = StatefulDecoder<DynDecoder<S>, S>;
 /// The initial capacity of the `DicomParser` buffer.
 const PARSER_BUFFER_CAPACITY: usize = 2048;
 
+/// The strategy for handling element values with an odd length.
+///
+/// DICOM values must have an even length,
+/// but some generators emit odd value lengths without padding.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum OddLengthStrategy {
+    /// Accept values with an odd length,
+    /// reading exactly the number of bytes declared in the header.
+    #[default]
+    Accept,
+    /// Fail with an error when a value with an odd length is found.
+    Fail,
+}
+
+/// A report of the progress of a stateful decoder,
+/// given to its progress hook.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct DecodeProgress {
+    /// the position of the decoder in the source,
+    /// which is the number of bytes consumed
+    /// unless a base position was assumed
+    pub position: u64,
+    /// the tag of the last data element header read
+    pub tag: Tag,
+}
+
+/// The callback of a stateful decoder for reporting its progress.
+#[derive(Default)]
+struct ProgressHook {
+    callback: Option<Box<dyn FnMut(DecodeProgress) + Send>>,
+    tag: Option<Tag>,
+}
+
+impl Debug for ProgressHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressHook")
+            .field("callback", &self.callback.as_ref().map(|_| ".."))
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
 /// A stateful abstraction for the full DICOM content reading process.
 /// This type encapsulates the necessary codecs in order
 /// to be as autonomous as possible in the DICOM content reading
@@ -234,6 +308,10 @@ pub struct StatefulDecoder<D, S, BD = BasicDecoder, TC = SpecificCharacterSet> {
     buffer: Vec<u8>,
     /// the assumed position of the reader source
     position: u64,
+    /// how to handle values with an odd length
+    odd_length: OddLengthStrategy,
+    /// the progress reporting callback, if any
+    progress: ProgressHook,
 }
 
 impl<S> StatefulDecoder<DynDecoder<S>, S> {
@@ -294,6 +372,8 @@ where
             dt_utc_offset: FixedOffset::east_opt(0).unwrap(),
             buffer: Vec::with_capacity(PARSER_BUFFER_CAPACITY),
             position: 0,
+            odd_length: OddLengthStrategy::default(),
+            progress: ProgressHook::default(),
         }
     }
 }
@@ -325,7 +405,65 @@ where
             dt_utc_offset: FixedOffset::east_opt(0).unwrap(),
             buffer: Vec::with_capacity(PARSER_BUFFER_CAPACITY),
             position,
+            odd_length: OddLengthStrategy::default(),
+            progress: ProgressHook::default(),
+        }
+    }
+
+    /// Set how values with an odd length are handled.
+    ///
+    /// Odd lengths are accepted by default.
+    pub fn set_odd_length_strategy(&mut self, strategy: OddLengthStrategy) {
+        self.odd_length = strategy;
+    }
+
+    /// Retrieve how values with an odd length are handled.
+    pub fn odd_length_strategy(&self) -> OddLengthStrategy {
+        self.odd_length
+    }
+
+    /// Set a callback to be called as the decoder progresses,
+    /// replacing any previous one.
+    ///
+    /// The callback is called after each data element header
+    /// and each item header is read,
+    /// with the position of the decoder
+    /// and the tag of the last data element header read.
+    /// It can be used to report progress while loading large files,
+    /// or to forward it to another thread through a channel.
+    pub fn set_progress_hook<F>(&mut self, callback: F)
+    where
+        F: FnMut(DecodeProgress) + Send + 'static,
+    {
+        self.progress.callback = Some(Box::new(callback));
+    }
+
+    /// Remove the progress reporting callback, if any.
+    pub fn clear_progress_hook(&mut self) {
+        self.progress.callback = None;
+    }
+
+    /// Report the current progress to the callback, if any.
+    fn report_progress(&mut self, tag: Option<Tag>) {
+        if tag.is_some() {
+            self.progress.tag = tag;
         }
+        if let (Some(callback), Some(tag)) = (&mut self.progress.callback, self.progress.tag) {
+            callback(DecodeProgress {
+                position: self.position,
+                tag,
+            });
+        }
+    }
+
+    /// Retrieve a reference to the underlying source.
+    pub(crate) fn reader_ref(&self) -> &S {
+        &self.from
+    }
+
+    /// Retrieve a mutable reference to the underlying source.
+    pub(crate) fn reader_mut(&mut self) -> &mut S {
+        &mut self.from
     }
 }
 
@@ -360,14 +498,34 @@ where
     // ---------------- private methods ---------------------
 
     fn require_known_length(&self, header: &DataElementHeader) -> Result<usize> {
-        header
-            .length()
-            .get()
-            .map(|len| len as usize)
-            .context(UndefinedValueLengthSnafu {
+        let len = header.length().get().context(UndefinedValueLengthSnafu {
+            position: self.position,
+            tag: header.tag,
+        })?;
+        ensure!(
+            len % 2 == 0 || self.odd_length == OddLengthStrategy::Accept,
+            OddValueLengthSnafu {
                 position: self.position,
                 tag: header.tag,
-            })
+                len,
+            }
+        );
+        Ok(len as usize)
+    }
+
+    /// Consume the trailing bytes of a binary value
+    /// which do not make up a full number,
+    /// so that the reader stays at the element boundary.
+    fn skip_trailing_bytes(&mut self, len: usize, item_size: usize) -> Result<()> {
+        let trailing = (len % item_size) as u64;
+        if trailing > 0 {
+            std::io::copy(&mut self.from.by_ref().take(trailing), &mut std::io::sink()).context(
+                ReadValueDataSnafu {
+                    position: self.position,
+                },
+            )?;
+        }
+        Ok(())
     }
 
     fn read_value_tag(&mut self, header: &DataElementHeader) -> Result<PrimitiveValue> {
@@ -384,6 +542,7 @@ where
                     })
             })
             .collect();
+        self.skip_trailing_bytes(len, 4)?;
         self.position += len as u64;
         Ok(PrimitiveValue::Tags(parts?))
     }
@@ -425,14 +584,12 @@ where
                 })
                 .collect(),
             _ => self
-                .buffer
-                .split(|v| *v == b'\\')
-                .map(|slice| {
-                    self.text.decode(slice).context(DecodeTextSnafu {
-                        position: self.position,
-                    })
-                })
-                .collect(),
+                .text
+                .decode_values(&self.buffer)
+                .map(|parts| parts.into_iter().collect())
+                .context(DecodeTextSnafu {
+                    position: self.position,
+                }),
         };
 
         self.position += len as u64;
@@ -471,6 +628,8 @@ where
                 position: self.position,
             })?;
 
+        self.skip_trailing_bytes(len, 2)?;
+
         self.position += len as u64;
         Ok(PrimitiveValue::I16(vec))
     }
@@ -485,6 +644,7 @@ where
             .context(ReadValueDataSnafu {
                 position: self.position,
             })?;
+        self.skip_trailing_bytes(len, 4)?;
         self.position += len as u64;
         Ok(PrimitiveValue::F32(vec))
     }
@@ -678,6 +838,7 @@ where
             .context(ReadValueDataSnafu {
                 position: self.position,
             })?;
+        self.skip_trailing_bytes(len, 8)?;
         self.position += len as u64;
         Ok(PrimitiveValue::F64(vec))
     }
@@ -693,6 +854,7 @@ where
             .context(ReadValueDataSnafu {
                 position: self.position,
             })?;
+        self.skip_trailing_bytes(len, 4)?;
         self.position += len as u64;
         Ok(PrimitiveValue::U32(vec))
     }
@@ -722,6 +884,8 @@ where
                 position: self.position,
             })?;
 
+        self.skip_trailing_bytes(len, 2)?;
+
         self.position += len as u64;
         Ok(PrimitiveValue::U16(vec))
     }
@@ -737,6 +901,7 @@ where
             .context(ReadValueDataSnafu {
                 position: self.position,
             })?;
+        self.skip_trailing_bytes(len, 8)?;
         self.position += len as u64;
         Ok(PrimitiveValue::U64(vec))
     }
@@ -752,6 +917,7 @@ where
             .context(ReadValueDataSnafu {
                 position: self.position,
             })?;
+        self.skip_trailing_bytes(len, 4)?;
         self.position += len as u64;
         Ok(PrimitiveValue::I32(vec))
     }
@@ -767,6 +933,7 @@ where
             .context(ReadValueDataSnafu {
                 position: self.position,
             })?;
+        self.skip_trailing_bytes(len, 8)?;
         self.position += len as u64;
         Ok(PrimitiveValue::I64(vec))
     }
@@ -778,11 +945,6 @@ where
     BD: BasicDecode,
     S: Read,
 {
-    fn set_character_set(&mut self, charset: SpecificCharacterSet) -> Result<()> {
-        self.text = charset;
-        Ok(())
-    }
-
     /// Read a sequence of Code String values. Similar to `read_value_strs`, but also
     /// triggers a character set change when it finds the _SpecificCharacterSet_
     /// attribute.
@@ -799,13 +961,11 @@ where
             // Edge case handling strategies for
             // unsupported specific character sets should probably be considered
             // in the future. See #40 for discussion.
-            if let Some(charset) = parts.first().map(|x| x.as_ref()).and_then(|name| {
-                SpecificCharacterSet::from_code(name).or_else(|| {
-                    tracing::warn!("Unsupported character set `{}`, ignoring", name);
-                    None
-                })
-            }) {
-                self.set_character_set(charset)?;
+            match SpecificCharacterSet::from_codes(parts.iter().map(|x| x.as_ref())) {
+                Some(charset) => self.set_character_set(charset),
+                None => {
+                    tracing::warn!("Unsupported character set `{}`, ignoring", parts.join("\\"));
+                }
             }
         }
 
@@ -869,6 +1029,21 @@ where
     {
         (**self).seek(position)
     }
+
+    fn skip_bytes_seek(&mut self, length: u32) -> Result<()>
+    where
+        Self::Reader: Seek,
+    {
+        (**self).skip_bytes_seek(length)
+    }
+
+    fn character_set(&self) -> SpecificCharacterSet {
+        (**self).character_set()
+    }
+
+    fn set_character_set(&mut self, charset: SpecificCharacterSet) {
+        (**self).set_character_set(charset)
+    }
 }
 
 impl<D, S, BD> StatefulDecode for StatefulDecoder<D, S, BD>
@@ -887,6 +1062,7 @@ where
             })
             .map(|(header, bytes_read)| {
                 self.position += bytes_read as u64;
+                self.report_progress(Some(header.tag));
                 header
             })
             .map_err(From::from)
@@ -900,6 +1076,7 @@ where
             })
             .map(|header| {
                 self.position += 8;
+                self.report_progress(None);
                 header
             })
             .map_err(From::from)
@@ -1051,6 +1228,29 @@ where
             })
             .map(|_| ())
     }
+
+    fn skip_bytes_seek(&mut self, length: u32) -> Result<()>
+    where
+        Self::Reader: Seek,
+    {
+        let new_position = self.position + u64::from(length);
+        self.from
+            .seek(SeekFrom::Current(i64::from(length)))
+            .context(SeekReaderSnafu {
+                position: self.position,
+                new_position,
+            })?;
+        self.position = new_position;
+        Ok(())
+    }
+
+    fn character_set(&self) -> SpecificCharacterSet {
+        self.text
+    }
+
+    fn set_character_set(&mut self, charset: SpecificCharacterSet) {
+        self.text = charset;
+    }
 }
 
 /// Remove trailing spaces and null characters.
@@ -1063,7 +1263,7 @@ fn trim_trail_empty_bytes(mut x: &[u8]) -> &[u8] {
 
 #[cfg(test)]
 mod tests {
-    use super::{StatefulDecode, StatefulDecoder};
+    use super::{DecodeProgress, Error, OddLengthStrategy, StatefulDecode, StatefulDecoder};
     use dicom_core::header::{DataElementHeader, HasLength, Header, Length, SequenceItemHeader};
     use dicom_core::{Tag, VR};
     use dicom_encoding::decode::basic::LittleEndianBasicDecoder;
@@ -1479,4 +1679,111 @@ mod tests {
 
         assert_eq!(decoder.position(), 138);
     }
+
+    #[test]
+    fn decode_odd_length_values() {
+        #[rustfmt::skip]
+        const RAW_ODD: &[u8] = &[
+            // (0028,0010) Rows, US, length 3
+            0x28, 0x00, 0x10, 0x00, b'U', b'S', 0x03, 0x00,
+            0x00, 0x02, 0xFF,
+            // (0028,0011) Columns, US, length 2
+            0x28, 0x00, 0x11, 0x00, b'U', b'S', 0x02, 0x00,
+            0x00, 0x01,
+        ];
+
+        let mut cursor = Cursor::new(RAW_ODD);
+        let mut decoder = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+        );
+        assert_eq!(decoder.odd_length_strategy(), OddLengthStrategy::Accept);
+
+        // the odd trailing byte is consumed
+        let header = decoder.decode_header().unwrap();
+        assert_eq!(header.length(), Length(3));
+        let value = decoder.read_value(&header).unwrap();
+        assert_eq!(value.to_int::<u16>().unwrap(), 512);
+        assert_eq!(decoder.position(), 11);
+
+        // so the next element is read from the right position
+        let header = decoder.decode_header().unwrap();
+        assert_eq!(header.tag(), Tag(0x0028, 0x0011));
+        let value = decoder.read_value(&header).unwrap();
+        assert_eq!(value.to_int::<u16>().unwrap(), 256);
+        assert_eq!(decoder.position(), 21);
+
+        // odd lengths can be rejected instead
+        let mut cursor = Cursor::new(RAW_ODD);
+        let mut decoder = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+        );
+        decoder.set_odd_length_strategy(OddLengthStrategy::Fail);
+        let header = decoder.decode_header().unwrap();
+        assert!(matches!(
+            decoder.read_value(&header),
+            Err(Error::OddValueLength { len: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn decode_with_progress_hook() {
+        #[rustfmt::skip]
+        const RAW: &[u8] = &[
+            // (0028,0010) Rows, US, length 2
+            0x28, 0x00, 0x10, 0x00, b'U', b'S', 0x02, 0x00,
+            0x00, 0x02,
+            // (0008,1140) ReferencedImageSequence, SQ, undefined length
+            0x08, 0x00, 0x40, 0x11, b'S', b'Q', 0x00, 0x00,
+            0xFF, 0xFF, 0xFF, 0xFF,
+            // sequence delimiter
+            0xFE, 0xFF, 0xDD, 0xE0, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let mut cursor = Cursor::new(RAW);
+        let mut decoder = StatefulDecoder::new(
+            &mut cursor,
+            ExplicitVRLittleEndianDecoder::default(),
+            LittleEndianBasicDecoder::default(),
+            SpecificCharacterSet::Default,
+        );
+        let (tx, rx) = std::sync::mpsc::channel();
+        decoder.set_progress_hook(move |progress| tx.send(progress).unwrap());
+
+        let header = decoder.decode_header().unwrap();
+        decoder.read_value(&header).unwrap();
+        decoder.decode_header().unwrap();
+        decoder.decode_item_header().unwrap();
+
+        let reports: Vec<DecodeProgress> = rx.try_iter().collect();
+        assert_eq!(
+            reports,
+            vec![
+                DecodeProgress {
+                    position: 8,
+                    tag: Tag(0x0028, 0x0010),
+                },
+                DecodeProgress {
+                    position: 22,
+                    tag: Tag(0x0008, 0x1140),
+                },
+                // item headers report the tag of the last element
+                DecodeProgress {
+                    position: 30,
+                    tag: Tag(0x0008, 0x1140),
+                },
+            ]
+        );
+
+        // nothing is reported once the hook is removed
+        decoder.clear_progress_hook();
+        decoder.seek(0).unwrap();
+        decoder.decode_header().unwrap();
+        assert!(rx.try_recv().is_err());
+    }
 }
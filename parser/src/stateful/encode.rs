@@ -76,6 +76,12 @@ impl<W, E, T> StatefulEncoder<W, E, T> {
             buffer: Vec::with_capacity(128),
         }
     }
+
+    /// Retrieve a mutable reference to the underlying writer.
+    #[cfg(feature = "async")]
+    pub(crate) fn writer_mut(&mut self) -> &mut W {
+        &mut self.to
+    }
 }
 
 impl<'s> DynStatefulEncoder<'s> {
@@ -164,6 +170,25 @@ where
         Ok(())
     }
 
+    /// Encode and write a data element
+    /// with the given value in its encoded form.
+    ///
+    /// The element's length is the exact number of bytes given,
+    /// and no padding is performed.
+    pub fn encode_raw_element(&mut self, de: &DataElementHeader, bytes: &[u8]) -> Result<()> {
+        let bytes_header = self
+            .encoder
+            .encode_element_header(
+                &mut self.to,
+                DataElementHeader::new(de.tag, de.vr, Length(bytes.len() as u32)),
+            )
+            .context(EncodeDataSnafu {
+                position: self.bytes_written,
+            })?;
+        self.bytes_written += bytes_header as u64;
+        self.write_raw_bytes(bytes)
+    }
+
     /// Write a primitive DICOM value as a bunch of bytes
     /// directly to the inner writer.
     ///
@@ -282,7 +307,7 @@ where
                 // if element is Specific Character Set,
                 // update the text codec
                 if de.tag == Tag(0x0008, 0x0005) {
-                    self.try_new_codec(text);
+                    self.try_new_codec(text.split('\\'));
                 }
 
                 Ok(())
@@ -293,9 +318,7 @@ where
                 // if element is Specific Character Set,
                 // update the text codec
                 if de.tag == Tag(0x0008, 0x0005) {
-                    if let Some(charset_name) = texts.first() {
-                        self.try_new_codec(charset_name);
-                    }
+                    self.try_new_codec(texts.iter().map(|t| t.as_str()));
                 }
                 Ok(())
             }
@@ -318,11 +341,15 @@ where
         }
     }
 
-    fn try_new_codec(&mut self, name: &str) {
-        if let Some(codec) = SpecificCharacterSet::from_code(name) {
+    fn try_new_codec<'a, I>(&mut self, codes: I)
+    where
+        I: IntoIterator<Item = &'a str> + Clone,
+    {
+        if let Some(codec) = SpecificCharacterSet::from_codes(codes.clone()) {
             self.text = codec;
         } else {
-            tracing::warn!("Unsupported character set `{}`, ignoring", name);
+            let name: Vec<_> = codes.into_iter().collect();
+            tracing::warn!("Unsupported character set `{}`, ignoring", name.join("\\"));
         }
     }
 
@@ -364,7 +391,7 @@ where
         // if element is Specific Character Set,
         // update the text codec
         if de.tag == Tag(0x0008, 0x0005) {
-            self.try_new_codec(text);
+            self.try_new_codec(text.split('\\'));
         }
 
         Ok(())
@@ -405,9 +432,7 @@ where
         // if element is Specific Character Set,
         // update the text codec
         if de.tag == Tag(0x0008, 0x0005) {
-            if let Some(charset_name) = texts.first() {
-                self.try_new_codec(charset_name.as_ref());
-            }
+            self.try_new_codec(texts.iter().map(|t| t.as_ref()));
         }
 
         Ok(())
@@ -1,6 +1,7 @@
 //! Utility module for fetching key attributes from a DICOM object.
 
-use dicom_core::{DataDictionary, Tag};
+use crate::ModalityLut;
+use dicom_core::{value::Value, DataDictionary, PrimitiveValue, Tag};
 use dicom_dictionary_std::tags;
 use dicom_object::{mem::InMemElement, FileDicomObject, InMemDicomObject};
 use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
@@ -18,6 +19,7 @@ pub enum AttributeName {
     Rows,
     BitsAllocated,
     BitsStored,
+    ExtendedOffsetTable,
     HighBit,
     NumberOfFrames,
     PhotometricInterpretation,
@@ -28,12 +30,18 @@ pub enum AttributeName {
     VoiLutFunction,
     WindowCenter,
     WindowWidth,
+    ModalityLutSequence,
+    LutDescriptor,
+    LutData,
 }
 
 impl std::fmt::Display for AttributeName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AttributeName::VoiLutFunction => f.write_str("VOILUTFunction"),
+            AttributeName::ModalityLutSequence => f.write_str("ModalityLUTSequence"),
+            AttributeName::LutDescriptor => f.write_str("LUTDescriptor"),
+            AttributeName::LutData => f.write_str("LUTData"),
             _ => std::fmt::Debug::fmt(self, f),
         }
     }
@@ -210,6 +218,100 @@ pub fn window_width<D: DataDictionary + Clone>(
     retrieve_optional_to_f64(obj, tags::WINDOW_WIDTH, AttributeName::WindowWidth)
 }
 
+/// Retrieve the lookup table in the first item
+/// of the ModalityLUTSequence from the DICOM object if it exists.
+///
+/// `pixel_representation` determines
+/// how the first mapped value in the LUT descriptor is interpreted.
+pub fn modality_lut<D: DataDictionary + Clone>(
+    obj: &FileDicomObject<InMemDicomObject<D>>,
+    pixel_representation: PixelRepresentation,
+) -> Result<Option<ModalityLut>> {
+    let name = AttributeName::ModalityLutSequence;
+    let item = match obj
+        .element_opt(tags::MODALITY_LUT_SEQUENCE)
+        .context(RetrieveSnafu { name })?
+        .and_then(|e| e.items())
+        .and_then(|items| items.first())
+    {
+        Some(item) => item,
+        None => return Ok(None),
+    };
+
+    let name = AttributeName::LutDescriptor;
+    let descriptor = item
+        .element_opt(tags::LUT_DESCRIPTOR)
+        .context(RetrieveSnafu { name })?
+        .context(MissingRequiredSnafu { name })?
+        .to_multi_int::<i32>()
+        .context(ConvertValueSnafu { name })?;
+    let (entries, first_mapped) = match descriptor[..] {
+        [entries, first_mapped, _bits] => (entries, first_mapped),
+        _ => {
+            return InvalidValueSnafu {
+                name,
+                value: format!("{:?}", descriptor),
+            }
+            .fail()
+        }
+    };
+    // a number of entries of 0 stands for 2^16 entries
+    let entries = if entries == 0 {
+        65_536
+    } else {
+        entries as usize
+    };
+    // the first mapped value is signed only if the pixel data is signed
+    let first_mapped =
+        if pixel_representation == PixelRepresentation::Signed && first_mapped >= 0x8000 {
+            first_mapped - 0x1_0000
+        } else {
+            first_mapped
+        };
+
+    let name = AttributeName::LutData;
+    let elem = item
+        .element_opt(tags::LUT_DATA)
+        .context(RetrieveSnafu { name })?
+        .context(MissingRequiredSnafu { name })?;
+    let mut data = match elem.value() {
+        Value::Primitive(PrimitiveValue::U8(bytes)) => bytes
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect(),
+        _ => elem
+            .to_multi_int::<u16>()
+            .context(ConvertValueSnafu { name })?,
+    };
+    ensure!(
+        data.len() >= entries,
+        InvalidValueSnafu {
+            name,
+            value: format!("{} entries", data.len()),
+        }
+    );
+    data.truncate(entries);
+
+    Ok(Some(ModalityLut::new(first_mapped, data)))
+}
+
+/// Retrieve the ExtendedOffsetTable from the DICOM object if it exists.
+pub fn extended_offset_table<D: DataDictionary + Clone>(
+    obj: &FileDicomObject<InMemDicomObject<D>>,
+) -> Result<Option<Vec<u64>>> {
+    let name = AttributeName::ExtendedOffsetTable;
+    match obj
+        .element_opt(tags::EXTENDED_OFFSET_TABLE)
+        .context(RetrieveSnafu { name })?
+    {
+        Some(e) => e
+            .to_multi_int::<u64>()
+            .context(ConvertValueSnafu { name })
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
 #[inline]
 fn retrieve_required_u16<D>(
     obj: &FileDicomObject<InMemDicomObject<D>>,
@@ -0,0 +1,77 @@
+//! Packing and unpacking of single-bit pixel data.
+//!
+//! Native pixel data with a _Bits Allocated_ of 1,
+//! as found in segmentations and some older nuclear medicine objects,
+//! packs 8 pixels into each byte,
+//! starting from the least significant bit (PS3.5 Section 8.1.1).
+//! Frames are not aligned to byte boundaries,
+//! so the pixels of all frames form a single bit stream.
+
+/// Unpack `nr_pixels` single-bit pixels from the given data
+/// into one byte per pixel, with values 0 or 1.
+///
+/// Missing bits at the end of the data are unpacked as 0.
+pub fn unpack_bits(data: &[u8], nr_pixels: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(nr_pixels);
+    for byte in data.iter().take(nr_pixels.div_ceil(8)) {
+        let remaining = (nr_pixels - pixels.len()).min(8);
+        pixels.extend((0..remaining).map(|bit| (byte >> bit) & 1));
+    }
+    pixels.resize(nr_pixels, 0);
+    pixels
+}
+
+/// Pack the given pixels, one byte per pixel,
+/// into single-bit pixel data.
+///
+/// Any non-zero pixel value is packed as 1.
+/// The output is padded with zeros to an even length,
+/// so that it can be used as the value of _Pixel Data_ as is.
+pub fn pack_bits(pixels: &[u8]) -> Vec<u8> {
+    let mut data: Vec<u8> = pixels
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (bit, &p)| byte | (u8::from(p != 0) << bit))
+        })
+        .collect();
+    if data.len() % 2 == 1 {
+        data.push(0);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_and_repack_bits() {
+        let data = [0b1000_0101, 0b0000_0011];
+        let pixels = unpack_bits(&data, 10);
+        assert_eq!(pixels, vec![1, 0, 1, 0, 0, 0, 0, 1, 1, 1]);
+
+        assert_eq!(pack_bits(&pixels), vec![0b1000_0101, 0b0000_0011]);
+        // odd length output is padded
+        assert_eq!(pack_bits(&[1, 1, 0]), vec![0b0000_0011, 0]);
+        // non-zero values are set
+        assert_eq!(pack_bits(&[0, 255, 2]), vec![0b0000_0110, 0]);
+    }
+
+    #[test]
+    fn unpack_frames_across_bytes() {
+        // two 3x3 frames share the second byte
+        let pixels: Vec<u8> = (0..18).map(|i| (i % 3 == 0) as u8).collect();
+        let data = pack_bits(&pixels);
+        assert_eq!(data.len(), 4);
+        assert_eq!(unpack_bits(&data, 18), pixels);
+
+        // truncated data is unpacked as zeros
+        assert_eq!(
+            unpack_bits(&data[..1], 10),
+            [&pixels[..8], &[0, 0]].concat()
+        );
+    }
+}
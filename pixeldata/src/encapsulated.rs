@@ -0,0 +1,504 @@
+//! Extraction of encapsulated frames without decoding.
+//!
+//! The functions in this module retrieve the encoded codestream
+//! of a single frame as is,
+//! so that it can be passed through to other services
+//! (such as a WADO-RS frame retrieval endpoint)
+//! without a decode/re-encode cycle.
+//!
+//! [`put_encapsulated_frames`] does the opposite,
+//! placing already encoded frames into an object
+//! along with an _Extended Offset Table_,
+//! so that frames can be located
+//! beyond the 4 GiB limit of the basic offset table.
+//! [`put_encapsulated_frames_with_options`] also allows
+//! splitting frames into fragments of a maximum size
+//! and choosing which offset table to generate.
+
+use crate::attribute::{extended_offset_table, number_of_frames, pixel_data};
+use crate::{
+    BasicOffsetTableOverflowSnafu, FragmentedExtendedOffsetTableSnafu, FrameOutOfRangeSnafu,
+    GetAttributeSnafu, InvalidOffsetTableSnafu, NotEncapsulatedSnafu, Result,
+    UnknownFrameFragmentsSnafu,
+};
+use dicom_core::{value::Value, DataDictionary, DataElement, PrimitiveValue, VR};
+use dicom_dictionary_std::tags;
+use dicom_encoding::adapters::fragments::frame_fragment_range;
+use dicom_object::{FileDicomObject, InMemDicomObject};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::convert::TryFrom;
+use std::ops::Range;
+
+/// The encoded data of a single frame of encapsulated pixel data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncapsulatedFrame {
+    /// The UID of the transfer syntax in which the frame is encoded.
+    pub transfer_syntax: String,
+    /// The frame's codestream,
+    /// joined from all of the fragments belonging to the frame.
+    pub data: Vec<u8>,
+}
+
+/// Extract the still encoded data of a single frame
+/// from a DICOM object with encapsulated pixel data,
+/// along with its transfer syntax.
+///
+/// `frame` is the index of the frame, starting at 0.
+/// The fragments of the frame are identified through
+/// the _Extended Offset Table_ or the basic offset table, if available.
+/// Without an offset table,
+/// the object must either have a single frame
+/// (whose data is the concatenation of all fragments)
+/// or exactly one fragment per frame.
+pub fn extract_encapsulated_frame<D>(
+    obj: &FileDicomObject<InMemDicomObject<D>>,
+    frame: u32,
+) -> Result<EncapsulatedFrame>
+where
+    D: DataDictionary + Clone,
+{
+    let (offset_table, fragments) = match pixel_data(obj).context(GetAttributeSnafu)?.value() {
+        Value::PixelSequence {
+            offset_table,
+            fragments,
+        } => (offset_table, fragments),
+        _ => return NotEncapsulatedSnafu.fail()?,
+    };
+    let number_of_frames = number_of_frames(obj).context(GetAttributeSnafu)?;
+
+    ensure!(
+        frame < number_of_frames,
+        FrameOutOfRangeSnafu {
+            frame_number: frame
+        }
+    );
+
+    let fragment_lengths: Vec<u64> = fragments.iter().map(|f| f.len() as u64).collect();
+
+    let range = if number_of_frames == 1 {
+        0..fragments.len()
+    } else if let Some(offsets) = extended_offset_table(obj).context(GetAttributeSnafu)? {
+        frame_fragments(&fragment_lengths, &offsets, number_of_frames, frame)?
+    } else if !offset_table.is_empty() {
+        let offsets: Vec<u64> = offset_table.iter().map(|&o| u64::from(o)).collect();
+        frame_fragments(&fragment_lengths, &offsets, number_of_frames, frame)?
+    } else if fragments.len() == number_of_frames as usize {
+        frame as usize..frame as usize + 1
+    } else {
+        return UnknownFrameFragmentsSnafu {
+            frame_number: frame,
+        }
+        .fail()?;
+    };
+
+    Ok(EncapsulatedFrame {
+        transfer_syntax: obj.meta().transfer_syntax().to_string(),
+        data: fragments[range].concat(),
+    })
+}
+
+/// Build the _Extended Offset Table_ and _Extended Offset Table Lengths_
+/// for the given frames, one fragment per frame.
+///
+/// Offsets are relative to the first byte of the first fragment's item,
+/// and each length is the length of the frame's fragment.
+pub fn build_extended_offset_table<T>(frames: &[T]) -> (Vec<u64>, Vec<u64>)
+where
+    T: AsRef<[u8]>,
+{
+    let lengths: Vec<u64> = frames.iter().map(|f| f.as_ref().len() as u64).collect();
+    let offsets = lengths
+        .iter()
+        .scan(0, |pos, len| {
+            let offset = *pos;
+            *pos += len + 8;
+            Some(offset)
+        })
+        .collect();
+    (offsets, lengths)
+}
+
+/// The offset table to generate when encapsulating frames.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OffsetTableOption {
+    /// Leave the basic offset table empty
+    /// and do not add an _Extended Offset Table_.
+    None,
+    /// Fill in the basic offset table
+    /// with the offset of the first fragment of each frame.
+    ///
+    /// All offsets must fit in 32 bits.
+    Basic,
+    /// _Default:_ add an _Extended Offset Table_
+    /// and _Extended Offset Table Lengths_,
+    /// leaving the basic offset table empty.
+    ///
+    /// Each frame must be in a single fragment.
+    #[default]
+    Extended,
+}
+
+/// Options for placing encoded frames into encapsulated pixel data.
+///
+/// By default, each frame is placed in a single fragment,
+/// and an _Extended Offset Table_ is generated.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct EncapsulationOptions {
+    /// The maximum size of each fragment in bytes,
+    /// or `None` to place each frame in a single fragment
+    pub max_fragment_size: Option<u32>,
+    /// The offset table to generate
+    pub offset_table: OffsetTableOption,
+}
+
+impl EncapsulationOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Split frames into fragments of at most the given size in bytes.
+    ///
+    /// As fragments must have an even length,
+    /// an odd size is rounded down.
+    pub fn with_max_fragment_size(mut self, max_fragment_size: u32) -> Self {
+        self.max_fragment_size = Some(max_fragment_size);
+        self
+    }
+
+    /// Place each frame in a single fragment.
+    pub fn one_fragment_per_frame(mut self) -> Self {
+        self.max_fragment_size = None;
+        self
+    }
+
+    /// Set the offset table to generate.
+    pub fn with_offset_table(mut self, offset_table: OffsetTableOption) -> Self {
+        self.offset_table = offset_table;
+        self
+    }
+}
+
+/// Put the given encoded frames into an object
+/// as encapsulated pixel data with one fragment per frame,
+/// along with an _Extended Offset Table_ to locate them.
+///
+/// Frames of odd length are padded with a trailing zero.
+/// The basic offset table is left empty,
+/// as required when the extended offset table is present.
+/// The transfer syntax and other image pixel attributes
+/// are not changed.
+pub fn put_encapsulated_frames<D>(obj: &mut InMemDicomObject<D>, frames: Vec<Vec<u8>>)
+where
+    D: DataDictionary + Clone,
+{
+    put_encapsulated_frames_with_options(obj, frames, &EncapsulationOptions::default())
+        .expect("one fragment per frame is always compatible with the extended offset table")
+}
+
+/// Put the given encoded frames into an object
+/// as encapsulated pixel data,
+/// fragmented and indexed as described by the given options.
+///
+/// Frames of odd length are padded with a trailing zero.
+/// The _Extended Offset Table_ is removed from the object
+/// if another kind of offset table is requested.
+/// The transfer syntax and other image pixel attributes
+/// are not changed.
+///
+/// Fails if the requested offset table cannot index the frames,
+/// in which case the object is left untouched.
+pub fn put_encapsulated_frames_with_options<D>(
+    obj: &mut InMemDicomObject<D>,
+    frames: Vec<Vec<u8>>,
+    options: &EncapsulationOptions,
+) -> Result<()>
+where
+    D: DataDictionary + Clone,
+{
+    let frames: Vec<Vec<u8>> = frames
+        .into_iter()
+        .map(|mut frame| {
+            if frame.len() % 2 == 1 {
+                frame.push(0);
+            }
+            frame
+        })
+        .collect();
+
+    // split frames into fragments, recording the first fragment of each frame
+    let mut frame_starts = Vec::with_capacity(frames.len());
+    let fragments: Vec<Vec<u8>> = match options.max_fragment_size {
+        Some(max_size) => {
+            let max_size = (max_size as usize & !1).max(2);
+            let mut fragments = Vec::new();
+            for frame in &frames {
+                frame_starts.push(fragments.len());
+                if frame.is_empty() {
+                    fragments.push(Vec::new());
+                }
+                fragments.extend(frame.chunks(max_size).map(|c| c.to_vec()));
+            }
+            fragments
+        }
+        None => {
+            frame_starts.extend(0..frames.len());
+            frames
+        }
+    };
+
+    let mut basic_offset_table = Vec::new();
+    match options.offset_table {
+        OffsetTableOption::None => {
+            obj.remove_element(tags::EXTENDED_OFFSET_TABLE);
+            obj.remove_element(tags::EXTENDED_OFFSET_TABLE_LENGTHS);
+        }
+        OffsetTableOption::Basic => {
+            let (offsets, _) = build_extended_offset_table(&fragments);
+            basic_offset_table = frame_starts
+                .iter()
+                .map(|&i| u32::try_from(offsets[i]).ok())
+                .collect::<Option<Vec<u32>>>()
+                .context(BasicOffsetTableOverflowSnafu)?;
+            obj.remove_element(tags::EXTENDED_OFFSET_TABLE);
+            obj.remove_element(tags::EXTENDED_OFFSET_TABLE_LENGTHS);
+        }
+        OffsetTableOption::Extended => {
+            ensure!(
+                fragments.len() == frame_starts.len(),
+                FragmentedExtendedOffsetTableSnafu
+            );
+            let (offsets, lengths) = build_extended_offset_table(&fragments);
+            obj.put(DataElement::new(
+                tags::EXTENDED_OFFSET_TABLE,
+                VR::OV,
+                PrimitiveValue::U64(offsets.into()),
+            ));
+            obj.put(DataElement::new(
+                tags::EXTENDED_OFFSET_TABLE_LENGTHS,
+                VR::OV,
+                PrimitiveValue::U64(lengths.into()),
+            ));
+        }
+    }
+
+    obj.put(DataElement::new(
+        tags::PIXEL_DATA,
+        VR::OB,
+        Value::PixelSequence {
+            offset_table: basic_offset_table.into(),
+            fragments: fragments.into(),
+        },
+    ));
+    Ok(())
+}
+
+/// Determine the range of fragments which belong to the given frame,
+/// based on a table of byte offsets to the first fragment of each frame.
+fn frame_fragments(
+    fragment_lengths: &[u64],
+    offsets: &[u64],
+    number_of_frames: u32,
+    frame: u32,
+) -> Result<Range<usize>> {
+    ensure!(
+        offsets.len() == number_of_frames as usize,
+        InvalidOffsetTableSnafu
+    );
+    Ok(frame_fragment_range(fragment_lengths, offsets, frame).context(InvalidOffsetTableSnafu)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{rle_lossless, FixtureSpec};
+    use dicom_object::FileMetaTableBuilder;
+
+    fn object_with_fragments(
+        number_of_frames: u32,
+        offset_table: Vec<u32>,
+        fragments: Vec<Vec<u8>>,
+    ) -> FileDicomObject<InMemDicomObject> {
+        InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::NUMBER_OF_FRAMES,
+                VR::IS,
+                PrimitiveValue::from(number_of_frames.to_string()),
+            ),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                Value::PixelSequence {
+                    offset_table: offset_table.into(),
+                    fragments: fragments.into(),
+                },
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.3.4")
+                .transfer_syntax("1.2.840.10008.1.2.4.50"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn extract_one_fragment_per_frame() {
+        let fixture = rle_lossless(&FixtureSpec::new(8, 8).frames(3));
+
+        let fragments = match fixture.object.element(tags::PIXEL_DATA).unwrap().value() {
+            Value::PixelSequence { fragments, .. } => fragments.clone(),
+            _ => panic!("fixture should have encapsulated pixel data"),
+        };
+
+        for (i, fragment) in fragments.iter().enumerate() {
+            let frame = extract_encapsulated_frame(&fixture.object, i as u32).unwrap();
+            assert_eq!(frame.transfer_syntax, "1.2.840.10008.1.2.5");
+            assert_eq!(&frame.data, fragment);
+        }
+
+        assert!(extract_encapsulated_frame(&fixture.object, 3).is_err());
+    }
+
+    #[test]
+    fn extract_single_frame_joins_all_fragments() {
+        let obj = object_with_fragments(1, vec![], vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+
+        let frame = extract_encapsulated_frame(&obj, 0).unwrap();
+        assert_eq!(frame.transfer_syntax, "1.2.840.10008.1.2.4.50");
+        assert_eq!(frame.data, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn extract_frames_with_basic_offset_table() {
+        // frame 0: fragments 0 and 1, frame 1: fragment 2
+        let obj = object_with_fragments(
+            2,
+            vec![0, 8 + 4 + 8 + 2],
+            vec![vec![1, 2, 3, 4], vec![5, 6], vec![7, 8]],
+        );
+
+        let frame = extract_encapsulated_frame(&obj, 0).unwrap();
+        assert_eq!(frame.data, vec![1, 2, 3, 4, 5, 6]);
+        let frame = extract_encapsulated_frame(&obj, 1).unwrap();
+        assert_eq!(frame.data, vec![7, 8]);
+    }
+
+    #[test]
+    fn extract_frames_with_extended_offset_table() {
+        // frame 0: fragment 0, frame 1: fragments 1 and 2
+        let mut obj =
+            object_with_fragments(2, vec![], vec![vec![1, 2, 3, 4], vec![5, 6], vec![7, 8]]);
+        obj.put(DataElement::new(
+            tags::EXTENDED_OFFSET_TABLE,
+            VR::OV,
+            PrimitiveValue::U64(vec![0, 8 + 4].into()),
+        ));
+
+        let frame = extract_encapsulated_frame(&obj, 0).unwrap();
+        assert_eq!(frame.data, vec![1, 2, 3, 4]);
+        let frame = extract_encapsulated_frame(&obj, 1).unwrap();
+        assert_eq!(frame.data, vec![5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn cannot_extract_ambiguous_frames() {
+        let obj = object_with_fragments(2, vec![], vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+        assert!(extract_encapsulated_frame(&obj, 0).is_err());
+
+        // offset does not point to the start of a fragment
+        let obj = object_with_fragments(2, vec![0, 4], vec![vec![1, 2], vec![3, 4]]);
+        assert!(extract_encapsulated_frame(&obj, 1).is_err());
+    }
+
+    #[test]
+    fn put_frames_with_extended_offset_table() {
+        let mut obj = object_with_fragments(3, vec![], vec![]);
+        put_encapsulated_frames(&mut obj, vec![vec![1, 2, 3, 4], vec![5, 6, 7], vec![8, 9]]);
+
+        let offsets = obj.element(tags::EXTENDED_OFFSET_TABLE).unwrap();
+        assert_eq!(offsets.to_multi_int::<u64>().unwrap(), vec![0, 12, 24]);
+        let lengths = obj.element(tags::EXTENDED_OFFSET_TABLE_LENGTHS).unwrap();
+        assert_eq!(lengths.to_multi_int::<u64>().unwrap(), vec![4, 4, 2]);
+
+        let frame = extract_encapsulated_frame(&obj, 0).unwrap();
+        assert_eq!(frame.data, vec![1, 2, 3, 4]);
+        let frame = extract_encapsulated_frame(&obj, 1).unwrap();
+        assert_eq!(frame.data, vec![5, 6, 7, 0]);
+        let frame = extract_encapsulated_frame(&obj, 2).unwrap();
+        assert_eq!(frame.data, vec![8, 9]);
+    }
+
+    #[test]
+    fn put_frames_in_fragments_with_basic_offset_table() {
+        let mut obj = object_with_fragments(3, vec![], vec![]);
+        let options = EncapsulationOptions::new()
+            .with_max_fragment_size(5)
+            .with_offset_table(OffsetTableOption::Basic);
+        put_encapsulated_frames_with_options(
+            &mut obj,
+            vec![vec![1, 2, 3, 4, 5, 6, 7], vec![8, 9], vec![]],
+            &options,
+        )
+        .unwrap();
+
+        assert!(obj
+            .element_opt(tags::EXTENDED_OFFSET_TABLE)
+            .unwrap()
+            .is_none());
+        match obj.element(tags::PIXEL_DATA).unwrap().value() {
+            Value::PixelSequence {
+                offset_table,
+                fragments,
+            } => {
+                // fragments of at most 4 bytes
+                assert_eq!(
+                    &fragments[..],
+                    &[vec![1, 2, 3, 4], vec![5, 6, 7, 0], vec![8, 9], vec![]]
+                );
+                assert_eq!(&offset_table[..], &[0, 24, 34]);
+            }
+            _ => panic!("pixel data should be encapsulated"),
+        }
+
+        let frame = extract_encapsulated_frame(&obj, 0).unwrap();
+        assert_eq!(frame.data, vec![1, 2, 3, 4, 5, 6, 7, 0]);
+        let frame = extract_encapsulated_frame(&obj, 1).unwrap();
+        assert_eq!(frame.data, vec![8, 9]);
+    }
+
+    #[test]
+    fn put_frames_without_offset_table() {
+        let mut obj = object_with_fragments(2, vec![], vec![]);
+        put_encapsulated_frames(&mut obj, vec![vec![1, 2], vec![3, 4]]);
+        assert!(obj
+            .element_opt(tags::EXTENDED_OFFSET_TABLE)
+            .unwrap()
+            .is_some());
+
+        let options = EncapsulationOptions::new().with_offset_table(OffsetTableOption::None);
+        put_encapsulated_frames_with_options(&mut obj, vec![vec![5, 6], vec![7, 8]], &options)
+            .unwrap();
+        assert!(obj
+            .element_opt(tags::EXTENDED_OFFSET_TABLE)
+            .unwrap()
+            .is_none());
+        assert!(obj
+            .element_opt(tags::EXTENDED_OFFSET_TABLE_LENGTHS)
+            .unwrap()
+            .is_none());
+        let frame = extract_encapsulated_frame(&obj, 1).unwrap();
+        assert_eq!(frame.data, vec![7, 8]);
+
+        // extended offset table cannot index fragmented frames
+        let options = EncapsulationOptions::new().with_max_fragment_size(2);
+        assert!(put_encapsulated_frames_with_options(
+            &mut obj,
+            vec![vec![1, 2, 3, 4], vec![5, 6]],
+            &options
+        )
+        .is_err());
+    }
+}
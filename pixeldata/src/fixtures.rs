@@ -0,0 +1,684 @@
+//! Generation of small synthetic DICOM images
+//! with encapsulated pixel data,
+//! for verifying pixel data codecs without real patient data.
+//!
+//! Each [`Fixture`] holds a complete DICOM file object,
+//! encoded with one of the supported transfer syntaxes,
+//! alongside the pixel data which decoding it should produce
+//! (native, little endian, interleaved samples).
+//! The pixel values follow a deterministic pattern,
+//! so the same specification always yields the same fixture.
+//!
+//! The following encodings are currently supported:
+//!
+//! - RLE Lossless, with [`rle_lossless`];
+//! - JPEG Baseline (Process 1), with [`jpeg_baseline`];
+//! - JPEG Lossless, Non-Hierarchical, First-Order Prediction
+//!   (Process 14, selection value 1), with [`jpeg_lossless`].
+//!
+//! This module is only available with the Cargo feature `test-support`.
+//!
+//! # Example
+//!
+//! ```
+//! use dicom_pixeldata::fixtures::{rle_lossless, FixtureSpec};
+//! use dicom_pixeldata::PixelDecoder;
+//!
+//! let fixture = rle_lossless(&FixtureSpec::new(16, 24).frames(2));
+//! let decoded = fixture.object.decode_pixel_data()?;
+//! assert!(fixture.matches(decoded.data()));
+//! # Ok::<_, dicom_pixeldata::Error>(())
+//! ```
+use byteorder::{ByteOrder, LittleEndian};
+use dicom_core::value::{PrimitiveValue, Value, C};
+use dicom_core::{DataElement, VR};
+use dicom_dictionary_std::tags;
+use dicom_encoding::adapters::rle_lossless::RLELosslessAdapter;
+use dicom_encoding::adapters::{EncodeOptions, PixelRWAdapter};
+use dicom_object::{DefaultDicomObject, FileMetaTableBuilder, InMemDicomObject};
+use image::codecs::jpeg::JpegEncoder;
+use image::ColorType;
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+
+/// Transfer syntax UID of Explicit VR Little Endian
+const EXPLICIT_VR_LITTLE_ENDIAN_UID: &str = "1.2.840.10008.1.2.1";
+/// Transfer syntax UID of RLE Lossless
+const RLE_LOSSLESS_UID: &str = "1.2.840.10008.1.2.5";
+/// Transfer syntax UID of JPEG Baseline (Process 1)
+const JPEG_BASELINE_UID: &str = "1.2.840.10008.1.2.4.50";
+/// Transfer syntax UID of JPEG Lossless, Non-Hierarchical, First-Order Prediction
+const JPEG_LOSSLESS_SV1_UID: &str = "1.2.840.10008.1.2.4.70";
+/// SOP class UID of Secondary Capture Image Storage
+const SECONDARY_CAPTURE_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.7";
+
+/// An error which may occur when generating a fixture.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum FixtureError {
+    /// The fixture specification is not supported by the encoding
+    #[snafu(display("Unsupported fixture specification: {}", message))]
+    UnsupportedSpec {
+        message: &'static str,
+        backtrace: Backtrace,
+    },
+    /// Failed to encode the image
+    #[snafu(display("Failed to encode image: {}", source))]
+    EncodeImage {
+        source: image::ImageError,
+        backtrace: Backtrace,
+    },
+}
+
+/// The properties of a synthetic image.
+///
+/// By default, the image is monochrome, 8 bits per sample,
+/// with a single frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FixtureSpec {
+    rows: u16,
+    columns: u16,
+    samples_per_pixel: u16,
+    bits_allocated: u16,
+    number_of_frames: u32,
+}
+
+impl FixtureSpec {
+    /// Create a new specification of a single frame
+    /// monochrome 8-bit image with the given dimensions.
+    pub fn new(rows: u16, columns: u16) -> Self {
+        FixtureSpec {
+            rows,
+            columns,
+            samples_per_pixel: 1,
+            bits_allocated: 8,
+            number_of_frames: 1,
+        }
+    }
+
+    /// Make the image an RGB image (3 samples per pixel).
+    pub fn rgb(mut self) -> Self {
+        self.samples_per_pixel = 3;
+        self
+    }
+
+    /// Set the number of bits per sample (8 or 16).
+    pub fn bits_allocated(mut self, bits_allocated: u16) -> Self {
+        self.bits_allocated = bits_allocated;
+        self
+    }
+
+    /// Set the number of frames.
+    pub fn frames(mut self, number_of_frames: u32) -> Self {
+        self.number_of_frames = number_of_frames;
+        self
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        self.bits_allocated as usize / 8
+    }
+
+    fn frame_len(&self) -> usize {
+        self.rows as usize
+            * self.columns as usize
+            * self.samples_per_pixel as usize
+            * self.bytes_per_sample()
+    }
+
+    /// Calculate the value of a sample in the synthetic pattern.
+    ///
+    /// The pattern is a set of smooth gradients,
+    /// which differ per sample and per frame.
+    fn sample(&self, frame: u32, row: u16, column: u16, sample: u16) -> u16 {
+        let max = ((1_u32 << self.bits_allocated) - 1) as f64;
+        let rows = f64::from(self.rows.max(2) - 1);
+        let columns = f64::from(self.columns.max(2) - 1);
+        let diagonal = (f64::from(row) / rows + f64::from(column) / columns) / 2.;
+        let t = match sample {
+            0 => diagonal,
+            1 => 1. - diagonal,
+            _ => f64::from(row) / rows,
+        };
+        let frame_shift = f64::from(frame) / f64::from(self.number_of_frames);
+        ((t + frame_shift) / 2. * max).round() as u16
+    }
+
+    /// Produce the native pixel data of one frame,
+    /// as the sample values in little endian, with interleaved samples.
+    fn native_frame(&self, frame: u32) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.frame_len());
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                for sample in 0..self.samples_per_pixel {
+                    let value = self.sample(frame, row, column, sample);
+                    if self.bits_allocated == 16 {
+                        let mut buf = [0; 2];
+                        LittleEndian::write_u16(&mut buf, value);
+                        out.extend(buf);
+                    } else {
+                        out.push(value as u8);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Produce the native pixel data of all frames,
+    /// as the sample values in little endian, with interleaved samples.
+    pub fn native_pixel_data(&self) -> Vec<u8> {
+        (0..self.number_of_frames)
+            .flat_map(|frame| self.native_frame(frame))
+            .collect()
+    }
+}
+
+/// A synthetic DICOM image with encapsulated pixel data
+/// and its expected decoded pixel data.
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    /// The DICOM file object with the encoded image.
+    pub object: DefaultDicomObject,
+    /// The pixel data expected from decoding the object,
+    /// in little endian with interleaved samples.
+    pub expected: Vec<u8>,
+    /// The maximum absolute difference allowed
+    /// between a decoded sample and its expected value.
+    /// This is always 0 for lossless encodings.
+    pub tolerance: u16,
+}
+
+impl Fixture {
+    /// Calculate the largest absolute difference between a sample
+    /// in the given decoded pixel data
+    /// and the corresponding expected sample.
+    ///
+    /// Returns `None` if the decoded data does not have the expected length.
+    pub fn max_error(&self, decoded: &[u8]) -> Option<u16> {
+        if decoded.len() != self.expected.len() {
+            return None;
+        }
+        let bits_allocated = self
+            .object
+            .element(tags::BITS_ALLOCATED)
+            .ok()?
+            .uint16()
+            .ok()?;
+        let max_error = if bits_allocated == 16 {
+            decoded
+                .chunks_exact(2)
+                .zip(self.expected.chunks_exact(2))
+                .map(|(a, b)| LittleEndian::read_u16(a).abs_diff(LittleEndian::read_u16(b)))
+                .max()
+        } else {
+            decoded
+                .iter()
+                .zip(&self.expected)
+                .map(|(&a, &b)| u16::from(a.abs_diff(b)))
+                .max()
+        };
+        Some(max_error.unwrap_or(0))
+    }
+
+    /// Check whether the given decoded pixel data
+    /// matches the expected pixel data within the fixture's tolerance.
+    pub fn matches(&self, decoded: &[u8]) -> bool {
+        matches!(self.max_error(decoded), Some(e) if e <= self.tolerance)
+    }
+}
+
+/// Generate an RLE Lossless encoded image.
+///
+/// All combinations of 8 or 16 bits allocated
+/// and 1 or 3 samples per pixel are supported.
+///
+/// # Panics
+///
+/// Panics if the number of bits allocated is neither 8 nor 16.
+pub fn rle_lossless(spec: &FixtureSpec) -> Fixture {
+    assert!(
+        spec.bits_allocated == 8 || spec.bits_allocated == 16,
+        "RLE fixtures require 8 or 16 bits allocated"
+    );
+
+    let photometric_interpretation = if spec.samples_per_pixel == 3 {
+        "RGB"
+    } else {
+        "MONOCHROME2"
+    };
+
+    // encode each frame of the native image with the RLE Lossless adapter
+    let native = build_native_object(spec, photometric_interpretation);
+    let fragments = (0..spec.number_of_frames)
+        .map(|frame| {
+            let mut fragment = Vec::new();
+            RLELosslessAdapter
+                .encode_frame(&native, frame, EncodeOptions::new(), &mut fragment)
+                .expect("native fixture should be encodable in RLE Lossless");
+            fragment
+        })
+        .collect();
+
+    Fixture {
+        object: build_object(
+            spec,
+            photometric_interpretation,
+            RLE_LOSSLESS_UID,
+            fragments,
+        ),
+        expected: spec.native_pixel_data(),
+        tolerance: 0,
+    }
+}
+
+/// Generate a JPEG Baseline (Process 1) encoded image,
+/// with one fragment per frame.
+///
+/// Only 8 bits allocated are supported.
+/// Since the encoding is lossy,
+/// the fixture has a non-zero tolerance.
+pub fn jpeg_baseline(spec: &FixtureSpec) -> Result<Fixture, FixtureError> {
+    ensure!(
+        spec.bits_allocated == 8,
+        UnsupportedSpecSnafu {
+            message: "JPEG baseline requires 8 bits allocated",
+        }
+    );
+
+    let color_type = if spec.samples_per_pixel == 3 {
+        ColorType::Rgb8
+    } else {
+        ColorType::L8
+    };
+
+    let fragments = (0..spec.number_of_frames)
+        .map(|frame| {
+            let mut fragment = Vec::new();
+            JpegEncoder::new_with_quality(&mut fragment, 100)
+                .encode(
+                    &spec.native_frame(frame),
+                    spec.columns.into(),
+                    spec.rows.into(),
+                    color_type,
+                )
+                .context(EncodeImageSnafu)?;
+            if fragment.len() & 1 != 0 {
+                fragment.push(0);
+            }
+            Ok(fragment)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let photometric_interpretation = if spec.samples_per_pixel == 3 {
+        "YBR_FULL_422"
+    } else {
+        "MONOCHROME2"
+    };
+
+    Ok(Fixture {
+        object: build_object(
+            spec,
+            photometric_interpretation,
+            JPEG_BASELINE_UID,
+            fragments,
+        ),
+        expected: spec.native_pixel_data(),
+        tolerance: 8,
+    })
+}
+
+/// Generate a JPEG Lossless, Non-Hierarchical, First-Order Prediction
+/// (Process 14, selection value 1) encoded image,
+/// with one fragment per frame.
+///
+/// All combinations of 8 or 16 bits allocated
+/// and 1 or 3 samples per pixel are supported.
+///
+/// # Panics
+///
+/// Panics if the number of bits allocated is neither 8 nor 16.
+pub fn jpeg_lossless(spec: &FixtureSpec) -> Fixture {
+    assert!(
+        spec.bits_allocated == 8 || spec.bits_allocated == 16,
+        "JPEG lossless fixtures require 8 or 16 bits allocated"
+    );
+
+    let fragments = (0..spec.number_of_frames)
+        .map(|frame| encode_jpeg_lossless_frame(spec, &spec.native_frame(frame)))
+        .collect();
+
+    let photometric_interpretation = if spec.samples_per_pixel == 3 {
+        "RGB"
+    } else {
+        "MONOCHROME2"
+    };
+
+    Fixture {
+        object: build_object(
+            spec,
+            photometric_interpretation,
+            JPEG_LOSSLESS_SV1_UID,
+            fragments,
+        ),
+        expected: spec.native_pixel_data(),
+        tolerance: 0,
+    }
+}
+
+/// Encode one frame of native pixel data
+/// into a JPEG Lossless fragment with first-order prediction,
+/// with all samples interleaved in a single scan.
+///
+/// The Huffman table assigns a 5-bit code to each difference category.
+/// See ITU-T T.81, Annex H.
+fn encode_jpeg_lossless_frame(spec: &FixtureSpec, frame: &[u8]) -> Vec<u8> {
+    let precision = spec.bits_allocated;
+    let components = spec.samples_per_pixel as usize;
+    let columns = spec.columns as usize;
+    let samples: Vec<i32> = if spec.bits_allocated == 16 {
+        frame
+            .chunks_exact(2)
+            .map(|b| i32::from(LittleEndian::read_u16(b)))
+            .collect()
+    } else {
+        frame.iter().map(|&b| i32::from(b)).collect()
+    };
+
+    let mut out = vec![0xFF, 0xD8];
+
+    // start of frame, lossless (SOF3)
+    out.extend([0xFF, 0xC3]);
+    out.extend((8 + 3 * components as u16).to_be_bytes());
+    out.push(precision as u8);
+    out.extend(spec.rows.to_be_bytes());
+    out.extend(spec.columns.to_be_bytes());
+    out.push(components as u8);
+    for c in 0..components {
+        out.extend([c as u8 + 1, 0x11, 0]);
+    }
+
+    // DC Huffman table 0: 17 categories, all with 5-bit codes
+    out.extend([0xFF, 0xC4]);
+    out.extend((2 + 1 + 16 + 17_u16).to_be_bytes());
+    out.push(0x00);
+    out.extend([0, 0, 0, 0, 17, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    out.extend(0..=16_u8);
+
+    // start of scan: predictor 1, no point transform
+    out.extend([0xFF, 0xDA]);
+    out.extend((6 + 2 * components as u16).to_be_bytes());
+    out.push(components as u8);
+    for c in 0..components {
+        out.extend([c as u8 + 1, 0x00]);
+    }
+    out.extend([1, 0, 0]);
+
+    let mut bits = BitWriter::default();
+    for (i, &value) in samples.iter().enumerate() {
+        let pixel = i / components;
+        let (row, column) = (pixel / columns, pixel % columns);
+        let prediction = match (row, column) {
+            (0, 0) => 1 << (precision - 1),
+            // left neighbour in the first row, upper neighbour in the first column
+            (0, _) => samples[i - components],
+            (_, 0) => samples[i - columns * components],
+            _ => samples[i - components],
+        };
+        // differences are calculated modulo 2^16
+        let diff = (value - prediction) as i16;
+        if diff == i16::MIN {
+            bits.write(16, 5);
+            continue;
+        }
+        let category = 16 - diff.unsigned_abs().leading_zeros();
+        bits.write(category, 5);
+        if category > 0 {
+            let extra = if diff > 0 {
+                diff as u32
+            } else {
+                (i32::from(diff) + (1 << category) - 1) as u32
+            };
+            bits.write(extra, category);
+        }
+    }
+    out.extend(bits.finish());
+
+    out.extend([0xFF, 0xD9]);
+    if out.len() & 1 != 0 {
+        out.push(0);
+    }
+    out
+}
+
+/// A writer of entropy-coded JPEG data,
+/// most significant bit first, with byte stuffing.
+#[derive(Debug, Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    current: u8,
+    nbits: u32,
+}
+
+impl BitWriter {
+    /// Write the lowest `count` bits of `value`.
+    fn write(&mut self, value: u32, count: u32) {
+        for i in (0..count).rev() {
+            self.current = (self.current << 1) | ((value >> i) & 1) as u8;
+            self.nbits += 1;
+            if self.nbits == 8 {
+                self.flush_byte();
+            }
+        }
+    }
+
+    fn flush_byte(&mut self) {
+        self.out.push(self.current);
+        if self.current == 0xFF {
+            self.out.push(0x00);
+        }
+        self.current = 0;
+        self.nbits = 0;
+    }
+
+    /// Pad the last byte with 1-bits and return the encoded data.
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            let padding = 8 - self.nbits;
+            self.write((1 << padding) - 1, padding);
+        }
+        self.out
+    }
+}
+
+/// Build an object with the fixture's pixel data in native form.
+fn build_native_object(spec: &FixtureSpec, photometric_interpretation: &str) -> DefaultDicomObject {
+    let mut obj = build_object(
+        spec,
+        photometric_interpretation,
+        EXPLICIT_VR_LITTLE_ENDIAN_UID,
+        Vec::new(),
+    );
+    let vr = if spec.bits_allocated == 16 {
+        VR::OW
+    } else {
+        VR::OB
+    };
+    obj.put(DataElement::new(
+        tags::PIXEL_DATA,
+        vr,
+        PrimitiveValue::from(spec.native_pixel_data()),
+    ));
+    obj
+}
+
+fn build_object(
+    spec: &FixtureSpec,
+    photometric_interpretation: &str,
+    transfer_syntax: &str,
+    fragments: Vec<Vec<u8>>,
+) -> DefaultDicomObject {
+    let mut obj = InMemDicomObject::from_element_iter([
+        DataElement::new(
+            tags::SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from(SECONDARY_CAPTURE_IMAGE_STORAGE),
+        ),
+        DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("2.25.137038125948464847900039011591283709926"),
+        ),
+        DataElement::new(
+            tags::SAMPLES_PER_PIXEL,
+            VR::US,
+            PrimitiveValue::from(spec.samples_per_pixel),
+        ),
+        DataElement::new(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            VR::CS,
+            PrimitiveValue::from(photometric_interpretation),
+        ),
+        DataElement::new(
+            tags::NUMBER_OF_FRAMES,
+            VR::IS,
+            PrimitiveValue::from(spec.number_of_frames.to_string()),
+        ),
+        DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(spec.rows)),
+        DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(spec.columns)),
+        DataElement::new(
+            tags::BITS_ALLOCATED,
+            VR::US,
+            PrimitiveValue::from(spec.bits_allocated),
+        ),
+        DataElement::new(
+            tags::BITS_STORED,
+            VR::US,
+            PrimitiveValue::from(spec.bits_allocated),
+        ),
+        DataElement::new(
+            tags::HIGH_BIT,
+            VR::US,
+            PrimitiveValue::from(spec.bits_allocated - 1),
+        ),
+        DataElement::new(
+            tags::PIXEL_REPRESENTATION,
+            VR::US,
+            PrimitiveValue::from(0_u16),
+        ),
+        DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OB,
+            Value::PixelSequence {
+                offset_table: C::new(),
+                fragments: fragments.into(),
+            },
+        ),
+    ]);
+    if spec.samples_per_pixel == 3 {
+        obj.put(DataElement::new(
+            tags::PLANAR_CONFIGURATION,
+            VR::US,
+            PrimitiveValue::from(0_u16),
+        ));
+    }
+
+    obj.with_meta(
+        FileMetaTableBuilder::new()
+            .media_storage_sop_class_uid(SECONDARY_CAPTURE_IMAGE_STORAGE)
+            .transfer_syntax(transfer_syntax),
+    )
+    .expect("fixture file meta group should be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PixelDecoder;
+
+    #[test]
+    fn decode_rle_fixtures() {
+        for spec in [
+            FixtureSpec::new(10, 12),
+            FixtureSpec::new(10, 12).frames(3),
+            FixtureSpec::new(9, 7).bits_allocated(16),
+            FixtureSpec::new(8, 8).rgb(),
+            FixtureSpec::new(8, 300).rgb().bits_allocated(16).frames(2),
+        ] {
+            let fixture = rle_lossless(&spec);
+            let decoded = fixture.object.decode_pixel_data().unwrap();
+            assert_eq!(fixture.max_error(decoded.data()), Some(0), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn decode_jpeg_baseline_fixtures() {
+        for spec in [
+            FixtureSpec::new(16, 16),
+            FixtureSpec::new(24, 20).frames(2),
+            FixtureSpec::new(16, 32).rgb(),
+        ] {
+            let fixture = jpeg_baseline(&spec).unwrap();
+            let decoded = fixture.object.decode_pixel_data().unwrap();
+            assert!(
+                fixture.matches(decoded.data()),
+                "{:?}: {:?}",
+                spec,
+                fixture.max_error(decoded.data())
+            );
+        }
+
+        assert!(jpeg_baseline(&FixtureSpec::new(8, 8).bits_allocated(16)).is_err());
+    }
+
+    #[test]
+    fn decode_jpeg_lossless_fixtures() {
+        for spec in [
+            FixtureSpec::new(10, 12),
+            FixtureSpec::new(16, 16).frames(3),
+            FixtureSpec::new(9, 7).bits_allocated(16),
+            FixtureSpec::new(8, 8).rgb(),
+            FixtureSpec::new(12, 20).rgb().bits_allocated(16).frames(2),
+        ] {
+            let fixture = jpeg_lossless(&spec);
+            let decoded = fixture.object.decode_pixel_data().unwrap();
+            assert_eq!(fixture.max_error(decoded.data()), Some(0), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn decode_jpeg_baseline_with_trailing_padding() {
+        let spec = FixtureSpec::new(16, 16).frames(2);
+        let mut fixture = jpeg_baseline(&spec).unwrap();
+
+        // some encoders write more than one padding byte after end of image
+        let fragments = match fixture.object.element(tags::PIXEL_DATA).unwrap().value() {
+            Value::PixelSequence { fragments, .. } => fragments
+                .iter()
+                .map(|fragment| {
+                    let mut fragment = fragment.clone();
+                    fragment.extend([0; 4]);
+                    fragment
+                })
+                .collect::<C<_>>(),
+            _ => unreachable!(),
+        };
+        fixture.object.put(DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OB,
+            Value::PixelSequence {
+                offset_table: C::new(),
+                fragments,
+            },
+        ));
+
+        let decoded = fixture.object.decode_pixel_data().unwrap();
+        assert!(fixture.matches(decoded.data()));
+
+        // declaring more frames than there are images is an error
+        fixture.object.put(DataElement::new(
+            tags::NUMBER_OF_FRAMES,
+            VR::IS,
+            PrimitiveValue::from("3"),
+        ));
+        assert!(fixture.object.decode_pixel_data().is_err());
+    }
+}
@@ -52,6 +52,7 @@ where
         let pixel_representation = pixel_representation(self).context(GetAttributeSnafu)?;
         let rescale_intercept = rescale_intercept(self);
         let rescale_slope = rescale_slope(self);
+        let modality_lut = modality_lut(self, pixel_representation).context(GetAttributeSnafu)?;
         let number_of_frames = number_of_frames(self).context(GetAttributeSnafu)?;
         let voi_lut_function = voi_lut_function(self).context(GetAttributeSnafu)?;
         let voi_lut_function = voi_lut_function.and_then(|v| VoiLutFunction::try_from(&*v).ok());
@@ -141,6 +142,7 @@ where
             pixel_representation,
             rescale_intercept,
             rescale_slope,
+            modality_lut,
             voi_lut_function,
             window,
         })
@@ -85,10 +85,14 @@
 //! See [`ConvertOptions`] for the options available,
 //! including the default behavior for each method.
 //!
+//! Objects can also be transcoded to another transfer syntax
+//! with [`Transcode`], frame by frame,
+//! using the pixel data adapters of the transfer syntax registry.
+//!
 
 use byteorder::{ByteOrder, NativeEndian};
 use dicom_core::{value::Value, DataDictionary};
-use dicom_encoding::adapters::DecodeError;
+use dicom_encoding::adapters::{DecodeError, EncodeError};
 #[cfg(not(feature = "gdcm"))]
 use dicom_encoding::transfer_syntax::TransferSyntaxIndex;
 #[cfg(not(feature = "gdcm"))]
@@ -108,18 +112,35 @@ pub use image;
 pub use ndarray;
 
 mod attribute;
+mod bits;
+mod encapsulated;
 mod lut;
+pub mod presentation;
+mod transcode;
+pub mod video;
 
 pub(crate) mod transform;
 
 // re-exports
 pub use attribute::{PhotometricInterpretation, PixelRepresentation, PlanarConfiguration};
+pub use bits::{pack_bits, unpack_bits};
+pub use encapsulated::{
+    build_extended_offset_table, extract_encapsulated_frame, put_encapsulated_frames,
+    put_encapsulated_frames_with_options, EncapsulatedFrame, EncapsulationOptions,
+    OffsetTableOption,
+};
 pub use lut::{CreateLutError, Lut};
-pub use transform::{Rescale, VoiLutFunction, WindowLevel, WindowLevelTransform};
+pub use transcode::{Transcode, TranscodeOptions};
+pub use transform::{
+    ModalityLut, ModalityTransform, Rescale, VoiLutFunction, WindowLevel, WindowLevelTransform,
+};
 
 #[cfg(feature = "gdcm")]
 mod gdcm;
 
+#[cfg(any(test, feature = "test-support"))]
+pub mod fixtures;
+
 /// Error type for most pixel data related operations.
 #[derive(Debug, Snafu)]
 pub struct Error(InnerError);
@@ -188,11 +209,48 @@ pub enum InnerError {
     #[snafu(display("Could not decode pixel data"))]
     DecodePixelData { source: DecodeError },
 
+    #[snafu(display("Could not encode pixel data"))]
+    EncodePixelData { source: EncodeError },
+
+    #[snafu(display("Operation cancelled"))]
+    Cancelled { backtrace: Backtrace },
+
     #[snafu(display("Frame #{} is out of range", frame_number))]
     FrameOutOfRange {
         frame_number: u32,
         backtrace: Backtrace,
     },
+
+    #[snafu(display("Pixel data is not encapsulated"))]
+    NotEncapsulated { backtrace: Backtrace },
+
+    #[snafu(display("Could not determine the fragments of frame #{}", frame_number))]
+    UnknownFrameFragments {
+        frame_number: u32,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Offset table does not match the pixel data fragments"))]
+    InvalidOffsetTable { backtrace: Backtrace },
+
+    #[snafu(display("Frame offsets do not fit in the basic offset table"))]
+    BasicOffsetTableOverflow { backtrace: Backtrace },
+
+    #[snafu(display("Extended offset table requires one fragment per frame"))]
+    FragmentedExtendedOffsetTable { backtrace: Backtrace },
+
+    #[snafu(display("Transfer syntax `{}` is not a video transfer syntax", ts_uid))]
+    NotVideoTransferSyntax {
+        ts_uid: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Could not write video stream to '{}'", filename.display()))]
+    WriteVideoStream {
+        filename: std::path::PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -205,7 +263,8 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 /// 1. The Modality LUT function (`modality_lut`)
 ///    is applied to the raw pixel data sample values.
 ///    This is usually an affine function based on the
-///    _Rescale Slope_ and _Rescale Intercept_ attributes.
+///    _Rescale Slope_ and _Rescale Intercept_ attributes,
+///    or a lookup table from the _Modality LUT Sequence_.
 ///    If this option is set to [`None`](ModalityLutOption::None),
 ///    the VOI LUT function is ignored.
 /// 2. The VOI LUT function (`voi_lut`)
@@ -272,8 +331,10 @@ impl ConvertOptions {
 #[non_exhaustive]
 pub enum ModalityLutOption {
     /// _Default behavior:_
-    /// rescale the pixel data values
-    /// as described in the decoded pixel data.
+    /// transform the pixel data values
+    /// as described in the decoded pixel data,
+    /// using the _Modality LUT Sequence_ if present,
+    /// or the rescale parameters otherwise.
     Default,
     /// Rescale the pixel data values
     /// according to the given rescale parameters
@@ -382,6 +443,7 @@ pub struct DecodedPixelData<'a> {
     /// the planar configuration: 0 for standard, 1 for channel-contiguous
     planar_configuration: PlanarConfiguration,
     /// the number of bits allocated, as a multiple of 8
+    /// (single-bit pixel data is unpacked to 8 bits)
     bits_allocated: u16,
     /// the number of bits stored
     bits_stored: u16,
@@ -395,6 +457,8 @@ pub struct DecodedPixelData<'a> {
     rescale_intercept: f64,
     /// the pixel value rescale slope
     rescale_slope: f64,
+    /// the modality LUT, which takes precedence over the rescale parameters
+    modality_lut: Option<ModalityLut>,
     // the VOI LUT function
     voi_lut_function: Option<VoiLutFunction>,
     /// the window level specified via width and center
@@ -526,6 +590,31 @@ impl DecodedPixelData<'_> {
         }
     }
 
+    /// Retrieve the modality LUT defined by the object, if any.
+    #[inline]
+    pub fn modality_lut(&self) -> Option<&ModalityLut> {
+        self.modality_lut.as_ref()
+    }
+
+    /// Retrieve the modality transformation defined by the object:
+    /// the modality LUT if present,
+    /// or the rescale function otherwise.
+    pub fn modality_transform(&self) -> ModalityTransform {
+        match &self.modality_lut {
+            Some(lut) => ModalityTransform::Lut(lut.clone()),
+            None => ModalityTransform::Rescale(self.rescale()),
+        }
+    }
+
+    /// Retrieve the modality transformation to apply
+    /// according to the given modality LUT option.
+    fn modality_transform_for(&self, modality_lut: &ModalityLutOption) -> ModalityTransform {
+        match modality_lut {
+            ModalityLutOption::Override(rescale) => ModalityTransform::Rescale(*rescale),
+            _ => self.modality_transform(),
+        }
+    }
+
     /// Retrieve the VOI LUT function defined by the object, if any.
     #[inline]
     pub fn voi_lut_function(&self) -> Option<VoiLutFunction> {
@@ -785,23 +874,19 @@ impl DecodedPixelData<'_> {
                     }
                     // other
                     ModalityLutOption::Default | ModalityLutOption::Override(..) => {
-                        let rescale = if let ModalityLutOption::Override(rescale) = modality_lut {
-                            *rescale
-                        } else {
-                            self.rescale()
-                        };
+                        let modality = self.modality_transform_for(modality_lut);
 
                         let signed = self.pixel_representation == PixelRepresentation::Signed;
 
                         let lut: Lut<u8> = match (voi_lut, self.window) {
                             (VoiLutOption::Identity, _) => {
-                                Lut::new_rescale(8, false, rescale).context(CreateLutSnafu)?
+                                Lut::new_modality(8, false, &modality).context(CreateLutSnafu)?
                             }
                             (VoiLutOption::Default | VoiLutOption::First, Some(window)) => {
-                                Lut::new_rescale_and_window(
+                                Lut::new_modality_and_window(
                                     8,
                                     signed,
-                                    rescale,
+                                    &modality,
                                     WindowLevelTransform::new(
                                         self.voi_lut_function.unwrap_or_default(),
                                         window,
@@ -811,28 +896,28 @@ impl DecodedPixelData<'_> {
                             }
                             (VoiLutOption::Default | VoiLutOption::First, None) => {
                                 tracing::warn!("Could not find window level for object");
-                                Lut::new_rescale_and_normalize(
+                                Lut::new_modality_and_normalize(
                                     8,
                                     signed,
-                                    rescale,
+                                    &modality,
                                     data.iter().copied(),
                                 )
                                 .context(CreateLutSnafu)?
                             }
-                            (VoiLutOption::Custom(window), _) => Lut::new_rescale_and_window(
+                            (VoiLutOption::Custom(window), _) => Lut::new_modality_and_window(
                                 8,
                                 signed,
-                                rescale,
+                                &modality,
                                 WindowLevelTransform::new(
                                     self.voi_lut_function.unwrap_or_default(),
                                     *window,
                                 ),
                             )
                             .context(CreateLutSnafu)?,
-                            (VoiLutOption::Normalize, _) => Lut::new_rescale_and_normalize(
+                            (VoiLutOption::Normalize, _) => Lut::new_modality_and_normalize(
                                 8,
                                 signed,
-                                rescale,
+                                &modality,
                                 data.iter().copied(),
                             )
                             .context(CreateLutSnafu)?,
@@ -883,11 +968,7 @@ impl DecodedPixelData<'_> {
                     }
 
                     ModalityLutOption::Default | ModalityLutOption::Override(..) => {
-                        let rescale = if let ModalityLutOption::Override(rescale) = modality_lut {
-                            *rescale
-                        } else {
-                            Rescale::new(self.rescale_slope, self.rescale_intercept)
-                        };
+                        let modality = self.modality_transform_for(modality_lut);
 
                         // fetch pixel data as a slice of u16 values,
                         // irrespective of pixel signedness
@@ -901,13 +982,13 @@ impl DecodedPixelData<'_> {
                         // use 16-bit precision to prevent possible loss of precision in image
                         let lut: Lut<u16> = match (voi_lut, self.window) {
                             (VoiLutOption::Identity, _) => {
-                                Lut::new_rescale(self.bits_stored, signed, rescale)
+                                Lut::new_modality(self.bits_stored, signed, &modality)
                             }
                             (VoiLutOption::Default | VoiLutOption::First, Some(window)) => {
-                                Lut::new_rescale_and_window(
+                                Lut::new_modality_and_window(
                                     self.bits_stored,
                                     signed,
-                                    rescale,
+                                    &modality,
                                     WindowLevelTransform::new(
                                         self.voi_lut_function.unwrap_or_default(),
                                         window,
@@ -917,26 +998,26 @@ impl DecodedPixelData<'_> {
                             (VoiLutOption::Default | VoiLutOption::First, None) => {
                                 tracing::warn!("Could not find window level for object");
 
-                                Lut::new_rescale_and_normalize(
+                                Lut::new_modality_and_normalize(
                                     self.bits_stored,
                                     signed,
-                                    rescale,
+                                    &modality,
                                     samples.iter().copied(),
                                 )
                             }
-                            (VoiLutOption::Custom(window), _) => Lut::new_rescale_and_window(
+                            (VoiLutOption::Custom(window), _) => Lut::new_modality_and_window(
                                 self.bits_stored,
                                 signed,
-                                rescale,
+                                &modality,
                                 WindowLevelTransform::new(
                                     self.voi_lut_function.unwrap_or_default(),
                                     *window,
                                 ),
                             ),
-                            (VoiLutOption::Normalize, _) => Lut::new_rescale_and_normalize(
+                            (VoiLutOption::Normalize, _) => Lut::new_modality_and_normalize(
                                 self.bits_stored,
                                 signed,
-                                rescale,
+                                &modality,
                                 samples.iter().copied(),
                             ),
                         }
@@ -1111,21 +1192,17 @@ impl DecodedPixelData<'_> {
                     ModalityLutOption::Default | ModalityLutOption::Override(_)
                         if self.photometric_interpretation.is_monochrome() =>
                     {
-                        let rescale = if let ModalityLutOption::Override(rescale) = modality_lut {
-                            *rescale
-                        } else {
-                            self.rescale()
-                        };
+                        let modality = self.modality_transform_for(modality_lut);
                         let signed = self.pixel_representation == PixelRepresentation::Signed;
 
                         let lut: Lut<T> = match (voi_lut, self.window) {
                             (VoiLutOption::Default | VoiLutOption::Identity, _) => {
-                                Lut::new_rescale(8, signed, rescale)
+                                Lut::new_modality(8, signed, &modality)
                             }
-                            (VoiLutOption::First, Some(window)) => Lut::new_rescale_and_window(
+                            (VoiLutOption::First, Some(window)) => Lut::new_modality_and_window(
                                 8,
                                 signed,
-                                rescale,
+                                &modality,
                                 WindowLevelTransform::new(
                                     self.voi_lut_function.unwrap_or_default(),
                                     window,
@@ -1133,21 +1210,21 @@ impl DecodedPixelData<'_> {
                             ),
                             (VoiLutOption::First, None) => {
                                 tracing::warn!("Could not find window level for object");
-                                Lut::new_rescale(8, signed, rescale)
+                                Lut::new_modality(8, signed, &modality)
                             }
-                            (VoiLutOption::Custom(window), _) => Lut::new_rescale_and_window(
+                            (VoiLutOption::Custom(window), _) => Lut::new_modality_and_window(
                                 8,
                                 signed,
-                                rescale,
+                                &modality,
                                 WindowLevelTransform::new(
                                     self.voi_lut_function.unwrap_or_default(),
                                     *window,
                                 ),
                             ),
-                            (VoiLutOption::Normalize, _) => Lut::new_rescale_and_normalize(
+                            (VoiLutOption::Normalize, _) => Lut::new_modality_and_normalize(
                                 8,
                                 signed,
-                                rescale,
+                                &modality,
                                 data.iter().copied(),
                             ),
                         }
@@ -1174,22 +1251,18 @@ impl DecodedPixelData<'_> {
                     {
                         let samples = bytes_to_vec_u16(data);
 
-                        let rescale = if let ModalityLutOption::Override(rescale) = modality_lut {
-                            *rescale
-                        } else {
-                            self.rescale()
-                        };
+                        let modality = self.modality_transform_for(modality_lut);
 
                         let signed = self.pixel_representation == PixelRepresentation::Signed;
 
                         let lut: Lut<T> = match (voi_lut, self.window) {
                             (VoiLutOption::Default | VoiLutOption::Identity, _) => {
-                                Lut::new_rescale(self.bits_stored, signed, rescale)
+                                Lut::new_modality(self.bits_stored, signed, &modality)
                             }
-                            (VoiLutOption::First, Some(window)) => Lut::new_rescale_and_window(
+                            (VoiLutOption::First, Some(window)) => Lut::new_modality_and_window(
                                 self.bits_stored,
                                 signed,
-                                rescale,
+                                &modality,
                                 WindowLevelTransform::new(
                                     self.voi_lut_function.unwrap_or_default(),
                                     window,
@@ -1197,26 +1270,26 @@ impl DecodedPixelData<'_> {
                             ),
                             (VoiLutOption::First, None) => {
                                 tracing::warn!("Could not find window level for object");
-                                Lut::new_rescale_and_normalize(
+                                Lut::new_modality_and_normalize(
                                     self.bits_stored,
                                     signed,
-                                    rescale,
+                                    &modality,
                                     samples.iter().copied(),
                                 )
                             }
-                            (VoiLutOption::Custom(window), _) => Lut::new_rescale_and_window(
+                            (VoiLutOption::Custom(window), _) => Lut::new_modality_and_window(
                                 self.bits_stored,
                                 signed,
-                                rescale,
+                                &modality,
                                 WindowLevelTransform::new(
                                     self.voi_lut_function.unwrap_or_default(),
                                     *window,
                                 ),
                             ),
-                            (VoiLutOption::Normalize, _) => Lut::new_rescale_and_normalize(
+                            (VoiLutOption::Normalize, _) => Lut::new_modality_and_normalize(
                                 self.bits_stored,
                                 signed,
-                                rescale,
+                                &modality,
                                 samples.iter().copied(),
                             ),
                         }
@@ -1503,6 +1576,7 @@ where
         let pixel_representation = pixel_representation(self).context(GetAttributeSnafu)?;
         let rescale_intercept = rescale_intercept(self);
         let rescale_slope = rescale_slope(self);
+        let modality_lut = modality_lut(self, pixel_representation).context(GetAttributeSnafu)?;
         let number_of_frames = number_of_frames(self).context(GetAttributeSnafu)?;
         let voi_lut_function = voi_lut_function(self).context(GetAttributeSnafu)?;
         let voi_lut_function = voi_lut_function.and_then(|v| VoiLutFunction::try_from(&*v).ok());
@@ -1561,6 +1635,7 @@ where
                 pixel_representation,
                 rescale_intercept,
                 rescale_slope,
+                modality_lut,
                 voi_lut_function,
                 window,
             });
@@ -1581,6 +1656,17 @@ where
             Value::Sequence { items: _, size: _ } => InvalidPixelDataSnafu.fail()?,
         };
 
+        // unpack single-bit pixel data into one byte per sample
+        let (decoded_pixel_data, bits_allocated) = if bits_allocated == 1 {
+            let nr_samples = rows as usize
+                * cols as usize
+                * samples_per_pixel as usize
+                * number_of_frames as usize;
+            (unpack_bits(&decoded_pixel_data, nr_samples), 8)
+        } else {
+            (decoded_pixel_data, bits_allocated)
+        };
+
         Ok(DecodedPixelData {
             data: Cow::from(decoded_pixel_data),
             cols: cols.into(),
@@ -1595,6 +1681,7 @@ where
             pixel_representation,
             rescale_intercept,
             rescale_slope,
+            modality_lut,
             voi_lut_function,
             window,
         })
@@ -1607,6 +1694,130 @@ mod tests {
     use dicom_object::open_file;
     use dicom_test_files;
 
+    /// single-bit pixel data is unpacked into one byte per pixel
+    #[test]
+    fn test_decode_1bit_native() {
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::FileMetaTableBuilder;
+
+        // 2 frames of 3x3 pixels, with a diagonal
+        let pixels: Vec<u8> = (0..18).map(|i| (i % 9 % 4 == 0) as u8).collect();
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(3_u16)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(3_u16)),
+            DataElement::new(tags::NUMBER_OF_FRAMES, VR::IS, PrimitiveValue::from("2")),
+            DataElement::new(tags::SAMPLES_PER_PIXEL, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(
+                tags::PHOTOMETRIC_INTERPRETATION,
+                VR::CS,
+                PrimitiveValue::from("MONOCHROME2"),
+            ),
+            DataElement::new(tags::BITS_ALLOCATED, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(tags::BITS_STORED, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(tags::HIGH_BIT, VR::US, PrimitiveValue::from(0_u16)),
+            DataElement::new(
+                tags::PIXEL_REPRESENTATION,
+                VR::US,
+                PrimitiveValue::from(0_u16),
+            ),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                PrimitiveValue::from(pack_bits(&pixels)),
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.66.4")
+                .media_storage_sop_instance_uid("1.2.3.4")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap();
+
+        let decoded = obj.decode_pixel_data().unwrap();
+        assert_eq!(decoded.bits_allocated(), 8);
+        assert_eq!(decoded.data(), &pixels[..]);
+        assert_eq!(decoded.frame_data(1).unwrap(), &pixels[9..]);
+    }
+
+    /// the modality LUT sequence takes precedence over the rescale function
+    #[test]
+    fn test_modality_lut_sequence_native() {
+        use dicom_core::{smallvec::smallvec, DataElement, Length, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::FileMetaTableBuilder;
+
+        let pixels: Vec<u16> = vec![0, 1, 2, 3, 4, 5];
+        let lut_item = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::LUT_DESCRIPTOR,
+                VR::US,
+                PrimitiveValue::U16(smallvec![4, 1, 16]),
+            ),
+            DataElement::new(tags::MODALITY_LUT_TYPE, VR::LO, PrimitiveValue::from("HU")),
+            DataElement::new(
+                tags::LUT_DATA,
+                VR::OW,
+                PrimitiveValue::U16(smallvec![100, 200, 300, 400]),
+            ),
+        ]);
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(2_u16)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(3_u16)),
+            DataElement::new(tags::SAMPLES_PER_PIXEL, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(
+                tags::PHOTOMETRIC_INTERPRETATION,
+                VR::CS,
+                PrimitiveValue::from("MONOCHROME2"),
+            ),
+            DataElement::new(tags::BITS_ALLOCATED, VR::US, PrimitiveValue::from(16_u16)),
+            DataElement::new(tags::BITS_STORED, VR::US, PrimitiveValue::from(16_u16)),
+            DataElement::new(tags::HIGH_BIT, VR::US, PrimitiveValue::from(15_u16)),
+            DataElement::new(
+                tags::PIXEL_REPRESENTATION,
+                VR::US,
+                PrimitiveValue::from(0_u16),
+            ),
+            DataElement::new(tags::RESCALE_SLOPE, VR::DS, PrimitiveValue::from("2")),
+            DataElement::new(
+                tags::MODALITY_LUT_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: smallvec![lut_item],
+                    size: Length::UNDEFINED,
+                },
+            ),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OW,
+                PrimitiveValue::U16(pixels.iter().copied().collect()),
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("1.2.3.4")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap();
+
+        let decoded = obj.decode_pixel_data().unwrap();
+        assert_eq!(
+            decoded.modality_lut(),
+            Some(&ModalityLut::new(1, vec![100, 200, 300, 400]))
+        );
+
+        // values outside of the table are clamped
+        let values = decoded.to_vec::<u16>().unwrap();
+        assert_eq!(values, vec![100, 100, 200, 300, 400, 400]);
+
+        // raw values are kept when the modality LUT is skipped
+        let options = ConvertOptions::new().with_modality_lut(ModalityLutOption::None);
+        let values = decoded.to_vec_with_options::<u16>(&options).unwrap();
+        assert_eq!(values, pixels);
+    }
+
     #[test]
     fn test_to_vec_rgb() {
         let test_file = dicom_test_files::path("pydicom/SC_rgb_16bit.dcm").unwrap();
@@ -1951,18 +2162,12 @@ mod tests {
         const MAX_TEST_FRAMES: u32 = 16;
 
         #[rstest]
-        // jpeg2000 encoding not supported
+        // jpeg2000 decoding not supported
         #[should_panic(expected = "UnsupportedTransferSyntax { ts: \"1.2.840.10008.1.2.4.91\"")]
         #[case("pydicom/693_J2KI.dcm", 1)]
-        #[should_panic(expected = "UnsupportedTransferSyntax { ts: \"1.2.840.10008.1.2.4.90\"")]
+        #[should_panic(expected = "JPEG 2000 decoding is not supported")]
         #[case("pydicom/693_J2KR.dcm", 1)]
         //
-        // jpeg-ls encoding not supported
-        #[should_panic(expected = "UnsupportedTransferSyntax { ts: \"1.2.840.10008.1.2.4.80\"")]
-        #[case("pydicom/emri_small_jpeg_ls_lossless.dcm", 10)]
-        #[should_panic(expected = "UnsupportedTransferSyntax { ts: \"1.2.840.10008.1.2.4.80\"")]
-        #[case("pydicom/MR_small_jpeg_ls_lossless.dcm", 1)]
-        //
         // sample precicion of 12 not supported
         #[should_panic(expected = "Unsupported(SamplePrecision(12))")]
         #[case("pydicom/JPEG-lossy.dcm", 1)]
@@ -1975,6 +2180,10 @@ mod tests {
         #[case("pydicom/JPGLosslessP14SV1_1s_1f_8b.dcm", 1)]
         #[case("pydicom/SC_rgb_jpeg_gdcm.dcm", 1)]
         #[case("pydicom/SC_rgb_jpeg_lossy_gdcm.dcm", 1)]
+        //
+        // jpeg-ls encoding
+        #[case("pydicom/emri_small_jpeg_ls_lossless.dcm", 10)]
+        #[case("pydicom/MR_small_jpeg_ls_lossless.dcm", 1)]
 
         fn test_parse_jpeg_encoded_dicom_pixel_data(#[case] value: &str, #[case] frames: u32) {
             let test_file = dicom_test_files::path(value).unwrap();
@@ -12,7 +12,7 @@ use num_traits::{NumCast, ToPrimitive};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use snafu::{OptionExt, Snafu};
 
-use crate::{Rescale, WindowLevelTransform};
+use crate::{ModalityTransform, Rescale, WindowLevelTransform};
 
 /// The LUT could not be created:
 /// entry #{index} was mapped to {y_value},
@@ -149,7 +149,29 @@ where
         Self::new_with_fn(bits_stored, signed, |v| rescale.apply(v))
     }
 
-    /// Create a new LUT containing the given modality rescale transformation
+    /// Create a new LUT containing only the given modality transformation,
+    /// either a rescale function or a modality lookup table.
+    ///
+    /// - `bits_stored`:
+    ///   the number of bits effectively used to represent the sample values
+    ///   (the _Bits Stored_ DICOM attribute)
+    /// - `signed`:
+    ///   whether the input sample values are expected to be signed
+    ///   (_Pixel Representation_ = 1)
+    /// - `modality`: the modality transformation
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits_stored` is 0 or too large.
+    pub fn new_modality(
+        bits_stored: u16,
+        signed: bool,
+        modality: &ModalityTransform,
+    ) -> Result<Self, CreateLutError> {
+        Self::new_with_fn(bits_stored, signed, |v| modality.apply(v))
+    }
+
+    /// Create a new LUT containing the given modality transformation
     /// and a min-max normalization
     /// which satisfies the raw samples given.
     /// The sample type `I` is expected to be either `u8` or `u16`,
@@ -161,16 +183,16 @@ where
     /// - `signed`:
     ///   whether the input sample values are expected to be signed
     ///   (_Pixel Representation_ = 1)
-    /// - `rescale`: the rescale parameters
+    /// - `modality`: the modality transformation
     /// - `samples`: the raw pixel data samples expected to be fed to the LUT
     ///
     /// # Panics
     ///
     /// Panics if `bits_stored` is 0 or too large.
-    pub(crate) fn new_rescale_and_normalize<I>(
+    pub(crate) fn new_modality_and_normalize<I>(
         bits_stored: u16,
         signed: bool,
-        rescale: Rescale,
+        modality: &ModalityTransform,
         samples: I,
     ) -> Result<Self, CreateLutError>
     where
@@ -185,15 +207,7 @@ where
         let min: f64 = samples_f64.clone().fold(f64::MAX, |a, b| a.min(b));
         let max: f64 = samples_f64.fold(f64::MIN, |a, b| a.max(b));
 
-        // convert to f64 and swap if negative slope
-        let (min, max) = if rescale.slope < 0. {
-            (max, min)
-        } else {
-            (min, max)
-        };
-
-        let max = max * rescale.slope + rescale.intercept;
-        let min = min * rescale.slope + rescale.intercept;
+        let (min, max) = modality.output_range(min, max);
 
         // create a linear window level transform
         let voi = WindowLevelTransform::linear(crate::WindowLevel {
@@ -201,7 +215,7 @@ where
             center: (min + max) / 2.,
         });
 
-        Self::new_rescale_and_window(bits_stored, signed, rescale, voi)
+        Self::new_modality_and_window(bits_stored, signed, modality, voi)
     }
 
     /// Create a new LUT containing the modality rescale transformation
@@ -230,12 +244,40 @@ where
         signed: bool,
         rescale: Rescale,
         voi: WindowLevelTransform,
+    ) -> Result<Self, CreateLutError> {
+        Self::new_modality_and_window(bits_stored, signed, &rescale.into(), voi)
+    }
+
+    /// Create a new LUT containing the given modality transformation
+    /// and the VOI transformation defined by a window level.
+    ///
+    /// The amplitude of the output values
+    /// goes from 0 to `2^n - 1`, where `n` is the power of two
+    /// which follows `bits_stored` (or itself if it is a power of two).
+    ///
+    /// - `bits_stored`:
+    ///   the number of bits effectively used to represent the sample values
+    ///   (the _Bits Stored_ DICOM attribute)
+    /// - `signed`:
+    ///   whether the input sample values are expected to be signed
+    ///   (_Pixel Representation_ = 1)
+    /// - `modality`: the modality transformation
+    /// - `voi`: the value of interest (VOI) function and parameters
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits_stored` is 0 or too large.
+    pub fn new_modality_and_window(
+        bits_stored: u16,
+        signed: bool,
+        modality: &ModalityTransform,
+        voi: WindowLevelTransform,
     ) -> Result<Self, CreateLutError> {
         let bits_allocated = (bits_stored as usize).next_power_of_two();
         let y_max = ((1 << bits_allocated) - 1) as f64;
         Self::new_with_fn(bits_stored, signed, |v| {
             let x = v as f64;
-            let v = rescale.apply(x);
+            let v = modality.apply(x);
             voi.apply(v, y_max)
         })
     }
@@ -394,10 +436,10 @@ mod tests {
     #[test]
     fn lut_rescale_and_normalize_16bit() {
         let bits_stored = 16;
-        let lut: Lut<u16> = Lut::new_rescale_and_normalize(
+        let lut: Lut<u16> = Lut::new_modality_and_normalize(
             bits_stored,
             false,
-            Rescale::new(1., -1024.),
+            &Rescale::new(1., -1024.).into(),
             [0_u16, 1, 2, 500, 23].iter().copied(),
         )
         .unwrap();
@@ -0,0 +1,729 @@
+//! Persistence of presentation parameters chosen during rendering.
+//!
+//! Once a suitable window level has been found for an image,
+//! typically through interactive windowing,
+//! a [`WindowSelection`] can record it in one of two ways:
+//!
+//! - [`apply_to`](WindowSelection::apply_to)
+//!   writes the window back into the image instance itself,
+//!   replacing its _VOI LUT_ attributes;
+//! - [`create_presentation_state`](WindowSelection::create_presentation_state)
+//!   creates a new Grayscale Softcopy Presentation State (GSPS) object
+//!   referencing the image,
+//!   leaving the image untouched.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_dictionary_std::tags;
+//! # use dicom_object::InMemDicomObject;
+//! use dicom_pixeldata::presentation::WindowSelection;
+//! use dicom_pixeldata::WindowLevel;
+//!
+//! let mut obj = InMemDicomObject::new_empty();
+//! WindowSelection::new(WindowLevel { center: 40., width: 400. })
+//!     .explanation("SOFT TISSUE")
+//!     .apply_to(&mut obj);
+//!
+//! assert_eq!(obj.element(tags::WINDOW_CENTER)?.to_str()?, "40");
+//! assert_eq!(obj.element(tags::WINDOW_WIDTH)?.to_str()?, "400");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::{VoiLutFunction, WindowLevel};
+use dicom_core::chrono::{DateTime, FixedOffset, Local};
+use dicom_core::value::{PrimitiveValue, Value, C};
+use dicom_core::{DataDictionary, DataElement, Length, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_object::mem::InMemElement;
+use dicom_object::{FileDicomObject, FileMetaTableBuilder, InMemDicomObject};
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+
+/// SOP Class UID of the Grayscale Softcopy Presentation State Storage.
+const GRAYSCALE_SOFTCOPY_PRESENTATION_STATE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.11.1";
+
+/// Transfer syntax UID of Explicit VR Little Endian.
+const EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+
+/// Attributes of the Patient and General Study modules
+/// copied from the referenced image into a presentation state,
+/// all of which are type 2.
+const PATIENT_STUDY_ATTRIBUTES: [(Tag, VR); 9] = [
+    (tags::PATIENT_NAME, VR::PN),
+    (tags::PATIENT_ID, VR::LO),
+    (tags::PATIENT_BIRTH_DATE, VR::DA),
+    (tags::PATIENT_SEX, VR::CS),
+    (tags::STUDY_DATE, VR::DA),
+    (tags::STUDY_TIME, VR::TM),
+    (tags::REFERRING_PHYSICIAN_NAME, VR::PN),
+    (tags::STUDY_ID, VR::SH),
+    (tags::ACCESSION_NUMBER, VR::SH),
+];
+
+/// An error which may occur when creating a presentation state.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum CreatePresentationStateError {
+    /// A required attribute is missing from the referenced image
+    #[snafu(display("Missing required attribute `{}`", name))]
+    MissingAttribute {
+        name: &'static str,
+        backtrace: Backtrace,
+    },
+    /// An attribute of the referenced image could not be read
+    #[snafu(display("Could not read attribute `{}`", name))]
+    ReadAttribute {
+        name: &'static str,
+        #[snafu(backtrace)]
+        source: dicom_object::Error,
+    },
+    /// An attribute of the referenced image has an invalid value
+    #[snafu(display("Invalid value for attribute `{}`", name))]
+    ConvertAttribute {
+        name: &'static str,
+        source: dicom_core::value::ConvertValueError,
+        backtrace: Backtrace,
+    },
+    /// The file meta group of the presentation state could not be built
+    #[snafu(display("Could not build file meta group"))]
+    BuildMeta {
+        #[snafu(backtrace)]
+        source: dicom_object::Error,
+    },
+}
+
+pub type Result<T, E = CreatePresentationStateError> = std::result::Result<T, E>;
+
+/// A window level chosen for the presentation of an image,
+/// along with the VOI LUT function to apply it with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSelection {
+    /// the chosen window level
+    pub window_level: WindowLevel,
+    /// the VOI LUT function
+    pub voi_lut_function: VoiLutFunction,
+    /// the free text explanation of the window,
+    /// as in _Window Center & Width Explanation_
+    pub explanation: Option<String>,
+}
+
+impl WindowSelection {
+    /// Create a new window selection
+    /// with a linear VOI LUT function and no explanation.
+    pub fn new(window_level: WindowLevel) -> Self {
+        WindowSelection {
+            window_level,
+            voi_lut_function: VoiLutFunction::default(),
+            explanation: None,
+        }
+    }
+
+    /// Set the VOI LUT function.
+    pub fn voi_lut_function(mut self, voi_lut_function: VoiLutFunction) -> Self {
+        self.voi_lut_function = voi_lut_function;
+        self
+    }
+
+    /// Set the explanation of the window.
+    pub fn explanation(mut self, explanation: impl Into<String>) -> Self {
+        self.explanation = Some(explanation.into());
+        self
+    }
+
+    /// Write this window into the _VOI LUT_ module of the given object.
+    ///
+    /// The chosen window becomes the only window of the object:
+    /// any previous _Window Center_, _Window Width_
+    /// and their explanations are replaced,
+    /// and the _VOI LUT Sequence_ is removed
+    /// so that it does not take precedence over the window.
+    pub fn apply_to<D>(&self, obj: &mut InMemDicomObject<D>)
+    where
+        D: DataDictionary + Clone,
+    {
+        obj.remove_element(tags::VOILUT_SEQUENCE);
+        for elem in self.to_elements() {
+            obj.put(elem);
+        }
+        if self.explanation.is_none() {
+            obj.remove_element(tags::WINDOW_CENTER_WIDTH_EXPLANATION);
+        }
+    }
+
+    /// Create a new Grayscale Softcopy Presentation State object
+    /// which applies this window to the given image,
+    /// with the current date and time as the presentation creation date-time.
+    ///
+    /// The new object belongs to the patient and study of the image,
+    /// and carries its _Modality LUT_ attributes
+    /// so that the window is interpreted against the same values.
+    /// The image itself is not modified.
+    pub fn create_presentation_state<D>(
+        &self,
+        image: &InMemDicomObject<D>,
+        options: &PresentationStateOptions,
+    ) -> Result<FileDicomObject<InMemDicomObject>>
+    where
+        D: DataDictionary + Clone,
+    {
+        self.create_presentation_state_at(image, options, Local::now().into())
+    }
+
+    /// Create a new Grayscale Softcopy Presentation State object
+    /// which applies this window to the given image,
+    /// with the given presentation creation date-time.
+    pub fn create_presentation_state_at<D>(
+        &self,
+        image: &InMemDicomObject<D>,
+        options: &PresentationStateOptions,
+        datetime: DateTime<FixedOffset>,
+    ) -> Result<FileDicomObject<InMemDicomObject>>
+    where
+        D: DataDictionary + Clone,
+    {
+        let sop_class_uid = required_str(image, tags::SOP_CLASS_UID, "SOPClassUID")?;
+        let sop_instance_uid = required_str(image, tags::SOP_INSTANCE_UID, "SOPInstanceUID")?;
+        let study_instance_uid = required_str(image, tags::STUDY_INSTANCE_UID, "StudyInstanceUID")?;
+        let series_instance_uid =
+            required_str(image, tags::SERIES_INSTANCE_UID, "SeriesInstanceUID")?;
+        let rows = required_int(image, tags::ROWS, "Rows")?;
+        let columns = required_int(image, tags::COLUMNS, "Columns")?;
+
+        let mut obj = InMemDicomObject::new_empty();
+
+        if let Some(elem) = optional_elem(image, tags::SPECIFIC_CHARACTER_SET) {
+            obj.put(elem);
+        }
+        for &(tag, vr) in &PATIENT_STUDY_ATTRIBUTES {
+            let elem = optional_elem(image, tag)
+                .unwrap_or_else(|| DataElement::new(tag, vr, PrimitiveValue::Empty));
+            obj.put(elem);
+        }
+
+        let date = datetime.format("%Y%m%d").to_string();
+        let time = datetime.format("%H%M%S%.6f").to_string();
+        let presentation_lut_shape = match optional_str(image, tags::PHOTOMETRIC_INTERPRETATION) {
+            Some(pi) if pi == "MONOCHROME1" => "INVERSE",
+            _ => "IDENTITY",
+        };
+
+        let elements = [
+            // SOP Common
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from(GRAYSCALE_SOFTCOPY_PRESENTATION_STATE_STORAGE),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(options.sop_instance_uid.as_str()),
+            ),
+            // General Study
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(study_instance_uid.as_str()),
+            ),
+            // Presentation Series
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("PR")),
+            DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(options.series_instance_uid.as_str()),
+            ),
+            DataElement::new(
+                tags::SERIES_NUMBER,
+                VR::IS,
+                PrimitiveValue::from(options.series_number.to_string()),
+            ),
+            // General Equipment
+            DataElement::new(tags::MANUFACTURER, VR::LO, PrimitiveValue::Empty),
+            // Presentation State Identification
+            DataElement::new(
+                tags::INSTANCE_NUMBER,
+                VR::IS,
+                PrimitiveValue::from(options.instance_number.to_string()),
+            ),
+            DataElement::new(
+                tags::CONTENT_LABEL,
+                VR::CS,
+                PrimitiveValue::from(options.content_label.as_str()),
+            ),
+            DataElement::new(
+                tags::CONTENT_DESCRIPTION,
+                VR::LO,
+                options
+                    .content_description
+                    .as_deref()
+                    .map(PrimitiveValue::from)
+                    .unwrap_or(PrimitiveValue::Empty),
+            ),
+            DataElement::new(
+                tags::CONTENT_CREATOR_NAME,
+                VR::PN,
+                options
+                    .content_creator_name
+                    .as_deref()
+                    .map(PrimitiveValue::from)
+                    .unwrap_or(PrimitiveValue::Empty),
+            ),
+            DataElement::new(
+                tags::PRESENTATION_CREATION_DATE,
+                VR::DA,
+                PrimitiveValue::from(date),
+            ),
+            DataElement::new(
+                tags::PRESENTATION_CREATION_TIME,
+                VR::TM,
+                PrimitiveValue::from(time),
+            ),
+            // Presentation State Relationship
+            DataElement::new(
+                tags::REFERENCED_SERIES_SEQUENCE,
+                VR::SQ,
+                sequence(vec![InMemDicomObject::from_element_iter(vec![
+                    DataElement::new(
+                        tags::SERIES_INSTANCE_UID,
+                        VR::UI,
+                        PrimitiveValue::from(series_instance_uid.as_str()),
+                    ),
+                    DataElement::new(
+                        tags::REFERENCED_IMAGE_SEQUENCE,
+                        VR::SQ,
+                        sequence(vec![image_reference(&sop_class_uid, &sop_instance_uid)]),
+                    ),
+                ])]),
+            ),
+            // Displayed Area
+            DataElement::new(
+                tags::DISPLAYED_AREA_SELECTION_SEQUENCE,
+                VR::SQ,
+                sequence(vec![InMemDicomObject::from_element_iter(vec![
+                    DataElement::new(
+                        tags::DISPLAYED_AREA_TOP_LEFT_HAND_CORNER,
+                        VR::SL,
+                        PrimitiveValue::from([1_i32, 1]),
+                    ),
+                    DataElement::new(
+                        tags::DISPLAYED_AREA_BOTTOM_RIGHT_HAND_CORNER,
+                        VR::SL,
+                        PrimitiveValue::from([i32::from(columns), i32::from(rows)]),
+                    ),
+                    DataElement::new(
+                        tags::PRESENTATION_SIZE_MODE,
+                        VR::CS,
+                        PrimitiveValue::from("SCALE TO FIT"),
+                    ),
+                ])]),
+            ),
+            // Softcopy VOI LUT
+            DataElement::new(
+                tags::SOFTCOPY_VOILUT_SEQUENCE,
+                VR::SQ,
+                sequence(vec![InMemDicomObject::from_element_iter(
+                    self.to_elements(),
+                )]),
+            ),
+            // Softcopy Presentation LUT
+            DataElement::new(
+                tags::PRESENTATION_LUT_SHAPE,
+                VR::CS,
+                PrimitiveValue::from(presentation_lut_shape),
+            ),
+        ];
+        for elem in elements {
+            obj.put(elem);
+        }
+
+        // Modality LUT
+        for &tag in &[
+            tags::RESCALE_INTERCEPT,
+            tags::RESCALE_SLOPE,
+            tags::RESCALE_TYPE,
+        ] {
+            if let Some(elem) = optional_elem(image, tag) {
+                obj.put(elem);
+            }
+        }
+
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax(EXPLICIT_VR_LITTLE_ENDIAN)
+                .media_storage_sop_class_uid(GRAYSCALE_SOFTCOPY_PRESENTATION_STATE_STORAGE),
+        )
+        .context(BuildMetaSnafu)
+    }
+
+    /// Build the window attributes of a _VOI LUT_ module.
+    fn to_elements<D>(&self) -> Vec<InMemElement<D>> {
+        let mut elements = vec![
+            DataElement::new(
+                tags::WINDOW_CENTER,
+                VR::DS,
+                PrimitiveValue::from(decimal_string(self.window_level.center)),
+            ),
+            DataElement::new(
+                tags::WINDOW_WIDTH,
+                VR::DS,
+                PrimitiveValue::from(decimal_string(self.window_level.width)),
+            ),
+            DataElement::new(
+                tags::VOILUT_FUNCTION,
+                VR::CS,
+                PrimitiveValue::from(self.voi_lut_function.as_str()),
+            ),
+        ];
+        if let Some(explanation) = &self.explanation {
+            elements.push(DataElement::new(
+                tags::WINDOW_CENTER_WIDTH_EXPLANATION,
+                VR::LO,
+                PrimitiveValue::from(explanation.as_str()),
+            ));
+        }
+        elements
+    }
+}
+
+/// Options for the creation of a presentation state object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresentationStateOptions {
+    sop_instance_uid: String,
+    series_instance_uid: String,
+    series_number: u32,
+    instance_number: u32,
+    content_label: String,
+    content_description: Option<String>,
+    content_creator_name: Option<String>,
+}
+
+impl PresentationStateOptions {
+    /// Create a new set of options
+    /// with the given SOP Instance UID and Series Instance UID
+    /// of the presentation state to create.
+    ///
+    /// The series and instance numbers default to 1,
+    /// and the content label defaults to `WINDOW`.
+    pub fn new(
+        sop_instance_uid: impl Into<String>,
+        series_instance_uid: impl Into<String>,
+    ) -> Self {
+        PresentationStateOptions {
+            sop_instance_uid: sop_instance_uid.into(),
+            series_instance_uid: series_instance_uid.into(),
+            series_number: 1,
+            instance_number: 1,
+            content_label: "WINDOW".to_string(),
+            content_description: None,
+            content_creator_name: None,
+        }
+    }
+
+    /// Set the series number of the presentation state.
+    pub fn series_number(mut self, series_number: u32) -> Self {
+        self.series_number = series_number;
+        self
+    }
+
+    /// Set the instance number of the presentation state.
+    pub fn instance_number(mut self, instance_number: u32) -> Self {
+        self.instance_number = instance_number;
+        self
+    }
+
+    /// Set the content label of the presentation state.
+    ///
+    /// This is a code string,
+    /// which should be in upper case and at most 16 characters long.
+    pub fn content_label(mut self, content_label: impl Into<String>) -> Self {
+        self.content_label = content_label.into();
+        self
+    }
+
+    /// Set the content description of the presentation state.
+    pub fn content_description(mut self, content_description: impl Into<String>) -> Self {
+        self.content_description = Some(content_description.into());
+        self
+    }
+
+    /// Set the name of the operator who created the presentation state.
+    pub fn content_creator_name(mut self, content_creator_name: impl Into<String>) -> Self {
+        self.content_creator_name = Some(content_creator_name.into());
+        self
+    }
+}
+
+/// Format a number as a decimal string
+/// no longer than the 16 characters allowed by the DS value representation.
+fn decimal_string(value: f64) -> String {
+    let text = value.to_string();
+    if text.len() <= 16 {
+        return text;
+    }
+    (0..=6)
+        .rev()
+        .map(|precision| format!("{:.*}", precision, value))
+        .find(|text| text.len() <= 16)
+        .unwrap_or_else(|| format!("{:.6e}", value))
+}
+
+fn sequence(items: Vec<InMemDicomObject>) -> Value<InMemDicomObject, Vec<u8>> {
+    Value::Sequence {
+        items: C::from_vec(items),
+        size: Length::UNDEFINED,
+    }
+}
+
+fn image_reference(sop_class_uid: &str, sop_instance_uid: &str) -> InMemDicomObject {
+    InMemDicomObject::from_element_iter(vec![
+        DataElement::new(
+            tags::REFERENCED_SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from(sop_class_uid),
+        ),
+        DataElement::new(
+            tags::REFERENCED_SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(sop_instance_uid),
+        ),
+    ])
+}
+
+/// Copy an element of the image, if present,
+/// into an element of a standard dictionary object.
+fn optional_elem<D>(image: &InMemDicomObject<D>, tag: Tag) -> Option<InMemElement>
+where
+    D: DataDictionary + Clone,
+{
+    let elem = image.element_opt(tag).ok().flatten()?;
+    elem.value()
+        .primitive()
+        .map(|value| DataElement::new(tag, elem.vr(), value.clone()))
+}
+
+fn optional_str<D>(image: &InMemDicomObject<D>, tag: Tag) -> Option<String>
+where
+    D: DataDictionary + Clone,
+{
+    let elem = optional_elem(image, tag)?;
+    let value = elem.to_str().ok()?;
+    Some(value.trim_end_matches([' ', '\0']).to_string())
+}
+
+fn required_str<D>(image: &InMemDicomObject<D>, tag: Tag, name: &'static str) -> Result<String>
+where
+    D: DataDictionary + Clone,
+{
+    optional_str(image, tag)
+        .filter(|s| !s.is_empty())
+        .context(MissingAttributeSnafu { name })
+}
+
+fn required_int<D>(image: &InMemDicomObject<D>, tag: Tag, name: &'static str) -> Result<u16>
+where
+    D: DataDictionary + Clone,
+{
+    image
+        .element_opt(tag)
+        .context(ReadAttributeSnafu { name })?
+        .context(MissingAttributeSnafu { name })?
+        .to_int::<u16>()
+        .context(ConvertAttributeSnafu { name })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::chrono::TimeZone;
+
+    fn image() -> InMemDicomObject {
+        InMemDicomObject::from_element_iter(vec![
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.2"),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.1"),
+            ),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.2"),
+            ),
+            DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.3"),
+            ),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("123")),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(512_u16)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(256_u16)),
+            DataElement::new(
+                tags::PHOTOMETRIC_INTERPRETATION,
+                VR::CS,
+                PrimitiveValue::from("MONOCHROME2"),
+            ),
+            DataElement::new(
+                tags::RESCALE_INTERCEPT,
+                VR::DS,
+                PrimitiveValue::from("-1024"),
+            ),
+            DataElement::new(tags::RESCALE_SLOPE, VR::DS, PrimitiveValue::from("1")),
+            DataElement::new(
+                tags::WINDOW_CENTER,
+                VR::DS,
+                PrimitiveValue::from("100\\200"),
+            ),
+            DataElement::new(tags::WINDOW_WIDTH, VR::DS, PrimitiveValue::from("50\\60")),
+            DataElement::new(
+                tags::WINDOW_CENTER_WIDTH_EXPLANATION,
+                VR::LO,
+                PrimitiveValue::from("A\\B"),
+            ),
+        ])
+    }
+
+    fn str_of(obj: &InMemDicomObject, tag: Tag) -> String {
+        obj.element(tag).unwrap().to_str().unwrap().into_owned()
+    }
+
+    #[test]
+    fn apply_window_to_instance() {
+        let mut obj = image();
+        WindowSelection::new(WindowLevel {
+            center: 40.,
+            width: 400.5,
+        })
+        .voi_lut_function(VoiLutFunction::Sigmoid)
+        .explanation("SOFT TISSUE")
+        .apply_to(&mut obj);
+
+        assert_eq!(str_of(&obj, tags::WINDOW_CENTER), "40");
+        assert_eq!(str_of(&obj, tags::WINDOW_WIDTH), "400.5");
+        assert_eq!(str_of(&obj, tags::VOILUT_FUNCTION), "SIGMOID");
+        assert_eq!(
+            str_of(&obj, tags::WINDOW_CENTER_WIDTH_EXPLANATION),
+            "SOFT TISSUE"
+        );
+
+        // previous explanations do not outlive their windows
+        WindowSelection::new(WindowLevel {
+            center: 0.,
+            width: 1.,
+        })
+        .apply_to(&mut obj);
+        assert!(obj
+            .element_opt(tags::WINDOW_CENTER_WIDTH_EXPLANATION)
+            .unwrap()
+            .is_none());
+        assert_eq!(str_of(&obj, tags::VOILUT_FUNCTION), "LINEAR");
+    }
+
+    #[test]
+    fn decimal_strings_fit_in_ds() {
+        assert_eq!(decimal_string(-1024.), "-1024");
+        assert_eq!(decimal_string(0.25), "0.25");
+        assert_eq!(decimal_string(1. / 3.), "0.333333");
+        assert!(decimal_string(-123456.789012345).len() <= 16);
+        assert!(decimal_string(1e300).len() <= 16);
+    }
+
+    #[test]
+    fn create_gsps_for_image() {
+        let image = image();
+        let datetime = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2022, 3, 4, 10, 20, 30)
+            .unwrap();
+        let options = PresentationStateOptions::new("2.25.10", "2.25.11")
+            .content_description("Soft tissue window");
+        let gsps = WindowSelection::new(WindowLevel {
+            center: 40.,
+            width: 400.,
+        })
+        .explanation("SOFT TISSUE")
+        .create_presentation_state_at(&image, &options, datetime)
+        .unwrap();
+
+        assert_eq!(
+            gsps.meta().media_storage_sop_class_uid(),
+            GRAYSCALE_SOFTCOPY_PRESENTATION_STATE_STORAGE
+        );
+        assert_eq!(gsps.meta().media_storage_sop_instance_uid(), "2.25.10");
+        assert_eq!(str_of(&gsps, tags::MODALITY), "PR");
+        assert_eq!(str_of(&gsps, tags::SERIES_INSTANCE_UID), "2.25.11");
+        assert_eq!(str_of(&gsps, tags::STUDY_INSTANCE_UID), "2.25.2");
+        assert_eq!(str_of(&gsps, tags::PATIENT_NAME), "Doe^John");
+        assert_eq!(str_of(&gsps, tags::STUDY_DATE), "");
+        assert_eq!(str_of(&gsps, tags::PRESENTATION_CREATION_DATE), "20220304");
+        assert_eq!(str_of(&gsps, tags::RESCALE_INTERCEPT), "-1024");
+        assert_eq!(str_of(&gsps, tags::PRESENTATION_LUT_SHAPE), "IDENTITY");
+
+        let series = gsps
+            .element(tags::REFERENCED_SERIES_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(str_of(&series[0], tags::SERIES_INSTANCE_UID), "2.25.3");
+        let images = series[0]
+            .element(tags::REFERENCED_IMAGE_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap();
+        assert_eq!(
+            str_of(&images[0], tags::REFERENCED_SOP_INSTANCE_UID),
+            "2.25.1"
+        );
+
+        let area = &gsps
+            .element(tags::DISPLAYED_AREA_SELECTION_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(
+            area.element(tags::DISPLAYED_AREA_BOTTOM_RIGHT_HAND_CORNER)
+                .unwrap()
+                .to_multi_int::<i32>()
+                .unwrap(),
+            vec![256, 512]
+        );
+
+        let voi = &gsps
+            .element(tags::SOFTCOPY_VOILUT_SEQUENCE)
+            .unwrap()
+            .items()
+            .unwrap()[0];
+        assert_eq!(str_of(voi, tags::WINDOW_CENTER), "40");
+        assert_eq!(str_of(voi, tags::WINDOW_WIDTH), "400");
+        assert_eq!(
+            str_of(voi, tags::WINDOW_CENTER_WIDTH_EXPLANATION),
+            "SOFT TISSUE"
+        );
+
+        // the image is left untouched
+        assert_eq!(str_of(&image, tags::WINDOW_CENTER), "100\\200");
+    }
+
+    #[test]
+    fn create_gsps_requires_image_identification() {
+        let mut image = image();
+        image.remove_element(tags::SOP_INSTANCE_UID);
+        let err = WindowSelection::new(WindowLevel {
+            center: 40.,
+            width: 400.,
+        })
+        .create_presentation_state(&image, &PresentationStateOptions::new("2.25.10", "2.25.11"))
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CreatePresentationStateError::MissingAttribute {
+                name: "SOPInstanceUID",
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,462 @@
+//! Transcoding of DICOM objects to another transfer syntax.
+//!
+//! [`Transcode`] changes the transfer syntax of a file object,
+//! decoding its pixel data one frame at a time
+//! and encoding each frame into the target transfer syntax
+//! with the pixel data adapter registered for it.
+//! Native pixel data in little endian can be both source and target.
+//!
+//! Since transcoding large multi-frame objects may take a while,
+//! [`transcode_with_progress`](Transcode::transcode_with_progress)
+//! reports progress to a [`Progress`] observer after each frame,
+//! which may also cancel the operation.
+//!
+//! The transcoding equipment can be recorded
+//! in the _Contributing Equipment Sequence_ of the object
+//! through [`TranscodeOptions::equipment`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! use dicom_object::open_file;
+//! use dicom_object::progress::{ProgressControl, ProgressStatus};
+//! use dicom_pixeldata::{Transcode, TranscodeOptions};
+//! use dicom_transfer_syntax_registry::entries::RLE_LOSSLESS;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let mut obj = open_file("multiframe.dcm")?;
+//! obj.transcode_with_progress(
+//!     &RLE_LOSSLESS.erased(),
+//!     &TranscodeOptions::new(),
+//!     |status: &ProgressStatus| {
+//!         println!("{} frames encoded", status.items_completed);
+//!         ProgressControl::Continue
+//!     },
+//! )?;
+//! obj.write_to_file("multiframe_rle.dcm")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    put_encapsulated_frames, CancelledSnafu, DecodePixelDataSnafu, EncodePixelDataSnafu,
+    GetAttributeSnafu, InvalidPixelDataSnafu, Result, UnsupportedOtherSnafu,
+    UnsupportedTransferSyntaxSnafu,
+};
+use dicom_core::value::{PrimitiveValue, C};
+use dicom_core::{DataDictionary, DataElement, VR};
+use dicom_dictionary_std::tags;
+use dicom_encoding::adapters::{EncodeOptions, PixelDataObject, RawPixelData};
+use dicom_encoding::transfer_syntax::{Codec, Endianness, TransferSyntaxIndex};
+use dicom_encoding::TransferSyntax;
+use dicom_object::equipment::{ContributingEquipment, PurposeOfReference};
+use dicom_object::progress::{NoProgress, Progress, ProgressStatus};
+use dicom_object::{FileDicomObject, InMemDicomObject};
+use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+use snafu::{ensure, OptionExt, ResultExt};
+
+/// Options for transcoding an object to another transfer syntax.
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct TranscodeOptions {
+    /// The options for encoding each frame
+    /// into the target transfer syntax
+    pub encode: EncodeOptions,
+    /// The equipment to record as modifying equipment
+    /// in the _Contributing Equipment Sequence_,
+    /// if any
+    pub equipment: Option<ContributingEquipment>,
+}
+
+impl TranscodeOptions {
+    /// Create a new set of transcoding options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the options for encoding each frame
+    /// into the target transfer syntax.
+    pub fn encode_options(mut self, encode: EncodeOptions) -> Self {
+        self.encode = encode;
+        self
+    }
+
+    /// Set the equipment to record as modifying equipment
+    /// in the _Contributing Equipment Sequence_ of the object.
+    /// No equipment is recorded by default.
+    pub fn equipment(mut self, equipment: ContributingEquipment) -> Self {
+        self.equipment = Some(equipment);
+        self
+    }
+}
+
+/// A DICOM object which can be transcoded to another transfer syntax.
+pub trait Transcode {
+    /// Transcode the object to the given transfer syntax,
+    /// with the default options.
+    fn transcode(&mut self, ts: &TransferSyntax) -> Result<()> {
+        self.transcode_with_options(ts, &TranscodeOptions::default())
+    }
+
+    /// Transcode the object to the given transfer syntax,
+    /// with the given options.
+    fn transcode_with_options(
+        &mut self,
+        ts: &TransferSyntax,
+        options: &TranscodeOptions,
+    ) -> Result<()> {
+        self.transcode_with_progress(ts, options, NoProgress)
+    }
+
+    /// Transcode the object to the given transfer syntax,
+    /// with the given options,
+    /// reporting progress to the given observer after each frame.
+    ///
+    /// The status reports the number of frames encoded
+    /// as the number of items completed,
+    /// and the number of bytes of native pixel data processed.
+    /// The object is left unchanged on failure or cancellation.
+    fn transcode_with_progress<P>(
+        &mut self,
+        ts: &TransferSyntax,
+        options: &TranscodeOptions,
+        progress: P,
+    ) -> Result<()>
+    where
+        P: Progress;
+}
+
+impl<D> Transcode for FileDicomObject<InMemDicomObject<D>>
+where
+    D: DataDictionary + Clone,
+{
+    fn transcode_with_progress<P>(
+        &mut self,
+        ts: &TransferSyntax,
+        options: &TranscodeOptions,
+        mut progress: P,
+    ) -> Result<()>
+    where
+        P: Progress,
+    {
+        let source_uid = self.meta().transfer_syntax();
+        let source_ts = TransferSyntaxRegistry
+            .get(source_uid)
+            .with_context(|| UnsupportedTransferSyntaxSnafu { ts: source_uid })?;
+        ensure!(
+            is_native_le(ts) || matches!(ts.codec(), Codec::PixelData(_)),
+            UnsupportedTransferSyntaxSnafu { ts: ts.uid() }
+        );
+
+        let mut frame = NativeFrame::from_object(self)?;
+        let number_of_frames =
+            crate::attribute::number_of_frames(self).context(GetAttributeSnafu)?;
+        let frame_len = frame.len();
+
+        // decode the source pixel data one frame at a time
+        let native = if is_native_le(source_ts) {
+            let data = self
+                .raw_pixel_data()
+                .and_then(|raw| raw.fragments.into_iter().next())
+                .context(InvalidPixelDataSnafu)?;
+            Some(data)
+        } else if let Codec::PixelData(_) = source_ts.codec() {
+            // pixels are interpreted by the decoder
+            frame.photometric_interpretation = match frame.samples_per_pixel {
+                1 => "MONOCHROME2".to_string(),
+                3 => "RGB".to_string(),
+                _ => frame.photometric_interpretation,
+            };
+            frame.planar_configuration = 0;
+            None
+        } else {
+            return UnsupportedTransferSyntaxSnafu { ts: source_uid }.fail()?;
+        };
+
+        let mut status = ProgressStatus::new(
+            Some(frame_len as u64 * u64::from(number_of_frames)),
+            Some(u64::from(number_of_frames)),
+        );
+        let mut native_frames = Vec::new();
+        let mut encoded_frames = Vec::new();
+        for i in 0..number_of_frames {
+            frame.data.clear();
+            match (&native, source_ts.codec()) {
+                (Some(data), _) => {
+                    let start = i as usize * frame_len;
+                    let data = data
+                        .get(start..start + frame_len)
+                        .context(InvalidPixelDataSnafu)?;
+                    frame.data.extend_from_slice(data);
+                }
+                (None, Codec::PixelData(adapter)) => {
+                    adapter
+                        .decode_frame(self, i, &mut frame.data)
+                        .context(DecodePixelDataSnafu)?;
+                }
+                _ => unreachable!("source transfer syntax was checked above"),
+            }
+
+            if let Codec::PixelData(adapter) = ts.codec() {
+                let mut encoded = Vec::new();
+                adapter
+                    .encode_frame(&frame, 0, options.encode.clone(), &mut encoded)
+                    .context(EncodePixelDataSnafu)?;
+                encoded_frames.push(encoded);
+            } else {
+                native_frames.extend_from_slice(&frame.data);
+            }
+
+            status.bytes_processed += frame_len as u64;
+            status.items_completed += 1;
+            ensure!(!progress.update(&status).is_cancel(), CancelledSnafu);
+        }
+
+        if let Codec::PixelData(_) = ts.codec() {
+            put_encapsulated_frames(self, encoded_frames);
+        } else {
+            let vr = if frame.bits_allocated > 8 {
+                VR::OW
+            } else {
+                VR::OB
+            };
+            self.remove_element(tags::EXTENDED_OFFSET_TABLE);
+            self.remove_element(tags::EXTENDED_OFFSET_TABLE_LENGTHS);
+            self.put(DataElement::new(
+                tags::PIXEL_DATA,
+                vr,
+                PrimitiveValue::from(native_frames),
+            ));
+        }
+        self.put(DataElement::new(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            VR::CS,
+            PrimitiveValue::from(frame.photometric_interpretation),
+        ));
+        if frame.samples_per_pixel > 1 {
+            self.put(DataElement::new(
+                tags::PLANAR_CONFIGURATION,
+                VR::US,
+                PrimitiveValue::from(frame.planar_configuration),
+            ));
+        }
+
+        if let Some(equipment) = &options.equipment {
+            self.add_contributing_equipment(equipment, PurposeOfReference::Modifying);
+        }
+
+        let meta = self.meta_mut();
+        meta.set_transfer_syntax(ts);
+        meta.update_information_group_length();
+        Ok(())
+    }
+}
+
+/// Check whether the transfer syntax holds native pixel data in little endian.
+fn is_native_le(ts: &TransferSyntax) -> bool {
+    matches!(ts.codec(), Codec::None) && ts.endianness() == Endianness::Little
+}
+
+/// A single frame of native pixel data,
+/// with the image pixel attributes of the object it belongs to.
+#[derive(Debug)]
+struct NativeFrame {
+    rows: u16,
+    cols: u16,
+    samples_per_pixel: u16,
+    bits_allocated: u16,
+    bits_stored: u16,
+    pixel_representation: u16,
+    planar_configuration: u16,
+    photometric_interpretation: String,
+    data: Vec<u8>,
+}
+
+impl NativeFrame {
+    /// Collect the image pixel attributes of the given object,
+    /// with no pixel data yet.
+    fn from_object<D>(obj: &FileDicomObject<InMemDicomObject<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        use crate::attribute::*;
+
+        let bits_allocated = bits_allocated(obj).context(GetAttributeSnafu)?;
+        ensure!(
+            bits_allocated > 0 && bits_allocated % 8 == 0,
+            UnsupportedOtherSnafu {
+                name: "BitsAllocated",
+                value: bits_allocated.to_string(),
+            }
+        );
+        Ok(NativeFrame {
+            rows: rows(obj).context(GetAttributeSnafu)?,
+            cols: cols(obj).context(GetAttributeSnafu)?,
+            samples_per_pixel: samples_per_pixel(obj).context(GetAttributeSnafu)?,
+            bits_allocated,
+            bits_stored: bits_stored(obj).context(GetAttributeSnafu)?,
+            pixel_representation: pixel_representation(obj).context(GetAttributeSnafu)? as u16,
+            planar_configuration: obj.planar_configuration().unwrap_or(0),
+            photometric_interpretation: photometric_interpretation(obj)
+                .context(GetAttributeSnafu)?
+                .as_str()
+                .to_string(),
+            data: Vec::new(),
+        })
+    }
+
+    /// The length of the frame in bytes.
+    fn len(&self) -> usize {
+        self.rows as usize
+            * self.cols as usize
+            * self.samples_per_pixel as usize
+            * (self.bits_allocated as usize / 8)
+    }
+}
+
+impl PixelDataObject for NativeFrame {
+    fn rows(&self) -> Option<u16> {
+        Some(self.rows)
+    }
+
+    fn cols(&self) -> Option<u16> {
+        Some(self.cols)
+    }
+
+    fn samples_per_pixel(&self) -> Option<u16> {
+        Some(self.samples_per_pixel)
+    }
+
+    fn bits_allocated(&self) -> Option<u16> {
+        Some(self.bits_allocated)
+    }
+
+    fn bits_stored(&self) -> Option<u16> {
+        Some(self.bits_stored)
+    }
+
+    fn pixel_representation(&self) -> Option<u16> {
+        Some(self.pixel_representation)
+    }
+
+    fn planar_configuration(&self) -> Option<u16> {
+        Some(self.planar_configuration)
+    }
+
+    fn photometric_interpretation(&self) -> Option<&str> {
+        Some(&self.photometric_interpretation)
+    }
+
+    fn number_of_frames(&self) -> Option<u16> {
+        Some(1)
+    }
+
+    fn number_of_fragments(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    /// Native pixel data has no fragments.
+    fn fragment(&self, _fragment: usize) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn raw_pixel_data(&self) -> Option<RawPixelData> {
+        Some(RawPixelData {
+            fragments: C::from_vec(vec![self.data.clone()]),
+            offset_table: C::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{rle_lossless, FixtureSpec};
+    use crate::PixelDecoder;
+    use dicom_object::progress::ProgressControl;
+
+    const EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+    const RLE_LOSSLESS: &str = "1.2.840.10008.1.2.5";
+
+    fn ts(uid: &str) -> &'static TransferSyntax {
+        TransferSyntaxRegistry.get(uid).unwrap()
+    }
+
+    #[test]
+    fn transcode_reports_progress_per_frame() {
+        let fixture = rle_lossless(&FixtureSpec::new(8, 6).bits_allocated(16).frames(3));
+        let mut obj = fixture.object.clone();
+
+        let mut updates = Vec::new();
+        obj.transcode_with_progress(
+            ts(EXPLICIT_VR_LITTLE_ENDIAN),
+            &TranscodeOptions::new(),
+            |status: &ProgressStatus| {
+                updates.push(*status);
+                ProgressControl::Continue
+            },
+        )
+        .unwrap();
+
+        assert_eq!(updates.len(), 3);
+        for (i, status) in updates.iter().enumerate() {
+            assert_eq!(status.items_completed, i as u64 + 1);
+            assert_eq!(status.items_total, Some(3));
+            assert_eq!(status.bytes_processed, (i as u64 + 1) * 8 * 6 * 2);
+            assert_eq!(status.bytes_total, Some(3 * 8 * 6 * 2));
+        }
+
+        assert_eq!(obj.meta().transfer_syntax(), EXPLICIT_VR_LITTLE_ENDIAN);
+        let pixel_data = obj.element(tags::PIXEL_DATA).unwrap();
+        assert_eq!(pixel_data.vr(), VR::OW);
+        assert_eq!(&*pixel_data.to_bytes().unwrap(), &fixture.expected[..]);
+
+        assert!(obj.contributing_equipment().is_empty());
+
+        // and back to RLE Lossless
+        obj.transcode_with_options(
+            ts(RLE_LOSSLESS),
+            &TranscodeOptions::new().equipment(ContributingEquipment::new("ACME")),
+        )
+        .unwrap();
+        assert_eq!(obj.meta().transfer_syntax(), RLE_LOSSLESS);
+        let equipment = obj.contributing_equipment();
+        assert_eq!(equipment.len(), 1);
+        assert_eq!(
+            equipment[0]
+                .element(tags::MANUFACTURER)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "ACME"
+        );
+        assert_eq!(obj.number_of_fragments(), Some(3));
+        let decoded = obj.decode_pixel_data().unwrap();
+        assert_eq!(decoded.data(), &fixture.expected[..]);
+    }
+
+    #[test]
+    fn transcode_can_be_cancelled() {
+        let fixture = rle_lossless(&FixtureSpec::new(4, 4).rgb().frames(4));
+        let mut obj = fixture.object.clone();
+
+        let mut calls = 0;
+        let result = obj.transcode_with_progress(
+            ts(EXPLICIT_VR_LITTLE_ENDIAN),
+            &TranscodeOptions::new(),
+            |_: &ProgressStatus| {
+                calls += 1;
+                ProgressControl::Cancel
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(crate::Error(crate::InnerError::Cancelled { .. }))
+        ));
+        assert_eq!(calls, 1);
+
+        // the object is left unchanged
+        assert_eq!(obj.meta().transfer_syntax(), RLE_LOSSLESS);
+        assert_eq!(obj.number_of_fragments(), Some(4));
+    }
+}
@@ -26,6 +26,110 @@ impl Rescale {
     }
 }
 
+/// A modality lookup table,
+/// as described by an item of the _Modality LUT Sequence_.
+///
+/// Input values below the first mapped value
+/// are mapped to the first entry,
+/// and input values beyond the last mapped value
+/// are mapped to the last entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModalityLut {
+    /// the first input value mapped
+    first_mapped: i32,
+    /// the LUT entries
+    data: Vec<u16>,
+}
+
+impl ModalityLut {
+    /// Create a new modality LUT
+    /// from the first input value mapped and the table entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is empty.
+    pub fn new(first_mapped: i32, data: Vec<u16>) -> Self {
+        assert!(!data.is_empty(), "modality LUT must not be empty");
+        ModalityLut { first_mapped, data }
+    }
+
+    /// Retrieve the first input value mapped by this table.
+    #[inline]
+    pub fn first_mapped(&self) -> i32 {
+        self.first_mapped
+    }
+
+    /// Retrieve the entries of this table.
+    #[inline]
+    pub fn data(&self) -> &[u16] {
+        &self.data
+    }
+
+    /// Apply the lookup table to a value.
+    pub fn apply(&self, value: f64) -> f64 {
+        let index = (value.round() - f64::from(self.first_mapped)).max(0.) as usize;
+        let index = index.min(self.data.len() - 1);
+        f64::from(self.data[index])
+    }
+}
+
+/// A modality transformation,
+/// which converts stored pixel values into values in modality units.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModalityTransform {
+    /// a linear rescale function
+    Rescale(Rescale),
+    /// a lookup table
+    Lut(ModalityLut),
+}
+
+impl ModalityTransform {
+    /// Apply the modality transformation to a value.
+    #[inline]
+    pub fn apply(&self, value: f64) -> f64 {
+        match self {
+            ModalityTransform::Rescale(rescale) => rescale.apply(value),
+            ModalityTransform::Lut(lut) => lut.apply(value),
+        }
+    }
+
+    /// Determine the range of the output values
+    /// given the range of the input values.
+    pub(crate) fn output_range(&self, min: f64, max: f64) -> (f64, f64) {
+        match self {
+            ModalityTransform::Rescale(rescale) => {
+                let (a, b) = (rescale.apply(min), rescale.apply(max));
+                (a.min(b), a.max(b))
+            }
+            ModalityTransform::Lut(lut) => {
+                let lo =
+                    (min.max(f64::from(lut.first_mapped)) - f64::from(lut.first_mapped)) as usize;
+                let hi =
+                    (max.max(f64::from(lut.first_mapped)) - f64::from(lut.first_mapped)) as usize;
+                let hi = hi.min(lut.data.len() - 1);
+                let lo = lo.min(hi);
+                let entries = &lut.data[lo..=hi];
+                (
+                    f64::from(*entries.iter().min().unwrap()),
+                    f64::from(*entries.iter().max().unwrap()),
+                )
+            }
+        }
+    }
+}
+
+impl From<Rescale> for ModalityTransform {
+    fn from(rescale: Rescale) -> Self {
+        ModalityTransform::Rescale(rescale)
+    }
+}
+
+impl From<ModalityLut> for ModalityTransform {
+    fn from(lut: ModalityLut) -> Self {
+        ModalityTransform::Lut(lut)
+    }
+}
+
 /// A known DICOM Value of Interest (VOI) LUT function descriptor.
 #[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
 pub enum VoiLutFunction {
@@ -56,6 +160,18 @@ impl std::convert::TryFrom<&str> for VoiLutFunction {
     }
 }
 
+impl VoiLutFunction {
+    /// Retrieve the defined term of this function,
+    /// as written in the _VOI LUT Function_ attribute.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VoiLutFunction::Linear => "LINEAR",
+            VoiLutFunction::LinearExact => "LINEAR_EXACT",
+            VoiLutFunction::Sigmoid => "SIGMOID",
+        }
+    }
+}
+
 impl Default for VoiLutFunction {
     fn default() -> Self {
         VoiLutFunction::Linear
@@ -210,6 +326,22 @@ mod tests {
         assert_eq!(rescale.apply(1024.), 0.);
     }
 
+    /// Applying a modality LUT clamps values outside of the table.
+    #[test]
+    fn modality_lut_table() {
+        let lut = ModalityLut::new(-2, vec![10, 20, 30, 40]);
+
+        assert_eq!(lut.apply(-5.), 10.);
+        assert_eq!(lut.apply(-2.), 10.);
+        assert_eq!(lut.apply(0.), 30.);
+        assert_eq!(lut.apply(1.), 40.);
+        assert_eq!(lut.apply(100.), 40.);
+
+        let transform = ModalityTransform::from(lut);
+        assert_eq!(transform.output_range(-1., 0.), (20., 30.));
+        assert_eq!(transform.output_range(-100., 100.), (10., 40.));
+    }
+
     /// Applying a linear window level
     /// as per the example described in the standard
     /// (C.11.2.1.2.1)
@@ -0,0 +1,341 @@
+//! Pass-through access to video encoded pixel data.
+//!
+//! Objects in one of the MPEG-2, MPEG-4 AVC/H.264 or HEVC/H.265
+//! transfer syntaxes hold a single video bitstream
+//! which spans all frames,
+//! split into pixel data fragments at arbitrary points.
+//! Decoding this bitstream is not supported by this crate,
+//! but a [`VideoStream`] exposes the encapsulated bitstream
+//! and its fragment boundaries as is,
+//! so that it can be passed to a video decoder or player.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! use dicom_object::open_file;
+//! use dicom_pixeldata::video::VideoStream;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let obj = open_file("video.dcm")?;
+//! let video = VideoStream::from_object(&obj)?;
+//! println!(
+//!     "{} stream with {} frames in {} fragments",
+//!     video.codec(),
+//!     video.number_of_frames(),
+//!     video.number_of_fragments(),
+//! );
+//! video.save("video.mp4")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::attribute::{number_of_frames, pixel_data};
+use crate::{
+    GetAttributeSnafu, NotEncapsulatedSnafu, NotVideoTransferSyntaxSnafu, Result,
+    WriteVideoStreamSnafu,
+};
+use dicom_core::{value::Value, DataDictionary};
+use dicom_dictionary_std::tags;
+use dicom_object::{FileDicomObject, InMemDicomObject};
+use dicom_transfer_syntax_registry::entries;
+use snafu::{OptionExt, ResultExt};
+use std::fmt;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+
+/// A family of video codecs supported by DICOM transfer syntaxes.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum VideoCodec {
+    /// MPEG-2 video
+    Mpeg2,
+    /// MPEG-4 AVC/H.264
+    H264,
+    /// HEVC/H.265
+    Hevc,
+}
+
+impl VideoCodec {
+    /// Identify the video codec of the transfer syntax with the given UID,
+    /// or return `None` if it is not a video transfer syntax.
+    pub fn from_transfer_syntax(uid: &str) -> Option<Self> {
+        let uid = uid.trim_end_matches(|c: char| c.is_whitespace() || c == '\0');
+        VIDEO_TRANSFER_SYNTAXES
+            .iter()
+            .find(|(ts_uid, _)| *ts_uid == uid)
+            .map(|(_, codec)| *codec)
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoCodec::Mpeg2 => f.write_str("MPEG-2"),
+            VideoCodec::H264 => f.write_str("MPEG-4 AVC/H.264"),
+            VideoCodec::Hevc => f.write_str("HEVC/H.265"),
+        }
+    }
+}
+
+/// The UIDs of the known video transfer syntaxes,
+/// paired with their video codec.
+pub const VIDEO_TRANSFER_SYNTAXES: &[(&str, VideoCodec)] = &[
+    (
+        entries::MPEG2_MAIN_PROFILE_MAIN_LEVEL.uid(),
+        VideoCodec::Mpeg2,
+    ),
+    (
+        entries::MPEG2_MAIN_PROFILE_HIGH_LEVEL.uid(),
+        VideoCodec::Mpeg2,
+    ),
+    (entries::MPEG4_AVC_H264_HIGH_PROFILE.uid(), VideoCodec::H264),
+    (
+        entries::MPEG4_AVC_H264_BD_COMPATIBLE_HIGH_PROFILE.uid(),
+        VideoCodec::H264,
+    ),
+    (
+        entries::MPEG4_AVC_H264_HIGH_PROFILE_FOR_2D_VIDEO.uid(),
+        VideoCodec::H264,
+    ),
+    (
+        entries::MPEG4_AVC_H264_HIGH_PROFILE_FOR_3D_VIDEO.uid(),
+        VideoCodec::H264,
+    ),
+    (
+        entries::MPEG4_AVC_H264_STEREO_HIGH_PROFILE.uid(),
+        VideoCodec::H264,
+    ),
+    (entries::HEVC_H265_MAIN_PROFILE.uid(), VideoCodec::Hevc),
+    (entries::HEVC_H265_MAIN_10_PROFILE.uid(), VideoCodec::Hevc),
+];
+
+/// Check whether the transfer syntax with the given UID
+/// encodes pixel data as a video bitstream.
+pub fn is_video_transfer_syntax(uid: &str) -> bool {
+    VideoCodec::from_transfer_syntax(uid).is_some()
+}
+
+/// The encapsulated video bitstream of a DICOM object,
+/// retrieved without decoding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoStream {
+    transfer_syntax: String,
+    codec: VideoCodec,
+    number_of_frames: u32,
+    frame_time: Option<f64>,
+    data: Vec<u8>,
+    fragments: Vec<Range<usize>>,
+}
+
+impl VideoStream {
+    /// Retrieve the video bitstream of a DICOM object
+    /// in one of the video transfer syntaxes.
+    ///
+    /// The bitstream is the concatenation of all pixel data fragments.
+    pub fn from_object<D>(obj: &FileDicomObject<InMemDicomObject<D>>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        let transfer_syntax = obj.meta().transfer_syntax();
+        let codec = VideoCodec::from_transfer_syntax(transfer_syntax).context(
+            NotVideoTransferSyntaxSnafu {
+                ts_uid: transfer_syntax,
+            },
+        )?;
+
+        let fragments = match pixel_data(obj).context(GetAttributeSnafu)?.value() {
+            Value::PixelSequence { fragments, .. } => fragments,
+            _ => return NotEncapsulatedSnafu.fail()?,
+        };
+        let number_of_frames = number_of_frames(obj).context(GetAttributeSnafu)?;
+        let frame_time = obj
+            .element_opt(tags::FRAME_TIME)
+            .ok()
+            .flatten()
+            .and_then(|e| e.to_float64().ok());
+
+        let mut data = Vec::with_capacity(fragments.iter().map(|f| f.len()).sum());
+        let mut ranges = Vec::with_capacity(fragments.len());
+        for fragment in fragments {
+            let start = data.len();
+            data.extend_from_slice(fragment);
+            ranges.push(start..data.len());
+        }
+
+        Ok(VideoStream {
+            transfer_syntax: transfer_syntax.to_string(),
+            codec,
+            number_of_frames,
+            frame_time,
+            data,
+            fragments: ranges,
+        })
+    }
+
+    /// The UID of the transfer syntax of the video stream.
+    pub fn transfer_syntax(&self) -> &str {
+        &self.transfer_syntax
+    }
+
+    /// The video codec of the stream.
+    pub fn codec(&self) -> VideoCodec {
+        self.codec
+    }
+
+    /// The number of frames in the video,
+    /// as declared by the object.
+    pub fn number_of_frames(&self) -> u32 {
+        self.number_of_frames
+    }
+
+    /// The nominal time per frame in milliseconds,
+    /// from the _Frame Time_ attribute, if available.
+    pub fn frame_time(&self) -> Option<f64> {
+        self.frame_time
+    }
+
+    /// The presentation time of the given frame
+    /// in milliseconds from the start of the video,
+    /// based on the nominal frame time.
+    ///
+    /// Returns `None` if the frame time is unknown
+    /// or the frame is out of range.
+    pub fn frame_timestamp(&self, frame: u32) -> Option<f64> {
+        if frame >= self.number_of_frames {
+            return None;
+        }
+        self.frame_time.map(|t| t * f64::from(frame))
+    }
+
+    /// The full bitstream.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Take the full bitstream.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The number of pixel data fragments which form the bitstream.
+    pub fn number_of_fragments(&self) -> usize {
+        self.fragments.len()
+    }
+
+    /// The byte ranges of each fragment in the bitstream.
+    pub fn fragment_boundaries(&self) -> &[Range<usize>] {
+        &self.fragments
+    }
+
+    /// Retrieve the data of a single fragment.
+    pub fn fragment(&self, index: usize) -> Option<&[u8]> {
+        self.fragments
+            .get(index)
+            .map(|range| &self.data[range.clone()])
+    }
+
+    /// Iterate over the data of each fragment.
+    pub fn fragments(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.fragments
+            .iter()
+            .map(move |range| &self.data[range.clone()])
+    }
+
+    /// Write the bitstream to the given writer.
+    pub fn write_to<W: Write>(&self, mut to: W) -> std::io::Result<()> {
+        to.write_all(&self.data)
+    }
+
+    /// Save the bitstream to a file for playback.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, &self.data).context(WriteVideoStreamSnafu { filename: path })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, PrimitiveValue, VR};
+    use dicom_object::FileMetaTableBuilder;
+
+    fn video_object(ts_uid: &str, fragments: Vec<Vec<u8>>) -> FileDicomObject<InMemDicomObject> {
+        InMemDicomObject::from_element_iter([
+            DataElement::new(tags::NUMBER_OF_FRAMES, VR::IS, PrimitiveValue::from("30")),
+            DataElement::new(tags::FRAME_TIME, VR::DS, PrimitiveValue::from("33.3")),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                Value::PixelSequence {
+                    offset_table: Default::default(),
+                    fragments: fragments.into(),
+                },
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.77.1.4.1")
+                .media_storage_sop_instance_uid("1.2.3.4")
+                .transfer_syntax(ts_uid),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn identify_video_transfer_syntaxes() {
+        assert_eq!(
+            VideoCodec::from_transfer_syntax("1.2.840.10008.1.2.4.100\0"),
+            Some(VideoCodec::Mpeg2)
+        );
+        assert_eq!(
+            VideoCodec::from_transfer_syntax("1.2.840.10008.1.2.4.102"),
+            Some(VideoCodec::H264)
+        );
+        assert_eq!(
+            VideoCodec::from_transfer_syntax("1.2.840.10008.1.2.4.107"),
+            Some(VideoCodec::Hevc)
+        );
+        assert!(!is_video_transfer_syntax("1.2.840.10008.1.2.4.50"));
+        assert!(!is_video_transfer_syntax("1.2.840.10008.1.2.1"));
+    }
+
+    #[test]
+    fn video_stream_from_object() {
+        let obj = video_object(
+            "1.2.840.10008.1.2.4.102",
+            vec![
+                vec![0, 0, 0, 1, 0x67],
+                vec![0x42, 0x00],
+                vec![0x1F, 0xAC, 0x34],
+            ],
+        );
+
+        let video = VideoStream::from_object(&obj).unwrap();
+        assert_eq!(video.transfer_syntax(), "1.2.840.10008.1.2.4.102");
+        assert_eq!(video.codec(), VideoCodec::H264);
+        assert_eq!(video.number_of_frames(), 30);
+        assert_eq!(video.frame_time(), Some(33.3));
+        assert_eq!(video.frame_timestamp(2), Some(66.6));
+        assert_eq!(video.frame_timestamp(30), None);
+        assert_eq!(
+            video.data(),
+            &[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1F, 0xAC, 0x34]
+        );
+        assert_eq!(video.number_of_fragments(), 3);
+        assert_eq!(video.fragment_boundaries(), &[0..5, 5..7, 7..10]);
+        assert_eq!(video.fragment(1), Some(&[0x42, 0x00][..]));
+        assert_eq!(video.fragment(3), None);
+        assert_eq!(video.fragments().count(), 3);
+
+        let mut out = Vec::new();
+        video.write_to(&mut out).unwrap();
+        assert_eq!(out, video.data());
+    }
+
+    #[test]
+    fn video_stream_requires_video_transfer_syntax() {
+        let obj = video_object("1.2.840.10008.1.2.4.50", vec![vec![0xFF, 0xD8]]);
+        assert!(VideoStream::from_object(&obj).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Image formatting helpers for preparing DICOM images for print.
+//!
+//! Images are rendered through the `dicom-pixeldata` pipeline
+//! (Modality LUT and VOI LUT included)
+//! into 8-bit grayscale images,
+//! which are then sent to the printer
+//! as preformatted grayscale image box content.
+use dicom_core::{DataElement, PrimitiveValue, VR};
+use dicom_dictionary_std::tags;
+use dicom_object::{DefaultDicomObject, InMemDicomObject};
+use dicom_pixeldata::image::{imageops, imageops::FilterType, GrayImage};
+use dicom_pixeldata::PixelDecoder;
+
+/// Render a frame of the given DICOM object
+/// into an 8-bit grayscale image.
+///
+/// Color images are converted to grayscale.
+pub fn render_grayscale(
+    obj: &DefaultDicomObject,
+    frame: u32,
+) -> Result<GrayImage, dicom_pixeldata::Error> {
+    let pixel_data = obj.decode_pixel_data()?;
+    Ok(pixel_data.to_dynamic_image(frame)?.to_luma8())
+}
+
+/// Scale the image down so that it fits in the given dimensions,
+/// preserving its aspect ratio.
+///
+/// Images which already fit are returned unchanged.
+pub fn fit_image(image: GrayImage, max_columns: u32, max_rows: u32) -> GrayImage {
+    let (columns, rows) = image.dimensions();
+    if columns <= max_columns && rows <= max_rows {
+        return image;
+    }
+    let scale = f64::min(
+        max_columns as f64 / columns as f64,
+        max_rows as f64 / rows as f64,
+    );
+    let new_columns = ((columns as f64 * scale).round() as u32).max(1);
+    let new_rows = ((rows as f64 * scale).round() as u32).max(1);
+    imageops::resize(&image, new_columns, new_rows, FilterType::Triangle)
+}
+
+/// Determine a standard _Image Display Format_ (2010,0010)
+/// with enough image boxes for the given number of images,
+/// arranged in an approximately square grid.
+pub fn image_display_format(count: usize) -> String {
+    let count = count.max(1);
+    let columns = (count as f64).sqrt().ceil() as usize;
+    let rows = count.div_ceil(columns);
+    format!("STANDARD\\{},{}", columns, rows)
+}
+
+/// Build an item of the _Basic Grayscale Image Sequence_ (2020,0110)
+/// containing the given image as a preformatted 8-bit grayscale image.
+pub fn preformatted_grayscale_image(image: &GrayImage) -> InMemDicomObject {
+    let (columns, rows) = image.dimensions();
+    let mut pixels = image.as_raw().clone();
+    if pixels.len() & 1 != 0 {
+        pixels.push(0);
+    }
+
+    InMemDicomObject::from_element_iter([
+        DataElement::new(tags::SAMPLES_PER_PIXEL, VR::US, PrimitiveValue::from(1_u16)),
+        DataElement::new(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            VR::CS,
+            PrimitiveValue::from("MONOCHROME2"),
+        ),
+        DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(rows as u16)),
+        DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(columns as u16)),
+        DataElement::new(
+            tags::PIXEL_ASPECT_RATIO,
+            VR::IS,
+            PrimitiveValue::from("1\\1"),
+        ),
+        DataElement::new(tags::BITS_ALLOCATED, VR::US, PrimitiveValue::from(8_u16)),
+        DataElement::new(tags::BITS_STORED, VR::US, PrimitiveValue::from(8_u16)),
+        DataElement::new(tags::HIGH_BIT, VR::US, PrimitiveValue::from(7_u16)),
+        DataElement::new(
+            tags::PIXEL_REPRESENTATION,
+            VR::US,
+            PrimitiveValue::from(0_u16),
+        ),
+        DataElement::new(tags::PIXEL_DATA, VR::OB, PrimitiveValue::from(pixels)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_format_grid() {
+        assert_eq!(image_display_format(0), "STANDARD\\1,1");
+        assert_eq!(image_display_format(1), "STANDARD\\1,1");
+        assert_eq!(image_display_format(2), "STANDARD\\2,1");
+        assert_eq!(image_display_format(4), "STANDARD\\2,2");
+        assert_eq!(image_display_format(5), "STANDARD\\3,2");
+        assert_eq!(image_display_format(9), "STANDARD\\3,3");
+    }
+
+    #[test]
+    fn fit_image_preserves_aspect_ratio() {
+        let image = GrayImage::new(400, 200);
+        let fitted = fit_image(image, 100, 100);
+        assert_eq!(fitted.dimensions(), (100, 50));
+
+        // small images are left untouched
+        let image = GrayImage::new(40, 20);
+        let fitted = fit_image(image, 100, 100);
+        assert_eq!(fitted.dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn preformatted_image_item() {
+        let image = GrayImage::from_raw(3, 1, vec![0, 128, 255]).unwrap();
+        let item = preformatted_grayscale_image(&image);
+        assert_eq!(
+            item.element(tags::ROWS).unwrap().to_int::<u16>().unwrap(),
+            1
+        );
+        assert_eq!(
+            item.element(tags::COLUMNS)
+                .unwrap()
+                .to_int::<u16>()
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            item.element(tags::PHOTOMETRIC_INTERPRETATION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "MONOCHROME2"
+        );
+        // padded to even length
+        assert_eq!(
+            &item.element(tags::PIXEL_DATA).unwrap().to_bytes().unwrap()[..],
+            &[0, 128, 255, 0]
+        );
+    }
+}
@@ -0,0 +1,631 @@
+//! DICOM Basic Grayscale Print Management SCU
+//!
+//! Prints one or more DICOM images on a single film
+//! by following the standard N-service sequence:
+//!
+//! 1. N-CREATE a _Basic Film Session_;
+//! 2. N-CREATE a _Basic Film Box_,
+//!    which creates the image boxes in the printer;
+//! 3. N-SET each _Basic Grayscale Image Box_ with an image;
+//! 4. N-ACTION print on the film box;
+//! 5. N-DELETE the film session.
+use dicom_core::value::Value;
+use dicom_core::{DataElement, Length, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_encoding::{TransferSyntax, TransferSyntaxIndex};
+use dicom_object::{mem::InMemElement, open_file, InMemDicomObject};
+use dicom_pixeldata::image::GrayImage;
+use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+use dicom_ul::association::client::{ClientAssociation, ClientAssociationOptions};
+use dicom_ul::dimse::normalized::{
+    NActionRequest, NActionResponse, NCreateRequest, NCreateResponse, NDeleteRequest,
+    NDeleteResponse, NEventReportRequest, NSetRequest, NSetResponse, BASIC_FILM_BOX_SOP_CLASS,
+    BASIC_FILM_SESSION_SOP_CLASS, BASIC_GRAYSCALE_PRINT_MANAGEMENT_META_SOP_CLASS,
+};
+use dicom_ul::dimse::{self, CommandField, StatusType};
+use dicom_ul::pdu::{PDataValueType, Pdu, PresentationContextResultReason};
+use snafu::{prelude::*, Whatever};
+use std::io::Write;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use tracing::{debug, info, warn, Level};
+
+mod image;
+
+/// DICOM Basic Grayscale Print Management SCU
+#[derive(Debug, StructOpt)]
+struct App {
+    /// socket address to the print SCP,
+    /// optionally with AE title
+    /// (example: "PRINTER@127.0.0.1:1045")
+    addr: String,
+    /// the DICOM files to print on a single film
+    #[structopt(required = true)]
+    files: Vec<PathBuf>,
+    /// verbose mode
+    #[structopt(short = "v", long = "verbose")]
+    verbose: bool,
+    /// the calling AE title
+    #[structopt(long = "calling-ae-title", default_value = "PRINT-SCU")]
+    calling_ae_title: String,
+    /// the called Application Entity title,
+    /// overrides AE title in address if present [default: ANY-SCP]
+    #[structopt(long = "called-ae-title")]
+    called_ae_title: Option<String>,
+    /// the maximum PDU length
+    #[structopt(long = "max-pdu-length", default_value = "16384")]
+    max_pdu_length: u32,
+    /// the number of copies to print
+    #[structopt(long = "copies", default_value = "1")]
+    copies: u32,
+    /// the print priority (HIGH, MED or LOW)
+    #[structopt(long = "priority", default_value = "MED")]
+    priority: String,
+    /// the medium type (e.g. PAPER, CLEAR FILM, BLUE FILM)
+    #[structopt(long = "medium-type")]
+    medium_type: Option<String>,
+    /// the film destination (e.g. MAGAZINE, PROCESSOR)
+    #[structopt(long = "film-destination")]
+    film_destination: Option<String>,
+    /// the image display format
+    /// [default: a standard grid fitting all images]
+    #[structopt(long = "display-format")]
+    display_format: Option<String>,
+    /// the film orientation (PORTRAIT or LANDSCAPE)
+    #[structopt(long = "orientation")]
+    orientation: Option<String>,
+    /// the film size ID (e.g. 14INX17IN, A4)
+    #[structopt(long = "film-size")]
+    film_size: Option<String>,
+    /// the magnification type (e.g. REPLICATE, BILINEAR, CUBIC, NONE)
+    #[structopt(long = "magnification")]
+    magnification: Option<String>,
+    /// scale images down to fit in this many pixels per side
+    #[structopt(long = "max-size")]
+    max_size: Option<u32>,
+}
+
+fn main() {
+    run().unwrap_or_else(|e| {
+        tracing::error!("{}", snafu::Report::from_error(e));
+        std::process::exit(-2);
+    })
+}
+
+fn run() -> Result<(), Whatever> {
+    let App {
+        addr,
+        files,
+        verbose,
+        calling_ae_title,
+        called_ae_title,
+        max_pdu_length,
+        copies,
+        priority,
+        medium_type,
+        film_destination,
+        display_format,
+        orientation,
+        film_size,
+        magnification,
+        max_size,
+    } = App::from_args();
+
+    tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(if verbose { Level::DEBUG } else { Level::INFO })
+            .finish(),
+    )
+    .whatever_context("Could not set up global logging subscriber")
+    .unwrap_or_else(|e: Whatever| {
+        eprintln!("[ERROR] {}", snafu::Report::from_error(e));
+    });
+
+    // render all images before talking to the printer
+    let mut images = Vec::with_capacity(files.len());
+    for file in &files {
+        let obj = open_file(file)
+            .with_whatever_context(|_| format!("Could not open file {}", file.display()))?;
+        let img = image::render_grayscale(&obj, 0)
+            .with_whatever_context(|_| format!("Could not render image {}", file.display()))?;
+        let img = match max_size {
+            Some(max_size) => image::fit_image(img, max_size, max_size),
+            None => img,
+        };
+        debug!(
+            "Rendered {} ({}x{})",
+            file.display(),
+            img.width(),
+            img.height()
+        );
+        images.push(img);
+    }
+
+    let mut association_opt = ClientAssociationOptions::new()
+        .with_abstract_syntax(BASIC_GRAYSCALE_PRINT_MANAGEMENT_META_SOP_CLASS)
+        .calling_ae_title(calling_ae_title)
+        .max_pdu_length(max_pdu_length);
+    if let Some(called_ae_title) = called_ae_title {
+        association_opt = association_opt.called_ae_title(called_ae_title);
+    }
+    let association = association_opt
+        .establish_with(&addr)
+        .whatever_context("Could not establish association with print SCP")?;
+
+    let pc = association
+        .presentation_contexts()
+        .iter()
+        .find(|pc| pc.reason == PresentationContextResultReason::Acceptance)
+        .whatever_context("Basic Grayscale Print Management not accepted by print SCP")?
+        .clone();
+    let ts = TransferSyntaxRegistry
+        .get(&pc.transfer_syntax)
+        .whatever_context("Unsupported transfer syntax accepted by print SCP")?;
+
+    info!("Association with {} successful", addr);
+
+    let mut scu = PrintScu {
+        association,
+        presentation_context_id: pc.id,
+        ts,
+        message_id: 1,
+    };
+
+    // Basic Film Session
+    let mut film_session = InMemDicomObject::from_element_iter([
+        DataElement::new(
+            tags::NUMBER_OF_COPIES,
+            VR::IS,
+            PrimitiveValue::from(copies.to_string()),
+        ),
+        cs_element(tags::PRINT_PRIORITY, &priority),
+    ]);
+    if let Some(medium_type) = medium_type {
+        film_session.put(cs_element(tags::MEDIUM_TYPE, &medium_type));
+    }
+    if let Some(film_destination) = film_destination {
+        film_session.put(cs_element(tags::FILM_DESTINATION, &film_destination));
+    }
+    let (rsp, _) = scu.create(BASIC_FILM_SESSION_SOP_CLASS, &film_session)?;
+    let film_session_uid = rsp
+        .affected_sop_instance_uid
+        .whatever_context("Missing film session SOP instance UID in N-CREATE response")?;
+    info!("Created film session {}", film_session_uid);
+
+    let result = print_film(
+        &mut scu,
+        &film_session_uid,
+        &images,
+        display_format.unwrap_or_else(|| image::image_display_format(images.len())),
+        orientation,
+        film_size,
+        magnification,
+    );
+
+    // always try to clean up the film session
+    let delete_result = scu.delete(BASIC_FILM_SESSION_SOP_CLASS, &film_session_uid);
+
+    let _ = scu.association.release();
+    result?;
+    delete_result?;
+
+    info!("Print job submitted");
+    Ok(())
+}
+
+fn print_film(
+    scu: &mut PrintScu,
+    film_session_uid: &str,
+    images: &[GrayImage],
+    display_format: String,
+    orientation: Option<String>,
+    film_size: Option<String>,
+    magnification: Option<String>,
+) -> Result<(), Whatever> {
+    // Basic Film Box
+    let mut film_box = InMemDicomObject::from_element_iter([
+        DataElement::new(
+            tags::IMAGE_DISPLAY_FORMAT,
+            VR::ST,
+            PrimitiveValue::from(display_format),
+        ),
+        DataElement::new(
+            tags::REFERENCED_FILM_SESSION_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: vec![InMemDicomObject::from_element_iter([
+                    uid_element(tags::REFERENCED_SOP_CLASS_UID, BASIC_FILM_SESSION_SOP_CLASS),
+                    uid_element(tags::REFERENCED_SOP_INSTANCE_UID, film_session_uid),
+                ])]
+                .into(),
+                size: Length::UNDEFINED,
+            },
+        ),
+    ]);
+    if let Some(orientation) = orientation {
+        film_box.put(cs_element(tags::FILM_ORIENTATION, &orientation));
+    }
+    if let Some(film_size) = film_size {
+        film_box.put(cs_element(tags::FILM_SIZE_ID, &film_size));
+    }
+    if let Some(magnification) = magnification {
+        film_box.put(cs_element(tags::MAGNIFICATION_TYPE, &magnification));
+    }
+    let (rsp, film_box) = scu.create(BASIC_FILM_BOX_SOP_CLASS, &film_box)?;
+    let film_box_uid = rsp
+        .affected_sop_instance_uid
+        .whatever_context("Missing film box SOP instance UID in N-CREATE response")?;
+    let film_box = film_box.whatever_context("Missing film box attributes in N-CREATE response")?;
+    info!("Created film box {}", film_box_uid);
+
+    // the image boxes created along with the film box
+    let image_boxes = film_box
+        .element(tags::REFERENCED_IMAGE_BOX_SEQUENCE)
+        .whatever_context("Missing Referenced Image Box Sequence in film box")?
+        .items()
+        .whatever_context("Referenced Image Box Sequence is not a sequence")?
+        .iter()
+        .map(|item| {
+            let class_uid = get_uid(item, tags::REFERENCED_SOP_CLASS_UID)?;
+            let instance_uid = get_uid(item, tags::REFERENCED_SOP_INSTANCE_UID)?;
+            Ok((class_uid, instance_uid))
+        })
+        .collect::<Result<Vec<_>, Whatever>>()?;
+
+    if image_boxes.len() < images.len() {
+        warn!(
+            "Film box only has {} image boxes, {} images will not be printed",
+            image_boxes.len(),
+            images.len() - image_boxes.len()
+        );
+    }
+
+    // Basic Grayscale Image Box
+    for (i, ((class_uid, instance_uid), img)) in image_boxes.iter().zip(images).enumerate() {
+        let image_box = InMemDicomObject::from_element_iter([
+            DataElement::new(
+                tags::IMAGE_BOX_POSITION,
+                VR::US,
+                PrimitiveValue::from((i + 1) as u16),
+            ),
+            DataElement::new(
+                tags::BASIC_GRAYSCALE_IMAGE_SEQUENCE,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![image::preformatted_grayscale_image(img)].into(),
+                    size: Length::UNDEFINED,
+                },
+            ),
+        ]);
+        scu.set(class_uid, instance_uid, &image_box)?;
+        debug!("Set image box #{}", i + 1);
+    }
+
+    // print
+    scu.action(BASIC_FILM_BOX_SOP_CLASS, &film_box_uid, 1)?;
+    Ok(())
+}
+
+/// The state of an association with a print SCP.
+struct PrintScu {
+    association: ClientAssociation,
+    presentation_context_id: u8,
+    ts: &'static TransferSyntax,
+    message_id: u16,
+}
+
+impl PrintScu {
+    fn next_message_id(&mut self) -> u16 {
+        let message_id = self.message_id;
+        self.message_id = self.message_id.wrapping_add(1);
+        message_id
+    }
+
+    fn create(
+        &mut self,
+        sop_class_uid: &str,
+        attributes: &InMemDicomObject,
+    ) -> Result<(NCreateResponse, Option<InMemDicomObject>), Whatever> {
+        let request = NCreateRequest {
+            message_id: self.next_message_id(),
+            affected_sop_class_uid: sop_class_uid.to_string(),
+            affected_sop_instance_uid: None,
+            has_data_set: true,
+        };
+        self.send(&request.to_command(), Some(attributes))?;
+        let (command, data) = self.receive()?;
+        let response = NCreateResponse::from_command(&command)
+            .whatever_context("Invalid N-CREATE response")?;
+        check_status(
+            "N-CREATE",
+            response.status,
+            response.error_comment.as_deref(),
+        )?;
+        Ok((response, data))
+    }
+
+    fn set(
+        &mut self,
+        sop_class_uid: &str,
+        sop_instance_uid: &str,
+        modifications: &InMemDicomObject,
+    ) -> Result<NSetResponse, Whatever> {
+        let request = NSetRequest {
+            message_id: self.next_message_id(),
+            requested_sop_class_uid: sop_class_uid.to_string(),
+            requested_sop_instance_uid: sop_instance_uid.to_string(),
+        };
+        self.send(&request.to_command(), Some(modifications))?;
+        let (command, _) = self.receive()?;
+        let response =
+            NSetResponse::from_command(&command).whatever_context("Invalid N-SET response")?;
+        check_status("N-SET", response.status, response.error_comment.as_deref())?;
+        Ok(response)
+    }
+
+    fn action(
+        &mut self,
+        sop_class_uid: &str,
+        sop_instance_uid: &str,
+        action_type_id: u16,
+    ) -> Result<NActionResponse, Whatever> {
+        let request = NActionRequest {
+            message_id: self.next_message_id(),
+            requested_sop_class_uid: sop_class_uid.to_string(),
+            requested_sop_instance_uid: sop_instance_uid.to_string(),
+            action_type_id,
+            has_data_set: false,
+        };
+        self.send(&request.to_command(), None)?;
+        let (command, _) = self.receive()?;
+        let response = NActionResponse::from_command(&command)
+            .whatever_context("Invalid N-ACTION response")?;
+        check_status(
+            "N-ACTION",
+            response.status,
+            response.error_comment.as_deref(),
+        )?;
+        Ok(response)
+    }
+
+    fn delete(
+        &mut self,
+        sop_class_uid: &str,
+        sop_instance_uid: &str,
+    ) -> Result<NDeleteResponse, Whatever> {
+        let request = NDeleteRequest {
+            message_id: self.next_message_id(),
+            requested_sop_class_uid: sop_class_uid.to_string(),
+            requested_sop_instance_uid: sop_instance_uid.to_string(),
+        };
+        self.send(&request.to_command(), None)?;
+        let (command, _) = self.receive()?;
+        let response = NDeleteResponse::from_command(&command)
+            .whatever_context("Invalid N-DELETE response")?;
+        check_status(
+            "N-DELETE",
+            response.status,
+            response.error_comment.as_deref(),
+        )?;
+        Ok(response)
+    }
+
+    /// Send a command, followed by its data set if applicable.
+    fn send(
+        &mut self,
+        command: &InMemDicomObject,
+        data: Option<&InMemDicomObject>,
+    ) -> Result<(), Whatever> {
+        let pdu = dimse::command_pdu(self.presentation_context_id, command)
+            .whatever_context("Could not encode command")?;
+        self.association
+            .send(&pdu)
+            .whatever_context("Could not send command")?;
+
+        if let Some(data) = data {
+            let mut bytes = Vec::new();
+            data.write_dataset_with_ts(&mut bytes, self.ts)
+                .whatever_context("Could not encode data set")?;
+            let mut pdata = self.association.send_pdata(self.presentation_context_id);
+            pdata
+                .write_all(&bytes)
+                .whatever_context("Could not send data set")?;
+        }
+        Ok(())
+    }
+
+    /// Receive the next response command and its data set, if any.
+    ///
+    /// Event reports sent by the printer in the meantime
+    /// are logged and acknowledged.
+    fn receive(&mut self) -> Result<(InMemDicomObject, Option<InMemDicomObject>), Whatever> {
+        loop {
+            let association = &mut self.association;
+            let (command, data) = read_message(|| {
+                association
+                    .receive()
+                    .whatever_context("Could not receive response from print SCP")
+            })?;
+            debug!("Received command {:?}", command);
+
+            let data = data
+                .map(|bytes| InMemDicomObject::read_dataset_with_ts(&bytes[..], self.ts))
+                .transpose()
+                .whatever_context("Could not read data set")?;
+
+            if dimse::command_field(&command).ok() == Some(CommandField::NEventReportRq) {
+                let report = NEventReportRequest::from_command(&command)
+                    .whatever_context("Invalid N-EVENT-REPORT request")?;
+                info!("Event report: {:?}", report.event());
+                self.send(&report.response(0).to_command(), None)?;
+                continue;
+            }
+
+            return Ok((command, data));
+        }
+    }
+}
+
+/// Reassemble the next command set and its data set, if any,
+/// from the P-DATA values of the PDUs obtained from `receive`.
+///
+/// Both may be fragmented across several PDUs,
+/// so fragments are gathered until the last one of each is found.
+fn read_message<F>(mut receive: F) -> Result<(InMemDicomObject, Option<Vec<u8>>), Whatever>
+where
+    F: FnMut() -> Result<Pdu, Whatever>,
+{
+    let mut command_data = Vec::new();
+    let mut command = None;
+    let mut data = Vec::new();
+    loop {
+        let values = match receive()? {
+            Pdu::PData { data } => data,
+            pdu => whatever!("Unexpected PDU {:?}", pdu),
+        };
+        for value in values {
+            match (value.value_type, command.take()) {
+                (PDataValueType::Command, None) => {
+                    command_data.extend(value.data);
+                    if value.is_last {
+                        let cmd = dimse::read_command(&command_data)
+                            .whatever_context("Could not read response command")?;
+                        if !dimse::has_data_set(&cmd).whatever_context("Invalid command")? {
+                            return Ok((cmd, None));
+                        }
+                        command = Some(cmd);
+                    }
+                }
+                (PDataValueType::Data, Some(cmd)) => {
+                    data.extend(value.data);
+                    if value.is_last {
+                        return Ok((cmd, Some(data)));
+                    }
+                    command = Some(cmd);
+                }
+                (PDataValueType::Command, Some(_)) => {
+                    whatever!("Expected data, but got command")
+                }
+                (PDataValueType::Data, None) => whatever!("Expected command, but got data"),
+            }
+        }
+    }
+}
+
+fn check_status(operation: &str, status: u16, error_comment: Option<&str>) -> Result<(), Whatever> {
+    match StatusType::from_status(status) {
+        StatusType::Success => Ok(()),
+        StatusType::Warning => {
+            warn!(
+                "Possible issue in {} (status code {:04X}H){}",
+                operation,
+                status,
+                error_comment
+                    .map(|c| format!(": {}", c))
+                    .unwrap_or_default()
+            );
+            Ok(())
+        }
+        _ => whatever!(
+            "{} failed (status code {:04X}H){}",
+            operation,
+            status,
+            error_comment
+                .map(|c| format!(": {}", c))
+                .unwrap_or_default()
+        ),
+    }
+}
+
+fn get_uid(obj: &InMemDicomObject, tag: Tag) -> Result<String, Whatever> {
+    Ok(obj
+        .element(tag)
+        .with_whatever_context(|_| format!("Missing attribute {}", tag))?
+        .to_str()
+        .with_whatever_context(|_| format!("Invalid value for attribute {}", tag))?
+        .trim_end_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_string())
+}
+
+fn cs_element(tag: Tag, value: &str) -> InMemElement {
+    DataElement::new(tag, VR::CS, PrimitiveValue::from(value))
+}
+
+fn uid_element(tag: Tag, uid: &str) -> InMemElement {
+    DataElement::new(tag, VR::UI, PrimitiveValue::from(uid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_ul::pdu::PDataValue;
+
+    fn pdv(value_type: PDataValueType, is_last: bool, data: &[u8]) -> PDataValue {
+        PDataValue {
+            presentation_context_id: 1,
+            value_type,
+            is_last,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn read_fragmented_message() {
+        let command = dimse::command_set([
+            DataElement::new(
+                tags::COMMAND_FIELD,
+                VR::US,
+                PrimitiveValue::from(CommandField::NCreateRsp.code()),
+            ),
+            DataElement::new(
+                tags::COMMAND_DATA_SET_TYPE,
+                VR::US,
+                PrimitiveValue::from(0x0001_u16),
+            ),
+            DataElement::new(tags::STATUS, VR::US, PrimitiveValue::from(0_u16)),
+        ]);
+        let command_bytes = dimse::write_command(&command).unwrap();
+        let (command_1, command_2) = command_bytes.split_at(10);
+
+        let data = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::FILM_SESSION_LABEL,
+            VR::LO,
+            PrimitiveValue::from("FILM"),
+        )]);
+        let ts = TransferSyntaxRegistry.get("1.2.840.10008.1.2").unwrap();
+        let mut data_bytes = Vec::new();
+        data.write_dataset_with_ts(&mut data_bytes, ts).unwrap();
+        let (data_1, data_2) = data_bytes.split_at(6);
+
+        // command and data set are both split across PDUs
+        let mut pdus = vec![
+            Pdu::PData {
+                data: vec![pdv(PDataValueType::Command, false, command_1)],
+            },
+            Pdu::PData {
+                data: vec![
+                    pdv(PDataValueType::Command, true, command_2),
+                    pdv(PDataValueType::Data, false, data_1),
+                ],
+            },
+            Pdu::PData {
+                data: vec![pdv(PDataValueType::Data, true, data_2)],
+            },
+        ]
+        .into_iter();
+
+        let (got_command, got_data) = read_message(|| Ok(pdus.next().unwrap())).unwrap();
+        assert_eq!(got_command, command);
+        assert_eq!(got_data.as_deref(), Some(&data_bytes[..]));
+        assert!(pdus.next().is_none());
+    }
+
+    #[test]
+    fn read_message_rejects_data_before_command() {
+        let mut pdus = vec![Pdu::PData {
+            data: vec![pdv(PDataValueType::Data, true, &[0; 4])],
+        }]
+        .into_iter();
+        assert!(read_message(|| Ok(pdus.next().unwrap())).is_err());
+    }
+}
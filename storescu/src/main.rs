@@ -1,6 +1,7 @@
 use dicom_core::{dicom_value, header::Tag, smallvec, DataElement, PrimitiveValue, VR};
 use dicom_dictionary_std::tags;
 use dicom_encoding::transfer_syntax;
+use dicom_object::progress::{Progress, ProgressControl, ProgressStatus};
 use dicom_object::{mem::InMemDicomObject, open_file, StandardDataDictionary};
 use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
 use dicom_ul::{
@@ -211,6 +212,8 @@ fn run() -> Result<(), Error> {
     } else {
         progress_bar = None;
     }
+    let mut progress = BarProgress(progress_bar.as_ref());
+    let mut status = ProgressStatus::new(None, Some(dicom_files.len() as u64));
 
     for file in dicom_files {
         if let (Some(pc_selected), Some(ts_uid_selected)) = (file.pc_selected, file.ts_selected) {
@@ -237,6 +240,7 @@ fn run() -> Result<(), Error> {
                 .whatever_context("Could not write object dataset")?;
 
             let nbytes = cmd_data.len() + object_data.len();
+            status.bytes_processed += nbytes as u64;
 
             if verbose {
                 info!(
@@ -376,9 +380,11 @@ fn run() -> Result<(), Error> {
                 }
             }
         }
-        if let Some(pb) = progress_bar.as_ref() {
-            pb.inc(1)
-        };
+        status.items_completed += 1;
+        if progress.update(&status).is_cancel() {
+            warn!("Sending cancelled");
+            break;
+        }
     }
 
     if let Some(pb) = progress_bar {
@@ -390,6 +396,18 @@ fn run() -> Result<(), Error> {
     Ok(())
 }
 
+/// Progress reporting of the files sent to an optional progress bar.
+struct BarProgress<'a>(Option<&'a ProgressBar>);
+
+impl Progress for BarProgress<'_> {
+    fn update(&mut self, status: &ProgressStatus) -> ProgressControl {
+        if let Some(pb) = self.0 {
+            pb.set_position(status.items_completed);
+        }
+        ProgressControl::Continue
+    }
+}
+
 fn store_req_command(
     storage_sop_class_uid: &str,
     storage_sop_instance_uid: &str,
@@ -1,43 +1,127 @@
 //! Implementation of Deflated Explicit VR Little Endian.
+//!
+//! The data set of a file in this transfer syntax
+//! is compressed as a whole with the _deflate_ algorithm
+//! (without a zlib header, as specified in RFC 1951).
 use std::io::{Read, Write};
 
-use byteordered::Endianness;
-use dicom_encoding::{Codec, TransferSyntax, transfer_syntax::DataRWAdapter};
-use flate2;
+use dicom_encoding::transfer_syntax::DataRWAdapter;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
 use flate2::Compression;
 
 /// Immaterial type representing an adapter for deflated data.
-#[derive(Debug)]
+///
+/// The adapted writer finishes the compressed stream when dropped.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct FlateAdapter;
 
-/// **Fully implemented**: Deflated Explicit VR Little Endian
-pub const DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN: TransferSyntax<FlateAdapter> = TransferSyntax::new(
-    "1.2.840.10008.1.2.1.99",
-    "Deflated Explicit VR Little Endian",
-    Endianness::Little,
-    true,
-    Codec::Dataset(FlateAdapter),
-);
-
 impl<R: 'static, W: 'static> DataRWAdapter<R, W> for FlateAdapter
 where
     R: Read,
     W: Write,
 {
-    type Reader = flate2::read::DeflateDecoder<R>;
-    type Writer = flate2::write::DeflateEncoder<W>;
+    type Reader = Box<dyn Read>;
+    type Writer = Box<dyn Write>;
 
     fn adapt_reader(&self, reader: R) -> Self::Reader
     where
         R: Read,
     {
-        flate2::read::DeflateDecoder::new(reader)
+        Box::new(DeflateDecoder::new(reader))
     }
 
     fn adapt_writer(&self, writer: W) -> Self::Writer
     where
         W: Write,
     {
-        flate2::write::DeflateEncoder::new(writer, Compression::fast())
+        Box::new(DeflateEncoder::new(writer, Compression::default()))
+    }
+
+    fn adapt_writer_with_level(&self, writer: W, level: u32) -> Self::Writer
+    where
+        W: Write,
+    {
+        Box::new(DeflateEncoder::new(writer, Compression::new(level.min(9))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlateAdapter;
+    use dicom_encoding::transfer_syntax::DataRWAdapter;
+    use std::cell::RefCell;
+    use std::io::{Cursor, Read, Write};
+    use std::rc::Rc;
+
+    /// A writer to a shared buffer, so that its contents
+    /// can be inspected after the adapted writer is dropped.
+    #[derive(Default, Clone)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn deflate_roundtrip() {
+        let data: Vec<u8> = b"ORIGINAL\0".iter().cycle().take(4096).copied().collect();
+
+        let buffer = SharedBuffer::default();
+        {
+            let mut writer: Box<dyn Write> =
+                DataRWAdapter::<Cursor<Vec<u8>>, _>::adapt_writer(&FlateAdapter, buffer.clone());
+            writer.write_all(&data).unwrap();
+            // compressed stream is finished on drop
+        }
+        let compressed = buffer.0.borrow().clone();
+        assert!(!compressed.is_empty());
+        assert!(compressed.len() < data.len());
+
+        let mut reader: Box<dyn Read> =
+            DataRWAdapter::<_, Vec<u8>>::adapt_reader(&FlateAdapter, Cursor::new(compressed));
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn deflate_with_level() {
+        let data: Vec<u8> = b"ORIGINAL\0".iter().cycle().take(4096).copied().collect();
+
+        let compress = |level| {
+            let buffer = SharedBuffer::default();
+            {
+                let mut writer: Box<dyn Write> =
+                    DataRWAdapter::<Cursor<Vec<u8>>, _>::adapt_writer_with_level(
+                        &FlateAdapter,
+                        buffer.clone(),
+                        level,
+                    );
+                writer.write_all(&data).unwrap();
+            }
+            let compressed = buffer.0.borrow().clone();
+            compressed
+        };
+
+        // level 0 only stores the data
+        let stored = compress(0);
+        assert!(stored.len() > data.len());
+        let compressed = compress(9);
+        assert!(compressed.len() < data.len());
+
+        for compressed in [stored, compressed] {
+            let mut reader: Box<dyn Read> =
+                DataRWAdapter::<_, Vec<u8>>::adapt_reader(&FlateAdapter, Cursor::new(compressed));
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            assert_eq!(out, data);
+        }
     }
 }
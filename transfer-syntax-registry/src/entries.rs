@@ -16,15 +16,46 @@
 //! Other crates may be developed to replace stubs,
 //! hence expanding support for those transfer syntaxes
 //! to the registry.
+//!
+//! The pixel data codecs behind each transfer syntax
+//! are subject to the Cargo features of this crate
+//! (`jpeg`, `jpeg-ls`, `jpeg2000` and `rle`,
+//! all enabled by default through the `codecs` feature).
+//! When a codec is disabled,
+//! the respective transfer syntaxes are still provided as stubs.
+//! The same applies to _Deflated Explicit VR Little Endian_,
+//! which depends on the `deflate` feature (also enabled by default).
 
 use crate::create_ts_stub;
+#[cfg(feature = "deflate")]
+use crate::deflate::FlateAdapter;
 use byteordered::Endianness;
-use dicom_encoding::{
-    adapters::jpeg::JPEGAdapter,
-    adapters::rle_lossless::RLELosslessAdapter,
-    transfer_syntax::{AdapterFreeTransferSyntax as Ts, Codec, NeverAdapter},
-    TransferSyntax,
-};
+#[cfg(feature = "jpeg")]
+use dicom_encoding::adapters::jpeg::JPEGAdapter;
+#[cfg(feature = "jpeg2000")]
+use dicom_encoding::adapters::jpeg2000::Jpeg2000Adapter;
+#[cfg(feature = "jpeg-ls")]
+use dicom_encoding::adapters::jpeg_ls::JpegLsAdapter;
+#[cfg(feature = "rle")]
+use dicom_encoding::adapters::rle_lossless::RLELosslessAdapter;
+#[cfg(feature = "deflate")]
+use dicom_encoding::adapters::NeverPixelAdapter;
+#[cfg(any(
+    feature = "jpeg",
+    feature = "jpeg-ls",
+    feature = "jpeg2000",
+    feature = "rle"
+))]
+use dicom_encoding::transfer_syntax::NeverAdapter;
+use dicom_encoding::transfer_syntax::{AdapterFreeTransferSyntax as Ts, Codec};
+#[cfg(any(
+    feature = "deflate",
+    feature = "jpeg",
+    feature = "jpeg-ls",
+    feature = "jpeg2000",
+    feature = "rle"
+))]
+use dicom_encoding::TransferSyntax;
 
 // -- the three base transfer syntaxes, fully supported --
 
@@ -57,19 +88,50 @@ pub const EXPLICIT_VR_BIG_ENDIAN: Ts = Ts::new(
 
 // -- transfer syntaxes with pixel data adapters, fully supported --
 
+/// An alias for a transfer syntax specifier with RLELosslessAdapter
+#[cfg(feature = "rle")]
+pub type RleTS = TransferSyntax<NeverAdapter, RLELosslessAdapter>;
+/// An alias for a transfer syntax specifier with RLELosslessAdapter
+/// (stub descriptor, the `rle` feature is disabled)
+#[cfg(not(feature = "rle"))]
+pub type RleTS = Ts;
+
 /// **Fully supported:** RLE Lossless
-pub const RLE_LOSSLESS: TransferSyntax<NeverAdapter, RLELosslessAdapter> = TransferSyntax::new(
+#[cfg(feature = "rle")]
+pub const RLE_LOSSLESS: RleTS = TransferSyntax::new(
     "1.2.840.10008.1.2.5",
     "RLE Lossless",
     Endianness::Little,
     true,
     Codec::PixelData(RLELosslessAdapter),
 );
+/// **Stub descriptor:** RLE Lossless (the `rle` feature is disabled)
+#[cfg(not(feature = "rle"))]
+pub const RLE_LOSSLESS: RleTS = create_ts_stub("1.2.840.10008.1.2.5", "RLE Lossless");
 
-// --- stub transfer syntaxes, known but not supported ---
+// -- transfer syntaxes with data set adapters --
 
+/// An alias for a transfer syntax specifier with FlateAdapter
+#[cfg(feature = "deflate")]
+pub type DeflatedTS = TransferSyntax<FlateAdapter, NeverPixelAdapter>;
+/// An alias for a transfer syntax specifier with FlateAdapter
+/// (stub descriptor, the `deflate` feature is disabled)
+#[cfg(not(feature = "deflate"))]
+pub type DeflatedTS = Ts;
+
+/// **Fully implemented:** Deflated Explicit VR Little Endian
+#[cfg(feature = "deflate")]
+pub const DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN: DeflatedTS = TransferSyntax::new(
+    "1.2.840.10008.1.2.1.99",
+    "Deflated Explicit VR Little Endian",
+    Endianness::Little,
+    true,
+    Codec::Dataset(FlateAdapter),
+);
 /// **Stub descriptor:** Deflated Explicit VR Little Endian
-pub const DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN: Ts = Ts::new(
+/// (the `deflate` feature is disabled)
+#[cfg(not(feature = "deflate"))]
+pub const DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN: DeflatedTS = Ts::new(
     "1.2.840.10008.1.2.1.99",
     "Deflated Explicit VR Little Endian",
     Endianness::Little,
@@ -77,6 +139,8 @@ pub const DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN: Ts = Ts::new(
     Codec::Unsupported,
 );
 
+// --- stub transfer syntaxes, known but not supported ---
+
 /// **Stub descriptor:** JPIP Referenced Deflate
 pub const JPIP_REFERENCED_DEFLATE: Ts = Ts::new(
     "1.2.840.10008.1.2.4.95",
@@ -88,9 +152,15 @@ pub const JPIP_REFERENCED_DEFLATE: Ts = Ts::new(
 
 // JPEG encoded pixel data
 /// An alias for a transfer syntax specifier with JPEGPixelAdapter
+#[cfg(feature = "jpeg")]
 pub type JpegTS = TransferSyntax<NeverAdapter, JPEGAdapter>;
+/// An alias for a transfer syntax specifier with JPEGPixelAdapter
+/// (stub descriptor, the `jpeg` feature is disabled)
+#[cfg(not(feature = "jpeg"))]
+pub type JpegTS = Ts;
 
 /// create a TS with jpeg encapsulation
+#[cfg(feature = "jpeg")]
 const fn create_ts_jpeg(uid: &'static str, name: &'static str) -> JpegTS {
     TransferSyntax::new(
         uid,
@@ -101,6 +171,12 @@ const fn create_ts_jpeg(uid: &'static str, name: &'static str) -> JpegTS {
     )
 }
 
+/// create a stub TS in place of jpeg encapsulation
+#[cfg(not(feature = "jpeg"))]
+const fn create_ts_jpeg(uid: &'static str, name: &'static str) -> JpegTS {
+    create_ts_stub(uid, name)
+}
+
 /// **Stub descriptor:** JPEG Baseline (Process 1): Default Transfer Syntax for Lossy JPEG 8 Bit Image Compression
 pub const JPEG_BASELINE: JpegTS =
     create_ts_jpeg("1.2.840.10008.1.2.4.50", "JPEG Baseline (Process 1)");
@@ -120,23 +196,73 @@ pub const JPEG_LOSSLESS_NON_HIERARCHICAL_FIRST_ORDER_PREDICTION: JpegTS = create
     "JPEG Lossless, Non-Hierarchical, First-Order Prediction",
 );
 
-// --- partially supported transfer syntaxes, pixel data encapsulation not supported ---
-/// **Stub descriptor:** JPEG-LS Lossless Image Compression
-pub const JPEG_LS_LOSSLESS_IMAGE_COMPRESSION: Ts = create_ts_stub(
+// JPEG-LS encoded pixel data
+/// An alias for a transfer syntax specifier with JpegLsAdapter
+#[cfg(feature = "jpeg-ls")]
+pub type JpegLsTS = TransferSyntax<NeverAdapter, JpegLsAdapter>;
+/// An alias for a transfer syntax specifier with JpegLsAdapter
+/// (stub descriptor, the `jpeg-ls` feature is disabled)
+#[cfg(not(feature = "jpeg-ls"))]
+pub type JpegLsTS = Ts;
+
+/// create a TS with JPEG-LS encapsulation
+#[cfg(feature = "jpeg-ls")]
+const fn create_ts_jpeg_ls(uid: &'static str, name: &'static str) -> JpegLsTS {
+    TransferSyntax::new(
+        uid,
+        name,
+        Endianness::Little,
+        true,
+        Codec::PixelData(JpegLsAdapter),
+    )
+}
+
+/// create a stub TS in place of JPEG-LS encapsulation
+#[cfg(not(feature = "jpeg-ls"))]
+const fn create_ts_jpeg_ls(uid: &'static str, name: &'static str) -> JpegLsTS {
+    create_ts_stub(uid, name)
+}
+
+/// **Fully supported:** JPEG-LS Lossless Image Compression
+pub const JPEG_LS_LOSSLESS_IMAGE_COMPRESSION: JpegLsTS = create_ts_jpeg_ls(
     "1.2.840.10008.1.2.4.80",
     "JPEG-LS Lossless Image Compression",
 );
-/// **Stub descriptor:** JPEG-LS Lossy (Near-Lossless) Image Compression
-pub const JPEG_LS_LOSSY_IMAGE_COMPRESSION: Ts = create_ts_stub(
+/// **Fully supported:** JPEG-LS Lossy (Near-Lossless) Image Compression
+pub const JPEG_LS_LOSSY_IMAGE_COMPRESSION: JpegLsTS = create_ts_jpeg_ls(
     "1.2.840.10008.1.2.4.81",
     "JPEG-LS Lossy (Near-Lossless) Image Compression",
 );
 
+// JPEG 2000 encoded pixel data
+/// An alias for a transfer syntax specifier with Jpeg2000Adapter
+#[cfg(feature = "jpeg2000")]
+pub type Jpeg2000TS = TransferSyntax<NeverAdapter, Jpeg2000Adapter>;
+/// An alias for a transfer syntax specifier with Jpeg2000Adapter
+/// (stub descriptor, the `jpeg2000` feature is disabled)
+#[cfg(not(feature = "jpeg2000"))]
+pub type Jpeg2000TS = Ts;
+
+/// **Partially supported:** JPEG 2000 Image Compression (Lossless Only).
+/// Pixel data can be encoded, but not decoded.
+#[cfg(feature = "jpeg2000")]
+pub const JPEG_2000_IMAGE_COMPRESSION_LOSSLESS_ONLY: Jpeg2000TS = TransferSyntax::new(
+    "1.2.840.10008.1.2.4.90",
+    "JPEG 2000 Image Compression (Lossless Only)",
+    Endianness::Little,
+    true,
+    Codec::PixelData(Jpeg2000Adapter::new()),
+);
 /// **Stub descriptor:** JPEG 2000 Image Compression (Lossless Only)
-pub const JPEG_2000_IMAGE_COMPRESSION_LOSSLESS_ONLY: Ts = create_ts_stub(
+/// (the `jpeg2000` feature is disabled)
+#[cfg(not(feature = "jpeg2000"))]
+pub const JPEG_2000_IMAGE_COMPRESSION_LOSSLESS_ONLY: Jpeg2000TS = create_ts_stub(
     "1.2.840.10008.1.2.4.90",
     "JPEG 2000 Image Compression (Lossless Only)",
 );
+
+// --- partially supported transfer syntaxes, pixel data encapsulation not supported ---
+
 /// **Stub descriptor:** JPEG 2000 Image Compression
 pub const JPEG_2000_IMAGE_COMPRESSION: Ts =
     create_ts_stub("1.2.840.10008.1.2.4.91", "JPEG 2000 Image Compression");
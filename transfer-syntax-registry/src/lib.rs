@@ -26,23 +26,73 @@
 //! _Implicit VR Little Endian_,
 //! _Explicit VR Little Endian_,
 //! and _Explicit VR Big Endian_ are built-in.
+//! _Deflated Explicit VR Little Endian_ is also supported,
+//! via a data set adapter which compresses and decompresses
+//! the whole data set.
 //! Transfer syntaxes which are not supported,
 //! or which rely on encapsulated pixel data,
 //! are only listed as _stubs_ to be replaced by separate libraries.
 //! The full list is available in the [`entries`](entries) module.
 //!
+//! ## Cargo features
+//!
+//! The pixel data codecs built into the registry
+//! can be selected through the features `jpeg`, `jpeg-ls`, `jpeg2000` and `rle`,
+//! all enabled by default through the `codecs` feature.
+//! Building without default features
+//! provides a minimal registry for parsing data sets,
+//! in which the transfer syntaxes of the disabled codecs are stubs.
+//! The `deflate` feature (enabled by default)
+//! provides support for _Deflated Explicit VR Little Endian_.
+//!
+//! ## Run-time registration
+//!
+//! Pixel data codecs which are not built in,
+//! such as proprietary or niche codecs,
+//! can also be plugged in while the program is running,
+//! with [`TransferSyntaxRegistry::register_pixel_codec`]
+//! for a known transfer syntax,
+//! or [`TransferSyntaxRegistry::register`] for a new one.
+//! These registrations take precedence over
+//! the built-in and inventory-based transfer syntaxes.
+//!
+//! ```
+//! # use dicom_encoding::adapters::{DecodeResult, PixelDataObject, PixelRWAdapter};
+//! use dicom_encoding::TransferSyntaxIndex;
+//! use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+//!
+//! /// a pixel data codec from another crate
+//! struct MyJpipAdapter;
+//!
+//! impl PixelRWAdapter for MyJpipAdapter {
+//!     // ...
+//! #   fn decode(&self, _src: &dyn PixelDataObject, _dst: &mut Vec<u8>) -> DecodeResult<()> {
+//! #       unimplemented!()
+//! #   }
+//! }
+//!
+//! // JPIP Referenced
+//! let uid = "1.2.840.10008.1.2.4.94";
+//! assert!(TransferSyntaxRegistry.register_pixel_codec(uid, MyJpipAdapter));
+//! assert!(TransferSyntaxRegistry.get(uid).unwrap().fully_supported());
+//! ```
+//!
 //! [inventory]: https://docs.rs/inventory/0.1.4/inventory
 
 use byteordered::Endianness;
+use dicom_encoding::adapters::{DynPixelRWAdapter, PixelRWAdapter};
 use dicom_encoding::transfer_syntax::{
     AdapterFreeTransferSyntax as Ts, Codec, TransferSyntaxIndex,
 };
 use lazy_static::lazy_static;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::sync::{RwLock, RwLockReadGuard};
 
 pub use dicom_encoding::TransferSyntax;
+#[cfg(feature = "deflate")]
+pub mod deflate;
 pub mod entries;
 
 /// Data type for a registry of DICOM.
@@ -120,9 +170,64 @@ impl TransferSyntaxIndex for TransferSyntaxRegistryImpl {
 
 impl TransferSyntaxRegistry {
     /// Obtain an iterator of all registered transfer syntaxes.
-    #[inline]
     pub fn iter(&self) -> impl Iterator<Item = &TransferSyntax> {
-        get_registry().iter()
+        let runtime: Vec<&'static TransferSyntax> =
+            get_runtime_registry().values().copied().collect();
+        let replaced: HashSet<&str> = runtime.iter().map(|ts| ts.uid()).collect();
+        get_registry()
+            .iter()
+            .filter(move |ts| !replaced.contains(ts.uid()))
+            .chain(runtime)
+    }
+
+    /// Register the given transfer syntax while the program is running.
+    ///
+    /// Unlike transfer syntaxes submitted through `inventory`,
+    /// it replaces any other transfer syntax with the same UID,
+    /// including built-in ones.
+    ///
+    /// Registered transfer syntaxes are never deallocated,
+    /// so this is meant to be called once per transfer syntax,
+    /// usually when the program starts.
+    pub fn register(&self, ts: TransferSyntax) {
+        let ts: &'static TransferSyntax = Box::leak(Box::new(ts));
+        RUNTIME_REGISTRY
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(ts.uid(), ts);
+    }
+
+    /// Register a pixel data codec
+    /// for the known transfer syntax with the given UID
+    /// while the program is running,
+    /// replacing the transfer syntax' current pixel data codec (if any).
+    ///
+    /// Returns `false` and makes no changes
+    /// if the transfer syntax is unknown,
+    /// or if it does not encapsulate pixel data.
+    /// See [`register`](Self::register) for more details.
+    pub fn register_pixel_codec<A>(&self, uid: &str, adapter: A) -> bool
+    where
+        A: PixelRWAdapter + Send + Sync + 'static,
+    {
+        let ts = match self.get(uid) {
+            Some(ts) => ts,
+            None => return false,
+        };
+        if !matches!(
+            ts.codec(),
+            Codec::EncapsulatedPixelData | Codec::PixelData(_)
+        ) {
+            return false;
+        }
+        self.register(TransferSyntax::new(
+            ts.uid(),
+            ts.name(),
+            ts.endianness(),
+            ts.explicit_vr(),
+            Codec::PixelData(Box::new(adapter) as DynPixelRWAdapter),
+        ));
+        true
     }
 }
 
@@ -133,6 +238,10 @@ pub struct TransferSyntaxRegistry;
 impl TransferSyntaxIndex for TransferSyntaxRegistry {
     #[inline]
     fn get(&self, uid: &str) -> Option<&TransferSyntax> {
+        let ts_uid = uid.trim_end_matches(|c: char| c.is_whitespace() || c == '\0');
+        if let Some(ts) = get_runtime_registry().get(ts_uid) {
+            return Some(*ts);
+        }
         get_registry().get(uid)
     }
 }
@@ -189,6 +298,13 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    /// Transfer syntaxes registered while the program is running,
+    /// which take precedence over the main registry.
+    static ref RUNTIME_REGISTRY: RwLock<HashMap<&'static str, &'static TransferSyntax>> =
+        RwLock::new(HashMap::new());
+}
+
 #[cfg(feature = "inventory-registry")]
 #[inline]
 fn inventory_populate(registry: &mut TransferSyntaxRegistryImpl) {
@@ -212,6 +328,12 @@ pub(crate) fn get_registry() -> &'static TransferSyntaxRegistryImpl {
     &REGISTRY
 }
 
+/// Retrieve the transfer syntaxes registered while the program is running.
+fn get_runtime_registry() -> RwLockReadGuard<'static, HashMap<&'static str, &'static TransferSyntax>>
+{
+    RUNTIME_REGISTRY.read().unwrap_or_else(|e| e.into_inner())
+}
+
 /// create a TS with an unsupported pixel encapsulation
 pub(crate) const fn create_ts_stub(uid: &'static str, name: &'static str) -> Ts {
     TransferSyntax::new(
@@ -230,9 +352,11 @@ pub fn default() -> Ts {
 
 #[cfg(test)]
 mod tests {
+    use dicom_encoding::adapters::{DecodeResult, PixelDataObject, PixelRWAdapter};
+    use dicom_encoding::transfer_syntax::{Codec, NeverAdapter};
     use dicom_encoding::TransferSyntaxIndex;
 
-    use crate::TransferSyntaxRegistry;
+    use crate::{Endianness, TransferSyntax, TransferSyntaxRegistry};
 
     #[test]
     fn has_mandatory_tss() {
@@ -273,4 +397,72 @@ mod tests {
         assert!(all_tss.iter().any(|ts| ts.uid() == "1.2.840.10008.1.2"));
         assert!(all_tss.iter().any(|ts| ts.uid() == "1.2.840.10008.1.2.1"));
     }
+
+    /// A pixel data adapter which is never called.
+    #[derive(Debug)]
+    struct DummyPixelAdapter;
+
+    impl PixelRWAdapter for DummyPixelAdapter {
+        fn decode(&self, _src: &dyn PixelDataObject, _dst: &mut Vec<u8>) -> DecodeResult<()> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn register_pixel_codec_at_run_time() {
+        let uid = "1.2.840.10008.1.2.7.1";
+        let ts = TransferSyntaxRegistry.get(uid).unwrap();
+        assert!(!ts.fully_supported());
+
+        assert!(TransferSyntaxRegistry.register_pixel_codec(uid, DummyPixelAdapter));
+        let ts = TransferSyntaxRegistry.get(uid).unwrap();
+        assert!(ts.fully_supported());
+        assert!(matches!(ts.codec(), Codec::PixelData(_)));
+        assert_eq!(
+            ts.name(),
+            "SMPTE ST 2110-20 Uncompressed Progressive Active Video"
+        );
+        assert!(ts.explicit_vr());
+
+        // replaces the stub when iterating
+        let matching: Vec<_> = TransferSyntaxRegistry
+            .iter()
+            .filter(|ts| ts.uid() == uid)
+            .collect();
+        assert_eq!(matching.len(), 1);
+        assert!(matching[0].fully_supported());
+
+        // unknown or native transfer syntaxes are not changed
+        assert!(!TransferSyntaxRegistry.register_pixel_codec("1.2.3.4.5.6", DummyPixelAdapter));
+        assert!(
+            !TransferSyntaxRegistry.register_pixel_codec("1.2.840.10008.1.2.1", DummyPixelAdapter)
+        );
+        assert!(TransferSyntaxRegistry
+            .get("1.2.840.10008.1.2.1")
+            .unwrap()
+            .is_codec_free());
+    }
+
+    #[test]
+    fn register_transfer_syntax_at_run_time() {
+        let uid = "1.2.840.10008.9999.9999.3";
+        assert!(TransferSyntaxRegistry.get(uid).is_none());
+
+        TransferSyntaxRegistry.register(
+            TransferSyntax::<NeverAdapter, _>::new(
+                uid,
+                "Private Lossless",
+                Endianness::Little,
+                true,
+                Codec::PixelData(DummyPixelAdapter),
+            )
+            .erased(),
+        );
+        let ts = TransferSyntaxRegistry
+            .get("1.2.840.10008.9999.9999.3\0")
+            .unwrap();
+        assert_eq!(ts.uid(), uid);
+        assert_eq!(ts.name(), "Private Lossless");
+        assert!(TransferSyntaxRegistry.iter().any(|ts| ts.uid() == uid));
+    }
 }
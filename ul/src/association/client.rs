@@ -20,6 +20,7 @@ use crate::{
     },
     AeAddr, IMPLEMENTATION_CLASS_UID, IMPLEMENTATION_VERSION_NAME,
 };
+use dicom_core::value::{AeTitle, AeTitleError};
 use snafu::{ensure, ResultExt, Snafu};
 
 use super::{
@@ -33,6 +34,9 @@ pub enum Error {
     /// missing abstract syntax to begin negotiation
     MissingAbstractSyntax,
 
+    /// invalid calling or called application entity title
+    InvalidAeTitle { source: AeTitleError },
+
     /// could not connect to server
     Connect { source: std::io::Error },
 
@@ -174,6 +178,8 @@ impl<'a> ClientAssociationOptions<'a> {
     /// which refers to this DICOM node.
     ///
     /// The default is `THIS-SCU`.
+    /// The title is validated when establishing the association,
+    /// which fails with [`Error::InvalidAeTitle`] if it is not a valid AE title.
     pub fn calling_ae_title<T>(mut self, calling_ae_title: T) -> Self
     where
         T: Into<Cow<'a, str>>,
@@ -188,6 +194,8 @@ impl<'a> ClientAssociationOptions<'a> {
     /// The default is `ANY-SCP`.
     /// Passing an emoty string resets the AE title to the default
     /// (or to the one passed via [`establish_with`](ClientAssociationOptions::establish_with)).
+    /// The title is validated when establishing the association,
+    /// which fails with [`Error::InvalidAeTitle`] if it is not a valid AE title.
     pub fn called_ae_title<T>(mut self, called_ae_title: T) -> Self
     where
         T: Into<Cow<'a, str>>,
@@ -319,6 +327,10 @@ impl<'a> ClientAssociationOptions<'a> {
             (None, None) => "ANY-SCP",
         };
 
+        // validate both AE titles before they reach the wire
+        let calling_ae_title = AeTitle::new(&calling_ae_title).context(InvalidAeTitleSnafu)?;
+        let called_ae_title = AeTitle::new(called_ae_title).context(InvalidAeTitleSnafu)?;
+
         let presentation_contexts: Vec<_> = presentation_contexts
             .into_iter()
             .enumerate()
@@ -334,8 +346,8 @@ impl<'a> ClientAssociationOptions<'a> {
             .collect();
         let msg = Pdu::AssociationRQ {
             protocol_version,
-            calling_ae_title: calling_ae_title.to_string(),
-            called_ae_title: called_ae_title.to_string(),
+            calling_ae_title: calling_ae_title.into_string(),
+            called_ae_title: called_ae_title.into_string(),
             application_context_name: application_context_name.to_string(),
             presentation_contexts,
             user_variables: vec![
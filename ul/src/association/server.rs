@@ -6,6 +6,7 @@
 //! for details and examples on how to create an association.
 use std::{borrow::Cow, io::Write, net::TcpStream};
 
+use dicom_core::value::{AeTitle, AeTitleError};
 use dicom_encoding::transfer_syntax::TransferSyntaxIndex;
 use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
 use snafu::{ensure, ResultExt, Snafu};
@@ -32,6 +33,9 @@ pub enum Error {
     /// missing at least one abstract syntax to accept negotiations
     MissingAbstractSyntax,
 
+    /// invalid application entity title of this node
+    InvalidAeTitle { source: AeTitleError },
+
     /// failed to receive association request
     ReceiveRequest { source: crate::pdu::reader::Error },
 
@@ -197,6 +201,8 @@ pub struct ServerAssociationOptions<'a, A> {
     max_pdu_length: u32,
     /// whether to receive PDUs in strict mode
     strict: bool,
+    /// whether to accept any abstract syntax
+    promiscuous: bool,
 }
 
 impl<'a> Default for ServerAssociationOptions<'a, AcceptAny> {
@@ -210,6 +216,7 @@ impl<'a> Default for ServerAssociationOptions<'a, AcceptAny> {
             protocol_version: 1,
             max_pdu_length: crate::pdu::reader::DEFAULT_MAX_PDU,
             strict: true,
+            promiscuous: false,
         }
     }
 }
@@ -258,6 +265,7 @@ where
             protocol_version,
             max_pdu_length,
             strict,
+            promiscuous,
             ae_access_control: _,
         } = self;
 
@@ -270,12 +278,15 @@ where
             protocol_version,
             max_pdu_length,
             strict,
+            promiscuous,
         }
     }
 
     /// Define the application entity title referring to this DICOM node.
     ///
     /// The default is `THIS-SCP`.
+    /// The title is validated when establishing an association,
+    /// which fails with [`Error::InvalidAeTitle`] if it is not a valid AE title.
     pub fn ae_title<T>(mut self, ae_title: T) -> Self
     where
         T: Into<Cow<'a, str>>,
@@ -319,12 +330,22 @@ where
         self
     }
 
+    /// Override promiscuous mode:
+    /// whether to accept presentation contexts
+    /// with any abstract syntax,
+    /// instead of only those in the list of abstract syntaxes.
+    pub fn promiscuous(mut self, promiscuous: bool) -> Self {
+        self.promiscuous = promiscuous;
+        self
+    }
+
     /// Negotiate an association with the given TCP stream.
     pub fn establish(&self, mut socket: TcpStream) -> Result<ServerAssociation> {
         ensure!(
-            !self.abstract_syntax_uids.is_empty(),
+            !self.abstract_syntax_uids.is_empty() || self.promiscuous,
             MissingAbstractSyntaxSnafu
         );
+        AeTitle::new(&self.ae_title).context(InvalidAeTitleSnafu)?;
 
         let max_pdu_length = self.max_pdu_length;
 
@@ -370,21 +391,30 @@ where
                     return RejectedSnafu.fail();
                 }
 
-                self.ae_access_control
-                    .check_access(&self.ae_title, &calling_ae_title, &called_ae_title)
-                    .map(Ok)
-                    .unwrap_or_else(|reason| {
-                        write_pdu(
-                            &mut buffer,
-                            &Pdu::AssociationRJ {
-                                result: AssociationRJResult::Permanent,
-                                source: AssociationRJSource::ServiceUser(reason),
-                            },
-                        )
-                        .context(SendResponseSnafu)?;
-                        socket.write_all(&buffer).context(WireSendSnafu)?;
-                        RejectedSnafu.fail()
-                    })?;
+                // in strict mode, malformed AE titles are never recognized
+                let access = if self.strict && !AeTitle::is_valid(&calling_ae_title) {
+                    Err(AssociationRJServiceUserReason::CallingAETitleNotRecognized)
+                } else if self.strict && !AeTitle::is_valid(&called_ae_title) {
+                    Err(AssociationRJServiceUserReason::CalledAETitleNotRecognized)
+                } else {
+                    self.ae_access_control.check_access(
+                        &self.ae_title,
+                        &calling_ae_title,
+                        &called_ae_title,
+                    )
+                };
+                access.map(Ok).unwrap_or_else(|reason| {
+                    write_pdu(
+                        &mut buffer,
+                        &Pdu::AssociationRJ {
+                            result: AssociationRJResult::Permanent,
+                            source: AssociationRJSource::ServiceUser(reason),
+                        },
+                    )
+                    .context(SendResponseSnafu)?;
+                    socket.write_all(&buffer).context(WireSendSnafu)?;
+                    RejectedSnafu.fail()
+                })?;
 
                 // fetch requested maximum PDU length
                 let requestor_max_pdu_length = user_variables
@@ -405,9 +435,10 @@ where
                 let presentation_contexts: Vec<_> = presentation_contexts
                     .into_iter()
                     .map(|pc| {
-                        if !self
-                            .abstract_syntax_uids
-                            .contains(&trim_uid(Cow::from(pc.abstract_syntax)))
+                        if !self.promiscuous
+                            && !self
+                                .abstract_syntax_uids
+                                .contains(&trim_uid(Cow::from(pc.abstract_syntax)))
                         {
                             return PresentationContextResult {
                                 id: pc.id,
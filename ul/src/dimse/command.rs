@@ -0,0 +1,208 @@
+//! DIMSE command sets as in-memory DICOM objects.
+use dicom_core::value::ConvertValueError;
+use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_object::mem::InMemElement;
+use dicom_object::InMemDicomObject;
+use dicom_transfer_syntax_registry::entries::IMPLICIT_VR_LITTLE_ENDIAN;
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+
+use crate::dimse::{CommandField, NO_DATA_SET};
+use crate::pdu::{PDataValue, PDataValueType, Pdu};
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// Could not encode the command set
+    WriteCommand {
+        #[snafu(backtrace)]
+        source: dicom_object::Error,
+    },
+    /// Could not decode the command set
+    ReadCommand {
+        #[snafu(backtrace)]
+        source: dicom_object::Error,
+    },
+    #[snafu(display("Missing command attribute {}", tag))]
+    MissingAttribute { tag: Tag, backtrace: Backtrace },
+    #[snafu(display("Invalid value for command attribute {}", tag))]
+    InvalidAttribute {
+        tag: Tag,
+        source: ConvertValueError,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Unknown command field {:04X}H", value))]
+    UnknownCommandField { value: u16, backtrace: Backtrace },
+    #[snafu(display("Expected {:?} message, but got {:?}", expected, got))]
+    UnexpectedCommand {
+        expected: CommandField,
+        got: CommandField,
+        backtrace: Backtrace,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Create a command set from the given elements.
+///
+/// The _Command Group Length_ (0000,0000) element is calculated
+/// and added to the command set automatically.
+/// Elements outside of the command group are ignored.
+pub fn command_set<I>(elements: I) -> InMemDicomObject
+where
+    I: IntoIterator<Item = InMemElement>,
+{
+    let mut obj =
+        InMemDicomObject::from_element_iter(elements.into_iter().filter(|e| {
+            e.header().tag.group() == 0 && e.header().tag != tags::COMMAND_GROUP_LENGTH
+        }));
+    let group_length: u32 = obj
+        .iter()
+        .map(|e| {
+            8 + even_len(
+                e.value()
+                    .primitive()
+                    .map(|v| v.calculate_byte_len())
+                    .unwrap_or(0),
+            )
+        })
+        .sum();
+    obj.put(DataElement::new(
+        tags::COMMAND_GROUP_LENGTH,
+        VR::UL,
+        PrimitiveValue::from(group_length),
+    ));
+    obj
+}
+
+/// Encode a command set into bytes in _Implicit VR Little Endian_.
+pub fn write_command(command: &InMemDicomObject) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(128);
+    command
+        .write_dataset_with_ts(&mut data, &IMPLICIT_VR_LITTLE_ENDIAN.erased())
+        .context(WriteCommandSnafu)?;
+    Ok(data)
+}
+
+/// Decode a command set from bytes in _Implicit VR Little Endian_.
+pub fn read_command(data: &[u8]) -> Result<InMemDicomObject> {
+    InMemDicomObject::read_dataset_with_ts(data, &IMPLICIT_VR_LITTLE_ENDIAN.erased())
+        .context(ReadCommandSnafu)
+}
+
+/// Encode a command set and wrap it in a single P-DATA-TF PDU.
+pub fn command_pdu(presentation_context_id: u8, command: &InMemDicomObject) -> Result<Pdu> {
+    Ok(Pdu::PData {
+        data: vec![PDataValue {
+            presentation_context_id,
+            value_type: PDataValueType::Command,
+            is_last: true,
+            data: write_command(command)?,
+        }],
+    })
+}
+
+/// Retrieve the command field of the given command set.
+pub fn command_field(command: &InMemDicomObject) -> Result<CommandField> {
+    let value = get_u16(command, tags::COMMAND_FIELD)?;
+    CommandField::from_code(value).context(UnknownCommandFieldSnafu { value })
+}
+
+/// Check whether the command set announces a data set
+/// following the command.
+pub fn has_data_set(command: &InMemDicomObject) -> Result<bool> {
+    Ok(get_u16(command, tags::COMMAND_DATA_SET_TYPE)? != NO_DATA_SET)
+}
+
+/// Retrieve the status code of the given response command set.
+pub fn status(command: &InMemDicomObject) -> Result<u16> {
+    get_u16(command, tags::STATUS)
+}
+
+pub(crate) fn expect_command(command: &InMemDicomObject, expected: CommandField) -> Result<()> {
+    let got = command_field(command)?;
+    snafu::ensure!(got == expected, UnexpectedCommandSnafu { expected, got });
+    Ok(())
+}
+
+pub(crate) fn get_u16(command: &InMemDicomObject, tag: Tag) -> Result<u16> {
+    command
+        .element_opt(tag)
+        .ok()
+        .flatten()
+        .context(MissingAttributeSnafu { tag })?
+        .to_int::<u16>()
+        .context(InvalidAttributeSnafu { tag })
+}
+
+pub(crate) fn get_uid(command: &InMemDicomObject, tag: Tag) -> Result<String> {
+    get_uid_opt(command, tag)?.context(MissingAttributeSnafu { tag })
+}
+
+pub(crate) fn get_uid_opt(command: &InMemDicomObject, tag: Tag) -> Result<Option<String>> {
+    Ok(command
+        .element_opt(tag)
+        .ok()
+        .flatten()
+        .and_then(|e| e.to_str().ok())
+        .map(|uid| {
+            uid.trim_end_matches(|c: char| c.is_whitespace() || c == '\0')
+                .to_string()
+        }))
+}
+
+pub(crate) fn get_str_opt(command: &InMemDicomObject, tag: Tag) -> Option<String> {
+    command
+        .element_opt(tag)
+        .ok()
+        .flatten()
+        .and_then(|e| e.to_str().ok())
+        .map(|s| s.into_owned())
+}
+
+fn even_len(l: usize) -> u32 {
+    ((l + 1) & !1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimse::CommandField;
+
+    #[test]
+    fn command_set_group_length() {
+        // same as the C-ECHO-RQ built in echoscu
+        let cmd = command_set([
+            DataElement::new(
+                tags::AFFECTED_SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.1.1"),
+            ),
+            DataElement::new(
+                tags::COMMAND_FIELD,
+                VR::US,
+                PrimitiveValue::from(0x0030_u16),
+            ),
+            DataElement::new(tags::MESSAGE_ID, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(
+                tags::COMMAND_DATA_SET_TYPE,
+                VR::US,
+                PrimitiveValue::from(NO_DATA_SET),
+            ),
+        ]);
+        let group_length = cmd
+            .element(tags::COMMAND_GROUP_LENGTH)
+            .unwrap()
+            .to_int::<u32>()
+            .unwrap();
+        assert_eq!(group_length, 8 + 18 + 8 + 2 + 8 + 2 + 8 + 2);
+
+        // group length matches the encoded command
+        let data = write_command(&cmd).unwrap();
+        assert_eq!(data.len() as u32, 12 + group_length);
+
+        let cmd = read_command(&data).unwrap();
+        assert_eq!(command_field(&cmd).unwrap(), CommandField::CEchoRq);
+        assert!(!has_data_set(&cmd).unwrap());
+    }
+}
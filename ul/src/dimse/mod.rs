@@ -0,0 +1,156 @@
+//! DIMSE message module
+//!
+//! This module provides the building blocks for composing and interpreting
+//! DICOM message service element (DIMSE) command sets,
+//! which are exchanged over an association
+//! in P-DATA-TF PDUs of the [command](crate::pdu::PDataValueType::Command) type.
+//!
+//! The command fields and status types defined here
+//! are always available.
+//! With the `object` feature,
+//! command sets are represented as [in-memory DICOM objects][1]
+//! and are always encoded in _Implicit VR Little Endian_.
+//! The `command_set` function builds one from a list of elements,
+//! filling in the _Command Group Length_ attribute automatically,
+//! and the `normalized` module contains typed message definitions
+//! for DIMSE-N services.
+//!
+//! [1]: https://docs.rs/dicom-object/latest/dicom_object/mem/struct.InMemDicomObject.html
+#[cfg(any(test, feature = "object"))]
+mod command;
+#[cfg(any(test, feature = "object"))]
+pub mod normalized;
+
+#[cfg(any(test, feature = "object"))]
+pub use self::command::*;
+
+/// The value of _Command Data Set Type_ (0000,0800)
+/// indicating that no data set is present in the message.
+pub const NO_DATA_SET: u16 = 0x0101;
+
+/// The value of _Command Data Set Type_ (0000,0800)
+/// conventionally used to indicate that a data set is present.
+pub const DATA_SET_PRESENT: u16 = 0x0000;
+
+/// An enumeration of all DIMSE commands,
+/// as identified by the _Command Field_ (0000,0100) attribute.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[repr(u16)]
+pub enum CommandField {
+    CStoreRq = 0x0001,
+    CStoreRsp = 0x8001,
+    CGetRq = 0x0010,
+    CGetRsp = 0x8010,
+    CFindRq = 0x0020,
+    CFindRsp = 0x8020,
+    CMoveRq = 0x0021,
+    CMoveRsp = 0x8021,
+    CEchoRq = 0x0030,
+    CEchoRsp = 0x8030,
+    NEventReportRq = 0x0100,
+    NEventReportRsp = 0x8100,
+    NGetRq = 0x0110,
+    NGetRsp = 0x8110,
+    NSetRq = 0x0120,
+    NSetRsp = 0x8120,
+    NActionRq = 0x0130,
+    NActionRsp = 0x8130,
+    NCreateRq = 0x0140,
+    NCreateRsp = 0x8140,
+    NDeleteRq = 0x0150,
+    NDeleteRsp = 0x8150,
+    CCancelRq = 0x0FFF,
+}
+
+impl CommandField {
+    /// Obtain the command field from its code,
+    /// or `None` if the code is not a known DIMSE command.
+    pub fn from_code(code: u16) -> Option<Self> {
+        use CommandField::*;
+        let field = match code {
+            0x0001 => CStoreRq,
+            0x8001 => CStoreRsp,
+            0x0010 => CGetRq,
+            0x8010 => CGetRsp,
+            0x0020 => CFindRq,
+            0x8020 => CFindRsp,
+            0x0021 => CMoveRq,
+            0x8021 => CMoveRsp,
+            0x0030 => CEchoRq,
+            0x8030 => CEchoRsp,
+            0x0100 => NEventReportRq,
+            0x8100 => NEventReportRsp,
+            0x0110 => NGetRq,
+            0x8110 => NGetRsp,
+            0x0120 => NSetRq,
+            0x8120 => NSetRsp,
+            0x0130 => NActionRq,
+            0x8130 => NActionRsp,
+            0x0140 => NCreateRq,
+            0x8140 => NCreateRsp,
+            0x0150 => NDeleteRq,
+            0x8150 => NDeleteRsp,
+            0x0FFF => CCancelRq,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    /// Retrieve the code of this command field.
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    /// Check whether this command is a response message.
+    pub fn is_response(self) -> bool {
+        self.code() & 0x8000 != 0
+    }
+}
+
+/// The general category of a DIMSE status code,
+/// as per PS3.7 Annex C.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StatusType {
+    Success,
+    Warning,
+    Failure,
+    Cancel,
+    Pending,
+}
+
+impl StatusType {
+    /// Classify the given status code.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            0x0000 => StatusType::Success,
+            0x0001 | 0x0107 | 0x0116 | 0xB000..=0xBFFF => StatusType::Warning,
+            0xFE00 => StatusType::Cancel,
+            0xFF00 | 0xFF01 => StatusType::Pending,
+            _ => StatusType::Failure,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_field_codes() {
+        assert_eq!(CommandField::from_code(0x0110), Some(CommandField::NGetRq));
+        assert_eq!(CommandField::NEventReportRsp.code(), 0x8100);
+        assert!(CommandField::NGetRsp.is_response());
+        assert!(!CommandField::CCancelRq.is_response());
+        assert_eq!(CommandField::from_code(0x1234), None);
+    }
+
+    #[test]
+    fn status_types() {
+        assert_eq!(StatusType::from_status(0), StatusType::Success);
+        assert_eq!(StatusType::from_status(0x0107), StatusType::Warning);
+        assert_eq!(StatusType::from_status(0xB605), StatusType::Warning);
+        assert_eq!(StatusType::from_status(0xFF01), StatusType::Pending);
+        assert_eq!(StatusType::from_status(0xFE00), StatusType::Cancel);
+        assert_eq!(StatusType::from_status(0x0112), StatusType::Failure);
+    }
+}
@@ -0,0 +1,961 @@
+//! DIMSE-N message definitions.
+//!
+//! Each message type can be turned into a command set
+//! with `to_command`,
+//! and obtained from a received command set with `from_command`.
+//! Data sets accompanying these messages
+//! (such as the attribute list of an N-GET response
+//! or the event information of an N-EVENT-REPORT request)
+//! are sent and received separately,
+//! in P-DATA-TF PDUs of the data type.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_ul::dimse::normalized::{NGetRequest, PRINTER_SOP_CLASS, PRINTER_SOP_INSTANCE};
+//! # use dicom_dictionary_std::tags;
+//! let request = NGetRequest {
+//!     message_id: 1,
+//!     requested_sop_class_uid: PRINTER_SOP_CLASS.to_string(),
+//!     requested_sop_instance_uid: PRINTER_SOP_INSTANCE.to_string(),
+//!     attribute_identifier_list: vec![tags::PRINTER_STATUS, tags::PRINTER_STATUS_INFO],
+//! };
+//! let command = request.to_command();
+//! assert_eq!(NGetRequest::from_command(&command)?, request);
+//! # Ok::<_, dicom_ul::dimse::Error>(())
+//! ```
+use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_object::mem::InMemElement;
+use dicom_object::InMemDicomObject;
+
+use super::{
+    command_set, expect_command, get_str_opt, get_u16, get_uid, get_uid_opt, has_data_set,
+    CommandField, Result, StatusType, DATA_SET_PRESENT, NO_DATA_SET,
+};
+
+/// Basic Grayscale Print Management Meta SOP Class UID
+pub const BASIC_GRAYSCALE_PRINT_MANAGEMENT_META_SOP_CLASS: &str = "1.2.840.10008.5.1.1.9";
+/// Basic Film Session SOP Class UID
+pub const BASIC_FILM_SESSION_SOP_CLASS: &str = "1.2.840.10008.5.1.1.1";
+/// Basic Film Box SOP Class UID
+pub const BASIC_FILM_BOX_SOP_CLASS: &str = "1.2.840.10008.5.1.1.2";
+/// Basic Grayscale Image Box SOP Class UID
+pub const BASIC_GRAYSCALE_IMAGE_BOX_SOP_CLASS: &str = "1.2.840.10008.5.1.1.4";
+/// Printer SOP Class UID
+pub const PRINTER_SOP_CLASS: &str = "1.2.840.10008.5.1.1.16";
+/// Well-known Printer SOP Instance UID
+pub const PRINTER_SOP_INSTANCE: &str = "1.2.840.10008.5.1.1.17";
+/// Print Job SOP Class UID
+pub const PRINT_JOB_SOP_CLASS: &str = "1.2.840.10008.5.1.1.14";
+/// Display System SOP Class UID
+pub const DISPLAY_SYSTEM_SOP_CLASS: &str = "1.2.840.10008.5.1.1.40";
+/// Well-known Display System SOP Instance UID
+pub const DISPLAY_SYSTEM_SOP_INSTANCE: &str = "1.2.840.10008.5.1.1.40.1";
+
+/// An N-GET request message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NGetRequest {
+    /// the message ID
+    pub message_id: u16,
+    /// the SOP class of the managed SOP instance
+    pub requested_sop_class_uid: String,
+    /// the managed SOP instance to retrieve attributes from
+    pub requested_sop_instance_uid: String,
+    /// the attributes to retrieve;
+    /// if empty, all attributes are requested
+    pub attribute_identifier_list: Vec<Tag>,
+}
+
+impl NGetRequest {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        let mut elements = vec![
+            uid_element(tags::REQUESTED_SOP_CLASS_UID, &self.requested_sop_class_uid),
+            command_field_element(CommandField::NGetRq),
+            u16_element(tags::MESSAGE_ID, self.message_id),
+            u16_element(tags::COMMAND_DATA_SET_TYPE, NO_DATA_SET),
+            uid_element(
+                tags::REQUESTED_SOP_INSTANCE_UID,
+                &self.requested_sop_instance_uid,
+            ),
+        ];
+        if !self.attribute_identifier_list.is_empty() {
+            elements.push(DataElement::new(
+                tags::ATTRIBUTE_IDENTIFIER_LIST,
+                VR::AT,
+                PrimitiveValue::Tags(self.attribute_identifier_list.iter().copied().collect()),
+            ));
+        }
+        command_set(elements)
+    }
+
+    /// Interpret the given command set as an N-GET request.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NGetRq)?;
+        let attribute_identifier_list = command
+            .element_opt(tags::ATTRIBUTE_IDENTIFIER_LIST)
+            .ok()
+            .flatten()
+            .and_then(|e| e.value().tags().ok())
+            .map(|tags| tags.to_vec())
+            .unwrap_or_default();
+        Ok(NGetRequest {
+            message_id: get_u16(command, tags::MESSAGE_ID)?,
+            requested_sop_class_uid: get_uid(command, tags::REQUESTED_SOP_CLASS_UID)?,
+            requested_sop_instance_uid: get_uid(command, tags::REQUESTED_SOP_INSTANCE_UID)?,
+            attribute_identifier_list,
+        })
+    }
+}
+
+/// An N-GET response message.
+///
+/// When the operation is successful,
+/// the retrieved attribute list follows as a data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NGetResponse {
+    /// the ID of the request message
+    pub message_id_being_responded_to: u16,
+    /// the SOP class of the managed SOP instance
+    pub affected_sop_class_uid: Option<String>,
+    /// the managed SOP instance
+    pub affected_sop_instance_uid: Option<String>,
+    /// the status code
+    pub status: u16,
+    /// whether the attribute list data set follows
+    pub has_data_set: bool,
+    /// an optional error comment from the provider
+    pub error_comment: Option<String>,
+}
+
+impl NGetResponse {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        response_command(
+            CommandField::NGetRsp,
+            self.message_id_being_responded_to,
+            self.affected_sop_class_uid.as_deref(),
+            self.affected_sop_instance_uid.as_deref(),
+            self.status,
+            self.has_data_set,
+            self.error_comment.as_deref(),
+        )
+    }
+
+    /// Interpret the given command set as an N-GET response.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NGetRsp)?;
+        Ok(NGetResponse {
+            message_id_being_responded_to: get_u16(command, tags::MESSAGE_ID_BEING_RESPONDED_TO)?,
+            affected_sop_class_uid: get_uid_opt(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid_opt(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            status: get_u16(command, tags::STATUS)?,
+            has_data_set: has_data_set(command)?,
+            error_comment: get_str_opt(command, tags::ERROR_COMMENT),
+        })
+    }
+
+    /// Obtain the category of the response's status code.
+    pub fn status_type(&self) -> StatusType {
+        StatusType::from_status(self.status)
+    }
+}
+
+/// An N-EVENT-REPORT request message.
+///
+/// Event information may follow as a data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NEventReportRequest {
+    /// the message ID
+    pub message_id: u16,
+    /// the SOP class of the SOP instance reporting the event
+    pub affected_sop_class_uid: String,
+    /// the SOP instance reporting the event
+    pub affected_sop_instance_uid: String,
+    /// the event type identifier, specific to the SOP class
+    pub event_type_id: u16,
+    /// whether the event information data set follows
+    pub has_data_set: bool,
+}
+
+impl NEventReportRequest {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        command_set(vec![
+            uid_element(tags::AFFECTED_SOP_CLASS_UID, &self.affected_sop_class_uid),
+            command_field_element(CommandField::NEventReportRq),
+            u16_element(tags::MESSAGE_ID, self.message_id),
+            data_set_type_element(self.has_data_set),
+            uid_element(
+                tags::AFFECTED_SOP_INSTANCE_UID,
+                &self.affected_sop_instance_uid,
+            ),
+            u16_element(tags::EVENT_TYPE_ID, self.event_type_id),
+        ])
+    }
+
+    /// Interpret the given command set as an N-EVENT-REPORT request.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NEventReportRq)?;
+        Ok(NEventReportRequest {
+            message_id: get_u16(command, tags::MESSAGE_ID)?,
+            affected_sop_class_uid: get_uid(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            event_type_id: get_u16(command, tags::EVENT_TYPE_ID)?,
+            has_data_set: has_data_set(command)?,
+        })
+    }
+
+    /// Interpret the reported event
+    /// according to the affected SOP class.
+    pub fn event(&self) -> ReportedEvent {
+        ReportedEvent::new(&self.affected_sop_class_uid, self.event_type_id)
+    }
+
+    /// Build the response to this request with the given status code.
+    pub fn response(&self, status: u16) -> NEventReportResponse {
+        NEventReportResponse {
+            message_id_being_responded_to: self.message_id,
+            affected_sop_class_uid: Some(self.affected_sop_class_uid.clone()),
+            affected_sop_instance_uid: Some(self.affected_sop_instance_uid.clone()),
+            event_type_id: Some(self.event_type_id),
+            status,
+            has_data_set: false,
+            error_comment: None,
+        }
+    }
+}
+
+/// An N-EVENT-REPORT response message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NEventReportResponse {
+    /// the ID of the request message
+    pub message_id_being_responded_to: u16,
+    /// the SOP class of the SOP instance which reported the event
+    pub affected_sop_class_uid: Option<String>,
+    /// the SOP instance which reported the event
+    pub affected_sop_instance_uid: Option<String>,
+    /// the event type identifier
+    pub event_type_id: Option<u16>,
+    /// the status code
+    pub status: u16,
+    /// whether an event reply data set follows
+    pub has_data_set: bool,
+    /// an optional error comment
+    pub error_comment: Option<String>,
+}
+
+impl NEventReportResponse {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        let mut command = response_command(
+            CommandField::NEventReportRsp,
+            self.message_id_being_responded_to,
+            self.affected_sop_class_uid.as_deref(),
+            self.affected_sop_instance_uid.as_deref(),
+            self.status,
+            self.has_data_set,
+            self.error_comment.as_deref(),
+        );
+        if let Some(event_type_id) = self.event_type_id {
+            command.put(u16_element(tags::EVENT_TYPE_ID, event_type_id));
+            command = command_set(command);
+        }
+        command
+    }
+
+    /// Interpret the given command set as an N-EVENT-REPORT response.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NEventReportRsp)?;
+        Ok(NEventReportResponse {
+            message_id_being_responded_to: get_u16(command, tags::MESSAGE_ID_BEING_RESPONDED_TO)?,
+            affected_sop_class_uid: get_uid_opt(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid_opt(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            event_type_id: get_u16(command, tags::EVENT_TYPE_ID).ok(),
+            status: get_u16(command, tags::STATUS)?,
+            has_data_set: has_data_set(command)?,
+            error_comment: get_str_opt(command, tags::ERROR_COMMENT),
+        })
+    }
+
+    /// Obtain the category of the response's status code.
+    pub fn status_type(&self) -> StatusType {
+        StatusType::from_status(self.status)
+    }
+}
+
+/// An N-SET request message.
+///
+/// The modification list always follows as a data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NSetRequest {
+    /// the message ID
+    pub message_id: u16,
+    /// the SOP class of the managed SOP instance
+    pub requested_sop_class_uid: String,
+    /// the managed SOP instance to modify
+    pub requested_sop_instance_uid: String,
+}
+
+impl NSetRequest {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        command_set(vec![
+            uid_element(tags::REQUESTED_SOP_CLASS_UID, &self.requested_sop_class_uid),
+            command_field_element(CommandField::NSetRq),
+            u16_element(tags::MESSAGE_ID, self.message_id),
+            data_set_type_element(true),
+            uid_element(
+                tags::REQUESTED_SOP_INSTANCE_UID,
+                &self.requested_sop_instance_uid,
+            ),
+        ])
+    }
+
+    /// Interpret the given command set as an N-SET request.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NSetRq)?;
+        Ok(NSetRequest {
+            message_id: get_u16(command, tags::MESSAGE_ID)?,
+            requested_sop_class_uid: get_uid(command, tags::REQUESTED_SOP_CLASS_UID)?,
+            requested_sop_instance_uid: get_uid(command, tags::REQUESTED_SOP_INSTANCE_UID)?,
+        })
+    }
+}
+
+/// An N-SET response message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NSetResponse {
+    /// the ID of the request message
+    pub message_id_being_responded_to: u16,
+    /// the SOP class of the managed SOP instance
+    pub affected_sop_class_uid: Option<String>,
+    /// the managed SOP instance
+    pub affected_sop_instance_uid: Option<String>,
+    /// the status code
+    pub status: u16,
+    /// whether the modified attribute list follows as a data set
+    pub has_data_set: bool,
+    /// an optional error comment from the provider
+    pub error_comment: Option<String>,
+}
+
+impl NSetResponse {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        response_command(
+            CommandField::NSetRsp,
+            self.message_id_being_responded_to,
+            self.affected_sop_class_uid.as_deref(),
+            self.affected_sop_instance_uid.as_deref(),
+            self.status,
+            self.has_data_set,
+            self.error_comment.as_deref(),
+        )
+    }
+
+    /// Interpret the given command set as an N-SET response.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NSetRsp)?;
+        Ok(NSetResponse {
+            message_id_being_responded_to: get_u16(command, tags::MESSAGE_ID_BEING_RESPONDED_TO)?,
+            affected_sop_class_uid: get_uid_opt(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid_opt(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            status: get_u16(command, tags::STATUS)?,
+            has_data_set: has_data_set(command)?,
+            error_comment: get_str_opt(command, tags::ERROR_COMMENT),
+        })
+    }
+
+    /// Obtain the category of the response's status code.
+    pub fn status_type(&self) -> StatusType {
+        StatusType::from_status(self.status)
+    }
+}
+
+/// An N-ACTION request message.
+///
+/// Action information may follow as a data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NActionRequest {
+    /// the message ID
+    pub message_id: u16,
+    /// the SOP class of the managed SOP instance
+    pub requested_sop_class_uid: String,
+    /// the managed SOP instance on which to perform the action
+    pub requested_sop_instance_uid: String,
+    /// the action type identifier, specific to the SOP class
+    pub action_type_id: u16,
+    /// whether the action information data set follows
+    pub has_data_set: bool,
+}
+
+impl NActionRequest {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        command_set(vec![
+            uid_element(tags::REQUESTED_SOP_CLASS_UID, &self.requested_sop_class_uid),
+            command_field_element(CommandField::NActionRq),
+            u16_element(tags::MESSAGE_ID, self.message_id),
+            data_set_type_element(self.has_data_set),
+            uid_element(
+                tags::REQUESTED_SOP_INSTANCE_UID,
+                &self.requested_sop_instance_uid,
+            ),
+            u16_element(tags::ACTION_TYPE_ID, self.action_type_id),
+        ])
+    }
+
+    /// Interpret the given command set as an N-ACTION request.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NActionRq)?;
+        Ok(NActionRequest {
+            message_id: get_u16(command, tags::MESSAGE_ID)?,
+            requested_sop_class_uid: get_uid(command, tags::REQUESTED_SOP_CLASS_UID)?,
+            requested_sop_instance_uid: get_uid(command, tags::REQUESTED_SOP_INSTANCE_UID)?,
+            action_type_id: get_u16(command, tags::ACTION_TYPE_ID)?,
+            has_data_set: has_data_set(command)?,
+        })
+    }
+}
+
+/// An N-ACTION response message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NActionResponse {
+    /// the ID of the request message
+    pub message_id_being_responded_to: u16,
+    /// the SOP class of the managed SOP instance
+    pub affected_sop_class_uid: Option<String>,
+    /// the managed SOP instance
+    pub affected_sop_instance_uid: Option<String>,
+    /// the action type identifier
+    pub action_type_id: Option<u16>,
+    /// the status code
+    pub status: u16,
+    /// whether an action reply data set follows
+    pub has_data_set: bool,
+    /// an optional error comment from the provider
+    pub error_comment: Option<String>,
+}
+
+impl NActionResponse {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        let mut command = response_command(
+            CommandField::NActionRsp,
+            self.message_id_being_responded_to,
+            self.affected_sop_class_uid.as_deref(),
+            self.affected_sop_instance_uid.as_deref(),
+            self.status,
+            self.has_data_set,
+            self.error_comment.as_deref(),
+        );
+        if let Some(action_type_id) = self.action_type_id {
+            command.put(u16_element(tags::ACTION_TYPE_ID, action_type_id));
+            command = command_set(command);
+        }
+        command
+    }
+
+    /// Interpret the given command set as an N-ACTION response.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NActionRsp)?;
+        Ok(NActionResponse {
+            message_id_being_responded_to: get_u16(command, tags::MESSAGE_ID_BEING_RESPONDED_TO)?,
+            affected_sop_class_uid: get_uid_opt(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid_opt(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            action_type_id: get_u16(command, tags::ACTION_TYPE_ID).ok(),
+            status: get_u16(command, tags::STATUS)?,
+            has_data_set: has_data_set(command)?,
+            error_comment: get_str_opt(command, tags::ERROR_COMMENT),
+        })
+    }
+
+    /// Obtain the category of the response's status code.
+    pub fn status_type(&self) -> StatusType {
+        StatusType::from_status(self.status)
+    }
+}
+
+/// An N-CREATE request message.
+///
+/// The initial attribute values may follow as a data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NCreateRequest {
+    /// the message ID
+    pub message_id: u16,
+    /// the SOP class of the SOP instance to create
+    pub affected_sop_class_uid: String,
+    /// the UID of the SOP instance to create;
+    /// if `None`, the provider assigns one
+    pub affected_sop_instance_uid: Option<String>,
+    /// whether the attribute list data set follows
+    pub has_data_set: bool,
+}
+
+impl NCreateRequest {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        let mut elements = vec![
+            uid_element(tags::AFFECTED_SOP_CLASS_UID, &self.affected_sop_class_uid),
+            command_field_element(CommandField::NCreateRq),
+            u16_element(tags::MESSAGE_ID, self.message_id),
+            data_set_type_element(self.has_data_set),
+        ];
+        if let Some(uid) = &self.affected_sop_instance_uid {
+            elements.push(uid_element(tags::AFFECTED_SOP_INSTANCE_UID, uid));
+        }
+        command_set(elements)
+    }
+
+    /// Interpret the given command set as an N-CREATE request.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NCreateRq)?;
+        Ok(NCreateRequest {
+            message_id: get_u16(command, tags::MESSAGE_ID)?,
+            affected_sop_class_uid: get_uid(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid_opt(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            has_data_set: has_data_set(command)?,
+        })
+    }
+}
+
+/// An N-CREATE response message.
+///
+/// The attribute list of the created SOP instance
+/// may follow as a data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NCreateResponse {
+    /// the ID of the request message
+    pub message_id_being_responded_to: u16,
+    /// the SOP class of the created SOP instance
+    pub affected_sop_class_uid: Option<String>,
+    /// the created SOP instance
+    pub affected_sop_instance_uid: Option<String>,
+    /// the status code
+    pub status: u16,
+    /// whether the attribute list data set follows
+    pub has_data_set: bool,
+    /// an optional error comment from the provider
+    pub error_comment: Option<String>,
+}
+
+impl NCreateResponse {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        response_command(
+            CommandField::NCreateRsp,
+            self.message_id_being_responded_to,
+            self.affected_sop_class_uid.as_deref(),
+            self.affected_sop_instance_uid.as_deref(),
+            self.status,
+            self.has_data_set,
+            self.error_comment.as_deref(),
+        )
+    }
+
+    /// Interpret the given command set as an N-CREATE response.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NCreateRsp)?;
+        Ok(NCreateResponse {
+            message_id_being_responded_to: get_u16(command, tags::MESSAGE_ID_BEING_RESPONDED_TO)?,
+            affected_sop_class_uid: get_uid_opt(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid_opt(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            status: get_u16(command, tags::STATUS)?,
+            has_data_set: has_data_set(command)?,
+            error_comment: get_str_opt(command, tags::ERROR_COMMENT),
+        })
+    }
+
+    /// Obtain the category of the response's status code.
+    pub fn status_type(&self) -> StatusType {
+        StatusType::from_status(self.status)
+    }
+}
+
+/// An N-DELETE request message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NDeleteRequest {
+    /// the message ID
+    pub message_id: u16,
+    /// the SOP class of the managed SOP instance
+    pub requested_sop_class_uid: String,
+    /// the managed SOP instance to delete
+    pub requested_sop_instance_uid: String,
+}
+
+impl NDeleteRequest {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        command_set(vec![
+            uid_element(tags::REQUESTED_SOP_CLASS_UID, &self.requested_sop_class_uid),
+            command_field_element(CommandField::NDeleteRq),
+            u16_element(tags::MESSAGE_ID, self.message_id),
+            data_set_type_element(false),
+            uid_element(
+                tags::REQUESTED_SOP_INSTANCE_UID,
+                &self.requested_sop_instance_uid,
+            ),
+        ])
+    }
+
+    /// Interpret the given command set as an N-DELETE request.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NDeleteRq)?;
+        Ok(NDeleteRequest {
+            message_id: get_u16(command, tags::MESSAGE_ID)?,
+            requested_sop_class_uid: get_uid(command, tags::REQUESTED_SOP_CLASS_UID)?,
+            requested_sop_instance_uid: get_uid(command, tags::REQUESTED_SOP_INSTANCE_UID)?,
+        })
+    }
+}
+
+/// An N-DELETE response message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NDeleteResponse {
+    /// the ID of the request message
+    pub message_id_being_responded_to: u16,
+    /// the SOP class of the deleted SOP instance
+    pub affected_sop_class_uid: Option<String>,
+    /// the deleted SOP instance
+    pub affected_sop_instance_uid: Option<String>,
+    /// the status code
+    pub status: u16,
+    /// an optional error comment from the provider
+    pub error_comment: Option<String>,
+}
+
+impl NDeleteResponse {
+    /// Build the command set of this message.
+    pub fn to_command(&self) -> InMemDicomObject {
+        response_command(
+            CommandField::NDeleteRsp,
+            self.message_id_being_responded_to,
+            self.affected_sop_class_uid.as_deref(),
+            self.affected_sop_instance_uid.as_deref(),
+            self.status,
+            false,
+            self.error_comment.as_deref(),
+        )
+    }
+
+    /// Interpret the given command set as an N-DELETE response.
+    pub fn from_command(command: &InMemDicomObject) -> Result<Self> {
+        expect_command(command, CommandField::NDeleteRsp)?;
+        Ok(NDeleteResponse {
+            message_id_being_responded_to: get_u16(command, tags::MESSAGE_ID_BEING_RESPONDED_TO)?,
+            affected_sop_class_uid: get_uid_opt(command, tags::AFFECTED_SOP_CLASS_UID)?,
+            affected_sop_instance_uid: get_uid_opt(command, tags::AFFECTED_SOP_INSTANCE_UID)?,
+            status: get_u16(command, tags::STATUS)?,
+            error_comment: get_str_opt(command, tags::ERROR_COMMENT),
+        })
+    }
+
+    /// Obtain the category of the response's status code.
+    pub fn status_type(&self) -> StatusType {
+        StatusType::from_status(self.status)
+    }
+}
+
+/// An event reported by the Printer SOP class.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PrinterEvent {
+    Normal = 1,
+    Warning = 2,
+    Failure = 3,
+}
+
+/// An event reported by the Print Job SOP class.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PrintJobEvent {
+    Pending = 1,
+    Printing = 2,
+    Done = 3,
+    Failure = 4,
+}
+
+/// A typed interpretation of an N-EVENT-REPORT event.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ReportedEvent {
+    /// An event reported by a printer
+    Printer(PrinterEvent),
+    /// An event reported by a print job
+    PrintJob(PrintJobEvent),
+    /// An event of another SOP class, or an unrecognized event type
+    Other(u16),
+}
+
+impl ReportedEvent {
+    /// Interpret an event type ID in the context of the given SOP class.
+    pub fn new(sop_class_uid: &str, event_type_id: u16) -> Self {
+        match (sop_class_uid, event_type_id) {
+            (PRINTER_SOP_CLASS, 1) => ReportedEvent::Printer(PrinterEvent::Normal),
+            (PRINTER_SOP_CLASS, 2) => ReportedEvent::Printer(PrinterEvent::Warning),
+            (PRINTER_SOP_CLASS, 3) => ReportedEvent::Printer(PrinterEvent::Failure),
+            (PRINT_JOB_SOP_CLASS, 1) => ReportedEvent::PrintJob(PrintJobEvent::Pending),
+            (PRINT_JOB_SOP_CLASS, 2) => ReportedEvent::PrintJob(PrintJobEvent::Printing),
+            (PRINT_JOB_SOP_CLASS, 3) => ReportedEvent::PrintJob(PrintJobEvent::Done),
+            (PRINT_JOB_SOP_CLASS, 4) => ReportedEvent::PrintJob(PrintJobEvent::Failure),
+            (_, id) => ReportedEvent::Other(id),
+        }
+    }
+
+    /// Retrieve the event type ID of this event.
+    pub fn event_type_id(self) -> u16 {
+        match self {
+            ReportedEvent::Printer(e) => e as u16,
+            ReportedEvent::PrintJob(e) => e as u16,
+            ReportedEvent::Other(id) => id,
+        }
+    }
+}
+
+/// Build a DIMSE-N response command set with the common attributes.
+pub(crate) fn response_command(
+    field: CommandField,
+    message_id_being_responded_to: u16,
+    affected_sop_class_uid: Option<&str>,
+    affected_sop_instance_uid: Option<&str>,
+    status: u16,
+    has_data_set: bool,
+    error_comment: Option<&str>,
+) -> InMemDicomObject {
+    let mut elements = vec![
+        command_field_element(field),
+        u16_element(
+            tags::MESSAGE_ID_BEING_RESPONDED_TO,
+            message_id_being_responded_to,
+        ),
+        data_set_type_element(has_data_set),
+        u16_element(tags::STATUS, status),
+    ];
+    if let Some(uid) = affected_sop_class_uid {
+        elements.push(uid_element(tags::AFFECTED_SOP_CLASS_UID, uid));
+    }
+    if let Some(uid) = affected_sop_instance_uid {
+        elements.push(uid_element(tags::AFFECTED_SOP_INSTANCE_UID, uid));
+    }
+    if let Some(comment) = error_comment {
+        elements.push(DataElement::new(
+            tags::ERROR_COMMENT,
+            VR::LO,
+            PrimitiveValue::from(comment),
+        ));
+    }
+    command_set(elements)
+}
+
+pub(crate) fn uid_element(tag: Tag, uid: &str) -> InMemElement {
+    DataElement::new(tag, VR::UI, PrimitiveValue::from(uid))
+}
+
+pub(crate) fn u16_element(tag: Tag, value: u16) -> InMemElement {
+    DataElement::new(tag, VR::US, PrimitiveValue::from(value))
+}
+
+pub(crate) fn command_field_element(field: CommandField) -> InMemElement {
+    u16_element(tags::COMMAND_FIELD, field.code())
+}
+
+pub(crate) fn data_set_type_element(has_data_set: bool) -> InMemElement {
+    u16_element(
+        tags::COMMAND_DATA_SET_TYPE,
+        if has_data_set {
+            DATA_SET_PRESENT
+        } else {
+            NO_DATA_SET
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimse::{read_command, write_command};
+
+    #[test]
+    fn n_get_roundtrip() {
+        let request = NGetRequest {
+            message_id: 3,
+            requested_sop_class_uid: DISPLAY_SYSTEM_SOP_CLASS.to_string(),
+            requested_sop_instance_uid: DISPLAY_SYSTEM_SOP_INSTANCE.to_string(),
+            attribute_identifier_list: vec![Tag(0x0008, 0x0070), Tag(0x0018, 0x1000)],
+        };
+        let data = write_command(&request.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert_eq!(NGetRequest::from_command(&command).unwrap(), request);
+
+        // not an N-GET response
+        assert!(NGetResponse::from_command(&command).is_err());
+
+        let response = NGetResponse {
+            message_id_being_responded_to: 3,
+            affected_sop_class_uid: Some(DISPLAY_SYSTEM_SOP_CLASS.to_string()),
+            affected_sop_instance_uid: Some(DISPLAY_SYSTEM_SOP_INSTANCE.to_string()),
+            status: 0x0107,
+            has_data_set: true,
+            error_comment: None,
+        };
+        let data = write_command(&response.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        let got = NGetResponse::from_command(&command).unwrap();
+        assert_eq!(got, response);
+        assert_eq!(got.status_type(), StatusType::Warning);
+    }
+
+    #[test]
+    fn n_get_all_attributes() {
+        let request = NGetRequest {
+            message_id: 1,
+            requested_sop_class_uid: PRINTER_SOP_CLASS.to_string(),
+            requested_sop_instance_uid: PRINTER_SOP_INSTANCE.to_string(),
+            attribute_identifier_list: vec![],
+        };
+        let command = request.to_command();
+        assert!(command.element(tags::ATTRIBUTE_IDENTIFIER_LIST).is_err());
+        assert_eq!(NGetRequest::from_command(&command).unwrap(), request);
+    }
+
+    #[test]
+    fn n_event_report_roundtrip() {
+        let request = NEventReportRequest {
+            message_id: 7,
+            affected_sop_class_uid: PRINT_JOB_SOP_CLASS.to_string(),
+            affected_sop_instance_uid: "1.2.3.4.5".to_string(),
+            event_type_id: 3,
+            has_data_set: true,
+        };
+        let data = write_command(&request.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        let got = NEventReportRequest::from_command(&command).unwrap();
+        assert_eq!(got, request);
+        assert_eq!(got.event(), ReportedEvent::PrintJob(PrintJobEvent::Done));
+
+        let response = got.response(0);
+        let data = write_command(&response.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        let got = NEventReportResponse::from_command(&command).unwrap();
+        assert_eq!(got, response);
+        assert_eq!(got.event_type_id, Some(3));
+        assert_eq!(got.status_type(), StatusType::Success);
+    }
+
+    #[test]
+    fn n_create_roundtrip() {
+        let request = NCreateRequest {
+            message_id: 1,
+            affected_sop_class_uid: BASIC_FILM_SESSION_SOP_CLASS.to_string(),
+            affected_sop_instance_uid: None,
+            has_data_set: true,
+        };
+        let data = write_command(&request.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert!(command.element(tags::AFFECTED_SOP_INSTANCE_UID).is_err());
+        assert_eq!(NCreateRequest::from_command(&command).unwrap(), request);
+
+        let response = NCreateResponse {
+            message_id_being_responded_to: 1,
+            affected_sop_class_uid: Some(BASIC_FILM_SESSION_SOP_CLASS.to_string()),
+            affected_sop_instance_uid: Some("1.2.3.4.5.6".to_string()),
+            status: 0,
+            has_data_set: false,
+            error_comment: None,
+        };
+        let data = write_command(&response.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert_eq!(NCreateResponse::from_command(&command).unwrap(), response);
+    }
+
+    #[test]
+    fn n_set_action_delete_roundtrip() {
+        let request = NSetRequest {
+            message_id: 3,
+            requested_sop_class_uid: BASIC_GRAYSCALE_IMAGE_BOX_SOP_CLASS.to_string(),
+            requested_sop_instance_uid: "1.2.3.4.5.6.1".to_string(),
+        };
+        let data = write_command(&request.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert!(has_data_set(&command).unwrap());
+        assert_eq!(NSetRequest::from_command(&command).unwrap(), request);
+
+        let response = NSetResponse {
+            message_id_being_responded_to: 3,
+            affected_sop_class_uid: None,
+            affected_sop_instance_uid: None,
+            status: 0xC616,
+            has_data_set: false,
+            error_comment: Some("Image position collision".to_string()),
+        };
+        let data = write_command(&response.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        let got = NSetResponse::from_command(&command).unwrap();
+        assert_eq!(got, response);
+        assert_eq!(got.status_type(), StatusType::Failure);
+
+        let request = NActionRequest {
+            message_id: 4,
+            requested_sop_class_uid: BASIC_FILM_BOX_SOP_CLASS.to_string(),
+            requested_sop_instance_uid: "1.2.3.4.5.6".to_string(),
+            action_type_id: 1,
+            has_data_set: false,
+        };
+        let data = write_command(&request.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert_eq!(NActionRequest::from_command(&command).unwrap(), request);
+
+        let response = NActionResponse {
+            message_id_being_responded_to: 4,
+            affected_sop_class_uid: Some(BASIC_FILM_BOX_SOP_CLASS.to_string()),
+            affected_sop_instance_uid: Some("1.2.3.4.5.6".to_string()),
+            action_type_id: Some(1),
+            status: 0,
+            has_data_set: true,
+            error_comment: None,
+        };
+        let data = write_command(&response.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert_eq!(NActionResponse::from_command(&command).unwrap(), response);
+
+        let request = NDeleteRequest {
+            message_id: 5,
+            requested_sop_class_uid: BASIC_FILM_SESSION_SOP_CLASS.to_string(),
+            requested_sop_instance_uid: "1.2.3.4.5".to_string(),
+        };
+        let data = write_command(&request.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert!(!has_data_set(&command).unwrap());
+        assert_eq!(NDeleteRequest::from_command(&command).unwrap(), request);
+
+        let response = NDeleteResponse {
+            message_id_being_responded_to: 5,
+            affected_sop_class_uid: Some(BASIC_FILM_SESSION_SOP_CLASS.to_string()),
+            affected_sop_instance_uid: Some("1.2.3.4.5".to_string()),
+            status: 0,
+            error_comment: None,
+        };
+        let data = write_command(&response.to_command()).unwrap();
+        let command = read_command(&data).unwrap();
+        assert_eq!(NDeleteResponse::from_command(&command).unwrap(), response);
+    }
+
+    #[test]
+    fn typed_events() {
+        assert_eq!(
+            ReportedEvent::new(PRINTER_SOP_CLASS, 2),
+            ReportedEvent::Printer(PrinterEvent::Warning)
+        );
+        assert_eq!(
+            ReportedEvent::new(PRINTER_SOP_CLASS, 9),
+            ReportedEvent::Other(9)
+        );
+        assert_eq!(
+            ReportedEvent::new("1.2.840.10008.3.1.2.3.3", 1),
+            ReportedEvent::Other(1)
+        );
+        assert_eq!(
+            ReportedEvent::PrintJob(PrintJobEvent::Failure).event_type_id(),
+            4
+        );
+    }
+}
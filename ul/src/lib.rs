@@ -16,10 +16,20 @@
 //! comprises abstractions for establishing and negotiating associations
 //! between application entities,
 //! via the upper layer protocol by TCP.
+//! - The [`dimse`](crate::dimse) module
+//! provides the means to compose and interpret DIMSE command sets,
+//! including typed messages for the DIMSE-N services with the `object` feature.
+//! - The `test_support` module,
+//! available with the `test-support` feature,
+//! provides an in-memory virtual PACS and a DICOMweb stub server
+//! for integration testing.
 
 pub mod address;
 pub mod association;
+pub mod dimse;
 pub mod pdu;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 /// The current implementation class UID generically referring to DICOM-rs.
 ///
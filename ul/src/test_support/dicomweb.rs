@@ -0,0 +1,868 @@
+//! A DICOMweb stub server backed by an [`InMemoryStore`].
+//!
+//! The stub speaks plain HTTP/1.1 over a local TCP port,
+//! serving one request per connection.
+//! It implements the following subset of the DICOMweb services:
+//!
+//! - QIDO-RS:
+//!   `GET /studies`,
+//!   `GET /studies/{study}/series`
+//!   and `GET /studies/{study}/series/{series}/instances`.
+//!   Query parameters may name attributes by keyword or by tag
+//!   (e.g. `PatientID=123` or `00100020=123`),
+//!   and `limit`, `offset` and `includefield` are recognized.
+//!   Results are encoded in the DICOM JSON model,
+//!   except that bulk data values are omitted.
+//! - WADO-RS:
+//!   `GET /studies/{study}`,
+//!   `GET /studies/{study}/series/{series}`
+//!   and `GET /studies/{study}/series/{series}/instances/{instance}`,
+//!   responding with `multipart/related; type="application/dicom"`.
+//! - STOW-RS:
+//!   `POST /studies`
+//!   with a `multipart/related; type="application/dicom"` body.
+//!
+//! Any other request is answered with _404 Not Found_.
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use dicom_core::dictionary::{DataDictionary, DictionaryEntry};
+use dicom_core::header::Header;
+use dicom_core::value::Value;
+use dicom_core::{DataElement, Length, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::{tags, StandardDataDictionary};
+use dicom_object::matching::QueryRetrieveLevel;
+use dicom_object::mem::InMemElement;
+use dicom_object::{InMemDicomObject, OpenFileOptions};
+use tracing::warn;
+
+use super::{InMemoryStore, StoredObject};
+
+/// The boundary used in multipart responses.
+const BOUNDARY: &str = "DICOM-rs-test-support-boundary";
+
+/// Attributes always included in QIDO-RS study level results.
+const STUDY_KEYS: &[Tag] = &[
+    tags::STUDY_DATE,
+    tags::STUDY_TIME,
+    tags::ACCESSION_NUMBER,
+    tags::MODALITIES_IN_STUDY,
+    tags::REFERRING_PHYSICIAN_NAME,
+    tags::PATIENT_NAME,
+    tags::PATIENT_ID,
+    tags::PATIENT_BIRTH_DATE,
+    tags::PATIENT_SEX,
+    tags::STUDY_INSTANCE_UID,
+    tags::STUDY_ID,
+    tags::NUMBER_OF_STUDY_RELATED_SERIES,
+    tags::NUMBER_OF_STUDY_RELATED_INSTANCES,
+];
+
+/// Attributes always included in QIDO-RS series level results.
+const SERIES_KEYS: &[Tag] = &[
+    tags::MODALITY,
+    tags::SERIES_DESCRIPTION,
+    tags::SERIES_INSTANCE_UID,
+    tags::SERIES_NUMBER,
+    tags::NUMBER_OF_SERIES_RELATED_INSTANCES,
+];
+
+/// Attributes always included in QIDO-RS instance level results.
+const INSTANCE_KEYS: &[Tag] = &[
+    tags::SOP_CLASS_UID,
+    tags::SOP_INSTANCE_UID,
+    tags::INSTANCE_NUMBER,
+    tags::ROWS,
+    tags::COLUMNS,
+];
+
+/// An in-process DICOMweb server for integration tests.
+///
+/// The server stops listening when the value is dropped.
+#[derive(Debug)]
+pub struct DicomWebStub {
+    addr: SocketAddr,
+    store: InMemoryStore,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DicomWebStub {
+    /// Spawn a DICOMweb stub with a new empty store.
+    pub fn spawn() -> std::io::Result<Self> {
+        Self::spawn_with_store(InMemoryStore::new())
+    }
+
+    /// Spawn a DICOMweb stub backed by the given store,
+    /// which may be shared with a [`VirtualPacs`](super::VirtualPacs).
+    pub fn spawn_with_store(store: InMemoryStore) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let running = Arc::clone(&running);
+            let store = store.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    match stream {
+                        Ok(stream) => {
+                            let store = store.clone();
+                            std::thread::spawn(move || {
+                                if let Err(e) = serve(&store, stream) {
+                                    warn!("Could not serve HTTP request: {}", e);
+                                }
+                            });
+                        }
+                        Err(e) => warn!("Could not accept connection: {}", e),
+                    }
+                }
+            })
+        };
+
+        Ok(DicomWebStub {
+            addr,
+            store,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// The socket address on which the server is listening.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The base URL of the DICOMweb services,
+    /// such as `http://127.0.0.1:8042`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The store backing the server.
+    pub fn store(&self) -> &InMemoryStore {
+        &self.store
+    }
+
+    /// Stop accepting new requests
+    /// and wait for the listener thread to finish.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.running.store(false, Ordering::SeqCst);
+            // wake up the listener so that it sees the flag
+            let _ = TcpStream::connect(self.addr);
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DicomWebStub {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A parsed HTTP request.
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+/// An HTTP response to be written back to the client.
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    reason: &'static str,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: u16, reason: &'static str) -> Self {
+        Response {
+            status,
+            reason,
+            content_type: None,
+            body: Vec::new(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Response::new(404, "Not Found")
+    }
+
+    fn bad_request(message: &str) -> Self {
+        Response::new(400, "Bad Request").with_body("text/plain", message.as_bytes().to_vec())
+    }
+
+    fn with_body(mut self, content_type: impl Into<String>, body: Vec<u8>) -> Self {
+        self.content_type = Some(content_type.into());
+        self.body = body;
+        self
+    }
+
+    fn write_to(&self, mut to: impl Write) -> std::io::Result<()> {
+        write!(to, "HTTP/1.1 {} {}\r\n", self.status, self.reason)?;
+        if let Some(content_type) = &self.content_type {
+            write!(to, "Content-Type: {}\r\n", content_type)?;
+        }
+        write!(
+            to,
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.body.len()
+        )?;
+        to.write_all(&self.body)?;
+        to.flush()
+    }
+}
+
+/// Read a single request from the stream and respond to it.
+fn serve(store: &InMemoryStore, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = match read_request(&mut reader)? {
+        Some(request) => request,
+        // connection closed without a request
+        None => return Ok(()),
+    };
+    let response = handle(store, &request);
+    response.write_to(&stream)
+}
+
+fn read_request(reader: &mut impl BufRead) -> std::io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, parse_query(query)),
+        None => (target, Vec::new()),
+    };
+    let path = path.trim_end_matches('/').to_string();
+
+    let mut content_type = None;
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(Request {
+        method,
+        path,
+        query,
+        content_type,
+        body,
+    }))
+}
+
+fn handle(store: &InMemoryStore, request: &Request) -> Response {
+    let segments: Vec<&str> = request.path.split('/').skip(1).collect();
+    match (request.method.as_str(), &segments[..]) {
+        ("GET", ["studies"]) => search(store, QueryRetrieveLevel::Study, &[], &request.query),
+        ("GET", ["studies", study, "series"]) => search(
+            store,
+            QueryRetrieveLevel::Series,
+            &[(tags::STUDY_INSTANCE_UID, study)],
+            &request.query,
+        ),
+        ("GET", ["studies", study, "series", series, "instances"]) => search(
+            store,
+            QueryRetrieveLevel::Image,
+            &[
+                (tags::STUDY_INSTANCE_UID, study),
+                (tags::SERIES_INSTANCE_UID, series),
+            ],
+            &request.query,
+        ),
+        ("GET", ["studies", study]) => retrieve(store, &[(tags::STUDY_INSTANCE_UID, study)]),
+        ("GET", ["studies", study, "series", series]) => retrieve(
+            store,
+            &[
+                (tags::STUDY_INSTANCE_UID, study),
+                (tags::SERIES_INSTANCE_UID, series),
+            ],
+        ),
+        ("GET", ["studies", study, "series", series, "instances", instance]) => retrieve(
+            store,
+            &[
+                (tags::STUDY_INSTANCE_UID, study),
+                (tags::SERIES_INSTANCE_UID, series),
+                (tags::SOP_INSTANCE_UID, instance),
+            ],
+        ),
+        ("POST", ["studies"]) => stow(store, request),
+        _ => Response::not_found(),
+    }
+}
+
+/// Serve a QIDO-RS request.
+fn search(
+    store: &InMemoryStore,
+    level: QueryRetrieveLevel,
+    path_keys: &[(Tag, &&str)],
+    query: &[(String, String)],
+) -> Response {
+    let mut identifier = InMemDicomObject::new_empty();
+    identifier.put(DataElement::new(
+        tags::QUERY_RETRIEVE_LEVEL,
+        VR::CS,
+        PrimitiveValue::from(level.code()),
+    ));
+
+    let default_keys: Vec<Tag> = match level {
+        QueryRetrieveLevel::Patient | QueryRetrieveLevel::Study => STUDY_KEYS.to_vec(),
+        QueryRetrieveLevel::Series => SERIES_KEYS.to_vec(),
+        QueryRetrieveLevel::Image => INSTANCE_KEYS.to_vec(),
+    };
+    for tag in default_keys {
+        identifier.put(empty_key(tag));
+    }
+    for (tag, value) in path_keys {
+        identifier.put(DataElement::new(
+            *tag,
+            VR::UI,
+            PrimitiveValue::from(**value),
+        ));
+    }
+
+    let mut limit = usize::MAX;
+    let mut offset = 0;
+    for (name, value) in query {
+        match name.as_str() {
+            "limit" => limit = value.parse().unwrap_or(limit),
+            "offset" => offset = value.parse().unwrap_or(0),
+            "fuzzymatching" => {}
+            "includefield" => {
+                for field in value.split(',') {
+                    if let Some(tag) = parse_attribute(field) {
+                        if identifier.element_opt(tag).ok().flatten().is_none() {
+                            identifier.put(empty_key(tag));
+                        }
+                    }
+                }
+            }
+            _ => match parse_attribute(name) {
+                Some(tag) => {
+                    let vr = vr_of(tag);
+                    identifier.put(DataElement::new(
+                        tag,
+                        vr,
+                        PrimitiveValue::from(value.as_str()),
+                    ));
+                }
+                None => return Response::bad_request(&format!("Unknown attribute `{}`", name)),
+            },
+        }
+    }
+
+    let results = match store.query(&identifier) {
+        Ok(results) => results,
+        Err(e) => return Response::bad_request(&e.to_string()),
+    };
+    let results: Vec<String> = results
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(to_json)
+        .collect();
+    if results.is_empty() {
+        return Response::new(204, "No Content");
+    }
+    Response::new(200, "OK").with_body(
+        "application/dicom+json",
+        format!("[{}]", results.join(",")).into_bytes(),
+    )
+}
+
+/// Serve a WADO-RS request.
+fn retrieve(store: &InMemoryStore, path_keys: &[(Tag, &&str)]) -> Response {
+    let identifier = InMemDicomObject::from_element_iter(
+        path_keys
+            .iter()
+            .map(|(tag, value)| DataElement::new(*tag, VR::UI, PrimitiveValue::from(**value))),
+    );
+    let instances = match store.retrieve(&identifier) {
+        Ok(instances) => instances,
+        Err(e) => return Response::bad_request(&e.to_string()),
+    };
+    if instances.is_empty() {
+        return Response::not_found();
+    }
+
+    let mut body = Vec::new();
+    for instance in instances {
+        body.extend_from_slice(
+            format!("--{}\r\nContent-Type: application/dicom\r\n\r\n", BOUNDARY).as_bytes(),
+        );
+        if let Err(e) = instance.write_all(&mut body) {
+            warn!("Could not encode instance: {}", e);
+            return Response::new(500, "Internal Server Error");
+        }
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+
+    Response::new(200, "OK").with_body(
+        format!(
+            "multipart/related; type=\"application/dicom\"; boundary={}",
+            BOUNDARY
+        ),
+        body,
+    )
+}
+
+/// Serve a STOW-RS request.
+///
+/// Responds with _200 OK_ if all instances were stored,
+/// _409 Conflict_ if none were,
+/// or _202 Accepted_ otherwise.
+/// The body lists the stored instances
+/// in the _Referenced SOP Sequence_
+/// and the rejected ones in the _Failed SOP Sequence_.
+fn stow(store: &InMemoryStore, request: &Request) -> Response {
+    let boundary = match request
+        .content_type
+        .as_deref()
+        .filter(|ct| ct.starts_with("multipart/related"))
+        .and_then(boundary_of)
+    {
+        Some(boundary) => boundary,
+        None => {
+            return Response::new(415, "Unsupported Media Type");
+        }
+    };
+
+    let mut referenced = Vec::new();
+    let mut failed = Vec::new();
+    for part in multipart_parts(&request.body, &boundary) {
+        match read_part(part) {
+            Some(object) => {
+                referenced.push(reference_item(&object));
+                store.insert(object);
+            }
+            None => failed.push(InMemDicomObject::new_empty()),
+        }
+    }
+
+    let (status, reason) = match (referenced.is_empty(), failed.is_empty()) {
+        (_, true) => (200, "OK"),
+        (true, false) => (409, "Conflict"),
+        (false, false) => (202, "Accepted"),
+    };
+    let mut response = InMemDicomObject::new_empty();
+    if !referenced.is_empty() {
+        response.put(InMemElement::new(
+            tags::REFERENCED_SOP_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: referenced.into(),
+                size: Length::UNDEFINED,
+            },
+        ));
+    }
+    if !failed.is_empty() {
+        response.put(InMemElement::new(
+            tags::FAILED_SOP_SEQUENCE,
+            VR::SQ,
+            Value::Sequence {
+                items: failed.into(),
+                size: Length::UNDEFINED,
+            },
+        ));
+    }
+    Response::new(status, reason)
+        .with_body("application/dicom+json", to_json(&response).into_bytes())
+}
+
+/// Decode a DICOM file from a multipart body part,
+/// with or without the 128-byte preamble.
+fn read_part(part: &[u8]) -> Option<StoredObject> {
+    let header_end = find(part, b"\r\n\r\n")?;
+    let mut content = &part[header_end + 4..];
+    if content.len() >= 132 && &content[128..132] == b"DICM" {
+        content = &content[128..];
+    }
+    OpenFileOptions::new().from_reader(content).ok()
+}
+
+fn reference_item(object: &StoredObject) -> InMemDicomObject {
+    let meta = object.meta();
+    InMemDicomObject::from_element_iter(vec![
+        DataElement::new(
+            tags::REFERENCED_SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from(meta.media_storage_sop_class_uid.as_str()),
+        ),
+        DataElement::new(
+            tags::REFERENCED_SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(meta.media_storage_sop_instance_uid.as_str()),
+        ),
+    ])
+}
+
+/// Extract the boundary parameter of a multipart content type.
+fn boundary_of(content_type: &str) -> Option<String> {
+    content_type.split(';').find_map(|param| {
+        let (name, value) = param.trim().split_once('=')?;
+        if name.eq_ignore_ascii_case("boundary") {
+            Some(value.trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Split a multipart body into its parts,
+/// each one including its headers.
+fn multipart_parts<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = Vec::new();
+    let mut rest = match find(body, &delimiter) {
+        Some(pos) => &body[pos + delimiter.len()..],
+        None => return parts,
+    };
+    // each part starts after the line break following the delimiter
+    while rest.starts_with(b"\r\n") {
+        let content = &rest[2..];
+        let end = match find(content, &delimiter) {
+            Some(end) => end,
+            None => break,
+        };
+        // the line break before the delimiter belongs to the delimiter
+        let part = &content[..end];
+        parts.push(part.strip_suffix(b"\r\n").unwrap_or(part));
+        rest = &content[end + delimiter.len()..];
+    }
+    parts
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Resolve a query parameter naming an attribute,
+/// either by keyword or by its tag in hexadecimal (`GGGGEEEE`).
+fn parse_attribute(name: &str) -> Option<Tag> {
+    if name.len() == 8 {
+        if let (Ok(group), Ok(element)) = (
+            u16::from_str_radix(&name[..4], 16),
+            u16::from_str_radix(&name[4..], 16),
+        ) {
+            return Some(Tag(group, element));
+        }
+    }
+    StandardDataDictionary
+        .by_name(name)
+        .map(|entry| entry.tag())
+}
+
+fn vr_of(tag: Tag) -> VR {
+    StandardDataDictionary
+        .by_tag(tag)
+        .map(|entry| entry.vr())
+        .unwrap_or(VR::UN)
+}
+
+fn empty_key(tag: Tag) -> InMemElement {
+    DataElement::new(tag, vr_of(tag), PrimitiveValue::Empty)
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let byte = std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match byte {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Encode a data set in the DICOM JSON model.
+///
+/// Values of bulk data attributes (OB, OD, OF, OL, OV, OW and UN)
+/// are omitted.
+fn to_json(object: &InMemDicomObject) -> String {
+    let members: Vec<String> = object
+        .iter()
+        .map(|elem| {
+            format!(
+                "\"{:04X}{:04X}\":{}",
+                elem.tag().group(),
+                elem.tag().element(),
+                element_to_json(elem)
+            )
+        })
+        .collect();
+    format!("{{{}}}", members.join(","))
+}
+
+fn element_to_json(elem: &InMemElement) -> String {
+    let vr = elem.vr();
+    let values: Vec<String> = match elem.value() {
+        Value::Sequence { items, .. } => items.iter().map(to_json).collect(),
+        Value::PixelSequence { .. } => Vec::new(),
+        Value::Primitive(value) => match vr {
+            VR::OB | VR::OD | VR::OF | VR::OL | VR::OV | VR::OW | VR::UN => Vec::new(),
+            _ => value
+                .to_multi_str()
+                .iter()
+                .map(|v| value_to_json(vr, v))
+                .collect(),
+        },
+    };
+    if values.is_empty() {
+        format!("{{\"vr\":\"{}\"}}", vr.to_string())
+    } else {
+        format!(
+            "{{\"vr\":\"{}\",\"Value\":[{}]}}",
+            vr.to_string(),
+            values.join(",")
+        )
+    }
+}
+
+fn value_to_json(vr: VR, value: &str) -> String {
+    let value = value.trim_matches(|c: char| c == ' ' || c == '\0');
+    match vr {
+        VR::PN => format!("{{\"Alphabetic\":{}}}", json_string(value)),
+        VR::IS | VR::SL | VR::SS | VR::SV | VR::UL | VR::US | VR::UV => match value.parse::<i128>()
+        {
+            Ok(number) => number.to_string(),
+            Err(_) => json_string(value),
+        },
+        VR::DS | VR::FL | VR::FD => match value.parse::<f64>() {
+            Ok(number) if number.is_finite() => number.to_string(),
+            _ => json_string(value),
+        },
+        _ => json_string(value),
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_object::FileMetaTableBuilder;
+    use std::io::Read;
+
+    fn instance(sop_instance_uid: &str) -> StoredObject {
+        InMemDicomObject::from_element_iter(vec![
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7"),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(sop_instance_uid),
+            ),
+            DataElement::new(tags::PATIENT_NAME, VR::PN, PrimitiveValue::from("Doe^John")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("P-002")),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("OT")),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.40"),
+            ),
+            DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.41"),
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .transfer_syntax("1.2.840.10008.1.2.1"),
+        )
+        .unwrap()
+    }
+
+    /// Perform a single HTTP request, returning the status code,
+    /// the content type and the body of the response.
+    fn http(
+        stub: &DicomWebStub,
+        method: &str,
+        target: &str,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> (u16, Option<String>, Vec<u8>) {
+        let mut stream = TcpStream::connect(stub.addr()).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\n",
+            method, target
+        )
+        .unwrap();
+        if let Some(content_type) = content_type {
+            write!(stream, "Content-Type: {}\r\n", content_type).unwrap();
+        }
+        write!(stream, "Content-Length: {}\r\n\r\n", body.len()).unwrap();
+        stream.write_all(body).unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let status = line.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let mut content_type = None;
+        loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Type: ") {
+                content_type = Some(value.to_string());
+            }
+        }
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        (status, content_type, body)
+    }
+
+    #[test]
+    fn stow_search_and_retrieve() {
+        let stub = DicomWebStub::spawn().unwrap();
+
+        let mut body = Vec::new();
+        for uid in ["2.25.400.1", "2.25.400.2"] {
+            body.extend_from_slice(b"--XYZ\r\nContent-Type: application/dicom\r\n\r\n");
+            instance(uid).write_all(&mut body).unwrap();
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--XYZ--\r\n");
+        let (status, _, response) = http(
+            &stub,
+            "POST",
+            "/studies",
+            Some("multipart/related; type=\"application/dicom\"; boundary=XYZ"),
+            &body,
+        );
+        assert_eq!(status, 200);
+        assert!(String::from_utf8(response).unwrap().contains("2.25.400.2"));
+        assert_eq!(stub.store().len(), 2);
+
+        let (status, content_type, response) =
+            http(&stub, "GET", "/studies?PatientID=P-002", None, &[]);
+        assert_eq!(status, 200);
+        assert_eq!(content_type.as_deref(), Some("application/dicom+json"));
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.contains(r#""00100010":{"vr":"PN","Value":[{"Alphabetic":"Doe^John"}]}"#));
+        assert!(response.contains(r#""00201208":{"vr":"IS","Value":[2]}"#));
+
+        let (status, _, _) = http(&stub, "GET", "/studies?PatientID=OTHER", None, &[]);
+        assert_eq!(status, 204);
+
+        let (status, _, response) = http(
+            &stub,
+            "GET",
+            "/studies/2.25.40/series/2.25.41/instances?limit=1",
+            None,
+            &[],
+        );
+        assert_eq!(status, 200);
+        let response = String::from_utf8(response).unwrap();
+        assert_eq!(response.matches(r#""00080018""#).count(), 1);
+
+        let (status, content_type, response) = http(
+            &stub,
+            "GET",
+            "/studies/2.25.40/series/2.25.41/instances/2.25.400.1",
+            None,
+            &[],
+        );
+        assert_eq!(status, 200);
+        let boundary = boundary_of(&content_type.unwrap()).unwrap();
+        let parts = multipart_parts(&response, &boundary);
+        assert_eq!(parts.len(), 1);
+        let object = read_part(parts[0]).unwrap();
+        assert_eq!(object.meta().media_storage_sop_instance_uid(), "2.25.400.1");
+
+        let (status, _, _) = http(&stub, "GET", "/workitems", None, &[]);
+        assert_eq!(status, 404);
+    }
+}
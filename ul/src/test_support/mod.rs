@@ -0,0 +1,1316 @@
+//! Test doubles for integration testing of DICOM network applications.
+//!
+//! This module is only available with the `test-support` Cargo feature.
+//!
+//! - [`VirtualPacs`] is a service class provider
+//!   of the Verification (C-ECHO), Storage (C-STORE)
+//!   and Query/Retrieve (C-FIND and C-MOVE) services,
+//!   running on a local TCP port in a background thread.
+//! - [`dicomweb::DicomWebStub`] is a minimal HTTP server
+//!   exposing the same kind of store through a subset of DICOMweb
+//!   (QIDO-RS, WADO-RS and STOW-RS).
+//!
+//! Both are backed by an [`InMemoryStore`],
+//! which can be shared between them
+//! and inspected by the test after the exchange.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use dicom_ul::test_support::VirtualPacs;
+//! # use dicom_ul::ClientAssociationOptions;
+//! let pacs = VirtualPacs::spawn()?;
+//!
+//! let association = ClientAssociationOptions::new()
+//!     .with_abstract_syntax("1.2.840.10008.1.1")
+//!     .called_ae_title(pacs.ae_title())
+//!     .establish(pacs.addr())?;
+//! // ... exchange messages ...
+//! association.release()?;
+//!
+//! assert!(pacs.store().is_empty());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use dicom_core::header::Header;
+use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_encoding::transfer_syntax::{TransferSyntax, TransferSyntaxIndex};
+use dicom_object::matching::{self, QueryMatcher, QueryRetrieveLevel};
+use dicom_object::mem::InMemElement;
+use dicom_object::{FileDicomObject, FileMetaTableBuilder, InMemDicomObject};
+use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+use snafu::{whatever, OptionExt, ResultExt, Whatever};
+use tracing::warn;
+
+use crate::dimse::{self, CommandField, DATA_SET_PRESENT, NO_DATA_SET};
+use crate::pdu::{PDataValueType, Pdu};
+use crate::ServerAssociationOptions;
+use crate::{ClientAssociation, ClientAssociationOptions, ServerAssociation};
+
+pub mod dicomweb;
+
+/// The type of DICOM objects kept in an [`InMemoryStore`].
+pub type StoredObject = FileDicomObject<InMemDicomObject>;
+
+/// A thread-safe collection of DICOM objects,
+/// indexed by SOP instance UID.
+///
+/// Clones of a store share the same underlying collection.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    instances: Arc<Mutex<BTreeMap<String, StoredObject>>>,
+}
+
+impl InMemoryStore {
+    /// Create a new empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an object to the store,
+    /// keyed by the _Media Storage SOP Instance UID_ in its file meta group.
+    ///
+    /// Returns the object previously stored under the same UID, if any.
+    pub fn insert(&self, object: StoredObject) -> Option<StoredObject> {
+        let key = trim(&object.meta().media_storage_sop_instance_uid).to_string();
+        self.instances.lock().unwrap().insert(key, object)
+    }
+
+    /// Retrieve a copy of the object with the given SOP instance UID.
+    pub fn get(&self, sop_instance_uid: &str) -> Option<StoredObject> {
+        self.instances
+            .lock()
+            .unwrap()
+            .get(trim(sop_instance_uid))
+            .cloned()
+    }
+
+    /// Retrieve a copy of all objects in the store,
+    /// ordered by SOP instance UID.
+    pub fn instances(&self) -> Vec<StoredObject> {
+        self.instances.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Get the number of objects in the store.
+    pub fn len(&self) -> usize {
+        self.instances.lock().unwrap().len()
+    }
+
+    /// Check whether the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all objects from the store.
+    pub fn clear(&self) {
+        self.instances.lock().unwrap().clear();
+    }
+
+    /// Evaluate a C-FIND identifier against the store.
+    ///
+    /// Matching instances are grouped by the unique key
+    /// of the identifier's query/retrieve level
+    /// (study level if absent),
+    /// yielding one response identifier per group.
+    /// Each response contains the keys requested in the identifier,
+    /// filled in from the first instance of the group.
+    /// The keys _Number of Study Related Instances_,
+    /// _Number of Study Related Series_,
+    /// _Number of Series Related Instances_
+    /// and _Modalities in Study_
+    /// are computed from the whole group.
+    pub fn query(
+        &self,
+        identifier: &InMemDicomObject,
+    ) -> Result<Vec<InMemDicomObject>, Box<matching::Error>> {
+        let matcher = QueryMatcher::new(identifier);
+        let level = matcher.level()?.unwrap_or(QueryRetrieveLevel::Study);
+
+        let instances = self.instances.lock().unwrap();
+        let mut groups: Vec<(String, Vec<&StoredObject>)> = Vec::new();
+        for object in instances.values() {
+            if !matcher.matches(object)? {
+                continue;
+            }
+            let key = str_value(object, level.unique_key()).unwrap_or_default();
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, members)) => members.push(object),
+                None => groups.push((key, vec![object])),
+            }
+        }
+
+        Ok(groups
+            .into_iter()
+            .map(|(_, members)| response_identifier(identifier, level, &members))
+            .collect())
+    }
+
+    /// Collect copies of all instances matching the given identifier,
+    /// as done for the retrieval of objects in C-MOVE.
+    pub fn retrieve(
+        &self,
+        identifier: &InMemDicomObject,
+    ) -> Result<Vec<StoredObject>, Box<matching::Error>> {
+        let matcher = QueryMatcher::new(identifier);
+        let instances = self.instances.lock().unwrap();
+        let mut out = Vec::new();
+        for object in instances.values() {
+            if matcher.matches(object)? {
+                out.push(object.clone());
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Build a C-FIND response identifier for a group of matching instances.
+fn response_identifier(
+    identifier: &InMemDicomObject,
+    level: QueryRetrieveLevel,
+    members: &[&StoredObject],
+) -> InMemDicomObject {
+    let first = members[0];
+    let mut out = InMemDicomObject::new_empty();
+    for elem in identifier.iter() {
+        let tag = elem.tag();
+        let (vr, value) = match tag {
+            tags::QUERY_RETRIEVE_LEVEL => continue,
+            tags::NUMBER_OF_STUDY_RELATED_INSTANCES | tags::NUMBER_OF_SERIES_RELATED_INSTANCES => {
+                (VR::IS, PrimitiveValue::from(members.len().to_string()))
+            }
+            tags::NUMBER_OF_STUDY_RELATED_SERIES => {
+                let series: BTreeSet<_> = members
+                    .iter()
+                    .filter_map(|obj| str_value(obj, tags::SERIES_INSTANCE_UID))
+                    .collect();
+                (VR::IS, PrimitiveValue::from(series.len().to_string()))
+            }
+            tags::MODALITIES_IN_STUDY => {
+                let modalities: BTreeSet<_> = members
+                    .iter()
+                    .filter_map(|obj| str_value(obj, tags::MODALITY))
+                    .collect();
+                (
+                    VR::CS,
+                    PrimitiveValue::Strs(modalities.into_iter().collect()),
+                )
+            }
+            _ => match first.element_opt(tag) {
+                Ok(Some(found)) => {
+                    out.put(found.clone());
+                    continue;
+                }
+                _ => (elem.vr(), PrimitiveValue::Empty),
+            },
+        };
+        out.put(DataElement::new(tag, vr, value));
+    }
+    out.put(DataElement::new(
+        tags::QUERY_RETRIEVE_LEVEL,
+        VR::CS,
+        PrimitiveValue::from(level.code()),
+    ));
+    out
+}
+
+/// Options for spawning a [`VirtualPacs`].
+#[derive(Debug, Clone)]
+pub struct VirtualPacsOptions {
+    ae_title: String,
+    store: InMemoryStore,
+    move_destinations: BTreeMap<String, SocketAddr>,
+}
+
+impl Default for VirtualPacsOptions {
+    fn default() -> Self {
+        VirtualPacsOptions {
+            ae_title: "VIRTUAL-PACS".to_string(),
+            store: InMemoryStore::new(),
+            move_destinations: BTreeMap::new(),
+        }
+    }
+}
+
+impl VirtualPacsOptions {
+    /// Create a new set of options with the default values:
+    /// AE title `VIRTUAL-PACS`, an empty store,
+    /// and no known move destinations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define the application entity title of the PACS.
+    pub fn ae_title(mut self, ae_title: impl Into<String>) -> Self {
+        self.ae_title = ae_title.into();
+        self
+    }
+
+    /// Use the given store,
+    /// which may already contain objects
+    /// or be shared with other test doubles.
+    pub fn store(mut self, store: InMemoryStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Register a destination application entity for C-MOVE requests.
+    pub fn move_destination(mut self, ae_title: impl Into<String>, address: SocketAddr) -> Self {
+        self.move_destinations.insert(ae_title.into(), address);
+        self
+    }
+
+    /// Bind to a free local port and start serving associations
+    /// in a background thread.
+    pub fn spawn(self) -> std::io::Result<VirtualPacs> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let provider = Arc::new(Provider {
+            ae_title: self.ae_title.clone(),
+            store: self.store.clone(),
+            move_destinations: self.move_destinations,
+        });
+
+        let handle = {
+            let running = Arc::clone(&running);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            warn!("Could not accept connection: {}", e);
+                            continue;
+                        }
+                    };
+                    let provider = Arc::clone(&provider);
+                    std::thread::spawn(move || {
+                        if let Err(e) = provider.serve(stream) {
+                            warn!("Association terminated with error: {}", e);
+                        }
+                    });
+                }
+            })
+        };
+
+        Ok(VirtualPacs {
+            addr,
+            ae_title: self.ae_title,
+            store: self.store,
+            running,
+            handle: Some(handle),
+        })
+    }
+}
+
+/// An in-process PACS for integration tests.
+///
+/// Accepts associations for any abstract syntax
+/// and serves C-ECHO, C-STORE, C-FIND and C-MOVE requests
+/// against an [`InMemoryStore`].
+/// Other DIMSE requests are answered with the status
+/// _Unrecognized Operation_ (0211H).
+///
+/// The server stops listening when the value is dropped.
+#[derive(Debug)]
+pub struct VirtualPacs {
+    addr: SocketAddr,
+    ae_title: String,
+    store: InMemoryStore,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl VirtualPacs {
+    /// Spawn a virtual PACS with the default options.
+    pub fn spawn() -> std::io::Result<Self> {
+        VirtualPacsOptions::new().spawn()
+    }
+
+    /// Obtain a new set of options for spawning a virtual PACS.
+    pub fn options() -> VirtualPacsOptions {
+        VirtualPacsOptions::new()
+    }
+
+    /// The socket address on which the PACS is listening.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The application entity title of the PACS.
+    pub fn ae_title(&self) -> &str {
+        &self.ae_title
+    }
+
+    /// The full AE address of the PACS,
+    /// in the form `{ae_title}@{address}`.
+    pub fn ae_address(&self) -> String {
+        format!("{}@{}", self.ae_title, self.addr)
+    }
+
+    /// The store backing the PACS.
+    pub fn store(&self) -> &InMemoryStore {
+        &self.store
+    }
+
+    /// Stop accepting new associations
+    /// and wait for the listener thread to finish.
+    ///
+    /// Associations already in progress are not interrupted.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.running.store(false, Ordering::SeqCst);
+            // wake up the listener so that it sees the flag
+            let _ = TcpStream::connect(self.addr);
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for VirtualPacs {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The shared state of the service class provider.
+#[derive(Debug)]
+struct Provider {
+    ae_title: String,
+    store: InMemoryStore,
+    move_destinations: BTreeMap<String, SocketAddr>,
+}
+
+/// A complete DIMSE message received over an association.
+#[derive(Debug)]
+struct Message {
+    presentation_context_id: u8,
+    command: InMemDicomObject,
+    data: Option<Vec<u8>>,
+}
+
+impl Provider {
+    /// Negotiate an association over the given stream
+    /// and serve requests until it is released or aborted.
+    fn serve(&self, stream: TcpStream) -> Result<(), Whatever> {
+        let mut association = ServerAssociationOptions::new()
+            .accept_any()
+            .ae_title(self.ae_title.as_str())
+            .promiscuous(true)
+            .establish(stream)
+            .whatever_context("Could not establish association")?;
+
+        while let Some(message) = receive_message(&mut association)? {
+            let field =
+                dimse::command_field(&message.command).whatever_context("Invalid command set")?;
+            match field {
+                CommandField::CEchoRq => {
+                    let rsp = response(&message.command, CommandField::CEchoRsp, 0x0000, false);
+                    send_message(
+                        &mut association,
+                        message.presentation_context_id,
+                        &rsp,
+                        None,
+                    )?;
+                }
+                CommandField::CStoreRq => self.handle_store(&mut association, message)?,
+                CommandField::CFindRq => self.handle_find(&mut association, message)?,
+                CommandField::CMoveRq => self.handle_move(&mut association, message)?,
+                CommandField::CCancelRq => {}
+                other => {
+                    // respond with the matching response command, if there is one
+                    if let Some(rsp_field) = CommandField::from_code(other.code() | 0x8000) {
+                        let rsp = response(&message.command, rsp_field, 0x0211, false);
+                        send_message(
+                            &mut association,
+                            message.presentation_context_id,
+                            &rsp,
+                            None,
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_store(
+        &self,
+        association: &mut ServerAssociation,
+        message: Message,
+    ) -> Result<(), Whatever> {
+        let pc_id = message.presentation_context_id;
+        let status = match self.store_instance(association, &message) {
+            Ok(()) => 0x0000,
+            Err(e) => {
+                warn!("Could not store instance: {}", e);
+                0xC000
+            }
+        };
+        let extra = dimse::get_uid_opt(&message.command, tags::AFFECTED_SOP_INSTANCE_UID)
+            .ok()
+            .flatten()
+            .map(|uid| {
+                DataElement::new(
+                    tags::AFFECTED_SOP_INSTANCE_UID,
+                    VR::UI,
+                    PrimitiveValue::from(uid),
+                )
+            })
+            .into_iter()
+            .collect();
+        let rsp = response_with(
+            &message.command,
+            CommandField::CStoreRsp,
+            status,
+            false,
+            extra,
+        );
+        send_message(association, pc_id, &rsp, None)
+    }
+
+    fn store_instance(
+        &self,
+        association: &ServerAssociation,
+        message: &Message,
+    ) -> Result<(), Whatever> {
+        let ts = transfer_syntax_of(association, message.presentation_context_id)?;
+        let data = message
+            .data
+            .as_ref()
+            .whatever_context("C-STORE request without a data set")?;
+        let sop_class_uid = dimse::get_uid(&message.command, tags::AFFECTED_SOP_CLASS_UID)
+            .whatever_context("Missing affected SOP class UID")?;
+        let sop_instance_uid = dimse::get_uid(&message.command, tags::AFFECTED_SOP_INSTANCE_UID)
+            .whatever_context("Missing affected SOP instance UID")?;
+        let object = InMemDicomObject::read_dataset_with_ts(&data[..], ts)
+            .whatever_context("Could not read data set")?
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid(sop_class_uid)
+                    .media_storage_sop_instance_uid(sop_instance_uid)
+                    .transfer_syntax(ts.uid()),
+            )
+            .whatever_context("Could not build file meta group")?;
+        self.store.insert(object);
+        Ok(())
+    }
+
+    fn handle_find(
+        &self,
+        association: &mut ServerAssociation,
+        message: Message,
+    ) -> Result<(), Whatever> {
+        let pc_id = message.presentation_context_id;
+        let ts = transfer_syntax_of(association, pc_id)?;
+        let identifier = read_identifier(&message, ts)?;
+
+        let results = match self.store.query(&identifier) {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("Could not evaluate C-FIND identifier: {}", e);
+                let rsp = response(&message.command, CommandField::CFindRsp, 0xA900, false);
+                return send_message(association, pc_id, &rsp, None);
+            }
+        };
+
+        for result in results {
+            let rsp = response(&message.command, CommandField::CFindRsp, 0xFF00, true);
+            send_message(association, pc_id, &rsp, Some((&result, ts)))?;
+        }
+        let rsp = response(&message.command, CommandField::CFindRsp, 0x0000, false);
+        send_message(association, pc_id, &rsp, None)
+    }
+
+    fn handle_move(
+        &self,
+        association: &mut ServerAssociation,
+        message: Message,
+    ) -> Result<(), Whatever> {
+        let pc_id = message.presentation_context_id;
+        let ts = transfer_syntax_of(association, pc_id)?;
+        let identifier = read_identifier(&message, ts)?;
+
+        let destination = dimse::get_str_opt(&message.command, tags::MOVE_DESTINATION)
+            .map(|ae| ae.trim().to_string())
+            .unwrap_or_default();
+        let address = match self.move_destinations.get(&destination) {
+            Some(address) => *address,
+            None => {
+                warn!("Unknown move destination `{}`", destination);
+                let rsp = response(&message.command, CommandField::CMoveRsp, 0xA801, false);
+                return send_message(association, pc_id, &rsp, None);
+            }
+        };
+
+        let instances = match self.store.retrieve(&identifier) {
+            Ok(instances) => instances,
+            Err(e) => {
+                warn!("Could not evaluate C-MOVE identifier: {}", e);
+                let rsp = response(&message.command, CommandField::CMoveRsp, 0xA900, false);
+                return send_message(association, pc_id, &rsp, None);
+            }
+        };
+
+        let mut counts = SubOperations {
+            remaining: instances.len() as u16,
+            ..Default::default()
+        };
+
+        if !instances.is_empty() {
+            let message_id = dimse::get_u16(&message.command, tags::MESSAGE_ID).unwrap_or(0);
+            match self.open_sub_association(&destination, address, &instances) {
+                Ok((mut sub_association, contexts)) => {
+                    for (i, instance) in instances.iter().enumerate() {
+                        let status = send_sub_operation(
+                            &mut sub_association,
+                            &contexts,
+                            instance,
+                            (i + 1) as u16,
+                            association.client_ae_title(),
+                            message_id,
+                        );
+                        counts.remaining -= 1;
+                        match status.map(dimse::StatusType::from_status) {
+                            Ok(dimse::StatusType::Success) => counts.completed += 1,
+                            Ok(dimse::StatusType::Warning) => counts.warning += 1,
+                            Ok(_) => counts.failed += 1,
+                            Err(e) => {
+                                warn!("C-STORE sub-operation failed: {}", e);
+                                counts.failed += 1;
+                            }
+                        }
+                        if counts.remaining > 0 {
+                            let rsp = response_with(
+                                &message.command,
+                                CommandField::CMoveRsp,
+                                0xFF00,
+                                false,
+                                counts.elements(true),
+                            );
+                            send_message(association, pc_id, &rsp, None)?;
+                        }
+                    }
+                    let _ = sub_association.release();
+                }
+                Err(e) => {
+                    warn!("Could not associate with move destination: {}", e);
+                    let rsp = response_with(
+                        &message.command,
+                        CommandField::CMoveRsp,
+                        0xA801,
+                        false,
+                        counts.elements(false),
+                    );
+                    return send_message(association, pc_id, &rsp, None);
+                }
+            }
+        }
+
+        let status = if counts.failed > 0 || counts.warning > 0 {
+            0xB000
+        } else {
+            0x0000
+        };
+        let rsp = response_with(
+            &message.command,
+            CommandField::CMoveRsp,
+            status,
+            false,
+            counts.elements(false),
+        );
+        send_message(association, pc_id, &rsp, None)
+    }
+
+    /// Associate with a C-MOVE destination,
+    /// proposing one presentation context
+    /// per combination of SOP class and transfer syntax
+    /// of the instances to send.
+    ///
+    /// Returns the association
+    /// and the presentation context ID of each combination.
+    fn open_sub_association(
+        &self,
+        destination: &str,
+        address: SocketAddr,
+        instances: &[StoredObject],
+    ) -> Result<(ClientAssociation, ContextIds), Whatever> {
+        let contexts: BTreeSet<(String, String)> = instances.iter().map(context_of).collect();
+        let mut options = ClientAssociationOptions::new()
+            .calling_ae_title(self.ae_title.clone())
+            .called_ae_title(destination.to_string());
+        let mut ids = BTreeMap::new();
+        for (i, (sop_class_uid, ts_uid)) in contexts.into_iter().enumerate() {
+            // presentation context IDs are assigned in order of proposal
+            ids.insert((sop_class_uid.clone(), ts_uid.clone()), (i + 1) as u8);
+            options = options.with_presentation_context(sop_class_uid, vec![ts_uid]);
+        }
+        let association = options
+            .establish(address)
+            .whatever_context("Could not establish association")?;
+        Ok((association, ids))
+    }
+}
+
+/// Presentation context IDs of a sub-association,
+/// by SOP class UID and transfer syntax UID.
+type ContextIds = BTreeMap<(String, String), u8>;
+
+/// Counters of C-STORE sub-operations in a C-MOVE.
+#[derive(Debug, Default)]
+struct SubOperations {
+    remaining: u16,
+    completed: u16,
+    failed: u16,
+    warning: u16,
+}
+
+impl SubOperations {
+    /// Build the counter elements of a C-MOVE response,
+    /// including the number of remaining sub-operations if pending.
+    fn elements(&self, pending: bool) -> Vec<InMemElement> {
+        let mut elements = vec![
+            (tags::NUMBER_OF_COMPLETED_SUBOPERATIONS, self.completed),
+            (tags::NUMBER_OF_FAILED_SUBOPERATIONS, self.failed),
+            (tags::NUMBER_OF_WARNING_SUBOPERATIONS, self.warning),
+        ];
+        if pending {
+            elements.push((tags::NUMBER_OF_REMAINING_SUBOPERATIONS, self.remaining));
+        }
+        elements
+            .into_iter()
+            .map(|(tag, value)| DataElement::new(tag, VR::US, PrimitiveValue::from(value)))
+            .collect()
+    }
+}
+
+/// Send a single C-STORE request for a C-MOVE sub-operation,
+/// returning the status of the response.
+fn send_sub_operation(
+    association: &mut ClientAssociation,
+    contexts: &ContextIds,
+    instance: &StoredObject,
+    message_id: u16,
+    move_originator: &str,
+    move_originator_message_id: u16,
+) -> Result<u16, Whatever> {
+    let context = context_of(instance);
+    let pc_id = contexts
+        .get(&context)
+        .copied()
+        .filter(|id| {
+            association
+                .presentation_contexts()
+                .iter()
+                .any(|pc| pc.id == *id)
+        })
+        .whatever_context("Presentation context was not accepted")?;
+    let (sop_class_uid, ts_uid) = context;
+    let ts = TransferSyntaxRegistry
+        .get(&ts_uid)
+        .whatever_context("Unsupported transfer syntax")?;
+    let sop_instance_uid = trim(&instance.meta().media_storage_sop_instance_uid).to_string();
+
+    let command = dimse::command_set(vec![
+        DataElement::new(
+            tags::AFFECTED_SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from(sop_class_uid),
+        ),
+        DataElement::new(
+            tags::COMMAND_FIELD,
+            VR::US,
+            PrimitiveValue::from(CommandField::CStoreRq.code()),
+        ),
+        DataElement::new(tags::MESSAGE_ID, VR::US, PrimitiveValue::from(message_id)),
+        DataElement::new(tags::PRIORITY, VR::US, PrimitiveValue::from(0_u16)),
+        DataElement::new(
+            tags::COMMAND_DATA_SET_TYPE,
+            VR::US,
+            PrimitiveValue::from(DATA_SET_PRESENT),
+        ),
+        DataElement::new(
+            tags::AFFECTED_SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(sop_instance_uid),
+        ),
+        DataElement::new(
+            tags::MOVE_ORIGINATOR_APPLICATION_ENTITY_TITLE,
+            VR::AE,
+            PrimitiveValue::from(move_originator),
+        ),
+        DataElement::new(
+            tags::MOVE_ORIGINATOR_MESSAGE_ID,
+            VR::US,
+            PrimitiveValue::from(move_originator_message_id),
+        ),
+    ]);
+    association
+        .send(&dimse::command_pdu(pc_id, &command).whatever_context("Could not encode command")?)
+        .whatever_context("Could not send C-STORE request")?;
+    {
+        let mut writer = association.send_pdata(pc_id);
+        let mut data = Vec::new();
+        instance
+            .write_dataset_with_ts(&mut data, ts)
+            .whatever_context("Could not encode data set")?;
+        writer
+            .write_all(&data)
+            .whatever_context("Could not send data set")?;
+        writer
+            .finish()
+            .whatever_context("Could not send data set")?;
+    }
+
+    match association
+        .receive()
+        .whatever_context("Could not receive C-STORE response")?
+    {
+        Pdu::PData { data } => {
+            let pdv = data
+                .into_iter()
+                .find(|pdv| pdv.value_type == PDataValueType::Command)
+                .whatever_context("Expected C-STORE response command")?;
+            let rsp =
+                dimse::read_command(&pdv.data).whatever_context("Invalid C-STORE response")?;
+            dimse::status(&rsp).whatever_context("Invalid C-STORE response")
+        }
+        pdu => whatever!("Unexpected PDU {:?}", pdu),
+    }
+}
+
+/// Receive the next complete message from the association,
+/// or `None` if the association was released or aborted.
+fn receive_message(association: &mut ServerAssociation) -> Result<Option<Message>, Whatever> {
+    let mut command_data = Vec::new();
+    let mut data = Vec::new();
+    let mut command: Option<InMemDicomObject> = None;
+    loop {
+        let pdu = match association.receive() {
+            Ok(pdu) => pdu,
+            // the peer closed the connection without releasing
+            Err(_) if command.is_none() && command_data.is_empty() => return Ok(None),
+            Err(e) => return Err(e).whatever_context("Could not receive PDU"),
+        };
+        match pdu {
+            Pdu::PData { data: pdvs } => {
+                for pdv in pdvs {
+                    let presentation_context_id = pdv.presentation_context_id;
+                    match pdv.value_type {
+                        PDataValueType::Command => {
+                            command_data.extend(pdv.data);
+                            if !pdv.is_last {
+                                continue;
+                            }
+                            let cmd = dimse::read_command(&command_data)
+                                .whatever_context("Invalid command set")?;
+                            if !dimse::has_data_set(&cmd).unwrap_or(false) {
+                                return Ok(Some(Message {
+                                    presentation_context_id,
+                                    command: cmd,
+                                    data: None,
+                                }));
+                            }
+                            command = Some(cmd);
+                        }
+                        PDataValueType::Data => {
+                            data.extend(pdv.data);
+                            if !pdv.is_last {
+                                continue;
+                            }
+                            let command = command
+                                .take()
+                                .whatever_context("Received data set before command")?;
+                            return Ok(Some(Message {
+                                presentation_context_id,
+                                command,
+                                data: Some(data),
+                            }));
+                        }
+                    }
+                }
+            }
+            Pdu::ReleaseRQ => {
+                association
+                    .send(&Pdu::ReleaseRP)
+                    .whatever_context("Could not send release response")?;
+                return Ok(None);
+            }
+            Pdu::AbortRQ { .. } => return Ok(None),
+            pdu => whatever!("Unexpected PDU {:?}", pdu),
+        }
+    }
+}
+
+/// Send a command set, optionally followed by a data set.
+fn send_message(
+    association: &mut ServerAssociation,
+    presentation_context_id: u8,
+    command: &InMemDicomObject,
+    data: Option<(&InMemDicomObject, &TransferSyntax)>,
+) -> Result<(), Whatever> {
+    association
+        .send(
+            &dimse::command_pdu(presentation_context_id, command)
+                .whatever_context("Could not encode command")?,
+        )
+        .whatever_context("Could not send command")?;
+    if let Some((object, ts)) = data {
+        let mut bytes = Vec::new();
+        object
+            .write_dataset_with_ts(&mut bytes, ts)
+            .whatever_context("Could not encode data set")?;
+        let mut writer = association.send_pdata(presentation_context_id);
+        writer
+            .write_all(&bytes)
+            .whatever_context("Could not send data set")?;
+        writer
+            .finish()
+            .whatever_context("Could not send data set")?;
+    }
+    Ok(())
+}
+
+/// Build a response command set to the given request.
+fn response(
+    request: &InMemDicomObject,
+    field: CommandField,
+    status: u16,
+    has_data_set: bool,
+) -> InMemDicomObject {
+    response_with(request, field, status, has_data_set, Vec::new())
+}
+
+/// Build a response command set to the given request,
+/// including additional command elements.
+fn response_with(
+    request: &InMemDicomObject,
+    field: CommandField,
+    status: u16,
+    has_data_set: bool,
+    extra: Vec<InMemElement>,
+) -> InMemDicomObject {
+    let mut elements = vec![
+        DataElement::new(
+            tags::COMMAND_FIELD,
+            VR::US,
+            PrimitiveValue::from(field.code()),
+        ),
+        DataElement::new(
+            tags::MESSAGE_ID_BEING_RESPONDED_TO,
+            VR::US,
+            PrimitiveValue::from(dimse::get_u16(request, tags::MESSAGE_ID).unwrap_or(0)),
+        ),
+        DataElement::new(
+            tags::COMMAND_DATA_SET_TYPE,
+            VR::US,
+            PrimitiveValue::from(if has_data_set {
+                DATA_SET_PRESENT
+            } else {
+                NO_DATA_SET
+            }),
+        ),
+        DataElement::new(tags::STATUS, VR::US, PrimitiveValue::from(status)),
+    ];
+    if let Ok(Some(uid)) = dimse::get_uid_opt(request, tags::AFFECTED_SOP_CLASS_UID) {
+        elements.push(DataElement::new(
+            tags::AFFECTED_SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from(uid),
+        ));
+    }
+    elements.extend(extra);
+    dimse::command_set(elements)
+}
+
+/// Decode the identifier data set of a query or retrieve request.
+fn read_identifier(message: &Message, ts: &TransferSyntax) -> Result<InMemDicomObject, Whatever> {
+    let data = message
+        .data
+        .as_ref()
+        .whatever_context("Request without an identifier")?;
+    InMemDicomObject::read_dataset_with_ts(&data[..], ts)
+        .whatever_context("Could not read identifier")
+}
+
+/// Look up the transfer syntax negotiated for a presentation context.
+fn transfer_syntax_of(
+    association: &ServerAssociation,
+    presentation_context_id: u8,
+) -> Result<&'static TransferSyntax, Whatever> {
+    let pc = association
+        .presentation_contexts()
+        .iter()
+        .find(|pc| pc.id == presentation_context_id)
+        .whatever_context("Unknown presentation context")?;
+    TransferSyntaxRegistry
+        .get(trim(&pc.transfer_syntax))
+        .whatever_context("Unsupported transfer syntax")
+}
+
+/// The SOP class UID and transfer syntax UID of a stored object.
+fn context_of(object: &StoredObject) -> (String, String) {
+    let meta = object.meta();
+    (
+        trim(&meta.media_storage_sop_class_uid).to_string(),
+        trim(&meta.transfer_syntax).to_string(),
+    )
+}
+
+/// Retrieve the trimmed string value of an attribute.
+fn str_value(object: &InMemDicomObject, tag: Tag) -> Option<String> {
+    object
+        .element_opt(tag)
+        .ok()
+        .flatten()
+        .and_then(|e| e.to_str().ok())
+        .map(|s| trim(&s).to_string())
+}
+
+fn trim(value: &str) -> &str {
+    value.trim_end_matches(|c: char| c.is_whitespace() || c == '\0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_transfer_syntax_registry::entries::EXPLICIT_VR_LITTLE_ENDIAN;
+
+    const CT_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+    const VERIFICATION: &str = "1.2.840.10008.1.1";
+    const STUDY_ROOT_FIND: &str = "1.2.840.10008.5.1.4.1.2.2.1";
+    const STUDY_ROOT_MOVE: &str = "1.2.840.10008.5.1.4.1.2.2.2";
+
+    fn instance(study: &str, series: &str, sop_instance_uid: &str) -> StoredObject {
+        InMemDicomObject::from_element_iter(vec![
+            DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from(CT_IMAGE_STORAGE),
+            ),
+            DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(sop_instance_uid),
+            ),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("CT")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("P-001")),
+            DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(study),
+            ),
+            DataElement::new(
+                tags::SERIES_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(series),
+            ),
+        ])
+        .with_meta(
+            FileMetaTableBuilder::new()
+                .media_storage_sop_class_uid(CT_IMAGE_STORAGE)
+                .transfer_syntax(EXPLICIT_VR_LITTLE_ENDIAN.uid()),
+        )
+        .unwrap()
+    }
+
+    fn associate(pacs: &VirtualPacs, abstract_syntax: &str) -> (ClientAssociation, u8, String) {
+        let association = ClientAssociationOptions::new()
+            .with_abstract_syntax(abstract_syntax)
+            .called_ae_title(pacs.ae_title())
+            .establish(pacs.addr())
+            .unwrap();
+        let pc = &association.presentation_contexts()[0];
+        let (pc_id, ts) = (pc.id, pc.transfer_syntax.clone());
+        (association, pc_id, ts)
+    }
+
+    fn request(
+        field: CommandField,
+        sop_class_uid: &str,
+        extra: Vec<InMemElement>,
+        has_data: bool,
+    ) -> InMemDicomObject {
+        let mut elements = vec![
+            DataElement::new(
+                tags::AFFECTED_SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from(sop_class_uid),
+            ),
+            DataElement::new(
+                tags::COMMAND_FIELD,
+                VR::US,
+                PrimitiveValue::from(field.code()),
+            ),
+            DataElement::new(tags::MESSAGE_ID, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(
+                tags::COMMAND_DATA_SET_TYPE,
+                VR::US,
+                PrimitiveValue::from(if has_data {
+                    DATA_SET_PRESENT
+                } else {
+                    NO_DATA_SET
+                }),
+            ),
+        ];
+        elements.extend(extra);
+        dimse::command_set(elements)
+    }
+
+    fn send(
+        association: &mut ClientAssociation,
+        pc_id: u8,
+        ts_uid: &str,
+        command: &InMemDicomObject,
+        data: Option<&InMemDicomObject>,
+    ) {
+        association
+            .send(&dimse::command_pdu(pc_id, command).unwrap())
+            .unwrap();
+        if let Some(data) = data {
+            let ts = TransferSyntaxRegistry.get(ts_uid).unwrap();
+            let mut bytes = Vec::new();
+            data.write_dataset_with_ts(&mut bytes, ts).unwrap();
+            let mut writer = association.send_pdata(pc_id);
+            writer.write_all(&bytes).unwrap();
+            writer.finish().unwrap();
+        }
+    }
+
+    fn receive(
+        association: &mut ClientAssociation,
+        ts_uid: &str,
+    ) -> (InMemDicomObject, Option<InMemDicomObject>) {
+        let mut command_data = Vec::new();
+        let mut data = Vec::new();
+        let mut command = None;
+        loop {
+            match association.receive().unwrap() {
+                Pdu::PData { data: pdvs } => {
+                    for pdv in pdvs {
+                        match pdv.value_type {
+                            PDataValueType::Command => {
+                                command_data.extend(pdv.data);
+                                if pdv.is_last {
+                                    let cmd = dimse::read_command(&command_data).unwrap();
+                                    if !dimse::has_data_set(&cmd).unwrap() {
+                                        return (cmd, None);
+                                    }
+                                    command = Some(cmd);
+                                }
+                            }
+                            PDataValueType::Data => {
+                                data.extend(pdv.data);
+                                if pdv.is_last {
+                                    let ts = TransferSyntaxRegistry.get(ts_uid).unwrap();
+                                    let obj = InMemDicomObject::read_dataset_with_ts(&data[..], ts)
+                                        .unwrap();
+                                    return (command.unwrap(), Some(obj));
+                                }
+                            }
+                        }
+                    }
+                }
+                pdu => panic!("unexpected PDU {:?}", pdu),
+            }
+        }
+    }
+
+    fn identifier(level: &str, elements: Vec<InMemElement>) -> InMemDicomObject {
+        let mut obj = InMemDicomObject::from_element_iter(elements);
+        obj.put(DataElement::new(
+            tags::QUERY_RETRIEVE_LEVEL,
+            VR::CS,
+            PrimitiveValue::from(level),
+        ));
+        obj
+    }
+
+    #[test]
+    fn echo() {
+        let pacs = VirtualPacs::spawn().unwrap();
+        let (mut association, pc_id, ts) = associate(&pacs, VERIFICATION);
+        send(
+            &mut association,
+            pc_id,
+            &ts,
+            &request(CommandField::CEchoRq, VERIFICATION, vec![], false),
+            None,
+        );
+        let (rsp, data) = receive(&mut association, &ts);
+        assert_eq!(dimse::command_field(&rsp).unwrap(), CommandField::CEchoRsp);
+        assert_eq!(dimse::status(&rsp).unwrap(), 0x0000);
+        assert!(data.is_none());
+        association.release().unwrap();
+    }
+
+    #[test]
+    fn store_then_find() {
+        let pacs = VirtualPacs::spawn().unwrap();
+
+        let (mut association, pc_id, ts) = associate(&pacs, CT_IMAGE_STORAGE);
+        for uid in ["2.25.100.1", "2.25.100.2"] {
+            let object = instance("2.25.10", "2.25.11", uid);
+            let cmd = request(
+                CommandField::CStoreRq,
+                CT_IMAGE_STORAGE,
+                vec![DataElement::new(
+                    tags::AFFECTED_SOP_INSTANCE_UID,
+                    VR::UI,
+                    PrimitiveValue::from(uid),
+                )],
+                true,
+            );
+            send(&mut association, pc_id, &ts, &cmd, Some(&object));
+            let (rsp, _) = receive(&mut association, &ts);
+            assert_eq!(dimse::status(&rsp).unwrap(), 0x0000);
+            assert_eq!(
+                dimse::get_uid(&rsp, tags::AFFECTED_SOP_INSTANCE_UID).unwrap(),
+                uid
+            );
+        }
+        association.release().unwrap();
+        assert_eq!(pacs.store().len(), 2);
+        let stored = pacs.store().get("2.25.100.2").unwrap();
+        assert_eq!(
+            stored.meta().media_storage_sop_class_uid(),
+            CT_IMAGE_STORAGE
+        );
+
+        let (mut association, pc_id, ts) = associate(&pacs, STUDY_ROOT_FIND);
+        let query = identifier(
+            "STUDY",
+            vec![
+                DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("P-001")),
+                DataElement::new(tags::STUDY_INSTANCE_UID, VR::UI, PrimitiveValue::Empty),
+                DataElement::new(
+                    tags::NUMBER_OF_STUDY_RELATED_INSTANCES,
+                    VR::IS,
+                    PrimitiveValue::Empty,
+                ),
+            ],
+        );
+        let cmd = request(CommandField::CFindRq, STUDY_ROOT_FIND, vec![], true);
+        send(&mut association, pc_id, &ts, &cmd, Some(&query));
+
+        let (rsp, result) = receive(&mut association, &ts);
+        assert_eq!(dimse::status(&rsp).unwrap(), 0xFF00);
+        let result = result.unwrap();
+        assert_eq!(
+            str_value(&result, tags::STUDY_INSTANCE_UID).unwrap(),
+            "2.25.10"
+        );
+        assert_eq!(
+            str_value(&result, tags::NUMBER_OF_STUDY_RELATED_INSTANCES).unwrap(),
+            "2"
+        );
+
+        let (rsp, result) = receive(&mut association, &ts);
+        assert_eq!(dimse::status(&rsp).unwrap(), 0x0000);
+        assert!(result.is_none());
+        association.release().unwrap();
+    }
+
+    #[test]
+    fn move_to_known_destination() {
+        let destination = VirtualPacs::options().ae_title("DEST").spawn().unwrap();
+        let store = InMemoryStore::new();
+        store.insert(instance("2.25.20", "2.25.21", "2.25.200.1"));
+        store.insert(instance("2.25.20", "2.25.21", "2.25.200.2"));
+        store.insert(instance("2.25.30", "2.25.31", "2.25.300.1"));
+        let pacs = VirtualPacs::options()
+            .store(store)
+            .move_destination("DEST", destination.addr())
+            .spawn()
+            .unwrap();
+
+        let (mut association, pc_id, ts) = associate(&pacs, STUDY_ROOT_MOVE);
+        let query = identifier(
+            "STUDY",
+            vec![DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.20"),
+            )],
+        );
+        let cmd = request(
+            CommandField::CMoveRq,
+            STUDY_ROOT_MOVE,
+            vec![DataElement::new(
+                tags::MOVE_DESTINATION,
+                VR::AE,
+                PrimitiveValue::from("DEST"),
+            )],
+            true,
+        );
+        send(&mut association, pc_id, &ts, &cmd, Some(&query));
+
+        // one pending response after the first sub-operation
+        let (rsp, _) = receive(&mut association, &ts);
+        assert_eq!(dimse::status(&rsp).unwrap(), 0xFF00);
+        assert_eq!(
+            dimse::get_u16(&rsp, tags::NUMBER_OF_REMAINING_SUBOPERATIONS).unwrap(),
+            1
+        );
+
+        let (rsp, _) = receive(&mut association, &ts);
+        assert_eq!(dimse::status(&rsp).unwrap(), 0x0000);
+        assert_eq!(
+            dimse::get_u16(&rsp, tags::NUMBER_OF_COMPLETED_SUBOPERATIONS).unwrap(),
+            2
+        );
+        assert_eq!(
+            dimse::get_u16(&rsp, tags::NUMBER_OF_FAILED_SUBOPERATIONS).unwrap(),
+            0
+        );
+        association.release().unwrap();
+
+        assert_eq!(destination.store().len(), 2);
+        assert!(destination.store().get("2.25.200.1").is_some());
+        assert!(destination.store().get("2.25.300.1").is_none());
+    }
+
+    #[test]
+    fn move_to_unknown_destination() {
+        let pacs = VirtualPacs::spawn().unwrap();
+        pacs.store()
+            .insert(instance("2.25.20", "2.25.21", "2.25.200.1"));
+
+        let (mut association, pc_id, ts) = associate(&pacs, STUDY_ROOT_MOVE);
+        let query = identifier(
+            "STUDY",
+            vec![DataElement::new(
+                tags::STUDY_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from("2.25.20"),
+            )],
+        );
+        let cmd = request(
+            CommandField::CMoveRq,
+            STUDY_ROOT_MOVE,
+            vec![DataElement::new(
+                tags::MOVE_DESTINATION,
+                VR::AE,
+                PrimitiveValue::from("NOWHERE"),
+            )],
+            true,
+        );
+        send(&mut association, pc_id, &ts, &cmd, Some(&query));
+        let (rsp, _) = receive(&mut association, &ts);
+        assert_eq!(dimse::status(&rsp).unwrap(), 0xA801);
+        association.release().unwrap();
+    }
+}